@@ -43,6 +43,7 @@ use smithay::{
             ServerDndGrabHandler,
         },
         dmabuf::DmabufFeedback,
+        ext::ClientCredentials,
         fractional_scale::{with_fractional_scale, FractionalScaleHandler, FractionalScaleManagerState},
         input_method::{InputMethodManagerState, InputMethodSeat},
         keyboard_shortcuts_inhibit::{
@@ -87,7 +88,10 @@ pub struct CalloopData<BackendData: Backend + 'static> {
 }
 
 #[derive(Debug, Default)]
-pub struct ClientState;
+pub struct ClientState {
+    /// Credentials of the client, if it connected through the wayland socket
+    pub credentials: Option<ClientCredentials>,
+}
 impl ClientData for ClientState {
     /// Notification that a client was initialized
     fn initialized(&self, _client_id: ClientId) {}
@@ -392,10 +396,11 @@ impl<BackendData: Backend + 'static> AnvilState<BackendData> {
             let socket_name = source.socket_name().to_string_lossy().into_owned();
             handle
                 .insert_source(source, |client_stream, _, data| {
+                    let credentials = ClientCredentials::from_stream(&client_stream);
                     if let Err(err) = data
                         .display
                         .handle()
-                        .insert_client(client_stream, Arc::new(ClientState))
+                        .insert_client(client_stream, Arc::new(ClientState { credentials }))
                     {
                         warn!("Error adding wayland client: {}", err);
                     };
@@ -73,7 +73,7 @@ use smithay::{
             linux_dmabuf::zv1::server::zwp_linux_dmabuf_feedback_v1,
             presentation_time::server::wp_presentation_feedback,
         },
-        wayland_server::{backend::GlobalId, protocol::wl_surface, Display, DisplayHandle},
+        wayland_server::{protocol::wl_surface, Display, DisplayHandle},
     },
     utils::{Clock, DeviceFd, IsAlive, Logical, Monotonic, Point, Scale, Transform},
     wayland::{
@@ -609,7 +609,7 @@ struct SurfaceData {
     dh: DisplayHandle,
     device_id: DrmNode,
     render_node: DrmNode,
-    global: Option<GlobalId>,
+    output: Output,
     compositor: SurfaceComposition,
     #[cfg(feature = "debug")]
     fps: fps_ticker::Fps,
@@ -620,9 +620,7 @@ struct SurfaceData {
 
 impl Drop for SurfaceData {
     fn drop(&mut self) {
-        if let Some(global) = self.global.take() {
-            self.dh.remove_global::<AnvilState<UdevData>>(global);
-        }
+        self.output.destroy_global::<AnvilState<UdevData>>(&self.dh);
     }
 }
 
@@ -743,6 +741,7 @@ impl AnvilState<UdevData> {
                     DrmEvent::VBlank(crtc) => {
                         data.state.frame_finish(node, crtc, metadata);
                     }
+                    DrmEvent::VBlankNotify(_, _) => {}
                     DrmEvent::Error(error) => {
                         error!("{:?}", error);
                     }
@@ -832,7 +831,7 @@ impl AnvilState<UdevData> {
                 model,
             },
         );
-        let global = output.create_global::<AnvilState<UdevData>>(&self.display_handle);
+        output.create_global::<AnvilState<UdevData>>(&self.display_handle);
 
         let x = self
             .space
@@ -931,7 +930,7 @@ impl AnvilState<UdevData> {
             dh: self.display_handle.clone(),
             device_id: node,
             render_node: device.render_node,
-            global: Some(global),
+            output: output.clone(),
             compositor,
             #[cfg(feature = "debug")]
             fps: fps_ticker::Fps::default(),
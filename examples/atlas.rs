@@ -0,0 +1,155 @@
+//! Compares rendering many small textures with and without a `TextureAtlas`
+//!
+//! Every icon is drawn with its own draw call in both cases, the atlas reduces the number of
+//! textures the renderer has to switch between to the number of atlas pages.
+
+use std::{path::PathBuf, time::Instant};
+
+use clap::Parser;
+use smithay::{
+    backend::{
+        egl::{EGLContext, EGLDevice, EGLDisplay},
+        renderer::{
+            element::{atlas::TextureAtlas, texture::TextureRenderElement, Element, Id, RenderElement},
+            gles2::{Gles2Renderbuffer, Gles2Renderer, Gles2Texture},
+            Bind, ExportMem, Frame, ImportMem, Offscreen, Renderer,
+        },
+    },
+    utils::{Rectangle, Transform},
+};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// Render node of the device to render with
+    #[arg(short, long)]
+    device: PathBuf,
+    /// Number of icons to draw per frame
+    #[arg(short, long, default_value_t = 2000)]
+    icons: usize,
+    /// Size of an icon in pixels
+    #[arg(short, long, default_value_t = 16)]
+    size: i32,
+    /// Number of frames to render
+    #[arg(short, long, default_value_t = 100)]
+    frames: usize,
+}
+
+const OUTPUT_SIZE: (i32, i32) = (1920, 1080);
+
+fn main() {
+    let args = Cli::parse();
+
+    if let Ok(env_filter) = tracing_subscriber::EnvFilter::try_from_default_env() {
+        tracing_subscriber::fmt().with_env_filter(env_filter).init();
+    } else {
+        tracing_subscriber::fmt().init();
+    }
+
+    let device = EGLDevice::enumerate()
+        .expect("Failed to enumerate EGL devices")
+        .find(|device| {
+            device.drm_device_path().ok().as_ref() == Some(&args.device)
+                || device.render_device_path().ok().as_ref() == Some(&args.device)
+        })
+        .expect("Unable to find egl device");
+    let display = EGLDisplay::new(device).expect("Failed to create EGL display");
+    let context = EGLContext::new(&display).expect("Failed to create EGL context");
+    let mut renderer = unsafe { Gles2Renderer::new(context).expect("Failed to init GL ES renderer") };
+
+    let icons = (0..args.icons)
+        .map(|idx| vec![(idx % 256) as u8; (args.size * args.size * 4) as usize])
+        .collect::<Vec<_>>();
+    let location = |idx: usize| {
+        let per_row = (OUTPUT_SIZE.0 / args.size) as usize;
+        let x = (idx % per_row) as i32 * args.size;
+        let y = (idx / per_row) as i32 * args.size % OUTPUT_SIZE.1;
+        (x as f64, y as f64)
+    };
+
+    // every icon in its own texture
+    let textures = icons
+        .iter()
+        .map(|icon| {
+            renderer
+                .import_memory(icon, (args.size, args.size).into(), false)
+                .expect("Failed to import icon")
+        })
+        .collect::<Vec<_>>();
+    let elements = textures
+        .iter()
+        .enumerate()
+        .map(|(idx, texture)| {
+            TextureRenderElement::from_static_texture(
+                Id::new(),
+                renderer.id(),
+                location(idx),
+                texture.clone(),
+                1,
+                Transform::Normal,
+                None,
+                None,
+                None,
+                None,
+            )
+        })
+        .collect::<Vec<_>>();
+    let separate = render(&mut renderer, &elements, args.frames);
+    report("separate textures", textures.len(), elements.len(), separate);
+
+    // all icons in an atlas
+    let mut atlas = TextureAtlas::new(&renderer, (2048, 2048));
+    let regions = icons
+        .iter()
+        .map(|icon| {
+            atlas
+                .allocate(&mut renderer, icon, (args.size, args.size))
+                .expect("Failed to allocate atlas region")
+        })
+        .collect::<Vec<_>>();
+    let elements = regions
+        .iter()
+        .enumerate()
+        .map(|(idx, region)| atlas.render_element(region, location(idx), 1, None).unwrap())
+        .collect::<Vec<_>>();
+    let atlased = render(&mut renderer, &elements, args.frames);
+    report("texture atlas", atlas.page_count(), elements.len(), atlased);
+}
+
+/// Render the elements for the given number of frames, returns the average frame time in ms
+fn render(
+    renderer: &mut Gles2Renderer,
+    elements: &[TextureRenderElement<Gles2Texture>],
+    frames: usize,
+) -> f64 {
+    let buffer: Gles2Renderbuffer = renderer
+        .create_buffer(OUTPUT_SIZE.into())
+        .expect("Failed to create offscreen buffer");
+    renderer.bind(buffer).expect("Failed to bind offscreen buffer");
+
+    let start = Instant::now();
+    for _ in 0..frames {
+        let mut frame = renderer
+            .render(OUTPUT_SIZE.into(), Transform::Normal)
+            .expect("Failed to create render frame");
+        for element in elements {
+            let dst = element.geometry(1.0.into());
+            RenderElement::<Gles2Renderer>::draw(element, &mut frame, element.src(), dst, &[dst])
+                .expect("Failed to draw element");
+        }
+        frame.finish().expect("Failed to finish render frame");
+        // reading back a pixel waits for the gpu to finish the frame
+        let mapping = renderer
+            .copy_framebuffer(Rectangle::from_loc_and_size((0, 0), (1, 1)))
+            .expect("Failed to copy framebuffer");
+        renderer.map_texture(&mapping).expect("Failed to map framebuffer");
+    }
+    start.elapsed().as_secs_f64() * 1000.0 / frames as f64
+}
+
+fn report(name: &str, textures: usize, draw_calls: usize, frame_time: f64) {
+    println!(
+        "{}: {} textures, {} draw calls per frame, {:.3} ms per frame",
+        name, textures, draw_calls, frame_time
+    );
+}
@@ -115,6 +115,7 @@ impl State {
         self.handle
             .insert_source(drm_notifier, move |event, _, _| match event {
                 drm::DrmEvent::VBlank(_) => {}
+                drm::DrmEvent::VBlankNotify(_, _) => {}
                 drm::DrmEvent::Error(_) => {}
             })
             .unwrap();
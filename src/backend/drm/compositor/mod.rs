@@ -119,7 +119,7 @@
 use std::{
     cell::RefCell,
     collections::{HashMap, HashSet},
-    os::unix::io::AsFd,
+    os::unix::io::{AsFd, OwnedFd},
     rc::Rc,
     sync::{Arc, Mutex},
 };
@@ -326,6 +326,7 @@ struct PlaneConfig<B> {
     pub damage_clips: Option<PlaneDamageClips>,
     pub buffer: Owned<B>,
     pub plane_claim: PlaneClaim,
+    pub fence: Option<Arc<OwnedFd>>,
 }
 
 impl<B> Clone for PlaneConfig<B> {
@@ -337,6 +338,7 @@ impl<B> Clone for PlaneConfig<B> {
             damage_clips: self.damage_clips.clone(),
             buffer: self.buffer.clone(),
             plane_claim: self.plane_claim.clone(),
+            fence: self.fence.clone(),
         }
     }
 }
@@ -492,6 +494,7 @@ impl<B: AsRef<framebuffer::Handle>> FrameState<B> {
                         transform: config.transform,
                         damage_clips: config.damage_clips.as_ref().map(|d| d.blob()),
                         fb: *config.buffer.as_ref(),
+                        fence: config.fence.as_ref().map(|fence| fence.as_fd()),
                     }),
                 }),
             allow_modeset,
@@ -519,6 +522,7 @@ impl<B: AsRef<framebuffer::Handle>> FrameState<B> {
                         transform: config.transform,
                         damage_clips: config.damage_clips.as_ref().map(|d| d.blob()),
                         fb: *config.buffer.as_ref(),
+                        fence: config.fence.as_ref().map(|fence| fence.as_fd()),
                     }),
                 }),
             event,
@@ -539,6 +543,7 @@ impl<B: AsRef<framebuffer::Handle>> FrameState<B> {
                         transform: config.transform,
                         damage_clips: config.damage_clips.as_ref().map(|d| d.blob()),
                         fb: *config.buffer.as_ref(),
+                        fence: config.fence.as_ref().map(|fence| fence.as_fd()),
                     }),
                 }),
             event,
@@ -1143,6 +1148,7 @@ where
     output: Output,
     surface: Arc<DrmSurface>,
     planes: Planes,
+    primary_plane_formats: HashSet<DrmFormat>,
     overlay_plane_element_ids: OverlayPlaneElementIds,
     damage_tracker: OutputDamageTracker,
     primary_plane_element_id: Id,
@@ -1208,9 +1214,8 @@ where
         gbm: Option<GbmDevice<G>>,
     ) -> FrameResult<Self, A, F> {
         let span = info_span!(
-            parent: None,
+            parent: &output.span(),
             "drm_compositor",
-            output = output.name(),
             device = ?surface.dev_path(),
             crtc = ?surface.crtc(),
         );
@@ -1227,6 +1232,8 @@ where
             .overlay
             .sort_by_key(|p| std::cmp::Reverse(p.zpos.unwrap_or_default()));
 
+        let primary_plane_formats = surface.supported_formats(planes.primary.handle)?;
+
         let cursor_size = Size::from((cursor_size.w as i32, cursor_size.h as i32));
         let damage_tracker = OutputDamageTracker::from_output(output);
 
@@ -1271,6 +1278,7 @@ where
                         damage_tracker,
                         output: output.clone(),
                         planes,
+                        primary_plane_formats,
                         overlay_plane_element_ids,
                         element_states: IndexMap::new(),
                         debug_flags: DebugFlags::empty(),
@@ -1435,6 +1443,7 @@ where
                     fb: handle,
                 }),
                 plane_claim,
+                fence: None,
             }),
         };
 
@@ -1586,6 +1595,7 @@ where
                     fb,
                 }),
                 plane_claim,
+                fence: None,
             }),
         };
 
@@ -2010,6 +2020,7 @@ where
     /// Otherwise the underlying swapchain will eventually run out of buffers.
     ///
     /// `user_data` can be used to attach some data to a specific buffer and later retrieved with [`DrmCompositor::frame_submitted`]
+    #[instrument(level = "trace", parent = &self.span, skip_all)]
     pub fn queue_frame(&mut self, user_data: U) -> FrameResult<(), A, F> {
         self.queued_frame = self.next_frame.take().map(|state| {
             if let Some(plane_state) = state.plane_state(self.planes.primary.handle) {
@@ -2033,6 +2044,7 @@ where
         Ok(())
     }
 
+    #[instrument(level = "trace", name = "page_flip", parent = &self.span, skip_all)]
     fn submit(&mut self) -> FrameResult<(), A, F> {
         let (state, user_data) = self.queued_frame.take().unwrap();
 
@@ -2053,6 +2065,7 @@ where
     /// *Note*: Needs to be called, after the vblank event of the matching [`DrmDevice`](super::super::DrmDevice)
     /// was received after calling [`DrmCompositor::queue_frame`] on this surface.
     /// Otherwise the underlying swapchain will run out of buffers eventually.
+    #[instrument(level = "trace", parent = &self.span, skip_all)]
     pub fn frame_submitted(&mut self) -> FrameResult<Option<U>, A, F> {
         if let Some((mut pending, user_data)) = self.pending_frame.take() {
             std::mem::swap(&mut pending, &mut self.current_frame);
@@ -2180,6 +2193,22 @@ where
         self.swapchain.format()
     }
 
+    /// Check if the primary plane can scan-out buffers with the given format and modifier
+    ///
+    /// This uses the formats of the primary plane read during initialization and does not
+    /// query the device, so it can be used as a cheap check before attempting direct scan-out
+    /// of a client buffer. Formats with an alpha channel are also considered supported if the
+    /// plane supports their opaque variant, as framebuffers for the primary plane fall back to it.
+    ///
+    /// Buffers with [`DrmModifier::Invalid`] use an implicit modifier, which can not be matched
+    /// against the formats of the plane. They are considered supported if the plane supports
+    /// the format with any modifier, leaving the decision to the test commit.
+    ///
+    /// A positive result does not guarantee a successful scan-out, which still depends on a test commit.
+    pub fn primary_plane_supports(&self, format: DrmFourcc, modifier: DrmModifier) -> bool {
+        plane_supports(&self.primary_plane_formats, format, modifier)
+    }
+
     #[allow(clippy::too_many_arguments)]
     #[instrument(level = "trace", skip_all)]
     fn try_assign_element<'a, R, E, Target>(
@@ -2560,6 +2589,7 @@ where
                 fb: OwnedFramebuffer::new(DrmFramebuffer::Gbm(framebuffer)),
             }),
             plane_claim,
+            fence: None,
         });
 
         let plane_state = PlaneState {
@@ -2733,6 +2763,23 @@ where
             .underlying_storage(renderer)
             .ok_or(ExportBufferError::NoUnderlyingStorage)?;
 
+        // Avoid a test commit for buffers the primary plane can not scan-out anyway
+        let dmabuf = match &underlying_storage {
+            UnderlyingStorage::Wayland(buffer) => crate::wayland::dmabuf::get_dmabuf(buffer).ok(),
+        };
+        if let Some(dmabuf) = dmabuf {
+            let format = dmabuf.format();
+            if !self.primary_plane_supports(format.code, format.modifier) {
+                trace!(
+                    "skipping direct scan-out on primary plane {:?} for element {:?}, unsupported format {:?}",
+                    self.planes.primary.handle,
+                    element.id(),
+                    format
+                );
+                return Ok(Err(Some(RenderingReason::ScanoutFailed)));
+            }
+        }
+
         // TODO: We should check if there is already an element assigned to the primary plane for completeness here
 
         trace!(
@@ -2931,6 +2978,7 @@ where
                     buffer: ScanoutBuffer::from(underlying_storage.clone()),
                 }),
                 plane_claim,
+                fence: element.underlying_storage_fence().map(Arc::new),
             }),
         };
 
@@ -3053,6 +3101,16 @@ fn apply_output_transform(transform: Transform, output_transform: Transform) ->
     }
 }
 
+fn plane_supports(formats: &HashSet<DrmFormat>, format: DrmFourcc, modifier: DrmModifier) -> bool {
+    std::iter::once(format).chain(get_opaque(format)).any(|code| {
+        if modifier == DrmModifier::Invalid {
+            formats.iter().any(|f| f.code == code)
+        } else {
+            formats.contains(&DrmFormat { code, modifier })
+        }
+    })
+}
+
 fn element_is_opaque<E: Element>(element: &E, scale: Scale<f64>) -> bool {
     let opaque_regions = element.opaque_regions(scale);
     let element_geometry = Rectangle::from_loc_and_size(Point::default(), element.geometry(scale).size);
@@ -3187,3 +3245,44 @@ impl<
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use drm_fourcc::{DrmFormat, DrmFourcc, DrmModifier};
+
+    use super::plane_supports;
+
+    #[test]
+    fn implicit_modifier_falls_through_to_test_commit() {
+        let formats = HashSet::from([
+            DrmFormat {
+                code: DrmFourcc::Xrgb8888,
+                modifier: DrmModifier::Linear,
+            },
+            DrmFormat {
+                code: DrmFourcc::Xrgb8888,
+                modifier: DrmModifier::I915_x_tiled,
+            },
+        ]);
+
+        assert!(plane_supports(
+            &formats,
+            DrmFourcc::Xrgb8888,
+            DrmModifier::Invalid
+        ));
+        assert!(plane_supports(
+            &formats,
+            DrmFourcc::Argb8888,
+            DrmModifier::Invalid
+        ));
+        assert!(plane_supports(&formats, DrmFourcc::Argb8888, DrmModifier::Linear));
+        assert!(!plane_supports(&formats, DrmFourcc::Nv12, DrmModifier::Invalid));
+        assert!(!plane_supports(
+            &formats,
+            DrmFourcc::Xrgb8888,
+            DrmModifier::I915_y_tiled
+        ));
+    }
+}
@@ -330,6 +330,12 @@ impl DrmDevice {
         if !self.resources.filter_crtcs(filter).contains(&crtc) {
             return Err(Error::PlaneNotCompatible(crtc, plane.handle));
         }
+        let pipe = self
+            .resources
+            .crtcs()
+            .iter()
+            .position(|handle| *handle == crtc)
+            .ok_or(Error::UnknownCrtc(crtc))? as u32;
 
         let active = match &*self.internal {
             DrmDeviceInternal::Atomic(dev) => dev.active.clone(),
@@ -364,6 +370,7 @@ impl DrmDevice {
         Ok(DrmSurface {
             dev_id: self.dev_id,
             crtc,
+            pipe,
             primary: plane.handle,
             internal: Arc::new(internal),
             has_universal_planes: self.has_universal_planes,
@@ -426,6 +433,8 @@ impl DrmDevice {
 pub enum DrmEvent {
     /// A vblank blank event on the provided crtc has happened
     VBlank(crtc::Handle),
+    /// A vblank requested through [`DrmSurface::request_vblank`] has happened
+    VBlankNotify(crtc::Handle, VblankEvent),
     /// An error happened while processing events
     Error(Error),
 }
@@ -439,6 +448,26 @@ pub struct EventMetadata {
     pub sequence: u32,
 }
 
+/// Timing information of a vblank requested through [`DrmSurface::request_vblank`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VblankEvent {
+    /// The vblank sequence number of the crtc
+    pub sequence: u64,
+    /// Seconds part of the vblank timestamp
+    pub sec: u64,
+    /// Microseconds part of the vblank timestamp
+    pub usec: u32,
+}
+
+impl VblankEvent {
+    /// The vblank timestamp as a [`Duration`]
+    ///
+    /// The clock of the timestamp is the same as for page-flip events, see [`Time`].
+    pub fn time(&self) -> Duration {
+        Duration::new(self.sec, self.usec * 1000)
+    }
+}
+
 /// Either a realtime or monotonic timestamp
 #[derive(Debug)]
 pub enum Time {
@@ -486,11 +515,16 @@ impl EventSource for DrmDeviceNotifier {
                             sequence: event.frame,
                         };
                         callback(DrmEvent::VBlank(event.crtc), &mut Some(metadata));
+                    } else if let Event::Vblank(event) = event {
+                        trace!("Got a vblank event for crtc ({:?})", event.crtc);
+                        let vblank = VblankEvent {
+                            sequence: event.frame as u64,
+                            sec: event.time.as_secs(),
+                            usec: event.time.subsec_micros(),
+                        };
+                        callback(DrmEvent::VBlankNotify(event.crtc, vblank), &mut None);
                     } else {
-                        trace!(
-                            "Got a non-page-flip event of device '{:?}'.",
-                            self.internal.dev_path()
-                        );
+                        trace!("Got an unknown event of device '{:?}'.", self.internal.dev_path());
                     }
                 }
             }
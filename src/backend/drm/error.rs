@@ -68,6 +68,9 @@ pub enum Error {
     /// Atomic Test failed for new properties
     #[error("Atomic Test failed for new properties on crtc ({0:?})")]
     TestFailed(crtc::Handle),
+    /// The crtc is not part of the resources of the device
+    #[error("Crtc `{0:?}` is not part of the device resources")]
+    UnknownCrtc(crtc::Handle),
 }
 
 impl From<Error> for SwapBuffersError {
@@ -83,7 +83,7 @@ pub(self) mod surface;
 use crate::utils::DevPath;
 pub use device::{
     DrmDevice, DrmDeviceFd, DrmDeviceNotifier, DrmEvent, EventMetadata as DrmEventMetadata, PlaneClaim,
-    Time as DrmEventTime,
+    Time as DrmEventTime, VblankEvent,
 };
 pub use error::Error as DrmError;
 pub use node::{CreateDrmNodeError, DrmNode, NodeType};
@@ -5,6 +5,7 @@ use drm::control::{
 };
 
 use std::collections::HashSet;
+use std::os::unix::io::AsRawFd;
 use std::sync::Mutex;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
@@ -289,6 +290,7 @@ impl AtomicDrmSurface {
                         transform: Transform::Normal,
                         damage_clips: None,
                         fb: test_buffer.fb,
+                        fence: None,
                     }),
                 }],
                 Some(pending.blob),
@@ -339,6 +341,7 @@ impl AtomicDrmSurface {
                     transform: Transform::Normal,
                     damage_clips: None,
                     fb: test_buffer.fb,
+                    fence: None,
                 }),
             }],
             Some(pending.blob),
@@ -391,6 +394,7 @@ impl AtomicDrmSurface {
                     transform: Transform::Normal,
                     damage_clips: None,
                     fb: test_buffer.fb,
+                    fence: None,
                 }),
             }],
             Some(pending.blob),
@@ -442,6 +446,7 @@ impl AtomicDrmSurface {
                     transform: Transform::Normal,
                     damage_clips: None,
                     fb: test_buffer.fb,
+                    fence: None,
                 }),
             }],
             Some(new_blob),
@@ -804,6 +809,13 @@ impl AtomicDrmSurface {
                         req.add_property(*handle, prop, property::Value::Blob(0));
                     }
                 }
+                if let Some(fence) = config.fence {
+                    req.add_property(
+                        *handle,
+                        plane_prop_handle(&prop_mapping, *handle, "IN_FENCE_FD")?,
+                        property::Value::SignedRange(fence.as_raw_fd() as i64),
+                    );
+                }
             } else {
                 // disconnect the plane from the CRTC
                 req.add_property(
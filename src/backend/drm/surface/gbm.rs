@@ -206,6 +206,7 @@ where
                 transform: Transform::Normal,
                 damage_clips: None,
                 fb: *handle.as_ref(),
+                fence: None,
             }),
         };
 
@@ -318,6 +319,7 @@ where
                 transform: Transform::Normal,
                 damage_clips: damage_clips.as_ref().map(|d| d.blob()),
                 fb: *handle.as_ref(),
+                fence: None,
             }),
         };
 
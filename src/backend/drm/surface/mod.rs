@@ -32,6 +32,8 @@ pub struct DrmSurface {
     #[allow(dead_code)]
     pub(super) dev_id: dev_t,
     pub(super) crtc: crtc::Handle,
+    // Index of the crtc in the device resources, as expected by vblank requests
+    pub(super) pipe: u32,
     pub(super) primary: plane::Handle,
     pub(super) internal: Arc<DrmSurfaceInternal>,
     pub(super) has_universal_planes: bool,
@@ -154,6 +156,11 @@ pub struct PlaneConfig<'a> {
     pub damage_clips: Option<drm::control::property::Value<'a>>,
     /// Framebuffer handle
     pub fb: framebuffer::Handle,
+    /// Fence to wait on before the framebuffer is scanned out
+    ///
+    /// Set as the `IN_FENCE_FD` of the plane. Only supported by atomic surfaces,
+    /// legacy surfaces reject configurations with a fence.
+    pub fence: Option<BorrowedFd<'a>>,
 }
 
 #[derive(Debug)]
@@ -251,6 +258,26 @@ impl DrmSurface {
         }
     }
 
+    /// Request a notification for the next vblank of the underlying [`crtc`](drm::control::crtc)
+    ///
+    /// This does not block. Once the vblank happened a [`DrmEvent::VBlankNotify`](super::DrmEvent::VBlankNotify)
+    /// carrying the timestamp of the vblank is emitted by the [`DrmDeviceNotifier`](super::DrmDeviceNotifier)
+    /// of the device. This can be used to schedule rendering relative to the vblank
+    /// independently of page-flips.
+    pub fn request_vblank(&self) -> Result<(), Error> {
+        use drm_ffi::drm_vblank_seq_type::{_DRM_VBLANK_EVENT, _DRM_VBLANK_RELATIVE};
+
+        let type_ =
+            _DRM_VBLANK_RELATIVE | _DRM_VBLANK_EVENT | (self.pipe << drm_ffi::_DRM_VBLANK_HIGH_CRTC_SHIFT);
+        drm_ffi::wait_vblank(self.as_fd().as_raw_fd(), type_, 1, u32::from(self.crtc) as usize)
+            .map(|_| ())
+            .map_err(|source| Error::Access {
+                errmsg: "Failed to request vblank event",
+                dev: self.dev_path(),
+                source,
+            })
+    }
+
     /// Returns the currently active [`Mode`](drm::control::Mode)
     /// of the underlying [`crtc`](drm::control::crtc)
     pub fn current_mode(&self) -> Mode {
@@ -581,5 +608,10 @@ fn ensure_legacy_planes<'a>(
         return Err(Error::UnsupportedPlaneConfiguration(state.handle));
     }
 
+    if config.fence.is_some() {
+        // legacy does not support explicit fencing
+        return Err(Error::UnsupportedPlaneConfiguration(state.handle));
+    }
+
     Ok(config.fb)
 }
@@ -19,7 +19,7 @@ use crate::{
     utils::user_data::UserDataMap,
 };
 
-use tracing::{info, info_span, instrument, trace};
+use tracing::{debug, info, info_span, instrument, trace};
 
 /// EGL context for rendering
 #[derive(Debug)]
@@ -74,7 +74,17 @@ impl EGLContext {
 
     /// Creates a new configless `EGLContext` from a given `EGLDisplay`
     pub fn new(display: &EGLDisplay) -> Result<EGLContext, Error> {
-        Self::new_internal(display, None, None)
+        Self::new_internal(display, None, ContextConfig::None, None)
+    }
+
+    /// Creates a new configless `EGLContext` from a given `EGLDisplay` with a priority hint
+    ///
+    /// See [`ContextPriority`] for details on how the hint is applied.
+    pub fn new_with_priority(
+        display: &EGLDisplay,
+        priority: Option<ContextPriority>,
+    ) -> Result<EGLContext, Error> {
+        Self::new_internal(display, None, ContextConfig::None, priority)
     }
 
     /// Create a new [`EGLContext`] from a given `EGLDisplay` and configuration requirements
@@ -83,12 +93,12 @@ impl EGLContext {
         attributes: GlAttributes,
         reqs: PixelFormatRequirements,
     ) -> Result<EGLContext, Error> {
-        Self::new_internal(display, None, Some((attributes, reqs)))
+        Self::new_internal(display, None, ContextConfig::Choose(attributes, reqs), None)
     }
 
     /// Create a new configless `EGLContext` from a given `EGLDisplay` sharing resources with another context
     pub fn new_shared(display: &EGLDisplay, share: &EGLContext) -> Result<EGLContext, Error> {
-        Self::new_internal(display, Some(share), None)
+        Self::new_internal(display, Some(share), ContextConfig::None, None)
     }
 
     /// Create a new `EGLContext` from a given `EGLDisplay` and configuration requirements sharing resources with another context
@@ -98,13 +108,56 @@ impl EGLContext {
         attributes: GlAttributes,
         reqs: PixelFormatRequirements,
     ) -> Result<EGLContext, Error> {
-        Self::new_internal(display, Some(share), Some((attributes, reqs)))
+        Self::new_internal(
+            display,
+            Some(share),
+            ContextConfig::Choose(attributes, reqs),
+            None,
+        )
+    }
+
+    /// Create a new `EGLContext` sharing resources with this context
+    ///
+    /// The new context uses the same config and client version as this context.
+    /// It can for example be moved to another thread to upload textures without
+    /// stalling rendering on this context.
+    pub fn create_shared(&self) -> Result<EGLContext, Error> {
+        self.create_shared_with_priority(None)
+    }
+
+    /// Create a new `EGLContext` sharing resources with this context with a priority hint
+    ///
+    /// See [`EGLContext::create_shared`] and [`ContextPriority`].
+    pub fn create_shared_with_priority(
+        &self,
+        priority: Option<ContextPriority>,
+    ) -> Result<EGLContext, Error> {
+        let mut version = 2;
+        unsafe {
+            ffi::egl::QueryContext(
+                **self.display.get_display_handle(),
+                self.context,
+                ffi::egl::CONTEXT_CLIENT_VERSION as i32,
+                &mut version,
+            );
+        }
+        Self::new_internal(
+            &self.display,
+            Some(self),
+            ContextConfig::Inherit {
+                config_id: self.config_id,
+                pixel_format: self.pixel_format,
+                version: version.max(2) as u8,
+            },
+            priority,
+        )
     }
 
     fn new_internal(
         display: &EGLDisplay,
         shared: Option<&EGLContext>,
-        config: Option<(GlAttributes, PixelFormatRequirements)>,
+        config: ContextConfig,
+        priority: Option<ContextPriority>,
     ) -> Result<EGLContext, Error> {
         let span = info_span!(parent: &display.span, "egl_context", ptr = tracing::field::Empty, shared = tracing::field::Empty);
         let _guard = span.enter();
@@ -114,11 +167,16 @@ impl EGLContext {
         }
 
         let (pixel_format, config_id) = match config {
-            Some((attributes, reqs)) => {
+            ContextConfig::Choose(attributes, reqs) => {
                 let (format, config_id) = display.choose_config(attributes, reqs)?;
                 (Some(format), config_id)
             }
-            None => {
+            ContextConfig::Inherit {
+                config_id,
+                pixel_format,
+                ..
+            } if config_id != ffi::egl::NO_CONFIG_KHR => (pixel_format, config_id),
+            ContextConfig::Inherit { .. } | ContextConfig::None => {
                 if !display
                     .extensions()
                     .iter()
@@ -144,7 +202,7 @@ impl EGLContext {
 
         let mut context_attributes = Vec::with_capacity(10);
 
-        if let Some((attributes, _)) = config {
+        if let ContextConfig::Choose(attributes, _) = config {
             let version = attributes.version;
 
             if display.get_egl_version() >= (1, 5)
@@ -171,9 +229,27 @@ impl EGLContext {
                 context_attributes.push(version.0 as i32);
             }
         } else {
-            trace!("Setting CONTEXT_CLIENT_VERSION to 2");
+            let version = match config {
+                ContextConfig::Inherit { version, .. } => version,
+                _ => 2,
+            };
+            trace!("Setting CONTEXT_CLIENT_VERSION to {}", version);
             context_attributes.push(ffi::egl::CONTEXT_CLIENT_VERSION as i32);
-            context_attributes.push(2);
+            context_attributes.push(version as i32);
+        }
+
+        if let Some(priority) = priority {
+            if display
+                .extensions()
+                .iter()
+                .any(|x| x == "EGL_IMG_context_priority")
+            {
+                trace!("Setting CONTEXT_PRIORITY_LEVEL_IMG to {:?}", priority);
+                context_attributes.push(ffi::egl::CONTEXT_PRIORITY_LEVEL_IMG as i32);
+                context_attributes.push(priority.to_raw());
+            } else {
+                debug!("EGL_IMG_context_priority is not supported, ignoring priority hint");
+            }
         }
 
         context_attributes.push(ffi::egl::NONE as i32);
@@ -273,6 +349,36 @@ impl EGLContext {
         unsafe { ffi::egl::GetCurrentContext() == self.context as *const _ }
     }
 
+    /// Returns the priority the context was created with
+    ///
+    /// This may differ from the requested priority, as the implementation is free to ignore the hint.
+    /// Returns `None` if `EGL_IMG_context_priority` is not supported.
+    pub fn priority(&self) -> Option<ContextPriority> {
+        if !self
+            .display
+            .extensions()
+            .iter()
+            .any(|x| x == "EGL_IMG_context_priority")
+        {
+            return None;
+        }
+
+        let mut value = 0;
+        let res = unsafe {
+            ffi::egl::QueryContext(
+                **self.display.get_display_handle(),
+                self.context,
+                ffi::egl::CONTEXT_PRIORITY_LEVEL_IMG as i32,
+                &mut value,
+            )
+        };
+        if res == ffi::egl::TRUE {
+            ContextPriority::from_raw(value)
+        } else {
+            None
+        }
+    }
+
     /// Returns the egl config for this context
     pub fn config_id(&self) -> ffi::egl::types::EGLConfig {
         self.config_id
@@ -319,7 +425,8 @@ impl EGLContext {
     /// Retrieve user_data associated with this context
     ///
     /// *Note:* UserData is shared between shared context, if constructed with
-    /// [`new_shared`](EGLContext::new_shared) or [`new_shared_with_config`](EGLContext::new_shared_with_config).
+    /// [`new_shared`](EGLContext::new_shared), [`new_shared_with_config`](EGLContext::new_shared_with_config)
+    /// or [`create_shared`](EGLContext::create_shared).
     pub fn user_data(&self) -> &UserDataMap {
         &self.user_data
     }
@@ -346,6 +453,51 @@ impl Drop for EGLContext {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+enum ContextConfig {
+    None,
+    Choose(GlAttributes, PixelFormatRequirements),
+    Inherit {
+        config_id: ffi::egl::types::EGLConfig,
+        pixel_format: Option<PixelFormat>,
+        version: u8,
+    },
+}
+
+/// Priority hint for context creation
+///
+/// Requires `EGL_IMG_context_priority`, the hint is ignored if the extension is not supported.
+/// The implementation may also grant a different priority than requested, which can be checked
+/// with [`EGLContext::priority`]. High priority usually requires elevated privileges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContextPriority {
+    /// High priority, e.g. for the context compositing the outputs
+    High,
+    /// Medium priority, the default
+    Medium,
+    /// Low priority, e.g. for background uploads
+    Low,
+}
+
+impl ContextPriority {
+    fn to_raw(self) -> i32 {
+        (match self {
+            ContextPriority::High => ffi::egl::CONTEXT_PRIORITY_HIGH_IMG,
+            ContextPriority::Medium => ffi::egl::CONTEXT_PRIORITY_MEDIUM_IMG,
+            ContextPriority::Low => ffi::egl::CONTEXT_PRIORITY_LOW_IMG,
+        }) as i32
+    }
+
+    fn from_raw(value: i32) -> Option<ContextPriority> {
+        match value as u32 {
+            ffi::egl::CONTEXT_PRIORITY_HIGH_IMG => Some(ContextPriority::High),
+            ffi::egl::CONTEXT_PRIORITY_MEDIUM_IMG => Some(ContextPriority::Medium),
+            ffi::egl::CONTEXT_PRIORITY_LOW_IMG => Some(ContextPriority::Low),
+            _ => None,
+        }
+    }
+}
+
 /// Attributes to use when creating an OpenGL context.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct GlAttributes {
@@ -724,7 +724,10 @@ impl EGLDisplay {
             );
 
             if image == ffi::egl::NO_IMAGE_KHR {
-                Err(Error::EGLImageCreationFailed)
+                Err(match EGLError::from_last_call() {
+                    Err(err) => Error::DmabufImportFailed(err),
+                    Ok(()) => Error::EGLImageCreationFailed,
+                })
             } else {
                 Ok(image)
             }
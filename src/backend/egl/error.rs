@@ -48,6 +48,12 @@ pub enum Error {
     /// Failed to create `EGLImage` from the buffer
     #[error("Failed to create `EGLImage` from the buffer")]
     EGLImageCreationFailed,
+    /// Failed to create `EGLImage` from a dmabuf
+    #[error("Failed to create `EGLImage` from the dmabuf")]
+    DmabufImportFailed(#[source] EGLError),
+    /// The texture is not backed by an `EGLImage`
+    #[error("The texture is not backed by an `EGLImage`")]
+    NoEGLImage,
     /// Failed to create `Dmabuf` from the image
     #[error("Faiedl to create `Dmabuf` from the image")]
     DmabufExportFailed(#[source] EGLError),
@@ -0,0 +1,135 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use super::{Device, Event, InputBackend, InputEvent};
+
+/// Tracker of the last activity of every input device
+///
+/// While idle notifications usually consider the seat as a whole, this allows to react
+/// to single devices becoming idle, e.g. to dim the display only when the touchscreen was
+/// not used for a while. Devices are identified by their [`Device::id`].
+#[derive(Debug, Default)]
+pub struct PerDeviceActivity {
+    last_activity: HashMap<String, Instant>,
+}
+
+impl PerDeviceActivity {
+    /// Create a new tracker without any known devices
+    pub fn new() -> PerDeviceActivity {
+        PerDeviceActivity::default()
+    }
+
+    /// Feed an event of an input backend to the tracker
+    ///
+    /// Added devices count as active, removed devices are forgotten.
+    pub fn process_event<B: InputBackend>(&mut self, event: &InputEvent<B>) {
+        let now = Instant::now();
+        match event {
+            InputEvent::DeviceRemoved { device } => self.remove_device(&device.id()),
+            event => {
+                if let Some(id) = event_device_id(event) {
+                    self.notify_activity_at(id, now);
+                }
+            }
+        }
+    }
+
+    /// Mark the device with the given id as active right now
+    pub fn notify_activity(&mut self, device_id: &str) {
+        self.notify_activity_at(device_id.to_owned(), Instant::now());
+    }
+
+    /// Forget about the device with the given id
+    pub fn remove_device(&mut self, device_id: &str) {
+        self.last_activity.remove(device_id);
+    }
+
+    /// Time of the last activity of the device with the given id
+    pub fn last_activity(&self, device_id: &str) -> Option<Instant> {
+        self.last_activity.get(device_id).copied()
+    }
+
+    /// Ids of all devices without any activity for at least `timeout`
+    pub fn idle_devices(&self, timeout: Duration) -> Vec<String> {
+        self.idle_devices_at(timeout, Instant::now())
+    }
+
+    fn notify_activity_at(&mut self, device_id: String, time: Instant) {
+        self.last_activity.insert(device_id, time);
+    }
+
+    fn idle_devices_at(&self, timeout: Duration, now: Instant) -> Vec<String> {
+        self.last_activity
+            .iter()
+            .filter(|(_, last)| now.saturating_duration_since(**last) >= timeout)
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+}
+
+fn event_device_id<B: InputBackend>(event: &InputEvent<B>) -> Option<String> {
+    let device = match event {
+        InputEvent::DeviceAdded { device } | InputEvent::DeviceRemoved { device } => {
+            return Some(device.id())
+        }
+        InputEvent::Keyboard { event } => event.device(),
+        InputEvent::PointerMotion { event } => event.device(),
+        InputEvent::PointerMotionAbsolute { event } => event.device(),
+        InputEvent::PointerButton { event } => event.device(),
+        InputEvent::PointerAxis { event } => event.device(),
+        InputEvent::GestureSwipeBegin { event } => event.device(),
+        InputEvent::GestureSwipeUpdate { event } => event.device(),
+        InputEvent::GestureSwipeEnd { event } => event.device(),
+        InputEvent::GesturePinchBegin { event } => event.device(),
+        InputEvent::GesturePinchUpdate { event } => event.device(),
+        InputEvent::GesturePinchEnd { event } => event.device(),
+        InputEvent::GestureHoldBegin { event } => event.device(),
+        InputEvent::GestureHoldEnd { event } => event.device(),
+        InputEvent::TouchDown { event } => event.device(),
+        InputEvent::TouchMotion { event } => event.device(),
+        InputEvent::TouchUp { event } => event.device(),
+        InputEvent::TouchCancel { event } => event.device(),
+        InputEvent::TouchFrame { event } => event.device(),
+        InputEvent::TabletToolAxis { event } => event.device(),
+        InputEvent::TabletToolProximity { event } => event.device(),
+        InputEvent::TabletToolTip { event } => event.device(),
+        InputEvent::TabletToolButton { event } => event.device(),
+        // backend specific events are not tied to a device
+        InputEvent::Special(_) => return None,
+    };
+    Some(device.id())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::PerDeviceActivity;
+
+    #[test]
+    fn one_of_two_devices_goes_idle() {
+        let mut activity = PerDeviceActivity::new();
+        let start = Instant::now();
+        let timeout = Duration::from_secs(30);
+
+        activity.notify_activity_at("keyboard".into(), start);
+        activity.notify_activity_at("touchscreen".into(), start);
+        assert!(activity.idle_devices_at(timeout, start).is_empty());
+
+        // only the keyboard is used afterwards
+        activity.notify_activity_at("keyboard".into(), start + Duration::from_secs(20));
+        let idle = activity.idle_devices_at(timeout, start + Duration::from_secs(40));
+        assert_eq!(idle, vec![String::from("touchscreen")]);
+
+        let idle = activity.idle_devices_at(timeout, start + Duration::from_secs(50));
+        assert_eq!(idle.len(), 2);
+
+        // removed devices are never reported as idle
+        activity.remove_device("touchscreen");
+        assert_eq!(activity.last_activity("touchscreen"), None);
+        let idle = activity.idle_devices_at(timeout, start + Duration::from_secs(50));
+        assert_eq!(idle, vec![String::from("keyboard")]);
+    }
+}
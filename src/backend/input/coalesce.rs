@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use super::{AbsolutePositionEvent, Device, Event, InputBackend};
+use crate::utils::{Logical, Point, Size};
+
+/// Default epsilon of an [`AbsoluteMotionCoalescer`], in logical pixels
+pub const DEFAULT_ABSOLUTE_MOTION_EPSILON: f64 = 0.01;
+
+/// Filter dropping absolute motion events, that do not move the position
+///
+/// Some touchscreens and tablets keep re-reporting the same absolute position while the
+/// finger or tool is still, which would wake up clients for nothing. This filter compares
+/// the transformed position of every absolute motion event with the last position of the
+/// same device that was let through and drops it, if both are within the configured epsilon
+/// on both axes.
+///
+/// The filter is disabled by default, so it can be kept around unconditionally and be
+/// turned on for devices known to behave like this.
+#[derive(Debug)]
+pub struct AbsoluteMotionCoalescer {
+    enabled: bool,
+    epsilon: f64,
+    last_position: HashMap<String, Point<f64, Logical>>,
+}
+
+impl Default for AbsoluteMotionCoalescer {
+    fn default() -> Self {
+        AbsoluteMotionCoalescer {
+            enabled: false,
+            epsilon: DEFAULT_ABSOLUTE_MOTION_EPSILON,
+            last_position: HashMap::new(),
+        }
+    }
+}
+
+impl AbsoluteMotionCoalescer {
+    /// Create a new, disabled filter
+    pub fn new() -> AbsoluteMotionCoalescer {
+        AbsoluteMotionCoalescer::default()
+    }
+
+    /// Whether redundant events are dropped
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Enable or disable dropping redundant events
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.last_position.clear();
+        }
+    }
+
+    /// Largest distance on each axis, in logical pixels, at which positions are considered equal
+    pub fn epsilon(&self) -> f64 {
+        self.epsilon
+    }
+
+    /// Set the largest distance at which positions are considered equal
+    ///
+    /// Negative values are treated as `0.0`, which only drops exactly identical positions.
+    pub fn set_epsilon(&mut self, epsilon: f64) {
+        self.epsilon = if epsilon.is_nan() { 0.0 } else { epsilon.max(0.0) };
+    }
+
+    /// Forget about the last position of a device, e.g. after it was removed
+    pub fn remove_device(&mut self, device_id: &str) {
+        self.last_position.remove(device_id);
+    }
+
+    /// Check an absolute motion event, returns `false` if it should be dropped
+    ///
+    /// `coordinate_space` is the size the position is transformed into, usually the size of the
+    /// output the device is mapped to, see [`AbsolutePositionEvent::position_transformed`].
+    pub fn filter_event<B: InputBackend>(
+        &mut self,
+        event: &B::PointerMotionAbsoluteEvent,
+        coordinate_space: Size<i32, Logical>,
+    ) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        let position = event.position_transformed(coordinate_space);
+        self.filter(&event.device().id(), position)
+    }
+
+    /// Check a transformed position of the given device, returns `false` if it should be dropped
+    pub fn filter(&mut self, device_id: &str, position: Point<f64, Logical>) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        match self.last_position.get_mut(device_id) {
+            Some(last)
+                if (last.x - position.x).abs() <= self.epsilon
+                    && (last.y - position.y).abs() <= self.epsilon =>
+            {
+                false
+            }
+            Some(last) => {
+                *last = position;
+                true
+            }
+            None => {
+                self.last_position.insert(device_id.to_owned(), position);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AbsoluteMotionCoalescer;
+
+    #[test]
+    fn identical_positions_are_coalesced() {
+        let mut filter = AbsoluteMotionCoalescer::new();
+        filter.set_enabled(true);
+
+        assert!(filter.filter("touchscreen", (100.0, 50.0).into()));
+        assert!(!filter.filter("touchscreen", (100.0, 50.0).into()));
+        assert!(!filter.filter("touchscreen", (100.005, 50.0).into()));
+        // a 1px move passes through
+        assert!(filter.filter("touchscreen", (101.0, 50.0).into()));
+        assert!(!filter.filter("touchscreen", (101.0, 50.0).into()));
+
+        // devices are compared separately
+        assert!(filter.filter("tablet", (101.0, 50.0).into()));
+
+        filter.set_enabled(false);
+        assert!(filter.filter("touchscreen", (101.0, 50.0).into()));
+    }
+}
@@ -0,0 +1,358 @@
+use std::time::Duration;
+
+use super::{ButtonState, Event, InputBackend, MouseButton, PointerButtonEvent};
+
+/// Default time window in which left and right have to be pressed to emulate a middle click
+pub const DEFAULT_MIDDLE_BUTTON_WINDOW: Duration = Duration::from_millis(50);
+
+const LEFT: u8 = 1;
+const RIGHT: u8 = 2;
+
+/// Button event produced by a [`MiddleButtonEmulator`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmulatedButtonEvent {
+    /// Button code of the event, see [`PointerButtonEvent::button_code`]
+    pub button_code: u32,
+    /// State of the button
+    pub state: ButtonState,
+    /// Timestamp of the event in microseconds, see [`Event::time`]
+    pub time: u64,
+}
+
+impl EmulatedButtonEvent {
+    fn new(button_code: u32, state: ButtonState, time: u64) -> EmulatedButtonEvent {
+        EmulatedButtonEvent {
+            button_code,
+            state,
+            time,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EmulationState {
+    Idle,
+    /// Left or right was pressed and is held back, waiting for the other button
+    Pending {
+        button: u8,
+        time: u64,
+    },
+    /// A middle press was sent, waiting for both buttons to be released
+    Emulating {
+        held: u8,
+    },
+    /// Left and/or right were forwarded unchanged
+    Passthrough {
+        held: u8,
+    },
+}
+
+/// Emulation of a middle button by pressing left and right together
+///
+/// A press of the left or right button is held back until either the other button is pressed
+/// within the configured time window, in which case a middle press is sent instead of both
+/// buttons, or the window expires, in which case the original press is forwarded.
+/// The middle button is released once both buttons are released again.
+///
+/// As a held back press is only forwarded with the next event passed to the emulator, the
+/// compositor should call [`MiddleButtonEmulator::timeout`] once the time returned by
+/// [`MiddleButtonEmulator::deadline`] has passed, e.g. from a calloop timer.
+///
+/// Events of other buttons are passed through unchanged.
+#[derive(Debug)]
+pub struct MiddleButtonEmulator {
+    window: Duration,
+    enabled: bool,
+    state: EmulationState,
+}
+
+impl Default for MiddleButtonEmulator {
+    fn default() -> Self {
+        MiddleButtonEmulator::new(DEFAULT_MIDDLE_BUTTON_WINDOW)
+    }
+}
+
+impl MiddleButtonEmulator {
+    /// Create a new emulator with the given time window
+    pub fn new(window: Duration) -> MiddleButtonEmulator {
+        MiddleButtonEmulator {
+            window,
+            enabled: true,
+            state: EmulationState::Idle,
+        }
+    }
+
+    /// Time window in which both buttons have to be pressed
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Set the time window in which both buttons have to be pressed
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = window;
+    }
+
+    /// Returns whether emulation is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Enable or disable emulation
+    ///
+    /// Disabling only takes effect once no left or right button is held down anymore,
+    /// so press and release events are always forwarded as pairs.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Timestamp in microseconds at which a held back press has to be forwarded,
+    /// if a press is currently held back
+    pub fn deadline(&self) -> Option<u64> {
+        match self.state {
+            EmulationState::Pending { time, .. } => Some(time + self.window.as_micros() as u64),
+            _ => None,
+        }
+    }
+
+    /// Process a button event of an input backend
+    ///
+    /// Returns the events to forward in place of the given event.
+    pub fn process_event<B: InputBackend>(
+        &mut self,
+        event: &B::PointerButtonEvent,
+    ) -> Vec<EmulatedButtonEvent> {
+        self.process(event.button_code(), event.state(), event.time())
+    }
+
+    /// Process a raw button event with a timestamp in microseconds
+    ///
+    /// Returns the events to forward in place of the given event.
+    pub fn process(&mut self, button_code: u32, state: ButtonState, time: u64) -> Vec<EmulatedButtonEvent> {
+        let mut events = self.timeout(time);
+
+        let button = if button_code == MouseButton::Left.button_code() {
+            LEFT
+        } else if button_code == MouseButton::Right.button_code() {
+            RIGHT
+        } else {
+            // keep ordering intact by forwarding a held back press first
+            events.extend(self.flush_pending());
+            events.push(EmulatedButtonEvent::new(button_code, state, time));
+            return events;
+        };
+
+        let forward = EmulatedButtonEvent::new(button_code, state, time);
+        self.state = match (self.state, state) {
+            (EmulationState::Idle, ButtonState::Pressed) if self.enabled => {
+                EmulationState::Pending { button, time }
+            }
+            (EmulationState::Idle, ButtonState::Pressed) => {
+                events.push(forward);
+                EmulationState::Passthrough { held: button }
+            }
+            (EmulationState::Idle, ButtonState::Released) => {
+                events.push(forward);
+                EmulationState::Idle
+            }
+            (EmulationState::Pending { button: pending, .. }, ButtonState::Pressed) if pending != button => {
+                events.push(EmulatedButtonEvent::new(
+                    MouseButton::Middle.button_code(),
+                    ButtonState::Pressed,
+                    time,
+                ));
+                EmulationState::Emulating { held: LEFT | RIGHT }
+            }
+            (EmulationState::Pending { .. }, _) => {
+                // released (or repeated) before the other button was pressed
+                events.extend(self.flush_pending());
+                return self.process_passthrough(events, button, forward);
+            }
+            (EmulationState::Emulating { held }, ButtonState::Released) => {
+                let held = held & !button;
+                if held == 0 {
+                    events.push(EmulatedButtonEvent::new(
+                        MouseButton::Middle.button_code(),
+                        ButtonState::Released,
+                        time,
+                    ));
+                    EmulationState::Idle
+                } else {
+                    EmulationState::Emulating { held }
+                }
+            }
+            (EmulationState::Emulating { held }, ButtonState::Pressed) => {
+                // pressed again while the other button keeps the middle button down
+                EmulationState::Emulating { held: held | button }
+            }
+            (EmulationState::Passthrough { .. }, _) => {
+                return self.process_passthrough(events, button, forward);
+            }
+        };
+        events
+    }
+
+    /// Forward a held back press, if its time window expired at the given time
+    pub fn timeout(&mut self, time: u64) -> Vec<EmulatedButtonEvent> {
+        match self.deadline() {
+            Some(deadline) if time >= deadline => self.flush_pending().into_iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn flush_pending(&mut self) -> Option<EmulatedButtonEvent> {
+        match self.state {
+            EmulationState::Pending { button, time } => {
+                self.state = EmulationState::Passthrough { held: button };
+                Some(EmulatedButtonEvent::new(
+                    button_code(button),
+                    ButtonState::Pressed,
+                    time,
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    fn process_passthrough(
+        &mut self,
+        mut events: Vec<EmulatedButtonEvent>,
+        button: u8,
+        forward: EmulatedButtonEvent,
+    ) -> Vec<EmulatedButtonEvent> {
+        let held = match self.state {
+            EmulationState::Passthrough { held } => held,
+            _ => 0,
+        };
+        let held = match forward.state {
+            ButtonState::Pressed => held | button,
+            ButtonState::Released => held & !button,
+        };
+        self.state = if held == 0 {
+            EmulationState::Idle
+        } else {
+            EmulationState::Passthrough { held }
+        };
+        events.push(forward);
+        events
+    }
+}
+
+fn button_code(button: u8) -> u32 {
+    if button == LEFT {
+        MouseButton::Left.button_code()
+    } else {
+        MouseButton::Right.button_code()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{ButtonState, EmulatedButtonEvent, MiddleButtonEmulator, MouseButton};
+
+    fn ms(ms: u64) -> u64 {
+        ms * 1000
+    }
+
+    fn event(button: MouseButton, state: ButtonState, time: u64) -> EmulatedButtonEvent {
+        EmulatedButtonEvent {
+            button_code: button.button_code(),
+            state,
+            time,
+        }
+    }
+
+    #[test]
+    fn simultaneous_press_emulates_middle() {
+        let mut emulator = MiddleButtonEmulator::new(Duration::from_millis(50));
+        let left = MouseButton::Left.button_code();
+        let right = MouseButton::Right.button_code();
+
+        assert!(emulator.process(left, ButtonState::Pressed, ms(0)).is_empty());
+        assert_eq!(emulator.deadline(), Some(ms(50)));
+        assert_eq!(
+            emulator.process(right, ButtonState::Pressed, ms(20)),
+            vec![event(MouseButton::Middle, ButtonState::Pressed, ms(20))]
+        );
+        assert!(emulator.process(left, ButtonState::Released, ms(100)).is_empty());
+        assert_eq!(
+            emulator.process(right, ButtonState::Released, ms(110)),
+            vec![event(MouseButton::Middle, ButtonState::Released, ms(110))]
+        );
+        assert_eq!(emulator.deadline(), None);
+    }
+
+    #[test]
+    fn sequential_press_is_not_emulated() {
+        let mut emulator = MiddleButtonEmulator::new(Duration::from_millis(50));
+        let left = MouseButton::Left.button_code();
+        let right = MouseButton::Right.button_code();
+
+        assert!(emulator.process(left, ButtonState::Pressed, ms(0)).is_empty());
+        assert_eq!(
+            emulator.process(right, ButtonState::Pressed, ms(80)),
+            vec![
+                event(MouseButton::Left, ButtonState::Pressed, ms(0)),
+                event(MouseButton::Right, ButtonState::Pressed, ms(80)),
+            ]
+        );
+        assert_eq!(
+            emulator.process(left, ButtonState::Released, ms(90)),
+            vec![event(MouseButton::Left, ButtonState::Released, ms(90))]
+        );
+        assert_eq!(
+            emulator.process(right, ButtonState::Released, ms(95)),
+            vec![event(MouseButton::Right, ButtonState::Released, ms(95))]
+        );
+    }
+
+    #[test]
+    fn click_within_window_is_forwarded() {
+        let mut emulator = MiddleButtonEmulator::default();
+        let right = MouseButton::Right.button_code();
+
+        assert!(emulator.process(right, ButtonState::Pressed, ms(0)).is_empty());
+        assert_eq!(
+            emulator.process(right, ButtonState::Released, ms(10)),
+            vec![
+                event(MouseButton::Right, ButtonState::Pressed, ms(0)),
+                event(MouseButton::Right, ButtonState::Released, ms(10)),
+            ]
+        );
+    }
+
+    #[test]
+    fn timeout_forwards_held_back_press() {
+        let mut emulator = MiddleButtonEmulator::new(Duration::from_millis(30));
+        let left = MouseButton::Left.button_code();
+
+        assert!(emulator.process(left, ButtonState::Pressed, ms(0)).is_empty());
+        assert!(emulator.timeout(ms(10)).is_empty());
+        assert_eq!(
+            emulator.timeout(ms(30)),
+            vec![event(MouseButton::Left, ButtonState::Pressed, ms(0))]
+        );
+        assert_eq!(
+            emulator.process(left, ButtonState::Released, ms(200)),
+            vec![event(MouseButton::Left, ButtonState::Released, ms(200))]
+        );
+    }
+
+    #[test]
+    fn disabled_emulator_passes_through() {
+        let mut emulator = MiddleButtonEmulator::default();
+        emulator.set_enabled(false);
+        let left = MouseButton::Left.button_code();
+        let right = MouseButton::Right.button_code();
+
+        assert_eq!(
+            emulator.process(left, ButtonState::Pressed, ms(0)),
+            vec![event(MouseButton::Left, ButtonState::Pressed, ms(0))]
+        );
+        assert_eq!(
+            emulator.process(right, ButtonState::Pressed, ms(1)),
+            vec![event(MouseButton::Right, ButtonState::Pressed, ms(1))]
+        );
+    }
+}
@@ -2,12 +2,34 @@
 
 use std::path::PathBuf;
 
+mod activity;
+mod coalesce;
+mod middle_button;
+mod queue;
+mod remap;
+mod retry;
+mod source;
 mod tablet;
-
+mod touch;
+mod tremor;
+
+pub use activity::PerDeviceActivity;
+pub use coalesce::{AbsoluteMotionCoalescer, DEFAULT_ABSOLUTE_MOTION_EPSILON};
+pub use middle_button::{EmulatedButtonEvent, MiddleButtonEmulator, DEFAULT_MIDDLE_BUTTON_WINDOW};
+pub use queue::{
+    InputQueue, InputQueueHandle, InputQueuePolicy, QueueAction, QueuedEventMetadata,
+    DEFAULT_INPUT_QUEUE_CAPACITY, DEFAULT_INPUT_QUEUE_MAX_AGE,
+};
+pub use remap::ButtonRemap;
+pub use retry::RetryingInputBackend;
+pub use source::{InputBackendSource, InputEventSender};
 pub use tablet::{
     ProximityState, TabletToolAxisEvent, TabletToolButtonEvent, TabletToolCapabilities, TabletToolDescriptor,
-    TabletToolEvent, TabletToolProximityEvent, TabletToolTipEvent, TabletToolTipState, TabletToolType,
+    TabletToolDescriptorBuilder, TabletToolEvent, TabletToolProximityEvent, TabletToolTipEvent,
+    TabletToolTipState, TabletToolType, ToolChangeTracker,
 };
+pub use touch::TouchSlotTracker;
+pub use tremor::{TremorFilter, DEFAULT_TREMOR_FILTER_STRENGTH};
 
 use crate::utils::{Logical, Point, Raw, Size};
 
@@ -29,6 +51,57 @@ pub trait Device: PartialEq + Eq + std::hash::Hash {
     ///
     /// The path is an absolute path and includes the sys mount point.
     fn syspath(&self) -> Option<PathBuf>;
+
+    /// Enable or disable drag lock for tap-and-drag
+    ///
+    /// With drag lock, lifting the finger during a tap-and-drag does not end the drag right away,
+    /// giving the user time to reposition the finger. Devices or backends not supporting
+    /// this ignore the setting.
+    fn set_tap_drag_lock(&self, enabled: bool) {
+        let _ = enabled;
+    }
+
+    /// Whether drag lock for tap-and-drag is enabled
+    fn tap_drag_lock_enabled(&self) -> bool {
+        false
+    }
+
+    /// Update the keyboard LEDs of this device
+    ///
+    /// Devices or backends without LEDs ignore this.
+    fn set_leds(&self, leds: LedState) {
+        let _ = leds;
+    }
+
+    /// Current state of the keyboard LEDs of this device
+    ///
+    /// Returns `None` if the device or backend has no LEDs or their state is unknown,
+    /// e.g. because they were never set. Compositors resyncing the LEDs after a VT switch
+    /// should not rely on this reflecting changes made by other sessions.
+    fn leds(&self) -> Option<LedState> {
+        None
+    }
+
+    /// Name of the output this device is mapped to
+    ///
+    /// Devices tied to a single output, like touchscreens and their integrated displays,
+    /// may be associated with it by the backend, e.g. through the `WL_OUTPUT` udev property
+    /// for libinput. Returns `None` if the device is not mapped to an output.
+    fn output_name(&self) -> Option<String> {
+        None
+    }
+}
+
+bitflags::bitflags! {
+    /// State of the keyboard LEDs of a device
+    pub struct LedState: u32 {
+        /// The num lock LED
+        const NUM_LOCK = 1;
+        /// The caps lock LED
+        const CAPS_LOCK = 2;
+        /// The scroll lock LED
+        const SCROLL_LOCK = 4;
+    }
 }
 
 /// Set of input types a device may provide
@@ -47,8 +120,20 @@ pub enum DeviceCapability {
 /// Trait for generic functions every input event does provide
 pub trait Event<B: InputBackend> {
     /// Timestamp in milliseconds
+    ///
+    /// This is the representation used by the wayland protocol, it wraps around after
+    /// roughly 49.7 days. Use [`cmp_time_msec`](crate::utils::cmp_time_msec) to compare
+    /// two of these timestamps.
     fn time_msec(&self) -> u32 {
-        (self.time() / 1000) as u32
+        (self.time_usec() / 1000) as u32
+    }
+
+    /// Timestamp in microseconds
+    ///
+    /// Backends timestamp their events with the [`Monotonic`](crate::utils::Monotonic) clock where
+    /// possible, so this can be compared to [`Clock::now`](crate::utils::Clock::now).
+    fn time_usec(&self) -> u64 {
+        self.time()
     }
 
     /// Timestamp in microseconds, with an undefined base.
@@ -61,6 +146,18 @@ pub trait Event<B: InputBackend> {
 
     /// Returns the device, that generated this event
     fn device(&self) -> B::Device;
+
+    /// Sequence number of the frame this event belongs to
+    ///
+    /// Backends tracking frames assign increasing numbers to them, so all events of the same
+    /// frame (e.g. up to a touch or pointer frame event) share the same sequence number.
+    /// This is meant to correlate events in logs and metrics. The X11 backend numbers the
+    /// X events the input events originate from.
+    ///
+    /// Returns `None` if the backend does not track frames.
+    fn sequence(&self) -> Option<u64> {
+        None
+    }
 }
 
 /// Used to mark events never emitted by an [`InputBackend`] implementation.
@@ -80,6 +177,10 @@ impl<B: InputBackend> Event<B> for UnusedEvent {
     fn device(&self) -> B::Device {
         match *self {}
     }
+
+    fn sequence(&self) -> Option<u64> {
+        match *self {}
+    }
 }
 
 /// State of key on a keyboard. Either pressed or released
@@ -136,6 +237,45 @@ pub enum MouseButton {
     Forward,
     /// Back mouse button.
     Back,
+    /// Any other button, holding its raw button code
+    ///
+    /// Use [`MouseButton::from_code`] to construct it, which maps the codes of the named
+    /// buttons to their variants.
+    Other(u16),
+}
+
+impl MouseButton {
+    /// Mouse button for the given numerical button code
+    ///
+    /// The value corresponds to the `BTN_` constants of the Linux input event codes, see
+    /// [`PointerButtonEvent::button_code`]. Codes of named buttons map to their variants, all
+    /// other codes to [`MouseButton::Other`]. Codes above `u16::MAX`, which are not valid
+    /// Linux input event codes, are saturated.
+    pub fn from_code(code: u32) -> MouseButton {
+        match code {
+            0x110 => MouseButton::Left,
+            0x111 => MouseButton::Right,
+            0x112 => MouseButton::Middle,
+            0x115 => MouseButton::Forward,
+            0x116 => MouseButton::Back,
+            code => MouseButton::Other(u16::try_from(code).unwrap_or(u16::MAX)),
+        }
+    }
+
+    /// Numerical button code of this mouse button
+    ///
+    /// The value corresponds to the matching `BTN_` constant of the Linux input event codes,
+    /// see [`PointerButtonEvent::button_code`].
+    pub fn button_code(&self) -> u32 {
+        match self {
+            MouseButton::Left => 0x110,
+            MouseButton::Right => 0x111,
+            MouseButton::Middle => 0x112,
+            MouseButton::Forward => 0x115,
+            MouseButton::Back => 0x116,
+            MouseButton::Other(code) => *code as u32,
+        }
+    }
 }
 
 /// State of a button on a pointer device, like mouse or tablet tool. Either pressed or released
@@ -151,17 +291,10 @@ pub enum ButtonState {
 pub trait PointerButtonEvent<B: InputBackend>: Event<B> {
     /// Pressed button of the event.
     ///
-    /// This may return [`None`] if the button pressed in the event is not a standard mouse button. You may
-    /// obtain the button code using [`PointerButtonEvent::button_code`].
+    /// Buttons without a named variant are returned as [`MouseButton::Other`], see
+    /// [`MouseButton::from_code`]. The default implementation never returns [`None`].
     fn button(&self) -> Option<MouseButton> {
-        match self.button_code() {
-            0x110 => Some(MouseButton::Left),
-            0x111 => Some(MouseButton::Right),
-            0x112 => Some(MouseButton::Middle),
-            0x115 => Some(MouseButton::Forward),
-            0x116 => Some(MouseButton::Back),
-            _ => None,
-        }
+        Some(MouseButton::from_code(self.button_code()))
     }
 
     /// Returns the numerical button code of the mouse button.
@@ -345,8 +478,81 @@ pub trait PointerAxisEvent<B: InputBackend>: Event<B> {
     /// Guaranteed to be `Some` when source returns either [`AxisSource::Wheel`] or [`AxisSource::WheelTilt`].
     fn amount_discrete(&self, axis: Axis) -> Option<f64>;
 
+    /// Amount of scrolling on the given [`Axis`] in fractions of 120 per discrete step.
+    ///
+    /// High-resolution wheels report values smaller than 120 for partial steps.
+    /// A device may report this value in addition to [`PointerAxisEvent::amount`] and
+    /// [`PointerAxisEvent::amount_discrete`] for the same event.
+    ///
+    /// The default implementation derives the value from [`PointerAxisEvent::amount_discrete`].
+    fn amount_v120(&self, axis: Axis) -> Option<f64> {
+        self.amount_discrete(axis).map(|steps| steps * 120.0)
+    }
+
+    /// All scroll amounts reported by this event on the given [`Axis`].
+    fn full_axis(&self, axis: Axis) -> AxisData {
+        AxisData {
+            px: self.amount(axis),
+            discrete: self.amount_discrete(axis),
+            v120: self.amount_v120(axis),
+        }
+    }
+
     /// Source of the scroll event.
     fn source(&self) -> AxisSource;
+
+    /// Direction of scrolling on the given [`Axis`]
+    ///
+    /// Positive amounts scroll down or to the right. The amounts already have natural scrolling
+    /// applied if it is enabled for the device, so the direction is inverted accordingly.
+    ///
+    /// The default implementation uses the first non-zero amount of [`PointerAxisEvent::amount`],
+    /// [`PointerAxisEvent::amount_v120`] and [`PointerAxisEvent::amount_discrete`].
+    /// Returns `None` if the event does not scroll on the given axis.
+    fn direction(&self, axis: Axis) -> Option<ScrollDirection> {
+        let amount = [
+            self.amount(axis),
+            self.amount_v120(axis),
+            self.amount_discrete(axis),
+        ]
+        .into_iter()
+        .flatten()
+        .find(|amount| *amount != 0.0 && amount.is_finite())?;
+
+        Some(match (axis, amount > 0.0) {
+            (Axis::Vertical, false) => ScrollDirection::Up,
+            (Axis::Vertical, true) => ScrollDirection::Down,
+            (Axis::Horizontal, false) => ScrollDirection::Left,
+            (Axis::Horizontal, true) => ScrollDirection::Right,
+        })
+    }
+}
+
+/// Direction of scrolling, see [`PointerAxisEvent::direction`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum ScrollDirection {
+    /// Scrolling up on the vertical axis
+    Up,
+    /// Scrolling down on the vertical axis
+    Down,
+    /// Scrolling left on the horizontal axis
+    Left,
+    /// Scrolling right on the horizontal axis
+    Right,
+}
+
+/// Combined scroll amounts of a [`PointerAxisEvent`] on a single [`Axis`]
+///
+/// Each of the values may be reported independently of the others,
+/// e.g. a high-resolution wheel can provide all of them at once.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct AxisData {
+    /// Amount of scrolling in pixels
+    pub px: Option<f64>,
+    /// Amount of scrolling in discrete steps
+    pub discrete: Option<f64>,
+    /// Amount of scrolling in fractions of 120 per discrete step
+    pub v120: Option<f64>,
 }
 
 impl<B: InputBackend> PointerAxisEvent<B> for UnusedEvent {
@@ -406,9 +612,34 @@ impl<B: InputBackend> PointerMotionEvent<B> for UnusedEvent {
     }
 }
 
+/// Transform a coordinate into a target extent, returning `0.0` for empty extents and non-finite values
+pub(crate) fn transformed_or_zero(extent: i32, transform: impl FnOnce(i32) -> f64) -> f64 {
+    if extent <= 0 {
+        return 0.0;
+    }
+    let value = transform(extent);
+    if value.is_finite() {
+        value
+    } else {
+        0.0
+    }
+}
+
 /// Trait for pointer events generated by absolute device positioning.
-pub trait PointerMotionAbsoluteEvent<B: InputBackend>: AbsolutePositionEvent<B> {}
-impl<B: InputBackend> PointerMotionAbsoluteEvent<B> for UnusedEvent {}
+pub trait PointerMotionAbsoluteEvent<B: InputBackend>: AbsolutePositionEvent<B> {
+    /// Name of the output this event targets, if known
+    ///
+    /// Allows routing the event to the output directly instead of hit-testing its position.
+    /// Defaults to the output the device is mapped to, see [`Device::output_name`].
+    fn output_name(&self) -> Option<String> {
+        self.device().output_name()
+    }
+}
+impl<B: InputBackend> PointerMotionAbsoluteEvent<B> for UnusedEvent {
+    fn output_name(&self) -> Option<String> {
+        match *self {}
+    }
+}
 
 /// Input event with absolute location data.
 pub trait AbsolutePositionEvent<B: InputBackend>: Event<B> {
@@ -431,10 +662,14 @@ pub trait AbsolutePositionEvent<B: InputBackend>: Event<B> {
 
     /// Device position converted to the targets coordinate space.
     /// E.g. the focused output's resolution.
+    ///
+    /// If the width or height of the coordinate space is zero or negative, e.g. for an output
+    /// being torn down, the corresponding coordinate is `0.0`. The same applies if the backend
+    /// is unable to produce a finite value, so the result never contains `NaN` or infinity.
     fn position_transformed(&self, coordinate_space: Size<i32, Logical>) -> Point<f64, Logical> {
         (
-            self.x_transformed(coordinate_space.w),
-            self.y_transformed(coordinate_space.h),
+            transformed_or_zero(coordinate_space.w, |width| self.x_transformed(width)),
+            transformed_or_zero(coordinate_space.h, |height| self.y_transformed(height)),
         )
             .into()
     }
@@ -500,13 +735,96 @@ impl<B: InputBackend> TouchEvent<B> for UnusedEvent {
     }
 }
 
+/// Shape of a touch contact
+///
+/// The contact is described as an ellipse, see [`TouchDownEvent::orientation`] for its orientation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchShape {
+    /// Length of the major axis of the contact ellipse, in surface-local coordinates
+    pub major: f64,
+    /// Length of the minor axis of the contact ellipse, in surface-local coordinates
+    pub minor: f64,
+}
+
 /// Trait for touch events starting at a given position.
-pub trait TouchDownEvent<B: InputBackend>: TouchEvent<B> + AbsolutePositionEvent<B> {}
-impl<B: InputBackend> TouchDownEvent<B> for UnusedEvent {}
+pub trait TouchDownEvent<B: InputBackend>: TouchEvent<B> + AbsolutePositionEvent<B> {
+    /// Shape of the touch contact, if reported by the device
+    fn shape(&self) -> Option<TouchShape> {
+        None
+    }
+
+    /// Angle between the major axis of the touch contact and the y-axis in degrees,
+    /// if reported by the device
+    fn orientation(&self) -> Option<f64> {
+        None
+    }
+    /// Whether the contact was classified as a palm by the device
+    ///
+    /// Palm touches are usually the result of the hand resting on the device while typing
+    /// and should be ignored by the compositor.
+    fn is_palm(&self) -> bool {
+        false
+    }
+
+    /// Name of the output this touch point belongs to, if known
+    ///
+    /// Allows routing the touch point to the output directly instead of hit-testing its position.
+    /// Defaults to the output the device is mapped to, see [`Device::output_name`].
+    fn output_name(&self) -> Option<String> {
+        self.device().output_name()
+    }
+}
+impl<B: InputBackend> TouchDownEvent<B> for UnusedEvent {
+    fn shape(&self) -> Option<TouchShape> {
+        match *self {}
+    }
+
+    fn orientation(&self) -> Option<f64> {
+        match *self {}
+    }
+
+    fn is_palm(&self) -> bool {
+        match *self {}
+    }
+
+    fn output_name(&self) -> Option<String> {
+        match *self {}
+    }
+}
 
 /// Trait for touch events regarding movement on the screen
-pub trait TouchMotionEvent<B: InputBackend>: TouchEvent<B> + AbsolutePositionEvent<B> {}
-impl<B: InputBackend> TouchMotionEvent<B> for UnusedEvent {}
+pub trait TouchMotionEvent<B: InputBackend>: TouchEvent<B> + AbsolutePositionEvent<B> {
+    /// Shape of the touch contact, if reported by the device
+    fn shape(&self) -> Option<TouchShape> {
+        None
+    }
+
+    /// Angle between the major axis of the touch contact and the y-axis in degrees,
+    /// if reported by the device
+    fn orientation(&self) -> Option<f64> {
+        None
+    }
+    /// Whether the contact was classified as a palm by the device
+    ///
+    /// Palm touches are usually the result of the hand resting on the device while typing
+    /// and should be ignored by the compositor.
+    fn is_palm(&self) -> bool {
+        false
+    }
+}
+impl<B: InputBackend> TouchMotionEvent<B> for UnusedEvent {
+    fn shape(&self) -> Option<TouchShape> {
+        match *self {}
+    }
+
+    fn orientation(&self) -> Option<f64> {
+        match *self {}
+    }
+
+    fn is_palm(&self) -> bool {
+        match *self {}
+    }
+}
 
 /// Trait for touch events finishing.
 pub trait TouchUpEvent<B: InputBackend>: TouchEvent<B> {}
@@ -574,6 +892,14 @@ pub trait InputBackend: Sized {
 
     /// Special events that are custom to this backend
     type SpecialEvent;
+
+    /// Look up a currently connected device by its [`Device::id`]
+    ///
+    /// Backends not keeping track of their devices always return `None`.
+    fn device_by_id(&self, id: &str) -> Option<&Self::Device> {
+        let _ = id;
+        None
+    }
 }
 
 /// Different events that can be generated by an input backend
@@ -711,6 +1037,96 @@ pub enum InputEvent<B: InputBackend> {
     Special(B::SpecialEvent),
 }
 
+/// Kind of an [`InputEvent`] without its payload
+///
+/// Useful to count events by type or to key dispatch tables, see [`InputEvent::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputEventKind {
+    /// See [`InputEvent::DeviceAdded`]
+    DeviceAdded,
+    /// See [`InputEvent::DeviceRemoved`]
+    DeviceRemoved,
+    /// See [`InputEvent::Keyboard`]
+    Keyboard,
+    /// See [`InputEvent::PointerMotion`]
+    PointerMotion,
+    /// See [`InputEvent::PointerMotionAbsolute`]
+    PointerMotionAbsolute,
+    /// See [`InputEvent::PointerButton`]
+    PointerButton,
+    /// See [`InputEvent::PointerAxis`]
+    PointerAxis,
+    /// See [`InputEvent::GestureSwipeBegin`]
+    GestureSwipeBegin,
+    /// See [`InputEvent::GestureSwipeUpdate`]
+    GestureSwipeUpdate,
+    /// See [`InputEvent::GestureSwipeEnd`]
+    GestureSwipeEnd,
+    /// See [`InputEvent::GesturePinchBegin`]
+    GesturePinchBegin,
+    /// See [`InputEvent::GesturePinchUpdate`]
+    GesturePinchUpdate,
+    /// See [`InputEvent::GesturePinchEnd`]
+    GesturePinchEnd,
+    /// See [`InputEvent::GestureHoldBegin`]
+    GestureHoldBegin,
+    /// See [`InputEvent::GestureHoldEnd`]
+    GestureHoldEnd,
+    /// See [`InputEvent::TouchDown`]
+    TouchDown,
+    /// See [`InputEvent::TouchMotion`]
+    TouchMotion,
+    /// See [`InputEvent::TouchUp`]
+    TouchUp,
+    /// See [`InputEvent::TouchCancel`]
+    TouchCancel,
+    /// See [`InputEvent::TouchFrame`]
+    TouchFrame,
+    /// See [`InputEvent::TabletToolAxis`]
+    TabletToolAxis,
+    /// See [`InputEvent::TabletToolProximity`]
+    TabletToolProximity,
+    /// See [`InputEvent::TabletToolTip`]
+    TabletToolTip,
+    /// See [`InputEvent::TabletToolButton`]
+    TabletToolButton,
+    /// See [`InputEvent::Special`]
+    Special,
+}
+
+impl<B: InputBackend> InputEvent<B> {
+    /// The kind of this event
+    pub fn kind(&self) -> InputEventKind {
+        match self {
+            InputEvent::DeviceAdded { .. } => InputEventKind::DeviceAdded,
+            InputEvent::DeviceRemoved { .. } => InputEventKind::DeviceRemoved,
+            InputEvent::Keyboard { .. } => InputEventKind::Keyboard,
+            InputEvent::PointerMotion { .. } => InputEventKind::PointerMotion,
+            InputEvent::PointerMotionAbsolute { .. } => InputEventKind::PointerMotionAbsolute,
+            InputEvent::PointerButton { .. } => InputEventKind::PointerButton,
+            InputEvent::PointerAxis { .. } => InputEventKind::PointerAxis,
+            InputEvent::GestureSwipeBegin { .. } => InputEventKind::GestureSwipeBegin,
+            InputEvent::GestureSwipeUpdate { .. } => InputEventKind::GestureSwipeUpdate,
+            InputEvent::GestureSwipeEnd { .. } => InputEventKind::GestureSwipeEnd,
+            InputEvent::GesturePinchBegin { .. } => InputEventKind::GesturePinchBegin,
+            InputEvent::GesturePinchUpdate { .. } => InputEventKind::GesturePinchUpdate,
+            InputEvent::GesturePinchEnd { .. } => InputEventKind::GesturePinchEnd,
+            InputEvent::GestureHoldBegin { .. } => InputEventKind::GestureHoldBegin,
+            InputEvent::GestureHoldEnd { .. } => InputEventKind::GestureHoldEnd,
+            InputEvent::TouchDown { .. } => InputEventKind::TouchDown,
+            InputEvent::TouchMotion { .. } => InputEventKind::TouchMotion,
+            InputEvent::TouchUp { .. } => InputEventKind::TouchUp,
+            InputEvent::TouchCancel { .. } => InputEventKind::TouchCancel,
+            InputEvent::TouchFrame { .. } => InputEventKind::TouchFrame,
+            InputEvent::TabletToolAxis { .. } => InputEventKind::TabletToolAxis,
+            InputEvent::TabletToolProximity { .. } => InputEventKind::TabletToolProximity,
+            InputEvent::TabletToolTip { .. } => InputEventKind::TabletToolTip,
+            InputEvent::TabletToolButton { .. } => InputEventKind::TabletToolButton,
+            InputEvent::Special(_) => InputEventKind::Special,
+        }
+    }
+}
+
 /// Converts an xorg mouse button to the format used by libinput.
 ///
 /// Taken from https://sources.debian.org/src/xserver-xorg-input-libinput/1.1.0-1/src/xf86libinput.c/?hl=1508#L236-L252
@@ -718,9 +1134,558 @@ pub enum InputEvent<B: InputBackend> {
 pub(crate) fn xorg_mouse_to_libinput(xorg: u32) -> u32 {
     match xorg {
         0 => 0,
-        1 => 0x110,            // BTN_LEFT
-        2 => 0x112,            // BTN_MIDDLE
-        3 => 0x111,            // BTN_RIGHT
-        _ => xorg - 8 + 0x113, // BTN_SIZE
+        1 => 0x110, // BTN_LEFT
+        2 => 0x112, // BTN_MIDDLE
+        3 => 0x111, // BTN_RIGHT
+        // 4 to 7 are scroll buttons without a matching code
+        4..=7 => 0,
+        _ => xorg - 8 + 0x113, // BTN_SIDE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    struct TestDevice;
+
+    impl Device for TestDevice {
+        fn id(&self) -> String {
+            "test".into()
+        }
+
+        fn name(&self) -> String {
+            "test".into()
+        }
+
+        fn has_capability(&self, capability: DeviceCapability) -> bool {
+            capability == DeviceCapability::Pointer
+        }
+
+        fn usb_id(&self) -> Option<(u32, u32)> {
+            None
+        }
+
+        fn syspath(&self) -> Option<PathBuf> {
+            None
+        }
+
+        fn output_name(&self) -> Option<String> {
+            Some("eDP-1".into())
+        }
+    }
+
+    #[derive(Debug)]
+    struct TestBackend;
+
+    impl InputBackend for TestBackend {
+        type Device = TestDevice;
+        type KeyboardKeyEvent = UnusedEvent;
+        type PointerAxisEvent = HiResWheelEvent;
+        type PointerButtonEvent = UnusedEvent;
+        type PointerMotionEvent = UnusedEvent;
+        type PointerMotionAbsoluteEvent = UnusedEvent;
+        type GestureSwipeBeginEvent = UnusedEvent;
+        type GestureSwipeUpdateEvent = UnusedEvent;
+        type GestureSwipeEndEvent = UnusedEvent;
+        type GesturePinchBeginEvent = UnusedEvent;
+        type GesturePinchUpdateEvent = UnusedEvent;
+        type GesturePinchEndEvent = UnusedEvent;
+        type GestureHoldBeginEvent = UnusedEvent;
+        type GestureHoldEndEvent = UnusedEvent;
+        type TouchDownEvent = FingerDownEvent;
+        type TouchUpEvent = UnusedEvent;
+        type TouchMotionEvent = UnusedEvent;
+        type TouchCancelEvent = UnusedEvent;
+        type TouchFrameEvent = UnusedEvent;
+        type TabletToolAxisEvent = UnusedEvent;
+        type TabletToolProximityEvent = EraserProximityEvent;
+        type TabletToolTipEvent = UnusedEvent;
+        type TabletToolButtonEvent = UnusedEvent;
+        type SpecialEvent = ();
+    }
+
+    /// A high-resolution wheel scrolling half a detent vertically
+    struct HiResWheelEvent;
+
+    impl Event<TestBackend> for HiResWheelEvent {
+        fn time(&self) -> u64 {
+            0
+        }
+
+        fn device(&self) -> TestDevice {
+            TestDevice
+        }
+    }
+
+    impl PointerAxisEvent<TestBackend> for HiResWheelEvent {
+        fn amount(&self, axis: Axis) -> Option<f64> {
+            match axis {
+                Axis::Vertical => Some(7.5),
+                Axis::Horizontal => None,
+            }
+        }
+
+        fn amount_discrete(&self, axis: Axis) -> Option<f64> {
+            match axis {
+                Axis::Vertical => Some(0.0),
+                Axis::Horizontal => None,
+            }
+        }
+
+        fn amount_v120(&self, axis: Axis) -> Option<f64> {
+            match axis {
+                Axis::Vertical => Some(60.0),
+                Axis::Horizontal => None,
+            }
+        }
+
+        fn source(&self) -> AxisSource {
+            AxisSource::Wheel
+        }
+    }
+
+    /// A scroll reporting only either pixels or discrete steps on both axes
+    struct ScrollEvent {
+        px: Option<(f64, f64)>,
+        discrete: Option<(f64, f64)>,
+    }
+
+    impl Event<TestBackend> for ScrollEvent {
+        fn time(&self) -> u64 {
+            0
+        }
+
+        fn device(&self) -> TestDevice {
+            TestDevice
+        }
+    }
+
+    impl PointerAxisEvent<TestBackend> for ScrollEvent {
+        fn amount(&self, axis: Axis) -> Option<f64> {
+            self.px.map(|(x, y)| if axis == Axis::Horizontal { x } else { y })
+        }
+
+        fn amount_discrete(&self, axis: Axis) -> Option<f64> {
+            self.discrete
+                .map(|(x, y)| if axis == Axis::Horizontal { x } else { y })
+        }
+
+        fn source(&self) -> AxisSource {
+            if self.px.is_some() {
+                AxisSource::Finger
+            } else {
+                AxisSource::Wheel
+            }
+        }
+    }
+
+    /// A finger touching down on slot 0, possibly classified as a palm
+    struct FingerDownEvent {
+        palm: bool,
+    }
+
+    impl Event<TestBackend> for FingerDownEvent {
+        fn time(&self) -> u64 {
+            0
+        }
+
+        fn device(&self) -> TestDevice {
+            TestDevice
+        }
+    }
+
+    impl TouchEvent<TestBackend> for FingerDownEvent {
+        fn slot(&self) -> TouchSlot {
+            Some(0).into()
+        }
+    }
+
+    impl AbsolutePositionEvent<TestBackend> for FingerDownEvent {
+        fn x(&self) -> f64 {
+            0.0
+        }
+        fn y(&self) -> f64 {
+            0.0
+        }
+        fn x_transformed(&self, _width: i32) -> f64 {
+            0.0
+        }
+        fn y_transformed(&self, _height: i32) -> f64 {
+            0.0
+        }
+    }
+
+    impl TouchDownEvent<TestBackend> for FingerDownEvent {
+        fn is_palm(&self) -> bool {
+            self.palm
+        }
+    }
+
+    /// An eraser entering proximity at the top left corner of the tablet
+    struct EraserProximityEvent {
+        hardware_serial: u64,
+    }
+
+    impl Event<TestBackend> for EraserProximityEvent {
+        fn time(&self) -> u64 {
+            0
+        }
+
+        fn device(&self) -> TestDevice {
+            TestDevice
+        }
+    }
+
+    impl TabletToolEvent<TestBackend> for EraserProximityEvent {
+        fn tool(&self) -> TabletToolDescriptor {
+            TabletToolDescriptor {
+                tool_type: TabletToolType::Eraser,
+                hardware_serial: self.hardware_serial,
+                hardware_id_wacom: 0,
+                capabilities: TabletToolCapabilities::PRESSURE,
+            }
+        }
+        fn delta_x(&self) -> f64 {
+            0.0
+        }
+        fn delta_y(&self) -> f64 {
+            0.0
+        }
+        fn x(&self) -> f64 {
+            0.0
+        }
+        fn y(&self) -> f64 {
+            0.0
+        }
+        fn x_transformed(&self, _width: i32) -> f64 {
+            0.0
+        }
+        fn y_transformed(&self, _height: i32) -> f64 {
+            0.0
+        }
+        fn distance(&self) -> f64 {
+            0.0
+        }
+        fn distance_has_changed(&self) -> bool {
+            false
+        }
+        fn pressure(&self) -> f64 {
+            0.0
+        }
+        fn pressure_has_changed(&self) -> bool {
+            false
+        }
+        fn slider_position(&self) -> f64 {
+            0.0
+        }
+        fn slider_has_changed(&self) -> bool {
+            false
+        }
+        fn tilt_x(&self) -> f64 {
+            0.0
+        }
+        fn tilt_x_has_changed(&self) -> bool {
+            false
+        }
+        fn tilt_y(&self) -> f64 {
+            0.0
+        }
+        fn tilt_y_has_changed(&self) -> bool {
+            false
+        }
+        fn rotation(&self) -> f64 {
+            0.0
+        }
+        fn rotation_has_changed(&self) -> bool {
+            false
+        }
+        fn wheel_delta(&self) -> f64 {
+            0.0
+        }
+        fn wheel_delta_discrete(&self) -> i32 {
+            0
+        }
+        fn wheel_has_changed(&self) -> bool {
+            false
+        }
+    }
+
+    impl TabletToolProximityEvent<TestBackend> for EraserProximityEvent {
+        fn state(&self) -> ProximityState {
+            ProximityState::In
+        }
+    }
+
+    #[test]
+    fn proximity_in_reports_tool_type() {
+        let event = EraserProximityEvent { hardware_serial: 1 };
+        assert_eq!(event.state(), ProximityState::In);
+        assert_eq!(event.tool_type(), TabletToolType::Eraser);
+    }
+
+    #[test]
+    fn different_serial_changes_tool() {
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let mut tracker = ToolChangeTracker::new();
+        let recorded = changes.clone();
+        tracker.on_tool_change(move |previous, new| {
+            recorded
+                .borrow_mut()
+                .push((previous.hardware_serial, new.hardware_serial))
+        });
+
+        // the first tool and the same tool coming back are no changes
+        assert!(!tracker.proximity_event::<TestBackend, _>(&EraserProximityEvent { hardware_serial: 1 }));
+        assert!(!tracker.proximity_event::<TestBackend, _>(&EraserProximityEvent { hardware_serial: 1 }));
+        assert!(tracker.proximity_event::<TestBackend, _>(&EraserProximityEvent { hardware_serial: 2 }));
+        assert_eq!(*changes.borrow(), vec![(1, 2)]);
+        assert_eq!(tracker.current_tool("test").unwrap().hardware_serial, 2);
+    }
+
+    #[test]
+    fn hi_res_wheel_full_axis() {
+        let event = HiResWheelEvent;
+        assert_eq!(
+            event.full_axis(Axis::Vertical),
+            AxisData {
+                px: Some(7.5),
+                discrete: Some(0.0),
+                v120: Some(60.0),
+            }
+        );
+        assert_eq!(event.full_axis(Axis::Horizontal), AxisData::default());
+    }
+
+    #[test]
+    fn scroll_direction_follows_sign() {
+        let finger = |x, y| ScrollEvent {
+            px: Some((x, y)),
+            discrete: None,
+        };
+        assert_eq!(
+            finger(0.0, -3.0).direction(Axis::Vertical),
+            Some(ScrollDirection::Up)
+        );
+        assert_eq!(
+            finger(0.0, 3.0).direction(Axis::Vertical),
+            Some(ScrollDirection::Down)
+        );
+        assert_eq!(
+            finger(-3.0, 0.0).direction(Axis::Horizontal),
+            Some(ScrollDirection::Left)
+        );
+        assert_eq!(
+            finger(3.0, 0.0).direction(Axis::Horizontal),
+            Some(ScrollDirection::Right)
+        );
+        assert_eq!(finger(3.0, 0.0).direction(Axis::Vertical), None);
+
+        let wheel = ScrollEvent {
+            px: None,
+            discrete: Some((1.0, -1.0)),
+        };
+        assert_eq!(wheel.direction(Axis::Vertical), Some(ScrollDirection::Up));
+        assert_eq!(wheel.direction(Axis::Horizontal), Some(ScrollDirection::Right));
+
+        // partial detents have no discrete steps, but still a direction
+        assert_eq!(
+            HiResWheelEvent.direction(Axis::Vertical),
+            Some(ScrollDirection::Down)
+        );
+        assert_eq!(HiResWheelEvent.direction(Axis::Horizontal), None);
+    }
+
+    #[test]
+    fn event_kind_matches_variant() {
+        // the remaining variants are uninhabited for the test backend
+        let events: [(InputEvent<TestBackend>, InputEventKind); 5] = [
+            (
+                InputEvent::DeviceAdded { device: TestDevice },
+                InputEventKind::DeviceAdded,
+            ),
+            (
+                InputEvent::DeviceRemoved { device: TestDevice },
+                InputEventKind::DeviceRemoved,
+            ),
+            (
+                InputEvent::PointerAxis {
+                    event: HiResWheelEvent,
+                },
+                InputEventKind::PointerAxis,
+            ),
+            (
+                InputEvent::TouchDown {
+                    event: FingerDownEvent { palm: false },
+                },
+                InputEventKind::TouchDown,
+            ),
+            (InputEvent::Special(()), InputEventKind::Special),
+        ];
+
+        let mut counts = std::collections::HashMap::new();
+        for (event, kind) in &events {
+            assert_eq!(event.kind(), *kind);
+            *counts.entry(event.kind()).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), events.len());
+    }
+
+    /// An absolute event in the center of a device with the given size, transformed like the x11 backend
+    struct CenteredAbsoluteEvent(Size<f64, Raw>);
+
+    impl Event<TestBackend> for CenteredAbsoluteEvent {
+        fn time(&self) -> u64 {
+            0
+        }
+
+        fn device(&self) -> TestDevice {
+            TestDevice
+        }
+    }
+
+    impl AbsolutePositionEvent<TestBackend> for CenteredAbsoluteEvent {
+        fn x(&self) -> f64 {
+            self.0.w / 2.0
+        }
+
+        fn y(&self) -> f64 {
+            self.0.h / 2.0
+        }
+
+        fn x_transformed(&self, width: i32) -> f64 {
+            self.x() * width as f64 / self.0.w
+        }
+
+        fn y_transformed(&self, height: i32) -> f64 {
+            self.y() * height as f64 / self.0.h
+        }
+    }
+
+    #[test]
+    fn position_transformed_into_zero_size() {
+        let event = CenteredAbsoluteEvent((200.0, 100.0).into());
+        assert_eq!(
+            event.position_transformed((1920, 1080).into()),
+            (960.0, 540.0).into()
+        );
+        assert_eq!(event.position_transformed((0, 0).into()), (0.0, 0.0).into());
+        assert_eq!(event.position_transformed((1920, 0).into()), (960.0, 0.0).into());
+    }
+
+    #[test]
+    fn position_transformed_from_zero_size() {
+        let event = CenteredAbsoluteEvent((0.0, 0.0).into());
+        let position = event.position_transformed((1920, 1080).into());
+        assert!(!position.x.is_nan() && !position.y.is_nan());
+        assert_eq!(position, (0.0, 0.0).into());
+    }
+
+    #[test]
+    fn mouse_button_code_round_trip() {
+        for button in [
+            MouseButton::Left,
+            MouseButton::Middle,
+            MouseButton::Right,
+            MouseButton::Forward,
+            MouseButton::Back,
+            MouseButton::Other(0x113),
+            MouseButton::Other(0x2ff),
+        ] {
+            assert_eq!(MouseButton::from_code(button.button_code()), button);
+        }
+
+        // BTN_LEFT + 256 used to be unrepresentable
+        for code in [0, 0x110 + 256, u16::MAX as u32] {
+            assert_eq!(MouseButton::from_code(code).button_code(), code);
+        }
+        assert_eq!(MouseButton::from_code(0x110), MouseButton::Left);
+        assert_eq!(MouseButton::from_code(u32::MAX), MouseButton::Other(u16::MAX));
+    }
+
+    #[test]
+    fn palm_touches_can_be_filtered() {
+        // a compositor dropping palm touches in the callback of its input source
+        let mut event_loop = calloop::EventLoop::<Vec<InputEventKind>>::try_new().unwrap();
+        let (source, sender) = InputBackendSource::<TestBackend>::new();
+        event_loop
+            .handle()
+            .insert_source(source, |event, _, delivered| {
+                if let InputEvent::TouchDown { event } = &event {
+                    if event.is_palm() {
+                        return;
+                    }
+                }
+                delivered.push(event.kind());
+            })
+            .unwrap();
+
+        sender
+            .send(InputEvent::DeviceAdded { device: TestDevice })
+            .unwrap();
+        sender
+            .send(InputEvent::TouchDown {
+                event: FingerDownEvent { palm: true },
+            })
+            .unwrap();
+        sender
+            .send(InputEvent::TouchDown {
+                event: FingerDownEvent { palm: false },
+            })
+            .unwrap();
+
+        let mut delivered = Vec::new();
+        event_loop
+            .dispatch(Some(std::time::Duration::ZERO), &mut delivered)
+            .unwrap();
+        assert_eq!(
+            delivered,
+            vec![InputEventKind::DeviceAdded, InputEventKind::TouchDown]
+        );
+    }
+
+    #[test]
+    fn touch_events_report_mapped_output() {
+        let event = FingerDownEvent { palm: false };
+        assert_eq!(event.output_name().as_deref(), Some("eDP-1"));
+        assert_eq!(event.output_name(), event.device().output_name());
+    }
+
+    #[test]
+    fn source_emits_sent_events() {
+        let mut event_loop = calloop::EventLoop::<Vec<InputEventKind>>::try_new().unwrap();
+        let (source, sender) = InputBackendSource::<TestBackend>::new();
+        event_loop
+            .handle()
+            .insert_source(source, |event, _, kinds| kinds.push(event.kind()))
+            .unwrap();
+
+        sender
+            .send(InputEvent::DeviceAdded { device: TestDevice })
+            .unwrap();
+        sender
+            .send(InputEvent::TouchDown {
+                event: FingerDownEvent { palm: false },
+            })
+            .unwrap();
+
+        let mut kinds = Vec::new();
+        event_loop
+            .dispatch(Some(std::time::Duration::ZERO), &mut kinds)
+            .unwrap();
+        assert_eq!(
+            kinds,
+            vec![InputEventKind::DeviceAdded, InputEventKind::TouchDown]
+        );
+    }
+
+    #[test]
+    fn tap_drag_lock_defaults_to_unsupported() {
+        let device = TestDevice;
+        device.set_tap_drag_lock(true);
+        assert!(!device.tap_drag_lock_enabled());
     }
 }
@@ -0,0 +1,607 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt, io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use calloop::{
+    ping::{make_ping, Ping, PingSource},
+    EventSource, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+use tracing::{debug, warn};
+
+use super::{InputBackend, InputEvent, InputEventKind, TouchEvent, TouchSlot};
+
+/// Default maximum number of events held back by an [`InputQueue`]
+pub const DEFAULT_INPUT_QUEUE_CAPACITY: usize = 256;
+
+/// Default maximum age of events held back by an [`InputQueue`]
+pub const DEFAULT_INPUT_QUEUE_MAX_AGE: Duration = Duration::from_secs(5);
+
+/// What an [`InputQueue`] does with events of a kind while not ready
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueAction {
+    /// Hold the event back and replay it
+    Keep,
+    /// Hold the event back, replacing a directly preceding event of the same kind
+    ///
+    /// Useful for absolute motion events, where only the latest position matters.
+    /// Relative pointer motion carries deltas, which are lost when coalesced.
+    /// Touch events do not support coalescing and are kept instead.
+    Coalesce,
+    /// Discard the event
+    Drop,
+}
+
+/// Per kind [`QueueAction`]s of an [`InputQueue`]
+///
+/// By default key, button, axis, relative pointer motion, touch and tablet events are kept,
+/// absolute pointer motion and tablet axis events are coalesced and gestures are dropped.
+///
+/// Device events are always kept and never evicted, so the compositor does not miss devices.
+/// Dropping a touch event drops the whole touch sequence it belongs to.
+#[derive(Debug, Clone)]
+pub struct InputQueuePolicy {
+    actions: HashMap<InputEventKind, QueueAction>,
+}
+
+impl Default for InputQueuePolicy {
+    fn default() -> Self {
+        let actions = [
+            (InputEventKind::PointerMotionAbsolute, QueueAction::Coalesce),
+            (InputEventKind::TabletToolAxis, QueueAction::Coalesce),
+            (InputEventKind::GestureSwipeBegin, QueueAction::Drop),
+            (InputEventKind::GestureSwipeUpdate, QueueAction::Drop),
+            (InputEventKind::GestureSwipeEnd, QueueAction::Drop),
+            (InputEventKind::GesturePinchBegin, QueueAction::Drop),
+            (InputEventKind::GesturePinchUpdate, QueueAction::Drop),
+            (InputEventKind::GesturePinchEnd, QueueAction::Drop),
+            (InputEventKind::GestureHoldBegin, QueueAction::Drop),
+            (InputEventKind::GestureHoldEnd, QueueAction::Drop),
+        ];
+        InputQueuePolicy {
+            actions: actions.into_iter().collect(),
+        }
+    }
+}
+
+impl InputQueuePolicy {
+    /// Action for events of the given kind
+    pub fn action(&self, kind: InputEventKind) -> QueueAction {
+        match kind {
+            InputEventKind::DeviceAdded | InputEventKind::DeviceRemoved => QueueAction::Keep,
+            kind => self.actions.get(&kind).copied().unwrap_or(QueueAction::Keep),
+        }
+    }
+
+    /// Set the action for events of the given kind
+    ///
+    /// Setting an action for device events has no effect.
+    pub fn set_action(&mut self, kind: InputEventKind, action: QueueAction) {
+        self.actions.insert(kind, action);
+    }
+}
+
+/// Metadata of the events emitted by an [`InputQueue`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QueuedEventMetadata {
+    /// Whether the event was held back and is replayed
+    ///
+    /// The timestamps of replayed events are the original ones and thereby lie in the past.
+    pub replayed: bool,
+    /// How long the event was held back
+    pub delay: Duration,
+}
+
+/// Handle controlling whether an [`InputQueue`] holds back events
+#[derive(Debug, Clone)]
+pub struct InputQueueHandle {
+    ready: Arc<AtomicBool>,
+    ping: Ping,
+}
+
+impl InputQueueHandle {
+    /// Whether the compositor is ready to process events
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    /// Set whether the compositor is ready to process events
+    ///
+    /// Once ready, the held back events are replayed the next time the queue is dispatched,
+    /// which is triggered right away.
+    pub fn set_ready(&self, ready: bool) {
+        if !self.ready.swap(ready, Ordering::AcqRel) && ready {
+            self.ping.ping();
+        }
+    }
+}
+
+struct QueuedEvent<B: InputBackend> {
+    event: InputEvent<B>,
+    time: Instant,
+    /// Touch sequence the event belongs to
+    sequence: Option<u64>,
+}
+
+struct EventQueue<B: InputBackend> {
+    events: VecDeque<QueuedEvent<B>>,
+    policy: InputQueuePolicy,
+    capacity: usize,
+    max_age: Duration,
+    touch_points: HashSet<TouchSlot>,
+    sequence: Option<u64>,
+    next_sequence: u64,
+    /// Touch sequence whose events are discarded
+    discarded_sequence: Option<u64>,
+}
+
+impl<B: InputBackend> EventQueue<B> {
+    /// Assign touch events to a sequence, from the first touch point going down until the
+    /// frame after the last one went up
+    fn touch_sequence(&mut self, event: &InputEvent<B>) -> Option<u64> {
+        match event {
+            InputEvent::TouchDown { event } => {
+                self.touch_points.insert(event.slot());
+            }
+            InputEvent::TouchUp { event } => {
+                self.touch_points.remove(&event.slot());
+            }
+            InputEvent::TouchCancel { .. } => self.touch_points.clear(),
+            InputEvent::TouchMotion { .. } => {}
+            InputEvent::TouchFrame { .. } => {
+                let sequence = self.sequence;
+                if self.touch_points.is_empty() {
+                    self.sequence = None;
+                }
+                return sequence;
+            }
+            _ => return None,
+        }
+        Some(*self.sequence.get_or_insert_with(|| {
+            self.next_sequence += 1;
+            self.next_sequence
+        }))
+    }
+
+    fn push(&mut self, event: InputEvent<B>) {
+        let now = Instant::now();
+        self.expire(now);
+
+        let kind = event.kind();
+        let sequence = self.touch_sequence(&event);
+        if sequence.is_some() && sequence == self.discarded_sequence {
+            return;
+        }
+        match self.policy.action(kind) {
+            QueueAction::Drop => {
+                if let Some(sequence) = sequence {
+                    self.discard_sequence(sequence);
+                }
+                return;
+            }
+            QueueAction::Coalesce if sequence.is_none() => {
+                if self.events.back().map_or(false, |last| last.event.kind() == kind) {
+                    self.events.pop_back();
+                }
+            }
+            _ => {}
+        }
+        self.events.push_back(QueuedEvent {
+            event,
+            time: now,
+            sequence,
+        });
+
+        while self.events.len() > self.capacity {
+            let Some(index) = self.events.iter().position(|queued| !is_device_event(&queued.event)) else {
+                break;
+            };
+            debug!("Input queue full, evicting events");
+            self.evict(index);
+        }
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while let Some(index) = self.events.iter().position(|queued| {
+            !is_device_event(&queued.event) && now.duration_since(queued.time) > self.max_age
+        }) {
+            self.evict(index);
+        }
+    }
+
+    /// Remove an event, touch events are removed together with their whole sequence
+    fn evict(&mut self, index: usize) {
+        match self.events[index].sequence {
+            Some(sequence) => self.discard_sequence(sequence),
+            None => {
+                self.events.remove(index);
+            }
+        }
+    }
+
+    fn discard_sequence(&mut self, sequence: u64) {
+        self.events.retain(|queued| queued.sequence != Some(sequence));
+        if self.sequence == Some(sequence) {
+            // drop the remainder of the sequence as well
+            self.discarded_sequence = Some(sequence);
+        }
+    }
+
+    fn replay<F>(&mut self, callback: &mut F)
+    where
+        F: FnMut(InputEvent<B>, &mut QueuedEventMetadata),
+    {
+        let now = Instant::now();
+        self.expire(now);
+        if !self.events.is_empty() {
+            debug!(count = self.events.len(), "Replaying held back input events");
+        }
+        for queued in self.events.drain(..) {
+            let mut meta = QueuedEventMetadata {
+                replayed: true,
+                delay: now.duration_since(queued.time),
+            };
+            callback(queued.event, &mut meta);
+        }
+    }
+
+    /// Forward an event while ready, finishing a discarded touch sequence
+    fn pass<F>(&mut self, event: InputEvent<B>, callback: &mut F)
+    where
+        F: FnMut(InputEvent<B>, &mut QueuedEventMetadata),
+    {
+        if self.discarded_sequence.is_some() {
+            let sequence = self.touch_sequence(&event);
+            if sequence.is_some() && sequence == self.discarded_sequence {
+                return;
+            }
+            if sequence.is_some() {
+                self.discarded_sequence = None;
+            }
+        } else {
+            // keep tracking touch points, so sequences spanning a readiness change are recognized
+            self.touch_sequence(&event);
+        }
+        callback(event, &mut QueuedEventMetadata::default());
+    }
+}
+
+fn is_device_event<B: InputBackend>(event: &InputEvent<B>) -> bool {
+    matches!(
+        event,
+        InputEvent::DeviceAdded { .. } | InputEvent::DeviceRemoved { .. }
+    )
+}
+
+/// Wrapper around an input backend event source holding back events until the compositor is ready
+///
+/// Backends deliver events as soon as they are started, e.g. during startup before any output
+/// exists or when resuming after a VT switch, when absolute positions cannot be mapped anywhere.
+/// While the [`InputQueueHandle`] reports not ready, events are held back according to an
+/// [`InputQueuePolicy`], limited in number and age, and replayed in order once it is set ready.
+/// Replayed events are flagged in their [`QueuedEventMetadata`].
+///
+/// Touch events are kept or dropped per sequence, from the first touch point going down until
+/// the frame after the last one went up, so no partial sequences are replayed.
+///
+/// The queue starts out not ready.
+///
+/// ```no_run
+/// # #[cfg(feature = "backend_libinput")]
+/// # fn example(event_loop: &calloop::EventLoop<'static, ()>, backend: smithay::backend::libinput::LibinputInputBackend) {
+/// use smithay::backend::input::InputQueue;
+///
+/// let (queue, handle) = InputQueue::new(backend).unwrap();
+/// event_loop
+///     .handle()
+///     .insert_source(queue, |event, meta, _| {
+///         // process the input event
+///     })
+///     .unwrap();
+///
+/// // once the outputs are set up
+/// handle.set_ready(true);
+/// # }
+/// ```
+pub struct InputQueue<B: InputBackend, S> {
+    inner: S,
+    ready: Arc<AtomicBool>,
+    ping: PingSource,
+    queue: EventQueue<B>,
+}
+
+impl<B: InputBackend, S: fmt::Debug> fmt::Debug for InputQueue<B, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InputQueue")
+            .field("inner", &self.inner)
+            .field("ready", &self.ready)
+            .field("queued", &self.queue.events.len())
+            .field("policy", &self.queue.policy)
+            .field("capacity", &self.queue.capacity)
+            .field("max_age", &self.queue.max_age)
+            .finish()
+    }
+}
+
+impl<B, S> InputQueue<B, S>
+where
+    B: InputBackend,
+    S: EventSource<Event = InputEvent<B>, Metadata = (), Ret = ()>,
+{
+    /// Wrap an event source, together with the handle controlling it
+    pub fn new(inner: S) -> io::Result<(InputQueue<B, S>, InputQueueHandle)> {
+        let (ping, ping_source) = make_ping()?;
+        let ready = Arc::new(AtomicBool::new(false));
+        let queue = InputQueue {
+            inner,
+            ready: ready.clone(),
+            ping: ping_source,
+            queue: EventQueue {
+                events: VecDeque::new(),
+                policy: InputQueuePolicy::default(),
+                capacity: DEFAULT_INPUT_QUEUE_CAPACITY,
+                max_age: DEFAULT_INPUT_QUEUE_MAX_AGE,
+                touch_points: HashSet::new(),
+                sequence: None,
+                next_sequence: 0,
+                discarded_sequence: None,
+            },
+        };
+        Ok((queue, InputQueueHandle { ready, ping }))
+    }
+
+    /// Policy deciding which events are held back
+    pub fn policy(&self) -> &InputQueuePolicy {
+        &self.queue.policy
+    }
+
+    /// Mutably access the policy deciding which events are held back
+    pub fn policy_mut(&mut self) -> &mut InputQueuePolicy {
+        &mut self.queue.policy
+    }
+
+    /// Maximum number of events held back
+    pub fn capacity(&self) -> usize {
+        self.queue.capacity
+    }
+
+    /// Set the maximum number of events held back, the oldest events are evicted first
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.queue.capacity = capacity;
+    }
+
+    /// Maximum age of events held back
+    pub fn max_age(&self) -> Duration {
+        self.queue.max_age
+    }
+
+    /// Set the maximum age of events held back, older events are not replayed
+    pub fn set_max_age(&mut self, max_age: Duration) {
+        self.queue.max_age = max_age;
+    }
+
+    /// Number of events currently held back
+    pub fn queued(&self) -> usize {
+        self.queue.events.len()
+    }
+
+    /// Access the wrapped event source
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Mutably access the wrapped event source
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+impl<B, S> EventSource for InputQueue<B, S>
+where
+    B: InputBackend,
+    S: EventSource<Event = InputEvent<B>, Metadata = (), Ret = ()>,
+{
+    type Event = InputEvent<B>;
+    type Metadata = QueuedEventMetadata;
+    type Ret = ();
+    type Error = S::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        if let Err(err) = self.ping.process_events(readiness, token, |_, _| {}) {
+            warn!(?err, "Failed to process input queue wakeup");
+        }
+
+        let ready = &self.ready;
+        let queue = &mut self.queue;
+        if ready.load(Ordering::Acquire) {
+            queue.replay(&mut callback);
+        }
+        self.inner.process_events(readiness, token, |event, _| {
+            if ready.load(Ordering::Acquire) {
+                // the compositor may have become ready while processing an earlier event
+                queue.replay(&mut callback);
+                queue.pass(event, &mut callback);
+            } else {
+                queue.push(event);
+            }
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.ping.register(poll, token_factory)?;
+        self.inner.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.ping.reregister(poll, token_factory)?;
+        self.inner.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.ping.unregister(poll)?;
+        self.inner.unregister(poll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use calloop::EventLoop;
+
+    use super::{InputQueue, InputQueuePolicy, QueueAction};
+    use crate::backend::input::{
+        Device, DeviceCapability, InputBackend, InputBackendSource, InputEvent, InputEventKind, UnusedEvent,
+    };
+
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    struct TestDevice;
+
+    impl Device for TestDevice {
+        fn id(&self) -> String {
+            "test".into()
+        }
+
+        fn name(&self) -> String {
+            "test".into()
+        }
+
+        fn has_capability(&self, _capability: DeviceCapability) -> bool {
+            false
+        }
+
+        fn usb_id(&self) -> Option<(u32, u32)> {
+            None
+        }
+
+        fn syspath(&self) -> Option<std::path::PathBuf> {
+            None
+        }
+    }
+
+    #[derive(Debug)]
+    struct TestBackend;
+
+    impl InputBackend for TestBackend {
+        type Device = TestDevice;
+        type KeyboardKeyEvent = UnusedEvent;
+        type PointerAxisEvent = UnusedEvent;
+        type PointerButtonEvent = UnusedEvent;
+        type PointerMotionEvent = UnusedEvent;
+        type PointerMotionAbsoluteEvent = UnusedEvent;
+        type GestureSwipeBeginEvent = UnusedEvent;
+        type GestureSwipeUpdateEvent = UnusedEvent;
+        type GestureSwipeEndEvent = UnusedEvent;
+        type GesturePinchBeginEvent = UnusedEvent;
+        type GesturePinchUpdateEvent = UnusedEvent;
+        type GesturePinchEndEvent = UnusedEvent;
+        type GestureHoldBeginEvent = UnusedEvent;
+        type GestureHoldEndEvent = UnusedEvent;
+        type TouchDownEvent = UnusedEvent;
+        type TouchUpEvent = UnusedEvent;
+        type TouchMotionEvent = UnusedEvent;
+        type TouchCancelEvent = UnusedEvent;
+        type TouchFrameEvent = UnusedEvent;
+        type TabletToolAxisEvent = UnusedEvent;
+        type TabletToolProximityEvent = UnusedEvent;
+        type TabletToolTipEvent = UnusedEvent;
+        type TabletToolButtonEvent = UnusedEvent;
+        type SpecialEvent = u32;
+    }
+
+    type Received = Vec<(Option<u32>, bool)>;
+
+    fn dispatch(event_loop: &mut EventLoop<'static, Received>, received: &mut Received) {
+        event_loop.dispatch(Some(Duration::ZERO), received).unwrap();
+    }
+
+    #[test]
+    fn events_are_replayed_once_ready() {
+        let mut event_loop = EventLoop::<Received>::try_new().unwrap();
+        let (source, sender) = InputBackendSource::<TestBackend>::new();
+        let (mut queue, handle) = InputQueue::new(source).unwrap();
+        queue.set_capacity(3);
+        event_loop
+            .handle()
+            .insert_source(queue, |event, meta, received: &mut Received| match event {
+                InputEvent::Special(value) => received.push((Some(value), meta.replayed)),
+                InputEvent::DeviceAdded { .. } => received.push((None, meta.replayed)),
+                _ => unreachable!(),
+            })
+            .unwrap();
+
+        let mut received = Vec::new();
+        sender
+            .send(InputEvent::DeviceAdded { device: TestDevice })
+            .unwrap();
+        for value in 1..=4 {
+            sender.send(InputEvent::Special(value)).unwrap();
+        }
+        dispatch(&mut event_loop, &mut received);
+        assert!(received.is_empty());
+
+        // the oldest event was evicted, the device event was kept
+        handle.set_ready(true);
+        dispatch(&mut event_loop, &mut received);
+        sender.send(InputEvent::Special(5)).unwrap();
+        dispatch(&mut event_loop, &mut received);
+        assert_eq!(
+            received,
+            vec![(None, true), (Some(3), true), (Some(4), true), (Some(5), false)]
+        );
+    }
+
+    #[test]
+    fn policy_coalesces_and_drops() {
+        let mut event_loop = EventLoop::<Received>::try_new().unwrap();
+        let (source, sender) = InputBackendSource::<TestBackend>::new();
+        let (mut queue, handle) = InputQueue::new(source).unwrap();
+        queue
+            .policy_mut()
+            .set_action(InputEventKind::Special, QueueAction::Coalesce);
+        event_loop
+            .handle()
+            .insert_source(queue, |event, meta, received: &mut Received| match event {
+                InputEvent::Special(value) => received.push((Some(value), meta.replayed)),
+                _ => received.push((None, meta.replayed)),
+            })
+            .unwrap();
+
+        let mut received = Vec::new();
+        sender.send(InputEvent::Special(1)).unwrap();
+        sender.send(InputEvent::Special(2)).unwrap();
+        sender
+            .send(InputEvent::DeviceAdded { device: TestDevice })
+            .unwrap();
+        sender.send(InputEvent::Special(3)).unwrap();
+        dispatch(&mut event_loop, &mut received);
+
+        handle.set_ready(true);
+        dispatch(&mut event_loop, &mut received);
+        // only directly consecutive events are coalesced
+        assert_eq!(received, vec![(Some(2), true), (None, true), (Some(3), true)]);
+    }
+
+    #[test]
+    fn relative_motion_is_kept_by_default() {
+        let policy = InputQueuePolicy::default();
+        assert_eq!(policy.action(InputEventKind::PointerMotion), QueueAction::Keep);
+        assert_eq!(
+            policy.action(InputEventKind::PointerMotionAbsolute),
+            QueueAction::Coalesce
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use super::{ButtonState, InputBackend, MouseButton, PointerButtonEvent};
+
+/// Remapping table for pointer buttons
+///
+/// Maps incoming buttons to a different [`MouseButton`] or drops them entirely.
+/// Buttons without an entry in the table are passed through unchanged.
+///
+/// The target of a button is determined when it is pressed and reused for the matching
+/// release, so press and release always reach the same target, even if the table is
+/// changed while the button is held down.
+#[derive(Debug, Default)]
+pub struct ButtonRemap {
+    table: HashMap<u32, Option<u32>>,
+    pressed: HashMap<u32, Option<u32>>,
+}
+
+impl ButtonRemap {
+    /// Create an empty remapping table
+    pub fn new() -> ButtonRemap {
+        ButtonRemap::default()
+    }
+
+    /// Remap `from` to `to`
+    ///
+    /// Passing `None` as `to` drops all events of the button.
+    pub fn set(&mut self, from: MouseButton, to: Option<MouseButton>) {
+        self.table
+            .insert(from.button_code(), to.map(|button| button.button_code()));
+    }
+
+    /// Remove the mapping of a button, restoring its default behavior
+    pub fn unset(&mut self, button: MouseButton) {
+        self.table.remove(&button.button_code());
+    }
+
+    /// Remove all mappings
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+
+    /// Apply the table to a button event
+    ///
+    /// Returns the button code the event should be forwarded with,
+    /// or `None` if the event should be dropped.
+    pub fn remap_event<B: InputBackend>(&mut self, event: &B::PointerButtonEvent) -> Option<u32> {
+        self.remap(event.button_code(), event.state())
+    }
+
+    /// Apply the table to a raw button code
+    ///
+    /// Returns the button code the event should be forwarded with,
+    /// or `None` if the event should be dropped.
+    pub fn remap(&mut self, button_code: u32, state: ButtonState) -> Option<u32> {
+        match state {
+            ButtonState::Pressed => {
+                let target = self.table.get(&button_code).copied().unwrap_or(Some(button_code));
+                self.pressed.insert(button_code, target);
+                target
+            }
+            ButtonState::Released => self
+                .pressed
+                .remove(&button_code)
+                .unwrap_or_else(|| self.table.get(&button_code).copied().unwrap_or(Some(button_code))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ButtonRemap, ButtonState, MouseButton};
+
+    #[test]
+    fn remapped_press_and_release_reach_target() {
+        let mut remap = ButtonRemap::new();
+        remap.set(MouseButton::Middle, Some(MouseButton::Right));
+
+        let middle = MouseButton::Middle.button_code();
+        let right = MouseButton::Right.button_code();
+        assert_eq!(remap.remap(middle, ButtonState::Pressed), Some(right));
+        assert_eq!(remap.remap(middle, ButtonState::Released), Some(right));
+    }
+
+    #[test]
+    fn mapping_change_while_pressed_keeps_pairing() {
+        let mut remap = ButtonRemap::new();
+        remap.set(MouseButton::Back, Some(MouseButton::Left));
+
+        let back = MouseButton::Back.button_code();
+        assert_eq!(
+            remap.remap(back, ButtonState::Pressed),
+            Some(MouseButton::Left.button_code())
+        );
+        remap.set(MouseButton::Back, None);
+        assert_eq!(
+            remap.remap(back, ButtonState::Released),
+            Some(MouseButton::Left.button_code())
+        );
+
+        // the new mapping applies to the next press
+        assert_eq!(remap.remap(back, ButtonState::Pressed), None);
+        assert_eq!(remap.remap(back, ButtonState::Released), None);
+    }
+
+    #[test]
+    fn unmapped_buttons_pass_through() {
+        let mut remap = ButtonRemap::new();
+        remap.set(MouseButton::Middle, None);
+
+        let left = MouseButton::Left.button_code();
+        assert_eq!(remap.remap(left, ButtonState::Pressed), Some(left));
+        assert_eq!(remap.remap(left, ButtonState::Released), Some(left));
+    }
+}
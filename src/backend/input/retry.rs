@@ -0,0 +1,252 @@
+use std::fmt;
+
+use calloop::{EventSource, Poll, PostAction, Readiness, Token, TokenFactory};
+use tracing::debug;
+
+/// Wrapper around an input backend event source retrying transient dispatch errors
+///
+/// Some backends fail to dispatch events for recoverable reasons, e.g. an interrupted system call.
+/// Propagating such an error out of the event loop would usually terminate the compositor.
+/// This wrapper classifies errors of the wrapped source using a predicate and retries dispatching
+/// up to a fixed number of times if the error is deemed transient, before propagating it.
+///
+/// ```no_run
+/// # #[cfg(feature = "backend_libinput")]
+/// # fn example(backend: smithay::backend::libinput::LibinputInputBackend) {
+/// use smithay::backend::input::RetryingInputBackend;
+///
+/// let backend = RetryingInputBackend::new(backend, 3, |err: &std::io::Error| {
+///     err.kind() == std::io::ErrorKind::Interrupted
+/// });
+/// # }
+/// ```
+pub struct RetryingInputBackend<B: EventSource> {
+    inner: B,
+    max_retries: usize,
+    is_transient: Box<dyn Fn(&B::Error) -> bool>,
+}
+
+impl<B: EventSource + fmt::Debug> fmt::Debug for RetryingInputBackend<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryingInputBackend")
+            .field("inner", &self.inner)
+            .field("max_retries", &self.max_retries)
+            .field("is_transient", &"...")
+            .finish()
+    }
+}
+
+impl<B: EventSource> RetryingInputBackend<B> {
+    /// Wrap an event source, retrying up to `max_retries` times on errors matching `is_transient`
+    pub fn new<F>(inner: B, max_retries: usize, is_transient: F) -> RetryingInputBackend<B>
+    where
+        F: Fn(&B::Error) -> bool + 'static,
+    {
+        RetryingInputBackend {
+            inner,
+            max_retries,
+            is_transient: Box::new(is_transient),
+        }
+    }
+
+    /// Maximum number of retries per dispatch
+    pub fn max_retries(&self) -> usize {
+        self.max_retries
+    }
+
+    /// Set the maximum number of retries per dispatch
+    pub fn set_max_retries(&mut self, max_retries: usize) {
+        self.max_retries = max_retries;
+    }
+
+    /// Access the wrapped event source
+    pub fn get_ref(&self) -> &B {
+        &self.inner
+    }
+
+    /// Mutably access the wrapped event source
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    /// Unwrap the wrapped event source
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: EventSource> EventSource for RetryingInputBackend<B> {
+    type Event = B::Event;
+    type Metadata = B::Metadata;
+    type Ret = B::Ret;
+    type Error = B::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let mut retries = 0;
+        loop {
+            match self.inner.process_events(readiness, token, &mut callback) {
+                Err(err) if retries < self.max_retries && (self.is_transient)(&err) => {
+                    retries += 1;
+                    debug!(retries, "Retrying transient input dispatch error");
+                }
+                res => return res,
+            }
+        }
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.inner.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.inner.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.inner.unregister(poll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use calloop::{
+        ping::{make_ping, Ping, PingSource},
+        EventLoop, EventSource, Poll, PostAction, Readiness, Token, TokenFactory,
+    };
+
+    use super::RetryingInputBackend;
+
+    /// Source failing a given number of times, before emitting a single event per dispatch
+    struct FlakySource {
+        ping: PingSource,
+        failures: usize,
+        attempts: usize,
+    }
+
+    fn flaky(failures: usize) -> (Ping, FlakySource) {
+        let (ping, source) = make_ping().unwrap();
+        (
+            ping,
+            FlakySource {
+                ping: source,
+                failures,
+                attempts: 0,
+            },
+        )
+    }
+
+    impl EventSource for FlakySource {
+        type Event = u32;
+        type Metadata = ();
+        type Ret = ();
+        type Error = io::Error;
+
+        fn process_events<F>(
+            &mut self,
+            readiness: Readiness,
+            token: Token,
+            mut callback: F,
+        ) -> io::Result<PostAction>
+        where
+            F: FnMut(u32, &mut ()),
+        {
+            self.attempts += 1;
+            self.ping
+                .process_events(readiness, token, |_, _| {})
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            callback(self.attempts as u32, &mut ());
+            Ok(PostAction::Continue)
+        }
+
+        fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+            self.ping.register(poll, token_factory)
+        }
+
+        fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+            self.ping.reregister(poll, token_factory)
+        }
+
+        fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+            self.ping.unregister(poll)
+        }
+    }
+
+    fn is_interrupted(err: &io::Error) -> bool {
+        err.kind() == io::ErrorKind::Interrupted
+    }
+
+    #[test]
+    fn transient_error_is_retried() {
+        let mut event_loop = EventLoop::<Vec<u32>>::try_new().unwrap();
+        let (ping, source) = flaky(1);
+        event_loop
+            .handle()
+            .insert_source(
+                RetryingInputBackend::new(source, 3, is_interrupted),
+                |event, _, events: &mut Vec<u32>| events.push(event),
+            )
+            .unwrap();
+
+        let mut events = Vec::new();
+        ping.ping();
+        event_loop
+            .dispatch(Some(std::time::Duration::ZERO), &mut events)
+            .unwrap();
+        // failed once, the second attempt delivered the event
+        assert_eq!(events, vec![2]);
+    }
+
+    #[test]
+    fn fatal_error_is_propagated() {
+        let mut event_loop = EventLoop::<Vec<u32>>::try_new().unwrap();
+        let (ping, source) = flaky(1);
+        event_loop
+            .handle()
+            .insert_source(
+                RetryingInputBackend::new(source, 3, |_: &io::Error| false),
+                |event, _, events: &mut Vec<u32>| events.push(event),
+            )
+            .unwrap();
+
+        let mut events = Vec::new();
+        ping.ping();
+        assert!(event_loop
+            .dispatch(Some(std::time::Duration::ZERO), &mut events)
+            .is_err());
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn retries_are_limited() {
+        let mut event_loop = EventLoop::<Vec<u32>>::try_new().unwrap();
+        let (ping, source) = flaky(3);
+        event_loop
+            .handle()
+            .insert_source(
+                RetryingInputBackend::new(source, 2, is_interrupted),
+                |event, _, events: &mut Vec<u32>| events.push(event),
+            )
+            .unwrap();
+
+        let mut events = Vec::new();
+        ping.ping();
+        assert!(event_loop
+            .dispatch(Some(std::time::Duration::ZERO), &mut events)
+            .is_err());
+        assert!(events.is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+
+use calloop::{
+    channel::{channel, Channel, ChannelError, Event as ChannelEvent, Sender},
+    EventSource, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+
+use super::{InputBackend, InputEvent};
+
+/// Event source emitting the [`InputEvent`]s of an input backend
+///
+/// Backends like [`LibinputInputBackend`](crate::backend::libinput::LibinputInputBackend) are
+/// event sources themselves. Others, like the winit backend or input emulated by the compositor,
+/// produce their events when driven manually. This source lets them feed their events into the
+/// event loop through an [`InputEventSender`], so they are processed by the same callback and
+/// once the loop wakes up, just like events of any other backend.
+///
+/// ```no_run
+/// # #[cfg(feature = "backend_winit")]
+/// # fn example(event_loop: &calloop::EventLoop<'static, ()>, mut winit: smithay::backend::winit::WinitEventLoop) {
+/// use smithay::backend::{
+///     input::InputBackendSource,
+///     winit::{WinitEvent, WinitInput},
+/// };
+///
+/// let (source, sender) = InputBackendSource::<WinitInput>::new();
+/// event_loop
+///     .handle()
+///     .insert_source(source, |event, _, _| {
+///         // process the input event
+///     })
+///     .unwrap();
+///
+/// winit
+///     .dispatch_new_events(|event| {
+///         if let WinitEvent::Input(event) = event {
+///             let _ = sender.send(event);
+///         }
+///     })
+///     .unwrap();
+/// # }
+/// ```
+pub struct InputBackendSource<B: InputBackend> {
+    channel: Channel<InputEvent<B>>,
+}
+
+impl<B: InputBackend> fmt::Debug for InputBackendSource<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InputBackendSource").finish_non_exhaustive()
+    }
+}
+
+impl<B: InputBackend> InputBackendSource<B> {
+    /// Create a new source, together with the sender feeding it
+    pub fn new() -> (InputBackendSource<B>, InputEventSender<B>) {
+        let (sender, channel) = channel();
+        (InputBackendSource { channel }, InputEventSender { sender })
+    }
+}
+
+/// Sending half of an [`InputBackendSource`]
+///
+/// Once all senders are dropped, the source does not emit any events anymore.
+pub struct InputEventSender<B: InputBackend> {
+    sender: Sender<InputEvent<B>>,
+}
+
+impl<B: InputBackend> fmt::Debug for InputEventSender<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InputEventSender").finish_non_exhaustive()
+    }
+}
+
+impl<B: InputBackend> Clone for InputEventSender<B> {
+    fn clone(&self) -> Self {
+        InputEventSender {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<B: InputBackend> InputEventSender<B> {
+    /// Queue an event to be emitted by the source
+    ///
+    /// Returns the event back if the source was dropped.
+    pub fn send(&self, event: InputEvent<B>) -> Result<(), InputEvent<B>> {
+        self.sender.send(event).map_err(|err| err.0)
+    }
+}
+
+impl<B: InputBackend> EventSource for InputBackendSource<B> {
+    type Event = InputEvent<B>;
+    type Metadata = ();
+    type Ret = ();
+    type Error = ChannelError;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, ChannelError>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        self.channel
+            .process_events(readiness, token, |event, meta| match event {
+                ChannelEvent::Msg(event) => callback(event, meta),
+                ChannelEvent::Closed => {}
+            })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.channel.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.channel.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.channel.unregister(poll)
+    }
+}
@@ -1,6 +1,7 @@
-use super::{ButtonState, Event, InputBackend, UnusedEvent};
+use super::{ButtonState, Device, Event, InputBackend, UnusedEvent};
 use crate::utils::{Logical, Point, Raw, Size};
 use bitflags::bitflags;
+use std::{collections::HashMap, fmt};
 
 /// Description of physical tablet tool
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
@@ -16,6 +17,60 @@ pub struct TabletToolDescriptor {
     pub capabilities: TabletToolCapabilities,
 }
 
+impl TabletToolDescriptor {
+    /// Start building a descriptor, e.g. for tools of a virtual or remote tablet
+    ///
+    /// Unset properties default to an [`Unknown`](TabletToolType::Unknown) tool with
+    /// serial and id `0` and no extra capabilities.
+    pub fn builder() -> TabletToolDescriptorBuilder {
+        TabletToolDescriptorBuilder {
+            descriptor: TabletToolDescriptor {
+                tool_type: TabletToolType::Unknown,
+                hardware_serial: 0,
+                hardware_id_wacom: 0,
+                capabilities: TabletToolCapabilities::empty(),
+            },
+        }
+    }
+}
+
+/// Builder for [`TabletToolDescriptor`], see [`TabletToolDescriptor::builder`]
+#[derive(Debug, Clone)]
+pub struct TabletToolDescriptorBuilder {
+    descriptor: TabletToolDescriptor,
+}
+
+impl TabletToolDescriptorBuilder {
+    /// Sets the type of the tool
+    pub fn tool_type(mut self, tool_type: TabletToolType) -> Self {
+        self.descriptor.tool_type = tool_type;
+        self
+    }
+
+    /// Sets the unique hardware serial number of the tool
+    pub fn hardware_serial(mut self, hardware_serial: u64) -> Self {
+        self.descriptor.hardware_serial = hardware_serial;
+        self
+    }
+
+    /// Sets the hardware id of the tool in Wacom’s format
+    pub fn hardware_id_wacom(mut self, hardware_id_wacom: u64) -> Self {
+        self.descriptor.hardware_id_wacom = hardware_id_wacom;
+        self
+    }
+
+    /// Sets the capabilities of the tool
+    pub fn capabilities(mut self, capabilities: TabletToolCapabilities) -> Self {
+        self.descriptor.capabilities = capabilities;
+        self
+    }
+
+    /// Creates the descriptor
+    pub fn build(self) -> TabletToolDescriptor {
+        self.descriptor
+    }
+}
+
 /// Describes the physical type of tool. The physical type of tool generally defines its base usage.
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
 pub enum TabletToolType {
@@ -75,10 +130,13 @@ pub trait TabletToolEvent<B: InputBackend> {
     }
 
     /// Tool position converted into the target coordinate space.
+    ///
+    /// If the width or height of the coordinate space is zero or negative, the corresponding
+    /// coordinate is `0.0`. The result never contains `NaN` or infinity.
     fn position_transformed(&self, coordinate_space: Size<i32, Logical>) -> Point<f64, Logical> {
         (
-            self.x_transformed(coordinate_space.w),
-            self.y_transformed(coordinate_space.h),
+            super::transformed_or_zero(coordinate_space.w, |width| self.x_transformed(width)),
+            super::transformed_or_zero(coordinate_space.h, |height| self.y_transformed(height)),
         )
             .into()
     }
@@ -292,6 +350,14 @@ pub trait TabletToolProximityEvent<B: InputBackend>: TabletToolEvent<B> + Event<
     /// See [Handling of proximity events](https://wayland.freedesktop.org/libinput/doc/latest/tablet-support.html#tablet-fake-proximity)
     /// for recommendations on proximity handling.
     fn state(&self) -> ProximityState;
+
+    /// Returns the type of the tool entering or leaving proximity
+    ///
+    /// Allows picking a fitting cursor or behavior as soon as the tool enters proximity.
+    /// Defaults to the tool type of the [descriptor](TabletToolEvent::tool).
+    fn tool_type(&self) -> TabletToolType {
+        self.tool().tool_type
+    }
 }
 
 impl<B: InputBackend> TabletToolProximityEvent<B> for UnusedEvent {
@@ -368,3 +434,106 @@ impl<B: InputBackend> TabletToolButtonEvent<B> for UnusedEvent {
         match *self {}
     }
 }
+
+/// Tracker for the tool in proximity of each tablet
+///
+/// Switching pens on the same tablet is only visible through a different
+/// [hardware serial](TabletToolDescriptor::hardware_serial), or a different tool type for the
+/// eraser end of a pen. Feed the tracker all proximity events to know when a different tool
+/// than before enters the proximity of a tablet, so its settings and cursor can be applied.
+/// An optional callback is invoked on every tool change.
+#[derive(Default)]
+pub struct ToolChangeTracker {
+    tools: HashMap<String, TabletToolDescriptor>,
+    changed: Option<Box<dyn FnMut(&TabletToolDescriptor, &TabletToolDescriptor)>>,
+}
+
+impl fmt::Debug for ToolChangeTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ToolChangeTracker")
+            .field("tools", &self.tools)
+            .field("changed", &self.changed.as_ref().map(|_| "..."))
+            .finish()
+    }
+}
+
+impl ToolChangeTracker {
+    /// Create a new tracker without any known tools
+    pub fn new() -> ToolChangeTracker {
+        ToolChangeTracker::default()
+    }
+
+    /// Set a callback invoked with the previous and the new tool whenever the tool of a tablet changes
+    pub fn on_tool_change<F>(&mut self, callback: F)
+    where
+        F: FnMut(&TabletToolDescriptor, &TabletToolDescriptor) + 'static,
+    {
+        self.changed = Some(Box::new(callback));
+    }
+
+    /// The tool that was last in proximity of the tablet with the given [device id](Device::id)
+    pub fn current_tool(&self, device_id: &str) -> Option<&TabletToolDescriptor> {
+        self.tools.get(device_id)
+    }
+
+    /// Notify the tracker about a proximity event
+    ///
+    /// Returns `true` if a different tool than before entered proximity of the tablet.
+    /// The first tool seen on a tablet is not considered a change.
+    pub fn proximity_event<B, E>(&mut self, event: &E) -> bool
+    where
+        B: InputBackend,
+        E: TabletToolProximityEvent<B>,
+    {
+        if event.state() != ProximityState::In {
+            return false;
+        }
+        self.tool_in(&event.device().id(), event.tool())
+    }
+
+    /// Notify the tracker that a tool entered proximity of the tablet with the given device id
+    ///
+    /// Returns `true` if it differs from the tool that was in proximity before.
+    pub fn tool_in(&mut self, device_id: &str, tool: TabletToolDescriptor) -> bool {
+        match self.tools.get_mut(device_id) {
+            Some(previous) if *previous == tool => false,
+            Some(previous) => {
+                let previous = std::mem::replace(previous, tool);
+                if let Some(callback) = self.changed.as_mut() {
+                    callback(&previous, &self.tools[device_id]);
+                }
+                true
+            }
+            None => {
+                self.tools.insert(device_id.to_owned(), tool);
+                false
+            }
+        }
+    }
+
+    /// Forget the tool of a tablet, e.g. once it was removed
+    pub fn remove_device(&mut self, device_id: &str) {
+        self.tools.remove(device_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TabletToolCapabilities, TabletToolDescriptor, TabletToolType};
+
+    #[test]
+    fn pen_descriptor_from_builder() {
+        let pen = TabletToolDescriptor::builder()
+            .tool_type(TabletToolType::Pen)
+            .hardware_serial(0x1234)
+            .capabilities(TabletToolCapabilities::PRESSURE | TabletToolCapabilities::TILT)
+            .build();
+
+        assert_eq!(pen.tool_type, TabletToolType::Pen);
+        assert_eq!(pen.hardware_serial, 0x1234);
+        assert_eq!(pen.hardware_id_wacom, 0);
+        assert!(pen.capabilities.contains(TabletToolCapabilities::PRESSURE));
+        assert!(pen.capabilities.contains(TabletToolCapabilities::TILT));
+        assert!(!pen.capabilities.contains(TabletToolCapabilities::DISTANCE));
+    }
+}
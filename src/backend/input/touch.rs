@@ -0,0 +1,128 @@
+use std::{collections::HashSet, fmt};
+
+use super::TouchSlot;
+
+/// Tracker for the currently active touch points of a device or seat
+///
+/// Feed it the slots of touch down, up and cancel events to know how many fingers
+/// are currently touching the surface. An optional callback is invoked every time the
+/// last active touch point is lifted, which is useful to end gestures or kinetic scrolling.
+#[derive(Default)]
+pub struct TouchSlotTracker {
+    active: HashSet<TouchSlot>,
+    all_up: Option<Box<dyn FnMut()>>,
+}
+
+impl fmt::Debug for TouchSlotTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TouchSlotTracker")
+            .field("active", &self.active)
+            .field("all_up", &self.all_up.as_ref().map(|_| "..."))
+            .finish()
+    }
+}
+
+impl TouchSlotTracker {
+    /// Create a new tracker without any active touch points
+    pub fn new() -> TouchSlotTracker {
+        TouchSlotTracker::default()
+    }
+
+    /// Set a callback invoked whenever the last active touch point is lifted or cancelled
+    pub fn on_all_up<F: FnMut() + 'static>(&mut self, callback: F) {
+        self.all_up = Some(Box::new(callback));
+    }
+
+    /// Number of currently active touch points
+    pub fn active_slots(&self) -> usize {
+        self.active.len()
+    }
+
+    /// Returns whether the given slot is currently active
+    pub fn is_active(&self, slot: TouchSlot) -> bool {
+        self.active.contains(&slot)
+    }
+
+    /// Notify the tracker about a touch down event
+    pub fn down(&mut self, slot: TouchSlot) {
+        self.active.insert(slot);
+    }
+
+    /// Notify the tracker about a touch up event
+    ///
+    /// Returns `true` if this was the last active touch point.
+    pub fn up(&mut self, slot: TouchSlot) -> bool {
+        if !self.active.remove(&slot) || !self.active.is_empty() {
+            return false;
+        }
+        self.notify_all_up();
+        true
+    }
+
+    /// Notify the tracker about a touch cancel event
+    ///
+    /// Cancellation applies to all active touch points.
+    /// Returns `true` if any touch point was active.
+    pub fn cancel(&mut self) -> bool {
+        if self.active.is_empty() {
+            return false;
+        }
+        self.active.clear();
+        self.notify_all_up();
+        true
+    }
+
+    fn notify_all_up(&mut self) {
+        if let Some(callback) = self.all_up.as_mut() {
+            callback();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::{TouchSlot, TouchSlotTracker};
+
+    #[test]
+    fn all_up_after_last_finger() {
+        let fired = Rc::new(Cell::new(0));
+        let mut tracker = TouchSlotTracker::new();
+        let counter = fired.clone();
+        tracker.on_all_up(move || counter.set(counter.get() + 1));
+
+        let first = TouchSlot::from(Some(0));
+        let second = TouchSlot::from(Some(1));
+        tracker.down(first);
+        tracker.down(second);
+        assert_eq!(tracker.active_slots(), 2);
+
+        assert!(!tracker.up(first));
+        assert_eq!(tracker.active_slots(), 1);
+        assert_eq!(fired.get(), 0);
+
+        assert!(tracker.up(second));
+        assert_eq!(tracker.active_slots(), 0);
+        assert_eq!(fired.get(), 1);
+
+        // unknown slots do not trigger the callback again
+        assert!(!tracker.up(second));
+        assert_eq!(fired.get(), 1);
+    }
+
+    #[test]
+    fn cancel_releases_all_slots() {
+        let fired = Rc::new(Cell::new(false));
+        let mut tracker = TouchSlotTracker::new();
+        let flag = fired.clone();
+        tracker.on_all_up(move || flag.set(true));
+
+        tracker.down(TouchSlot::from(Some(0)));
+        tracker.down(TouchSlot::from(Some(3)));
+        assert!(tracker.cancel());
+        assert_eq!(tracker.active_slots(), 0);
+        assert!(fired.get());
+        assert!(!tracker.cancel());
+    }
+}
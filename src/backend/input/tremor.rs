@@ -0,0 +1,143 @@
+use super::{InputBackend, PointerMotionEvent};
+use crate::utils::{Logical, Point};
+
+/// Default strength of a [`TremorFilter`]
+pub const DEFAULT_TREMOR_FILTER_STRENGTH: f64 = 0.75;
+
+/// Largest accepted strength, a strength of `1.0` would freeze the filtered axis
+const MAX_STRENGTH: f64 = 0.95;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct AxisState {
+    /// Last unfiltered delta
+    last: f64,
+    /// Last filtered delta
+    smoothed: f64,
+}
+
+impl AxisState {
+    fn filter(&mut self, delta: f64, strength: f64) -> f64 {
+        // only direction reversals are smoothed, so steady movement is not delayed
+        let reversal = delta * self.last < 0.0;
+        let weight = if reversal { strength } else { 0.0 };
+        self.last = delta;
+        self.smoothed = self.smoothed * weight + delta * (1.0 - weight);
+        self.smoothed
+    }
+}
+
+/// Filter for relative pointer motion of users with hand tremor
+///
+/// Hand tremor shows up as rapid, tiny movements that keep reversing their direction.
+/// Unlike a dead-zone, which drops small movements regardless of their direction, this
+/// filter low-pass filters each axis whenever its direction reverses, while deltas keeping
+/// their direction are passed through unchanged. Deliberate movements therefore stay
+/// responsive, while oscillations are damped towards their average.
+///
+/// The strength ranges from `0.0`, which disables the filter, to `0.95`, with higher values
+/// damping oscillations more.
+#[derive(Debug)]
+pub struct TremorFilter {
+    strength: f64,
+    x: AxisState,
+    y: AxisState,
+}
+
+impl Default for TremorFilter {
+    fn default() -> Self {
+        TremorFilter::new(DEFAULT_TREMOR_FILTER_STRENGTH)
+    }
+}
+
+impl TremorFilter {
+    /// Create a new filter with the given strength
+    pub fn new(strength: f64) -> TremorFilter {
+        TremorFilter {
+            strength: clamp_strength(strength),
+            x: AxisState::default(),
+            y: AxisState::default(),
+        }
+    }
+
+    /// Strength of the filter
+    pub fn strength(&self) -> f64 {
+        self.strength
+    }
+
+    /// Set the strength of the filter
+    ///
+    /// Values outside of `0.0..=0.95` are clamped.
+    pub fn set_strength(&mut self, strength: f64) {
+        self.strength = clamp_strength(strength);
+    }
+
+    /// Forget about previous motion, e.g. after the pointer was warped
+    pub fn reset(&mut self) {
+        self.x = AxisState::default();
+        self.y = AxisState::default();
+    }
+
+    /// Filter the delta of a relative motion event
+    pub fn filter_event<B: InputBackend>(&mut self, event: &B::PointerMotionEvent) -> Point<f64, Logical> {
+        self.filter(event.delta())
+    }
+
+    /// Filter a raw relative motion delta
+    pub fn filter(&mut self, delta: Point<f64, Logical>) -> Point<f64, Logical> {
+        (
+            self.x.filter(delta.x, self.strength),
+            self.y.filter(delta.y, self.strength),
+        )
+            .into()
+    }
+}
+
+fn clamp_strength(strength: f64) -> f64 {
+    if strength.is_nan() {
+        0.0
+    } else {
+        strength.clamp(0.0, MAX_STRENGTH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TremorFilter;
+
+    #[test]
+    fn oscillation_is_smoothed() {
+        let mut filter = TremorFilter::new(0.8);
+        let output = (0..20)
+            .map(|i| {
+                let delta = if i % 2 == 0 { 4.0 } else { -4.0 };
+                filter.filter((delta, 0.0).into()).x
+            })
+            .collect::<Vec<_>>();
+
+        // once settled the oscillation is damped well below its input amplitude
+        let settled = &output[10..];
+        assert!(settled.iter().all(|delta| delta.abs() < 1.0));
+        let max = settled.iter().cloned().fold(f64::MIN, f64::max);
+        let min = settled.iter().cloned().fold(f64::MAX, f64::min);
+        assert!(max - min < 2.0);
+    }
+
+    #[test]
+    fn steady_motion_passes_through() {
+        let mut filter = TremorFilter::new(0.8);
+        for _ in 0..5 {
+            assert_eq!(filter.filter((3.0, -2.0).into()), (3.0, -2.0).into());
+        }
+    }
+
+    #[test]
+    fn zero_strength_disables_filter() {
+        let mut filter = TremorFilter::new(0.0);
+        assert_eq!(filter.filter((4.0, 0.0).into()), (4.0, 0.0).into());
+        assert_eq!(filter.filter((-4.0, 0.0).into()), (-4.0, 0.0).into());
+        assert_eq!(filter.strength(), 0.0);
+
+        filter.set_strength(2.0);
+        assert_eq!(filter.strength(), 0.95);
+    }
+}
@@ -4,13 +4,15 @@
 
 //! Implementation of input backend trait for types provided by `libinput`
 
-use crate::backend::input::{self as backend, Axis, InputBackend, InputEvent};
+use crate::backend::input::{self as backend, Axis, InputBackend, InputEvent, LedState};
 #[cfg(feature = "backend_session")]
 use crate::backend::session::{AsErrno, Session};
+use crate::input::pointer::{AccelProfile, PointerConfigTracker, SeatPointerConfig};
 use input as libinput;
 use input::event;
 
 use std::{
+    collections::HashMap,
     io,
     os::unix::io::{AsRawFd, RawFd},
     path::PathBuf,
@@ -23,7 +25,7 @@ use std::{
 
 use calloop::{EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory};
 
-use tracing::{debug_span, info, trace};
+use tracing::{debug, debug_span, info, trace};
 
 mod tablet;
 
@@ -36,6 +38,10 @@ pub struct LibinputInputBackend {
     context: libinput::Libinput,
     token: Option<Token>,
     span: tracing::Span,
+    pointer_config: Option<PointerConfigTracker>,
+    pointer_config_serial: u64,
+    devices: HashMap<String, libinput::Device>,
+    leds: HashMap<libinput::Device, LedState>,
 }
 
 impl LibinputInputBackend {
@@ -52,6 +58,10 @@ impl LibinputInputBackend {
             context,
             token: None,
             span,
+            pointer_config: None,
+            pointer_config_serial: 0,
+            devices: HashMap::new(),
+            leds: HashMap::new(),
         }
     }
 
@@ -59,6 +69,72 @@ impl LibinputInputBackend {
     pub fn context(&self) -> &libinput::Libinput {
         &self.context
     }
+
+    /// Update the keyboard LEDs of a device and remember their state
+    ///
+    /// libinput can not be queried for the LED state, use [`LibinputInputBackend::leds`]
+    /// to get the state last set through this method.
+    pub fn set_leds(&mut self, device: &libinput::Device, leds: LedState) {
+        backend::Device::set_leds(device, leds);
+        self.leds.insert(device.clone(), leds);
+    }
+
+    /// State of the keyboard LEDs last set on a device through [`LibinputInputBackend::set_leds`]
+    ///
+    /// Returns `None` if the LEDs of the device were never set or the device was removed.
+    pub fn leds(&self, device: &libinput::Device) -> Option<LedState> {
+        self.leds.get(device).copied()
+    }
+
+    /// Apply the pointer configuration of a seat to all pointer devices of this backend
+    ///
+    /// The configuration is applied to devices added later on as well, changes made
+    /// through [`Seat::set_pointer_config`](crate::input::Seat::set_pointer_config) are
+    /// picked up the next time the backend is dispatched.
+    pub fn track_pointer_config(&mut self, tracker: PointerConfigTracker) {
+        self.pointer_config = Some(tracker);
+        self.pointer_config_serial = 0;
+        self.update_pointer_config();
+    }
+
+    fn update_pointer_config(&mut self) {
+        let Some(tracker) = self.pointer_config.as_ref() else {
+            return;
+        };
+        if let Some(config) = tracker.changed_since(&mut self.pointer_config_serial) {
+            let pointer_devices = self
+                .devices
+                .values_mut()
+                .filter(|device| device.has_capability(libinput::DeviceCapability::Pointer));
+            for device in pointer_devices {
+                apply_pointer_config(device, &config);
+            }
+        }
+    }
+}
+
+fn apply_pointer_config(device: &mut libinput::Device, config: &SeatPointerConfig) {
+    let _span = debug_span!("pointer_config", device = device.sysname()).entered();
+
+    if device.config_accel_is_available() {
+        if let Err(err) = device.config_accel_set_speed(config.accel_speed) {
+            debug!(?err, "Failed to set acceleration speed");
+        }
+        let profile = match config.accel_profile {
+            AccelProfile::Flat => libinput::AccelProfile::Flat,
+            AccelProfile::Adaptive => libinput::AccelProfile::Adaptive,
+        };
+        if device.config_accel_profiles().contains(&profile) {
+            if let Err(err) = device.config_accel_set_profile(profile) {
+                debug!(?err, "Failed to set acceleration profile");
+            }
+        }
+    }
+    if device.config_scroll_has_natural_scroll() {
+        if let Err(err) = device.config_scroll_set_natural_scroll_enabled(config.natural_scroll) {
+            debug!(?err, "Failed to set natural scrolling");
+        }
+    }
 }
 
 impl backend::Device for libinput::Device {
@@ -88,6 +164,27 @@ impl backend::Device for libinput::Device {
         #[cfg(not(feature = "udev"))]
         None
     }
+
+    fn set_tap_drag_lock(&self, enabled: bool) {
+        // the libinput device is reference counted, configuring a clone configures the device
+        if let Err(err) = self.clone().config_tap_set_drag_lock_enabled(enabled) {
+            debug!(device = self.sysname(), ?err, "Failed to configure tap drag lock");
+        }
+    }
+
+    fn tap_drag_lock_enabled(&self) -> bool {
+        self.config_tap_drag_lock_enabled()
+    }
+
+    fn set_leds(&self, leds: LedState) {
+        // the libinput device is reference counted, updating a clone updates the device
+        self.clone()
+            .led_update(libinput::Led::from_bits_truncate(leds.bits()));
+    }
+
+    fn output_name(&self) -> Option<String> {
+        libinput::Device::output_name(self).map(Into::into)
+    }
 }
 
 impl From<backend::DeviceCapability> for libinput::DeviceCapability {
@@ -397,7 +494,21 @@ impl backend::Event<LibinputInputBackend> for event::touch::TouchDownEvent {
     }
 }
 
-impl backend::TouchDownEvent<LibinputInputBackend> for event::touch::TouchDownEvent {}
+// libinput does not expose the contact geometry of touch points. It handles palms itself:
+// touches classified as palm are cancelled or never reported, so no event is ever a palm.
+impl backend::TouchDownEvent<LibinputInputBackend> for event::touch::TouchDownEvent {
+    fn shape(&self) -> Option<backend::TouchShape> {
+        None
+    }
+
+    fn orientation(&self) -> Option<f64> {
+        None
+    }
+
+    fn is_palm(&self) -> bool {
+        false
+    }
+}
 
 impl backend::TouchEvent<LibinputInputBackend> for event::touch::TouchDownEvent {
     fn slot(&self) -> backend::TouchSlot {
@@ -433,7 +544,21 @@ impl backend::Event<LibinputInputBackend> for event::touch::TouchMotionEvent {
     }
 }
 
-impl backend::TouchMotionEvent<LibinputInputBackend> for event::touch::TouchMotionEvent {}
+// libinput does not expose the contact geometry of touch points. It handles palms itself:
+// touches classified as palm are cancelled or never reported, so no event is ever a palm.
+impl backend::TouchMotionEvent<LibinputInputBackend> for event::touch::TouchMotionEvent {
+    fn shape(&self) -> Option<backend::TouchShape> {
+        None
+    }
+
+    fn orientation(&self) -> Option<f64> {
+        None
+    }
+
+    fn is_palm(&self) -> bool {
+        false
+    }
+}
 
 impl backend::TouchEvent<LibinputInputBackend> for event::touch::TouchMotionEvent {
     fn slot(&self) -> backend::TouchSlot {
@@ -541,6 +666,10 @@ impl InputBackend for LibinputInputBackend {
     type TabletToolButtonEvent = event::tablet_tool::TabletToolButtonEvent;
 
     type SpecialEvent = backend::UnusedEvent;
+
+    fn device_by_id(&self, id: &str) -> Option<&libinput::Device> {
+        self.devices.get(id)
+    }
 }
 
 impl From<event::keyboard::KeyState> for backend::KeyState {
@@ -638,15 +767,23 @@ impl EventSource for LibinputInputBackend {
         if Some(token) == self.token {
             let _guard = self.span.enter();
             self.context.dispatch()?;
+            self.update_pointer_config();
 
             for event in &mut self.context {
                 match event {
                     libinput::Event::Device(device_event) => match device_event {
                         event::DeviceEvent::Added(device_added_event) => {
-                            let added = event::EventTrait::device(&device_added_event);
+                            let mut added = event::EventTrait::device(&device_added_event);
 
                             info!("New device {:?}", added.sysname(),);
 
+                            if added.has_capability(libinput::DeviceCapability::Pointer) {
+                                if let Some(config) = self.pointer_config.as_ref().and_then(|t| t.config()) {
+                                    apply_pointer_config(&mut added, &config);
+                                }
+                            }
+
+                            self.devices.insert(backend::Device::id(&added), added.clone());
                             callback(InputEvent::DeviceAdded { device: added }, &mut ());
                         }
                         event::DeviceEvent::Removed(device_removed_event) => {
@@ -654,6 +791,11 @@ impl EventSource for LibinputInputBackend {
 
                             info!("Removed device {:?}", removed.sysname(),);
 
+                            self.leds.remove(&removed);
+                            let id = backend::Device::id(&removed);
+                            if self.devices.get(&id) == Some(&removed) {
+                                self.devices.remove(&id);
+                            }
                             callback(InputEvent::DeviceRemoved { device: removed }, &mut ());
                         }
                         _ => {
@@ -781,3 +923,224 @@ impl EventSource for LibinputInputBackend {
         poll.unregister(self.as_raw_fd())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{File, OpenOptions},
+        os::unix::{
+            fs::OpenOptionsExt,
+            io::{AsRawFd, OwnedFd},
+        },
+        path::{Path, PathBuf},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::libinput;
+    use crate::backend::input::Device;
+
+    const EV_KEY: libc::c_ulong = 0x01;
+    const EV_ABS: libc::c_ulong = 0x03;
+    const INPUT_PROP_POINTER: libc::c_ulong = 0x00;
+    const INPUT_PROP_BUTTONPAD: libc::c_ulong = 0x02;
+    const BTN_LEFT: libc::c_ulong = 0x110;
+    const BTN_TOOL_FINGER: libc::c_ulong = 0x145;
+    const BTN_TOUCH: libc::c_ulong = 0x14a;
+    const BTN_TOOL_DOUBLETAP: libc::c_ulong = 0x14d;
+    const BTN_TOOL_TRIPLETAP: libc::c_ulong = 0x14e;
+    const ABS_X: u16 = 0x00;
+    const ABS_Y: u16 = 0x01;
+    const ABS_MT_SLOT: u16 = 0x2f;
+    const ABS_MT_POSITION_X: u16 = 0x35;
+    const ABS_MT_POSITION_Y: u16 = 0x36;
+    const ABS_MT_TRACKING_ID: u16 = 0x39;
+    const BUS_VIRTUAL: u16 = 0x06;
+
+    #[repr(C)]
+    struct InputId {
+        bustype: u16,
+        vendor: u16,
+        product: u16,
+        version: u16,
+    }
+
+    #[repr(C)]
+    struct UinputSetup {
+        id: InputId,
+        name: [u8; 80],
+        ff_effects_max: u32,
+    }
+
+    #[repr(C)]
+    struct InputAbsinfo {
+        value: i32,
+        minimum: i32,
+        maximum: i32,
+        fuzz: i32,
+        flat: i32,
+        resolution: i32,
+    }
+
+    #[repr(C)]
+    struct UinputAbsSetup {
+        code: u16,
+        absinfo: InputAbsinfo,
+    }
+
+    nix::ioctl_none!(ui_dev_create, b'U', 1);
+    nix::ioctl_none!(ui_dev_destroy, b'U', 2);
+    nix::ioctl_write_ptr!(ui_dev_setup, b'U', 3, UinputSetup);
+    nix::ioctl_write_ptr!(ui_abs_setup, b'U', 4, UinputAbsSetup);
+    nix::ioctl_read_buf!(ui_get_sysname, b'U', 44, u8);
+    nix::ioctl_write_int!(ui_set_evbit, b'U', 100);
+    nix::ioctl_write_int!(ui_set_keybit, b'U', 101);
+    nix::ioctl_write_int!(ui_set_absbit, b'U', 103);
+    nix::ioctl_write_int!(ui_set_propbit, b'U', 110);
+
+    /// Virtual clickpad created through uinput, destroyed when dropped
+    struct VirtualTouchpad {
+        uinput: File,
+        node: PathBuf,
+    }
+
+    impl VirtualTouchpad {
+        fn new() -> nix::Result<VirtualTouchpad> {
+            let uinput = OpenOptions::new()
+                .write(true)
+                .custom_flags(libc::O_NONBLOCK)
+                .open("/dev/uinput")
+                .map_err(|err| nix::Error::from_i32(err.raw_os_error().unwrap_or(libc::EIO)))?;
+            let fd = uinput.as_raw_fd();
+
+            let mut name = [0u8; 80];
+            name[..17].copy_from_slice(b"smithay-touchpad\0");
+            let setup = UinputSetup {
+                id: InputId {
+                    bustype: BUS_VIRTUAL,
+                    vendor: 0x1234,
+                    product: 0x5678,
+                    version: 1,
+                },
+                name,
+                ff_effects_max: 0,
+            };
+            // SAFETY: all ioctls operate on the uinput fd we own with correctly sized arguments
+            unsafe {
+                ui_set_propbit(fd, INPUT_PROP_POINTER)?;
+                ui_set_propbit(fd, INPUT_PROP_BUTTONPAD)?;
+                ui_set_evbit(fd, EV_KEY)?;
+                for key in [
+                    BTN_LEFT,
+                    BTN_TOOL_FINGER,
+                    BTN_TOUCH,
+                    BTN_TOOL_DOUBLETAP,
+                    BTN_TOOL_TRIPLETAP,
+                ] {
+                    ui_set_keybit(fd, key)?;
+                }
+                ui_set_evbit(fd, EV_ABS)?;
+                for (code, maximum) in [
+                    (ABS_X, 1000),
+                    (ABS_Y, 600),
+                    (ABS_MT_SLOT, 4),
+                    (ABS_MT_POSITION_X, 1000),
+                    (ABS_MT_POSITION_Y, 600),
+                    (ABS_MT_TRACKING_ID, 0xffff),
+                ] {
+                    ui_set_absbit(fd, code as libc::c_ulong)?;
+                    let resolution = if code == ABS_MT_SLOT || code == ABS_MT_TRACKING_ID {
+                        0
+                    } else {
+                        10
+                    };
+                    let abs = UinputAbsSetup {
+                        code,
+                        absinfo: InputAbsinfo {
+                            value: 0,
+                            minimum: 0,
+                            maximum,
+                            fuzz: 0,
+                            flat: 0,
+                            resolution,
+                        },
+                    };
+                    ui_abs_setup(fd, &abs)?;
+                }
+                ui_dev_setup(fd, &setup)?;
+                ui_dev_create(fd)?;
+            }
+
+            let mut sysname = [0u8; 64];
+            // SAFETY: the buffer length is passed along with it
+            unsafe { ui_get_sysname(fd, &mut sysname) }?;
+            let len = sysname.iter().position(|&b| b == 0).unwrap_or(sysname.len());
+            let sysname = std::str::from_utf8(&sysname[..len]).unwrap().to_owned();
+
+            // the event node appears asynchronously, once udev processed the new device
+            let sysfs = Path::new("/sys/devices/virtual/input").join(sysname);
+            let deadline = Instant::now() + Duration::from_secs(5);
+            let node = loop {
+                let node = std::fs::read_dir(&sysfs).ok().and_then(|entries| {
+                    entries
+                        .filter_map(Result::ok)
+                        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                        .find(|name| name.starts_with("event"))
+                        .map(|name| Path::new("/dev/input").join(name))
+                        .filter(|node| node.exists())
+                });
+                match node {
+                    Some(node) => break node,
+                    None if Instant::now() >= deadline => return Err(nix::Error::ETIMEDOUT),
+                    None => thread::sleep(Duration::from_millis(10)),
+                }
+            };
+
+            Ok(VirtualTouchpad { uinput, node })
+        }
+    }
+
+    impl Drop for VirtualTouchpad {
+        fn drop(&mut self) {
+            // SAFETY: the uinput fd is still owned by us
+            let _ = unsafe { ui_dev_destroy(self.uinput.as_raw_fd()) };
+        }
+    }
+
+    struct OpenInterface;
+
+    impl libinput::LibinputInterface for OpenInterface {
+        fn open_restricted(&mut self, path: &Path, flags: i32) -> Result<OwnedFd, i32> {
+            OpenOptions::new()
+                .read(true)
+                .write(flags & libc::O_ACCMODE != libc::O_RDONLY)
+                .custom_flags(flags & !libc::O_ACCMODE)
+                .open(path)
+                .map(OwnedFd::from)
+                .map_err(|err| err.raw_os_error().unwrap_or(libc::EIO))
+        }
+
+        fn close_restricted(&mut self, fd: OwnedFd) {
+            drop(File::from(fd));
+        }
+    }
+
+    #[test]
+    #[ignore = "creates a virtual touchpad, which needs write access to /dev/uinput"]
+    fn tap_drag_lock_round_trips_through_libinput() {
+        let touchpad = VirtualTouchpad::new().expect("Failed to create a virtual touchpad");
+        let mut context = libinput::Libinput::new_from_path(OpenInterface);
+        let device = context
+            .path_add_device(touchpad.node.to_str().unwrap())
+            .expect("libinput did not accept the virtual touchpad");
+        assert!(device.config_tap_finger_count() > 0);
+
+        device.set_tap_drag_lock(true);
+        assert!(device.tap_drag_lock_enabled());
+        assert!(device.config_tap_drag_lock_enabled());
+
+        device.set_tap_drag_lock(false);
+        assert!(!device.tap_drag_lock_enabled());
+        assert!(!device.config_tap_drag_lock_enabled());
+    }
+}
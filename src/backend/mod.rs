@@ -103,6 +103,43 @@ pub mod winit;
 #[cfg(feature = "backend_x11")]
 pub mod x11;
 
+use std::time::Duration;
+
+use crate::utils::{Monotonic, Time};
+
+bitflags::bitflags! {
+    /// How a frame was presented, see [`PresentEvent`]
+    ///
+    /// The flags match the `kind` of `wp_presentation_feedback` and convert into it.
+    pub struct PresentFlags: u32 {
+        /// The presentation was synchronized to the vertical retrace of the display
+        const VSYNC = 0x1;
+        /// The timestamp was provided by the display hardware
+        const HW_CLOCK = 0x2;
+        /// The display hardware signalled the completion of the presentation
+        const HW_COMPLETION = 0x4;
+        /// The buffer was scanned out directly, without being copied
+        const ZERO_COPY = 0x8;
+    }
+}
+
+/// Timing of a presented frame, as reported by a backend
+///
+/// Backends report these on a best-effort basis, the accuracy of the timestamp is described
+/// by the [`flags`](PresentEvent::flags). Compositors forward them to the presentation feedback
+/// of the frame, e.g. `OutputPresentationFeedback::presented` of the `desktop` module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresentEvent {
+    /// Time the frame was presented
+    pub time: Time<Monotonic>,
+    /// Refresh interval of the display, `None` if unknown
+    pub refresh: Option<Duration>,
+    /// Sequence number of the presentation, e.g. the vertical retrace counter, `0` if unknown
+    pub seq: u64,
+    /// How the frame was presented
+    pub flags: PresentFlags,
+}
+
 /// Error that can happen when swapping buffers.
 #[derive(Debug, thiserror::Error)]
 pub enum SwapBuffersError {
@@ -23,6 +23,24 @@
 //! See the [`renderer::element`](crate::backend::renderer::element) module for more information
 //! about how to use [`RenderElement`].
 //!
+//! # Switching renderers
+//!
+//! The state of the damage tracker does not reference any resources of a [`Renderer`].
+//! It consists of the last [`CommitCounter`], geometry and z-index of each element keyed by its [`Id`],
+//! the last output geometry and the damage of previous frames used to repair buffers by their age.
+//!
+//! Only the latter is bound to the buffers of the renderer. When switching the renderer of an output,
+//! e.g. falling back to a software renderer after a gpu reset, call [`OutputDamageTracker::migrate`]
+//! to drop the damage history while keeping the element state. The next frame will be fully damaged
+//! once and subsequent frames only redraw elements that actually changed.
+//! [`OutputDamageTracker::reset`] drops all state instead.
+//!
+//! # Copies
+//!
+//! If the same frame is needed in multiple buffers, e.g. for screencasting an output, use
+//! [`OutputDamageTracker::render_output_with_copies`]. It draws the elements only once and blits
+//! the result into the additional targets, keeping track of the damage for every buffer age.
+//!
 //! # How to use it
 //!
 //! ```no_run
@@ -195,7 +213,7 @@ use super::{
     utils::CommitCounter,
 };
 
-use super::{Renderer, Texture};
+use super::{Blit, Renderer, Texture, TextureFilter};
 
 #[derive(Debug, Clone, Copy)]
 struct ElementInstanceState {
@@ -270,6 +288,34 @@ impl TryInto<(Size<i32, Physical>, Scale<f64>, Transform)> for OutputDamageTrack
     }
 }
 
+/// Additional target for [`OutputDamageTracker::render_output_with_copies`]
+#[derive(Debug, Clone)]
+pub struct RenderCopy<T> {
+    /// Target the rendered frame is copied into
+    pub target: T,
+    /// Age of the buffer of the target, see [`OutputDamageTracker::render_output`]
+    pub age: usize,
+    /// Size of the target, the frame is scaled if it differs from the output size
+    pub size: Size<i32, Physical>,
+    /// Filter used for scaling the frame
+    pub filter: TextureFilter,
+}
+
+/// Damage of a [`RenderCopy`] or the error that prevented copying into it
+pub type CopyResult<R> = Result<Option<Vec<Rectangle<i32, Physical>>>, <R as Renderer>::Error>;
+
+/// Result of rendering a frame, kept around to render it into additional targets
+struct RenderedFrame<'a, E> {
+    damage: Option<Vec<Rectangle<i32, Physical>>>,
+    states: RenderElementStates,
+    render_elements: Vec<&'a E>,
+    opaque_regions: Vec<(usize, Vec<Rectangle<i32, Physical>>)>,
+    output_size: Size<i32, Physical>,
+    output_scale: Scale<f64>,
+    output_transform: Transform,
+    output_geo: Rectangle<i32, Physical>,
+}
+
 /// Damage tracker for a single output
 #[derive(Debug)]
 pub struct OutputDamageTracker {
@@ -334,6 +380,22 @@ impl OutputDamageTracker {
         &self.mode
     }
 
+    /// Reset the complete state of the damage tracker
+    ///
+    /// The next frame will be fully damaged and all elements are treated as new.
+    pub fn reset(&mut self) {
+        self.last_state = Default::default();
+    }
+
+    /// Prepare the damage tracker for rendering with a different renderer
+    ///
+    /// Drops the damage history of previous frames, as it only applies to the buffers of the
+    /// previous renderer, but keeps the state of the elements. The next frame will be fully damaged,
+    /// regardless of the buffer age, after that only the damage of changed elements is rendered.
+    pub fn migrate(&mut self) {
+        self.last_state.old_damage.clear();
+    }
+
     /// Render this output with the provided [`Renderer`]
     ///
     /// - `elements` for this output in front-to-back order
@@ -345,6 +407,146 @@ impl OutputDamageTracker {
         elements: &[E],
         clear_color: [f32; 4],
     ) -> Result<(Option<Vec<Rectangle<i32, Physical>>>, RenderElementStates), Error<R>>
+    where
+        E: RenderElement<R>,
+        R: Renderer,
+        <R as Renderer>::TextureId: Texture,
+    {
+        let frame = self.render_output_internal(renderer, age, age, elements, clear_color)?;
+        Ok((frame.damage, frame.states))
+    }
+
+    /// Render this output with the provided [`Renderer`] and copy the result into additional targets
+    ///
+    /// This behaves like [`render_output`](OutputDamageTracker::render_output), but after rendering
+    /// into the currently bound target, the frame is blitted into every target in `copies`, e.g. the
+    /// buffers of a screencast. This avoids drawing the elements once per target.
+    ///
+    /// Every copy has its own buffer age and receives the damage accumulated since then. Copies with
+    /// a size different from the output are scaled, which always copies the whole frame.
+    /// If blitting into a copy fails, e.g. because the renderer does not support it, the elements are
+    /// drawn into it again instead. Only copies matching the output size can be rendered this way.
+    /// In this case the currently bound target is replaced by the last re-rendered copy.
+    ///
+    /// Returns the damage of the output, the element states and the damage of every copy in the
+    /// order of `copies`, in the coordinate space of the copy. No copy is made if the output has
+    /// no damage, their damage is `None` in that case.
+    ///
+    /// - `elements` for this output in front-to-back order
+    #[allow(clippy::type_complexity)]
+    #[instrument(level = "trace", parent = &self.span, skip(renderer, elements, copies))]
+    pub fn render_output_with_copies<E, R, T>(
+        &mut self,
+        renderer: &mut R,
+        age: usize,
+        elements: &[E],
+        clear_color: [f32; 4],
+        copies: &[RenderCopy<T>],
+    ) -> Result<
+        (
+            Option<Vec<Rectangle<i32, Physical>>>,
+            RenderElementStates,
+            Vec<CopyResult<R>>,
+        ),
+        Error<R>,
+    >
+    where
+        E: RenderElement<R>,
+        R: Renderer + Blit<T>,
+        <R as Renderer>::TextureId: Texture,
+        T: Clone,
+    {
+        let history = copies.iter().map(|copy| copy.age).fold(age, usize::max);
+        let frame = self.render_output_internal(renderer, age, history, elements, clear_color)?;
+        if frame.damage.is_none() {
+            let copy_results = copies.iter().map(|_| Ok(None)).collect();
+            return Ok((frame.damage, frame.states, copy_results));
+        }
+
+        let framebuffer = Rectangle::from_loc_and_size((0, 0), frame.output_size);
+        let to_framebuffer = |rect: Rectangle<i32, Physical>| {
+            frame
+                .output_transform
+                .transform_rect_in(rect, &frame.output_geo.size)
+        };
+
+        // copy into all targets first, re-rendering unbinds the output
+        let mut copy_results = Vec::with_capacity(copies.len());
+        let mut failed = Vec::new();
+        for (index, copy) in copies.iter().enumerate() {
+            let damage = self.damage_since(copy.age, frame.output_geo);
+            let res = if copy.size == frame.output_size {
+                damage
+                    .iter()
+                    .map(|rect| to_framebuffer(*rect))
+                    .try_for_each(|rect| renderer.blit_to(copy.target.clone(), rect, rect, copy.filter))
+                    .map(|_| {
+                        damage
+                            .iter()
+                            .map(|rect| to_framebuffer(*rect))
+                            .collect::<Vec<_>>()
+                    })
+            } else {
+                let scale = Scale {
+                    x: copy.size.w as f64 / frame.output_size.w as f64,
+                    y: copy.size.h as f64 / frame.output_size.h as f64,
+                };
+                renderer
+                    .blit_to(
+                        copy.target.clone(),
+                        framebuffer,
+                        Rectangle::from_loc_and_size((0, 0), copy.size),
+                        copy.filter,
+                    )
+                    .map(|_| {
+                        damage
+                            .iter()
+                            .map(|rect| to_framebuffer(*rect).to_f64().upscale(scale).to_i32_up::<i32>())
+                            .collect::<Vec<_>>()
+                    })
+            };
+            match res {
+                Ok(damage) => copy_results.push(Ok(Some(damage))),
+                Err(err) => {
+                    trace!(index, ?copy.size, "failed to blit into copy: {:?}", err);
+                    copy_results.push(Err(err));
+                    if copy.size == frame.output_size {
+                        failed.push((index, damage));
+                    }
+                }
+            }
+        }
+
+        for (index, damage) in failed {
+            let copy = &copies[index];
+            trace!(index, "re-rendering copy with damage {:?}", damage);
+            let res = renderer.bind(copy.target.clone()).and_then(|_| {
+                draw_elements(
+                    renderer,
+                    frame.output_size,
+                    frame.output_transform,
+                    frame.output_scale,
+                    clear_color,
+                    &damage,
+                    &frame.render_elements,
+                    &frame.opaque_regions,
+                )
+            });
+            copy_results[index] =
+                res.map(|_| Some(damage.iter().map(|rect| to_framebuffer(*rect)).collect()));
+        }
+
+        Ok((frame.damage, frame.states, copy_results))
+    }
+
+    fn render_output_internal<'a, E, R>(
+        &mut self,
+        renderer: &mut R,
+        age: usize,
+        history: usize,
+        elements: &'a [E],
+        clear_color: [f32; 4],
+    ) -> Result<RenderedFrame<'a, E>, Error<R>>
     where
         E: RenderElement<R>,
         R: Renderer,
@@ -367,6 +569,7 @@ impl OutputDamageTracker {
         let mut opaque_regions: Vec<(usize, Vec<Rectangle<i32, Physical>>)> = Vec::new();
         let states = self.damage_output_internal(
             age,
+            history,
             elements,
             output_scale,
             output_geo,
@@ -375,87 +578,38 @@ impl OutputDamageTracker {
             &mut opaque_regions,
         );
 
+        let mut frame = RenderedFrame {
+            damage: None,
+            states,
+            render_elements,
+            opaque_regions,
+            output_size,
+            output_scale,
+            output_transform,
+            output_geo,
+        };
+
         if damage.is_empty() {
             trace!("no damage, skipping rendering");
-            return Ok((None, states));
+            return Ok(frame);
         }
 
         trace!(
             "rendering with damage {:?} and opaque regions {:?}",
             damage,
-            opaque_regions
+            frame.opaque_regions
         );
 
-        let render_res = (|| {
-            let mut frame = renderer.render(output_size, output_transform)?;
-
-            let clear_damage = opaque_regions.iter().flat_map(|(_, regions)| regions).fold(
-                damage.clone(),
-                |damage, region| {
-                    damage
-                        .into_iter()
-                        .flat_map(|geo| geo.subtract_rect(*region))
-                        .collect::<Vec<_>>()
-                },
-            );
-
-            trace!("clearing damage {:?}", clear_damage);
-            frame.clear(clear_color, &clear_damage)?;
-
-            for (mut z_index, element) in render_elements.iter().rev().enumerate() {
-                // This is necessary because we reversed the render elements to draw
-                // them back to front, but z-index including opaque regions is defined
-                // front to back
-                z_index = render_elements.len() - 1 - z_index;
-
-                let element_id = element.id();
-                let element_geometry = element.geometry(output_scale);
-
-                let element_damage = opaque_regions
-                    .iter()
-                    .filter(|(index, _)| *index < z_index)
-                    .flat_map(|(_, regions)| regions)
-                    .fold(
-                        damage
-                            .clone()
-                            .into_iter()
-                            .filter_map(|d| d.intersection(element_geometry))
-                            .collect::<Vec<_>>(),
-                        |damage, region| {
-                            damage
-                                .into_iter()
-                                .flat_map(|geo| geo.subtract_rect(*region))
-                                .collect::<Vec<_>>()
-                        },
-                    )
-                    .into_iter()
-                    .map(|mut d| {
-                        d.loc -= element_geometry.loc;
-                        d
-                    })
-                    .collect::<Vec<_>>();
-
-                if element_damage.is_empty() {
-                    trace!(
-                        "skipping rendering element {:?} with geometry {:?}, no damage",
-                        element_id,
-                        element_geometry
-                    );
-                    continue;
-                }
-
-                trace!(
-                    "rendering element {:?} with geometry {:?} and damage {:?}",
-                    element_id,
-                    element_geometry,
-                    element_damage,
-                );
-
-                element.draw(&mut frame, element.src(), element_geometry, &element_damage)?;
-            }
-
-            Result::<(), R::Error>::Ok(())
-        })();
+        let render_res = draw_elements(
+            renderer,
+            output_size,
+            output_transform,
+            output_scale,
+            clear_color,
+            &damage,
+            &frame.render_elements,
+            &frame.opaque_regions,
+        );
 
         if let Err(err) = render_res {
             // if the rendering errors on us, we need to be prepared, that this whole buffer was partially updated and thus now unusable.
@@ -464,7 +618,24 @@ impl OutputDamageTracker {
             return Err(Error::Rendering(err));
         }
 
-        Ok((Some(damage), states))
+        frame.damage = Some(damage);
+        Ok(frame)
+    }
+
+    /// Damage accumulated over the last `age` frames, including the current one
+    fn damage_since(
+        &self,
+        age: usize,
+        output_geo: Rectangle<i32, Physical>,
+    ) -> Vec<Rectangle<i32, Physical>> {
+        let old_damage = &self.last_state.old_damage;
+        let mut damage = if age > 0 && old_damage.len() > age {
+            old_damage.iter().take(age + 1).flatten().copied().collect()
+        } else {
+            vec![output_geo]
+        };
+        optimize_damage(&mut damage, output_geo);
+        damage
     }
 
     /// Damage this output and return the damage without actually rendering the difference
@@ -490,6 +661,7 @@ impl OutputDamageTracker {
         let mut render_elements: Vec<&E> = Vec::with_capacity(elements.len());
         let mut opaque_regions: Vec<(usize, Vec<Rectangle<i32, Physical>>)> = Vec::new();
         let states = self.damage_output_internal(
+            age,
             age,
             elements,
             output_scale,
@@ -510,6 +682,7 @@ impl OutputDamageTracker {
     fn damage_output_internal<'a, E>(
         &mut self,
         age: usize,
+        history: usize,
         elements: &'a [E],
         output_scale: Scale<f64>,
         output_geo: Rectangle<i32, Physical>,
@@ -687,9 +860,9 @@ impl OutputDamageTracker {
         // We now add old damage states, if we have an age value
         if age > 0 && self.last_state.old_damage.len() >= age {
             trace!("age of {} recent enough, using old damage", age);
-            // We do not need even older states anymore
-            self.last_state.old_damage.truncate(age);
-            damage.extend(self.last_state.old_damage.iter().flatten().copied());
+            // We do not need even older states anymore, unless a copy of the output is older
+            self.last_state.old_damage.truncate(history.max(age));
+            damage.extend(self.last_state.old_damage.iter().take(age).flatten().copied());
         } else {
             trace!(
                 "no old damage available, re-render everything. age: {} old_damage len: {}",
@@ -700,26 +873,7 @@ impl OutputDamageTracker {
             *damage = vec![output_geo];
         };
 
-        // Optimize the damage for rendering
-        damage.dedup();
-        damage.retain(|rect| rect.overlaps_or_touches(output_geo));
-        damage.retain(|rect| !rect.is_empty());
-        // filter damage outside of the output gep and merge overlapping rectangles
-        *damage = damage
-            .drain(..)
-            .filter_map(|rect| rect.intersection(output_geo))
-            .fold(Vec::new(), |new_damage, mut rect| {
-                // replace with drain_filter, when that becomes stable to reuse the original Vec's memory
-                let (overlapping, mut new_damage): (Vec<_>, Vec<_>) = new_damage
-                    .into_iter()
-                    .partition(|other| other.overlaps_or_touches(rect));
-
-                for overlap in overlapping {
-                    rect = rect.merge(overlap);
-                }
-                new_damage.push(rect);
-                new_damage
-            });
+        optimize_damage(damage, output_geo);
 
         if damage.is_empty() {
             trace!("nothing damaged, exiting early");
@@ -764,3 +918,360 @@ impl OutputDamageTracker {
         element_render_states
     }
 }
+
+/// Clip the damage to the output and merge overlapping rectangles
+fn optimize_damage(damage: &mut Vec<Rectangle<i32, Physical>>, output_geo: Rectangle<i32, Physical>) {
+    damage.dedup();
+    damage.retain(|rect| rect.overlaps_or_touches(output_geo));
+    damage.retain(|rect| !rect.is_empty());
+    // filter damage outside of the output gep and merge overlapping rectangles
+    *damage = damage
+        .drain(..)
+        .filter_map(|rect| rect.intersection(output_geo))
+        .fold(Vec::new(), |new_damage, mut rect| {
+            // replace with drain_filter, when that becomes stable to reuse the original Vec's memory
+            let (overlapping, mut new_damage): (Vec<_>, Vec<_>) = new_damage
+                .into_iter()
+                .partition(|other| other.overlaps_or_touches(rect));
+
+            for overlap in overlapping {
+                rect = rect.merge(overlap);
+            }
+            new_damage.push(rect);
+            new_damage
+        });
+}
+
+/// Draw the damaged parts of the elements into a new frame of the currently bound target
+#[allow(clippy::too_many_arguments)]
+fn draw_elements<E, R>(
+    renderer: &mut R,
+    output_size: Size<i32, Physical>,
+    output_transform: Transform,
+    output_scale: Scale<f64>,
+    clear_color: [f32; 4],
+    damage: &[Rectangle<i32, Physical>],
+    render_elements: &[&E],
+    opaque_regions: &[(usize, Vec<Rectangle<i32, Physical>>)],
+) -> Result<(), R::Error>
+where
+    E: RenderElement<R>,
+    R: Renderer,
+    <R as Renderer>::TextureId: Texture,
+{
+    let mut frame = renderer.render(output_size, output_transform)?;
+
+    let clear_damage =
+        opaque_regions
+            .iter()
+            .flat_map(|(_, regions)| regions)
+            .fold(damage.to_vec(), |damage, region| {
+                damage
+                    .into_iter()
+                    .flat_map(|geo| geo.subtract_rect(*region))
+                    .collect::<Vec<_>>()
+            });
+
+    trace!("clearing damage {:?}", clear_damage);
+    frame.clear(clear_color, &clear_damage)?;
+
+    for (mut z_index, element) in render_elements.iter().rev().enumerate() {
+        // This is necessary because we reversed the render elements to draw
+        // them back to front, but z-index including opaque regions is defined
+        // front to back
+        z_index = render_elements.len() - 1 - z_index;
+
+        let element_id = element.id();
+        let element_geometry = element.geometry(output_scale);
+
+        let element_damage = opaque_regions
+            .iter()
+            .filter(|(index, _)| *index < z_index)
+            .flat_map(|(_, regions)| regions)
+            .fold(
+                damage
+                    .iter()
+                    .filter_map(|d| d.intersection(element_geometry))
+                    .collect::<Vec<_>>(),
+                |damage, region| {
+                    damage
+                        .into_iter()
+                        .flat_map(|geo| geo.subtract_rect(*region))
+                        .collect::<Vec<_>>()
+                },
+            )
+            .into_iter()
+            .map(|mut d| {
+                d.loc -= element_geometry.loc;
+                d
+            })
+            .collect::<Vec<_>>();
+
+        if element_damage.is_empty() {
+            trace!(
+                "skipping rendering element {:?} with geometry {:?}, no damage",
+                element_id,
+                element_geometry
+            );
+            continue;
+        }
+
+        trace!(
+            "rendering element {:?} with geometry {:?} and damage {:?}",
+            element_id,
+            element_geometry,
+            element_damage,
+        );
+
+        element.draw(&mut frame, element.src(), element_geometry, &element_damage)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, convert::Infallible, rc::Rc};
+
+    use crate::{
+        backend::renderer::{
+            element::{solid::SolidColorRenderElement, Id},
+            Bind, Blit, DebugFlags, Frame, Renderer, Texture, TextureFilter, Unbind,
+        },
+        utils::{Buffer, Physical, Rectangle, Size, Transform},
+    };
+
+    use super::{OutputDamageTracker, RenderCopy};
+
+    struct MockTexture;
+
+    impl Texture for MockTexture {
+        fn width(&self) -> u32 {
+            0
+        }
+        fn height(&self) -> u32 {
+            0
+        }
+    }
+
+    struct MockFrame(usize);
+
+    impl Frame for MockFrame {
+        type Error = Infallible;
+        type TextureId = MockTexture;
+
+        fn id(&self) -> usize {
+            self.0
+        }
+        fn clear(&mut self, _: [f32; 4], _: &[Rectangle<i32, Physical>]) -> Result<(), Infallible> {
+            Ok(())
+        }
+        fn draw_solid(
+            &mut self,
+            _: Rectangle<i32, Physical>,
+            _: &[Rectangle<i32, Physical>],
+            _: [f32; 4],
+        ) -> Result<(), Infallible> {
+            Ok(())
+        }
+        fn render_texture_from_to(
+            &mut self,
+            _: &MockTexture,
+            _: Rectangle<f64, Buffer>,
+            _: Rectangle<i32, Physical>,
+            _: &[Rectangle<i32, Physical>],
+            _: Transform,
+            _: f32,
+        ) -> Result<(), Infallible> {
+            Ok(())
+        }
+        fn transformation(&self) -> Transform {
+            Transform::Normal
+        }
+        fn finish(self) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    struct MockRenderer<const ID: usize>;
+
+    impl<const ID: usize> Renderer for MockRenderer<ID> {
+        type Error = Infallible;
+        type TextureId = MockTexture;
+        type Frame<'a> = MockFrame;
+
+        fn id(&self) -> usize {
+            ID
+        }
+        fn downscale_filter(&mut self, _: TextureFilter) -> Result<(), Infallible> {
+            Ok(())
+        }
+        fn upscale_filter(&mut self, _: TextureFilter) -> Result<(), Infallible> {
+            Ok(())
+        }
+        fn set_debug_flags(&mut self, _: DebugFlags) {}
+        fn debug_flags(&self) -> DebugFlags {
+            DebugFlags::empty()
+        }
+        fn render(&mut self, _: Size<i32, Physical>, _: Transform) -> Result<MockFrame, Infallible> {
+            Ok(MockFrame(ID))
+        }
+    }
+
+    /// Target recording the destination of every blit into it
+    #[derive(Clone, Default)]
+    struct MockTarget(Rc<RefCell<Vec<Rectangle<i32, Physical>>>>);
+
+    impl<const ID: usize> Unbind for MockRenderer<ID> {
+        fn unbind(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    impl<const ID: usize> Bind<MockTarget> for MockRenderer<ID> {
+        fn bind(&mut self, _: MockTarget) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    impl<const ID: usize> Blit<MockTarget> for MockRenderer<ID> {
+        fn blit_to(
+            &mut self,
+            to: MockTarget,
+            _: Rectangle<i32, Physical>,
+            dst: Rectangle<i32, Physical>,
+            _: TextureFilter,
+        ) -> Result<(), Infallible> {
+            to.0.borrow_mut().push(dst);
+            Ok(())
+        }
+        fn blit_from(
+            &mut self,
+            _: MockTarget,
+            _: Rectangle<i32, Physical>,
+            _: Rectangle<i32, Physical>,
+            _: TextureFilter,
+        ) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    fn output() -> Rectangle<i32, Physical> {
+        Rectangle::from_loc_and_size((0, 0), (100, 100))
+    }
+
+    fn render<const ID: usize>(
+        tracker: &mut OutputDamageTracker,
+        renderer: &mut MockRenderer<ID>,
+        age: usize,
+        element: &SolidColorRenderElement,
+    ) -> Option<Vec<Rectangle<i32, Physical>>> {
+        tracker
+            .render_output(renderer, age, std::slice::from_ref(element), [0.0; 4])
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn migrate_damages_one_full_frame() {
+        let mut tracker = OutputDamageTracker::new(output().size, 1.0, Transform::Normal);
+        let id = Id::new();
+        let geometry = Rectangle::from_loc_and_size((10, 10), (20, 20));
+        let element = |commit: usize| SolidColorRenderElement::new(id.clone(), geometry, commit, [1.0; 4]);
+
+        let mut gl = MockRenderer::<1>;
+        let mut software = MockRenderer::<2>;
+
+        assert_eq!(
+            render(&mut tracker, &mut gl, 0, &element(0)),
+            Some(vec![output()])
+        );
+        render(&mut tracker, &mut gl, 1, &element(0));
+        assert_eq!(render(&mut tracker, &mut gl, 1, &element(0)), None);
+
+        // the buffers of the new renderer might report an age, but the history does not apply to them
+        tracker.migrate();
+        let mut full_frames = 0;
+        for (age, commit) in [(1, 0), (1, 0), (1, 1), (2, 1)] {
+            if render(&mut tracker, &mut software, age, &element(commit)) == Some(vec![output()]) {
+                full_frames += 1;
+            }
+        }
+        assert_eq!(full_frames, 1);
+
+        // the element state survived the swap, only the changed element gets damaged
+        assert_eq!(
+            render(&mut tracker, &mut software, 1, &element(2)),
+            Some(vec![geometry])
+        );
+    }
+
+    #[test]
+    fn reset_damages_full_output() {
+        let mut tracker = OutputDamageTracker::new(output().size, 1.0, Transform::Normal);
+        let element = SolidColorRenderElement::new(
+            Id::new(),
+            Rectangle::from_loc_and_size((0, 0), (10, 10)),
+            0,
+            [1.0; 4],
+        );
+        let mut renderer = MockRenderer::<1>;
+
+        render(&mut tracker, &mut renderer, 0, &element);
+        render(&mut tracker, &mut renderer, 1, &element);
+        assert_eq!(render(&mut tracker, &mut renderer, 1, &element), None);
+
+        tracker.reset();
+        assert_eq!(
+            render(&mut tracker, &mut renderer, 1, &element),
+            Some(vec![output()])
+        );
+    }
+
+    #[test]
+    fn copies_receive_damage_of_their_age() {
+        let mut tracker = OutputDamageTracker::new(output().size, 1.0, Transform::Normal);
+        let id = Id::new();
+        let geometry = Rectangle::from_loc_and_size((10, 10), (20, 20));
+        let element = |commit: usize| SolidColorRenderElement::new(id.clone(), geometry, commit, [1.0; 4]);
+        let mut renderer = MockRenderer::<1>;
+
+        render(&mut tracker, &mut renderer, 0, &element(0));
+        render(&mut tracker, &mut renderer, 1, &element(1));
+        render(&mut tracker, &mut renderer, 1, &element(2));
+
+        let copy = |age: usize, size: (i32, i32)| RenderCopy {
+            target: MockTarget::default(),
+            age,
+            size: size.into(),
+            filter: TextureFilter::Linear,
+        };
+        let copies = [copy(2, (100, 100)), copy(3, (100, 100)), copy(2, (200, 200))];
+        let (damage, _, copy_damage) = tracker
+            .render_output_with_copies(
+                &mut renderer,
+                1,
+                std::slice::from_ref(&element(3)),
+                [0.0; 4],
+                &copies,
+            )
+            .unwrap();
+        assert_eq!(damage, Some(vec![geometry]));
+
+        // the last two frames only damaged the element
+        assert_eq!(copy_damage[0], Ok(Some(vec![geometry])));
+        assert_eq!(*copies[0].target.0.borrow(), vec![geometry]);
+        // the history does not reach back far enough
+        assert_eq!(copy_damage[1], Ok(Some(vec![output()])));
+        assert_eq!(*copies[1].target.0.borrow(), vec![output()]);
+        // scaled copies receive the whole frame, but only report the scaled damage
+        assert_eq!(
+            copy_damage[2],
+            Ok(Some(vec![Rectangle::from_loc_and_size((20, 20), (40, 40))]))
+        );
+        assert_eq!(
+            *copies[2].target.0.borrow(),
+            vec![Rectangle::from_loc_and_size((0, 0), (200, 200))]
+        );
+    }
+}
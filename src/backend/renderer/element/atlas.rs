@@ -0,0 +1,649 @@
+//! Texture atlas for small textures
+//!
+//! # Why use this implementation
+//!
+//! Compositor-drawn UI like borders, shadows or icons usually consists of many small images.
+//! Importing each of them as a separate texture fragments GPU memory and requires the renderer
+//! to bind a different texture for every element. The [`TextureAtlas`] instead allocates regions
+//! of a few large backing textures (pages) using shelf packing and renders them as
+//! [`TextureRenderElement`]s sampling only their sub-rectangle of the page. Every region is
+//! surrounded by a 1px gutter repeating its edge pixels, so filtering at the edges of a region
+//! does not pick up the content of its neighbours.
+//!
+//! # Why **not** to use this implementation
+//!
+//! The atlas keeps a copy of every page in memory, to support partial uploads and compaction
+//! through [`ImportMem`]. Large or frequently changing content, like client buffers, is better
+//! kept in its own texture, e.g. using a [`MemoryRenderBuffer`](super::memory::MemoryRenderBuffer).
+//!
+//! # How to use it
+//!
+//! ```no_run
+//! # use smithay::backend::renderer::{ImportMem, Renderer};
+//! use smithay::backend::renderer::element::atlas::TextureAtlas;
+//!
+//! # fn example<R>(renderer: &mut R) where R: Renderer + ImportMem, <R as Renderer>::TextureId: Clone {
+//! let mut atlas = TextureAtlas::new(&*renderer, (1024, 1024));
+//!
+//! // upload a 16x16 icon
+//! let icon = atlas.allocate(renderer, &[0xff; 16 * 16 * 4], (16, 16)).unwrap();
+//!
+//! // in the render loop
+//! if let Some(element) = atlas.render_element(&icon, (100.0, 100.0), 1, None) {
+//!     // render the element
+//! }
+//!
+//! // once the icon is not needed anymore
+//! atlas.free(icon);
+//! // from time to time, e.g. after many regions were freed
+//! atlas.compact(renderer).unwrap();
+//! # }
+//! ```
+
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    backend::renderer::{utils::DamageBag, ImportMem, Renderer, Texture},
+    utils::{Buffer, Physical, Point, Rectangle, Size, Transform},
+};
+
+use super::{texture::TextureRenderElement, Id};
+
+/// Errors of a [`TextureAtlas`]
+#[derive(Debug, thiserror::Error)]
+pub enum AtlasError<E: std::error::Error> {
+    /// The requested region is larger than a page of the atlas
+    #[error("Region of size {0:?} does not fit into an atlas page")]
+    TooLarge(Size<i32, Buffer>),
+    /// The provided data is smaller than the region
+    #[error("Expected at least {expected} bytes of data, got {actual}")]
+    DataSize {
+        /// Expected length of the data
+        expected: usize,
+        /// Actual length of the data
+        actual: usize,
+    },
+    /// The region was not allocated by this atlas
+    #[error("Region of a different atlas")]
+    UnknownRegion,
+    /// The renderer failed to import or update a page
+    #[error(transparent)]
+    Renderer(E),
+}
+
+/// Width of the border around every region, repeating its edge pixels
+const GUTTER: i32 = 1;
+
+/// Rectangle of a region including its gutter
+fn with_gutter(rect: Rectangle<i32, Buffer>) -> Rectangle<i32, Buffer> {
+    Rectangle::from_loc_and_size(
+        rect.loc - Point::from((GUTTER, GUTTER)),
+        rect.size + Size::from((2 * GUTTER, 2 * GUTTER)),
+    )
+}
+
+/// Row of allocations of the same maximum height
+#[derive(Debug, Clone)]
+struct Shelf {
+    y: i32,
+    height: i32,
+    /// Free spans as `(x, width)`, sorted by `x`
+    free: Vec<(i32, i32)>,
+    allocations: usize,
+}
+
+/// Shelf packing of rectangles into a fixed size area
+#[derive(Debug, Clone)]
+struct ShelfPacker {
+    size: Size<i32, Buffer>,
+    shelves: Vec<Shelf>,
+}
+
+impl ShelfPacker {
+    fn new(size: Size<i32, Buffer>) -> ShelfPacker {
+        ShelfPacker {
+            size,
+            shelves: Vec::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.shelves.iter().all(|shelf| shelf.allocations == 0)
+    }
+
+    fn allocate(&mut self, size: Size<i32, Buffer>) -> Option<Rectangle<i32, Buffer>> {
+        if size.w <= 0 || size.h <= 0 || size.w > self.size.w || size.h > self.size.h {
+            return None;
+        }
+
+        // prefer the lowest shelf fitting the allocation to reduce wasted space
+        let existing = self
+            .shelves
+            .iter()
+            .enumerate()
+            .filter(|(_, shelf)| shelf.height >= size.h)
+            .filter_map(|(idx, shelf)| {
+                shelf
+                    .free
+                    .iter()
+                    .position(|(_, w)| *w >= size.w)
+                    .map(|span| (idx, span, shelf.height))
+            })
+            .min_by_key(|(_, _, height)| *height);
+
+        let (idx, span) = match existing {
+            Some((idx, span, _)) => (idx, span),
+            None => {
+                let y = self.shelves.last().map_or(0, |shelf| shelf.y + shelf.height);
+                if y + size.h > self.size.h {
+                    return None;
+                }
+                self.shelves.push(Shelf {
+                    y,
+                    height: size.h,
+                    free: vec![(0, self.size.w)],
+                    allocations: 0,
+                });
+                (self.shelves.len() - 1, 0)
+            }
+        };
+
+        let shelf = &mut self.shelves[idx];
+        let (x, w) = shelf.free[span];
+        if w == size.w {
+            shelf.free.remove(span);
+        } else {
+            shelf.free[span] = (x + size.w, w - size.w);
+        }
+        shelf.allocations += 1;
+        Some(Rectangle::from_loc_and_size((x, shelf.y), size))
+    }
+
+    fn deallocate(&mut self, rect: Rectangle<i32, Buffer>) {
+        let Some(shelf) = self.shelves.iter_mut().find(|shelf| shelf.y == rect.loc.y) else {
+            return;
+        };
+
+        let idx = shelf.free.partition_point(|(x, _)| *x < rect.loc.x);
+        shelf.free.insert(idx, (rect.loc.x, rect.size.w));
+        // merge with the following and preceding span
+        if idx + 1 < shelf.free.len() && shelf.free[idx].0 + shelf.free[idx].1 == shelf.free[idx + 1].0 {
+            shelf.free[idx].1 += shelf.free.remove(idx + 1).1;
+        }
+        if idx > 0 && shelf.free[idx - 1].0 + shelf.free[idx - 1].1 == shelf.free[idx].0 {
+            shelf.free[idx - 1].1 += shelf.free.remove(idx).1;
+        }
+        shelf.allocations -= 1;
+
+        // give space of empty shelves at the end back to new shelves of any height
+        while self.shelves.last().map_or(false, |shelf| shelf.allocations == 0) {
+            self.shelves.pop();
+        }
+    }
+}
+
+/// Copy `rect` of `src` into `dst` at `dst_loc`, both being RGBA8 with the given widths
+fn blit(
+    dst: &mut [u8],
+    dst_width: i32,
+    dst_loc: Point<i32, Buffer>,
+    src: &[u8],
+    src_width: i32,
+    rect: Rectangle<i32, Buffer>,
+) {
+    let row_len = rect.size.w as usize * 4;
+    for row in 0..rect.size.h {
+        let src_offset = ((rect.loc.y + row) * src_width + rect.loc.x) as usize * 4;
+        let dst_offset = ((dst_loc.y + row) * dst_width + dst_loc.x) as usize * 4;
+        dst[dst_offset..dst_offset + row_len].copy_from_slice(&src[src_offset..src_offset + row_len]);
+    }
+}
+
+/// Fill the gutter around `rect` of `pixels`, being RGBA8 with the given width, with its edge pixels
+fn fill_gutter(pixels: &mut [u8], width: i32, rect: Rectangle<i32, Buffer>) {
+    let offset = |x: i32, y: i32| (y * width + x) as usize * 4;
+    let (left, right) = (rect.loc.x, rect.loc.x + rect.size.w - 1);
+    let (top, bottom) = (rect.loc.y, rect.loc.y + rect.size.h - 1);
+
+    // extend the left and right columns
+    for y in top..=bottom {
+        pixels.copy_within(offset(left, y)..offset(left, y) + 4, offset(left - 1, y));
+        pixels.copy_within(offset(right, y)..offset(right, y) + 4, offset(right + 1, y));
+    }
+    // then the top and bottom rows, including the corners
+    let row_len = (rect.size.w + 2) as usize * 4;
+    pixels.copy_within(
+        offset(left - 1, top)..offset(left - 1, top) + row_len,
+        offset(left - 1, top - 1),
+    );
+    pixels.copy_within(
+        offset(left - 1, bottom)..offset(left - 1, bottom) + row_len,
+        offset(left - 1, bottom + 1),
+    );
+}
+
+#[derive(Debug)]
+struct Page<T> {
+    id: usize,
+    texture: T,
+    packer: ShelfPacker,
+    pixels: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct RegionState {
+    page: usize,
+    /// Content of the region, without the gutter
+    rect: Rectangle<i32, Buffer>,
+    id: Id,
+    /// Damage in coordinates of the page
+    damage: DamageBag<i32, Buffer>,
+}
+
+/// Handle to a region of a [`TextureAtlas`]
+///
+/// Returned by [`TextureAtlas::allocate`] and only valid for the atlas that created it.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct AtlasRegion {
+    atlas: usize,
+    key: usize,
+}
+
+static NEXT_ATLAS_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Atlas packing small textures into larger pages
+///
+/// See the [module documentation](self) for details.
+#[derive(Debug)]
+pub struct TextureAtlas<T> {
+    id: usize,
+    renderer_id: usize,
+    page_size: Size<i32, Buffer>,
+    pages: Vec<Page<T>>,
+    regions: HashMap<usize, RegionState>,
+    next_page: usize,
+    next_region: usize,
+}
+
+impl<T: Texture + Clone> TextureAtlas<T> {
+    /// Create an empty atlas, allocating pages of the given size on demand
+    pub fn new<R: Renderer<TextureId = T>>(renderer: &R, page_size: impl Into<Size<i32, Buffer>>) -> Self {
+        TextureAtlas {
+            id: NEXT_ATLAS_ID.fetch_add(1, Ordering::Relaxed),
+            renderer_id: renderer.id(),
+            page_size: page_size.into(),
+            pages: Vec::new(),
+            regions: HashMap::new(),
+            next_page: 0,
+            next_region: 0,
+        }
+    }
+
+    /// Number of backing textures currently allocated
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Size of a region
+    ///
+    /// Returns `None` if the region was not allocated by this atlas.
+    pub fn region_size(&self, region: &AtlasRegion) -> Option<Size<i32, Buffer>> {
+        self.region(region).map(|state| state.rect.size)
+    }
+
+    fn region(&self, region: &AtlasRegion) -> Option<&RegionState> {
+        if region.atlas != self.id {
+            return None;
+        }
+        self.regions.get(&region.key)
+    }
+
+    /// Allocate a region and upload its content
+    ///
+    /// `data` has to be in RGBA8 format and contain at least `size.w * size.h * 4` bytes.
+    pub fn allocate<R>(
+        &mut self,
+        renderer: &mut R,
+        data: &[u8],
+        size: impl Into<Size<i32, Buffer>>,
+    ) -> Result<AtlasRegion, AtlasError<<R as Renderer>::Error>>
+    where
+        R: Renderer<TextureId = T> + ImportMem,
+    {
+        let size = size.into();
+        check_data_size(data, size)?;
+        let padded = with_gutter(Rectangle::from_loc_and_size((0, 0), size)).size;
+
+        let allocation = self
+            .pages
+            .iter_mut()
+            .enumerate()
+            .find_map(|(idx, page)| page.packer.allocate(padded).map(|slot| (idx, slot)));
+        let (page_idx, slot) = match allocation {
+            Some(allocation) => allocation,
+            None => {
+                let mut packer = ShelfPacker::new(self.page_size);
+                let slot = packer.allocate(padded).ok_or(AtlasError::TooLarge(size))?;
+                let pixels = vec![0; (self.page_size.w * self.page_size.h * 4) as usize];
+                let texture = renderer
+                    .import_memory(&pixels, self.page_size, false)
+                    .map_err(AtlasError::Renderer)?;
+                self.pages.push(Page {
+                    id: self.next_page,
+                    texture,
+                    packer,
+                    pixels,
+                });
+                self.next_page += 1;
+                (self.pages.len() - 1, slot)
+            }
+        };
+
+        let page = &mut self.pages[page_idx];
+        let rect = Rectangle::from_loc_and_size(slot.loc + Point::from((GUTTER, GUTTER)), size);
+        blit(
+            &mut page.pixels,
+            self.page_size.w,
+            rect.loc,
+            data,
+            size.w,
+            Rectangle::from_loc_and_size((0, 0), size),
+        );
+        fill_gutter(&mut page.pixels, self.page_size.w, rect);
+        if let Err(err) = renderer.update_memory(&page.texture, &page.pixels, slot) {
+            page.packer.deallocate(slot);
+            return Err(AtlasError::Renderer(err));
+        }
+
+        let key = self.next_region;
+        self.next_region += 1;
+        self.regions.insert(
+            key,
+            RegionState {
+                page: page.id,
+                rect,
+                id: Id::new(),
+                damage: DamageBag::default(),
+            },
+        );
+        Ok(AtlasRegion { atlas: self.id, key })
+    }
+
+    /// Update a part of the content of a region
+    ///
+    /// `data` contains the whole content of the region in RGBA8 format, but only the
+    /// `damage` rectangle, relative to the region, is uploaded.
+    ///
+    /// Fails with [`AtlasError::UnknownRegion`] if the region was not allocated by this atlas.
+    pub fn update<R>(
+        &mut self,
+        renderer: &mut R,
+        region: &AtlasRegion,
+        data: &[u8],
+        damage: Rectangle<i32, Buffer>,
+    ) -> Result<(), AtlasError<<R as Renderer>::Error>>
+    where
+        R: Renderer<TextureId = T> + ImportMem,
+    {
+        if region.atlas != self.id {
+            return Err(AtlasError::UnknownRegion);
+        }
+        let state = self
+            .regions
+            .get_mut(&region.key)
+            .ok_or(AtlasError::UnknownRegion)?;
+        check_data_size(data, state.rect.size)?;
+        let Some(damage) = damage.intersection(Rectangle::from_loc_and_size((0, 0), state.rect.size)) else {
+            return Ok(());
+        };
+
+        let page = self.pages.iter_mut().find(|page| page.id == state.page).unwrap();
+        let target = Rectangle::from_loc_and_size(state.rect.loc + damage.loc, damage.size);
+        blit(
+            &mut page.pixels,
+            self.page_size.w,
+            target.loc,
+            data,
+            state.rect.size.w,
+            damage,
+        );
+        fill_gutter(&mut page.pixels, self.page_size.w, state.rect);
+        // the gutter next to the damage changes with it
+        let upload = with_gutter(target);
+        renderer
+            .update_memory(&page.texture, &page.pixels, upload)
+            .map_err(AtlasError::Renderer)?;
+        state.damage.add([upload]);
+        Ok(())
+    }
+
+    /// Free a region, making its space available for new allocations
+    ///
+    /// The backing texture is only released by [`TextureAtlas::compact`].
+    pub fn free(&mut self, region: AtlasRegion) {
+        if region.atlas != self.id {
+            return;
+        }
+        let Some(state) = self.regions.remove(&region.key) else {
+            return;
+        };
+        if let Some(page) = self.pages.iter_mut().find(|page| page.id == state.page) {
+            page.packer.deallocate(with_gutter(state.rect));
+        }
+    }
+
+    /// Release empty pages and repack fragmented ones
+    ///
+    /// Repacking moves regions inside their page and re-uploads the page, so it is
+    /// best called periodically rather than on every frame.
+    pub fn compact<R>(&mut self, renderer: &mut R) -> Result<(), <R as Renderer>::Error>
+    where
+        R: Renderer<TextureId = T> + ImportMem,
+    {
+        self.pages.retain(|page| !page.packer.is_empty());
+
+        for page in &mut self.pages {
+            let mut regions = self
+                .regions
+                .values_mut()
+                .filter(|state| state.page == page.id)
+                .collect::<Vec<_>>();
+            // packing the tallest regions first results in the fewest shelves
+            regions.sort_by_key(|state| std::cmp::Reverse(state.rect.size.h));
+
+            let mut packer = ShelfPacker::new(self.page_size);
+            let Some(rects) = regions
+                .iter()
+                .map(|state| {
+                    packer.allocate(with_gutter(state.rect).size).map(|slot| {
+                        Rectangle::from_loc_and_size(slot.loc + Point::from((GUTTER, GUTTER)), state.rect.size)
+                    })
+                })
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            if regions
+                .iter()
+                .zip(&rects)
+                .all(|(state, rect)| state.rect == *rect)
+            {
+                continue;
+            }
+
+            let mut pixels = vec![0; page.pixels.len()];
+            for (state, rect) in regions.iter_mut().zip(rects) {
+                // the gutter moves along with the content
+                blit(
+                    &mut pixels,
+                    self.page_size.w,
+                    with_gutter(rect).loc,
+                    &page.pixels,
+                    self.page_size.w,
+                    with_gutter(state.rect),
+                );
+                if state.rect != rect {
+                    state.rect = rect;
+                    state.damage.add([with_gutter(rect)]);
+                }
+            }
+            renderer.update_memory(
+                &page.texture,
+                &pixels,
+                Rectangle::from_loc_and_size((0, 0), self.page_size),
+            )?;
+            page.pixels = pixels;
+            page.packer = packer;
+        }
+
+        Ok(())
+    }
+
+    /// Create a render element for a region
+    ///
+    /// * `location` - Location of the element in physical coordinates
+    /// * `scale` - Buffer scale of the region content
+    /// * `alpha` - Alpha of the element, defaults to `1.0`
+    ///
+    /// Returns `None` if the region was not allocated by this atlas.
+    pub fn render_element(
+        &self,
+        region: &AtlasRegion,
+        location: impl Into<Point<f64, Physical>>,
+        scale: i32,
+        alpha: Option<f32>,
+    ) -> Option<TextureRenderElement<T>> {
+        let state = self.region(region)?;
+        let page = self.pages.iter().find(|page| page.id == state.page)?;
+        let src = state.rect.to_logical(scale, Transform::Normal, &self.page_size);
+        Some(TextureRenderElement::from_texture_with_damage(
+            state.id.clone(),
+            self.renderer_id,
+            location,
+            page.texture.clone(),
+            scale,
+            Transform::Normal,
+            alpha,
+            Some(src.to_f64()),
+            Some(src.size),
+            None,
+            state.damage.snapshot(),
+        ))
+    }
+}
+
+fn check_data_size<E: std::error::Error>(data: &[u8], size: Size<i32, Buffer>) -> Result<(), AtlasError<E>> {
+    let expected = (size.w.max(0) * size.h.max(0) * 4) as usize;
+    if data.len() < expected {
+        return Err(AtlasError::DataSize {
+            expected,
+            actual: data.len(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{blit, fill_gutter, with_gutter, ShelfPacker};
+    use crate::utils::{Buffer, Rectangle, Size};
+
+    fn packer() -> ShelfPacker {
+        ShelfPacker::new(Size::<i32, Buffer>::from((64, 64)))
+    }
+
+    #[test]
+    fn allocations_share_shelves() {
+        let mut packer = packer();
+        let a = packer.allocate((16, 16).into()).unwrap();
+        let b = packer.allocate((16, 8).into()).unwrap();
+        let c = packer.allocate((32, 32).into()).unwrap();
+
+        assert_eq!(a, Rectangle::from_loc_and_size((0, 0), (16, 16)));
+        assert_eq!(b, Rectangle::from_loc_and_size((16, 0), (16, 8)));
+        assert_eq!(c, Rectangle::from_loc_and_size((0, 16), (32, 32)));
+        assert!(packer.allocate((64, 32).into()).is_none());
+        assert!(packer.allocate((65, 1).into()).is_none());
+    }
+
+    #[test]
+    fn freed_space_is_reused() {
+        let mut packer = packer();
+        let regions = (0..4)
+            .map(|_| packer.allocate((16, 16).into()).unwrap())
+            .collect::<Vec<_>>();
+        packer.deallocate(regions[1]);
+        packer.deallocate(regions[2]);
+
+        // the two freed spans were merged
+        assert_eq!(
+            packer.allocate((32, 16).into()),
+            Some(Rectangle::from_loc_and_size((16, 0), (32, 16)))
+        );
+
+        packer.deallocate(regions[0]);
+        packer.deallocate(regions[3]);
+        packer.deallocate(Rectangle::from_loc_and_size((16, 0), (32, 16)));
+        assert!(packer.is_empty());
+        // the empty shelf is released, so a taller allocation fits at the top again
+        assert_eq!(
+            packer.allocate((64, 64).into()),
+            Some(Rectangle::from_loc_and_size((0, 0), (64, 64)))
+        );
+    }
+
+    #[test]
+    fn blit_copies_sub_rectangle() {
+        // 2x2 source with distinct pixels
+        let src = (0u8..16).collect::<Vec<_>>();
+        let mut dst = vec![0u8; 4 * 4 * 4];
+        blit(
+            &mut dst,
+            4,
+            (1, 2).into(),
+            &src,
+            2,
+            Rectangle::from_loc_and_size((1, 0), (1, 2)),
+        );
+
+        let pixel = |x: usize, y: usize| &dst[(y * 4 + x) * 4..(y * 4 + x) * 4 + 4];
+        assert_eq!(pixel(1, 2), &[4, 5, 6, 7]);
+        assert_eq!(pixel(1, 3), &[12, 13, 14, 15]);
+        assert_eq!(pixel(0, 2), &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn gutter_repeats_edge_pixels() {
+        // 2x2 region at (1, 1) of a 4x4 page
+        let mut pixels = vec![0u8; 4 * 4 * 4];
+        let rect = Rectangle::from_loc_and_size((1, 1), (2, 2));
+        let src = (1u8..=16).collect::<Vec<_>>();
+        blit(
+            &mut pixels,
+            4,
+            rect.loc,
+            &src,
+            2,
+            Rectangle::from_loc_and_size((0, 0), (2, 2)),
+        );
+        fill_gutter(&mut pixels, 4, rect);
+
+        let pixel = |x: usize, y: usize| pixels[(y * 4 + x) * 4];
+        let rows = (0..4)
+            .map(|y| (0..4).map(|x| pixel(x, y)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            vec![
+                vec![1, 1, 5, 5],
+                vec![1, 1, 5, 5],
+                vec![9, 9, 13, 13],
+                vec![9, 9, 13, 13],
+            ]
+        );
+        assert_eq!(with_gutter(rect), Rectangle::from_loc_and_size((0, 0), (4, 4)));
+    }
+}
@@ -0,0 +1,568 @@
+//! Elements to render the pointer cursor
+//!
+//! The cursor image of a seat is either a surface provided by a client, a cursor
+//! drawn by the compositor itself or hidden (see [`CursorImageStatus`]).
+//! Positioning the image correctly requires to offset the pointer location by the
+//! hotspot of the image, which has to happen in the same coordinate space for every
+//! output to prevent the cursor from jumping when moving between outputs of different scales.
+//!
+//! [`render_cursor_elements`] takes care of that for the software rendered cursor,
+//! while [`cursor_plane_position`] provides the same positioning for a hardware cursor plane
+//! and [`cursor_plane_image`] the image to put on it.
+//! Both compute the position of the pointer and the hotspot separately in physical
+//! coordinates of the output, so the offset between the pointer and the image origin is
+//! constant for a given output scale, regardless of the fractional part of the pointer location.
+//!
+//! The hotspot of client-provided surfaces is read on every call, so cursor surfaces
+//! changing their hotspot with every commit (e.g. animated cursors) are always positioned correctly.
+
+use std::sync::Mutex;
+
+use wayland_server::protocol::wl_surface::WlSurface;
+
+use crate::{
+    backend::renderer::{
+        utils::{Buffer, RendererSurfaceStateUserData},
+        ImportAll, Renderer, Texture,
+    },
+    input::pointer::{CursorImageAttributes, CursorImageStatus},
+    utils::{Buffer as BufferCoord, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
+    wayland::compositor,
+};
+
+use super::{
+    surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
+    texture::{TextureBuffer, TextureRenderElement},
+};
+
+crate::backend::renderer::element::render_elements! {
+    /// Render element of a cursor image
+    pub CursorRenderElement<R> where R: ImportAll;
+    /// A client-provided cursor surface
+    Surface=WaylandSurfaceRenderElement<R>,
+    /// A compositor-provided cursor image
+    Texture=TextureRenderElement<<R as Renderer>::TextureId>,
+}
+
+impl<R: Renderer> std::fmt::Debug for CursorRenderElement<R>
+where
+    <R as Renderer>::TextureId: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Surface(arg0) => f.debug_tuple("Surface").field(arg0).finish(),
+            Self::Texture(arg0) => f.debug_tuple("Texture").field(arg0).finish(),
+            Self::_GenericCatcher(_) => unreachable!(),
+        }
+    }
+}
+
+/// Cursor image drawn by the compositor
+///
+/// Used whenever the cursor status is [`CursorImageStatus::Default`].
+#[derive(Debug)]
+pub struct DefaultCursor<T> {
+    /// The image of the cursor
+    ///
+    /// The buffer scale of the texture should match the scale of the output
+    /// the cursor is rendered on for a crisp image.
+    pub buffer: TextureBuffer<T>,
+    /// Hotspot of the image in logical coordinates
+    pub hotspot: Point<i32, Logical>,
+}
+
+/// Retrieve the hotspot of a client-provided cursor surface
+///
+/// Returns `(0, 0)` if the surface has no cursor role.
+pub fn cursor_surface_hotspot(surface: &WlSurface) -> Point<i32, Logical> {
+    compositor::with_states(surface, |states| {
+        states
+            .data_map
+            .get::<Mutex<CursorImageAttributes>>()
+            .map(|attrs| attrs.lock().unwrap().hotspot)
+            .unwrap_or_default()
+    })
+}
+
+/// Position of the cursor image origin relative to an output
+///
+/// * `pointer_location` - The location of the pointer in the global compositor space
+/// * `hotspot` - The hotspot of the cursor image
+/// * `output_geometry` - The geometry of the output in the global compositor space
+/// * `scale` - The scale of the output
+pub fn cursor_plane_position(
+    pointer_location: Point<f64, Logical>,
+    hotspot: Point<i32, Logical>,
+    output_geometry: Rectangle<i32, Logical>,
+    scale: impl Into<Scale<f64>>,
+) -> Point<i32, Physical> {
+    let scale = scale.into();
+    let pointer = (pointer_location - output_geometry.loc.to_f64())
+        .to_physical(scale)
+        .to_i32_round();
+    pointer - cursor_physical_hotspot(hotspot, scale)
+}
+
+/// Hotspot of a cursor image in physical coordinates of an output
+///
+/// This is what a hardware cursor plane expects as the hotspot of its buffer.
+pub fn cursor_physical_hotspot(
+    hotspot: Point<i32, Logical>,
+    scale: impl Into<Scale<f64>>,
+) -> Point<i32, Physical> {
+    hotspot.to_f64().to_physical(scale.into()).to_i32_round()
+}
+
+/// Buffer holding the image of a cursor, see [`cursor_plane_image`]
+#[derive(Debug)]
+pub enum CursorPlaneBuffer<'a, T> {
+    /// The image of the [`DefaultCursor`]
+    Default(&'a TextureBuffer<T>),
+    /// The buffer currently attached to a client-provided cursor surface
+    Surface(Buffer),
+}
+
+/// Image of a cursor at the scale of an output, as needed by a hardware cursor plane
+#[derive(Debug)]
+pub struct CursorPlaneImage<'a, T> {
+    /// The buffer holding the image
+    pub buffer: CursorPlaneBuffer<'a, T>,
+    /// Size of the image on the output in physical coordinates
+    pub size: Size<i32, Physical>,
+    /// Hotspot of the image in physical coordinates, see [`cursor_physical_hotspot`]
+    pub hotspot: Point<i32, Physical>,
+    /// Whether `buffer` has to be rendered at `size` before it can be put on a cursor plane
+    ///
+    /// This is the case if the buffer does not match the output pixel for pixel, because its
+    /// buffer scale differs from the output scale, it is transformed or cropped and scaled by a
+    /// viewport, or if the cursor surface has subsurfaces. [`render_cursor_elements`] provides
+    /// the elements to render the image at the scale of the output.
+    pub needs_rendering: bool,
+}
+
+/// Retrieve the cursor image for a hardware cursor plane of a given output
+///
+/// Returns `None` if the cursor is hidden, the cursor status is [`CursorImageStatus::Default`]
+/// without a `default_cursor` being provided or the cursor surface has no buffer attached.
+///
+/// * `status` - The current cursor status of the seat
+/// * `default_cursor` - The image to use for [`CursorImageStatus::Default`]
+/// * `scale` - The scale of the output
+pub fn cursor_plane_image<'a, T: Texture>(
+    status: &CursorImageStatus,
+    default_cursor: Option<&'a DefaultCursor<T>>,
+    scale: impl Into<Scale<f64>>,
+) -> Option<CursorPlaneImage<'a, T>> {
+    let scale = scale.into();
+    match status {
+        CursorImageStatus::Hidden => None,
+        CursorImageStatus::Default => {
+            let cursor = default_cursor?;
+            let buffer = &cursor.buffer;
+            let pixels = buffer.texture().size();
+            let size = pixels
+                .to_logical(buffer.scale(), buffer.transform())
+                .to_physical_precise_round(scale);
+            Some(CursorPlaneImage {
+                buffer: CursorPlaneBuffer::Default(buffer),
+                size,
+                hotspot: cursor_physical_hotspot(cursor.hotspot, scale),
+                needs_rendering: buffer.transform() != Transform::Normal || !same_pixels(pixels, size),
+            })
+        }
+        CursorImageStatus::Surface(surface) => {
+            let hotspot = cursor_physical_hotspot(cursor_surface_hotspot(surface), scale);
+            let has_subsurfaces = !compositor::get_children(surface).is_empty();
+            compositor::with_states(surface, |states| {
+                let data = states.data_map.get::<RendererSurfaceStateUserData>()?.borrow();
+                let buffer = data.buffer()?.clone();
+                let pixels = data.buffer_dimensions?;
+                let view = data.view()?;
+                let size = view.dst.to_physical_precise_round(scale);
+                let uncropped =
+                    view.src == Rectangle::from_loc_and_size((0.0, 0.0), data.buffer_size()?.to_f64());
+                Some(CursorPlaneImage {
+                    buffer: CursorPlaneBuffer::Surface(buffer),
+                    size,
+                    hotspot,
+                    needs_rendering: has_subsurfaces
+                        || data.buffer_transform != Transform::Normal
+                        || !uncropped
+                        || !same_pixels(pixels, size),
+                })
+            })
+        }
+    }
+}
+
+fn same_pixels(buffer: Size<i32, BufferCoord>, output: Size<i32, Physical>) -> bool {
+    buffer.w == output.w && buffer.h == output.h
+}
+
+/// Retrieve the render elements of a cursor image for a given output
+///
+/// Returns no elements if the cursor is hidden or the cursor status is
+/// [`CursorImageStatus::Default`] without a `default_cursor` being provided.
+///
+/// * `status` - The current cursor status of the seat
+/// * `default_cursor` - The image to use for [`CursorImageStatus::Default`]
+/// * `pointer_location` - The location of the pointer in the global compositor space
+/// * `output_geometry` - The geometry of the output in the global compositor space
+/// * `scale` - The scale of the output
+pub fn render_cursor_elements<R, E>(
+    renderer: &mut R,
+    status: &CursorImageStatus,
+    default_cursor: Option<&DefaultCursor<<R as Renderer>::TextureId>>,
+    pointer_location: Point<f64, Logical>,
+    output_geometry: Rectangle<i32, Logical>,
+    scale: impl Into<Scale<f64>>,
+) -> Vec<E>
+where
+    R: Renderer + ImportAll,
+    <R as Renderer>::TextureId: Clone + 'static,
+    E: From<CursorRenderElement<R>>,
+{
+    let scale = scale.into();
+    match status {
+        CursorImageStatus::Hidden => Vec::new(),
+        CursorImageStatus::Default => default_cursor
+            .map(|cursor| {
+                let location =
+                    cursor_plane_position(pointer_location, cursor.hotspot, output_geometry, scale);
+                vec![E::from(CursorRenderElement::Texture(
+                    TextureRenderElement::from_texture_buffer(
+                        location.to_f64(),
+                        &cursor.buffer,
+                        None,
+                        None,
+                        None,
+                    ),
+                ))]
+            })
+            .unwrap_or_default(),
+        CursorImageStatus::Surface(surface) => {
+            let hotspot = cursor_surface_hotspot(surface);
+            let location = cursor_plane_position(pointer_location, hotspot, output_geometry, scale);
+            render_elements_from_surface_tree::<R, CursorRenderElement<R>>(renderer, surface, location, scale)
+                .into_iter()
+                .map(E::from)
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::{Physical, Point, Rectangle};
+
+    use super::{cursor_physical_hotspot, cursor_plane_position};
+
+    #[test]
+    fn hotspot_offset_constant_at_fractional_scale() {
+        let output = Rectangle::from_loc_and_size((0, 0), (1280, 720));
+        let hotspot = Point::from((3, 3));
+        let scale = 1.5;
+
+        // the image must keep the same distance to the pointer regardless of sub-pixel motion
+        for x in 0..10 {
+            let pointer = Point::from((100.0 + x as f64 * 0.1, 50.0));
+            let pointer_physical = (pointer.to_physical(scale)).to_i32_round::<i32>();
+            let position = cursor_plane_position(pointer, hotspot, output, scale);
+            assert_eq!(
+                pointer_physical - position,
+                Point::<i32, Physical>::from((5, 5)),
+                "pointer at {:?}",
+                pointer
+            );
+        }
+    }
+
+    #[test]
+    fn hotspot_relative_to_output() {
+        let left = Rectangle::from_loc_and_size((0, 0), (1920, 1080));
+        let right = Rectangle::from_loc_and_size((1920, 0), (1280, 720));
+        let hotspot = Point::from((4, 2));
+        let pointer = Point::from((1930.0, 10.0));
+
+        assert_eq!(
+            cursor_plane_position(pointer, hotspot, left, 1.0),
+            Point::from((1926, 8))
+        );
+        assert_eq!(
+            cursor_plane_position(pointer, hotspot, right, 2.0),
+            Point::from((12, 16))
+        );
+    }
+
+    #[test]
+    fn physical_hotspot_fractional_scales() {
+        let hotspot = Point::from((5, 7));
+        assert_eq!(cursor_physical_hotspot(hotspot, 1.0), Point::from((5, 7)));
+        assert_eq!(cursor_physical_hotspot(hotspot, 1.25), Point::from((6, 9)));
+        assert_eq!(cursor_physical_hotspot(hotspot, 1.5), Point::from((8, 11)));
+        assert_eq!(cursor_physical_hotspot(hotspot, 2.0), Point::from((10, 14)));
+    }
+
+    #[test]
+    fn changing_hotspot_between_frames() {
+        // animated cursor surfaces may move their hotspot with every commit
+        let output = Rectangle::from_loc_and_size((0, 0), (1280, 720));
+        let pointer = Point::from((100.0, 100.0));
+        for (frame, hotspot) in [(0, 0), (1, 1), (2, 2), (3, 3)].into_iter().enumerate() {
+            let position = cursor_plane_position(pointer, hotspot.into(), output, 2.0);
+            assert_eq!(
+                position,
+                Point::from((200 - 2 * frame as i32, 200 - 2 * frame as i32))
+            );
+        }
+    }
+
+    #[cfg(feature = "test_utils")]
+    mod surface {
+        use std::fs::File;
+
+        use wayland_client::{
+            protocol::{
+                wl_buffer::WlBuffer as ClientBuffer,
+                wl_compositor::WlCompositor,
+                wl_pointer::WlPointer,
+                wl_seat::WlSeat as ClientSeat,
+                wl_shm::{self, WlShm},
+                wl_shm_pool::WlShmPool,
+                wl_surface::WlSurface as ClientSurface,
+            },
+            Proxy,
+        };
+        use wayland_server::{
+            protocol::{wl_buffer::WlBuffer, wl_surface::WlSurface},
+            Resource,
+        };
+
+        use super::super::{cursor_plane_image, CursorPlaneBuffer, CursorPlaneImage};
+        use crate::{
+            backend::renderer::{utils::on_commit_buffer_handler, Texture},
+            input::{
+                pointer::{CursorImageStatus, MotionEvent},
+                Seat, SeatHandler, SeatState,
+            },
+            testing::{Recorded, TestClient, TestCompositor},
+            utils::{Point, SERIAL_COUNTER},
+            wayland::{
+                buffer::BufferHandler,
+                compositor::{CompositorHandler, CompositorState},
+                shm::{ShmHandler, ShmState},
+            },
+        };
+
+        struct State {
+            compositor_state: CompositorState,
+            shm_state: ShmState,
+            seat_state: SeatState<State>,
+            seat: Seat<State>,
+            cursor: CursorImageStatus,
+        }
+
+        impl CompositorHandler for State {
+            fn compositor_state(&mut self) -> &mut CompositorState {
+                &mut self.compositor_state
+            }
+
+            fn commit(&mut self, surface: &WlSurface) {
+                on_commit_buffer_handler(surface);
+            }
+        }
+
+        impl BufferHandler for State {
+            fn buffer_destroyed(&mut self, _buffer: &WlBuffer) {}
+        }
+
+        impl ShmHandler for State {
+            fn shm_state(&self) -> &ShmState {
+                &self.shm_state
+            }
+        }
+
+        impl SeatHandler for State {
+            type KeyboardFocus = WlSurface;
+            type PointerFocus = WlSurface;
+
+            fn seat_state(&mut self) -> &mut SeatState<Self> {
+                &mut self.seat_state
+            }
+
+            fn cursor_image(&mut self, _seat: &Seat<Self>, image: CursorImageStatus) {
+                self.cursor = image;
+            }
+        }
+
+        crate::delegate_compositor!(State);
+        crate::delegate_shm!(State);
+        crate::delegate_seat!(State);
+
+        /// A client with the pointer on one of its surfaces, using another one as its cursor
+        struct Cursor {
+            compositor: TestCompositor<State>,
+            client: TestClient,
+            pool: WlShmPool,
+            _pool_memory: File,
+            pointer: WlPointer,
+            surface: ClientSurface,
+        }
+
+        impl Cursor {
+            fn new() -> Cursor {
+                let mut compositor = TestCompositor::new(|dh, _| {
+                    let mut seat_state = SeatState::new();
+                    let mut seat = seat_state.new_wl_seat(dh, "seat");
+                    seat.add_pointer();
+                    State {
+                        compositor_state: CompositorState::new::<State>(dh),
+                        shm_state: ShmState::new::<State>(dh, vec![]),
+                        seat_state,
+                        seat,
+                        cursor: CursorImageStatus::Default,
+                    }
+                });
+                let mut client = compositor.connect();
+                let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+                let shm = client.bind::<WlShm, _>(&mut compositor);
+                let seat = client.bind::<ClientSeat, _>(&mut compositor);
+                let qh = client.queue_handle();
+                let (pool, pool_memory) = client.create_shm_pool(&shm, 4096);
+                let focus = wl_compositor.create_surface(&qh, Recorded);
+                let surface = wl_compositor.create_surface(&qh, Recorded);
+                let pointer = seat.get_pointer(&qh, Recorded);
+                client.roundtrip(&mut compositor);
+
+                // only the client with the pointer focus may set the cursor
+                let focus = client
+                    .client()
+                    .object_from_protocol_id::<WlSurface>(&compositor.handle(), focus.id().protocol_id())
+                    .unwrap();
+                let handle = compositor.state.seat.get_pointer().unwrap();
+                handle.motion(
+                    &mut compositor.state,
+                    Some((focus, (0, 0).into())),
+                    &MotionEvent {
+                        location: (10.0, 10.0).into(),
+                        serial: SERIAL_COUNTER.next_serial(),
+                        time: 0,
+                    },
+                );
+
+                Cursor {
+                    compositor,
+                    client,
+                    pool,
+                    _pool_memory: pool_memory,
+                    pointer,
+                    surface,
+                }
+            }
+
+            fn buffer(&self, offset: i32, size: i32) -> ClientBuffer {
+                self.pool.create_buffer(
+                    offset,
+                    size,
+                    size,
+                    size * 4,
+                    wl_shm::Format::Argb8888,
+                    &self.client.queue_handle(),
+                    Recorded,
+                )
+            }
+
+            fn commit(&mut self, buffer: &ClientBuffer, scale: i32) {
+                self.surface.attach(Some(buffer), 0, 0);
+                self.surface.set_buffer_scale(scale);
+                self.surface.commit();
+                self.client.roundtrip(&mut self.compositor);
+            }
+
+            fn set_hotspot(&mut self, hotspot: (i32, i32)) {
+                self.pointer
+                    .set_cursor(0, Some(&self.surface), hotspot.0, hotspot.1);
+                self.client.roundtrip(&mut self.compositor);
+            }
+
+            fn image(&self, scale: f64) -> CursorPlaneImage<'static, NoTexture> {
+                cursor_plane_image(&self.compositor.state.cursor, None, scale).unwrap()
+            }
+        }
+
+        /// Texture type of a compositor without a default cursor
+        #[derive(Debug)]
+        struct NoTexture;
+
+        impl Texture for NoTexture {
+            fn width(&self) -> u32 {
+                0
+            }
+            fn height(&self) -> u32 {
+                0
+            }
+        }
+
+        #[test]
+        fn buffer_scale_differing_from_output_scale() {
+            let mut cursor = Cursor::new();
+            let small = cursor.buffer(0, 8);
+            let large = cursor.buffer(8 * 8 * 4, 16);
+
+            // a buffer with scale 1 has to be scaled up for a 2x output
+            cursor.commit(&small, 1);
+            cursor.set_hotspot((2, 3));
+            let image = cursor.image(2.0);
+            assert_eq!(image.size, (16, 16).into());
+            assert_eq!(image.hotspot, Point::from((4, 6)));
+            assert!(image.needs_rendering);
+            assert!(
+                matches!(&image.buffer, CursorPlaneBuffer::Surface(buffer) if buffer.id().protocol_id() == small.id().protocol_id())
+            );
+            let image = cursor.image(1.0);
+            assert_eq!(image.size, (8, 8).into());
+            assert!(!image.needs_rendering);
+
+            // a buffer with scale 2 matches a 2x output, but no 1x or fractional one
+            cursor.commit(&large, 2);
+            let image = cursor.image(2.0);
+            assert_eq!(image.size, (16, 16).into());
+            assert_eq!(image.hotspot, Point::from((4, 6)));
+            assert!(!image.needs_rendering);
+            let image = cursor.image(1.0);
+            assert_eq!(image.size, (8, 8).into());
+            assert!(image.needs_rendering);
+            let image = cursor.image(1.5);
+            assert_eq!(image.size, (12, 12).into());
+            assert_eq!(image.hotspot, Point::from((3, 5)));
+            assert!(image.needs_rendering);
+        }
+
+        #[test]
+        fn animated_cursor_surface() {
+            let mut cursor = Cursor::new();
+            let frames = [cursor.buffer(0, 8), cursor.buffer(8 * 8 * 4, 16)];
+
+            // every frame commits a new buffer and moves the hotspot
+            for frame in 0..8 {
+                let size = if frame % 2 == 0 { 8 } else { 16 };
+                cursor.commit(&frames[frame % 2], 1);
+                cursor.set_hotspot((frame as i32, frame as i32));
+
+                let image = cursor.image(2.0);
+                assert_eq!(image.size, (2 * size, 2 * size).into(), "frame {}", frame);
+                assert_eq!(
+                    image.hotspot,
+                    Point::from((2 * frame as i32, 2 * frame as i32)),
+                    "frame {}",
+                    frame
+                );
+                assert!(
+                    matches!(&image.buffer, CursorPlaneBuffer::Surface(buffer) if buffer.id().protocol_id() == frames[frame % 2].id().protocol_id()),
+                    "frame {}",
+                    frame
+                );
+            }
+        }
+    }
+}
@@ -12,6 +12,7 @@
 //! - [`texture`](crate::backend::renderer::element::texture) - Texture based render element
 //! - [`surface`](crate::backend::renderer::element::surface) - Wayland surface render element
 //! - [`solid`](crate::backend::renderer::element::solid) - Solid color render element
+//! - [`cursor`](crate::backend::renderer::element::cursor) - Pointer cursor render element
 //!
 //! The [`render_elements!`] macro provides an easy way to aggregate multiple different [RenderElement]s
 //! into a single enum.
@@ -19,7 +20,7 @@
 //! See the [`damage`](crate::backend::renderer::damage) module for more information on
 //! damage tracking.
 
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, os::unix::io::OwnedFd, sync::Arc};
 
 #[cfg(feature = "wayland_frontend")]
 use wayland_server::{backend::ObjectId, Resource};
@@ -33,6 +34,9 @@ use crate::{
 use super::utils::Buffer;
 use super::{utils::CommitCounter, Renderer};
 
+pub mod atlas;
+#[cfg(feature = "wayland_frontend")]
+pub mod cursor;
 pub mod memory;
 pub mod solid;
 #[cfg(feature = "wayland_frontend")]
@@ -367,6 +371,13 @@ pub trait RenderElement<R: Renderer>: Element {
         let _ = renderer;
         None
     }
+
+    /// Get a sync file signaled once the underlying storage is ready to be read
+    ///
+    /// Used as the `IN_FENCE_FD` when the element is assigned to a drm plane.
+    fn underlying_storage_fence(&self) -> Option<OwnedFd> {
+        None
+    }
 }
 
 /// Types that can be converted into [`RenderElement`]s
@@ -435,6 +446,10 @@ where
         (*self).underlying_storage(renderer)
     }
 
+    fn underlying_storage_fence(&self) -> Option<OwnedFd> {
+        (*self).underlying_storage_fence()
+    }
+
     fn draw<'a>(
         &self,
         frame: &mut <R as Renderer>::Frame<'a>,
@@ -733,6 +748,20 @@ macro_rules! render_elements_internal {
                 Self::_GenericCatcher(_) => unreachable!(),
             }
         }
+
+        fn underlying_storage_fence(&self) -> Option<std::os::unix::io::OwnedFd>
+        {
+            match self {
+                $(
+                    #[allow(unused_doc_comments)]
+                    $(
+                        #[$meta]
+                    )*
+                    Self::$body(x) => $crate::render_elements_internal!(@call $renderer $(as $other_renderer)?; underlying_storage_fence; x)
+                ),*,
+                Self::_GenericCatcher(_) => unreachable!(),
+            }
+        }
     };
     (@draw $renderer:ty; $($(#[$meta:meta])* $body:ident=$field:ty $(as <$other_renderer:ty>)?),* $(,)?) => {
         fn draw<'frame>(
@@ -768,6 +797,20 @@ macro_rules! render_elements_internal {
                 Self::_GenericCatcher(_) => unreachable!(),
             }
         }
+
+        fn underlying_storage_fence(&self) -> Option<std::os::unix::io::OwnedFd>
+        {
+            match self {
+                $(
+                    #[allow(unused_doc_comments)]
+                    $(
+                        #[$meta]
+                    )*
+                    Self::$body(x) => $crate::render_elements_internal!(@call $renderer $(as $other_renderer)?; underlying_storage_fence; x)
+                ),*,
+                Self::_GenericCatcher(_) => unreachable!(),
+            }
+        }
     };
     // Generic renderer
     (@impl $name:ident<$renderer:ident> $(where $($target:ty: $bound:tt $(+ $additional_bound:tt)*),+)?; $($tail:tt)*) => {
@@ -1393,6 +1436,10 @@ where
     fn underlying_storage(&self, renderer: &mut R) -> Option<UnderlyingStorage> {
         self.0.underlying_storage(renderer)
     }
+
+    fn underlying_storage_fence(&self) -> Option<OwnedFd> {
+        self.0.underlying_storage_fence()
+    }
 }
 
 #[cfg(all(test, feature = "renderer_gl"))]
@@ -474,6 +474,23 @@ where
         })
     }
 
+    #[cfg(feature = "backend_drm")]
+    fn underlying_storage_fence(&self) -> Option<std::os::unix::io::OwnedFd> {
+        use crate::wayland::drm_syncobj::DrmSyncobjCachedState;
+
+        compositor::with_states(&self.surface, |states| {
+            let acquire_point = states
+                .cached_state
+                .current::<DrmSyncobjCachedState>()
+                .acquire_point
+                .clone()?;
+            acquire_point
+                .export_sync_file()
+                .map_err(|err| warn!(?err, "Failed to export drm syncobj acquire point"))
+                .ok()
+        })
+    }
+
     #[instrument(level = "trace", skip(frame))]
     fn draw<'a>(
         &self,
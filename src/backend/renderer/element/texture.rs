@@ -430,6 +430,21 @@ impl<T> TextureBuffer<T> {
             opaque_regions,
         ))
     }
+
+    /// The texture of this buffer
+    pub fn texture(&self) -> &T {
+        &self.texture
+    }
+
+    /// The scale of this buffer
+    pub fn scale(&self) -> i32 {
+        self.scale
+    }
+
+    /// The transform of this buffer
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
 }
 
 /// A texture backed render buffer
@@ -1,5 +1,7 @@
 //! Utilities and helpers around the `Element` trait.
 
+use std::os::unix::io::OwnedFd;
+
 use crate::{
     backend::renderer::{
         element::{AsRenderElements, Element, Id, RenderElement, UnderlyingStorage},
@@ -100,6 +102,10 @@ impl<R: Renderer, E: RenderElement<R>> RenderElement<R> for RescaleRenderElement
     fn underlying_storage(&self, renderer: &mut R) -> Option<UnderlyingStorage> {
         self.element.underlying_storage(renderer)
     }
+
+    fn underlying_storage_fence(&self) -> Option<OwnedFd> {
+        self.element.underlying_storage_fence()
+    }
 }
 
 /// A element that allows to crop another element
@@ -274,6 +280,10 @@ impl<R: Renderer, E: RenderElement<R>> RenderElement<R> for CropRenderElement<E>
     fn underlying_storage(&self, renderer: &mut R) -> Option<UnderlyingStorage> {
         self.element.underlying_storage(renderer)
     }
+
+    fn underlying_storage_fence(&self) -> Option<OwnedFd> {
+        self.element.underlying_storage_fence()
+    }
 }
 
 /// Defines how the location parameter should apply in [`RelocateRenderElement::from_element`]
@@ -366,6 +376,10 @@ impl<R: Renderer, E: RenderElement<R>> RenderElement<R> for RelocateRenderElemen
     fn underlying_storage(&self, renderer: &mut R) -> Option<UnderlyingStorage> {
         self.element.underlying_storage(renderer)
     }
+
+    fn underlying_storage_fence(&self) -> Option<OwnedFd> {
+        self.element.underlying_storage_fence()
+    }
 }
 
 /// Defines the scale behavior for the constrain
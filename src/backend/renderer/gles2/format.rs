@@ -0,0 +1,264 @@
+use crate::{
+    backend::allocator::Fourcc,
+    utils::{Buffer as BufferCoord, Rectangle},
+};
+
+use super::ffi;
+
+/// GL parameters to upload a given memory format into a texture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlUploadFormat {
+    /// Internal format of the texture
+    pub internal: ffi::types::GLenum,
+    /// Format of the pixel data
+    pub format: ffi::types::GLenum,
+    /// Data type of the pixel data
+    pub data_type: ffi::types::GLenum,
+    /// Index of the shader variant used to sample the texture
+    pub shader_idx: usize,
+}
+
+impl GlUploadFormat {
+    /// Tightly packed 8-bit RGBA, as used for [`ImportMem`](crate::backend::renderer::ImportMem)
+    pub const RGBA8: GlUploadFormat = GlUploadFormat::new(ffi::RGBA, ffi::RGBA, ffi::UNSIGNED_BYTE, 0);
+
+    const fn new(
+        internal: ffi::types::GLenum,
+        format: ffi::types::GLenum,
+        data_type: ffi::types::GLenum,
+        shader_idx: usize,
+    ) -> Self {
+        GlUploadFormat {
+            internal,
+            format,
+            data_type,
+            shader_idx,
+        }
+    }
+}
+
+/// Returns the GL parameters to upload a shm buffer of the given format, if supported
+///
+/// 10-bit formats require GLES 3.0 or `GL_EXT_texture_type_2_10_10_10_REV`, other 10-bit
+/// layouts than ABGR/XBGR have no matching GL type without swizzling and are not supported.
+#[cfg(feature = "wayland_frontend")]
+pub fn shm_format_to_gl(
+    format: wayland_server::protocol::wl_shm::Format,
+    gl_version: super::version::GlVersion,
+    extensions: &[String],
+) -> Option<GlUploadFormat> {
+    use super::version;
+    use wayland_server::protocol::wl_shm::Format;
+
+    let has_2_10_10_10 = gl_version >= version::GLES_3_0
+        || extensions
+            .iter()
+            .any(|ext| ext == "GL_EXT_texture_type_2_10_10_10_REV");
+    // GLES 2.0 requires the internal format to match the format
+    let internal_2_10_10_10 = if gl_version >= version::GLES_3_0 {
+        ffi::RGB10_A2
+    } else {
+        ffi::RGBA
+    };
+
+    match format {
+        Format::Abgr8888 => Some(GlUploadFormat::new(ffi::RGBA, ffi::RGBA, ffi::UNSIGNED_BYTE, 0)),
+        Format::Xbgr8888 => Some(GlUploadFormat::new(ffi::RGBA, ffi::RGBA, ffi::UNSIGNED_BYTE, 1)),
+        Format::Argb8888 => Some(GlUploadFormat::new(
+            ffi::BGRA_EXT,
+            ffi::BGRA_EXT,
+            ffi::UNSIGNED_BYTE,
+            0,
+        )),
+        Format::Xrgb8888 => Some(GlUploadFormat::new(
+            ffi::BGRA_EXT,
+            ffi::BGRA_EXT,
+            ffi::UNSIGNED_BYTE,
+            1,
+        )),
+        Format::Abgr2101010 if has_2_10_10_10 => Some(GlUploadFormat::new(
+            internal_2_10_10_10,
+            ffi::RGBA,
+            ffi::UNSIGNED_INT_2_10_10_10_REV,
+            0,
+        )),
+        Format::Xbgr2101010 if has_2_10_10_10 => Some(GlUploadFormat::new(
+            internal_2_10_10_10,
+            ffi::RGBA,
+            ffi::UNSIGNED_INT_2_10_10_10_REV,
+            1,
+        )),
+        _ => None,
+    }
+}
+
+/// Returns the GL parameters to upload the contents of a linear dmabuf of the given format, if supported
+///
+/// Used to import dmabufs through the CPU, if the EGL driver fails to import them.
+/// Only single-plane 8-bit RGB formats are supported.
+pub fn dmabuf_format_to_gl(format: Fourcc) -> Option<GlUploadFormat> {
+    match format {
+        Fourcc::Abgr8888 => Some(GlUploadFormat::new(ffi::RGBA, ffi::RGBA, ffi::UNSIGNED_BYTE, 0)),
+        Fourcc::Xbgr8888 => Some(GlUploadFormat::new(ffi::RGBA, ffi::RGBA, ffi::UNSIGNED_BYTE, 1)),
+        Fourcc::Argb8888 => Some(GlUploadFormat::new(
+            ffi::BGRA_EXT,
+            ffi::BGRA_EXT,
+            ffi::UNSIGNED_BYTE,
+            0,
+        )),
+        Fourcc::Xrgb8888 => Some(GlUploadFormat::new(
+            ffi::BGRA_EXT,
+            ffi::BGRA_EXT,
+            ffi::UNSIGNED_BYTE,
+            1,
+        )),
+        _ => None,
+    }
+}
+
+/// Copies `region` out of a buffer with the given stride into a tightly packed buffer
+///
+/// Used to upload sub-regions of a buffer, if the GL implementation does not support
+/// `GL_UNPACK_ROW_LENGTH` (neither GLES 3.0 nor `GL_EXT_unpack_subimage`), or if the
+/// stride is not a multiple of the pixel size.
+pub fn pack_region(
+    data: &[u8],
+    stride: usize,
+    pixelsize: usize,
+    region: Rectangle<i32, BufferCoord>,
+) -> Vec<u8> {
+    let mut packed = vec![0u8; region.size.w as usize * pixelsize * region.size.h as usize];
+    copy_region(data, stride, pixelsize, region, &mut packed);
+    packed
+}
+
+/// Copies `region` out of a buffer with the given stride into `dst` without any padding
+///
+/// `dst` has to be at least `region.size.w * region.size.h * pixelsize` bytes long.
+pub fn copy_region(
+    data: &[u8],
+    stride: usize,
+    pixelsize: usize,
+    region: Rectangle<i32, BufferCoord>,
+    dst: &mut [u8],
+) {
+    let row_len = region.size.w as usize * pixelsize;
+    for (i, row) in (region.loc.y as usize..(region.loc.y + region.size.h) as usize).enumerate() {
+        let start = row * stride + region.loc.x as usize * pixelsize;
+        dst[i * row_len..(i + 1) * row_len].copy_from_slice(&data[start..start + row_len]);
+    }
+}
+
+/// Returns `true` if a region of a buffer with the given stride can be uploaded
+/// straight from the buffer using `GL_UNPACK_ROW_LENGTH`
+pub fn can_unpack_subimage(supports_unpack_subimage: bool, stride: usize, pixelsize: usize) -> bool {
+    supports_unpack_subimage && stride % pixelsize == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{can_unpack_subimage, dmabuf_format_to_gl, pack_region};
+    use crate::{backend::allocator::Fourcc, utils::Rectangle};
+
+    const PIXELSIZE: usize = 4;
+
+    fn buffer(width: usize, height: usize, stride: usize) -> Vec<u8> {
+        let mut data = vec![0xffu8; stride * height];
+        for y in 0..height {
+            for x in 0..width * PIXELSIZE {
+                data[y * stride + x] = (y * width * PIXELSIZE + x) as u8;
+            }
+        }
+        data
+    }
+
+    // reference implementation of the GL unpack state used with `GL_EXT_unpack_subimage`
+    fn gl_unpack(
+        data: &[u8],
+        row_length: usize,
+        skip_pixels: usize,
+        skip_rows: usize,
+        width: usize,
+        height: usize,
+    ) -> Vec<u8> {
+        let mut out = Vec::new();
+        for row in skip_rows..skip_rows + height {
+            let start = (row * row_length + skip_pixels) * PIXELSIZE;
+            out.extend_from_slice(&data[start..start + width * PIXELSIZE]);
+        }
+        out
+    }
+
+    #[test]
+    fn packed_full_upload_with_padded_stride() {
+        let (width, height, stride) = (5, 3, 32);
+        let data = buffer(width, height, stride);
+        let packed = pack_region(
+            &data,
+            stride,
+            PIXELSIZE,
+            Rectangle::from_loc_and_size((0, 0), (width as i32, height as i32)),
+        );
+        assert_eq!(packed.len(), width * height * PIXELSIZE);
+        assert_eq!(packed, gl_unpack(&data, stride / PIXELSIZE, 0, 0, width, height));
+        // padding must not leak into the packed data
+        assert!(!packed.contains(&0xff));
+    }
+
+    #[test]
+    fn packed_partial_upload_matches_unpack_subimage() {
+        let (width, height, stride) = (8, 6, 40);
+        let data = buffer(width, height, stride);
+        let region = Rectangle::from_loc_and_size((3, 2), (4, 3));
+
+        assert!(can_unpack_subimage(true, stride, PIXELSIZE));
+        let unpacked = gl_unpack(&data, stride / PIXELSIZE, 3, 2, 4, 3);
+        assert_eq!(pack_region(&data, stride, PIXELSIZE, region), unpacked);
+    }
+
+    #[test]
+    fn tight_stride_is_identity() {
+        let (width, height) = (4, 4);
+        let stride = width * PIXELSIZE;
+        let data = buffer(width, height, stride);
+        let packed = pack_region(
+            &data,
+            stride,
+            PIXELSIZE,
+            Rectangle::from_loc_and_size((0, 0), (width as i32, height as i32)),
+        );
+        assert_eq!(packed, data);
+    }
+
+    #[test]
+    fn unaligned_stride_requires_packing() {
+        assert!(!can_unpack_subimage(true, 4 * PIXELSIZE + 2, PIXELSIZE));
+        assert!(!can_unpack_subimage(false, 4 * PIXELSIZE, PIXELSIZE));
+
+        let (width, height, stride) = (4, 3, 4 * PIXELSIZE + 2);
+        let data = buffer(width, height, stride);
+        let packed = pack_region(
+            &data,
+            stride,
+            PIXELSIZE,
+            Rectangle::from_loc_and_size((1, 1), (2, 2)),
+        );
+        let expected = [
+            &data[stride + PIXELSIZE..stride + 3 * PIXELSIZE],
+            &data[2 * stride + PIXELSIZE..2 * stride + 3 * PIXELSIZE],
+        ]
+        .concat();
+        assert_eq!(packed, expected);
+    }
+
+    #[test]
+    fn dmabuf_fallback_formats() {
+        let argb = dmabuf_format_to_gl(Fourcc::Argb8888).unwrap();
+        let xrgb = dmabuf_format_to_gl(Fourcc::Xrgb8888).unwrap();
+        assert_eq!(argb.format, xrgb.format);
+        // opaque formats ignore the alpha channel in the shader
+        assert_ne!(argb.shader_idx, xrgb.shader_idx);
+        // multi-planar formats need a conversion, which is left to EGL
+        assert!(dmabuf_format_to_gl(Fourcc::Nv12).is_none());
+    }
+}
@@ -22,6 +22,7 @@ use tracing::{debug, error, info, info_span, instrument, span, span::EnteredSpan
 use std::cell::RefCell;
 
 pub mod element;
+mod format;
 mod shaders;
 mod uniform;
 mod version;
@@ -38,7 +39,7 @@ use crate::backend::allocator::{
 };
 use crate::backend::egl::{
     ffi::egl::{self as ffi_egl, types::EGLImage},
-    EGLContext, EGLSurface, MakeCurrentError,
+    EGLContext, EGLDisplay, EGLSurface, MakeCurrentError,
 };
 use crate::backend::SwapBuffersError;
 use crate::utils::{Buffer as BufferCoord, Physical, Rectangle, Size, Transform};
@@ -59,6 +60,20 @@ pub mod ffi {
 
 crate::utils::ids::id_gen!(next_renderer_id, RENDERER_ID, RENDERER_IDS);
 
+/// Shm formats the renderer can import, if supported by the GL implementation
+#[cfg(feature = "wayland_frontend")]
+const SUPPORTED_SHM_FORMATS: &[wl_shm::Format] = &[
+    wl_shm::Format::Abgr8888,
+    wl_shm::Format::Xbgr8888,
+    wl_shm::Format::Argb8888,
+    wl_shm::Format::Xrgb8888,
+    wl_shm::Format::Abgr2101010,
+    wl_shm::Format::Xbgr2101010,
+];
+
+/// Default maximum amount of imported dmabufs kept in the cache of a [`Gles2Renderer`]
+pub const DEFAULT_DMABUF_CACHE_SIZE: usize = 64;
+
 #[derive(Debug)]
 struct Gles2TexProgramInternal {
     program: ffi::types::GLuint,
@@ -187,6 +202,30 @@ impl Gles2Texture {
     pub fn tex_id(&self) -> ffi::types::GLuint {
         self.0.texture
     }
+
+    /// The [`EGLImage`] backing this texture, if any
+    ///
+    /// Only textures imported from a dmabuf or an EGL buffer are backed by an [`EGLImage`].
+    /// The image is owned by the texture and stays valid until the texture is dropped.
+    pub fn as_egl_image(&self) -> Option<EGLImage> {
+        self.0
+            .egl_images
+            .as_ref()
+            .and_then(|images| images.first().copied())
+            .filter(|image| *image != ffi_egl::NO_IMAGE_KHR)
+    }
+
+    /// Export the [`EGLImage`] backing this texture as a [`Dmabuf`]
+    ///
+    /// This requires the `EGL_MESA_image_dma_buf_export` extension and only works for textures
+    /// backed by an [`EGLImage`] (see [`as_egl_image`](Gles2Texture::as_egl_image)).
+    /// Other textures can be exported with [`ExportDma::export_texture`].
+    pub fn export_dmabuf(&self, display: &EGLDisplay) -> Result<Dmabuf, crate::backend::egl::Error> {
+        let image = self
+            .as_egl_image()
+            .ok_or(crate::backend::egl::Error::NoEGLImage)?;
+        display.create_dmabuf_from_image(image, self.0.size, self.0.y_inverted)
+    }
 }
 
 #[derive(Debug)]
@@ -221,7 +260,9 @@ enum CleanupResource {
     RenderbufferObject(ffi::types::GLuint),
     EGLImage(EGLImage),
     Mapping(ffi::types::GLuint, *const nix::libc::c_void),
+    PixelBuffer(ffi::types::GLuint),
     Program(ffi::types::GLuint),
+    Sync(ffi::types::GLsync),
 }
 
 impl Texture for Gles2Texture {
@@ -271,6 +312,102 @@ impl Drop for Gles2Mapping {
     }
 }
 
+/// Contents of a shm buffer staged in a pixel buffer object
+///
+/// Created by [`Gles2Renderer::upload_shm_to_pbo`] and turned into a texture
+/// by [`Gles2Renderer::pbo_to_texture`].
+#[cfg(feature = "wayland_frontend")]
+#[derive(Debug)]
+pub struct Gles2Pbo {
+    pbo: ffi::types::GLuint,
+    size: Size<i32, BufferCoord>,
+    upload_format: format::GlUploadFormat,
+    destruction_callback_sender: Sender<CleanupResource>,
+}
+
+#[cfg(feature = "wayland_frontend")]
+impl Gles2Pbo {
+    /// Size of the staged buffer contents
+    pub fn size(&self) -> Size<i32, BufferCoord> {
+        self.size
+    }
+}
+
+#[cfg(feature = "wayland_frontend")]
+impl Drop for Gles2Pbo {
+    fn drop(&mut self) {
+        let _ = self
+            .destruction_callback_sender
+            .send(CleanupResource::PixelBuffer(self.pbo));
+    }
+}
+
+/// GL fence sync object
+///
+/// Signaled once all commands submitted on the context that created it before the fence
+/// have completed. Fences can be waited on by any context sharing resources with the
+/// creating context, see [`EGLContext::create_shared`].
+///
+/// Dropping a fence that was not waited on frees it the next time the renderer
+/// that created it cleans up its resources.
+#[derive(Debug)]
+pub struct Gles2Fence {
+    sync: ffi::types::GLsync,
+    destruction_callback_sender: Sender<CleanupResource>,
+}
+
+// SAFETY: sync objects are shared between all contexts of a share group and
+// are only accessed through a current context of that group. The sender is only
+// used to hand the sync object back to the renderer that created it.
+unsafe impl Send for Gles2Fence {}
+
+impl Drop for Gles2Fence {
+    fn drop(&mut self) {
+        if !self.sync.is_null() {
+            let _ = self
+                .destruction_callback_sender
+                .send(CleanupResource::Sync(self.sync));
+        }
+    }
+}
+
+/// Texture uploaded on a shared context, which can be moved to the rendering thread
+///
+/// Created by [`Gles2Renderer::upload_shm_shared`] and has to be imported into the renderer
+/// of the context it was shared with using [`Gles2Renderer::import_shared_texture`].
+/// Dropping it without importing frees the texture the next time the renderer that
+/// uploaded it cleans up its resources.
+#[derive(Debug)]
+pub struct Gles2SharedTexture {
+    texture: ffi::types::GLuint,
+    texture_kind: usize,
+    size: Size<i32, BufferCoord>,
+    fence: Option<Gles2Fence>,
+    destruction_callback_sender: Sender<CleanupResource>,
+}
+
+// SAFETY: textures are shared between all contexts of a share group, the sender is
+// only used to hand the texture back to the renderer that uploaded it.
+unsafe impl Send for Gles2SharedTexture {}
+
+impl Gles2SharedTexture {
+    /// Size of the texture
+    pub fn size(&self) -> Size<i32, BufferCoord> {
+        self.size
+    }
+}
+
+impl Drop for Gles2SharedTexture {
+    fn drop(&mut self) {
+        // the texture is owned by the importing renderer otherwise
+        if self.fence.is_some() {
+            let _ = self
+                .destruction_callback_sender
+                .send(CleanupResource::Texture(self.texture));
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Gles2Buffer {
     dmabuf: WeakDmabuf,
@@ -348,7 +485,12 @@ pub struct Gles2Renderer {
     tex_program: Gles2TexProgram,
     solid_program: Gles2SolidProgram,
 
-    dmabuf_cache: std::collections::HashMap<WeakDmabuf, Gles2Texture>,
+    // ordered from least to most recently used
+    dmabuf_cache: indexmap::IndexMap<WeakDmabuf, Gles2Texture>,
+    dmabuf_cache_size: usize,
+    dmabuf_import_failures: HashMap<Format, usize>,
+    dmabuf_import_failure_hook: Option<Box<dyn FnMut(&Dmabuf, &Gles2Error) -> bool>>,
+    disabled_dmabuf_formats: HashSet<Format>,
     egl: EGLContext,
     #[cfg(all(feature = "wayland_frontend", feature = "use_system_lib"))]
     egl_reader: Option<EGLBufferReader>,
@@ -362,6 +504,9 @@ pub struct Gles2Renderer {
     min_filter: TextureFilter,
     max_filter: TextureFilter,
     supports_instancing: bool,
+    supports_unpack_subimage: bool,
+    #[cfg(feature = "wayland_frontend")]
+    shm_formats: Vec<wl_shm::Format>,
     debug_flags: DebugFlags,
     _not_send: *mut (),
     span: tracing::Span,
@@ -413,6 +558,9 @@ impl fmt::Debug for Gles2Renderer {
             .field("tex_program", &self.tex_program)
             .field("solid_program", &self.solid_program)
             .field("dmabuf_cache", &self.dmabuf_cache)
+            .field("dmabuf_cache_size", &self.dmabuf_cache_size)
+            .field("dmabuf_import_failures", &self.dmabuf_import_failures)
+            .field("disabled_dmabuf_formats", &self.disabled_dmabuf_formats)
             .field("egl", &self.egl)
             .field("gl_version", &self.gl_version)
             // ffi::Gles2 does not implement Debug
@@ -420,6 +568,7 @@ impl fmt::Debug for Gles2Renderer {
             .field("min_filter", &self.min_filter)
             .field("max_filter", &self.max_filter)
             .field("supports_instancing", &self.supports_instancing)
+            .field("supports_unpack_subimage", &self.supports_unpack_subimage)
             .finish()
     }
 }
@@ -456,6 +605,19 @@ pub enum Gles2Error {
     ///The given dmabuf could not be converted to an EGLImage for framebuffer use
     #[error("Failed to convert between dmabuf and EGLImage")]
     BindBufferEGLError(#[source] crate::backend::egl::Error),
+    /// The given dmabuf could not be imported by the EGL driver
+    #[error("Failed to import dmabuf (format: {format:?}, size: {size:?}, planes: {planes:?})")]
+    DmabufImportFailed {
+        /// Format and modifier of the dmabuf
+        format: Format,
+        /// Size of the dmabuf
+        size: Size<i32, BufferCoord>,
+        /// Offset and stride of every plane of the dmabuf
+        planes: Vec<(u32, u32)>,
+        /// Error returned by the EGL driver
+        #[source]
+        source: crate::backend::egl::Error,
+    },
     /// The given buffer has an unsupported pixel format
     #[error("Unsupported pixel format: {0:?}")]
     #[cfg(feature = "wayland_frontend")]
@@ -510,6 +672,7 @@ impl From<Gles2Error> for SwapBuffersError {
             Gles2Error::ContextActivationError(err) => err.into(),
             x @ Gles2Error::FramebufferBindingError
             | x @ Gles2Error::BindBufferEGLError(_)
+            | x @ Gles2Error::DmabufImportFailed { .. }
             | x @ Gles2Error::UnsupportedPixelFormat(_)
             | x @ Gles2Error::BufferAccessError(_)
             | x @ Gles2Error::MappingError
@@ -539,7 +702,8 @@ impl From<Gles2Error> for SwapBuffersError {
             | x @ Gles2Error::CreateShaderObject
             | x @ Gles2Error::UniformTypeMismatch { .. }
             | x @ Gles2Error::UnknownUniform(_)
-            | x @ Gles2Error::BindBufferEGLError(_) => SwapBuffersError::TemporaryFailure(Box::new(x)),
+            | x @ Gles2Error::BindBufferEGLError(_)
+            | x @ Gles2Error::DmabufImportFailed { .. } => SwapBuffersError::TemporaryFailure(Box::new(x)),
         }
     }
 }
@@ -792,7 +956,9 @@ impl Gles2Renderer {
 
         context.make_current()?;
 
-        let (gl, gl_version, exts, supports_instancing, gl_debug_span) = {
+        #[cfg(feature = "wayland_frontend")]
+        let shm_formats;
+        let (gl, gl_version, exts, supports_instancing, supports_unpack_subimage, gl_debug_span) = {
             let gl = ffi::Gles2::load_with(|s| crate::backend::egl::get_proc_address(s) as *const _);
             let ext_ptr = gl.GetString(ffi::EXTENSIONS) as *const c_char;
             if ext_ptr.is_null() {
@@ -831,9 +997,20 @@ impl Gles2Renderer {
                     "GL_EXT_texture_format_BGRA8888",
                 ]));
             }
-            // required for buffers without linear memory layout
-            if gl_version < version::GLES_3_0 && !exts.iter().any(|ext| ext == "GL_EXT_unpack_subimage") {
-                return Err(Gles2Error::GLExtensionNotSupported(&["GL_EXT_unpack_subimage"]));
+            // allows uploading sub-regions of buffers straight from memory,
+            // without it regions are copied into a packed buffer before uploading
+            let supports_unpack_subimage =
+                gl_version >= version::GLES_3_0 || exts.iter().any(|ext| ext == "GL_EXT_unpack_subimage");
+            if !supports_unpack_subimage {
+                info!("GL_EXT_unpack_subimage not supported, falling back to packed uploads");
+            }
+            #[cfg(feature = "wayland_frontend")]
+            {
+                shm_formats = SUPPORTED_SHM_FORMATS
+                    .iter()
+                    .copied()
+                    .filter(|format| format::shm_format_to_gl(*format, gl_version, &exts).is_some())
+                    .collect::<Vec<_>>();
             }
             // Check if GPU supports instanced rendering.
             let supports_instancing = gl_version >= version::GLES_3_0
@@ -850,7 +1027,14 @@ impl Gles2Renderer {
                 None
             };
 
-            (gl, gl_version, exts, supports_instancing, gl_debug_span)
+            (
+                gl,
+                gl_version,
+                exts,
+                supports_instancing,
+                supports_unpack_subimage,
+                gl_debug_span,
+            )
         };
 
         let (tx, rx) = channel();
@@ -891,13 +1075,20 @@ impl Gles2Renderer {
             solid_program,
             target: None,
             buffers: Vec::new(),
-            dmabuf_cache: std::collections::HashMap::new(),
+            dmabuf_cache: indexmap::IndexMap::new(),
+            dmabuf_cache_size: DEFAULT_DMABUF_CACHE_SIZE,
+            dmabuf_import_failures: HashMap::new(),
+            dmabuf_import_failure_hook: None,
+            disabled_dmabuf_formats: HashSet::new(),
             destruction_callback: rx,
             destruction_callback_sender: tx,
             vbos,
             min_filter: TextureFilter::Linear,
             max_filter: TextureFilter::Linear,
             supports_instancing,
+            supports_unpack_subimage,
+            #[cfg(feature = "wayland_frontend")]
+            shm_formats,
             debug_flags: DebugFlags::empty(),
             _not_send: std::ptr::null_mut(),
             span,
@@ -972,9 +1163,15 @@ impl Gles2Renderer {
                     }
                     self.gl.DeleteBuffers(1, &pbo);
                 },
+                CleanupResource::PixelBuffer(pbo) => unsafe {
+                    self.gl.DeleteBuffers(1, &pbo);
+                },
                 CleanupResource::Program(program) => unsafe {
                     self.gl.DeleteProgram(program);
                 },
+                CleanupResource::Sync(sync) => unsafe {
+                    self.gl.DeleteSync(sync);
+                },
             }
         }
     }
@@ -1003,20 +1200,15 @@ impl ImportMemWl for Gles2Renderer {
             let height = data.height as i32;
             let stride = data.stride as i32;
 
-            // number of bytes per pixel
-            // TODO: compute from data.format
+            // number of bytes per pixel, all supported formats are 32 bits wide
             let pixelsize = 4i32;
 
             // ensure consistency, the SHM handler of smithay should ensure this
             assert!((offset + (height - 1) * stride + width * pixelsize) as usize <= len);
 
-            let (gl_format, shader_idx) = match data.format {
-                wl_shm::Format::Abgr8888 => (ffi::RGBA, 0),
-                wl_shm::Format::Xbgr8888 => (ffi::RGBA, 1),
-                wl_shm::Format::Argb8888 => (ffi::BGRA_EXT, 0),
-                wl_shm::Format::Xrgb8888 => (ffi::BGRA_EXT, 1),
-                format => return Err(Gles2Error::UnsupportedPixelFormat(format)),
-            };
+            let upload_format = format::shm_format_to_gl(data.format, self.gl_version, &self.extensions)
+                .ok_or(Gles2Error::UnsupportedPixelFormat(data.format))?;
+            let shader_idx = upload_format.shader_idx;
 
             let mut upload_full = false;
 
@@ -1069,43 +1261,18 @@ impl ImportMemWl for Gles2Renderer {
                     .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_S, ffi::CLAMP_TO_EDGE as i32);
                 self.gl
                     .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_T, ffi::CLAMP_TO_EDGE as i32);
-                self.gl.PixelStorei(ffi::UNPACK_ROW_LENGTH, stride / pixelsize);
-
+                let pixels = slice::from_raw_parts(ptr.offset(offset as isize), len - offset as usize);
+                let full = Rectangle::from_loc_and_size((0, 0), (width, height));
                 if upload_full || damage.is_empty() {
                     trace!("Uploading shm texture");
-                    self.gl.TexImage2D(
-                        ffi::TEXTURE_2D,
-                        0,
-                        gl_format as i32,
-                        width,
-                        height,
-                        0,
-                        gl_format,
-                        ffi::UNSIGNED_BYTE as u32,
-                        ptr.offset(offset as isize) as *const _,
-                    );
+                    self.upload_region(pixels, stride, pixelsize, full, upload_format, true);
                 } else {
-                    for region in damage.iter() {
+                    for region in damage.iter().filter_map(|region| region.intersection(full)) {
                         trace!("Uploading partial shm texture");
-                        self.gl.PixelStorei(ffi::UNPACK_SKIP_PIXELS, region.loc.x);
-                        self.gl.PixelStorei(ffi::UNPACK_SKIP_ROWS, region.loc.y);
-                        self.gl.TexSubImage2D(
-                            ffi::TEXTURE_2D,
-                            0,
-                            region.loc.x,
-                            region.loc.y,
-                            region.size.w,
-                            region.size.h,
-                            gl_format,
-                            ffi::UNSIGNED_BYTE as u32,
-                            ptr.offset(offset as isize) as *const _,
-                        );
-                        self.gl.PixelStorei(ffi::UNPACK_SKIP_PIXELS, 0);
-                        self.gl.PixelStorei(ffi::UNPACK_SKIP_ROWS, 0);
+                        self.upload_region(pixels, stride, pixelsize, region, upload_format, false);
                     }
                 }
 
-                self.gl.PixelStorei(ffi::UNPACK_ROW_LENGTH, 0);
                 self.gl.BindTexture(ffi::TEXTURE_2D, 0);
             }
 
@@ -1115,12 +1282,276 @@ impl ImportMemWl for Gles2Renderer {
     }
 
     fn shm_formats(&self) -> &[wl_shm::Format] {
-        &[
-            wl_shm::Format::Abgr8888,
-            wl_shm::Format::Xbgr8888,
-            wl_shm::Format::Argb8888,
-            wl_shm::Format::Xrgb8888,
-        ]
+        &self.shm_formats
+    }
+}
+
+#[cfg(feature = "wayland_frontend")]
+impl Gles2Renderer {
+    /// Copy the contents of a shm buffer into a pixel buffer object
+    ///
+    /// Other than [`import_shm_buffer`](ImportMemWl::import_shm_buffer) the buffer contents are only
+    /// accessed for a plain memory copy, so the client may modify the buffer again as soon as this
+    /// returns. The transfer into a texture happens asynchronously on the GPU after calling
+    /// [`pbo_to_texture`](Gles2Renderer::pbo_to_texture).
+    ///
+    /// Requires GLES 3.0.
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    pub fn upload_shm_to_pbo(&mut self, buffer: &wl_buffer::WlBuffer) -> Result<Gles2Pbo, Gles2Error> {
+        use crate::wayland::shm::with_buffer_contents;
+
+        if self.gl_version < version::GLES_3_0 {
+            return Err(Gles2Error::GLVersionNotSupported(version::GLES_3_0));
+        }
+
+        with_buffer_contents(buffer, |ptr, len, data| {
+            self.make_current()?;
+
+            let offset = data.offset as usize;
+            let size = Size::from((data.width, data.height));
+            let stride = data.stride as usize;
+            // all supported formats are 32 bits wide
+            let pixelsize = 4usize;
+
+            let upload_format = format::shm_format_to_gl(data.format, self.gl_version, &self.extensions)
+                .ok_or(Gles2Error::UnsupportedPixelFormat(data.format))?;
+
+            let pbo_size = size.w as usize * size.h as usize * pixelsize;
+            unsafe {
+                let mut pbo = 0;
+                self.gl.GenBuffers(1, &mut pbo);
+                let pbo = Gles2Pbo {
+                    pbo,
+                    size,
+                    upload_format,
+                    destruction_callback_sender: self.destruction_callback_sender.clone(),
+                };
+
+                self.gl.BindBuffer(ffi::PIXEL_UNPACK_BUFFER, pbo.pbo);
+                self.gl.BufferData(
+                    ffi::PIXEL_UNPACK_BUFFER,
+                    pbo_size as isize,
+                    ptr::null(),
+                    ffi::STREAM_DRAW,
+                );
+                let mapping = self.gl.MapBufferRange(
+                    ffi::PIXEL_UNPACK_BUFFER,
+                    0,
+                    pbo_size as isize,
+                    ffi::MAP_WRITE_BIT | ffi::MAP_INVALIDATE_BUFFER_BIT,
+                );
+                if mapping.is_null() {
+                    self.gl.BindBuffer(ffi::PIXEL_UNPACK_BUFFER, 0);
+                    return Err(Gles2Error::MappingError);
+                }
+
+                let pixels = slice::from_raw_parts(ptr.add(offset), len - offset);
+                let dst = slice::from_raw_parts_mut(mapping as *mut u8, pbo_size);
+                format::copy_region(
+                    pixels,
+                    stride,
+                    pixelsize,
+                    Rectangle::from_loc_and_size((0, 0), size),
+                    dst,
+                );
+
+                self.gl.UnmapBuffer(ffi::PIXEL_UNPACK_BUFFER);
+                self.gl.BindBuffer(ffi::PIXEL_UNPACK_BUFFER, 0);
+                Ok(pbo)
+            }
+        })
+        .map_err(Gles2Error::BufferAccessError)?
+    }
+
+    /// Create a texture from the contents staged in a pixel buffer object
+    ///
+    /// The transfer from the pixel buffer object into the texture is executed asynchronously by the GPU.
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    pub fn pbo_to_texture(&mut self, pbo: Gles2Pbo) -> Result<Gles2Texture, Gles2Error> {
+        self.make_current()?;
+
+        let mut tex = 0;
+        unsafe {
+            self.gl.GenTextures(1, &mut tex);
+            self.gl.BindTexture(ffi::TEXTURE_2D, tex);
+            self.gl
+                .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_S, ffi::CLAMP_TO_EDGE as i32);
+            self.gl
+                .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_T, ffi::CLAMP_TO_EDGE as i32);
+            self.gl.BindBuffer(ffi::PIXEL_UNPACK_BUFFER, pbo.pbo);
+            // with a bound unpack buffer the data pointer is an offset into the buffer
+            self.gl.TexImage2D(
+                ffi::TEXTURE_2D,
+                0,
+                pbo.upload_format.internal as i32,
+                pbo.size.w,
+                pbo.size.h,
+                0,
+                pbo.upload_format.format,
+                pbo.upload_format.data_type,
+                ptr::null(),
+            );
+            self.gl.BindBuffer(ffi::PIXEL_UNPACK_BUFFER, 0);
+            self.gl.BindTexture(ffi::TEXTURE_2D, 0);
+        }
+
+        // deleting the buffer object is deferred by GL until the transfer finished
+        Ok(Gles2Texture(Rc::new(Gles2TextureInternal {
+            texture: tex,
+            texture_kind: pbo.upload_format.shader_idx,
+            is_external: false,
+            y_inverted: false,
+            size: pbo.size,
+            egl_images: None,
+            destruction_callback_sender: self.destruction_callback_sender.clone(),
+        })))
+    }
+}
+
+impl Gles2Renderer {
+    /// Insert a fence into the command stream of this renderer
+    ///
+    /// The fence is flushed, so it can be waited on by other contexts sharing resources with this one.
+    ///
+    /// Requires GLES 3.0.
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    pub fn create_fence(&mut self) -> Result<Gles2Fence, Gles2Error> {
+        if self.gl_version < version::GLES_3_0 {
+            return Err(Gles2Error::GLVersionNotSupported(version::GLES_3_0));
+        }
+        self.make_current()?;
+
+        unsafe {
+            let sync = self.gl.FenceSync(ffi::SYNC_GPU_COMMANDS_COMPLETE, 0);
+            self.gl.Flush();
+            Ok(Gles2Fence {
+                sync,
+                destruction_callback_sender: self.destruction_callback_sender.clone(),
+            })
+        }
+    }
+
+    /// Returns whether the given fence was already signaled
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    pub fn is_fence_signaled(&mut self, fence: &Gles2Fence) -> Result<bool, Gles2Error> {
+        self.make_current()?;
+
+        let status = unsafe { self.gl.ClientWaitSync(fence.sync, 0, 0) };
+        Ok(status == ffi::ALREADY_SIGNALED || status == ffi::CONDITION_SATISFIED)
+    }
+
+    /// Make all following commands of this renderer wait for the given fence
+    ///
+    /// This does not block the calling thread, the wait happens on the GPU.
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    pub fn wait_fence(&mut self, mut fence: Gles2Fence) -> Result<(), Gles2Error> {
+        self.make_current()?;
+
+        unsafe {
+            self.gl.WaitSync(fence.sync, 0, ffi::TIMEOUT_IGNORED);
+            // deletion is deferred by GL until the wait completed
+            self.gl.DeleteSync(fence.sync);
+        }
+        fence.sync = std::ptr::null();
+        Ok(())
+    }
+
+    /// Import a texture uploaded on a shared context
+    ///
+    /// The upload is synchronized with the following rendering commands of this renderer, so the
+    /// texture may be used right away. The texture has to originate from a context sharing resources
+    /// with the context of this renderer.
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    pub fn import_shared_texture(
+        &mut self,
+        mut shared: Gles2SharedTexture,
+    ) -> Result<Gles2Texture, Gles2Error> {
+        // fail before taking the fence, so the texture is still freed on drop
+        self.make_current()?;
+        let fence = shared.fence.take().expect("shared texture without fence");
+        self.wait_fence(fence)?;
+
+        Ok(Gles2Texture(Rc::new(Gles2TextureInternal {
+            texture: shared.texture,
+            texture_kind: shared.texture_kind,
+            is_external: false,
+            y_inverted: false,
+            size: shared.size,
+            egl_images: None,
+            destruction_callback_sender: self.destruction_callback_sender.clone(),
+        })))
+    }
+
+    /// Upload a shm buffer into a new texture to be used by another renderer
+    ///
+    /// Meant to be called on a renderer of a secondary context created with
+    /// [`EGLContext::create_shared`] living on another thread, so large buffer uploads do not
+    /// delay rendering on the main context. The returned texture can be moved to the thread of the
+    /// main renderer and imported using [`Gles2Renderer::import_shared_texture`].
+    ///
+    /// Requires GLES 3.0.
+    #[cfg(feature = "wayland_frontend")]
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    pub fn upload_shm_shared(
+        &mut self,
+        buffer: &wl_buffer::WlBuffer,
+    ) -> Result<Gles2SharedTexture, Gles2Error> {
+        use crate::wayland::shm::with_buffer_contents;
+
+        if self.gl_version < version::GLES_3_0 {
+            return Err(Gles2Error::GLVersionNotSupported(version::GLES_3_0));
+        }
+
+        let (texture, texture_kind, size) = with_buffer_contents(buffer, |ptr, len, data| {
+            self.make_current()?;
+
+            let offset = data.offset as usize;
+            let size: Size<i32, BufferCoord> = (data.width, data.height).into();
+            // all supported formats are 32 bits wide
+            let pixelsize = 4i32;
+
+            let upload_format = format::shm_format_to_gl(data.format, self.gl_version, &self.extensions)
+                .ok_or(Gles2Error::UnsupportedPixelFormat(data.format))?;
+
+            let mut tex = 0;
+            unsafe {
+                self.gl.GenTextures(1, &mut tex);
+                self.gl.BindTexture(ffi::TEXTURE_2D, tex);
+                self.gl
+                    .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_S, ffi::CLAMP_TO_EDGE as i32);
+                self.gl
+                    .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_T, ffi::CLAMP_TO_EDGE as i32);
+                let pixels = slice::from_raw_parts(ptr.add(offset), len - offset);
+                self.upload_region(
+                    pixels,
+                    data.stride,
+                    pixelsize,
+                    Rectangle::from_loc_and_size((0, 0), size),
+                    upload_format,
+                    true,
+                );
+                self.gl.BindTexture(ffi::TEXTURE_2D, 0);
+            }
+
+            Ok::<_, Gles2Error>((tex, upload_format.shader_idx, size))
+        })
+        .map_err(Gles2Error::BufferAccessError)??;
+
+        let fence = match self.create_fence() {
+            Ok(fence) => fence,
+            Err(err) => {
+                unsafe { self.gl.DeleteTextures(1, &texture) };
+                return Err(err);
+            }
+        };
+
+        Ok(Gles2SharedTexture {
+            texture,
+            texture_kind,
+            size,
+            fence: Some(fence),
+            destruction_callback_sender: self.destruction_callback_sender.clone(),
+        })
     }
 }
 
@@ -1194,23 +1625,14 @@ impl ImportMem for Gles2Renderer {
                 .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_S, ffi::CLAMP_TO_EDGE as i32);
             self.gl
                 .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_T, ffi::CLAMP_TO_EDGE as i32);
-            self.gl.PixelStorei(ffi::UNPACK_ROW_LENGTH, texture.0.size.w);
-            self.gl.PixelStorei(ffi::UNPACK_SKIP_PIXELS, region.loc.x);
-            self.gl.PixelStorei(ffi::UNPACK_SKIP_ROWS, region.loc.y);
-            self.gl.TexSubImage2D(
-                ffi::TEXTURE_2D,
-                0,
-                region.loc.x,
-                region.loc.y,
-                region.size.w,
-                region.size.h,
-                ffi::RGBA,
-                ffi::UNSIGNED_BYTE as u32,
-                data.as_ptr() as *const _,
+            self.upload_region(
+                data,
+                texture.0.size.w * 4,
+                4,
+                region,
+                format::GlUploadFormat::RGBA8,
+                false,
             );
-            self.gl.PixelStorei(ffi::UNPACK_ROW_LENGTH, 0);
-            self.gl.PixelStorei(ffi::UNPACK_SKIP_PIXELS, 0);
-            self.gl.PixelStorei(ffi::UNPACK_SKIP_ROWS, 0);
             self.gl.BindTexture(ffi::TEXTURE_2D, 0);
         }
 
@@ -1307,11 +1729,13 @@ impl ImportDma for Gles2Renderer {
         self.make_current()?;
         self.existing_dmabuf_texture(buffer)?.map(Ok).unwrap_or_else(|| {
             let is_external = !self.egl.dmabuf_render_formats().contains(&buffer.format());
-            let image = self
-                .egl
-                .display()
-                .create_image_from_dmabuf(buffer)
-                .map_err(Gles2Error::BindBufferEGLError)?;
+            let image = match self.egl.display().create_image_from_dmabuf(buffer) {
+                Ok(image) => image,
+                Err(err @ crate::backend::egl::Error::EglExtensionNotSupported(_)) => {
+                    return Err(Gles2Error::BindBufferEGLError(err))
+                }
+                Err(err) => return self.dmabuf_import_failed(buffer, err),
+            };
 
             let tex = self.import_egl_image(image, is_external, None)?;
             let texture = Gles2Texture(Rc::new(Gles2TextureInternal {
@@ -1324,12 +1748,18 @@ impl ImportDma for Gles2Renderer {
                 destruction_callback_sender: self.destruction_callback_sender.clone(),
             }));
             self.dmabuf_cache.insert(buffer.weak(), texture.clone());
+            self.evict_dmabuf_cache();
             Ok(texture)
         })
     }
 
     fn dmabuf_formats<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Format> + 'a> {
-        Box::new(self.egl.dmabuf_texture_formats().iter())
+        Box::new(
+            self.egl
+                .dmabuf_texture_formats()
+                .iter()
+                .filter(|format| !self.disabled_dmabuf_formats.contains(format)),
+        )
     }
 }
 
@@ -1337,12 +1767,291 @@ impl ImportDma for Gles2Renderer {
 impl ImportDmaWl for Gles2Renderer {}
 
 impl Gles2Renderer {
-    fn existing_dmabuf_texture(&self, buffer: &Dmabuf) -> Result<Option<Gles2Texture>, Gles2Error> {
+    /// Returns the number of failed dmabuf imports per format
+    ///
+    /// Every failure of the EGL driver to import a dmabuf is counted, even if the buffer
+    /// could be imported through the CPU afterwards. Useful to debug driver regressions.
+    pub fn dmabuf_import_failures(&self) -> &HashMap<Format, usize> {
+        &self.dmabuf_import_failures
+    }
+
+    /// Set a hook, that is called whenever the EGL driver fails to import a dmabuf
+    ///
+    /// The hook receives the offending buffer and a [`Gles2Error::DmabufImportFailed`] describing the failure.
+    /// If it returns `true`, the format and modifier of the buffer are not advertised by
+    /// [`ImportDma::dmabuf_formats`] anymore. The dmabuf feedback sent to clients should then be
+    /// rebuilt from the remaining formats, e.g. through
+    /// [`DmabufState::set_default_feedback`](crate::wayland::dmabuf::DmabufState::set_default_feedback).
+    pub fn set_dmabuf_import_failure_hook(
+        &mut self,
+        hook: impl FnMut(&Dmabuf, &Gles2Error) -> bool + 'static,
+    ) {
+        self.dmabuf_import_failure_hook = Some(Box::new(hook));
+    }
+
+    fn dmabuf_import_failed(
+        &mut self,
+        buffer: &Dmabuf,
+        source: crate::backend::egl::Error,
+    ) -> Result<Gles2Texture, Gles2Error> {
+        use crate::backend::allocator::Buffer;
+
+        let format = buffer.format();
+        let err = Gles2Error::DmabufImportFailed {
+            format,
+            size: buffer.size(),
+            planes: buffer.offsets().zip(buffer.strides()).collect(),
+            source,
+        };
+        warn!("Failed to import dmabuf: {:?}", err);
+        *self.dmabuf_import_failures.entry(format).or_default() += 1;
+
+        if let Some(hook) = self.dmabuf_import_failure_hook.as_mut() {
+            if hook(buffer, &err) {
+                debug!("Disabling dmabuf format {:?}", format);
+                self.disabled_dmabuf_formats.insert(format);
+            }
+        }
+
+        self.import_dmabuf_mapped(buffer).ok_or(err)
+    }
+
+    /// Import a linear dmabuf by mapping it and uploading its contents
+    ///
+    /// The texture is not cached, as the contents have to be uploaded again on every import.
+    fn import_dmabuf_mapped(&mut self, buffer: &Dmabuf) -> Option<Gles2Texture> {
+        use crate::backend::allocator::{Buffer, Modifier};
+        use nix::{sys::mman, unistd};
+        use std::{num::NonZeroUsize, os::unix::io::AsRawFd};
+
+        let format = buffer.format();
+        if format.modifier != Modifier::Linear || buffer.num_planes() != 1 {
+            return None;
+        }
+        let upload_format = format::dmabuf_format_to_gl(format.code)?;
+
+        // number of bytes per pixel, all supported formats are 32 bits wide
+        let pixelsize = 4i32;
+        let size = buffer.size();
+        let fd = buffer.handles().next()?.as_raw_fd();
+        let offset = buffer.offsets().next()? as usize;
+        let stride = buffer.strides().next()? as i32;
+        if size.w <= 0 || size.h <= 0 || stride < size.w * pixelsize {
+            return None;
+        }
+
+        // accessing the mapping beyond the end of the buffer would raise SIGBUS
+        let len = offset + stride as usize * size.h as usize;
+        let buffer_len = unistd::lseek(fd, 0, unistd::Whence::SeekEnd).ok()?;
+        if (buffer_len as usize) < len {
+            debug!("Dmabuf too small for mapping: {} < {}", buffer_len, len);
+            return None;
+        }
+        let len = NonZeroUsize::new(len)?;
+
+        let ptr = match unsafe {
+            mman::mmap(
+                None,
+                len,
+                mman::ProtFlags::PROT_READ,
+                mman::MapFlags::MAP_SHARED,
+                fd,
+                0,
+            )
+        } {
+            Ok(ptr) => ptr as *mut u8,
+            Err(err) => {
+                debug!("Failed to map dmabuf: {}", err);
+                return None;
+            }
+        };
+
+        trace!("Uploading mapped dmabuf");
+        let mut tex = 0;
+        unsafe {
+            let data = slice::from_raw_parts(ptr.add(offset), len.get() - offset);
+            self.gl.GenTextures(1, &mut tex);
+            self.gl.BindTexture(ffi::TEXTURE_2D, tex);
+            self.gl
+                .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_S, ffi::CLAMP_TO_EDGE as i32);
+            self.gl
+                .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_T, ffi::CLAMP_TO_EDGE as i32);
+            self.upload_region(
+                data,
+                stride,
+                pixelsize,
+                Rectangle::from_loc_and_size((0, 0), size),
+                upload_format,
+                true,
+            );
+            self.gl.BindTexture(ffi::TEXTURE_2D, 0);
+            let _ = mman::munmap(ptr as *mut _, len.get());
+        }
+
+        Some(Gles2Texture(Rc::new(Gles2TextureInternal {
+            texture: tex,
+            texture_kind: upload_format.shader_idx,
+            is_external: false,
+            y_inverted: buffer.y_inverted(),
+            size,
+            egl_images: None,
+            destruction_callback_sender: self.destruction_callback_sender.clone(),
+        })))
+    }
+
+    /// Import an [`EGLImage`] as an external texture
+    ///
+    /// The image is bound to a `GL_TEXTURE_EXTERNAL_OES` texture, which is required for
+    /// images that cannot be sampled as regular 2D textures, like the output of many
+    /// hardware video decoders. The returned texture is sampled using `samplerExternalOES`
+    /// and can be rendered like any other [`Gles2Texture`], e.g. through a
+    /// [`TextureRenderElement`](crate::backend::renderer::element::texture::TextureRenderElement).
+    ///
+    /// Ownership over the image is taken by the renderer, it will be destroyed
+    /// once the texture is dropped.
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    pub fn import_external_texture(
+        &mut self,
+        image: EGLImage,
+        size: Size<i32, BufferCoord>,
+        y_inverted: bool,
+    ) -> Result<Gles2Texture, Gles2Error> {
+        if !self
+            .extensions
+            .iter()
+            .any(|ext| ext == "GL_OES_EGL_image_external")
+        {
+            return Err(Gles2Error::GLExtensionNotSupported(&[
+                "GL_OES_EGL_image_external",
+            ]));
+        }
+
+        self.make_current()?;
+        let tex = self.import_egl_image(image, true, None)?;
+        Ok(Gles2Texture(Rc::new(Gles2TextureInternal {
+            texture: tex,
+            texture_kind: 2,
+            is_external: true,
+            y_inverted,
+            size,
+            egl_images: Some(vec![image]),
+            destruction_callback_sender: self.destruction_callback_sender.clone(),
+        })))
+    }
+
+    /// Upload `region` of `data` into the currently bound `GL_TEXTURE_2D`
+    ///
+    /// If `full` is set, the texture storage is (re-)specified and `region` is expected
+    /// to cover the whole texture. Regions are uploaded straight from `data` if possible,
+    /// otherwise they are copied into a tightly packed buffer first.
+    unsafe fn upload_region(
+        &self,
+        data: &[u8],
+        stride: i32,
+        pixelsize: i32,
+        region: Rectangle<i32, BufferCoord>,
+        upload_format: format::GlUploadFormat,
+        full: bool,
+    ) {
+        let packed;
+        let pixels = if format::can_unpack_subimage(
+            self.supports_unpack_subimage,
+            stride as usize,
+            pixelsize as usize,
+        ) {
+            self.gl.PixelStorei(ffi::UNPACK_ROW_LENGTH, stride / pixelsize);
+            self.gl.PixelStorei(ffi::UNPACK_SKIP_PIXELS, region.loc.x);
+            self.gl.PixelStorei(ffi::UNPACK_SKIP_ROWS, region.loc.y);
+            data
+        } else if stride == region.size.w * pixelsize && region.loc.x == 0 {
+            // the rows of the region are already contiguous
+            &data[(region.loc.y * stride) as usize..]
+        } else {
+            packed = format::pack_region(data, stride as usize, pixelsize as usize, region);
+            &packed[..]
+        };
+
+        if full {
+            self.gl.TexImage2D(
+                ffi::TEXTURE_2D,
+                0,
+                upload_format.internal as i32,
+                region.size.w,
+                region.size.h,
+                0,
+                upload_format.format,
+                upload_format.data_type,
+                pixels.as_ptr() as *const _,
+            );
+        } else {
+            self.gl.TexSubImage2D(
+                ffi::TEXTURE_2D,
+                0,
+                region.loc.x,
+                region.loc.y,
+                region.size.w,
+                region.size.h,
+                upload_format.format,
+                upload_format.data_type,
+                pixels.as_ptr() as *const _,
+            );
+        }
+
+        if self.supports_unpack_subimage {
+            self.gl.PixelStorei(ffi::UNPACK_ROW_LENGTH, 0);
+            self.gl.PixelStorei(ffi::UNPACK_SKIP_PIXELS, 0);
+            self.gl.PixelStorei(ffi::UNPACK_SKIP_ROWS, 0);
+        }
+    }
+
+    /// Maximum amount of imported dmabufs kept in the cache
+    ///
+    /// See [`set_dmabuf_cache_size`](Gles2Renderer::set_dmabuf_cache_size).
+    pub fn dmabuf_cache_size(&self) -> usize {
+        self.dmabuf_cache_size
+    }
+
+    /// Set the maximum amount of imported dmabufs kept in the cache
+    ///
+    /// Importing a dmabuf creates an [`EGLImage`], which is expensive on some drivers.
+    /// The renderer therefore keeps the textures of imported dmabufs around and re-uses
+    /// them when the same dmabuf is imported again, until the dmabuf is destroyed.
+    /// Once more dmabufs than `size` are cached, the least recently used ones are evicted.
+    ///
+    /// Defaults to [`DEFAULT_DMABUF_CACHE_SIZE`].
+    pub fn set_dmabuf_cache_size(&mut self, size: usize) {
+        self.dmabuf_cache_size = size;
+        self.evict_dmabuf_cache();
+    }
+
+    /// Remove a dmabuf from the import cache
+    ///
+    /// The next import of the dmabuf will create a new [`EGLImage`]. The image of the
+    /// cached texture is destroyed once all handles to the texture are dropped.
+    pub fn invalidate_dmabuf(&mut self, buffer: &Dmabuf) {
+        self.dmabuf_cache
+            .retain(|weak, _| weak.upgrade().map(|entry| &entry != buffer).unwrap_or(false));
+    }
+
+    fn evict_dmabuf_cache(&mut self) {
+        while self.dmabuf_cache.len() > self.dmabuf_cache_size {
+            if let Some((_, texture)) = self.dmabuf_cache.shift_remove_index(0) {
+                trace!("Evicting texture {:?} from dmabuf cache", texture.0.texture);
+            }
+        }
+    }
+
+    fn existing_dmabuf_texture(&mut self, buffer: &Dmabuf) -> Result<Option<Gles2Texture>, Gles2Error> {
         let existing_texture = self
             .dmabuf_cache
             .iter()
-            .find(|(weak, _)| weak.upgrade().map(|entry| &entry == buffer).unwrap_or(false))
-            .map(|(_, tex)| tex.clone());
+            .position(|(weak, _)| weak.upgrade().map(|entry| &entry == buffer).unwrap_or(false))
+            .and_then(|idx| self.dmabuf_cache.shift_remove_index(idx))
+            .map(|(weak, tex)| {
+                // mark the entry as most recently used
+                self.dmabuf_cache.insert(weak, tex.clone());
+                tex
+            });
 
         if let Some(texture) = existing_texture {
             trace!("Re-using texture {:?} for {:?}", texture.0.texture, buffer);
@@ -1518,8 +2227,10 @@ impl ExportDma for Gles2Renderer {
             ]));
         }
 
-        let image = if let Some(egl_images) = texture.0.egl_images.as_ref() {
-            egl_images[0]
+        // images owned by the texture must not be destroyed after the export
+        let owned_image = texture.as_egl_image();
+        let image = if let Some(image) = owned_image {
+            image
         } else {
             unsafe {
                 let attributes: [ffi_egl::types::EGLAttrib; 3] = [
@@ -1548,7 +2259,9 @@ impl ExportDma for Gles2Renderer {
             .display()
             .create_dmabuf_from_image(image, texture.size(), true)
             .map_err(Gles2Error::BindBufferEGLError);
-        unsafe { ffi_egl::DestroyImageKHR(**self.egl.display().get_display_handle(), image) };
+        if owned_image.is_none() {
+            unsafe { ffi_egl::DestroyImageKHR(**self.egl.display().get_display_handle(), image) };
+        }
         res
     }
 
@@ -4,6 +4,8 @@
 use crate::utils::{Buffer as BufferCoord, Coordinate, Logical, Physical, Point, Rectangle, Size};
 use std::{collections::VecDeque, fmt};
 
+use super::Frame;
+
 #[cfg(feature = "wayland_frontend")]
 mod wayland;
 #[cfg(feature = "wayland_frontend")]
@@ -302,3 +304,64 @@ pub struct SurfaceView {
     /// The logical offset for a sub-surface
     pub offset: Point<i32, Logical>,
 }
+
+/// Draw a rectangle filled with a solid color
+///
+/// This directly uses [`Frame::draw_solid`], which does not require a texture,
+/// and is meant for custom render passes drawing backgrounds or highlights
+/// without wrapping them into a [`RenderElement`](super::element::RenderElement).
+pub fn draw_solid_color<F: Frame>(
+    frame: &mut F,
+    color: [f32; 4],
+    geometry: Rectangle<i32, Physical>,
+) -> Result<(), F::Error> {
+    if geometry.is_empty() {
+        return Ok(());
+    }
+    // damage is relative to the destination
+    frame.draw_solid(
+        geometry,
+        &[Rectangle::from_loc_and_size((0, 0), geometry.size)],
+        color,
+    )
+}
+
+/// Draw the outline of a rectangle with a solid color
+///
+/// The outline is drawn inside of `geometry` with the given `border_width`,
+/// e.g. for window borders or focus rings. If the border is wider than half of
+/// the rectangle, the whole rectangle is filled.
+pub fn draw_rectangle<F: Frame>(
+    frame: &mut F,
+    color: [f32; 4],
+    geometry: Rectangle<i32, Physical>,
+    border_width: i32,
+) -> Result<(), F::Error> {
+    if geometry.is_empty() || border_width <= 0 {
+        return Ok(());
+    }
+    if border_width * 2 >= geometry.size.w || border_width * 2 >= geometry.size.h {
+        return draw_solid_color(frame, color, geometry);
+    }
+
+    let (x, y) = (geometry.loc.x, geometry.loc.y);
+    let (w, h) = (geometry.size.w, geometry.size.h);
+    let inner_height = h - 2 * border_width;
+    let edges = [
+        // top
+        Rectangle::from_loc_and_size((x, y), (w, border_width)),
+        // bottom
+        Rectangle::from_loc_and_size((x, y + h - border_width), (w, border_width)),
+        // left
+        Rectangle::from_loc_and_size((x, y + border_width), (border_width, inner_height)),
+        // right
+        Rectangle::from_loc_and_size(
+            (x + w - border_width, y + border_width),
+            (border_width, inner_height),
+        ),
+    ];
+    for edge in edges {
+        draw_solid_color(frame, color, edge)?;
+    }
+    Ok(())
+}
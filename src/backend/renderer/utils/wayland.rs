@@ -53,7 +53,62 @@ struct InnerBuffer(WlBuffer);
 
 impl Drop for InnerBuffer {
     fn drop(&mut self) {
-        self.0.release();
+        let held = HELD_BUFFER_RELEASES
+            .try_with(|held| match held.borrow_mut().as_mut() {
+                Some(held) => {
+                    held.push(self.0.clone());
+                    true
+                }
+                None => false,
+            })
+            .unwrap_or(false);
+        if !held {
+            self.0.release();
+        }
+    }
+}
+
+thread_local! {
+    /// Releases held back while batching is enabled on this thread
+    static HELD_BUFFER_RELEASES: RefCell<Option<Vec<WlBuffer>>> = RefCell::new(None);
+}
+
+/// Enable or disable batching of buffer releases on the current thread
+///
+/// Buffers managed through [`on_commit_buffer_handler`] are released as soon as the
+/// compositor does not need them anymore, which usually results in a flurry of individual
+/// `wl_buffer.release` events during rendering. With batching enabled, these releases are held
+/// back until [`flush_buffer_releases`] is called, which should happen once per frame after it
+/// was queued. The frame callback helpers of the desktop module, like
+/// `desktop::utils::send_frames_surface_tree`, flush the
+/// releases before sending any callback, so clients can reuse their buffers right away once
+/// they are asked to draw the next frame.
+///
+/// Disabling batching flushes the held back releases.
+pub fn set_buffer_release_batching(enabled: bool) {
+    let held = HELD_BUFFER_RELEASES.with(|held| {
+        let mut held = held.borrow_mut();
+        match (enabled, held.is_some()) {
+            (true, false) => {
+                *held = Some(Vec::new());
+                None
+            }
+            (false, true) => held.take(),
+            _ => None,
+        }
+    });
+    for buffer in held.into_iter().flatten() {
+        buffer.release();
+    }
+}
+
+/// Send the buffer releases held back on the current thread
+///
+/// Does nothing unless batching was enabled with [`set_buffer_release_batching`].
+pub fn flush_buffer_releases() {
+    let held = HELD_BUFFER_RELEASES.with(|held| held.borrow_mut().as_mut().map(std::mem::take));
+    for buffer in held.into_iter().flatten() {
+        buffer.release();
     }
 }
 
@@ -92,6 +147,17 @@ impl PartialEq<WlBuffer> for &Buffer {
 }
 
 impl RendererSurfaceState {
+    /// Drop the buffer and all textures, to free them once the surface got destroyed
+    fn reset(&mut self) {
+        self.buffer_dimensions = None;
+        self.buffer = None;
+        self.textures.clear();
+        self.renderer_seen.clear();
+        self.damage = DamageBag::default();
+        self.surface_view = None;
+        self.opaque_regions.clear();
+    }
+
     pub(crate) fn update_buffer(&mut self, states: &SurfaceData) {
         let mut attrs = states.cached_state.current::<SurfaceAttributes>();
         self.buffer_delta = attrs.buffer_delta.take();
@@ -336,6 +402,13 @@ pub fn on_commit_buffer_handler(surface: &WlSurface) {
             },
             |_, _, _| true,
         );
+        for surf in &new_surfaces {
+            compositor::add_destruction_hook(surf, |data| {
+                if let Some(state) = data.data_map.get::<RendererSurfaceStateUserData>() {
+                    state.borrow_mut().reset();
+                }
+            });
+        }
     }
 }
 
@@ -603,3 +676,58 @@ where
 
     Ok(Some(render_damage))
 }
+
+#[cfg(all(test, feature = "test_utils"))]
+mod tests {
+    use std::time::Duration;
+
+    use wayland_client::protocol::{
+        wl_buffer,
+        wl_compositor::WlCompositor,
+        wl_shm::{self, WlShm},
+    };
+
+    use super::{flush_buffer_releases, set_buffer_release_batching, HELD_BUFFER_RELEASES};
+    use crate::testing::{Recorded, ShmTestState, TestCompositor};
+
+    fn held_releases() -> usize {
+        HELD_BUFFER_RELEASES.with(|held| held.borrow().as_ref().map_or(0, Vec::len))
+    }
+
+    #[test]
+    fn double_buffered_client_gets_buffer_back_each_frame() {
+        let mut compositor = TestCompositor::new(|dh, _| ShmTestState::new(dh));
+        set_buffer_release_batching(true);
+
+        let mut client = compositor.connect();
+        let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+        let shm = client.bind::<WlShm, _>(&mut compositor);
+        let qh = client.queue_handle();
+
+        let (pool, _file) = client.create_shm_pool(&shm, 2 * 64);
+        let buffers = [0, 64]
+            .map(|offset| pool.create_buffer(offset, 4, 4, 16, wl_shm::Format::Argb8888, &qh, Recorded));
+        let surface = wl_compositor.create_surface(&qh, Recorded);
+
+        for frame in 0..4 {
+            surface.attach(Some(&buffers[frame % 2]), 0, 0);
+            surface.commit();
+            client.roundtrip(&mut compositor);
+
+            // replacing the buffer does not release the previous one right away
+            assert_eq!(held_releases(), usize::from(frame > 0));
+
+            // but at the frame boundary, so only the attached buffer is held
+            flush_buffer_releases();
+            assert_eq!(held_releases(), 0);
+            if frame > 0 {
+                assert!(matches!(
+                    client.wait_for_event::<wl_buffer::Event, _>(&mut compositor, Duration::from_secs(1)),
+                    Some(wl_buffer::Event::Release)
+                ));
+            }
+        }
+
+        set_buffer_release_batching(false);
+    }
+}
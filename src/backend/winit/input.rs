@@ -7,7 +7,7 @@ use winit::{
 
 use crate::backend::input::{
     self, AbsolutePositionEvent, Axis, AxisSource, ButtonState, Device, DeviceCapability, Event,
-    InputBackend, KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent,
+    InputBackend, KeyState, KeyboardKeyEvent, MouseButton, PointerAxisEvent, PointerButtonEvent,
     PointerMotionAbsoluteEvent, TouchCancelEvent, TouchDownEvent, TouchEvent, TouchMotionEvent, TouchSlot,
     TouchUpEvent, UnusedEvent,
 };
@@ -114,12 +114,18 @@ impl AbsolutePositionEvent<WinitInput> for WinitMouseMovedEvent {
     fn x_transformed(&self, width: i32) -> f64 {
         let wsize = self.size.borrow();
         let w_width = wsize.logical_size().w;
+        if w_width <= 0.0 {
+            return 0.0;
+        }
         f64::max(self.logical_position.x * width as f64 / w_width, 0.0)
     }
 
     fn y_transformed(&self, height: i32) -> f64 {
         let wsize = self.size.borrow();
         let w_height = wsize.logical_size().h;
+        if w_height <= 0.0 {
+            return 0.0;
+        }
         f64::max(self.logical_position.y * height as f64 / w_height, 0.0)
     }
 }
@@ -188,9 +194,9 @@ impl Event<WinitInput> for WinitMouseInputEvent {
 impl PointerButtonEvent<WinitInput> for WinitMouseInputEvent {
     fn button_code(&self) -> u32 {
         match self.button {
-            WinitMouseButton::Left => 0x110,
-            WinitMouseButton::Right => 0x111,
-            WinitMouseButton::Middle => 0x112,
+            WinitMouseButton::Left => MouseButton::Left.button_code(),
+            WinitMouseButton::Right => MouseButton::Right.button_code(),
+            WinitMouseButton::Middle => MouseButton::Middle.button_code(),
             WinitMouseButton::Other(b) => {
                 if self.is_x11 {
                     input::xorg_mouse_to_libinput(b as u32)
@@ -33,10 +33,11 @@ use crate::{
             gles2::{Gles2Error, Gles2Renderer},
             Bind,
         },
+        PresentEvent, PresentFlags,
     },
-    utils::{Logical, Physical, Rectangle, Size},
+    utils::{Clock, Logical, Monotonic, Physical, Rectangle, Size},
 };
-use std::{cell::RefCell, rc::Rc, sync::Arc, time::Instant};
+use std::{cell::RefCell, collections::HashSet, rc::Rc, sync::Arc};
 use wayland_egl as wegl;
 use winit::{
     dpi::LogicalSize,
@@ -100,6 +101,8 @@ pub struct WinitGraphicsBackend<R> {
     size: Rc<RefCell<WindowSize>>,
     damage_tracking: bool,
     resize_notification: Rc<Cell<Option<Size<i32, Physical>>>>,
+    clock: Clock<Monotonic>,
+    presentations: Rc<RefCell<Vec<PresentEvent>>>,
     span: tracing::Span,
 }
 
@@ -111,11 +114,14 @@ pub struct WinitGraphicsBackend<R> {
 pub struct WinitEventLoop {
     window: Arc<WinitWindow>,
     events_loop: EventLoop<()>,
-    time: Instant,
+    clock: Clock<Monotonic>,
     key_counter: u32,
+    // ids of the touch points currently down
+    touches: HashSet<u64>,
     initialized: bool,
     size: Rc<RefCell<WindowSize>>,
     resize_notification: Rc<Cell<Option<Size<i32, Physical>>>>,
+    presentations: Rc<RefCell<Vec<PresentEvent>>>,
     /// Whether winit is using Wayland or X11 as it's backend.
     is_x11: bool,
     span: tracing::Span,
@@ -231,6 +237,7 @@ where
     let egl = Rc::new(surface);
     let renderer = unsafe { Gles2Renderer::new(context)?.into() };
     let resize_notification = Rc::new(Cell::new(None));
+    let presentations = Rc::new(RefCell::new(Vec::new()));
     let damage_tracking = display.supports_damage();
 
     drop(_guard);
@@ -243,16 +250,20 @@ where
             damage_tracking,
             size: size.clone(),
             resize_notification: resize_notification.clone(),
+            clock: Clock::new().expect("the monotonic clock is always available"),
+            presentations: presentations.clone(),
             span: span.clone(),
         },
         WinitEventLoop {
             resize_notification,
             events_loop,
             window: winit_window,
-            time: Instant::now(),
+            clock: Clock::new().expect("the monotonic clock is always available"),
             key_counter: 0,
+            touches: HashSet::new(),
             initialized: false,
             size,
+            presentations,
             is_x11,
             span,
         },
@@ -278,6 +289,12 @@ pub enum WinitEvent {
 
     /// A redraw was requested
     Refresh,
+
+    /// A frame submitted through [`WinitGraphicsBackend::submit`] was presented
+    ///
+    /// Winit offers no presentation timing, so the timestamp is taken right after swapping
+    /// the buffers. Neither the synchronization to the display nor its refresh rate are known.
+    Presented(PresentEvent),
 }
 
 impl<R> WinitGraphicsBackend<R>
@@ -363,6 +380,12 @@ where
             _ => None,
         };
         self.egl.swap_buffers(damage.as_deref_mut())?;
+        self.presentations.borrow_mut().push(PresentEvent {
+            time: self.clock.now(),
+            refresh: None,
+            seq: 0,
+            flags: PresentFlags::empty(),
+        });
         Ok(())
     }
 }
@@ -406,7 +429,8 @@ impl WinitEventLoop {
             // wrong interference.
             let closed_ptr = &mut closed;
             let key_counter = &mut self.key_counter;
-            let time = &self.time;
+            let touches = &mut self.touches;
+            let clock = &self.clock;
             let window = &self.window;
             let resize_notification = &self.resize_notification;
             let window_size = &self.size;
@@ -419,6 +443,12 @@ impl WinitEventLoop {
                 self.initialized = true;
             }
 
+            // taken out first, the callback might submit the next frame
+            let presentations = std::mem::take(&mut *self.presentations.borrow_mut());
+            for presentation in presentations {
+                callback(Presented(presentation));
+            }
+
             self.events_loop
                 .run_return(move |event, _target, control_flow| match event {
                     Event::RedrawEventsCleared => {
@@ -428,8 +458,7 @@ impl WinitEventLoop {
                         callback(WinitEvent::Refresh);
                     }
                     Event::WindowEvent { event, .. } => {
-                        let duration = Instant::now().duration_since(*time);
-                        let time = duration.as_micros() as u64;
+                        let time = clock.now().as_micros();
                         match event {
                             WindowEvent::Resized(psize) => {
                                 trace!("Resizing window to {:?}", psize);
@@ -447,6 +476,14 @@ impl WinitEventLoop {
                                 });
                             }
                             WindowEvent::Focused(focus) => {
+                                if !focus {
+                                    // the window won't receive the end of touches in progress
+                                    for id in touches.drain() {
+                                        callback(Input(InputEvent::TouchCancel {
+                                            event: WinitTouchCancelledEvent { time, id },
+                                        }));
+                                    }
+                                }
                                 callback(WinitEvent::Focus(focus));
                             }
 
@@ -515,6 +552,7 @@ impl WinitEventLoop {
                                 id,
                                 ..
                             }) => {
+                                touches.insert(id);
                                 let location = location.to_logical(window_size.borrow().scale_factor);
                                 callback(Input(InputEvent::TouchDown {
                                     event: WinitTouchStartedEvent {
@@ -557,6 +595,7 @@ impl WinitEventLoop {
                                         id,
                                     },
                                 }));
+                                touches.remove(&id);
                                 callback(Input(InputEvent::TouchUp {
                                     event: WinitTouchEndedEvent { time, id },
                                 }))
@@ -567,6 +606,7 @@ impl WinitEventLoop {
                                 id,
                                 ..
                             }) => {
+                                touches.remove(&id);
                                 callback(Input(InputEvent::TouchCancel {
                                     event: WinitTouchCancelledEvent { time, id },
                                 }));
@@ -61,7 +61,7 @@ macro_rules! extensions {
                                     version.minor_version,
                                 );
 
-                                Some((version.major_version, version.minor_version))
+                                Some((version.major_version as u32, version.minor_version as u32))
                             } else {
                                 if $required {
                                     error!(
@@ -77,8 +77,8 @@ macro_rules! extensions {
                                         name: X11_EXTENSION_NAME,
                                         required_major: $req_major,
                                         required_minor: $req_minor,
-                                        available_major: version.major_version,
-                                        available_minor: version.minor_version,
+                                        available_major: version.major_version as u32,
+                                        available_minor: version.minor_version as u32,
                                     }.into());
                                 } else {
                                     None
@@ -132,4 +132,12 @@ extensions! {
         minimum: (1, 0),
         request: (1, 2),
     },
+
+    // touch events, only used if available
+    xinput {
+        xinput_xi_query_version,
+        required: false,
+        minimum: (2, 2),
+        request: (2, 2),
+    },
 }
@@ -5,7 +5,7 @@ use crate::{
     backend::input::{
         self, AbsolutePositionEvent, Axis, AxisSource, ButtonState, Device, DeviceCapability, InputBackend,
         KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent, PointerMotionAbsoluteEvent,
-        UnusedEvent,
+        TouchDownEvent, TouchEvent, TouchFrameEvent, TouchMotionEvent, TouchSlot, TouchUpEvent, UnusedEvent,
     },
     utils::{Logical, Size},
 };
@@ -48,6 +48,7 @@ impl Device for X11VirtualDevice {
 #[derive(Debug, Clone)]
 pub struct X11KeyboardInputEvent {
     pub(crate) time: u32,
+    pub(crate) sequence: u64,
     pub(crate) key: u32,
     pub(crate) count: u32,
     pub(crate) state: KeyState,
@@ -71,6 +72,10 @@ impl input::Event<X11Input> for X11KeyboardInputEvent {
     fn device(&self) -> X11VirtualDevice {
         X11VirtualDevice
     }
+
+    fn sequence(&self) -> Option<u64> {
+        Some(self.sequence)
+    }
 }
 
 impl KeyboardKeyEvent<X11Input> for X11KeyboardInputEvent {
@@ -91,6 +96,7 @@ impl KeyboardKeyEvent<X11Input> for X11KeyboardInputEvent {
 #[derive(Debug, Clone)]
 pub struct X11MouseWheelEvent {
     pub(crate) time: u32,
+    pub(crate) sequence: u64,
     pub(crate) axis: Axis,
     pub(crate) amount: f64,
     pub(crate) window: Weak<WindowInner>,
@@ -113,6 +119,10 @@ impl input::Event<X11Input> for X11MouseWheelEvent {
     fn device(&self) -> X11VirtualDevice {
         X11VirtualDevice
     }
+
+    fn sequence(&self) -> Option<u64> {
+        Some(self.sequence)
+    }
 }
 
 impl PointerAxisEvent<X11Input> for X11MouseWheelEvent {
@@ -138,6 +148,7 @@ impl PointerAxisEvent<X11Input> for X11MouseWheelEvent {
 #[derive(Debug, Clone)]
 pub struct X11MouseInputEvent {
     pub(crate) time: u32,
+    pub(crate) sequence: u64,
     pub(crate) raw: u32,
     pub(crate) state: ButtonState,
     pub(crate) window: Weak<WindowInner>,
@@ -160,6 +171,10 @@ impl input::Event<X11Input> for X11MouseInputEvent {
     fn device(&self) -> X11VirtualDevice {
         X11VirtualDevice
     }
+
+    fn sequence(&self) -> Option<u64> {
+        Some(self.sequence)
+    }
 }
 
 impl PointerButtonEvent<X11Input> for X11MouseInputEvent {
@@ -176,6 +191,7 @@ impl PointerButtonEvent<X11Input> for X11MouseInputEvent {
 #[derive(Debug, Clone)]
 pub struct X11MouseMovedEvent {
     pub(crate) time: u32,
+    pub(crate) sequence: u64,
     pub(crate) x: f64,
     pub(crate) y: f64,
     pub(crate) size: Size<u16, Logical>,
@@ -199,6 +215,10 @@ impl input::Event<X11Input> for X11MouseMovedEvent {
     fn device(&self) -> X11VirtualDevice {
         X11VirtualDevice
     }
+
+    fn sequence(&self) -> Option<u64> {
+        Some(self.sequence)
+    }
 }
 
 impl PointerMotionAbsoluteEvent<X11Input> for X11MouseMovedEvent {}
@@ -212,14 +232,229 @@ impl AbsolutePositionEvent<X11Input> for X11MouseMovedEvent {
     }
 
     fn x_transformed(&self, width: i32) -> f64 {
+        if self.size.w == 0 {
+            return 0.0;
+        }
+        f64::max(self.x * width as f64 / self.size.w as f64, 0.0)
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        if self.size.h == 0 {
+            return 0.0;
+        }
+        f64::max(self.y * height as f64 / self.size.h as f64, 0.0)
+    }
+}
+
+/// X11-Backend internal event wrapping `X11`'s types into a [`TouchDownEvent`]
+#[derive(Debug, Clone)]
+pub struct X11TouchDownEvent {
+    pub(crate) time: u32,
+    pub(crate) sequence: u64,
+    pub(crate) id: u32,
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    pub(crate) size: Size<u16, Logical>,
+    pub(crate) window: Weak<WindowInner>,
+}
+
+impl X11TouchDownEvent {
+    /// Returns a temporary reference to the window belonging to this event.
+    ///
+    /// Returns None if the window is not alive anymore.
+    pub fn window(&self) -> Option<impl AsRef<Window> + '_> {
+        self.window.upgrade().map(Window).map(WindowTemporary)
+    }
+}
+
+impl input::Event<X11Input> for X11TouchDownEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> X11VirtualDevice {
+        X11VirtualDevice
+    }
+
+    fn sequence(&self) -> Option<u64> {
+        Some(self.sequence)
+    }
+}
+
+impl TouchDownEvent<X11Input> for X11TouchDownEvent {}
+
+impl TouchEvent<X11Input> for X11TouchDownEvent {
+    fn slot(&self) -> TouchSlot {
+        Some(self.id).into()
+    }
+}
+
+impl AbsolutePositionEvent<X11Input> for X11TouchDownEvent {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        if self.size.w == 0 {
+            return 0.0;
+        }
         f64::max(self.x * width as f64 / self.size.w as f64, 0.0)
     }
 
     fn y_transformed(&self, height: i32) -> f64 {
+        if self.size.h == 0 {
+            return 0.0;
+        }
         f64::max(self.y * height as f64 / self.size.h as f64, 0.0)
     }
 }
 
+/// X11-Backend internal event wrapping `X11`'s types into a [`TouchMotionEvent`]
+#[derive(Debug, Clone)]
+pub struct X11TouchMotionEvent {
+    pub(crate) time: u32,
+    pub(crate) sequence: u64,
+    pub(crate) id: u32,
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    pub(crate) size: Size<u16, Logical>,
+    pub(crate) window: Weak<WindowInner>,
+}
+
+impl X11TouchMotionEvent {
+    /// Returns a temporary reference to the window belonging to this event.
+    ///
+    /// Returns None if the window is not alive anymore.
+    pub fn window(&self) -> Option<impl AsRef<Window> + '_> {
+        self.window.upgrade().map(Window).map(WindowTemporary)
+    }
+}
+
+impl input::Event<X11Input> for X11TouchMotionEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> X11VirtualDevice {
+        X11VirtualDevice
+    }
+
+    fn sequence(&self) -> Option<u64> {
+        Some(self.sequence)
+    }
+}
+
+impl TouchMotionEvent<X11Input> for X11TouchMotionEvent {}
+
+impl TouchEvent<X11Input> for X11TouchMotionEvent {
+    fn slot(&self) -> TouchSlot {
+        Some(self.id).into()
+    }
+}
+
+impl AbsolutePositionEvent<X11Input> for X11TouchMotionEvent {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        if self.size.w == 0 {
+            return 0.0;
+        }
+        f64::max(self.x * width as f64 / self.size.w as f64, 0.0)
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        if self.size.h == 0 {
+            return 0.0;
+        }
+        f64::max(self.y * height as f64 / self.size.h as f64, 0.0)
+    }
+}
+
+/// X11-Backend internal event wrapping `X11`'s types into a [`TouchUpEvent`]
+#[derive(Debug, Clone)]
+pub struct X11TouchUpEvent {
+    pub(crate) time: u32,
+    pub(crate) sequence: u64,
+    pub(crate) id: u32,
+    pub(crate) window: Weak<WindowInner>,
+}
+
+impl X11TouchUpEvent {
+    /// Returns a temporary reference to the window belonging to this event.
+    ///
+    /// Returns None if the window is not alive anymore.
+    pub fn window(&self) -> Option<impl AsRef<Window> + '_> {
+        self.window.upgrade().map(Window).map(WindowTemporary)
+    }
+}
+
+impl input::Event<X11Input> for X11TouchUpEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> X11VirtualDevice {
+        X11VirtualDevice
+    }
+
+    fn sequence(&self) -> Option<u64> {
+        Some(self.sequence)
+    }
+}
+
+impl TouchUpEvent<X11Input> for X11TouchUpEvent {}
+
+impl TouchEvent<X11Input> for X11TouchUpEvent {
+    fn slot(&self) -> TouchSlot {
+        Some(self.id).into()
+    }
+}
+
+/// X11-Backend internal event wrapping `X11`'s types into a [`TouchFrameEvent`]
+///
+/// XInput2 has no notion of touch frames, so one is emitted after every touch event.
+#[derive(Debug, Clone)]
+pub struct X11TouchFrameEvent {
+    pub(crate) time: u32,
+    pub(crate) sequence: u64,
+    pub(crate) window: Weak<WindowInner>,
+}
+
+impl X11TouchFrameEvent {
+    /// Returns a temporary reference to the window belonging to this event.
+    ///
+    /// Returns None if the window is not alive anymore.
+    pub fn window(&self) -> Option<impl AsRef<Window> + '_> {
+        self.window.upgrade().map(Window).map(WindowTemporary)
+    }
+}
+
+impl input::Event<X11Input> for X11TouchFrameEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> X11VirtualDevice {
+        X11VirtualDevice
+    }
+
+    fn sequence(&self) -> Option<u64> {
+        Some(self.sequence)
+    }
+}
+
+impl TouchFrameEvent<X11Input> for X11TouchFrameEvent {}
+
 impl InputBackend for X11Input {
     type Device = X11VirtualDevice;
     type KeyboardKeyEvent = X11KeyboardInputEvent;
@@ -239,11 +474,11 @@ impl InputBackend for X11Input {
     type GestureHoldBeginEvent = UnusedEvent;
     type GestureHoldEndEvent = UnusedEvent;
 
-    type TouchDownEvent = UnusedEvent;
-    type TouchUpEvent = UnusedEvent;
-    type TouchMotionEvent = UnusedEvent;
+    type TouchDownEvent = X11TouchDownEvent;
+    type TouchUpEvent = X11TouchUpEvent;
+    type TouchMotionEvent = X11TouchMotionEvent;
     type TouchCancelEvent = UnusedEvent;
-    type TouchFrameEvent = UnusedEvent;
+    type TouchFrameEvent = X11TouchFrameEvent;
     type TabletToolAxisEvent = UnusedEvent;
     type TabletToolProximityEvent = UnusedEvent;
     type TabletToolTipEvent = UnusedEvent;
@@ -87,6 +87,7 @@ use crate::{
         drm::{node::path_to_type, CreateDrmNodeError, DrmNode, NodeType},
         egl::{native::X11DefaultDisplay, EGLDevice, EGLDisplay, Error as EGLError},
         input::{Axis, ButtonState, InputEvent, KeyState},
+        PresentEvent, PresentFlags,
     },
     utils::{x11rb::X11Source, Logical, Size},
 };
@@ -104,6 +105,7 @@ use std::{
         atomic::{AtomicU32, Ordering},
         mpsc, Arc, Mutex, Weak,
     },
+    time::Duration,
 };
 use tracing::{debug_span, error, info, instrument, warn};
 use x11rb::{
@@ -112,6 +114,7 @@ use x11rb::{
     protocol::{
         self as x11,
         dri3::ConnectionExt as _,
+        present,
         xproto::{ColormapAlloc, ConnectionExt, CreateWindowAux, VisualClass, WindowClass, WindowWrapper},
         ErrorKind,
     },
@@ -152,6 +155,11 @@ pub enum X11Event {
     PresentCompleted {
         /// XID of the window
         window_id: u32,
+        /// Timing of the presentation, `None` if the X server skipped the buffer
+        ///
+        /// The timestamp is the UST of the Present extension, which is `CLOCK_MONOTONIC`
+        /// on Linux. The refresh interval is estimated from consecutive presentations.
+        presentation: Option<PresentEvent>,
     },
 
     /// The window has received a request to be closed.
@@ -251,6 +259,7 @@ impl X11Backend {
             depth,
             visual_id,
             devices: false,
+            input_sequence: 0,
         };
 
         drop(_guard);
@@ -607,6 +616,7 @@ pub(crate) struct X11Inner {
     depth: x11::xproto::Depth,
     visual_id: u32,
     devices: bool,
+    input_sequence: u64,
 }
 
 impl X11Inner {
@@ -620,8 +630,10 @@ impl X11Inner {
     where
         F: FnMut(X11Event, &mut ()),
     {
-        {
+        let sequence = {
             let mut inner = inner.lock().unwrap();
+            // all input events emitted for one X event form a frame
+            inner.input_sequence += 1;
             if !inner.windows.is_empty() && !inner.devices {
                 callback(
                     Input(InputEvent::DeviceAdded {
@@ -639,7 +651,8 @@ impl X11Inner {
                 );
                 inner.devices = false;
             }
-        }
+            inner.input_sequence
+        };
 
         use self::X11Event::Input;
 
@@ -672,6 +685,7 @@ impl X11Inner {
                             Input(InputEvent::PointerAxis {
                                 event: X11MouseWheelEvent {
                                     time: button_press.time,
+                                    sequence,
                                     axis: match button_press.detail {
                                         // Up | Down
                                         4 | 5 => Axis::Vertical,
@@ -700,6 +714,7 @@ impl X11Inner {
                             Input(InputEvent::PointerButton {
                                 event: X11MouseInputEvent {
                                     time: button_press.time,
+                                    sequence,
                                     raw: button_press.detail as u32,
                                     state: ButtonState::Pressed,
                                     window,
@@ -724,6 +739,7 @@ impl X11Inner {
                         Input(InputEvent::PointerButton {
                             event: X11MouseInputEvent {
                                 time: button_release.time,
+                                sequence,
                                 raw: button_release.detail as u32,
                                 state: ButtonState::Released,
                                 window,
@@ -743,6 +759,7 @@ impl X11Inner {
                         Input(InputEvent::Keyboard {
                             event: X11KeyboardInputEvent {
                                 time: key_press.time,
+                                sequence,
                                 // X11's keycodes are +8 relative to the libinput keycodes
                                 // that are expected, so subtract 8 from each keycode to
                                 // match libinput.
@@ -776,6 +793,7 @@ impl X11Inner {
                         Input(InputEvent::Keyboard {
                             event: X11KeyboardInputEvent {
                                 time: key_release.time,
+                                sequence,
                                 // X11's keycodes are +8 relative to the libinput keycodes
                                 // that are expected, so subtract 8 from each keycode to
                                 // match libinput.
@@ -806,6 +824,7 @@ impl X11Inner {
                         Input(InputEvent::PointerMotionAbsolute {
                             event: X11MouseMovedEvent {
                                 time: motion_notify.time,
+                                sequence,
                                 x,
                                 y,
                                 size: window_size,
@@ -817,6 +836,98 @@ impl X11Inner {
                 }
             }
 
+            x11::Event::XinputTouchBegin(touch_begin) => {
+                if let Some(window) =
+                    X11Inner::window_ref_from_id(inner, &touch_begin.event).and_then(|w| w.upgrade())
+                {
+                    let window_size = { *window.size.lock().unwrap() };
+
+                    callback(
+                        Input(InputEvent::TouchDown {
+                            event: X11TouchDownEvent {
+                                time: touch_begin.time,
+                                sequence,
+                                id: touch_begin.detail,
+                                x: fp1616_to_f64(touch_begin.event_x),
+                                y: fp1616_to_f64(touch_begin.event_y),
+                                size: window_size,
+                                window: Arc::downgrade(&window),
+                            },
+                        }),
+                        &mut (),
+                    );
+                    callback(
+                        Input(InputEvent::TouchFrame {
+                            event: X11TouchFrameEvent {
+                                time: touch_begin.time,
+                                sequence,
+                                window: Arc::downgrade(&window),
+                            },
+                        }),
+                        &mut (),
+                    );
+                }
+            }
+
+            x11::Event::XinputTouchUpdate(touch_update) => {
+                if let Some(window) =
+                    X11Inner::window_ref_from_id(inner, &touch_update.event).and_then(|w| w.upgrade())
+                {
+                    let window_size = { *window.size.lock().unwrap() };
+
+                    callback(
+                        Input(InputEvent::TouchMotion {
+                            event: X11TouchMotionEvent {
+                                time: touch_update.time,
+                                sequence,
+                                id: touch_update.detail,
+                                x: fp1616_to_f64(touch_update.event_x),
+                                y: fp1616_to_f64(touch_update.event_y),
+                                size: window_size,
+                                window: Arc::downgrade(&window),
+                            },
+                        }),
+                        &mut (),
+                    );
+                    callback(
+                        Input(InputEvent::TouchFrame {
+                            event: X11TouchFrameEvent {
+                                time: touch_update.time,
+                                sequence,
+                                window: Arc::downgrade(&window),
+                            },
+                        }),
+                        &mut (),
+                    );
+                }
+            }
+
+            x11::Event::XinputTouchEnd(touch_end) => {
+                if let Some(window) = X11Inner::window_ref_from_id(inner, &touch_end.event) {
+                    callback(
+                        Input(InputEvent::TouchUp {
+                            event: X11TouchUpEvent {
+                                time: touch_end.time,
+                                sequence,
+                                id: touch_end.detail,
+                                window: window.clone(),
+                            },
+                        }),
+                        &mut (),
+                    );
+                    callback(
+                        Input(InputEvent::TouchFrame {
+                            event: X11TouchFrameEvent {
+                                time: touch_end.time,
+                                sequence,
+                                window,
+                            },
+                        }),
+                        &mut (),
+                    );
+                }
+            }
+
             x11::Event::ConfigureNotify(configure_notify) => {
                 if let Some(window) =
                     X11Inner::window_ref_from_id(inner, &configure_notify.window).and_then(|w| w.upgrade())
@@ -899,11 +1010,13 @@ impl X11Inner {
                 if let Some(window) =
                     X11Inner::window_ref_from_id(inner, &complete_notify.window).and_then(|w| w.upgrade())
                 {
-                    window.last_msc.store(complete_notify.msc, Ordering::SeqCst);
+                    let last_msc = window.last_msc.swap(complete_notify.msc, Ordering::SeqCst);
+                    let last_ust = window.last_ust.swap(complete_notify.ust, Ordering::SeqCst);
 
                     (callback)(
                         X11Event::PresentCompleted {
                             window_id: complete_notify.window,
+                            presentation: present_event(&complete_notify, last_ust, last_msc),
                         },
                         &mut (),
                     );
@@ -923,6 +1036,40 @@ impl X11Inner {
     }
 }
 
+fn present_event(event: &present::CompleteNotifyEvent, last_ust: u64, last_msc: u64) -> Option<PresentEvent> {
+    if event.kind != present::CompleteKind::PIXMAP || event.mode == present::CompleteMode::SKIP {
+        return None;
+    }
+
+    Some(PresentEvent {
+        time: Duration::from_micros(event.ust).into(),
+        refresh: estimate_refresh(last_ust, last_msc, event.ust, event.msc),
+        seq: event.msc,
+        flags: present_flags(event.mode),
+    })
+}
+
+/// Presentation feedback flags for a completed presentation
+fn present_flags(mode: present::CompleteMode) -> PresentFlags {
+    if mode == present::CompleteMode::FLIP {
+        // flips complete on vblank events of the kernel, timestamped by the driver
+        PresentFlags::VSYNC | PresentFlags::HW_CLOCK | PresentFlags::HW_COMPLETION | PresentFlags::ZERO_COPY
+    } else if mode == present::CompleteMode::COPY || mode == present::CompleteMode::SUBOPTIMAL_COPY {
+        // copies are scheduled for a vblank, but the server completes them on its own time
+        PresentFlags::VSYNC
+    } else {
+        PresentFlags::empty()
+    }
+}
+
+/// Refresh interval derived from two presentations, `None` without a previous presentation
+fn estimate_refresh(last_ust: u64, last_msc: u64, ust: u64, msc: u64) -> Option<Duration> {
+    if last_ust == 0 || ust <= last_ust || msc <= last_msc {
+        return None;
+    }
+    Some(Duration::from_nanos((ust - last_ust) * 1000 / (msc - last_msc)))
+}
+
 fn egl_init(_: &X11Inner) -> Result<(DrmNode, OwnedFd), EGLInitError> {
     let display = EGLDisplay::new(X11DefaultDisplay)?;
     let device = EGLDevice::device_for_display(&display)?;
@@ -1005,3 +1152,48 @@ fn dri3_init(x11: &X11Inner) -> Result<(DrmNode, OwnedFd), X11Error> {
 
     Ok((dri_node, unsafe { OwnedFd::from_raw_fd(fd) }))
 }
+
+/// Convert a 16.16 fixed point value of the XInput extension
+fn fp1616_to_f64(value: x11::xinput::Fp1616) -> f64 {
+    value as f64 / 65536.0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use x11rb::protocol::present::CompleteMode;
+
+    use super::{estimate_refresh, present_flags, PresentFlags};
+
+    #[test]
+    fn refresh_is_estimated_from_consecutive_presentations() {
+        // nothing presented yet
+        assert_eq!(estimate_refresh(0, 0, 1_000_000, 60), None);
+        // one frame at 60Hz
+        assert_eq!(
+            estimate_refresh(1_000_000, 60, 1_016_666, 61),
+            Some(Duration::from_nanos(16_666_000))
+        );
+        // frames skipped in between
+        assert_eq!(
+            estimate_refresh(1_000_000, 60, 1_050_000, 63),
+            Some(Duration::from_nanos(16_666_666))
+        );
+        // the counter did not advance
+        assert_eq!(estimate_refresh(1_000_000, 60, 1_016_666, 60), None);
+    }
+
+    #[test]
+    fn only_flips_are_tied_to_the_hardware() {
+        assert_eq!(
+            present_flags(CompleteMode::FLIP),
+            PresentFlags::VSYNC
+                | PresentFlags::HW_CLOCK
+                | PresentFlags::HW_COMPLETION
+                | PresentFlags::ZERO_COPY
+        );
+        assert_eq!(present_flags(CompleteMode::COPY), PresentFlags::VSYNC);
+        assert_eq!(present_flags(CompleteMode::SUBOPTIMAL_COPY), PresentFlags::VSYNC);
+    }
+}
@@ -23,6 +23,7 @@ use x11rb::{
     protocol::{
         present::{self, ConnectionExt as _},
         xfixes::ConnectionExt as _,
+        xinput::{self, ConnectionExt as _},
         xproto::{
             self as x11, AtomEnum, ConnectionExt, CreateWindowAux, Depth, EventMask, PropMode, Screen,
             UnmapNotifyEvent, WindowClass,
@@ -67,6 +68,8 @@ pub(crate) struct WindowInner {
     pub resize: Mutex<Option<Sender<Size<u16, Logical>>>>,
     pub next_serial: AtomicU32,
     pub last_msc: Arc<AtomicU64>,
+    /// Timestamp of the last completed presentation, in microseconds
+    pub last_ust: AtomicU64,
     pub format: DrmFourcc,
     pub depth: Depth,
     pub extensions: Extensions,
@@ -146,6 +149,20 @@ impl WindowInner {
             present::EventMask::COMPLETE_NOTIFY | present::EventMask::IDLE_NOTIFY,
         )?;
 
+        // Touch events are only delivered through XInput 2.2.
+        if extensions.xinput >= Some((2, 2)) {
+            connection.xinput_xi_select_events(
+                window,
+                &[xinput::EventMask {
+                    deviceid: xinput::Device::ALL_MASTER.into(),
+                    mask: vec![(xinput::XIEventMask::TOUCH_BEGIN
+                        | xinput::XIEventMask::TOUCH_UPDATE
+                        | xinput::XIEventMask::TOUCH_END)
+                        .into()],
+                }],
+            )?;
+        }
+
         // Send requests to change window properties while we wait for the window creation request to complete.
         let window = WindowInner {
             connection: weak,
@@ -156,6 +173,7 @@ impl WindowInner {
             size: Mutex::new(size),
             next_serial: AtomicU32::new(0),
             last_msc: Arc::new(AtomicU64::new(0)),
+            last_ust: AtomicU64::new(0),
             format,
             depth,
             extensions,
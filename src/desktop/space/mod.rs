@@ -29,6 +29,8 @@ mod utils;
 
 #[cfg(feature = "wayland_frontend")]
 mod wayland;
+#[cfg(feature = "wayland_frontend")]
+pub use self::wayland::{FocusTarget, PopupRoot};
 
 pub use self::element::*;
 use self::output::*;
@@ -41,6 +43,8 @@ struct InnerElement<E> {
     element: E,
     location: Point<i32, Logical>,
     outputs: HashMap<Output, Rectangle<i32, Logical>>,
+    // value of the activation counter when the element was last activated
+    last_activated: Option<u64>,
 }
 
 /// Represents two dimensional plane to map windows and outputs upon.
@@ -55,6 +59,7 @@ pub struct Space<E: SpaceElement> {
     // in z-order, back to front
     elements: Vec<InnerElement<E>>,
     outputs: Vec<Output>,
+    activation_counter: u64,
     span: tracing::Span,
 }
 
@@ -79,6 +84,7 @@ impl<E: SpaceElement> Default for Space<E> {
             id,
             elements: Default::default(),
             outputs: Default::default(),
+            activation_counter: 0,
             span,
         }
     }
@@ -102,22 +108,31 @@ impl<E: SpaceElement + PartialEq> Space<E> {
     where
         P: Into<Point<i32, Logical>>,
     {
-        let outputs = if let Some(pos) = self.elements.iter().position(|inner| inner.element == element) {
-            self.elements.remove(pos).outputs
-        } else {
-            HashMap::new()
-        };
+        let (outputs, last_activated) =
+            if let Some(pos) = self.elements.iter().position(|inner| inner.element == element) {
+                let old = self.elements.remove(pos);
+                (old.outputs, old.last_activated)
+            } else {
+                (HashMap::new(), None)
+            };
 
         let inner = InnerElement {
             element,
             location: location.into(),
             outputs,
+            last_activated,
         };
         self.insert_elem(inner, activate);
     }
 
     /// Moves an already mapped [`SpaceElement`] to top of the stack
     ///
+    /// The element is placed above all other elements with the same [`SpaceElement::z_index`],
+    /// but stays below elements with a higher z-index. Layer surfaces are ordered by their
+    /// z-index as well, so a window with the default [`RenderZindex::Shell`] index never
+    /// moves above `top` or `overlay` layer surfaces.
+    ///
+    /// If the element already is on top, only its activation state is updated.
     /// This function does nothing for unmapped windows.
     ///
     /// If activate is true it will set the new windows state
@@ -130,8 +145,45 @@ impl<E: SpaceElement + PartialEq> Space<E> {
         }
     }
 
-    fn insert_elem(&mut self, elem: InnerElement<E>, activate: bool) {
+    /// Moves an already mapped [`SpaceElement`] to the bottom of the stack
+    ///
+    /// The element is placed below all other elements with the same [`SpaceElement::z_index`],
+    /// but stays above elements with a lower z-index. Layer surfaces are ordered by their
+    /// z-index as well, so a window with the default [`RenderZindex::Shell`] index stays
+    /// above `background` and `bottom` layer surfaces.
+    ///
+    /// The activation state is not changed. This function does nothing for unmapped windows.
+    pub fn lower_element(&mut self, element: &E) {
+        if let Some(pos) = self.elements.iter().position(|inner| &inner.element == element) {
+            let inner = self.elements.remove(pos);
+            self.elements.insert(0, inner);
+            self.elements
+                .sort_by(|e1, e2| e1.element.z_index().cmp(&e2.element.z_index()));
+        }
+    }
+
+    /// Swaps the stacking positions of two mapped [`SpaceElement`]s
+    ///
+    /// Meant for tiling layouts, that reorder windows without raising them.
+    /// The z-index still takes precedence, so the order of two elements with a different
+    /// [`SpaceElement::z_index`] does not change. Layer surfaces are not affected.
+    ///
+    /// The activation state is not changed. This function does nothing if any
+    /// of the elements is not mapped.
+    pub fn swap_elements(&mut self, a: &E, b: &E) {
+        let pos_a = self.elements.iter().position(|inner| &inner.element == a);
+        let pos_b = self.elements.iter().position(|inner| &inner.element == b);
+        if let (Some(pos_a), Some(pos_b)) = (pos_a, pos_b) {
+            self.elements.swap(pos_a, pos_b);
+            self.elements
+                .sort_by(|e1, e2| e1.element.z_index().cmp(&e2.element.z_index()));
+        }
+    }
+
+    fn insert_elem(&mut self, mut elem: InnerElement<E>, activate: bool) {
         if activate {
+            self.activation_counter += 1;
+            elem.last_activated = Some(self.activation_counter);
             elem.element.set_activate(true);
             for e in self.elements.iter() {
                 e.element.set_activate(false);
@@ -160,6 +212,20 @@ impl<E: SpaceElement + PartialEq> Space<E> {
         self.elements.iter().map(|e| &e.element)
     }
 
+    /// Iterate elements in most-recently-activated order
+    ///
+    /// Elements activated through [`Space::map_element`] or [`Space::raise_element`]
+    /// come first, starting with the most recently activated one. Elements that were
+    /// never activated follow in z-order front to back.
+    ///
+    /// This is the order typically used to cycle the focus between windows (e.g. alt-tab).
+    pub fn focus_candidates(&self) -> impl Iterator<Item = &E> {
+        let mut candidates = self.elements.iter().rev().collect::<Vec<_>>();
+        // stable sort, so never activated elements stay in z-order
+        candidates.sort_by_key(|e| std::cmp::Reverse(e.last_activated));
+        candidates.into_iter().map(|e| &e.element)
+    }
+
     /// Iterate elements on a specific output in z-order back to front
     pub fn elements_for_output<'output>(
         &'output self,
@@ -321,6 +387,54 @@ impl<E: SpaceElement + PartialEq> Space<E> {
             .collect()
     }
 
+    /// Returns the primary [`Output`] of a [`SpaceElement`].
+    ///
+    /// This is the output with the largest intersection with the geometry of the element,
+    /// or the first mapped one of those in case of a tie. Returns `None` if the element is
+    /// not mapped or does not overlap any output.
+    ///
+    /// Unlike [`Space::outputs_for_element`] this does not depend on [`Space::refresh`] being called.
+    pub fn output_for_element(&self, elem: &E) -> Option<Output> {
+        let geometry = self.element_geometry(elem)?;
+        let mut primary: Option<(&Output, i64)> = None;
+        for output in &self.outputs {
+            let overlap = self
+                .output_geometry(output)
+                .and_then(|output_geo| output_geo.intersection(geometry))
+                .map(|overlap| overlap.size.w as i64 * overlap.size.h as i64)
+                .filter(|area| *area > 0);
+            if let Some(area) = overlap {
+                if primary.map_or(true, |(_, max)| area > max) {
+                    primary = Some((output, area));
+                }
+            }
+        }
+        primary.map(|(output, _)| output.clone())
+    }
+
+    /// Returns the geometry of a [`SpaceElement`] in the physical coordinate space of an [`Output`].
+    ///
+    /// The geometry is made relative to the output location, scaled by its current scale and
+    /// transformed by its current transform, matching the framebuffer of the output. It is not clipped,
+    /// so intersecting it with the output mode gives the part of the element visible on the output.
+    ///
+    /// Returns `None` if either the element or the output is not mapped or the output has no mode.
+    pub fn element_geometry_on_output(&self, elem: &E, output: &Output) -> Option<Rectangle<i32, Physical>> {
+        let output_geo = self.output_geometry(output)?;
+        let geometry = self.element_geometry(elem)?;
+        let mode_size = output.current_mode()?.size;
+        let transform = output.current_transform();
+        let scale = output.current_scale().fractional_scale();
+
+        let geometry = Rectangle::from_loc_and_size(geometry.loc - output_geo.loc, geometry.size)
+            .to_physical_precise_round(scale);
+        Some(
+            transform
+                .invert()
+                .transform_rect_in(geometry, &transform.transform_size(mode_size)),
+        )
+    }
+
     /// Refresh some internal values and update client state,
     /// meaning this will handle output enter and leave events
     /// for mapped outputs and windows based on their position.
@@ -687,3 +801,143 @@ where
 
     damage_tracker.render_output(renderer, age, &render_elements, clear_color)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        output::Output,
+        utils::{IsAlive, Logical, Point, Rectangle},
+    };
+
+    use super::{Space, SpaceElement};
+
+    #[derive(Debug, PartialEq)]
+    struct TestElement(u32);
+
+    impl SpaceElement for TestElement {
+        fn bbox(&self) -> Rectangle<i32, Logical> {
+            Rectangle::from_loc_and_size((0, 0), (10, 10))
+        }
+        fn is_in_input_region(&self, _point: &Point<f64, Logical>) -> bool {
+            true
+        }
+        fn set_activate(&self, _activated: bool) {}
+        fn output_enter(&self, _output: &Output, _overlap: Rectangle<i32, Logical>) {}
+        fn output_leave(&self, _output: &Output) {}
+    }
+
+    impl IsAlive for TestElement {
+        fn alive(&self) -> bool {
+            true
+        }
+    }
+
+    fn candidates(space: &Space<TestElement>) -> Vec<u32> {
+        space.focus_candidates().map(|e| e.0).collect()
+    }
+
+    #[test]
+    fn focus_candidates_in_activation_order() {
+        let mut space = Space::default();
+        space.map_element(TestElement(1), (0, 0), true);
+        space.map_element(TestElement(2), (0, 0), true);
+        space.map_element(TestElement(3), (0, 0), false);
+        space.map_element(TestElement(4), (0, 0), false);
+
+        // never activated elements follow in z-order front to back
+        assert_eq!(candidates(&space), vec![2, 1, 4, 3]);
+
+        space.raise_element(&TestElement(1), true);
+        assert_eq!(candidates(&space), vec![1, 2, 4, 3]);
+
+        // raising without activation only changes the z-order
+        space.raise_element(&TestElement(3), false);
+        assert_eq!(candidates(&space), vec![1, 2, 3, 4]);
+
+        // remapping keeps the activation history
+        space.map_element(TestElement(2), (5, 5), false);
+        assert_eq!(candidates(&space), vec![1, 2, 3, 4]);
+
+        space.unmap_elem(&TestElement(1));
+        assert_eq!(candidates(&space), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn lower_and_swap_elements() {
+        let mut space = Space::default();
+        for id in 1..=4 {
+            space.map_element(TestElement(id), (0, 0), false);
+        }
+        let order = |space: &Space<TestElement>| space.elements().map(|e| e.0).collect::<Vec<_>>();
+        assert_eq!(order(&space), vec![1, 2, 3, 4]);
+
+        space.lower_element(&TestElement(3));
+        assert_eq!(order(&space), vec![3, 1, 2, 4]);
+
+        // lowering the bottom element keeps it there
+        space.lower_element(&TestElement(3));
+        assert_eq!(order(&space), vec![3, 1, 2, 4]);
+
+        space.swap_elements(&TestElement(3), &TestElement(4));
+        assert_eq!(order(&space), vec![4, 1, 2, 3]);
+
+        // unmapped elements are ignored
+        space.swap_elements(&TestElement(1), &TestElement(5));
+        space.lower_element(&TestElement(5));
+        assert_eq!(order(&space), vec![4, 1, 2, 3]);
+    }
+
+    #[cfg(feature = "test_utils")]
+    #[test]
+    fn element_geometry_on_scaled_and_transformed_output() {
+        use crate::{output::Scale, testing::test_output, utils::Transform};
+
+        let output = test_output("test", (1920, 1080));
+        output.change_current_state(None, Some(Transform::Normal), Some(Scale::Integer(2)), None);
+
+        let mut space = Space::default();
+        space.map_output(&output, (100, 0));
+        assert_eq!(space.element_geometry_on_output(&TestElement(1), &output), None);
+
+        space.map_element(TestElement(1), (110, 20), false);
+        assert_eq!(
+            space.element_geometry_on_output(&TestElement(1), &output),
+            Some(Rectangle::from_loc_and_size((20, 40), (20, 20)))
+        );
+
+        // rotated outputs have their framebuffer in portrait orientation
+        output.change_current_state(None, Some(Transform::_90), None, None);
+        space.map_element(TestElement(1), (110, 20), false);
+        assert_eq!(
+            space.element_geometry_on_output(&TestElement(1), &output),
+            Some(Rectangle::from_loc_and_size((40, 1040), (20, 20)))
+        );
+    }
+
+    #[cfg(feature = "test_utils")]
+    #[test]
+    fn output_for_element_picks_largest_overlap() {
+        use crate::testing::test_output;
+
+        let left = test_output("left", (100, 100));
+        let right = test_output("right", (100, 100));
+
+        let mut space = Space::default();
+        space.map_output(&left, (0, 0));
+        space.map_output(&right, (100, 0));
+        assert_eq!(space.output_for_element(&TestElement(1)), None);
+
+        space.map_element(TestElement(1), (97, 0), false);
+        assert_eq!(space.output_for_element(&TestElement(1)), Some(right.clone()));
+
+        space.map_element(TestElement(1), (93, 0), false);
+        assert_eq!(space.output_for_element(&TestElement(1)), Some(left.clone()));
+
+        // ties go to the output mapped first
+        space.map_element(TestElement(1), (95, 0), false);
+        assert_eq!(space.output_for_element(&TestElement(1)), Some(left));
+
+        space.map_element(TestElement(1), (300, 0), false);
+        assert_eq!(space.output_for_element(&TestElement(1)), None);
+    }
+}
@@ -8,11 +8,21 @@ use wayland_server::{protocol::wl_surface::WlSurface, Resource, Weak as WlWeak};
 
 use crate::{
     backend::renderer::utils::RendererSurfaceStateUserData,
+    desktop::{
+        layer_map_for_output, utils::under_from_surface_tree, LayerMap, LayerSurface, PopupKind,
+        PopupManager, WindowSurfaceType,
+    },
     output::{Output, WeakOutput},
     utils::{Logical, Point, Rectangle},
-    wayland::compositor::{with_surface_tree_downward, TraversalAction},
+    wayland::{
+        compositor::{with_surface_tree_downward, TraversalAction},
+        seat::WaylandFocus,
+        shell::wlr_layer::Layer as WlrLayer,
+    },
 };
 
+use super::{InnerElement, Space, SpaceElement};
+
 mod layer;
 mod window;
 #[cfg(feature = "xwayland")]
@@ -100,6 +110,138 @@ fn output_leave(output: &Output, surface_list: &mut HashSet<WlWeak<WlSurface>>,
     }
 }
 
+/// Target of an input event found by [`Space::surface_and_element_under`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum FocusTarget<E> {
+    /// An element mapped in the space
+    ///
+    /// Depending on the elements of the space this is a toplevel window
+    /// or an X11 window, including override-redirect ones.
+    Element(E),
+    /// A popup
+    Popup {
+        /// The popup under the point
+        popup: PopupKind,
+        /// The root of the popup chain the popup belongs to
+        root: PopupRoot<E>,
+    },
+    /// A layer surface of an output mapped in the space
+    LayerSurface(LayerSurface),
+}
+
+/// Root of a popup chain
+#[derive(Debug, Clone, PartialEq)]
+pub enum PopupRoot<E> {
+    /// The popup chain belongs to an element of the space
+    Element(E),
+    /// The popup chain belongs to a layer surface
+    LayerSurface(LayerSurface),
+}
+
+impl<E: SpaceElement + WaylandFocus + PartialEq + Clone> Space<E> {
+    /// Finds the topmost focus target under this point and returns it together with
+    /// the location of the target relative to this space.
+    ///
+    /// Targets are tested in global z-order, which is from top to bottom:
+    /// - [`Overlay`](WlrLayer::Overlay) and [`Top`](WlrLayer::Top) layer surfaces
+    /// - the elements of the space
+    /// - [`Bottom`](WlrLayer::Bottom) and [`Background`](WlrLayer::Background) layer surfaces
+    ///
+    /// Popups are tested before the element or layer surface they belong to.
+    /// `surface_type` limits the types of surfaces taken into account, so passing a
+    /// type without [`WindowSurfaceType::POPUP`] ignores all popups.
+    ///
+    /// Layer surfaces are only taken into account for the output under the point.
+    pub fn surface_and_element_under<P: Into<Point<f64, Logical>>>(
+        &self,
+        point: P,
+        surface_type: WindowSurfaceType,
+    ) -> Option<(FocusTarget<E>, Point<i32, Logical>)> {
+        let point = point.into();
+        let layers = self.output_under(point).next().map(|output| {
+            let output_location = self.output_geometry(output).unwrap().loc;
+            (layer_map_for_output(output), output_location)
+        });
+
+        let layer_under = |layer: WlrLayer| {
+            layers
+                .as_ref()
+                .and_then(|(map, location)| layer_target_under(map, layer, *location, point, surface_type))
+        };
+
+        layer_under(WlrLayer::Overlay)
+            .or_else(|| layer_under(WlrLayer::Top))
+            .or_else(|| {
+                self.elements
+                    .iter()
+                    .rev()
+                    .find_map(|e| element_target_under(e, point, surface_type))
+            })
+            .or_else(|| layer_under(WlrLayer::Bottom))
+            .or_else(|| layer_under(WlrLayer::Background))
+    }
+}
+
+fn popup_under(
+    surface: &WlSurface,
+    geometry_location: Point<i32, Logical>,
+    point: Point<f64, Logical>,
+    surface_type: WindowSurfaceType,
+) -> Option<(PopupKind, Point<i32, Logical>)> {
+    if !surface_type.contains(WindowSurfaceType::POPUP) {
+        return None;
+    }
+
+    PopupManager::popups_for_surface(surface).find_map(|(popup, location)| {
+        let popup_location = geometry_location + location - popup.geometry().loc;
+        under_from_surface_tree(popup.wl_surface(), point, popup_location, surface_type)
+            .map(|_| (popup, popup_location))
+    })
+}
+
+fn element_target_under<E: SpaceElement + WaylandFocus + Clone>(
+    elem: &InnerElement<E>,
+    point: Point<f64, Logical>,
+    surface_type: WindowSurfaceType,
+) -> Option<(FocusTarget<E>, Point<i32, Logical>)> {
+    let render_location = elem.render_location();
+    match elem.element.wl_surface() {
+        Some(surface) => {
+            if let Some((popup, location)) = popup_under(&surface, elem.location, point, surface_type) {
+                let root = PopupRoot::Element(elem.element.clone());
+                return Some((FocusTarget::Popup { popup, root }, location));
+            }
+            under_from_surface_tree(&surface, point, render_location, surface_type)
+                .map(|_| (FocusTarget::Element(elem.element.clone()), render_location))
+        }
+        // elements without a surface can only be tested as a whole
+        None => (surface_type.contains(WindowSurfaceType::TOPLEVEL)
+            && elem.bbox().to_f64().contains(point)
+            && elem
+                .element
+                .is_in_input_region(&(point - render_location.to_f64())))
+        .then(|| (FocusTarget::Element(elem.element.clone()), render_location)),
+    }
+}
+
+fn layer_target_under<E>(
+    map: &LayerMap,
+    layer: WlrLayer,
+    output_location: Point<i32, Logical>,
+    point: Point<f64, Logical>,
+    surface_type: WindowSurfaceType,
+) -> Option<(FocusTarget<E>, Point<i32, Logical>)> {
+    map.layers_on(layer).rev().find_map(|l| {
+        let location = output_location + map.layer_geometry(l)?.loc;
+        if let Some((popup, popup_location)) = popup_under(l.wl_surface(), location, point, surface_type) {
+            let root = PopupRoot::LayerSurface(l.clone());
+            return Some((FocusTarget::Popup { popup, root }, popup_location));
+        }
+        under_from_surface_tree(l.wl_surface(), point, location, surface_type)
+            .map(|_| (FocusTarget::LayerSurface(l.clone()), location))
+    })
+}
+
 #[derive(Debug, Default)]
 struct WindowOutputState {
     output_overlap: HashMap<WeakOutput, Rectangle<i32, Logical>>,
@@ -45,6 +45,7 @@ pub struct LayerMap {
     layers: IndexSet<LayerSurface>,
     output: WeakOutput,
     zone: Rectangle<i32, Logical>,
+    zone_changed: bool,
     // surfaces for tracking enter and leave events
     surfaces: HashSet<Weak<WlSurface>>,
 }
@@ -64,23 +65,29 @@ pub fn layer_map_for_output(o: &Output) -> RefMut<'_, LayerMap> {
         RefCell::new(LayerMap {
             layers: IndexSet::new(),
             output: o.downgrade(),
-            zone: Rectangle::from_loc_and_size(
-                (0, 0),
-                o.current_mode()
-                    .map(|mode| {
-                        mode.size
-                            .to_f64()
-                            .to_logical(o.current_scale().fractional_scale())
-                            .to_i32_round()
-                    })
-                    .unwrap_or_else(|| (0, 0).into()),
-            ),
+            zone: output_area(o),
+            zone_changed: false,
             surfaces: HashSet::new(),
         })
     });
     userdata.get::<RefCell<LayerMap>>().unwrap().borrow_mut()
 }
 
+/// Area of the output in logical coordinates, relative to the output
+pub(crate) fn output_area(o: &Output) -> Rectangle<i32, Logical> {
+    Rectangle::from_loc_and_size(
+        (0, 0),
+        o.current_mode()
+            .map(|mode| {
+                mode.size
+                    .to_f64()
+                    .to_logical(o.current_scale().fractional_scale())
+                    .to_i32_round()
+            })
+            .unwrap_or_else(|| (0, 0).into()),
+    )
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum LayerError {
     #[error("Layer is already mapped to a different map")]
@@ -151,6 +158,33 @@ impl LayerMap {
         self.zone
     }
 
+    /// Return the area of this output usable by windows, relative to the output.
+    ///
+    /// This is the area maximized windows should cover, see
+    /// [`Window::send_maximize_configure_for_output`](crate::desktop::Window::send_maximize_configure_for_output).
+    /// It is equal to [`LayerMap::non_exclusive_zone`].
+    pub fn usable_area(&self) -> Rectangle<i32, Logical> {
+        self.zone
+    }
+
+    /// Returns the new usable area, if it changed since the last call.
+    ///
+    /// The usable area changes, when a layer surface with an exclusive zone is mapped or unmapped,
+    /// a layer surface changes its exclusive zone (after [`LayerMap::arrange`] was called),
+    /// or the output mode changes. Maximized windows on this output should be re-configured
+    /// in that case.
+    pub fn take_usable_area_change(&mut self) -> Option<Rectangle<i32, Logical>> {
+        std::mem::take(&mut self.zone_changed).then_some(self.zone)
+    }
+
+    fn set_zone(&mut self, zone: Rectangle<i32, Logical>) {
+        if self.zone != zone {
+            trace!("Usable area changed to {:?}", zone);
+            self.zone = zone;
+            self.zone_changed = true;
+        }
+    }
+
     /// Returns the geometry of a given mapped [`LayerSurface`].
     ///
     /// If the surface was not previously mapped onto this layer map,
@@ -251,18 +285,7 @@ impl LayerMap {
             let span = debug_span!("layer_map", output = output.name());
             let _guard = span.enter();
 
-            let output_rect = Rectangle::from_loc_and_size(
-                (0, 0),
-                output
-                    .current_mode()
-                    .map(|mode| {
-                        mode.size
-                            .to_f64()
-                            .to_logical(output.current_scale().fractional_scale())
-                            .to_i32_round()
-                    })
-                    .unwrap_or_else(|| (0, 0).into()),
-            );
+            let output_rect = output_area(&output);
             let mut zone = output_rect;
             trace!("Arranging layers into {:?}", output_rect.size);
 
@@ -357,49 +380,7 @@ impl LayerMap {
 
                 let location: Point<i32, Logical> = (x, y).into();
 
-                if let ExclusiveZone::Exclusive(amount) = data.exclusive_zone {
-                    match data.anchor {
-                        x if x.contains(Anchor::TOP) && x.contains(Anchor::BOTTOM) => {
-                            zone.size.w -= amount as i32;
-                            if x.contains(Anchor::LEFT) {
-                                zone.loc.x += amount as i32 + data.margin.left;
-                                zone.size.w -= data.margin.left;
-                            }
-                            if x.contains(Anchor::RIGHT) {
-                                zone.size.w -= data.margin.right
-                            }
-                        }
-                        x if x.contains(Anchor::LEFT) && x.contains(Anchor::RIGHT) => {
-                            zone.size.h -= amount as i32;
-                            if x.contains(Anchor::TOP) {
-                                zone.loc.y += amount as i32 + data.margin.top;
-                                zone.size.h -= data.margin.top
-                            }
-                            if x.contains(Anchor::BOTTOM) {
-                                zone.size.h -= data.margin.bottom
-                            }
-                        }
-                        x if x == Anchor::all() => {
-                            zone.size.w = 0;
-                            zone.size.h = 0;
-                        }
-                        x if x.contains(Anchor::LEFT) && !x.contains(Anchor::RIGHT) => {
-                            zone.loc.x += amount as i32 + data.margin.left;
-                            zone.size.w -= amount as i32 + data.margin.left;
-                        }
-                        x if x.contains(Anchor::TOP) && !x.contains(Anchor::BOTTOM) => {
-                            zone.loc.y += amount as i32 + data.margin.top;
-                            zone.size.h -= amount as i32 + data.margin.top;
-                        }
-                        x if x.contains(Anchor::RIGHT) && !x.contains(Anchor::LEFT) => {
-                            zone.size.w -= amount as i32 + data.margin.right;
-                        }
-                        x if x.contains(Anchor::BOTTOM) && !x.contains(Anchor::TOP) => {
-                            zone.size.h -= amount as i32 + data.margin.bottom;
-                        }
-                        _ => {}
-                    }
-                }
+                zone = exclude_zone(zone, &data);
 
                 trace!("Setting layer to pos {:?} and size {:?}", location, size);
                 let size_changed = layer.0.surface.with_pending_state(|state| {
@@ -429,7 +410,7 @@ impl LayerMap {
             }
 
             trace!("Remaining zone {:?}", zone);
-            self.zone = zone;
+            self.set_zone(zone);
         }
     }
 
@@ -456,6 +437,57 @@ impl LayerMap {
     }
 }
 
+/// Remove the exclusive zone of a layer with the given state from `zone`
+fn exclude_zone(
+    mut zone: Rectangle<i32, Logical>,
+    data: &LayerSurfaceCachedState,
+) -> Rectangle<i32, Logical> {
+    if let ExclusiveZone::Exclusive(amount) = data.exclusive_zone {
+        match data.anchor {
+            x if x.contains(Anchor::TOP) && x.contains(Anchor::BOTTOM) => {
+                zone.size.w -= amount as i32;
+                if x.contains(Anchor::LEFT) {
+                    zone.loc.x += amount as i32 + data.margin.left;
+                    zone.size.w -= data.margin.left;
+                }
+                if x.contains(Anchor::RIGHT) {
+                    zone.size.w -= data.margin.right
+                }
+            }
+            x if x.contains(Anchor::LEFT) && x.contains(Anchor::RIGHT) => {
+                zone.size.h -= amount as i32;
+                if x.contains(Anchor::TOP) {
+                    zone.loc.y += amount as i32 + data.margin.top;
+                    zone.size.h -= data.margin.top
+                }
+                if x.contains(Anchor::BOTTOM) {
+                    zone.size.h -= data.margin.bottom
+                }
+            }
+            x if x == Anchor::all() => {
+                zone.size.w = 0;
+                zone.size.h = 0;
+            }
+            x if x.contains(Anchor::LEFT) && !x.contains(Anchor::RIGHT) => {
+                zone.loc.x += amount as i32 + data.margin.left;
+                zone.size.w -= amount as i32 + data.margin.left;
+            }
+            x if x.contains(Anchor::TOP) && !x.contains(Anchor::BOTTOM) => {
+                zone.loc.y += amount as i32 + data.margin.top;
+                zone.size.h -= amount as i32 + data.margin.top;
+            }
+            x if x.contains(Anchor::RIGHT) && !x.contains(Anchor::LEFT) => {
+                zone.size.w -= amount as i32 + data.margin.right;
+            }
+            x if x.contains(Anchor::BOTTOM) && !x.contains(Anchor::TOP) => {
+                zone.size.h -= amount as i32 + data.margin.bottom;
+            }
+            _ => {}
+        }
+    }
+    zone
+}
+
 #[derive(Debug, Default)]
 pub struct LayerState {
     pub location: Point<i32, Logical>,
@@ -780,3 +812,162 @@ impl WaylandFocus for LayerSurface {
         self.0.surface.wl_surface().id().same_client_as(object_id)
     }
 }
+
+#[cfg(all(test, feature = "test_utils"))]
+mod tests {
+    use super::layer_map_for_output;
+    use crate::{output::Mode, testing::test_output, utils::Rectangle};
+
+    mod layer_shell {
+        use wayland_client::protocol::wl_compositor::WlCompositor;
+        use wayland_protocols::xdg::shell::client::xdg_wm_base::XdgWmBase;
+        use wayland_protocols_wlr::layer_shell::v1::client::{
+            zwlr_layer_shell_v1::{Layer, ZwlrLayerShellV1},
+            zwlr_layer_surface_v1::Anchor,
+        };
+        use wayland_server::protocol::{wl_output::WlOutput, wl_seat, wl_surface::WlSurface};
+
+        use crate::{
+            desktop::{layer_map_for_output, LayerSurface, Window},
+            output::Output,
+            testing::{test_output, Recorded, TestCompositor},
+            utils::{Rectangle, Serial},
+            wayland::{
+                compositor::{CompositorHandler, CompositorState},
+                shell::{
+                    wlr_layer::{self, WlrLayerShellHandler, WlrLayerShellState},
+                    xdg::{PopupSurface, PositionerState, ToplevelSurface, XdgShellHandler, XdgShellState},
+                },
+            },
+        };
+
+        struct State {
+            compositor_state: CompositorState,
+            xdg_shell_state: XdgShellState,
+            layer_shell_state: WlrLayerShellState,
+            output: Output,
+            windows: Vec<Window>,
+        }
+
+        impl CompositorHandler for State {
+            fn compositor_state(&mut self) -> &mut CompositorState {
+                &mut self.compositor_state
+            }
+
+            fn commit(&mut self, _surface: &WlSurface) {
+                layer_map_for_output(&self.output).arrange();
+            }
+        }
+
+        impl XdgShellHandler for State {
+            fn xdg_shell_state(&mut self) -> &mut XdgShellState {
+                &mut self.xdg_shell_state
+            }
+
+            fn new_toplevel(&mut self, surface: ToplevelSurface) {
+                self.windows.push(Window::new(surface));
+            }
+            fn new_popup(&mut self, _surface: PopupSurface, _positioner: PositionerState) {}
+            fn grab(&mut self, _surface: PopupSurface, _seat: wl_seat::WlSeat, _serial: Serial) {}
+        }
+
+        impl WlrLayerShellHandler for State {
+            fn shell_state(&mut self) -> &mut WlrLayerShellState {
+                &mut self.layer_shell_state
+            }
+
+            fn new_layer_surface(
+                &mut self,
+                surface: wlr_layer::LayerSurface,
+                _output: Option<WlOutput>,
+                _layer: wlr_layer::Layer,
+                namespace: String,
+            ) {
+                layer_map_for_output(&self.output)
+                    .map_layer(&LayerSurface::new(surface, namespace))
+                    .unwrap();
+            }
+        }
+
+        crate::delegate_compositor!(State);
+        crate::delegate_xdg_shell!(State);
+        crate::delegate_layer_shell!(State);
+
+        #[test]
+        fn panel_appears_while_maximized() {
+            let mut compositor = TestCompositor::new(|dh, _| State {
+                compositor_state: CompositorState::new::<State>(dh),
+                xdg_shell_state: XdgShellState::new::<State>(dh),
+                layer_shell_state: WlrLayerShellState::new::<State>(dh),
+                output: test_output("test", (1920, 1080)),
+                windows: Vec::new(),
+            });
+            let mut client = compositor.connect();
+            let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+            let wm_base = client.bind::<XdgWmBase, _>(&mut compositor);
+            let layer_shell = client.bind::<ZwlrLayerShellV1, _>(&mut compositor);
+            let qh = client.queue_handle();
+
+            // a window gets maximized before any panel is mapped
+            let surface = wl_compositor.create_surface(&qh, Recorded);
+            let xdg_surface = wm_base.get_xdg_surface(&surface, &qh, Recorded);
+            let _toplevel = xdg_surface.get_toplevel(&qh, Recorded);
+            surface.commit();
+            client.roundtrip(&mut compositor);
+
+            let output = compositor.state.output.clone();
+            let window = compositor.state.windows[0].clone();
+            {
+                let mut map = layer_map_for_output(&output);
+                map.arrange();
+                assert_eq!(map.take_usable_area_change(), None);
+                assert_eq!(
+                    window.send_maximize_configure_for_output(&map),
+                    Rectangle::from_loc_and_size((0, 0), (1920, 1080))
+                );
+            }
+
+            // a panel with an exclusive zone is mapped afterwards
+            let panel = wl_compositor.create_surface(&qh, Recorded);
+            let layer_surface =
+                layer_shell.get_layer_surface(&panel, None, Layer::Top, "panel".into(), &qh, Recorded);
+            layer_surface.set_size(0, 30);
+            layer_surface.set_anchor(Anchor::Top | Anchor::Left | Anchor::Right);
+            layer_surface.set_exclusive_zone(30);
+            panel.commit();
+            client.roundtrip(&mut compositor);
+
+            // the compositor is notified to re-configure the maximized window,
+            // which works while the layer map is still borrowed
+            let usable_area = Rectangle::from_loc_and_size((0, 30), (1920, 1050));
+            let mut map = layer_map_for_output(&output);
+            assert_eq!(map.take_usable_area_change(), Some(usable_area));
+            assert_eq!(map.take_usable_area_change(), None);
+            assert_eq!(window.send_maximize_configure_for_output(&map), usable_area);
+            assert_eq!(
+                window.send_fullscreen_configure_for_output(&output),
+                Rectangle::from_loc_and_size((0, 0), (1920, 1080))
+            );
+        }
+    }
+
+    #[test]
+    fn mode_change_changes_usable_area() {
+        let output = test_output("test", (1920, 1080));
+        let mut map = layer_map_for_output(&output);
+        output.change_current_state(
+            Some(Mode {
+                size: (1280, 720).into(),
+                refresh: 60_000,
+            }),
+            None,
+            None,
+            None,
+        );
+        map.arrange();
+        assert_eq!(
+            map.take_usable_area_change(),
+            Some(Rectangle::from_loc_and_size((0, 0), (1280, 720)))
+        );
+    }
+}
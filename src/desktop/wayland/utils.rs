@@ -5,20 +5,22 @@ use crate::{
         element::{
             PrimaryScanoutOutput, RenderElementPresentationState, RenderElementState, RenderElementStates,
         },
-        utils::RendererSurfaceState,
+        utils::{flush_buffer_releases, RendererSurfaceState},
     },
-    desktop::WindowSurfaceType,
+    desktop::{PopupManager, WindowSurfaceType},
     output::{Output, WeakOutput},
-    utils::{Logical, Point, Rectangle, Time},
+    utils::{duration_as_msec, Logical, Point, Rectangle, Time},
     wayland::{
-        compositor::{with_surface_tree_downward, SurfaceAttributes, SurfaceData, TraversalAction},
+        compositor::{
+            with_states, with_surface_tree_downward, SurfaceAttributes, SurfaceData, TraversalAction,
+        },
         dmabuf::{DmabufFeedback, SurfaceDmabufFeedbackState},
         presentation::{PresentationFeedbackCachedState, PresentationFeedbackCallback},
     },
 };
 use std::{cell::RefCell, sync::Mutex, time::Duration};
 use wayland_protocols::wp::presentation_time::server::wp_presentation_feedback;
-use wayland_server::protocol::wl_surface;
+use wayland_server::protocol::{wl_callback, wl_surface};
 
 impl RendererSurfaceState {
     fn contains_point<P: Into<Point<f64, Logical>>>(&self, attrs: &SurfaceAttributes, point: P) -> bool {
@@ -146,6 +148,94 @@ where
 
 type SurfacePrimaryScanoutOutput = Mutex<PrimaryScanoutOutput>;
 
+/// Returns the location of a (sub-)surface of a surface tree.
+///
+/// - `location` is the location of the root surface, it is added to the returned location.
+///
+/// In case `target` is not a mapped surface of the tree [`None`] is returned.
+pub fn location_in_surface_tree<P>(
+    surface: &wl_surface::WlSurface,
+    target: &wl_surface::WlSurface,
+    location: P,
+) -> Option<Point<i32, Logical>>
+where
+    P: Into<Point<i32, Logical>>,
+{
+    let found = RefCell::new(None);
+    with_surface_tree_downward(
+        surface,
+        location.into(),
+        |wl_surface, states, location: &Point<i32, Logical>| {
+            let mut location = *location;
+            let data = states.data_map.get::<RefCell<RendererSurfaceState>>();
+
+            if let Some(surface_view) = data.and_then(|d| d.borrow().surface_view) {
+                location += surface_view.offset;
+                if wl_surface == target {
+                    *found.borrow_mut() = Some(location);
+                    return TraversalAction::Break;
+                }
+                TraversalAction::DoChildren(location)
+            } else {
+                // We are completely hidden
+                TraversalAction::SkipChildren
+            }
+        },
+        |_, _, _| {},
+        |_, _, _| true,
+    );
+    found.into_inner()
+}
+
+/// Finds the topmost surface of a window-like surface tree under a point in the space it is mapped in.
+///
+/// `location` is the location of the window geometry in that space, `geometry_location` the offset of the
+/// window geometry relative to the root surface. Returns the surface together with the point relative to it.
+pub(crate) fn surface_under_in_space(
+    surface: &wl_surface::WlSurface,
+    geometry_location: Point<i32, Logical>,
+    location: Point<i32, Logical>,
+    point: Point<f64, Logical>,
+    surface_type: WindowSurfaceType,
+) -> Option<(wl_surface::WlSurface, Point<f64, Logical>)> {
+    if surface_type.contains(WindowSurfaceType::POPUP) {
+        for (popup, popup_location) in PopupManager::popups_for_surface(surface) {
+            let offset = location + popup_location - popup.geometry().loc;
+            if let Some((surface, surface_location)) =
+                under_from_surface_tree(popup.wl_surface(), point, offset, surface_type)
+            {
+                return Some((surface, point - surface_location.to_f64()));
+            }
+        }
+    }
+
+    let render_location = location - geometry_location;
+    under_from_surface_tree(surface, point, render_location, surface_type)
+        .map(|(surface, surface_location)| (surface, point - surface_location.to_f64()))
+}
+
+/// Converts a point relative to a surface of a window-like surface tree into the space it is mapped in.
+///
+/// Inverse of [`surface_under_in_space`], returns [`None`] if `target` is not a mapped surface of the
+/// tree or its popups.
+pub(crate) fn surface_point_to_space(
+    surface: &wl_surface::WlSurface,
+    geometry_location: Point<i32, Logical>,
+    location: Point<i32, Logical>,
+    target: &wl_surface::WlSurface,
+    point: Point<f64, Logical>,
+) -> Option<Point<f64, Logical>> {
+    let render_location = location - geometry_location;
+    location_in_surface_tree(surface, target, render_location)
+        .or_else(|| {
+            PopupManager::popups_for_surface(surface).find_map(|(popup, popup_location)| {
+                let offset = location + popup_location - popup.geometry().loc;
+                location_in_surface_tree(popup.wl_surface(), target, offset)
+            })
+        })
+        .map(|surface_location| surface_location.to_f64() + point)
+}
+
 /// Run a closure on all surfaces of a surface tree
 pub fn with_surfaces_surface_tree<F>(surface: &wl_surface::WlSurface, mut processor: F)
 where
@@ -211,23 +301,66 @@ where
 /// throttle threshold. If the threshold is `None` this will never send frame callbacks
 /// for a surface that is not visible. Specifying [`Duration::ZERO`] as the throttle threshold
 /// will always send frame callbacks for non visible surfaces.
+///
+/// Frame callbacks of surfaces limited by [`set_frame_rate_limit`] are held back until the
+/// minimum interval has passed and are sent with the next frame afterwards.
 pub fn send_frames_surface_tree<T, F>(
     surface: &wl_surface::WlSurface,
     output: &Output,
     time: T,
     throttle: Option<Duration>,
-    mut primary_scan_out_output: F,
+    primary_scan_out_output: F,
 ) where
     T: Into<Duration>,
     F: FnMut(&wl_surface::WlSurface, &SurfaceData) -> Option<Output>,
 {
     let time = time.into();
+    for callback in take_frames_surface_tree(surface, output, time, throttle, primary_scan_out_output) {
+        callback.done(duration_as_msec(time));
+    }
+}
 
+/// Takes the frame callbacks for a surface and its subsurfaces without sending them.
+///
+/// Selects the same callbacks [`send_frames_surface_tree`] would send at the given `time`,
+/// but leaves sending the `done` event to the caller. This allows holding back the callbacks
+/// until the frame was actually presented, e.g. from the page-flip handler of a drm device.
+pub fn take_frames_surface_tree<T, F>(
+    surface: &wl_surface::WlSurface,
+    output: &Output,
+    time: T,
+    throttle: Option<Duration>,
+    mut primary_scan_out_output: F,
+) -> Vec<wl_callback::WlCallback>
+where
+    T: Into<Duration>,
+    F: FnMut(&wl_surface::WlSurface, &SurfaceData) -> Option<Output>,
+{
+    let time = time.into();
+    let mut callbacks = Vec::new();
+    // clients may reuse their buffers once asked for the next frame
+    flush_buffer_releases();
+
+    // the frame rate limit of a surface also applies to its subsurfaces
     with_surface_tree_downward(
         surface,
-        (),
-        |_, _, &()| TraversalAction::DoChildren(()),
-        |surface, states, &()| {
+        None,
+        |_, states, parent_interval| {
+            TraversalAction::DoChildren(frame_rate_limit_interval(states).or(*parent_interval))
+        },
+        |surface, states, parent_interval| {
+            let interval = frame_rate_limit_interval(states).or(*parent_interval);
+            if let Some(interval) = interval {
+                states
+                    .data_map
+                    .insert_if_missing_threadsafe(SurfaceFrameRateLimit::default);
+                let limit = states.data_map.get::<SurfaceFrameRateLimit>().unwrap();
+                if !limit.elapsed(time, interval) {
+                    // keep the callbacks queued until the interval has passed
+                    return;
+                }
+            }
+
             states
                 .data_map
                 .insert_if_missing_threadsafe(SurfaceFrameThrottlingState::default);
@@ -248,18 +381,71 @@ pub fn send_frames_surface_tree<T, F>(
             if send_frame_callback {
                 // the surface may not have any user_data if it is a subsurface and has not
                 // yet been commited
-                for callback in states
-                    .cached_state
-                    .current::<SurfaceAttributes>()
-                    .frame_callbacks
-                    .drain(..)
-                {
-                    callback.done(time.as_millis() as u32);
+                let len = callbacks.len();
+                callbacks.extend(states.take_frame_callbacks());
+                if interval.is_some() && callbacks.len() > len {
+                    let limit = states.data_map.get::<SurfaceFrameRateLimit>().unwrap();
+                    limit.0.lock().unwrap().last_frame = Some(time);
                 }
             }
         },
-        |_, _, &()| true,
+        |_, _, _| true,
     );
+
+    callbacks
+}
+
+/// Limits the rate at which frame callbacks are sent to a surface and its subsurfaces
+///
+/// [`send_frames_surface_tree`] and [`take_frames_surface_tree`] hold back the frame callbacks
+/// of the surface until at least `1 / fps` seconds have passed since the last ones were sent.
+/// Held back callbacks are sent with the next frame after that interval. This can be used to
+/// throttle clients in the background, e.g. minimized or occluded windows, to save power.
+///
+/// `None` removes the limit, as does any `fps` value that is not a positive, finite number
+/// or too small for its interval to be represented as a [`Duration`].
+pub fn set_frame_rate_limit(surface: &wl_surface::WlSurface, fps: Option<f64>) {
+    let fps = fps.filter(|fps| fps_interval(*fps).is_some());
+    with_states(surface, |states| {
+        states
+            .data_map
+            .insert_if_missing_threadsafe(SurfaceFrameRateLimit::default);
+        states
+            .data_map
+            .get::<SurfaceFrameRateLimit>()
+            .unwrap()
+            .0
+            .lock()
+            .unwrap()
+            .fps = fps;
+    });
+}
+
+/// Retrieve the frame rate limit of a surface set with [`set_frame_rate_limit`]
+///
+/// This does not include limits inherited from a parent surface.
+pub fn frame_rate_limit(surface: &wl_surface::WlSurface) -> Option<f64> {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .get::<SurfaceFrameRateLimit>()
+            .and_then(|limit| limit.0.lock().unwrap().fps)
+    })
+}
+
+fn frame_rate_limit_interval(states: &SurfaceData) -> Option<Duration> {
+    states
+        .data_map
+        .get::<SurfaceFrameRateLimit>()
+        .and_then(|limit| limit.0.lock().unwrap().fps)
+        .and_then(fps_interval)
+}
+
+fn fps_interval(fps: f64) -> Option<Duration> {
+    let secs = 1.0 / fps;
+    // `Duration::from_secs_f64` panics for values it can not represent
+    (fps.is_finite() && fps > 0.0 && secs < Duration::MAX.as_secs_f64())
+        .then(|| Duration::from_secs_f64(secs))
 }
 
 /// Sends dmabuf feedback for a surface and its subsurfaces with the given select function.
@@ -496,3 +682,152 @@ impl SurfaceFrameThrottlingState {
         }
     }
 }
+
+#[derive(Debug, Default)]
+struct SurfaceFrameRateLimit(Mutex<FrameRateLimitState>);
+
+#[derive(Debug, Default)]
+struct FrameRateLimitState {
+    fps: Option<f64>,
+    last_frame: Option<Duration>,
+}
+
+impl SurfaceFrameRateLimit {
+    fn elapsed(&self, time: Duration, interval: Duration) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .last_frame
+            .map(|last| time.saturating_sub(last) >= interval)
+            .unwrap_or(true)
+    }
+}
+
+#[cfg(all(test, feature = "test_utils"))]
+mod tests {
+    use std::time::Duration;
+
+    use wayland_client::{
+        protocol::{
+            wl_compositor::WlCompositor,
+            wl_shm::{self, WlShm},
+            wl_subcompositor::WlSubcompositor,
+            wl_surface::WlSurface as ClientSurface,
+        },
+        Proxy,
+    };
+    use wayland_server::protocol::wl_surface::WlSurface;
+
+    use super::{
+        frame_rate_limit, set_frame_rate_limit, surface_point_to_space, surface_under_in_space,
+        take_frames_surface_tree,
+    };
+    use crate::{
+        desktop::WindowSurfaceType,
+        testing::{test_output, Recorded, ShmTestState, TestClient, TestCompositor},
+        utils::{Logical, Point},
+    };
+
+    fn server_surface(
+        compositor: &TestCompositor<ShmTestState>,
+        client: &TestClient,
+        surface: &ClientSurface,
+    ) -> WlSurface {
+        client
+            .client()
+            .object_from_protocol_id::<WlSurface>(&compositor.handle(), surface.id().protocol_id())
+            .unwrap()
+    }
+
+    #[test]
+    fn window_with_client_side_shadow() {
+        let mut compositor = TestCompositor::new(|dh, _| ShmTestState::new(dh));
+        let mut client = compositor.connect();
+        let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+        let subcompositor = client.bind::<WlSubcompositor, _>(&mut compositor);
+        let shm = client.bind::<WlShm, _>(&mut compositor);
+        let qh = client.queue_handle();
+
+        let (pool, _file) = client.create_shm_pool(&shm, 80_000);
+        let root_buffer = pool.create_buffer(0, 140, 140, 560, wl_shm::Format::Argb8888, &qh, Recorded);
+        let child_buffer = pool.create_buffer(78_400, 20, 20, 80, wl_shm::Format::Argb8888, &qh, Recorded);
+
+        // a 100x100 window surrounded by a 20px shadow, the window geometry starts at (20, 20)
+        let root = wl_compositor.create_surface(&qh, Recorded);
+        // with a badge sticking out to the left of the shadow
+        let child = wl_compositor.create_surface(&qh, Recorded);
+        let subsurface = subcompositor.get_subsurface(&child, &root, &qh, Recorded);
+        subsurface.set_position(-10, 50);
+        child.attach(Some(&child_buffer), 0, 0);
+        child.commit();
+        root.attach(Some(&root_buffer), 0, 0);
+        root.commit();
+        let unrelated = wl_compositor.create_surface(&qh, Recorded);
+        client.roundtrip(&mut compositor);
+
+        let root = server_surface(&compositor, &client, &root);
+        let child = server_surface(&compositor, &client, &child);
+        let unrelated = server_surface(&compositor, &client, &unrelated);
+        let geometry_location = Point::<i32, Logical>::from((20, 20));
+        // the window geometry is mapped at (500, 300)
+        let location = Point::<i32, Logical>::from((500, 300));
+
+        let under = |x: f64, y: f64| {
+            surface_under_in_space(
+                &root,
+                geometry_location,
+                location,
+                (x, y).into(),
+                WindowSurfaceType::ALL,
+            )
+        };
+        assert_eq!(under(500.0, 300.0), Some((root.clone(), (20.0, 20.0).into())));
+        // the shadow is part of the surface
+        assert_eq!(under(485.0, 285.0), Some((root.clone(), (5.0, 5.0).into())));
+        assert_eq!(under(475.0, 335.0), Some((child.clone(), (5.0, 5.0).into())));
+        assert_eq!(under(470.0, 300.0), None);
+        assert_eq!(
+            surface_under_in_space(
+                &root,
+                geometry_location,
+                location,
+                (475.0, 335.0).into(),
+                WindowSurfaceType::TOPLEVEL,
+            ),
+            None
+        );
+
+        let to_space = |surface: &WlSurface, x: f64, y: f64| {
+            surface_point_to_space(&root, geometry_location, location, surface, (x, y).into())
+        };
+        assert_eq!(to_space(&root, 20.0, 20.0), Some((500.0, 300.0).into()));
+        assert_eq!(to_space(&child, 5.0, 5.0), Some((475.0, 335.0).into()));
+        assert_eq!(to_space(&unrelated, 0.0, 0.0), None);
+    }
+
+    #[test]
+    fn tiny_frame_rate_limit_removes_the_limit() {
+        let mut compositor = TestCompositor::new(|dh, _| ShmTestState::new(dh));
+        let mut client = compositor.connect();
+        let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+        let qh = client.queue_handle();
+
+        let surface = wl_compositor.create_surface(&qh, Recorded);
+        surface.frame(&qh, Recorded);
+        surface.commit();
+        client.roundtrip(&mut compositor);
+        let server = server_surface(&compositor, &client, &surface);
+
+        set_frame_rate_limit(&server, Some(f64::MIN_POSITIVE));
+        assert_eq!(frame_rate_limit(&server), None);
+        set_frame_rate_limit(&server, Some(30.0));
+        assert_eq!(frame_rate_limit(&server), Some(30.0));
+
+        let output = test_output("test", (1920, 1080));
+        set_frame_rate_limit(&server, Some(1e-300));
+        let callbacks = take_frames_surface_tree(&server, &output, Duration::ZERO, None, |_, _| {
+            Some(output.clone())
+        });
+        assert_eq!(callbacks.len(), 1);
+    }
+}
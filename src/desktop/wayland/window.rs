@@ -1,6 +1,6 @@
 use crate::{
     backend::input::KeyState,
-    desktop::{space::RenderZindex, utils::*, PopupManager},
+    desktop::{space::RenderZindex, utils::*, LayerMap, PopupManager},
     input::{
         keyboard::{KeyboardTarget, KeysymHandle, ModifiersState},
         pointer::{AxisFrame, ButtonEvent, MotionEvent, PointerTarget, RelativeMotionEvent},
@@ -26,7 +26,12 @@ use std::{
 use wayland_protocols::{
     wp::presentation_time::server::wp_presentation_feedback, xdg::shell::server::xdg_toplevel,
 };
-use wayland_server::protocol::wl_surface;
+use wayland_server::{
+    protocol::{wl_output::WlOutput, wl_surface},
+    Resource,
+};
+
+use super::layer::output_area;
 
 crate::utils::ids::id_gen!(next_window_id, WINDOW_ID, WINDOW_IDS);
 
@@ -266,6 +271,52 @@ impl Window {
         under_from_surface_tree(surface, point, (0, 0), surface_type)
     }
 
+    /// Finds the topmost surface under a point in the space this window is mapped in.
+    ///
+    /// Unlike [`Window::surface_under`] this takes care of all offsets between the space and
+    /// the surfaces of the window, including the window geometry, which is offset from the
+    /// toplevel surface e.g. by client-side shadows.
+    ///
+    /// - `location` is the location of the window geometry in the space, like returned by
+    ///   [`Space::element_location`](crate::desktop::Space::element_location).
+    ///
+    /// Returns the surface together with `point` relative to it, as expected by clients.
+    pub fn surface_under_global<P: Into<Point<f64, Logical>>>(
+        &self,
+        location: Point<i32, Logical>,
+        point: P,
+        surface_type: WindowSurfaceType,
+    ) -> Option<(wl_surface::WlSurface, Point<f64, Logical>)> {
+        surface_under_in_space(
+            self.0.toplevel.wl_surface(),
+            self.geometry().loc,
+            location,
+            point.into(),
+            surface_type,
+        )
+    }
+
+    /// Converts a point relative to a surface of this window into the space the window is mapped in.
+    ///
+    /// This is the inverse of [`Window::surface_under_global`], e.g. to place popups or input method
+    /// rectangles given relative to a subsurface. Popups tracked by the [`PopupManager`] are considered.
+    ///
+    /// Returns [`None`] if `surface` is not a mapped surface of this window.
+    pub fn surface_point_to_global<P: Into<Point<f64, Logical>>>(
+        &self,
+        location: Point<i32, Logical>,
+        surface: &wl_surface::WlSurface,
+        point: P,
+    ) -> Option<Point<f64, Logical>> {
+        surface_point_to_space(
+            self.0.toplevel.wl_surface(),
+            self.geometry().loc,
+            location,
+            surface,
+            point.into(),
+        )
+    }
+
     /// Returns the underlying xdg toplevel surface
     pub fn toplevel(&self) -> &ToplevelSurface {
         &self.0.toplevel
@@ -276,6 +327,122 @@ impl Window {
         self.0.z_index.store(z_index, Ordering::SeqCst);
     }
 
+    /// Configure this window to be maximized on the output of the given [`LayerMap`]
+    ///
+    /// The window is sized to the usable area of the output, excluding the exclusive zones
+    /// of layer surfaces (see [`LayerMap::usable_area`]).
+    /// Returns the geometry the window should be mapped at, relative to the output.
+    ///
+    /// The layer map is passed in instead of being looked up, so this can be called while
+    /// holding the borrow returned by [`layer_map_for_output`](crate::desktop::layer_map_for_output), e.g. when reacting to
+    /// [`LayerMap::take_usable_area_change`], which requires calling this again for maximized windows.
+    pub fn send_maximize_configure_for_output(&self, layer_map: &LayerMap) -> Rectangle<i32, Logical> {
+        let geometry = layer_map.usable_area();
+        self.0.toplevel.with_pending_state(|state| {
+            state.states.unset(xdg_toplevel::State::Fullscreen);
+            state.states.set(xdg_toplevel::State::Maximized);
+            state.fullscreen_output = None;
+            state.size = Some(geometry.size);
+        });
+        self.0.toplevel.send_configure();
+        self.override_z_index(RenderZindex::Shell as u8);
+        geometry
+    }
+
+    /// Configure this window to be fullscreen on the given output
+    ///
+    /// The window is sized to cover the whole output including the exclusive zones
+    /// of layer surfaces. Returns the geometry the window should be mapped at, relative to the output.
+    /// This does not access the [`LayerMap`] of the output, so it is safe to call while
+    /// holding the borrow returned by [`layer_map_for_output`](crate::desktop::layer_map_for_output).
+    ///
+    /// As mandated by the layer-shell protocol, fullscreen windows are displayed above
+    /// the top layer, so the z-index of the window is raised accordingly.
+    /// Use [`Window::override_z_index`] with [`RenderZindex::Shell`] to restore it
+    /// once the window leaves fullscreen.
+    pub fn send_fullscreen_configure_for_output(&self, output: &Output) -> Rectangle<i32, Logical> {
+        let geometry = output_area(output);
+        let wl_output = self.client_output(output);
+        self.0.toplevel.with_pending_state(|state| {
+            state.states.unset(xdg_toplevel::State::Maximized);
+            state.states.set(xdg_toplevel::State::Fullscreen);
+            state.fullscreen_output = wl_output;
+            state.size = Some(geometry.size);
+        });
+        self.0.toplevel.send_configure();
+        self.override_z_index(RenderZindex::Top as u8 + 1);
+        geometry
+    }
+
+    /// Ask the client to close this window
+    ///
+    /// The client may ask the user for confirmation or ignore the request entirely,
+    /// the window stays mapped until its surface is destroyed.
+    pub fn close(&self) {
+        self.0.toplevel.send_close();
+    }
+
+    /// Ask the client to enter or leave the maximized state
+    ///
+    /// Only the state is changed, the size of the window is left to the client.
+    /// Use [`Window::send_maximize_configure_for_output`] to also size it to an output.
+    /// Leaving the maximized state drops a size set by it.
+    pub fn request_maximize(&self, maximized: bool) {
+        self.0.toplevel.with_pending_state(|state| {
+            if maximized {
+                state.states.set(xdg_toplevel::State::Maximized);
+            } else if state.states.unset(xdg_toplevel::State::Maximized) {
+                state.size = None;
+            }
+        });
+        self.0.toplevel.send_configure();
+    }
+
+    /// Ask the client to enter the fullscreen state, optionally on a given output
+    ///
+    /// Only the state is changed, the size of the window is left to the client.
+    /// Use [`Window::send_fullscreen_configure_for_output`] to also size it to the output
+    /// and [`Window::request_unfullscreen`] to leave the fullscreen state.
+    pub fn request_fullscreen(&self, output: Option<&Output>) {
+        let wl_output = output.and_then(|output| self.client_output(output));
+        self.0.toplevel.with_pending_state(|state| {
+            state.states.unset(xdg_toplevel::State::Maximized);
+            state.states.set(xdg_toplevel::State::Fullscreen);
+            state.fullscreen_output = wl_output;
+        });
+        self.0.toplevel.send_configure();
+    }
+
+    /// Ask the client to leave the fullscreen state
+    ///
+    /// A size set by [`Window::send_fullscreen_configure_for_output`] is dropped as well.
+    pub fn request_unfullscreen(&self) {
+        self.0.toplevel.with_pending_state(|state| {
+            if state.states.unset(xdg_toplevel::State::Fullscreen) {
+                state.size = None;
+            }
+            state.fullscreen_output = None;
+        });
+        self.0.toplevel.send_configure();
+    }
+
+    /// Tell the client this window got minimized
+    ///
+    /// xdg-shell has no minimized state, the window is only deactivated. Hiding it,
+    /// e.g. by unmapping it from its [`Space`](crate::desktop::Space), is up to the compositor.
+    pub fn request_minimize(&self) {
+        self.set_activated(false);
+        self.0.toplevel.send_configure();
+    }
+
+    fn client_output(&self, output: &Output) -> Option<WlOutput> {
+        self.0
+            .toplevel
+            .wl_surface()
+            .client()
+            .and_then(|client| output.client_outputs(&client).into_iter().next())
+    }
+
     /// Returns a [`UserDataMap`] to allow associating arbitrary data with this window.
     pub fn user_data(&self) -> &UserDataMap {
         &self.0.user_data
@@ -352,3 +519,133 @@ impl WaylandFocus for Window {
         Some(self.0.toplevel.wl_surface().clone())
     }
 }
+
+#[cfg(all(test, feature = "test_utils"))]
+mod tests {
+    use std::time::Duration;
+
+    use wayland_client::protocol::wl_compositor::WlCompositor;
+    use wayland_protocols::xdg::shell::{
+        client::{xdg_toplevel, xdg_wm_base::XdgWmBase},
+        server::xdg_toplevel::State as ToplevelState,
+    };
+    use wayland_server::protocol::{wl_seat, wl_surface::WlSurface};
+
+    use super::Window;
+    use crate::{
+        desktop::layer_map_for_output,
+        testing::{test_output, Recorded, TestClient, TestCompositor},
+        utils::Serial,
+        wayland::{
+            compositor::{CompositorHandler, CompositorState},
+            shell::xdg::{PopupSurface, PositionerState, ToplevelSurface, XdgShellHandler, XdgShellState},
+        },
+    };
+
+    struct State {
+        compositor_state: CompositorState,
+        xdg_shell_state: XdgShellState,
+        windows: Vec<Window>,
+    }
+
+    impl CompositorHandler for State {
+        fn compositor_state(&mut self) -> &mut CompositorState {
+            &mut self.compositor_state
+        }
+
+        fn commit(&mut self, _surface: &WlSurface) {}
+    }
+
+    impl XdgShellHandler for State {
+        fn xdg_shell_state(&mut self) -> &mut XdgShellState {
+            &mut self.xdg_shell_state
+        }
+
+        fn new_toplevel(&mut self, surface: ToplevelSurface) {
+            self.windows.push(Window::new(surface));
+        }
+        fn new_popup(&mut self, _surface: PopupSurface, _positioner: PositionerState) {}
+        fn grab(&mut self, _surface: PopupSurface, _seat: wl_seat::WlSeat, _serial: Serial) {}
+    }
+
+    crate::delegate_compositor!(State);
+    crate::delegate_xdg_shell!(State);
+
+    fn window() -> (TestCompositor<State>, TestClient, Window) {
+        let mut compositor = TestCompositor::new(|dh, _| State {
+            compositor_state: CompositorState::new::<State>(dh),
+            xdg_shell_state: XdgShellState::new::<State>(dh),
+            windows: Vec::new(),
+        });
+        let mut client = compositor.connect();
+        let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+        let wm_base = client.bind::<XdgWmBase, _>(&mut compositor);
+        let qh = client.queue_handle();
+
+        let surface = wl_compositor.create_surface(&qh, Recorded);
+        let xdg_surface = wm_base.get_xdg_surface(&surface, &qh, Recorded);
+        let _toplevel = xdg_surface.get_toplevel(&qh, Recorded);
+        surface.commit();
+        client.roundtrip(&mut compositor);
+
+        let window = compositor.state.windows[0].clone();
+        (compositor, client, window)
+    }
+
+    /// Size and states of the last configure received by the client
+    fn last_configure(
+        compositor: &mut TestCompositor<State>,
+        client: &mut TestClient,
+    ) -> ((i32, i32), Vec<ToplevelState>) {
+        client.roundtrip(compositor);
+        let mut last = None;
+        while let Some(event) = client.wait_for_event::<xdg_toplevel::Event, _>(compositor, Duration::ZERO) {
+            if let xdg_toplevel::Event::Configure {
+                width,
+                height,
+                states,
+            } = event
+            {
+                let states = states
+                    .chunks_exact(4)
+                    .filter_map(|state| {
+                        ToplevelState::try_from(u32::from_ne_bytes([state[0], state[1], state[2], state[3]]))
+                            .ok()
+                    })
+                    .collect();
+                last = Some(((width, height), states));
+            }
+        }
+        last.expect("No configure received")
+    }
+
+    #[test]
+    fn unmaximize_drops_maximized_size() {
+        let (mut compositor, mut client, window) = window();
+        let output = test_output("test", (1920, 1080));
+
+        window.send_maximize_configure_for_output(&layer_map_for_output(&output));
+        assert_eq!(
+            last_configure(&mut compositor, &mut client),
+            ((1920, 1080), vec![ToplevelState::Maximized])
+        );
+
+        window.request_maximize(false);
+        assert_eq!(last_configure(&mut compositor, &mut client), ((0, 0), vec![]));
+    }
+
+    #[test]
+    fn fullscreen_replaces_maximized() {
+        let (mut compositor, mut client, window) = window();
+        let output = test_output("test", (1920, 1080));
+
+        window.send_maximize_configure_for_output(&layer_map_for_output(&output));
+        window.request_fullscreen(None);
+        let (_, states) = last_configure(&mut compositor, &mut client);
+        assert_eq!(states, vec![ToplevelState::Fullscreen]);
+
+        window.send_fullscreen_configure_for_output(&output);
+        window.request_unfullscreen();
+        assert_eq!(last_configure(&mut compositor, &mut client), ((0, 0), vec![]));
+    }
+}
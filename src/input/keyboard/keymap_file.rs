@@ -6,6 +6,11 @@ use std::ffi::CString;
 use std::os::unix::io::RawFd;
 
 /// Wraps an XKB keymap into a sealed file or stores as just a string for sending to WlKeyboard over an fd
+///
+/// The sealed file is created once per keymap and the same fd is shared with every client
+/// supporting it (`wl_keyboard` version 7 and up, which are required to map it with `MAP_PRIVATE`).
+/// Older clients, or systems without support for memfd seals, receive a private copy of the
+/// keymap in a temporary file instead.
 #[derive(Debug)]
 pub struct KeymapFile {
     sealed: Option<SealedFile>,
@@ -31,6 +36,11 @@ impl KeymapFile {
 
     #[cfg(feature = "wayland_frontend")]
     pub(crate) fn change_keymap(&mut self, keymap: String) {
+        if keymap == self.keymap {
+            // keep sharing the existing file
+            return;
+        }
+
         let name = CString::new("smithay-keymap-file").unwrap();
         let sealed = SealedFile::with_content(name, CString::new(keymap.clone()).unwrap());
 
@@ -78,3 +88,63 @@ impl KeymapFile {
         })
     }
 }
+
+#[cfg(all(test, feature = "wayland_frontend"))]
+mod tests {
+    use std::{
+        fs::File,
+        os::unix::{
+            fs::FileExt,
+            io::{FromRawFd, RawFd},
+        },
+    };
+
+    use xkbcommon::xkb;
+
+    use super::KeymapFile;
+
+    fn keymap(layout: &str) -> xkb::Keymap {
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        xkb::Keymap::new_from_names(&context, "", "", layout, "", None, xkb::KEYMAP_COMPILE_NO_FLAGS).unwrap()
+    }
+
+    fn read_fd(fd: RawFd, size: usize) -> Vec<u8> {
+        // read from a duplicate, so dropping our file does not close the shared fd
+        let file = unsafe { File::from_raw_fd(nix::unistd::dup(fd).unwrap()) };
+        let mut data = vec![0; size];
+        file.read_exact_at(&mut data, 0).unwrap();
+        data
+    }
+
+    fn sent_fd(keymap_file: &KeymapFile) -> (RawFd, Vec<u8>) {
+        let mut sent = None;
+        keymap_file
+            .with_fd(true, |fd, size| sent = Some((fd, read_fd(fd, size))))
+            .unwrap();
+        sent.unwrap()
+    }
+
+    #[test]
+    fn keyboards_share_sealed_keymap() {
+        let keymap_file = KeymapFile::new(&keymap("us"));
+
+        let (first_fd, first_data) = sent_fd(&keymap_file);
+        let (second_fd, second_data) = sent_fd(&keymap_file);
+        assert_eq!(first_fd, second_fd);
+        assert_eq!(first_data, second_data);
+    }
+
+    #[test]
+    fn changing_keymap_creates_new_file() {
+        let mut keymap_file = KeymapFile::new(&keymap("us"));
+        let (old_fd, old_data) = sent_fd(&keymap_file);
+
+        // an identical keymap keeps the existing file
+        keymap_file.change_keymap(keymap("us").get_as_string(xkb::KEYMAP_FORMAT_TEXT_V1));
+        assert_eq!(sent_fd(&keymap_file), (old_fd, old_data.clone()));
+
+        keymap_file.change_keymap(keymap("de").get_as_string(xkb::KEYMAP_FORMAT_TEXT_V1));
+        let (_, new_data) = sent_fd(&keymap_file);
+        assert_ne!(old_data, new_data);
+    }
+}
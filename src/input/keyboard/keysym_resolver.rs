@@ -0,0 +1,116 @@
+use std::fmt;
+
+use tracing::warn;
+use xkbcommon::xkb;
+
+use super::{Error, Keysym, XkbConfig};
+
+/// Translation of key codes into keysyms, independent of any keyboard
+///
+/// This allows to interpret key codes before they reach a [`KeyboardHandle`](super::KeyboardHandle),
+/// e.g. for key bindings of devices not attached to a seat. Key codes are the raw codes of the
+/// input backend, like the ones passed to [`KeyboardHandle::input`](super::KeyboardHandle::input).
+///
+/// Loading the keymap fails on systems missing the xkb data files, as is common for minimal or
+/// headless setups. Instead of failing, [`KeysymResolver::new`] then returns a fallback resolver,
+/// which resolves no keysyms at all and leaves compositors with the raw key codes.
+pub struct KeysymResolver {
+    keymap: Option<xkb::Keymap>,
+}
+
+impl fmt::Debug for KeysymResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeysymResolver")
+            .field("keymap", &self.keymap.as_ref().map(|keymap| keymap.get_raw_ptr()))
+            .finish()
+    }
+}
+
+impl KeysymResolver {
+    /// Create a resolver for the given configuration, falling back to resolving nothing
+    ///
+    /// See [`KeysymResolver::try_new`] to handle a keymap that cannot be loaded yourself.
+    pub fn new(xkb_config: XkbConfig<'_>) -> KeysymResolver {
+        KeysymResolver::try_new(xkb_config).unwrap_or_else(|err| {
+            warn!(
+                ?err,
+                "Failed to load keymap, key codes are not resolved to keysyms"
+            );
+            KeysymResolver::fallback()
+        })
+    }
+
+    /// Create a resolver for the given configuration
+    ///
+    /// Returns [`Error::BadKeymap`] if libxkbcommon could not load the keymap.
+    pub fn try_new(xkb_config: XkbConfig<'_>) -> Result<KeysymResolver, Error> {
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let keymap = xkb::Keymap::new_from_names(
+            &context,
+            &xkb_config.rules,
+            &xkb_config.model,
+            &xkb_config.layout,
+            &xkb_config.variant,
+            xkb_config.options,
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        )
+        .ok_or(Error::BadKeymap)?;
+        Ok(KeysymResolver { keymap: Some(keymap) })
+    }
+
+    /// Create a resolver without a keymap, which never resolves any keysym
+    pub fn fallback() -> KeysymResolver {
+        KeysymResolver { keymap: None }
+    }
+
+    /// Whether this is a fallback resolver without a keymap
+    pub fn is_fallback(&self) -> bool {
+        self.keymap.is_none()
+    }
+
+    /// The keysym of a key code without any modifiers applied
+    ///
+    /// Returns `None` for key codes without a keysym in the first layout and for every
+    /// key code if this is a fallback resolver.
+    pub fn keysym(&self, keycode: u32) -> Option<Keysym> {
+        let keymap = self.keymap.as_ref()?;
+        // xkb key codes are offset by 8 from evdev key codes
+        let keycode = keycode.checked_add(8)?;
+        keymap
+            .key_get_syms_by_level(keycode, 0, 0)
+            .first()
+            .copied()
+            .filter(|sym| *sym != xkb::keysyms::KEY_NoSymbol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeysymResolver;
+    use crate::input::keyboard::{keysyms, XkbConfig};
+
+    const KEY_A: u32 = 30;
+
+    #[test]
+    fn fallback_resolves_nothing() {
+        let resolver = KeysymResolver::fallback();
+        assert!(resolver.is_fallback());
+        for keycode in [0, 1, KEY_A, 255] {
+            assert_eq!(resolver.keysym(keycode), None);
+        }
+    }
+
+    #[test]
+    fn keymap_resolves_keysyms() {
+        let resolver = KeysymResolver::try_new(XkbConfig::default()).unwrap();
+        assert!(!resolver.is_fallback());
+        assert_eq!(resolver.keysym(KEY_A), Some(keysyms::KEY_a));
+    }
+
+    #[test]
+    fn out_of_range_keycodes_resolve_nothing() {
+        let resolver = KeysymResolver::try_new(XkbConfig::default()).unwrap();
+        assert_eq!(resolver.keysym(u32::MAX - 8), None);
+        assert_eq!(resolver.keysym(u32::MAX), None);
+    }
+}
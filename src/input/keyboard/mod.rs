@@ -20,6 +20,9 @@ mod keymap_file;
 #[cfg(feature = "wayland_frontend")]
 pub use keymap_file::KeymapFile;
 
+mod keysym_resolver;
+pub use keysym_resolver::KeysymResolver;
+
 mod modifiers_state;
 pub use modifiers_state::ModifiersState;
 
@@ -65,6 +68,8 @@ pub(crate) struct KbdInternal<D: SeatHandler> {
     pub(crate) repeat_rate: i32,
     pub(crate) repeat_delay: i32,
     grab: GrabStatus<D>,
+    interceptor: Option<Box<dyn KeyInterceptor<D>>>,
+    intercepted_keys: HashSet<u32>,
 }
 
 // focus_hook does not implement debug, so we have to impl Debug manually
@@ -82,6 +87,8 @@ where
             .field("state", &self.state.get_raw_ptr())
             .field("repeat_rate", &self.repeat_rate)
             .field("repeat_delay", &self.repeat_delay)
+            .field("interceptor", &self.interceptor.is_some())
+            .field("intercepted_keys", &self.intercepted_keys)
             .finish()
     }
 }
@@ -120,9 +127,37 @@ impl<D: SeatHandler + 'static> KbdInternal<D> {
             repeat_rate,
             repeat_delay,
             grab: GrabStatus::None,
+            interceptor: None,
+            intercepted_keys: HashSet::new(),
         })
     }
 
+    // return true if the key event was consumed by the interceptor
+    fn intercept(&mut self, data: &mut D, keycode: u32, state: KeyState) -> bool {
+        let grabbed = !matches!(self.grab, GrabStatus::None);
+        let consumed = self.interceptor.as_mut().map_or(false, |interceptor| {
+            let key_handle = KeysymHandle {
+                keycode: keycode + 8,
+                state: &self.state,
+                keymap: &self.keymap,
+            };
+            matches!(
+                interceptor.intercept(data, &self.mods_state, key_handle, state, grabbed),
+                InterceptResult::Consume
+            )
+        });
+
+        // releases of consumed presses are never forwarded, the client did not see the key going down
+        match state {
+            KeyState::Pressed if consumed => {
+                self.intercepted_keys.insert(keycode);
+                true
+            }
+            KeyState::Pressed => false,
+            KeyState::Released => self.intercepted_keys.remove(&keycode) || consumed,
+        }
+    }
+
     // return true if modifier state has changed
     fn key_input(&mut self, keycode: u32, state: KeyState) -> bool {
         // track pressed keys as xkbcommon does not seem to expose it :(
@@ -274,6 +309,58 @@ pub enum FilterResult<T> {
     Intercept(T),
 }
 
+/// Result of a [`KeyInterceptor`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterceptResult {
+    /// Let the event continue to the input filter, grab and client
+    Pass,
+    /// Consume the event, it is not processed any further
+    Consume,
+}
+
+/// A trait to implement a key interceptor
+///
+/// An interceptor is installed on a keyboard using [`KeyboardHandle::set_interceptor`] and sees every
+/// key event before the filter passed to [`KeyboardHandle::input`] and before any keyboard grab. Unlike
+/// the filter, it is told whether a grab is active, so compositor shortcuts can decide to keep working
+/// while a client holds a grab.
+///
+/// If the press of a key is consumed, its release is consumed as well, regardless of the result
+/// returned for it. The interceptor is called while the keyboard is locked, it must not call
+/// methods of the [`KeyboardHandle`] it is installed on.
+///
+/// It is implemented for closures taking the same arguments as [`KeyInterceptor::intercept`].
+pub trait KeyInterceptor<D: SeatHandler>: Send {
+    /// A key event was reported
+    ///
+    /// `grabbed` is `true` if a keyboard grab is currently active.
+    fn intercept(
+        &mut self,
+        data: &mut D,
+        modifiers: &ModifiersState,
+        keysym: KeysymHandle<'_>,
+        state: KeyState,
+        grabbed: bool,
+    ) -> InterceptResult;
+}
+
+impl<D, F> KeyInterceptor<D> for F
+where
+    D: SeatHandler,
+    F: FnMut(&mut D, &ModifiersState, KeysymHandle<'_>, KeyState, bool) -> InterceptResult + Send,
+{
+    fn intercept(
+        &mut self,
+        data: &mut D,
+        modifiers: &ModifiersState,
+        keysym: KeysymHandle<'_>,
+        state: KeyState,
+        grabbed: bool,
+    ) -> InterceptResult {
+        self(data, modifiers, keysym, state, grabbed)
+    }
+}
+
 /// Data about the event that started the grab.
 pub struct GrabStartData<D: SeatHandler> {
     /// The focused surface, if any, at the start of the grab.
@@ -457,6 +544,20 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         !matches!(guard.grab, GrabStatus::None)
     }
 
+    /// Install an interceptor, running before the input filter and any grab
+    ///
+    /// Replaces any previously installed interceptor. See [`KeyInterceptor`] for details.
+    pub fn set_interceptor<I: KeyInterceptor<D> + 'static>(&self, interceptor: I) {
+        self.arc.internal.lock().unwrap().interceptor = Some(Box::new(interceptor));
+    }
+
+    /// Remove the current interceptor
+    ///
+    /// Releases of keys whose press was consumed are still consumed.
+    pub fn unset_interceptor(&self) {
+        self.arc.internal.lock().unwrap().interceptor = None;
+    }
+
     /// Returns the start data for the grab, if any.
     pub fn grab_start_data(&self) -> Option<GrabStartData<D>> {
         let guard = self.arc.internal.lock().unwrap();
@@ -471,9 +572,12 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
     /// All keystrokes from the input backend should be fed _in order_ to this method of the
     /// keyboard handler. It will internally track the state of the keymap.
     ///
+    /// If an interceptor is installed using [`KeyboardHandle::set_interceptor`], it is called first
+    /// and may consume the input, in which case `None` is returned.
+    ///
     /// The `filter` argument is expected to be a closure which will peek at the generated input
     /// as interpreted by the keymap before it is forwarded to the focused client. If this closure
-    /// returns [`FilterResult::Forward`], the input will not be sent to the client. If it returns
+    /// returns [`FilterResult::Forward`], the input will be sent to the client. If it returns
     /// [`FilterResult::Intercept`] a value can be passed to be returned by the whole function.
     /// This mechanism can be used to implement compositor-level key bindings for example.
     ///
@@ -495,6 +599,19 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         trace!("Handling keystroke");
         let mut guard = self.arc.internal.lock().unwrap();
         let mods_changed = guard.key_input(keycode, state);
+        if guard.intercept(data, keycode, state) {
+            trace!("Input was consumed by interceptor");
+            // the key is hidden from the client, but it has to know the resulting modifiers
+            if mods_changed {
+                let seat = self.get_seat(data);
+                let mods = guard.mods_state;
+                if let Some((focus, _)) = guard.focus.as_ref() {
+                    focus.modifiers(&seat, data, mods, serial);
+                }
+            }
+            return None;
+        }
+
         let key_handle = KeysymHandle {
             // Offset the keycode by 8, as the evdev XKB rules reflect X's
             // broken keycode system, which starts at 8.
@@ -563,6 +680,23 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         }
     }
 
+    /// Access the keymap currently used to interpret key codes
+    ///
+    /// This is the keymap the keyboard was created with, e.g. to translate key codes of key
+    /// bindings, to extract layout information or to serialize it with
+    /// [`get_as_string`](xkb::Keymap::get_as_string). Keymaps sent by virtual keyboards only
+    /// replace the keymap of clients temporarily and are not returned here.
+    ///
+    /// libxkbcommon objects are not thread-safe, so the keymap is only lent to the closure
+    /// while the keyboard is locked. The closure must not call back into this keyboard.
+    pub fn with_current_keymap<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&xkb::Keymap) -> R,
+    {
+        let guard = self.arc.internal.lock().unwrap();
+        f(&guard.keymap)
+    }
+
     /// Get the current modifiers state
     pub fn modifier_state(&self) -> ModifiersState {
         self.arc.internal.lock().unwrap().mods_state
@@ -806,3 +940,187 @@ impl<D: SeatHandler + 'static> KeyboardGrab<D> for DefaultGrab {
         unreachable!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{
+        FilterResult, GrabStartData, InterceptResult, KeyboardGrab, KeyboardInnerHandle, KeysymHandle,
+        ModifiersState, XkbConfig,
+    };
+    use crate::{
+        backend::input::KeyState,
+        input::{
+            test_utils::{Log, Target, TestState},
+            SeatState,
+        },
+        utils::{Serial, SERIAL_COUNTER},
+    };
+
+    const KEY_LEFTSHIFT: u32 = 42;
+    const KEY_A: u32 = 30;
+    const KEY_S: u32 = 31;
+
+    /// Grab recording all forwarded key events
+    struct RecordingGrab {
+        start_data: GrabStartData<TestState>,
+        keys: Arc<Mutex<Vec<(u32, KeyState)>>>,
+    }
+
+    impl KeyboardGrab<TestState> for RecordingGrab {
+        fn input(
+            &mut self,
+            _data: &mut TestState,
+            _handle: &mut KeyboardInnerHandle<'_, TestState>,
+            keycode: u32,
+            state: KeyState,
+            _modifiers: Option<ModifiersState>,
+            _serial: Serial,
+            _time: u32,
+        ) {
+            self.keys.lock().unwrap().push((keycode, state));
+        }
+
+        fn set_focus(
+            &mut self,
+            _data: &mut TestState,
+            _handle: &mut KeyboardInnerHandle<'_, TestState>,
+            _focus: Option<Target>,
+            _serial: Serial,
+        ) {
+        }
+
+        fn start_data(&self) -> &GrabStartData<TestState> {
+            &self.start_data
+        }
+    }
+
+    #[test]
+    fn current_keymap_matches_config() {
+        let mut seat_state = SeatState::<TestState>::new();
+        let mut seat = seat_state.new_seat("test");
+        let keyboard = seat
+            .add_keyboard(
+                XkbConfig {
+                    layout: "de",
+                    ..XkbConfig::default()
+                },
+                200,
+                25,
+            )
+            .unwrap();
+
+        keyboard.with_current_keymap(|keymap| {
+            assert_eq!(keymap.layouts().next(), Some("German"));
+            // the keymap can be used to translate key codes without any state applied
+            let syms = keymap.key_get_syms_by_level(KEY_A + 8, 0, 0);
+            assert_eq!(syms, &[super::keysyms::KEY_a]);
+        });
+    }
+
+    #[test]
+    fn interceptor_consumes_key_before_grab() {
+        let mut state = TestState {
+            seat_state: SeatState::new(),
+        };
+        let mut seat = state.seat_state.new_seat("test");
+        let keyboard = seat.add_keyboard(XkbConfig::default(), 200, 25).unwrap();
+
+        let forwarded = Arc::new(Mutex::new(Vec::new()));
+        keyboard.set_grab(
+            RecordingGrab {
+                start_data: GrabStartData { focus: None },
+                keys: forwarded.clone(),
+            },
+            SERIAL_COUNTER.next_serial(),
+        );
+
+        let intercepted = Arc::new(Mutex::new(Vec::new()));
+        let intercepted_clone = intercepted.clone();
+        keyboard.set_interceptor(
+            move |_: &mut TestState, _: &ModifiersState, keysym: KeysymHandle<'_>, key_state, grabbed| {
+                assert!(grabbed);
+                intercepted_clone.lock().unwrap().push(key_state);
+                // only consume the press, the release has to follow it
+                if keysym.raw_code() == KEY_A + 8 && key_state == KeyState::Pressed {
+                    InterceptResult::Consume
+                } else {
+                    InterceptResult::Pass
+                }
+            },
+        );
+
+        for (keycode, key_state) in [
+            (KEY_A, KeyState::Pressed),
+            (KEY_A, KeyState::Released),
+            (KEY_S, KeyState::Pressed),
+            (KEY_S, KeyState::Released),
+        ] {
+            let filtered = keyboard.input::<(), _>(
+                &mut state,
+                keycode,
+                key_state,
+                SERIAL_COUNTER.next_serial(),
+                0,
+                |_, _, _| FilterResult::Forward,
+            );
+            assert!(filtered.is_none());
+        }
+
+        assert_eq!(intercepted.lock().unwrap().len(), 4);
+        assert_eq!(
+            *forwarded.lock().unwrap(),
+            vec![(KEY_S, KeyState::Pressed), (KEY_S, KeyState::Released)]
+        );
+
+        keyboard.unset_interceptor();
+        keyboard.input::<(), _>(
+            &mut state,
+            KEY_A,
+            KeyState::Pressed,
+            SERIAL_COUNTER.next_serial(),
+            0,
+            |_, _, _| FilterResult::Forward,
+        );
+        assert_eq!(
+            forwarded.lock().unwrap().last(),
+            Some(&(KEY_A, KeyState::Pressed))
+        );
+    }
+
+    #[test]
+    fn consumed_modifier_updates_focus_modifiers() {
+        let mut state = TestState {
+            seat_state: SeatState::new(),
+        };
+        let mut seat = state.seat_state.new_seat("test");
+        let keyboard = seat.add_keyboard(XkbConfig::default(), 200, 25).unwrap();
+
+        let log = Log::default();
+        keyboard.set_focus(
+            &mut state,
+            Some(Target::new(1, &log)),
+            SERIAL_COUNTER.next_serial(),
+        );
+        keyboard.set_interceptor(
+            |_: &mut TestState, _: &ModifiersState, _: KeysymHandle<'_>, _, _| InterceptResult::Consume,
+        );
+        log.lock().unwrap().clear();
+
+        for key_state in [KeyState::Pressed, KeyState::Released] {
+            keyboard.input::<(), _>(
+                &mut state,
+                KEY_LEFTSHIFT,
+                key_state,
+                SERIAL_COUNTER.next_serial(),
+                0,
+                |_, _, _| FilterResult::Forward,
+            );
+        }
+
+        // the shift key itself never reaches the client, but shift going down and up does
+        assert_eq!(*log.lock().unwrap(), vec![(1, "modifiers"), (1, "modifiers")]);
+        assert!(!keyboard.modifier_state().shift);
+    }
+}
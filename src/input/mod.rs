@@ -106,11 +106,15 @@ use std::{
 use tracing::{info_span, instrument};
 
 use self::keyboard::{Error as KeyboardError, KeyboardHandle, KeyboardTarget};
-use self::pointer::{CursorImageStatus, PointerHandle, PointerTarget};
+use self::pointer::{
+    CursorImageStatus, PointerConfigTracker, PointerHandle, PointerTarget, SeatPointerConfig,
+};
 use crate::utils::user_data::UserDataMap;
 
 pub mod keyboard;
 pub mod pointer;
+#[cfg(test)]
+pub(crate) mod test_utils;
 
 /// Handler trait for Seats
 pub trait SeatHandler: Sized {
@@ -127,6 +131,22 @@ pub trait SeatHandler: Sized {
 
     /// Callback that will be notified whenever a client requests to set a custom cursor image.
     fn cursor_image(&mut self, _seat: &Seat<Self>, _image: CursorImageStatus) {}
+
+    /// Callback to filter the capabilities of a seat advertised to a client.
+    ///
+    /// Called whenever a client binds the `wl_seat` global of `seat`. Only capabilities contained
+    /// in the returned mask are ever advertised through this binding, objects of other capabilities
+    /// requested by the client anyway stay inert.
+    ///
+    /// The default implementation advertises all capabilities.
+    #[cfg(feature = "wayland_frontend")]
+    fn client_seat_filter(
+        &mut self,
+        _seat: &Seat<Self>,
+        _client: &wayland_server::Client,
+    ) -> wayland_server::protocol::wl_seat::Capability {
+        wayland_server::protocol::wl_seat::Capability::all()
+    }
 }
 /// Delegate type for all [Seat] globals.
 ///
@@ -190,6 +210,8 @@ pub(crate) struct Inner<D: SeatHandler> {
     pub(crate) global: Option<wayland_server::backend::GlobalId>,
     #[cfg(feature = "wayland_frontend")]
     pub(crate) known_seats: Vec<wayland_server::Weak<wayland_server::protocol::wl_seat::WlSeat>>,
+    #[cfg(feature = "wayland_frontend")]
+    pub(crate) capability_update: Option<crate::wayland::seat::CapabilityUpdate>,
 }
 
 #[cfg(not(feature = "wayland_frontend"))]
@@ -219,6 +241,7 @@ where
             .field("touch", &self.touch)
             .field("global", &self.global)
             .field("known_seats", &self.known_seats)
+            .field("capability_update", &self.capability_update)
             .finish()
     }
 }
@@ -229,6 +252,7 @@ pub(crate) struct SeatRc<D: SeatHandler> {
     pub(crate) inner: Mutex<Inner<D>>,
     span: tracing::Span,
     user_data_map: UserDataMap,
+    pointer_config: pointer::PointerConfigTracker,
 }
 
 impl<D: SeatHandler> fmt::Debug for SeatRc<D>
@@ -241,6 +265,7 @@ where
             .field("name", &self.name)
             .field("inner", &self.inner)
             .field("user_data_map", &self.user_data_map)
+            .field("pointer_config", &self.pointer_config)
             .finish()
     }
 }
@@ -271,7 +296,7 @@ impl<D: SeatHandler> SeatState<D> {
         N: Into<String>,
     {
         let name = name.into();
-        let span = info_span!("input_seat", name);
+        let span = info_span!("input_seat", seat = name);
 
         let arc = Arc::new(SeatRc {
             name,
@@ -285,9 +310,12 @@ impl<D: SeatHandler> SeatState<D> {
                 global: None,
                 #[cfg(feature = "wayland_frontend")]
                 known_seats: Vec::new(),
+                #[cfg(feature = "wayland_frontend")]
+                capability_update: None,
             }),
             span,
             user_data_map: UserDataMap::new(),
+            pointer_config: Default::default(),
         });
         self.seats.push(Seat { arc: arc.clone() });
 
@@ -382,6 +410,28 @@ impl<D: SeatHandler + 'static> Seat<D> {
         self.arc.inner.lock().unwrap().pointer.clone()
     }
 
+    /// Set the configuration of all pointer devices of this seat
+    ///
+    /// The configuration is applied by the input backends following the
+    /// [`pointer_config_tracker`](Seat::pointer_config_tracker) of this seat, to the devices
+    /// they already know as well as to devices added later on.
+    pub fn set_pointer_config(&self, config: SeatPointerConfig) {
+        self.arc.pointer_config.set(config);
+    }
+
+    /// The configuration of the pointer devices of this seat, if any was set
+    pub fn pointer_config(&self) -> Option<SeatPointerConfig> {
+        self.arc.pointer_config.config()
+    }
+
+    /// Handle to follow the pointer configuration of this seat
+    ///
+    /// Pass it to your input backend, e.g. `LibinputInputBackend::track_pointer_config`, to have
+    /// the configuration applied to its devices.
+    pub fn pointer_config_tracker(&self) -> PointerConfigTracker {
+        self.arc.pointer_config.clone()
+    }
+
     /// Remove the pointer capability from this seat
     ///
     /// Clients will be appropriately notified.
@@ -513,4 +563,11 @@ impl<D: SeatHandler + 'static> Seat<D> {
     pub fn name(&self) -> &str {
         &self.arc.name
     }
+
+    /// Tracing span of this seat
+    ///
+    /// Create spans handling events of this seat as its children, to have them carry its `seat` field.
+    pub fn span(&self) -> &tracing::Span {
+        &self.arc.span
+    }
 }
@@ -0,0 +1,87 @@
+use std::sync::{Arc, Mutex};
+
+/// Acceleration profile of pointer devices
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AccelProfile {
+    /// Constant factor applied to all motion
+    Flat,
+    /// Acceleration depending on the speed of the motion
+    #[default]
+    Adaptive,
+}
+
+/// Configuration applied to all pointer devices of a seat
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SeatPointerConfig {
+    /// Acceleration speed in the range `[-1, 1]`, `0` being the device default
+    pub accel_speed: f64,
+    /// Acceleration profile
+    pub accel_profile: AccelProfile,
+    /// Whether scrolling moves the content instead of the viewport
+    pub natural_scroll: bool,
+}
+
+#[derive(Debug, Default)]
+struct TrackedConfig {
+    serial: u64,
+    config: Option<SeatPointerConfig>,
+}
+
+/// Shared handle to the pointer configuration of a seat
+///
+/// Input backends use it to apply the configuration set through
+/// [`Seat::set_pointer_config`](crate::input::Seat::set_pointer_config) to their devices.
+#[derive(Debug, Clone, Default)]
+pub struct PointerConfigTracker {
+    inner: Arc<Mutex<TrackedConfig>>,
+}
+
+impl PointerConfigTracker {
+    /// The current configuration, if any was set
+    pub fn config(&self) -> Option<SeatPointerConfig> {
+        self.inner.lock().unwrap().config
+    }
+
+    pub(crate) fn set(&self, config: SeatPointerConfig) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.serial += 1;
+        inner.config = Some(config);
+    }
+
+    /// The configuration if it was changed since `serial`, updating `serial`
+    #[cfg(feature = "backend_libinput")]
+    pub(crate) fn changed_since(&self, serial: &mut u64) -> Option<SeatPointerConfig> {
+        let inner = self.inner.lock().unwrap();
+        if inner.serial == *serial {
+            return None;
+        }
+        *serial = inner.serial;
+        inner.config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccelProfile, PointerConfigTracker, SeatPointerConfig};
+
+    #[test]
+    fn tracker_reports_changes_once() {
+        let tracker = PointerConfigTracker::default();
+        assert_eq!(tracker.config(), None);
+
+        let config = SeatPointerConfig {
+            accel_speed: -0.5,
+            accel_profile: AccelProfile::Flat,
+            natural_scroll: true,
+        };
+        tracker.clone().set(config);
+        assert_eq!(tracker.config(), Some(config));
+
+        #[cfg(feature = "backend_libinput")]
+        {
+            let mut serial = 0;
+            assert_eq!(tracker.changed_since(&mut serial), Some(config));
+            assert_eq!(tracker.changed_since(&mut serial), None);
+        }
+    }
+}
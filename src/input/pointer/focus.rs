@@ -0,0 +1,141 @@
+use crate::utils::{Logical, Point};
+
+/// Change of pointer focus computed by a [`FocusTracker`]
+///
+/// If both fields are set, `leave` has to be sent for `left` before `enter` is sent for `entered`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FocusChange<S> {
+    /// Previously focused surface, that the pointer left
+    pub left: Option<S>,
+    /// Newly focused surface, that the pointer entered
+    pub entered: Option<S>,
+}
+
+impl<S> Default for FocusChange<S> {
+    fn default() -> Self {
+        FocusChange {
+            left: None,
+            entered: None,
+        }
+    }
+}
+
+impl<S> FocusChange<S> {
+    /// Returns `true` if the focus did not change
+    pub fn is_empty(&self) -> bool {
+        self.left.is_none() && self.entered.is_none()
+    }
+}
+
+/// Tracker of the surface under the pointer to generate enter and leave events
+///
+/// The tracker is generic over a surface key `S`, which is compared to detect focus changes.
+/// On every motion it is fed with the pointer location and a hit-test, returning the surface
+/// at a given location, and yields the resulting [`FocusChange`].
+#[derive(Debug, Clone)]
+pub struct FocusTracker<S> {
+    focus: Option<S>,
+}
+
+impl<S> Default for FocusTracker<S> {
+    fn default() -> Self {
+        FocusTracker { focus: None }
+    }
+}
+
+impl<S: PartialEq + Clone> FocusTracker<S> {
+    /// Create a new tracker without any focus
+    pub fn new() -> FocusTracker<S> {
+        FocusTracker::default()
+    }
+
+    /// Currently focused surface
+    pub fn current(&self) -> Option<&S> {
+        self.focus.as_ref()
+    }
+
+    /// Update the focus for a new pointer location
+    ///
+    /// `hit_test` is called with the location and has to return the surface under it, if any.
+    pub fn motion<F>(&mut self, location: Point<f64, Logical>, hit_test: F) -> FocusChange<S>
+    where
+        F: FnOnce(Point<f64, Logical>) -> Option<S>,
+    {
+        self.set_focus(hit_test(location))
+    }
+
+    /// Set the focus to a given surface
+    ///
+    /// Useful to update the focus if the surface under the pointer changed without
+    /// the pointer moving, e.g. because a window was mapped or destroyed.
+    pub fn set_focus(&mut self, focus: Option<S>) -> FocusChange<S> {
+        if self.focus == focus {
+            return FocusChange::default();
+        }
+        let left = std::mem::replace(&mut self.focus, focus);
+        FocusChange {
+            left,
+            entered: self.focus.clone(),
+        }
+    }
+
+    /// Clear the focus, e.g. if the pointer left the output layout
+    pub fn clear(&mut self) -> FocusChange<S> {
+        self.set_focus(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FocusChange, FocusTracker};
+    use crate::utils::{Logical, Point, Rectangle};
+
+    fn hit_test(location: Point<f64, Logical>) -> Option<u32> {
+        [
+            (
+                1,
+                Rectangle::<f64, Logical>::from_loc_and_size((0.0, 0.0), (100.0, 100.0)),
+            ),
+            (2, Rectangle::from_loc_and_size((100.0, 0.0), (100.0, 100.0))),
+        ]
+        .into_iter()
+        .find(|(_, geo)| geo.contains(location))
+        .map(|(id, _)| id)
+    }
+
+    #[test]
+    fn motion_within_surface() {
+        let mut tracker = FocusTracker::new();
+        assert_eq!(
+            tracker.motion((10.0, 10.0).into(), hit_test),
+            FocusChange {
+                left: None,
+                entered: Some(1)
+            }
+        );
+        assert!(tracker.motion((50.0, 20.0).into(), hit_test).is_empty());
+        assert_eq!(tracker.current(), Some(&1));
+    }
+
+    #[test]
+    fn motion_across_surfaces() {
+        let mut tracker = FocusTracker::new();
+        tracker.motion((10.0, 10.0).into(), hit_test);
+        assert_eq!(
+            tracker.motion((150.0, 10.0).into(), hit_test),
+            FocusChange {
+                left: Some(1),
+                entered: Some(2)
+            }
+        );
+        assert_eq!(
+            tracker.motion((250.0, 10.0).into(), hit_test),
+            FocusChange {
+                left: Some(2),
+                entered: None
+            }
+        );
+        assert_eq!(tracker.current(), None);
+        assert!(tracker.clear().is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+use crate::utils::{Logical, Point, Serial};
+
+/// Kind of a touchpad gesture
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GestureKind {
+    /// Multi-finger swipe
+    Swipe,
+    /// Multi-finger pinch and rotate
+    Pinch,
+    /// Fingers resting on the touchpad
+    Hold,
+}
+
+/// Begin of a swipe gesture
+#[derive(Debug, Clone, Copy)]
+pub struct GestureSwipeBeginEvent {
+    /// Serial of the event
+    pub serial: Serial,
+    /// Timestamp of the event, with millisecond granularity
+    pub time: u32,
+    /// Number of fingers
+    pub fingers: u32,
+}
+
+/// Update of a swipe gesture
+#[derive(Debug, Clone, Copy)]
+pub struct GestureSwipeUpdateEvent {
+    /// Timestamp of the event, with millisecond granularity
+    pub time: u32,
+    /// Motion of the logical center of the gesture
+    pub delta: Point<f64, Logical>,
+}
+
+/// End of a swipe gesture
+#[derive(Debug, Clone, Copy)]
+pub struct GestureSwipeEndEvent {
+    /// Serial of the event
+    pub serial: Serial,
+    /// Timestamp of the event, with millisecond granularity
+    pub time: u32,
+    /// Whether the gesture was cancelled
+    pub cancelled: bool,
+}
+
+/// Begin of a pinch gesture
+#[derive(Debug, Clone, Copy)]
+pub struct GesturePinchBeginEvent {
+    /// Serial of the event
+    pub serial: Serial,
+    /// Timestamp of the event, with millisecond granularity
+    pub time: u32,
+    /// Number of fingers
+    pub fingers: u32,
+}
+
+/// Update of a pinch gesture
+#[derive(Debug, Clone, Copy)]
+pub struct GesturePinchUpdateEvent {
+    /// Timestamp of the event, with millisecond granularity
+    pub time: u32,
+    /// Motion of the logical center of the gesture
+    pub delta: Point<f64, Logical>,
+    /// Absolute scale compared to the begin of the gesture
+    pub scale: f64,
+    /// Relative angle in degrees clockwise compared to the previous event
+    pub rotation: f64,
+}
+
+/// End of a pinch gesture
+#[derive(Debug, Clone, Copy)]
+pub struct GesturePinchEndEvent {
+    /// Serial of the event
+    pub serial: Serial,
+    /// Timestamp of the event, with millisecond granularity
+    pub time: u32,
+    /// Whether the gesture was cancelled
+    pub cancelled: bool,
+}
+
+/// Begin of a hold gesture
+#[derive(Debug, Clone, Copy)]
+pub struct GestureHoldBeginEvent {
+    /// Serial of the event
+    pub serial: Serial,
+    /// Timestamp of the event, with millisecond granularity
+    pub time: u32,
+    /// Number of fingers
+    pub fingers: u32,
+}
+
+/// End of a hold gesture
+#[derive(Debug, Clone, Copy)]
+pub struct GestureHoldEndEvent {
+    /// Serial of the event
+    pub serial: Serial,
+    /// Timestamp of the event, with millisecond granularity
+    pub time: u32,
+    /// Whether the gesture was cancelled
+    pub cancelled: bool,
+}
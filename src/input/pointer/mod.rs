@@ -12,13 +12,25 @@ use crate::{
     utils::{IsAlive, Logical, Point},
 };
 
+mod config;
+pub use config::{AccelProfile, PointerConfigTracker, SeatPointerConfig};
+
 mod cursor_image;
 pub use cursor_image::{CursorImageAttributes, CursorImageStatus, CursorImageSurfaceData};
 
+mod focus;
+pub use focus::{FocusChange, FocusTracker};
+
+mod gesture;
+pub use gesture::{
+    GestureHoldBeginEvent, GestureHoldEndEvent, GestureKind, GesturePinchBeginEvent, GesturePinchEndEvent,
+    GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent, GestureSwipeUpdateEvent,
+};
+
 mod grab;
 use grab::{DefaultGrab, GrabStatus};
 pub use grab::{GrabStartData, PointerGrab};
-use tracing::{info_span, instrument};
+use tracing::{info_span, instrument, trace};
 
 /// An handle to a pointer handler
 ///
@@ -100,6 +112,22 @@ where
     fn axis(&self, seat: &Seat<D>, data: &mut D, frame: AxisFrame);
     /// A pointer of a given seat left this handler
     fn leave(&self, seat: &Seat<D>, data: &mut D, serial: Serial, time: u32);
+    /// A swipe gesture of a given seat started on this handler
+    fn gesture_swipe_begin(&self, _seat: &Seat<D>, _data: &mut D, _event: &GestureSwipeBeginEvent) {}
+    /// A swipe gesture of a given seat on this handler was updated
+    fn gesture_swipe_update(&self, _seat: &Seat<D>, _data: &mut D, _event: &GestureSwipeUpdateEvent) {}
+    /// A swipe gesture of a given seat on this handler ended or was cancelled
+    fn gesture_swipe_end(&self, _seat: &Seat<D>, _data: &mut D, _event: &GestureSwipeEndEvent) {}
+    /// A pinch gesture of a given seat started on this handler
+    fn gesture_pinch_begin(&self, _seat: &Seat<D>, _data: &mut D, _event: &GesturePinchBeginEvent) {}
+    /// A pinch gesture of a given seat on this handler was updated
+    fn gesture_pinch_update(&self, _seat: &Seat<D>, _data: &mut D, _event: &GesturePinchUpdateEvent) {}
+    /// A pinch gesture of a given seat on this handler ended or was cancelled
+    fn gesture_pinch_end(&self, _seat: &Seat<D>, _data: &mut D, _event: &GesturePinchEndEvent) {}
+    /// A hold gesture of a given seat started on this handler
+    fn gesture_hold_begin(&self, _seat: &Seat<D>, _data: &mut D, _event: &GestureHoldBeginEvent) {}
+    /// A hold gesture of a given seat on this handler ended or was cancelled
+    fn gesture_hold_end(&self, _seat: &Seat<D>, _data: &mut D, _event: &GestureHoldEndEvent) {}
 }
 
 impl<D: SeatHandler + 'static> PointerHandle<D> {
@@ -185,6 +213,44 @@ impl<D: SeatHandler + 'static> PointerHandle<D> {
         });
     }
 
+    /// Move the pointer to a new location without any physical motion
+    ///
+    /// This is meant for compositor-initiated pointer movement, like placing the pointer
+    /// on a newly mapped window or moving it after the focused window closed. In contrast
+    /// to [`PointerHandle::motion`] the active grab is bypassed and no relative motion
+    /// is reported to clients. The appropriate enter/motion/leave events are still sent
+    /// for the new focus.
+    ///
+    /// If a grab is currently active only the location is updated, the given focus will
+    /// then be restored once the grab is released.
+    #[instrument(level = "debug", parent = &self.span, skip(self, data, focus), fields(focus = ?focus.as_ref().map(|(_, loc)| ("...", loc))))]
+    pub fn warp(
+        &self,
+        data: &mut D,
+        focus: Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+        location: Point<f64, Logical>,
+        serial: Serial,
+        time: u32,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.pending_focus = focus.clone();
+        if !matches!(inner.grab, GrabStatus::None) {
+            inner.location = location;
+            return;
+        }
+        let seat = self.get_seat(data);
+        inner.motion(
+            data,
+            &seat,
+            focus,
+            &MotionEvent {
+                location,
+                serial,
+                time,
+            },
+        );
+    }
+
     /// Notify about relative pointer motion
     ///
     /// This will internally send the appropriate button event to the client
@@ -237,6 +303,128 @@ impl<D: SeatHandler + 'static> PointerHandle<D> {
         });
     }
 
+    /// Notify that a swipe gesture began
+    ///
+    /// Gestures are delivered to the current focus and stay with it until they end. Unlike other
+    /// events they bypass pointer grabs: no gesture can begin while a grab is active, and a gesture
+    /// in progress is cancelled when the focus changes or a grab is set. Cancelled gestures are
+    /// ended with `cancelled` set for the target, unless it is no longer alive.
+    #[instrument(level = "trace", parent = &self.span, skip(self, data))]
+    pub fn gesture_swipe_begin(&self, data: &mut D, event: &GestureSwipeBeginEvent) {
+        let seat = self.get_seat(data);
+        self.inner.lock().unwrap().gesture_begin(
+            data,
+            &seat,
+            GestureKind::Swipe,
+            event.serial,
+            event.time,
+            |target, data| target.gesture_swipe_begin(&seat, data, event),
+        );
+    }
+
+    /// Notify that a swipe gesture was updated
+    #[instrument(level = "trace", parent = &self.span, skip(self, data))]
+    pub fn gesture_swipe_update(&self, data: &mut D, event: &GestureSwipeUpdateEvent) {
+        let seat = self.get_seat(data);
+        let target = self
+            .inner
+            .lock()
+            .unwrap()
+            .gesture_target(GestureKind::Swipe, false);
+        if let Some(target) = target {
+            target.gesture_swipe_update(&seat, data, event);
+        }
+    }
+
+    /// Notify that a swipe gesture ended
+    #[instrument(level = "trace", parent = &self.span, skip(self, data))]
+    pub fn gesture_swipe_end(&self, data: &mut D, event: &GestureSwipeEndEvent) {
+        let seat = self.get_seat(data);
+        let target = self
+            .inner
+            .lock()
+            .unwrap()
+            .gesture_target(GestureKind::Swipe, true);
+        if let Some(target) = target {
+            target.gesture_swipe_end(&seat, data, event);
+        }
+    }
+
+    /// Notify that a pinch gesture began
+    ///
+    /// See [`PointerHandle::gesture_swipe_begin`] for how gestures are delivered.
+    #[instrument(level = "trace", parent = &self.span, skip(self, data))]
+    pub fn gesture_pinch_begin(&self, data: &mut D, event: &GesturePinchBeginEvent) {
+        let seat = self.get_seat(data);
+        self.inner.lock().unwrap().gesture_begin(
+            data,
+            &seat,
+            GestureKind::Pinch,
+            event.serial,
+            event.time,
+            |target, data| target.gesture_pinch_begin(&seat, data, event),
+        );
+    }
+
+    /// Notify that a pinch gesture was updated
+    #[instrument(level = "trace", parent = &self.span, skip(self, data))]
+    pub fn gesture_pinch_update(&self, data: &mut D, event: &GesturePinchUpdateEvent) {
+        let seat = self.get_seat(data);
+        let target = self
+            .inner
+            .lock()
+            .unwrap()
+            .gesture_target(GestureKind::Pinch, false);
+        if let Some(target) = target {
+            target.gesture_pinch_update(&seat, data, event);
+        }
+    }
+
+    /// Notify that a pinch gesture ended
+    #[instrument(level = "trace", parent = &self.span, skip(self, data))]
+    pub fn gesture_pinch_end(&self, data: &mut D, event: &GesturePinchEndEvent) {
+        let seat = self.get_seat(data);
+        let target = self
+            .inner
+            .lock()
+            .unwrap()
+            .gesture_target(GestureKind::Pinch, true);
+        if let Some(target) = target {
+            target.gesture_pinch_end(&seat, data, event);
+        }
+    }
+
+    /// Notify that a hold gesture began
+    ///
+    /// See [`PointerHandle::gesture_swipe_begin`] for how gestures are delivered.
+    #[instrument(level = "trace", parent = &self.span, skip(self, data))]
+    pub fn gesture_hold_begin(&self, data: &mut D, event: &GestureHoldBeginEvent) {
+        let seat = self.get_seat(data);
+        self.inner.lock().unwrap().gesture_begin(
+            data,
+            &seat,
+            GestureKind::Hold,
+            event.serial,
+            event.time,
+            |target, data| target.gesture_hold_begin(&seat, data, event),
+        );
+    }
+
+    /// Notify that a hold gesture ended
+    #[instrument(level = "trace", parent = &self.span, skip(self, data))]
+    pub fn gesture_hold_end(&self, data: &mut D, event: &GestureHoldEndEvent) {
+        let seat = self.get_seat(data);
+        let target = self.inner.lock().unwrap().gesture_target(GestureKind::Hold, true);
+        if let Some(target) = target {
+            target.gesture_hold_end(&seat, data, event);
+        }
+    }
+
+    /// Kind of the gesture currently in progress, if any
+    pub fn current_gesture(&self) -> Option<GestureKind> {
+        self.inner.lock().unwrap().gesture.as_ref().map(|(kind, _)| *kind)
+    }
+
     /// Access the current location of this pointer in the global space
     pub fn current_location(&self) -> Point<f64, Logical> {
         self.inner.lock().unwrap().location
@@ -262,6 +450,13 @@ where
     pub fn current_focus(&self) -> Option<<D as SeatHandler>::PointerFocus> {
         self.inner.lock().unwrap().focus.clone().map(|(focus, _)| focus)
     }
+
+    #[cfg(feature = "wayland_frontend")]
+    pub(crate) fn current_focus_with_location(
+        &self,
+    ) -> Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)> {
+        self.inner.lock().unwrap().focus.clone()
+    }
 }
 
 /// This inner handle is accessed from inside a pointer grab logic, and directly
@@ -383,6 +578,7 @@ pub(crate) struct PointerInternal<D: SeatHandler> {
     location: Point<f64, Logical>,
     grab: GrabStatus<D>,
     pressed_buttons: Vec<u32>,
+    gesture: Option<(GestureKind, <D as SeatHandler>::PointerFocus)>,
 }
 
 // image_callback does not implement debug, so we have to impl Debug manually
@@ -397,6 +593,7 @@ where
             .field("location", &self.location)
             .field("grab", &self.grab)
             .field("pressed_buttons", &self.pressed_buttons)
+            .field("gesture", &self.gesture)
             .field("image_callback", &"...")
             .finish()
     }
@@ -410,6 +607,7 @@ impl<D: SeatHandler + 'static> PointerInternal<D> {
             location: (0.0, 0.0).into(),
             grab: GrabStatus::None,
             pressed_buttons: Vec::new(),
+            gesture: None,
         }
     }
 
@@ -421,6 +619,7 @@ impl<D: SeatHandler + 'static> PointerInternal<D> {
         grab: G,
         focus: Focus,
     ) {
+        self.cancel_gesture(data, seat, serial, 0);
         self.grab = GrabStatus::Active(serial, Box::new(grab));
 
         if matches!(focus, Focus::Clear) {
@@ -473,6 +672,8 @@ impl<D: SeatHandler + 'static> PointerInternal<D> {
             }
         }
         if leave {
+            // the gesture has to end before its target loses the focus
+            self.cancel_gesture(data, seat, event.serial, event.time);
             if let Some((focused, _)) = self.focus.as_mut() {
                 focused.leave(seat, data, event.serial, event.time);
             }
@@ -513,6 +714,86 @@ impl<D: SeatHandler + 'static> PointerInternal<D> {
         }
     }
 
+    fn gesture_begin<F>(
+        &mut self,
+        data: &mut D,
+        seat: &Seat<D>,
+        kind: GestureKind,
+        serial: Serial,
+        time: u32,
+        begin: F,
+    ) where
+        F: FnOnce(&<D as SeatHandler>::PointerFocus, &mut D),
+    {
+        self.cancel_gesture(data, seat, serial, time);
+        if !matches!(self.grab, GrabStatus::None) {
+            trace!(?kind, "Gesture refused during grab");
+            return;
+        }
+        if let Some((focus, _)) = self.focus.as_ref() {
+            begin(focus, data);
+            self.gesture = Some((kind, focus.clone()));
+        }
+    }
+
+    // returns the target of the gesture in progress, if it is of the given kind and still alive
+    fn gesture_target(&mut self, kind: GestureKind, end: bool) -> Option<<D as SeatHandler>::PointerFocus> {
+        match self.gesture.as_ref() {
+            Some((current, target)) if *current == kind && target.alive() => {
+                let target = target.clone();
+                if end {
+                    self.gesture = None;
+                }
+                Some(target)
+            }
+            Some((current, _)) if *current == kind => {
+                // the target died, nobody is left to end the gesture
+                self.gesture = None;
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn cancel_gesture(&mut self, data: &mut D, seat: &Seat<D>, serial: Serial, time: u32) {
+        let Some((kind, target)) = self.gesture.take() else {
+            return;
+        };
+        if !target.alive() {
+            return;
+        }
+        trace!(?kind, "Cancelling gesture");
+        match kind {
+            GestureKind::Swipe => target.gesture_swipe_end(
+                seat,
+                data,
+                &GestureSwipeEndEvent {
+                    serial,
+                    time,
+                    cancelled: true,
+                },
+            ),
+            GestureKind::Pinch => target.gesture_pinch_end(
+                seat,
+                data,
+                &GesturePinchEndEvent {
+                    serial,
+                    time,
+                    cancelled: true,
+                },
+            ),
+            GestureKind::Hold => target.gesture_hold_end(
+                seat,
+                data,
+                &GestureHoldEndEvent {
+                    serial,
+                    time,
+                    cancelled: true,
+                },
+            ),
+        }
+    }
+
     fn with_grab<F>(&mut self, seat: &Seat<D>, f: F)
     where
         F: FnOnce(PointerInnerHandle<'_, D>, &mut dyn PointerGrab<D>),
@@ -695,3 +976,145 @@ impl AxisFrame {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use super::{
+        GesturePinchBeginEvent, GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent,
+        GestureSwipeEndEvent, GestureSwipeUpdateEvent, MotionEvent, PointerHandle,
+    };
+    use crate::{
+        input::{
+            test_utils::{Log, Target, TestState},
+            SeatState,
+        },
+        utils::SERIAL_COUNTER,
+    };
+
+    fn setup() -> (TestState, PointerHandle<TestState>) {
+        let mut state = TestState {
+            seat_state: SeatState::new(),
+        };
+        let mut seat = state.seat_state.new_seat("test");
+        let pointer = seat.add_pointer();
+        (state, pointer)
+    }
+
+    fn move_to(state: &mut TestState, pointer: &PointerHandle<TestState>, target: &Target) {
+        pointer.motion(
+            state,
+            Some((target.clone(), (0, 0).into())),
+            &MotionEvent {
+                location: (10.0, 10.0).into(),
+                serial: SERIAL_COUNTER.next_serial(),
+                time: 0,
+            },
+        );
+    }
+
+    #[test]
+    fn focus_change_cancels_pinch() {
+        let log = Log::default();
+        let (mut state, pointer) = setup();
+        let first = Target::new(1, &log);
+        let second = Target::new(2, &log);
+
+        move_to(&mut state, &pointer, &first);
+        pointer.gesture_pinch_begin(
+            &mut state,
+            &GesturePinchBeginEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: 0,
+                fingers: 2,
+            },
+        );
+        let update = GesturePinchUpdateEvent {
+            time: 1,
+            delta: (0.0, 0.0).into(),
+            scale: 1.5,
+            rotation: 0.0,
+        };
+        pointer.gesture_pinch_update(&mut state, &update);
+
+        move_to(&mut state, &pointer, &second);
+        assert_eq!(pointer.current_gesture(), None);
+        // the rest of the gesture must not reach the new focus
+        pointer.gesture_pinch_update(&mut state, &update);
+        pointer.gesture_pinch_end(
+            &mut state,
+            &GesturePinchEndEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: 2,
+                cancelled: false,
+            },
+        );
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                (1, "pinch_begin"),
+                (1, "pinch_update"),
+                (1, "pinch_cancel"),
+                (1, "leave"),
+            ]
+        );
+    }
+
+    #[test]
+    fn destroyed_surface_ends_swipe() {
+        let log = Log::default();
+        let (mut state, pointer) = setup();
+        let first = Target::new(1, &log);
+        let second = Target::new(2, &log);
+
+        move_to(&mut state, &pointer, &first);
+        pointer.gesture_swipe_begin(
+            &mut state,
+            &GestureSwipeBeginEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: 0,
+                fingers: 3,
+            },
+        );
+        first.alive.store(false, Ordering::SeqCst);
+        pointer.gesture_swipe_update(
+            &mut state,
+            &GestureSwipeUpdateEvent {
+                time: 1,
+                delta: (5.0, 0.0).into(),
+            },
+        );
+        assert_eq!(pointer.current_gesture(), None);
+
+        move_to(&mut state, &pointer, &second);
+        pointer.gesture_swipe_begin(
+            &mut state,
+            &GestureSwipeBeginEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: 2,
+                fingers: 3,
+            },
+        );
+        pointer.gesture_swipe_end(
+            &mut state,
+            &GestureSwipeEndEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: 3,
+                cancelled: false,
+            },
+        );
+
+        let log = log.lock().unwrap();
+        let gestures = log
+            .iter()
+            .filter(|(_, event)| *event != "leave")
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            gestures,
+            vec![(1, "swipe_begin"), (2, "swipe_begin"), (2, "swipe_end")]
+        );
+    }
+}
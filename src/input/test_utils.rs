@@ -0,0 +1,170 @@
+//! Seat state and focus target shared by the input unit tests
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+use crate::{
+    backend::input::KeyState,
+    input::{
+        keyboard::{KeyboardTarget, KeysymHandle, ModifiersState},
+        pointer::{
+            AxisFrame, ButtonEvent, GesturePinchBeginEvent, GesturePinchEndEvent, GesturePinchUpdateEvent,
+            GestureSwipeBeginEvent, GestureSwipeEndEvent, GestureSwipeUpdateEvent, MotionEvent,
+            PointerTarget, RelativeMotionEvent,
+        },
+        Seat, SeatHandler, SeatState,
+    },
+    utils::{IsAlive, Serial},
+};
+
+/// Events received by [`Target`]s, with the id of the receiving target
+pub(crate) type Log = Arc<Mutex<Vec<(u32, &'static str)>>>;
+
+/// Pointer and keyboard focus recording the events it receives into a [`Log`]
+#[derive(Debug, Clone)]
+pub(crate) struct Target {
+    id: u32,
+    pub(crate) alive: Arc<AtomicBool>,
+    log: Log,
+}
+
+impl Target {
+    pub(crate) fn new(id: u32, log: &Log) -> Target {
+        Target {
+            id,
+            alive: Arc::new(AtomicBool::new(true)),
+            log: log.clone(),
+        }
+    }
+
+    fn record(&self, event: &'static str) {
+        self.log.lock().unwrap().push((self.id, event));
+    }
+}
+
+impl PartialEq for Target {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl IsAlive for Target {
+    fn alive(&self) -> bool {
+        self.alive.load(Ordering::SeqCst)
+    }
+}
+
+impl PointerTarget<TestState> for Target {
+    fn enter(&self, _seat: &Seat<TestState>, _data: &mut TestState, _event: &MotionEvent) {}
+    fn motion(&self, _seat: &Seat<TestState>, _data: &mut TestState, _event: &MotionEvent) {}
+    fn relative_motion(&self, _seat: &Seat<TestState>, _data: &mut TestState, _event: &RelativeMotionEvent) {}
+    fn button(&self, _seat: &Seat<TestState>, _data: &mut TestState, _event: &ButtonEvent) {}
+    fn axis(&self, _seat: &Seat<TestState>, _data: &mut TestState, _frame: AxisFrame) {}
+    fn leave(&self, _seat: &Seat<TestState>, _data: &mut TestState, _serial: Serial, _time: u32) {
+        self.record("leave");
+    }
+    fn gesture_swipe_begin(
+        &self,
+        _seat: &Seat<TestState>,
+        _data: &mut TestState,
+        _event: &GestureSwipeBeginEvent,
+    ) {
+        self.record("swipe_begin");
+    }
+    fn gesture_swipe_update(
+        &self,
+        _seat: &Seat<TestState>,
+        _data: &mut TestState,
+        _event: &GestureSwipeUpdateEvent,
+    ) {
+        self.record("swipe_update");
+    }
+    fn gesture_swipe_end(
+        &self,
+        _seat: &Seat<TestState>,
+        _data: &mut TestState,
+        event: &GestureSwipeEndEvent,
+    ) {
+        self.record(if event.cancelled {
+            "swipe_cancel"
+        } else {
+            "swipe_end"
+        });
+    }
+    fn gesture_pinch_begin(
+        &self,
+        _seat: &Seat<TestState>,
+        _data: &mut TestState,
+        _event: &GesturePinchBeginEvent,
+    ) {
+        self.record("pinch_begin");
+    }
+    fn gesture_pinch_update(
+        &self,
+        _seat: &Seat<TestState>,
+        _data: &mut TestState,
+        _event: &GesturePinchUpdateEvent,
+    ) {
+        self.record("pinch_update");
+    }
+    fn gesture_pinch_end(
+        &self,
+        _seat: &Seat<TestState>,
+        _data: &mut TestState,
+        event: &GesturePinchEndEvent,
+    ) {
+        self.record(if event.cancelled {
+            "pinch_cancel"
+        } else {
+            "pinch_end"
+        });
+    }
+}
+
+impl KeyboardTarget<TestState> for Target {
+    fn enter(
+        &self,
+        _seat: &Seat<TestState>,
+        _data: &mut TestState,
+        _keys: Vec<KeysymHandle<'_>>,
+        _serial: Serial,
+    ) {
+    }
+    fn leave(&self, _seat: &Seat<TestState>, _data: &mut TestState, _serial: Serial) {}
+    fn key(
+        &self,
+        _seat: &Seat<TestState>,
+        _data: &mut TestState,
+        _key: KeysymHandle<'_>,
+        _state: KeyState,
+        _serial: Serial,
+        _time: u32,
+    ) {
+        self.record("key");
+    }
+    fn modifiers(
+        &self,
+        _seat: &Seat<TestState>,
+        _data: &mut TestState,
+        _modifiers: ModifiersState,
+        _serial: Serial,
+    ) {
+        self.record("modifiers");
+    }
+}
+
+/// Seat handler using [`Target`] as its focus
+pub(crate) struct TestState {
+    pub(crate) seat_state: SeatState<TestState>,
+}
+
+impl SeatHandler for TestState {
+    type KeyboardFocus = Target;
+    type PointerFocus = Target;
+
+    fn seat_state(&mut self) -> &mut SeatState<Self> {
+        &mut self.seat_state
+    }
+}
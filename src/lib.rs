@@ -55,6 +55,8 @@ pub mod backend;
 pub mod desktop;
 pub mod input;
 pub mod output;
+#[cfg(feature = "test_utils")]
+pub mod testing;
 pub mod utils;
 #[cfg(feature = "wayland_frontend")]
 pub mod wayland;
@@ -20,6 +20,9 @@
 //!
 //! You can attach additional properties to your `Output`s by using [`Output::user_data`].
 //!
+//! To arrange multiple outputs according to a declarative, persistable configuration
+//! take a look at [`OutputLayout`].
+//!
 //! ```
 //! # extern crate smithay;
 //! use smithay::output::{Output, PhysicalProperties, Scale, Mode, Subpixel};
@@ -54,17 +57,27 @@ use std::{
     sync::{Arc, Mutex, Weak},
 };
 
-use tracing::{info, instrument};
+use tracing::{info, info_span, instrument};
 
 #[cfg(feature = "wayland_frontend")]
 use crate::wayland::output::xdg::XdgOutput;
 #[cfg(feature = "backend_drm")]
 use drm::control::{Mode as DrmMode, ModeFlags};
 #[cfg(feature = "wayland_frontend")]
-use wayland_server::{backend::WeakHandle, protocol::wl_output::WlOutput};
+use wayland_server::{
+    backend::{GlobalId, WeakHandle},
+    protocol::wl_output::WlOutput,
+};
 
 use crate::utils::{user_data::UserDataMap, Logical, Physical, Point, Raw, Size, Transform};
 
+mod layout;
+
+pub use layout::{
+    LayoutChanges, LayoutConfig, LayoutError, ModeConfig, OutputChange, OutputConfig, OutputLayout,
+    OutputMatch, OutputState, PositionConfig,
+};
+
 /// An output mode
 ///
 /// A possible combination of dimensions and refresh rate for an output.
@@ -200,6 +213,7 @@ pub(crate) struct Inner {
     pub(crate) modes: Vec<Mode>,
     pub(crate) current_mode: Option<Mode>,
     pub(crate) preferred_mode: Option<Mode>,
+    pub(crate) span: tracing::Span,
 
     // used by the wayland::output module.
     #[cfg(feature = "wayland_frontend")]
@@ -207,6 +221,8 @@ pub(crate) struct Inner {
     #[cfg(feature = "wayland_frontend")]
     pub(crate) handle: Option<WeakHandle>,
     #[cfg(feature = "wayland_frontend")]
+    pub(crate) global: Option<GlobalId>,
+    #[cfg(feature = "wayland_frontend")]
     pub(crate) xdg_output: Option<XdgOutput>,
 }
 
@@ -245,6 +261,8 @@ impl Output {
                 instances: Vec::new(),
                 #[cfg(feature = "wayland_frontend")]
                 handle: None,
+                #[cfg(feature = "wayland_frontend")]
+                global: None,
                 physical,
                 location: (0, 0).into(),
                 transform: Transform::Normal,
@@ -252,6 +270,7 @@ impl Output {
                 modes: Vec::new(),
                 current_mode: None,
                 preferred_mode: None,
+                span: info_span!(parent: None, "output", output = name),
                 #[cfg(feature = "wayland_frontend")]
                 xdg_output: None,
             }),
@@ -316,6 +335,14 @@ impl Output {
         self.inner.0.lock().unwrap().description.clone()
     }
 
+    /// Returns the tracing span of the output
+    ///
+    /// Create spans handling this output, like rendering frames for it, as its children
+    /// to have them carry its `output` field.
+    pub fn span(&self) -> tracing::Span {
+        self.inner.0.lock().unwrap().span.clone()
+    }
+
     /// Returns the physical properties of the output
     pub fn physical_properties(&self) -> PhysicalProperties {
         self.inner.0.lock().unwrap().physical.clone()
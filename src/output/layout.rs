@@ -0,0 +1,922 @@
+use std::fmt;
+
+use crate::utils::{Logical, Point, Rectangle, Size, Transform};
+
+use super::{Mode, Output, Scale};
+
+/// Selects an output of an [`OutputLayout`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputMatch {
+    /// Match the output by its name, usually the connector (see [`Output::name`])
+    Name(String),
+    /// Match the output by make and model of the monitor, as reported by its EDID
+    ///
+    /// Unlike the name this stays the same if the monitor is connected to a different port.
+    Identity {
+        /// Make of the monitor
+        make: String,
+        /// Model of the monitor
+        model: String,
+    },
+}
+
+impl OutputMatch {
+    /// Selector matching the make and model of the given output
+    pub fn identity_of(output: &Output) -> OutputMatch {
+        let physical = output.physical_properties();
+        OutputMatch::Identity {
+            make: physical.make,
+            model: physical.model,
+        }
+    }
+
+    /// Returns whether the given output is selected
+    pub fn matches(&self, output: &Output) -> bool {
+        match self {
+            OutputMatch::Name(name) => output.name() == *name,
+            OutputMatch::Identity { make, model } => {
+                let physical = output.physical_properties();
+                physical.make == *make && physical.model == *model
+            }
+        }
+    }
+}
+
+impl fmt::Display for OutputMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputMatch::Name(name) => write!(f, "{}", name),
+            OutputMatch::Identity { make, model } => write!(f, "{} {}", make, model),
+        }
+    }
+}
+
+/// Mode to use for an output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ModeConfig {
+    /// The preferred mode of the output
+    #[default]
+    Preferred,
+    /// A mode of the given size
+    Size {
+        /// Width in pixels
+        width: i32,
+        /// Height in pixels
+        height: i32,
+        /// Refresh rate in millihertz, the highest available if `None`
+        refresh: Option<i32>,
+    },
+}
+
+/// Position of an output in the global space
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PositionConfig {
+    /// Placed to the right of the outputs configured before it
+    ///
+    /// Only outputs with automatic or fixed positions and outputs not part of the
+    /// configuration are taken into account.
+    #[default]
+    Auto,
+    /// Placed at the given location
+    Fixed {
+        /// Horizontal location in the global space
+        x: i32,
+        /// Vertical location in the global space
+        y: i32,
+    },
+    /// Placed directly to the right of another output, aligned to its top edge
+    RightOf(OutputMatch),
+    /// Placed at the location of another output, mirroring its contents
+    MirrorOf(OutputMatch),
+}
+
+/// Configuration of a single output
+///
+/// Like all configuration types of the layout this is plain data, making it easy
+/// to persist. With the `serde` feature enabled it can be (de)serialized directly.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OutputConfig {
+    /// The configured output
+    pub output: OutputMatch,
+    /// Whether the output should be used
+    pub enabled: bool,
+    /// Mode of the output
+    pub mode: ModeConfig,
+    /// Position of the output
+    pub position: PositionConfig,
+    /// Scale of the output
+    pub scale: f64,
+    /// Transform of the output
+    pub transform: Transform,
+}
+
+impl OutputConfig {
+    /// Default configuration for an output, enabling it with its preferred mode
+    pub fn new(output: OutputMatch) -> OutputConfig {
+        OutputConfig {
+            output,
+            enabled: true,
+            mode: ModeConfig::Preferred,
+            position: PositionConfig::Auto,
+            scale: 1.0,
+            transform: Transform::Normal,
+        }
+    }
+}
+
+/// Declarative configuration of an [`OutputLayout`]
+///
+/// Outputs are resolved in order, which matters for [`PositionConfig::Auto`].
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayoutConfig {
+    /// Configuration of the outputs
+    pub outputs: Vec<OutputConfig>,
+}
+
+/// Resolved state of an output
+#[derive(Debug, Clone, Copy)]
+pub struct OutputState {
+    /// Mode of the output, `None` if the output is disabled
+    pub mode: Option<Mode>,
+    /// Location in the global space
+    pub location: Point<i32, Logical>,
+    /// Scale of the output
+    pub scale: Scale,
+    /// Transform of the output
+    pub transform: Transform,
+}
+
+impl OutputState {
+    /// Returns whether the output is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.mode.is_some()
+    }
+
+    /// Geometry of the output in the global space, if enabled
+    pub fn geometry(&self) -> Option<Rectangle<i32, Logical>> {
+        self.mode.map(|mode| {
+            Rectangle::from_loc_and_size(
+                self.location,
+                self.transform
+                    .transform_size(mode.size)
+                    .to_f64()
+                    .to_logical(self.scale.fractional_scale())
+                    .to_i32_ceil(),
+            )
+        })
+    }
+
+    fn same_as(&self, other: &OutputState) -> bool {
+        if self.mode != other.mode {
+            return false;
+        }
+        // properties of disabled outputs do not matter
+        self.mode.is_none()
+            || (self.location == other.location
+                && self.transform == other.transform
+                && self.scale.fractional_scale() == other.scale.fractional_scale()
+                && self.scale.integer_scale() == other.scale.integer_scale())
+    }
+}
+
+/// Errors resolving the configuration of an output
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum LayoutError {
+    /// The output has no preferred mode
+    #[error("the output has no preferred mode")]
+    NoPreferredMode,
+    /// The output does not support the requested mode
+    #[error("the output does not support the mode {0:?}")]
+    UnsupportedMode(ModeConfig),
+    /// The scale is not a positive number
+    #[error("invalid scale {0}")]
+    InvalidScale(f64),
+    /// A referenced output is not part of the layout
+    #[error("the referenced output {0} is not part of the layout")]
+    UnknownOutput(OutputMatch),
+    /// A referenced output is disabled
+    #[error("the referenced output {0} is disabled")]
+    DisabledOutput(OutputMatch),
+    /// The configuration of a referenced output is invalid
+    #[error("the configuration of the referenced output {0} is invalid")]
+    InvalidReference(OutputMatch),
+    /// Output positions reference each other
+    #[error("the output positions reference each other in a cycle")]
+    CyclicReference,
+}
+
+/// Outcome of applying a configuration to a single output
+#[derive(Debug)]
+pub enum OutputChange<E> {
+    /// The output already was in the requested state
+    Unchanged,
+    /// The new state was accepted by the backend and applied to the output
+    Applied,
+    /// The configuration could not be resolved
+    Invalid(LayoutError),
+    /// The backend rejected the new state
+    Rejected(E),
+}
+
+/// Result of [`OutputLayout::apply`]
+#[derive(Debug)]
+pub struct LayoutChanges<E> {
+    /// Outcome for every configured output, in order of the configuration
+    pub outputs: Vec<(Output, OutputChange<E>)>,
+    /// Configured outputs not found in the layout
+    pub unmatched: Vec<OutputMatch>,
+}
+
+impl<E> LayoutChanges<E> {
+    /// Returns whether every configured output was found and is in its requested state
+    pub fn is_complete(&self) -> bool {
+        self.unmatched.is_empty()
+            && self
+                .outputs
+                .iter()
+                .all(|(_, change)| matches!(change, OutputChange::Unchanged | OutputChange::Applied))
+    }
+
+    /// Outputs whose state changed
+    pub fn applied(&self) -> impl Iterator<Item = &Output> {
+        self.outputs
+            .iter()
+            .filter(|(_, change)| matches!(change, OutputChange::Applied))
+            .map(|(output, _)| output)
+    }
+}
+
+#[derive(Debug)]
+struct LayoutOutput {
+    output: Output,
+    enabled: bool,
+}
+
+impl LayoutOutput {
+    fn state(&self) -> OutputState {
+        OutputState {
+            mode: self.output.current_mode().filter(|_| self.enabled),
+            location: self.output.current_location(),
+            scale: self.output.current_scale(),
+            transform: self.output.current_transform(),
+        }
+    }
+}
+
+/// Arrangement of a set of outputs
+///
+/// The layout applies declarative [`LayoutConfig`]s to its outputs, resolving preferred
+/// modes and relative positions, and only touches outputs whose state actually changed.
+/// [`OutputLayout::current_config`] provides the inverse, a configuration reproducing
+/// the current state, which can be persisted and applied again later on.
+///
+/// Outputs without a current mode are considered disabled when added to the layout.
+#[derive(Debug, Default)]
+pub struct OutputLayout {
+    outputs: Vec<LayoutOutput>,
+}
+
+impl OutputLayout {
+    /// Create a new layout from a set of outputs
+    pub fn new(outputs: impl IntoIterator<Item = Output>) -> OutputLayout {
+        let mut layout = OutputLayout::default();
+        for output in outputs {
+            layout.add_output(output);
+        }
+        layout
+    }
+
+    /// Add an output to the layout
+    pub fn add_output(&mut self, output: Output) {
+        if self.outputs.iter().any(|entry| entry.output == output) {
+            return;
+        }
+        let enabled = output.current_mode().is_some();
+        self.outputs.push(LayoutOutput { output, enabled });
+    }
+
+    /// Remove an output from the layout
+    pub fn remove_output(&mut self, output: &Output) {
+        self.outputs.retain(|entry| entry.output != *output);
+    }
+
+    /// Outputs of this layout
+    pub fn outputs(&self) -> impl Iterator<Item = &Output> {
+        self.outputs.iter().map(|entry| &entry.output)
+    }
+
+    /// Current state of an output of the layout
+    pub fn state(&self, output: &Output) -> Option<OutputState> {
+        self.outputs
+            .iter()
+            .find(|entry| entry.output == *output)
+            .map(LayoutOutput::state)
+    }
+
+    /// Configuration reproducing the current state of all outputs
+    ///
+    /// Outputs are selected by name and placed at fixed positions.
+    pub fn current_config(&self) -> LayoutConfig {
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|entry| {
+                let state = entry.state();
+                let mode = entry
+                    .output
+                    .current_mode()
+                    .map(|mode| ModeConfig::Size {
+                        width: mode.size.w,
+                        height: mode.size.h,
+                        refresh: Some(mode.refresh),
+                    })
+                    .unwrap_or_default();
+                OutputConfig {
+                    output: OutputMatch::Name(entry.output.name()),
+                    enabled: state.is_enabled(),
+                    mode,
+                    position: PositionConfig::Fixed {
+                        x: state.location.x,
+                        y: state.location.y,
+                    },
+                    scale: state.scale.fractional_scale(),
+                    transform: state.transform,
+                }
+            })
+            .collect();
+        LayoutConfig { outputs }
+    }
+
+    /// Resolve the configuration without applying it
+    ///
+    /// Returns the state every configured output would end up in.
+    pub fn resolve(&self, config: &LayoutConfig) -> Vec<(Output, Result<OutputState, LayoutError>)> {
+        let (mut resolver, _) = Resolver::new(self, config);
+        (0..resolver.targets.len())
+            .map(|idx| {
+                let output = self.outputs[resolver.targets[idx].output].output.clone();
+                (output, resolver.state(idx))
+            })
+            .collect()
+    }
+
+    /// Apply a configuration to the outputs of this layout
+    ///
+    /// `backend` is called for every output whose state changes and should validate the new
+    /// state, e.g. using a DRM test commit, and configure the backend accordingly. If it
+    /// succeeds the state is applied to the [`Output`], otherwise the output is left untouched.
+    ///
+    /// Outputs are applied after the outputs their position depends on. Outputs positioned
+    /// relative to an output the backend rejected are placed relative to its unchanged state.
+    ///
+    /// Outputs of the layout not part of the configuration keep their state.
+    pub fn apply<E, F>(&mut self, config: &LayoutConfig, mut backend: F) -> LayoutChanges<E>
+    where
+        F: FnMut(&Output, &OutputState) -> Result<(), E>,
+    {
+        let (targets, unmatched) = {
+            let (resolver, unmatched) = Resolver::new(self, config);
+            let targets = resolver
+                .targets
+                .iter()
+                .map(|target| target.output)
+                .collect::<Vec<_>>();
+            (targets, unmatched)
+        };
+
+        let mut changes = targets
+            .iter()
+            .map(|_| None)
+            .collect::<Vec<Option<OutputChange<E>>>>();
+        let mut rejected = vec![false; targets.len()];
+        while changes.iter().any(Option::is_none) {
+            // resolve again after every change, so rejected outputs are taken into account
+            let (next, state) = {
+                let (mut resolver, _) = Resolver::new(self, config);
+                resolver.pinned = rejected.clone();
+                let states = (0..targets.len())
+                    .map(|idx| resolver.state(idx))
+                    .collect::<Vec<_>>();
+                let pending = |idx: &usize| changes[*idx].is_none();
+                // outputs in a reference cycle are invalid anyway, so their order does not matter
+                let next = (0..targets.len())
+                    .filter(pending)
+                    .find(|idx| !resolver.dependencies[*idx].iter().any(pending))
+                    .or_else(|| (0..targets.len()).find(pending))
+                    .unwrap();
+                (next, states.into_iter().nth(next).unwrap())
+            };
+
+            let entry = &mut self.outputs[targets[next]];
+            let change = match state {
+                Err(err) => OutputChange::Invalid(err),
+                Ok(state) if state.same_as(&entry.state()) => OutputChange::Unchanged,
+                Ok(state) => match backend(&entry.output, &state) {
+                    Ok(()) => {
+                        if state.mode.is_some() {
+                            entry.output.change_current_state(
+                                state.mode,
+                                Some(state.transform),
+                                Some(state.scale),
+                                Some(state.location),
+                            );
+                        }
+                        entry.enabled = state.is_enabled();
+                        OutputChange::Applied
+                    }
+                    Err(err) => {
+                        rejected[next] = true;
+                        OutputChange::Rejected(err)
+                    }
+                },
+            };
+            changes[next] = Some(change);
+        }
+
+        let outputs = targets
+            .into_iter()
+            .zip(changes)
+            .map(|(idx, change)| (self.outputs[idx].output.clone(), change.unwrap()))
+            .collect();
+        LayoutChanges { outputs, unmatched }
+    }
+}
+
+struct Target<'a> {
+    output: usize,
+    config: &'a OutputConfig,
+}
+
+enum Slot {
+    Pending,
+    Resolving,
+    Done(Result<Point<i32, Logical>, LayoutError>),
+}
+
+struct Resolver<'a> {
+    layout: &'a OutputLayout,
+    targets: Vec<Target<'a>>,
+    positions: Vec<Slot>,
+    /// Targets whose position depends on the state of other targets
+    dependencies: Vec<Vec<usize>>,
+    /// Targets that keep their current state, regardless of their configuration
+    pinned: Vec<bool>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(layout: &'a OutputLayout, config: &'a LayoutConfig) -> (Resolver<'a>, Vec<OutputMatch>) {
+        let mut targets: Vec<Target<'a>> = Vec::new();
+        let mut unmatched = Vec::new();
+        for output_config in &config.outputs {
+            let found = layout.outputs.iter().enumerate().position(|(idx, entry)| {
+                output_config.output.matches(&entry.output) && targets.iter().all(|t| t.output != idx)
+            });
+            match found {
+                Some(output) => targets.push(Target {
+                    output,
+                    config: output_config,
+                }),
+                None => unmatched.push(output_config.output.clone()),
+            }
+        }
+        let positions = targets.iter().map(|_| Slot::Pending).collect();
+        let dependencies = targets.iter().map(|_| Vec::new()).collect();
+        let pinned = vec![false; targets.len()];
+        (
+            Resolver {
+                layout,
+                targets,
+                positions,
+                dependencies,
+                pinned,
+            },
+            unmatched,
+        )
+    }
+
+    fn entry(&self, idx: usize) -> &'a LayoutOutput {
+        &self.layout.outputs[self.targets[idx].output]
+    }
+
+    fn state(&mut self, idx: usize) -> Result<OutputState, LayoutError> {
+        let config = self.targets[idx].config;
+        let current = self.entry(idx).state();
+        if self.pinned[idx] {
+            return Ok(current);
+        }
+        if !config.enabled {
+            return Ok(OutputState {
+                mode: None,
+                ..current
+            });
+        }
+
+        let mode = resolve_mode(&self.entry(idx).output, config.mode)?;
+        if !(config.scale.is_finite() && config.scale > 0.0) {
+            return Err(LayoutError::InvalidScale(config.scale));
+        }
+        let scale = if config.scale.fract() == 0.0 {
+            Scale::Integer(config.scale as i32)
+        } else {
+            Scale::Fractional(config.scale)
+        };
+        let location = self.position(idx)?;
+
+        Ok(OutputState {
+            mode: Some(mode),
+            location,
+            scale,
+            transform: config.transform,
+        })
+    }
+
+    fn position(&mut self, idx: usize) -> Result<Point<i32, Logical>, LayoutError> {
+        match &self.positions[idx] {
+            Slot::Done(result) => return result.clone(),
+            Slot::Resolving => return Err(LayoutError::CyclicReference),
+            Slot::Pending => {}
+        }
+        self.positions[idx] = Slot::Resolving;
+        let result = self.compute_position(idx);
+        self.positions[idx] = Slot::Done(result.clone());
+        result
+    }
+
+    fn compute_position(&mut self, idx: usize) -> Result<Point<i32, Logical>, LayoutError> {
+        let config = self.targets[idx].config;
+        if !config.enabled {
+            return Ok(self.entry(idx).output.current_location());
+        }
+
+        match &config.position {
+            PositionConfig::Auto => {
+                let mut right_edge = self
+                    .unconfigured()
+                    .filter_map(|entry| entry.state().geometry())
+                    .map(|geometry| geometry.loc.x + geometry.size.w)
+                    .max()
+                    .unwrap_or(0);
+                for other in 0..idx {
+                    // relative outputs may be placed relative to this one
+                    if !matches!(
+                        self.targets[other].config.position,
+                        PositionConfig::Auto | PositionConfig::Fixed { .. }
+                    ) {
+                        continue;
+                    }
+                    self.dependencies[idx].push(other);
+                    if let Ok(Some(geometry)) = self.geometry(other) {
+                        right_edge = right_edge.max(geometry.loc.x + geometry.size.w);
+                    }
+                }
+                Ok((right_edge, 0).into())
+            }
+            PositionConfig::Fixed { x, y } => Ok((*x, *y).into()),
+            PositionConfig::RightOf(reference) => {
+                let geometry = self.reference_geometry(idx, reference)?;
+                Ok((geometry.loc.x + geometry.size.w, geometry.loc.y).into())
+            }
+            PositionConfig::MirrorOf(reference) => Ok(self.reference_geometry(idx, reference)?.loc),
+        }
+    }
+
+    fn geometry(&mut self, idx: usize) -> Result<Option<Rectangle<i32, Logical>>, LayoutError> {
+        Ok(self.state(idx)?.geometry())
+    }
+
+    fn unconfigured(&self) -> impl Iterator<Item = &'a LayoutOutput> + '_ {
+        let layout = self.layout;
+        layout
+            .outputs
+            .iter()
+            .enumerate()
+            .filter(move |(idx, _)| self.targets.iter().all(|t| t.output != *idx))
+            .map(|(_, entry)| entry)
+    }
+
+    fn reference_geometry(
+        &mut self,
+        idx: usize,
+        reference: &OutputMatch,
+    ) -> Result<Rectangle<i32, Logical>, LayoutError> {
+        let layout = self.layout;
+        let configured = self
+            .targets
+            .iter()
+            .position(|target| reference.matches(&layout.outputs[target.output].output));
+
+        let geometry = match configured {
+            Some(configured) => {
+                self.dependencies[idx].push(configured);
+                match self.geometry(configured) {
+                    Ok(geometry) => geometry,
+                    Err(LayoutError::CyclicReference) => return Err(LayoutError::CyclicReference),
+                    Err(_) => return Err(LayoutError::InvalidReference(reference.clone())),
+                }
+            }
+            None => match self.unconfigured().find(|entry| reference.matches(&entry.output)) {
+                Some(entry) => entry.state().geometry(),
+                None => return Err(LayoutError::UnknownOutput(reference.clone())),
+            },
+        };
+        geometry.ok_or_else(|| LayoutError::DisabledOutput(reference.clone()))
+    }
+}
+
+fn resolve_mode(output: &Output, config: ModeConfig) -> Result<Mode, LayoutError> {
+    match config {
+        ModeConfig::Preferred => output.preferred_mode().ok_or(LayoutError::NoPreferredMode),
+        ModeConfig::Size {
+            width,
+            height,
+            refresh,
+        } => output
+            .modes()
+            .into_iter()
+            .filter(|mode| mode.size == Size::from((width, height)))
+            .filter(|mode| refresh.is_none() || refresh == Some(mode.refresh))
+            .max_by_key(|mode| mode.refresh)
+            .ok_or(LayoutError::UnsupportedMode(config)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{PhysicalProperties, Subpixel};
+
+    fn output(name: &str, model: &str) -> Output {
+        let output = Output::new(
+            name.into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Screens Inc".into(),
+                model: model.into(),
+            },
+        );
+        output.set_preferred(Mode {
+            size: (1920, 1080).into(),
+            refresh: 60_000,
+        });
+        output.add_mode(Mode {
+            size: (1280, 720).into(),
+            refresh: 60_000,
+        });
+        output.add_mode(Mode {
+            size: (1280, 720).into(),
+            refresh: 75_000,
+        });
+        output
+    }
+
+    fn locations(layout: &OutputLayout) -> Vec<Option<Point<i32, Logical>>> {
+        layout
+            .outputs()
+            .map(|output| {
+                let state = layout.state(output).unwrap();
+                state.geometry().map(|geometry| geometry.loc)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn relative_positions_and_minimal_changes() {
+        let left = output("DP-1", "Left");
+        let right = output("DP-2", "Right");
+        let mirror = output("HDMI-A-1", "Projector");
+        let mut layout = OutputLayout::new([left.clone(), right.clone(), mirror.clone()]);
+        assert_eq!(locations(&layout), vec![None, None, None]);
+
+        let mut config = LayoutConfig {
+            outputs: vec![
+                OutputConfig {
+                    position: PositionConfig::RightOf(OutputMatch::identity_of(&left)),
+                    scale: 2.0,
+                    ..OutputConfig::new(OutputMatch::Name("DP-2".into()))
+                },
+                OutputConfig {
+                    mode: ModeConfig::Size {
+                        width: 1280,
+                        height: 720,
+                        refresh: None,
+                    },
+                    ..OutputConfig::new(OutputMatch::Name("DP-1".into()))
+                },
+                OutputConfig {
+                    position: PositionConfig::MirrorOf(OutputMatch::Name("DP-1".into())),
+                    ..OutputConfig::new(OutputMatch::Name("HDMI-A-1".into()))
+                },
+                OutputConfig::new(OutputMatch::Name("DP-3".into())),
+            ],
+        };
+
+        let mut tested = Vec::new();
+        let changes = layout.apply(&config, |output, _| {
+            tested.push(output.clone());
+            Ok::<_, ()>(())
+        });
+        // outputs are applied after the outputs they are placed relative to
+        assert_eq!(tested, vec![left.clone(), right.clone(), mirror.clone()]);
+        assert_eq!(changes.unmatched, vec![OutputMatch::Name("DP-3".into())]);
+        assert!(!changes.is_complete());
+        assert_eq!(
+            left.current_mode(),
+            Some(Mode {
+                size: (1280, 720).into(),
+                refresh: 75_000
+            })
+        );
+        assert_eq!(
+            locations(&layout),
+            vec![Some((0, 0).into()), Some((1280, 0).into()), Some((0, 0).into())]
+        );
+
+        // only the changed output is touched
+        config.outputs.pop();
+        config.outputs[2].transform = Transform::_180;
+        let changes = layout.apply(&config, |output, state| {
+            assert_eq!(output, &mirror);
+            assert_eq!(state.transform, Transform::_180);
+            Ok::<_, ()>(())
+        });
+        assert!(changes.is_complete());
+        assert_eq!(changes.applied().collect::<Vec<_>>(), vec![&mirror]);
+
+        // outputs placed relative to a changed output follow it
+        config.outputs[1].transform = Transform::_90;
+        let changes = layout.apply(&config, |_, _| Ok::<_, ()>(()));
+        assert_eq!(changes.applied().collect::<Vec<_>>(), vec![&right, &left]);
+        assert_eq!(
+            locations(&layout),
+            vec![Some((0, 0).into()), Some((720, 0).into()), Some((0, 0).into())]
+        );
+    }
+
+    #[test]
+    fn rejected_and_invalid_outputs_are_untouched() {
+        let first = output("DP-1", "First");
+        let second = output("DP-2", "Second");
+        let mut layout = OutputLayout::new([first.clone(), second.clone()]);
+
+        let config = LayoutConfig {
+            outputs: vec![
+                OutputConfig {
+                    position: PositionConfig::RightOf(OutputMatch::Name("DP-2".into())),
+                    ..OutputConfig::new(OutputMatch::Name("DP-1".into()))
+                },
+                OutputConfig {
+                    position: PositionConfig::RightOf(OutputMatch::Name("DP-1".into())),
+                    ..OutputConfig::new(OutputMatch::Name("DP-2".into()))
+                },
+            ],
+        };
+        let changes = layout.apply(&config, |_, _| Ok::<_, ()>(()));
+        assert!(changes
+            .outputs
+            .iter()
+            .all(|(_, change)| matches!(change, OutputChange::Invalid(LayoutError::CyclicReference))));
+
+        let config = LayoutConfig {
+            outputs: vec![
+                OutputConfig::new(OutputMatch::Name("DP-1".into())),
+                OutputConfig {
+                    mode: ModeConfig::Size {
+                        width: 640,
+                        height: 480,
+                        refresh: None,
+                    },
+                    ..OutputConfig::new(OutputMatch::Name("DP-2".into()))
+                },
+            ],
+        };
+        let changes = layout.apply(&config, |_, _| Err("no crtc available"));
+        assert!(matches!(
+            changes.outputs[0].1,
+            OutputChange::Rejected("no crtc available")
+        ));
+        assert!(matches!(
+            changes.outputs[1].1,
+            OutputChange::Invalid(LayoutError::UnsupportedMode(_))
+        ));
+        assert_eq!(first.current_mode(), None);
+        assert_eq!(second.current_mode(), None);
+    }
+
+    #[test]
+    fn dependents_of_rejected_outputs_follow_their_current_state() {
+        let left = output("DP-1", "Left");
+        let right = output("DP-2", "Right");
+        let mut layout = OutputLayout::new([left.clone(), right.clone()]);
+
+        let mut config = LayoutConfig {
+            outputs: vec![
+                OutputConfig {
+                    position: PositionConfig::RightOf(OutputMatch::Name("DP-1".into())),
+                    ..OutputConfig::new(OutputMatch::Name("DP-2".into()))
+                },
+                OutputConfig::new(OutputMatch::Name("DP-1".into())),
+            ],
+        };
+        assert!(layout.apply(&config, |_, _| Ok::<_, ()>(())).is_complete());
+        assert_eq!(
+            locations(&layout),
+            vec![Some((0, 0).into()), Some((1920, 0).into())]
+        );
+
+        // the smaller mode of the left output is rejected, so the right output stays where it is
+        config.outputs[1].mode = ModeConfig::Size {
+            width: 1280,
+            height: 720,
+            refresh: None,
+        };
+        let mut tested = Vec::new();
+        let changes = layout.apply(&config, |output, _| {
+            tested.push(output.clone());
+            Err("no bandwidth")
+        });
+        assert_eq!(tested, vec![left.clone()]);
+        assert!(matches!(changes.outputs[0], (ref output, OutputChange::Unchanged) if output == &right));
+        assert!(matches!(
+            changes.outputs[1].1,
+            OutputChange::Rejected("no bandwidth")
+        ));
+        assert_eq!(
+            locations(&layout),
+            vec![Some((0, 0).into()), Some((1920, 0).into())]
+        );
+    }
+
+    #[test]
+    fn current_config_round_trip() {
+        let first = output("DP-1", "First");
+        let second = output("DP-2", "Second");
+        let mut layout = OutputLayout::new([first, second.clone()]);
+        let config = LayoutConfig {
+            outputs: vec![
+                OutputConfig {
+                    scale: 1.5,
+                    transform: Transform::Flipped,
+                    ..OutputConfig::new(OutputMatch::Name("DP-1".into()))
+                },
+                OutputConfig {
+                    position: PositionConfig::Fixed { x: 0, y: 2000 },
+                    ..OutputConfig::new(OutputMatch::Name("DP-2".into()))
+                },
+            ],
+        };
+        layout.apply(&config, |_, _| Ok::<_, ()>(()));
+
+        let saved = layout.current_config();
+        let mut restored = OutputLayout::new(layout.outputs().cloned());
+        let changes = restored.apply(&saved, |_, _| Ok::<_, ()>(()));
+        assert!(changes.is_complete());
+        assert_eq!(changes.applied().count(), 0);
+        assert_eq!(restored.current_config(), saved);
+
+        // disabling is part of the configuration as well
+        let mut disabled = saved.clone();
+        disabled.outputs[1].enabled = false;
+        let changes = restored.apply(&disabled, |_, state| {
+            assert!(!state.is_enabled());
+            Ok::<_, ()>(())
+        });
+        assert_eq!(changes.applied().collect::<Vec<_>>(), vec![&second]);
+        assert_eq!(restored.current_config(), disabled);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn config_serde_round_trip() {
+        let config = LayoutConfig {
+            outputs: vec![
+                OutputConfig {
+                    mode: ModeConfig::Size {
+                        width: 1280,
+                        height: 720,
+                        refresh: Some(75_000),
+                    },
+                    position: PositionConfig::Fixed { x: -1280, y: 0 },
+                    transform: Transform::_90,
+                    ..OutputConfig::new(OutputMatch::Name("DP-1".into()))
+                },
+                OutputConfig {
+                    position: PositionConfig::MirrorOf(OutputMatch::Name("DP-1".into())),
+                    scale: 1.5,
+                    ..OutputConfig::new(OutputMatch::Identity {
+                        make: "Screens Inc".into(),
+                        model: "Projector".into(),
+                    })
+                },
+            ],
+        };
+
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(serde_json::from_str::<LayoutConfig>(&json).unwrap(), config);
+    }
+}
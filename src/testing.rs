@@ -0,0 +1,639 @@
+//! In-process compositor and client for testing
+//!
+//! Testing protocol handling usually requires a running compositor and a real client connecting
+//! to it. This module instead runs a [`TestCompositor`] and any number of [`TestClient`]s in the
+//! same thread, connected through a socket pair. Nothing happens in the background: the client
+//! methods drive the compositor whenever they need it to answer.
+//!
+//! Client objects are created with [`Recorded`] as their user data. All events they receive are
+//! recorded and can be retrieved using [`TestClient::wait_for_event`]. Objects created by events,
+//! like data offers, can be used to send requests, but their own events are dropped.
+//!
+//! Tests that only need clients to attach shm buffers to surfaces can use [`ShmTestState`] as the
+//! compositor state and [`TestClient::create_shm_pool`] to create the buffers.
+//! Outputs with a fixed mode are created using [`test_output`].
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use smithay::{
+//!     delegate_output,
+//!     testing::{test_output, TestCompositor},
+//!     wayland::output::OutputManagerState,
+//! };
+//! use wayland_client::protocol::wl_output;
+//!
+//! struct State {
+//!     output_manager_state: OutputManagerState,
+//! }
+//! delegate_output!(State);
+//!
+//! let mut compositor = TestCompositor::new(|dh, _| {
+//!     test_output("test", (1920, 1080)).create_global::<State>(dh);
+//!     State {
+//!         output_manager_state: OutputManagerState::new(),
+//!     }
+//! });
+//!
+//! let mut client = compositor.connect();
+//! let _output = client.bind::<wl_output::WlOutput, _>(&mut compositor);
+//! let event = client.wait_for_event::<wl_output::Event, _>(&mut compositor, Duration::from_secs(1));
+//! assert!(matches!(event, Some(wl_output::Event::Geometry { .. })));
+//! ```
+
+use std::{
+    any::Any,
+    collections::VecDeque,
+    ffi::CString,
+    fmt,
+    fs::File,
+    io::ErrorKind,
+    os::unix::{
+        io::{AsRawFd, FromRawFd, OwnedFd},
+        net::UnixStream,
+    },
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use calloop::{EventLoop, LoopHandle};
+use nix::sys::memfd::{memfd_create, MemFdCreateFlag};
+use wayland_client::{
+    backend::{
+        protocol::{Message, ProtocolError},
+        Backend, ObjectData, ObjectId, WaylandError,
+    },
+    protocol::{wl_callback, wl_registry, wl_shm::WlShm, wl_shm_pool::WlShmPool},
+    Connection, Dispatch, EventQueue, Proxy, QueueHandle,
+};
+use wayland_server::{
+    backend::{ClientData, ClientId, DisconnectReason},
+    protocol::{wl_buffer::WlBuffer, wl_surface::WlSurface},
+    Client, Display, DisplayHandle,
+};
+
+use crate::{
+    backend::renderer::utils::on_commit_buffer_handler,
+    output::{Mode, Output, PhysicalProperties, Subpixel},
+    utils::{Physical, Size},
+    wayland::{
+        buffer::BufferHandler,
+        compositor::{CompositorHandler, CompositorState},
+        shm::{ShmHandler, ShmState},
+    },
+};
+
+/// Number of dispatch iterations after which [`TestClient::roundtrip`] gives up
+const MAX_ROUNDTRIP_ITERATIONS: usize = 100;
+
+/// In-process compositor to run protocol tests against
+///
+/// See the [module documentation](self) for details.
+pub struct TestCompositor<D: 'static> {
+    /// Wayland display of the compositor
+    pub display: Display<D>,
+    /// Event loop of the compositor, it does not contain the display
+    pub event_loop: EventLoop<'static, D>,
+    /// State of the compositor
+    pub state: D,
+}
+
+impl<D: fmt::Debug + 'static> fmt::Debug for TestCompositor<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TestCompositor")
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<D: 'static> TestCompositor<D> {
+    /// Create a new compositor, with the state returned by `init`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the display or the event loop cannot be created.
+    pub fn new<F>(init: F) -> TestCompositor<D>
+    where
+        F: FnOnce(&DisplayHandle, &LoopHandle<'static, D>) -> D,
+    {
+        let display = Display::new().expect("Failed to create the display");
+        let event_loop = EventLoop::try_new().expect("Failed to create the event loop");
+        let state = init(&display.handle(), &event_loop.handle());
+        TestCompositor {
+            display,
+            event_loop,
+            state,
+        }
+    }
+
+    /// Handle of the display
+    pub fn handle(&self) -> DisplayHandle {
+        self.display.handle()
+    }
+
+    /// Connect a new client
+    pub fn connect(&mut self) -> TestClient {
+        let (server, client) = UnixStream::pair().expect("Failed to create a socket pair");
+        let server_client = self
+            .display
+            .handle()
+            .insert_client(server, Arc::new(TestClientData))
+            .expect("Failed to insert the client");
+        TestClient::new(client, server_client)
+    }
+
+    /// Process one batch of events
+    ///
+    /// Dispatches pending event sources of the event loop, all pending client requests and
+    /// flushes the resulting events to the clients, without blocking.
+    ///
+    /// # Panics
+    ///
+    /// Panics if dispatching fails.
+    pub fn advance(&mut self) {
+        self.event_loop
+            .dispatch(Some(Duration::ZERO), &mut self.state)
+            .expect("Failed to dispatch the event loop");
+        self.display
+            .dispatch_clients(&mut self.state)
+            .expect("Failed to dispatch clients");
+        // clients may already have disconnected, which is not an error for tests
+        let _ = self.display.flush_clients();
+    }
+}
+
+/// Create an output for tests
+///
+/// The output has a single mode of the given size at 60Hz, which is both its current and
+/// preferred mode. No global is created for it.
+pub fn test_output(name: &str, size: impl Into<Size<i32, Physical>>) -> Output {
+    let output = Output::new(
+        name.into(),
+        PhysicalProperties {
+            size: (0, 0).into(),
+            subpixel: Subpixel::Unknown,
+            make: "Smithay".into(),
+            model: "Test".into(),
+        },
+    );
+    let mode = Mode {
+        size: size.into(),
+        refresh: 60_000,
+    };
+    output.set_preferred(mode);
+    output.change_current_state(Some(mode), None, None, None);
+    output
+}
+
+/// Compositor state for tests of clients attaching shm buffers to surfaces
+///
+/// Advertises the `wl_compositor`, `wl_subcompositor` and `wl_shm` globals. Committed buffers
+/// are handled using [`on_commit_buffer_handler`].
+#[derive(Debug)]
+pub struct ShmTestState {
+    /// State of the compositor globals
+    pub compositor_state: CompositorState,
+    /// State of the shm global
+    pub shm_state: ShmState,
+}
+
+impl ShmTestState {
+    /// Create the globals on the given display
+    pub fn new(dh: &DisplayHandle) -> ShmTestState {
+        ShmTestState {
+            compositor_state: CompositorState::new::<ShmTestState>(dh),
+            shm_state: ShmState::new::<ShmTestState>(dh, vec![]),
+        }
+    }
+}
+
+impl CompositorHandler for ShmTestState {
+    fn compositor_state(&mut self) -> &mut CompositorState {
+        &mut self.compositor_state
+    }
+
+    fn commit(&mut self, surface: &WlSurface) {
+        on_commit_buffer_handler(surface);
+    }
+}
+
+impl BufferHandler for ShmTestState {
+    fn buffer_destroyed(&mut self, _buffer: &WlBuffer) {}
+}
+
+impl ShmHandler for ShmTestState {
+    fn shm_state(&self) -> &ShmState {
+        &self.shm_state
+    }
+}
+
+crate::delegate_compositor!(ShmTestState);
+crate::delegate_shm!(ShmTestState);
+
+#[derive(Debug)]
+struct TestClientData;
+
+impl ClientData for TestClientData {
+    fn initialized(&self, _client_id: ClientId) {}
+    fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+}
+
+/// User data of client objects whose events are recorded by a [`TestClient`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Recorded;
+
+#[derive(Debug, Clone, Copy)]
+struct SyncData;
+
+/// Object data of objects created by events, whose events are not recorded
+#[derive(Debug)]
+struct Unrecorded;
+
+impl ObjectData for Unrecorded {
+    fn event(
+        self: Arc<Self>,
+        _backend: &Backend,
+        _msg: Message<ObjectId, OwnedFd>,
+    ) -> Option<Arc<dyn ObjectData>> {
+        None
+    }
+
+    fn destroyed(&self, _object_id: ObjectId) {}
+}
+
+#[derive(Debug, Clone)]
+struct Global {
+    name: u32,
+    interface: String,
+    version: u32,
+}
+
+/// State of the client side event queue
+#[derive(Debug, Default)]
+pub struct TestClientState {
+    globals: Vec<Global>,
+    events: VecDeque<Box<dyn Any>>,
+    synced: bool,
+}
+
+impl Dispatch<wl_registry::WlRegistry, ()> for TestClientState {
+    fn event(
+        state: &mut Self,
+        _registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } => state.globals.push(Global {
+                name,
+                interface,
+                version,
+            }),
+            wl_registry::Event::GlobalRemove { name } => state.globals.retain(|global| global.name != name),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<wl_callback::WlCallback, SyncData> for TestClientState {
+    fn event(
+        state: &mut Self,
+        _callback: &wl_callback::WlCallback,
+        _event: wl_callback::Event,
+        _data: &SyncData,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        state.synced = true;
+    }
+}
+
+impl<I> Dispatch<I, Recorded> for TestClientState
+where
+    I: Proxy + 'static,
+    I::Event: 'static,
+{
+    fn event(
+        state: &mut Self,
+        _proxy: &I,
+        event: I::Event,
+        _data: &Recorded,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        state.events.push_back(Box::new(event));
+    }
+
+    fn event_created_child(_opcode: u16, _qhandle: &QueueHandle<Self>) -> Arc<dyn ObjectData> {
+        Arc::new(Unrecorded)
+    }
+}
+
+/// In-process client connected to a [`TestCompositor`]
+///
+/// See the [module documentation](self) for details.
+pub struct TestClient {
+    /// Connection of the client
+    pub connection: Connection,
+    queue: EventQueue<TestClientState>,
+    state: TestClientState,
+    registry: wl_registry::WlRegistry,
+    client: Client,
+}
+
+impl fmt::Debug for TestClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TestClient")
+            .field("state", &self.state)
+            .field("client", &self.client)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TestClient {
+    fn new(stream: UnixStream, client: Client) -> TestClient {
+        let connection = Connection::from_socket(stream).expect("Failed to connect the client");
+        let queue = connection.new_event_queue();
+        let registry = connection.display().get_registry(&queue.handle(), ());
+        TestClient {
+            connection,
+            queue,
+            state: TestClientState::default(),
+            registry,
+            client,
+        }
+    }
+
+    /// Server side handle of this client
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Handle to create new objects on the event queue of this client
+    ///
+    /// Objects created with [`Recorded`] as their user data get their events recorded.
+    pub fn queue_handle(&self) -> QueueHandle<TestClientState> {
+        self.queue.handle()
+    }
+
+    /// Flush pending requests, let the compositor process them and dispatch its answers
+    ///
+    /// # Panics
+    ///
+    /// Panics if the connection failed, e.g. because of a protocol error.
+    pub fn dispatch<D: 'static>(&mut self, compositor: &mut TestCompositor<D>) {
+        self.queue.flush().expect("Failed to flush the client");
+        compositor.advance();
+        if let Some(guard) = self.queue.prepare_read() {
+            match guard.read() {
+                Ok(_) => {}
+                Err(WaylandError::Io(err)) if err.kind() == ErrorKind::WouldBlock => {}
+                Err(err) => panic!("Failed to read events: {}", err),
+            }
+        }
+        self.queue
+            .dispatch_pending(&mut self.state)
+            .expect("Failed to dispatch client events");
+    }
+
+    /// Dispatch until the compositor processed all requests sent so far
+    ///
+    /// # Panics
+    ///
+    /// Panics if the compositor does not answer.
+    pub fn roundtrip<D: 'static>(&mut self, compositor: &mut TestCompositor<D>) {
+        self.state.synced = false;
+        self.connection.display().sync(&self.queue.handle(), SyncData);
+        for _ in 0..MAX_ROUNDTRIP_ITERATIONS {
+            self.dispatch(compositor);
+            if self.state.synced {
+                return;
+            }
+        }
+        panic!("Compositor did not answer the roundtrip");
+    }
+
+    /// Whether the compositor currently advertises a global of the given interface
+    ///
+    /// Only reflects the events received during the last dispatch of this client.
+    pub fn has_global(&self, interface: &str) -> bool {
+        self.state
+            .globals
+            .iter()
+            .any(|global| global.interface == interface)
+    }
+
+    /// Bind the global of the given interface
+    ///
+    /// The global is bound with the highest version supported by both sides.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the compositor does not advertise a global of this interface.
+    pub fn bind<I, D>(&mut self, compositor: &mut TestCompositor<D>) -> I
+    where
+        I: Proxy + 'static,
+        TestClientState: Dispatch<I, Recorded>,
+        D: 'static,
+    {
+        self.roundtrip(compositor);
+        let interface = I::interface();
+        let global = self
+            .state
+            .globals
+            .iter()
+            .find(|global| global.interface == interface.name)
+            .unwrap_or_else(|| panic!("No global for {}", interface.name));
+        let version = global.version.min(interface.version);
+        self.registry
+            .bind::<I, _, _>(global.name, version, &self.queue.handle(), Recorded)
+    }
+
+    /// Create a shm pool of `size` bytes, backed by a memfd
+    ///
+    /// The returned file is the memory of the pool, it can be used to write the buffer contents.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memfd cannot be created.
+    pub fn create_shm_pool(&self, shm: &WlShm, size: i32) -> (WlShmPool, File) {
+        let name = CString::new("smithay-test-pool").unwrap();
+        let fd = memfd_create(&name, MemFdCreateFlag::MFD_CLOEXEC).expect("Failed to create a memfd");
+        // SAFETY: the fd was just created and is not owned by anything else
+        let file = unsafe { File::from_raw_fd(fd) };
+        file.set_len(size as u64).expect("Failed to resize the memfd");
+        let pool = shm.create_pool(file.as_raw_fd(), size, &self.queue.handle(), Recorded);
+        (pool, file)
+    }
+
+    /// Wait for the compositor to post a protocol error to this client
+    ///
+    /// Returns the error, or `None` if the connection is still alive after `timeout` passed.
+    pub fn wait_for_protocol_error<D: 'static>(
+        &mut self,
+        compositor: &mut TestCompositor<D>,
+        timeout: Duration,
+    ) -> Option<ProtocolError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            // the connection fails once the error arrived, so failures are expected here
+            let _ = self.queue.flush();
+            compositor.advance();
+            if let Some(guard) = self.queue.prepare_read() {
+                let _ = guard.read();
+            }
+            let _ = self.queue.dispatch_pending(&mut self.state);
+            if let Some(err) = self.connection.protocol_error() {
+                return Some(err);
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+        }
+    }
+
+    /// Wait for an event of the type `E` to arrive
+    ///
+    /// Returns the oldest recorded event of this type, skipping events of other types, or `None`
+    /// if no such event arrived until `timeout` passed. Events of different objects with the same
+    /// interface are not distinguished.
+    pub fn wait_for_event<E, D>(&mut self, compositor: &mut TestCompositor<D>, timeout: Duration) -> Option<E>
+    where
+        E: 'static,
+        D: 'static,
+    {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(idx) = self.state.events.iter().position(|event| event.is::<E>()) {
+                let event = self.state.events.remove(idx).unwrap();
+                return event.downcast().ok().map(|event| *event);
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            self.dispatch(compositor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use wayland_client::{
+        protocol::{
+            wl_buffer,
+            wl_compositor::WlCompositor,
+            wl_shm::{self, WlShm},
+        },
+        Proxy,
+    };
+    use wayland_server::{protocol::wl_surface::WlSurface, Resource};
+
+    use super::{Recorded, ShmTestState, TestCompositor};
+    use crate::{backend::renderer::utils::RendererSurfaceStateUserData, wayland::compositor::with_states};
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn globals_are_bound_with_common_version() {
+        let mut compositor = TestCompositor::new(|dh, _| ShmTestState::new(dh));
+        let mut client = compositor.connect();
+        let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+        assert!(client.has_global("wl_shm"));
+        assert!(!client.has_global("wl_seat"));
+        assert_eq!(wl_compositor.version(), WlCompositor::interface().version.min(5));
+    }
+
+    #[test]
+    fn roundtrip_creates_server_objects() {
+        let mut compositor = TestCompositor::new(|dh, _| ShmTestState::new(dh));
+        let mut client = compositor.connect();
+        let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+        let surface = wl_compositor.create_surface(&client.queue_handle(), Recorded);
+        client.roundtrip(&mut compositor);
+
+        let server_surface = client
+            .client()
+            .object_from_protocol_id::<WlSurface>(&compositor.handle(), surface.id().protocol_id())
+            .unwrap();
+        assert!(server_surface.is_alive());
+
+        // dropping the client disconnects it
+        drop(client);
+        compositor.advance();
+        assert!(!server_surface.is_alive());
+    }
+
+    #[test]
+    fn events_are_recorded_by_type() {
+        let mut compositor = TestCompositor::new(|dh, _| ShmTestState::new(dh));
+        let mut client = compositor.connect();
+        let _shm = client.bind::<WlShm, _>(&mut compositor);
+
+        // every format event is returned once, while no buffer was ever released
+        assert!(matches!(
+            client.wait_for_event::<wl_shm::Event, _>(&mut compositor, TIMEOUT),
+            Some(wl_shm::Event::Format { .. })
+        ));
+        assert!(matches!(
+            client.wait_for_event::<wl_shm::Event, _>(&mut compositor, TIMEOUT),
+            Some(wl_shm::Event::Format { .. })
+        ));
+        assert!(client
+            .wait_for_event::<wl_shm::Event, _>(&mut compositor, Duration::ZERO)
+            .is_none());
+        assert!(client
+            .wait_for_event::<wl_buffer::Event, _>(&mut compositor, Duration::from_millis(10))
+            .is_none());
+    }
+
+    #[test]
+    fn protocol_errors_are_reported() {
+        let mut compositor = TestCompositor::new(|dh, _| ShmTestState::new(dh));
+        let mut client = compositor.connect();
+        let shm = client.bind::<WlShm, _>(&mut compositor);
+        assert!(client
+            .wait_for_protocol_error(&mut compositor, Duration::ZERO)
+            .is_none());
+
+        let (_pool, _file) = client.create_shm_pool(&shm, 0);
+        let err = client
+            .wait_for_protocol_error(&mut compositor, TIMEOUT)
+            .expect("no protocol error");
+        assert_eq!(err.code, wl_shm::Error::InvalidStride as u32);
+    }
+
+    #[test]
+    fn shm_buffers_are_attached() {
+        let mut compositor = TestCompositor::new(|dh, _| ShmTestState::new(dh));
+        let mut client = compositor.connect();
+        let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+        let shm = client.bind::<WlShm, _>(&mut compositor);
+        let qh = client.queue_handle();
+
+        let (pool, _file) = client.create_shm_pool(&shm, 8 * 4 * 4);
+        let buffer = pool.create_buffer(0, 8, 4, 8 * 4, wl_shm::Format::Argb8888, &qh, Recorded);
+        let surface = wl_compositor.create_surface(&qh, Recorded);
+        surface.attach(Some(&buffer), 0, 0);
+        surface.commit();
+        client.roundtrip(&mut compositor);
+
+        let server_surface = client
+            .client()
+            .object_from_protocol_id::<WlSurface>(&compositor.handle(), surface.id().protocol_id())
+            .unwrap();
+        let size = with_states(&server_surface, |states| {
+            states
+                .data_map
+                .get::<RendererSurfaceStateUserData>()
+                .and_then(|data| data.borrow().surface_size())
+        });
+        assert_eq!(size, Some((8, 4).into()));
+    }
+}
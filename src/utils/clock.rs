@@ -1,5 +1,33 @@
 use std::{cmp::Ordering, marker::PhantomData, mem::MaybeUninit, time::Duration};
 
+/// Compares two millisecond timestamps taking wrapping into account
+///
+/// Protocol timestamps are 32-bit millisecond values, which wrap around after roughly 49.7 days.
+/// Two timestamps are compared by their distance, a timestamp less than half the range ahead of
+/// another one is considered later, even if its numerical value is smaller.
+pub fn cmp_time_msec(time: u32, other: u32) -> Ordering {
+    match time.wrapping_sub(other) {
+        0 => Ordering::Equal,
+        distance if distance < u32::MAX / 2 => Ordering::Greater,
+        _ => Ordering::Less,
+    }
+}
+
+/// Millisecond representation of a duration as used by the wayland protocol, wrapping on overflow
+pub(crate) fn duration_as_msec(duration: Duration) -> u32 {
+    duration.as_millis() as u32
+}
+
+/// Splits a duration into the `tv_sec_hi`, `tv_sec_lo` and `tv_nsec` triple used by the wayland protocol
+pub(crate) fn duration_as_timespec_triple(duration: Duration) -> (u32, u32, u32) {
+    let secs = duration.as_secs();
+    (
+        (secs >> 32) as u32,
+        (secs & 0xFFFFFFFF) as u32,
+        duration.subsec_nanos(),
+    )
+}
+
 /// Marker for clock source that never returns a negative [`Time`]
 pub trait NonNegativeClockSource: ClockSource {}
 
@@ -95,6 +123,26 @@ impl<Kind> Time<Kind> {
     }
 }
 
+impl<Kind: NonNegativeClockSource> Time<Kind> {
+    /// Timestamp in milliseconds, as used by input events and frame callbacks
+    ///
+    /// The value wraps around after roughly 49.7 days, use [`cmp_time_msec`] to compare
+    /// two of these timestamps.
+    pub fn as_millis(&self) -> u32 {
+        duration_as_msec(Duration::from(*self))
+    }
+
+    /// Timestamp in microseconds, as used by [`Event::time_usec`](crate::backend::input::Event::time_usec)
+    pub fn as_micros(&self) -> u64 {
+        Duration::from(*self).as_micros() as u64
+    }
+
+    /// Timestamp split into the `tv_sec_hi`, `tv_sec_lo` and `tv_nsec` triple used by presentation feedback
+    pub fn as_timespec_triple(&self) -> (u32, u32, u32) {
+        duration_as_timespec_triple(Duration::from(*self))
+    }
+}
+
 impl<Kind> Clone for Time<Kind> {
     fn clone(&self) -> Self {
         Self {
@@ -108,8 +156,8 @@ impl<Kind> Copy for Time<Kind> {}
 
 impl<Kind: NonNegativeClockSource> From<Time<Kind>> for Duration {
     fn from(time: Time<Kind>) -> Self {
-        debug_assert!(time.tp.tv_sec > 0);
-        debug_assert!(time.tp.tv_nsec > 0);
+        debug_assert!(time.tp.tv_sec >= 0);
+        debug_assert!(time.tp.tv_nsec >= 0);
         Duration::new(time.tp.tv_sec as u64, time.tp.tv_nsec as u32)
     }
 }
@@ -214,7 +262,9 @@ fn clock_get_time(clk_id: libc::clockid_t) -> Result<libc::timespec, std::io::Er
 mod test {
     use std::time::Duration;
 
-    use crate::utils::{Boottime, Clock, Monotonic, Time};
+    use std::cmp::Ordering;
+
+    use crate::utils::{cmp_time_msec, Boottime, Clock, Monotonic, Time};
 
     #[test]
     fn monotonic() {
@@ -231,4 +281,22 @@ mod test {
         let zero = Time::<Boottime>::from(Duration::ZERO);
         assert_eq!(zero.duration_since(now), now.into());
     }
+
+    #[test]
+    fn protocol_representations() {
+        let time = Time::<Monotonic>::from(Duration::new((1 << 32) + 5, 42_000_000));
+        assert_eq!(time.as_timespec_triple(), (1, 5, 42_000_000));
+        assert_eq!(time.as_micros(), ((1u64 << 32) + 5) * 1_000_000 + 42_000);
+        // wraps around
+        assert_eq!(time.as_millis(), (((1u64 << 32) + 5) * 1000 + 42) as u32);
+    }
+
+    #[test]
+    fn msec_comparison_wraps() {
+        assert_eq!(cmp_time_msec(5, 5), Ordering::Equal);
+        assert_eq!(cmp_time_msec(6, 5), Ordering::Greater);
+        assert_eq!(cmp_time_msec(5, 6), Ordering::Less);
+        assert_eq!(cmp_time_msec(2, u32::MAX - 2), Ordering::Greater);
+        assert_eq!(cmp_time_msec(u32::MAX - 2, 2), Ordering::Less);
+    }
 }
@@ -1459,6 +1459,7 @@ impl<N: Default, Kind> Default for Rectangle<N, Kind> {
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Possible transformations to two-dimensional planes
 pub enum Transform {
     /// Identity transformation (plane is unaltered when applied)
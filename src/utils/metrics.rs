@@ -0,0 +1,339 @@
+//! Runtime metrics of a compositor
+//!
+//! [`CompositorMetrics`] collects frame timings per output, buffer submissions per client,
+//! buffer import latency and input-to-display latency. Recording a value only touches atomic
+//! counters, so the handles can be used in the hot path. Locks are only taken when registering
+//! outputs or clients and when taking a [`MetricsSnapshot`].
+//!
+//! ```
+//! use std::time::Duration;
+//! use smithay::utils::metrics::CompositorMetrics;
+//!
+//! let metrics = CompositorMetrics::new();
+//!
+//! // keep the handles around, e.g. in the output and client user data
+//! let output = metrics.register_output("DP-1");
+//! let client = metrics.register_client("weston-terminal");
+//!
+//! client.record_buffer_submission();
+//! output.record_frame(Duration::from_millis(4));
+//! output.record_missed_frame();
+//! metrics.record_input_latency(Duration::from_millis(12));
+//!
+//! let snapshot = metrics.snapshot();
+//! assert_eq!(snapshot.outputs[0].missed_frames, 1);
+//! assert_eq!(snapshot.clients[0].buffer_submissions, 1);
+//! ```
+//!
+//! Exposing the snapshots, e.g. through an HTTP endpoint or a unix socket, is left to the compositor.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, Weak,
+    },
+    time::Duration,
+};
+
+/// Upper bounds of the histogram buckets, the last bucket collects all larger values
+pub const HISTOGRAM_BUCKETS: [Duration; 9] = [
+    Duration::from_millis(1),
+    Duration::from_millis(2),
+    Duration::from_millis(4),
+    Duration::from_millis(8),
+    Duration::from_millis(16),
+    Duration::from_millis(33),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+];
+
+/// Lock-free histogram of durations
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [AtomicU64; HISTOGRAM_BUCKETS.len() + 1],
+    count: AtomicU64,
+    sum_ns: AtomicU64,
+    max_ns: AtomicU64,
+}
+
+impl Histogram {
+    fn record(&self, value: Duration) {
+        let idx = HISTOGRAM_BUCKETS
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(HISTOGRAM_BUCKETS.len());
+        let ns = value.as_nanos().min(u64::MAX as u128) as u64;
+        self.buckets[idx].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_ns.fetch_add(ns, Ordering::Relaxed);
+        self.max_ns.fetch_max(ns, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> HistogramSnapshot {
+        HistogramSnapshot {
+            buckets: self
+                .buckets
+                .iter()
+                .map(|bucket| bucket.load(Ordering::Relaxed))
+                .collect(),
+            count: self.count.load(Ordering::Relaxed),
+            sum: Duration::from_nanos(self.sum_ns.load(Ordering::Relaxed)),
+            max: Duration::from_nanos(self.max_ns.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Point-in-time copy of a histogram
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistogramSnapshot {
+    /// Number of values per bucket
+    ///
+    /// The bucket at index `i` counts the values less or equal than [`HISTOGRAM_BUCKETS`]`[i]`
+    /// and greater than the previous bound. The last bucket counts all values greater than the
+    /// last bound.
+    pub buckets: Vec<u64>,
+    /// Number of recorded values
+    pub count: u64,
+    /// Sum of all recorded values
+    pub sum: Duration,
+    /// Largest recorded value
+    pub max: Duration,
+}
+
+impl HistogramSnapshot {
+    /// Average of the recorded values, if any value was recorded
+    pub fn average(&self) -> Option<Duration> {
+        (self.count > 0).then(|| Duration::from_nanos((self.sum.as_nanos() / self.count as u128) as u64))
+    }
+}
+
+#[derive(Debug)]
+struct OutputMetricsInner {
+    name: String,
+    frame_times: Histogram,
+    missed_frames: AtomicU64,
+}
+
+/// Handle to record metrics of an output
+///
+/// Created by [`CompositorMetrics::register_output`]. The output is removed from
+/// the metrics once all handles are dropped.
+#[derive(Debug, Clone)]
+pub struct OutputMetrics(Arc<OutputMetricsInner>);
+
+impl OutputMetrics {
+    /// Record the time it took to produce a frame
+    pub fn record_frame(&self, frame_time: Duration) {
+        self.0.frame_times.record(frame_time);
+    }
+
+    /// Record a frame that missed its presentation deadline
+    pub fn record_missed_frame(&self) {
+        self.0.missed_frames.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug)]
+struct ClientMetricsInner {
+    name: String,
+    buffer_submissions: AtomicU64,
+    frame_callbacks: AtomicU64,
+}
+
+/// Handle to record metrics of a client
+///
+/// Created by [`CompositorMetrics::register_client`]. The client is removed from
+/// the metrics once all handles are dropped.
+#[derive(Debug, Clone)]
+pub struct ClientMetrics(Arc<ClientMetricsInner>);
+
+impl ClientMetrics {
+    /// Record a buffer submitted by the client
+    pub fn record_buffer_submission(&self) {
+        self.0.buffer_submissions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a frame callback sent to the client
+    pub fn record_frame_callback(&self) {
+        self.0.frame_callbacks.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Default)]
+struct CompositorMetricsInner {
+    outputs: Mutex<Vec<Weak<OutputMetricsInner>>>,
+    clients: Mutex<Vec<Weak<ClientMetricsInner>>>,
+    buffer_import: Histogram,
+    input_latency: Histogram,
+}
+
+/// Collection of runtime metrics of a compositor
+///
+/// It can be cloned and all clones record into the same metrics.
+#[derive(Debug, Clone, Default)]
+pub struct CompositorMetrics(Arc<CompositorMetricsInner>);
+
+impl CompositorMetrics {
+    /// Create a new empty collection of metrics
+    pub fn new() -> CompositorMetrics {
+        CompositorMetrics::default()
+    }
+
+    /// Register an output with the given name
+    pub fn register_output(&self, name: impl Into<String>) -> OutputMetrics {
+        let inner = Arc::new(OutputMetricsInner {
+            name: name.into(),
+            frame_times: Histogram::default(),
+            missed_frames: AtomicU64::new(0),
+        });
+        let mut outputs = self.0.outputs.lock().unwrap();
+        outputs.retain(|output| output.strong_count() > 0);
+        outputs.push(Arc::downgrade(&inner));
+        OutputMetrics(inner)
+    }
+
+    /// Register a client with the given name
+    pub fn register_client(&self, name: impl Into<String>) -> ClientMetrics {
+        let inner = Arc::new(ClientMetricsInner {
+            name: name.into(),
+            buffer_submissions: AtomicU64::new(0),
+            frame_callbacks: AtomicU64::new(0),
+        });
+        let mut clients = self.0.clients.lock().unwrap();
+        clients.retain(|client| client.strong_count() > 0);
+        clients.push(Arc::downgrade(&inner));
+        ClientMetrics(inner)
+    }
+
+    /// Record the time it took to import a buffer into the renderer
+    pub fn record_buffer_import(&self, latency: Duration) {
+        self.0.buffer_import.record(latency);
+    }
+
+    /// Record the time between an input event and the presentation of the frame reflecting it
+    pub fn record_input_latency(&self, latency: Duration) {
+        self.0.input_latency.record(latency);
+    }
+
+    /// Take a point-in-time snapshot of all metrics
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let outputs = self
+            .0
+            .outputs
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(|output| OutputSnapshot {
+                name: output.name.clone(),
+                frame_times: output.frame_times.snapshot(),
+                missed_frames: output.missed_frames.load(Ordering::Relaxed),
+            })
+            .collect();
+        let clients = self
+            .0
+            .clients
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(|client| ClientSnapshot {
+                name: client.name.clone(),
+                buffer_submissions: client.buffer_submissions.load(Ordering::Relaxed),
+                frame_callbacks: client.frame_callbacks.load(Ordering::Relaxed),
+            })
+            .collect();
+
+        MetricsSnapshot {
+            outputs,
+            clients,
+            buffer_import: self.0.buffer_import.snapshot(),
+            input_latency: self.0.input_latency.snapshot(),
+        }
+    }
+}
+
+/// Point-in-time copy of the metrics of an output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputSnapshot {
+    /// Name the output was registered with
+    pub name: String,
+    /// Histogram of the frame times
+    pub frame_times: HistogramSnapshot,
+    /// Number of frames that missed their presentation deadline
+    pub missed_frames: u64,
+}
+
+/// Point-in-time copy of the metrics of a client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientSnapshot {
+    /// Name the client was registered with
+    pub name: String,
+    /// Number of submitted buffers
+    pub buffer_submissions: u64,
+    /// Number of sent frame callbacks
+    pub frame_callbacks: u64,
+}
+
+/// Point-in-time copy of [`CompositorMetrics`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// Metrics of all registered outputs
+    pub outputs: Vec<OutputSnapshot>,
+    /// Metrics of all registered clients
+    pub clients: Vec<ClientSnapshot>,
+    /// Histogram of the buffer import latency
+    pub buffer_import: HistogramSnapshot,
+    /// Histogram of the input-to-display latency
+    pub input_latency: HistogramSnapshot,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{CompositorMetrics, HISTOGRAM_BUCKETS};
+
+    #[test]
+    fn frame_times_are_bucketed() {
+        let metrics = CompositorMetrics::new();
+        let output = metrics.register_output("test");
+        output.record_frame(Duration::from_micros(500));
+        output.record_frame(Duration::from_millis(16));
+        output.record_frame(Duration::from_secs(1));
+
+        let frame_times = &metrics.snapshot().outputs[0].frame_times;
+        assert_eq!(frame_times.buckets.len(), HISTOGRAM_BUCKETS.len() + 1);
+        assert_eq!(frame_times.buckets[0], 1);
+        assert_eq!(frame_times.buckets[4], 1);
+        assert_eq!(frame_times.buckets[HISTOGRAM_BUCKETS.len()], 1);
+        assert_eq!(frame_times.count, 3);
+        assert_eq!(frame_times.max, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn average_input_latency() {
+        let metrics = CompositorMetrics::new();
+        assert_eq!(metrics.snapshot().input_latency.average(), None);
+
+        metrics.record_input_latency(Duration::from_millis(10));
+        metrics.record_input_latency(Duration::from_millis(20));
+        assert_eq!(
+            metrics.snapshot().input_latency.average(),
+            Some(Duration::from_millis(15))
+        );
+    }
+
+    #[test]
+    fn dropped_handles_are_removed() {
+        let metrics = CompositorMetrics::new();
+        let client = metrics.register_client("client");
+        client.record_buffer_submission();
+        client.clone().record_buffer_submission();
+        assert_eq!(metrics.snapshot().clients[0].buffer_submissions, 2);
+
+        drop(client);
+        assert!(metrics.snapshot().clients.is_empty());
+    }
+}
@@ -28,6 +28,9 @@ pub use serial::*;
 mod clock;
 pub use clock::*;
 
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
 /// This resource is not managed by Smithay
 #[derive(Debug)]
 pub struct UnmanagedResource;
@@ -0,0 +1,282 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use super::protocol::server::wp_color_manager_v1::{Primaries, TransferFunction};
+
+static NEXT_IDENTITY: AtomicU32 = AtomicU32::new(1);
+
+fn next_identity() -> u32 {
+    loop {
+        let identity = NEXT_IDENTITY.fetch_add(1, Ordering::Relaxed);
+        // identities have to be non-zero
+        if identity != 0 {
+            return identity;
+        }
+    }
+}
+
+/// CIE 1931 xy chromaticity coordinates of the primaries and white point of a color volume
+///
+/// All coordinates are multiplied by 1 000 000, as in the protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chromaticities {
+    /// Red primary
+    pub red: (i32, i32),
+    /// Green primary
+    pub green: (i32, i32),
+    /// Blue primary
+    pub blue: (i32, i32),
+    /// White point
+    pub white: (i32, i32),
+}
+
+const D65: (i32, i32) = (312_700, 329_000);
+const ILLUMINANT_C: (i32, i32) = (310_000, 316_000);
+
+impl Chromaticities {
+    /// Chromaticities of a named set of primaries
+    pub fn from_named(primaries: Primaries) -> Chromaticities {
+        let (red, green, blue, white) = match primaries {
+            Primaries::Srgb => ((640_000, 330_000), (300_000, 600_000), (150_000, 60_000), D65),
+            Primaries::PalM => (
+                (670_000, 330_000),
+                (210_000, 710_000),
+                (140_000, 80_000),
+                ILLUMINANT_C,
+            ),
+            Primaries::Pal => ((640_000, 330_000), (290_000, 600_000), (150_000, 60_000), D65),
+            Primaries::Ntsc => ((630_000, 340_000), (310_000, 595_000), (155_000, 70_000), D65),
+            Primaries::GenericFilm => (
+                (681_000, 319_000),
+                (243_000, 692_000),
+                (145_000, 49_000),
+                ILLUMINANT_C,
+            ),
+            Primaries::Bt2020 => ((708_000, 292_000), (170_000, 797_000), (131_000, 46_000), D65),
+            Primaries::Cie1931Xyz => ((1_000_000, 0), (0, 1_000_000), (0, 0), (333_333, 333_333)),
+            Primaries::DciP3 => (
+                (680_000, 320_000),
+                (265_000, 690_000),
+                (150_000, 60_000),
+                (314_000, 351_000),
+            ),
+            Primaries::DisplayP3 => ((680_000, 320_000), (265_000, 690_000), (150_000, 60_000), D65),
+            Primaries::AdobeRgb => ((640_000, 330_000), (210_000, 710_000), (150_000, 60_000), D65),
+        };
+        Chromaticities {
+            red,
+            green,
+            blue,
+            white,
+        }
+    }
+}
+
+/// Primaries and white point of the primary color volume of an image description
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorPrimaries {
+    /// A named set of primaries
+    Named(Primaries),
+    /// Arbitrary chromaticities
+    Custom(Chromaticities),
+}
+
+impl ColorPrimaries {
+    /// Chromaticities of the primaries
+    pub fn chromaticities(&self) -> Chromaticities {
+        match self {
+            ColorPrimaries::Named(primaries) => Chromaticities::from_named(*primaries),
+            ColorPrimaries::Custom(chromaticities) => *chromaticities,
+        }
+    }
+}
+
+/// Transfer characteristic of an image description
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferCharacteristic {
+    /// A named transfer function
+    Named(TransferFunction),
+    /// A pure power curve, with the exponent multiplied by 10 000
+    Power(u32),
+}
+
+/// Luminance range and reference white of the primary color volume
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Luminances {
+    /// Minimum luminance in cd/m², multiplied by 10 000
+    pub min: u32,
+    /// Maximum luminance in cd/m²
+    pub max: u32,
+    /// Reference white luminance in cd/m²
+    pub reference: u32,
+}
+
+impl Luminances {
+    /// Luminances assumed by the protocol if a description does not set them
+    pub fn default_for(transfer_characteristic: TransferCharacteristic) -> Luminances {
+        match transfer_characteristic {
+            TransferCharacteristic::Named(TransferFunction::St2084Pq) => Luminances {
+                min: 50,
+                max: 10_000,
+                reference: 203,
+            },
+            TransferCharacteristic::Named(TransferFunction::Hlg) => Luminances {
+                min: 50,
+                max: 1_000,
+                reference: 203,
+            },
+            _ => Luminances {
+                min: 2_000,
+                max: 80,
+                reference: 80,
+            },
+        }
+    }
+}
+
+/// Luminance range of the display the content was mastered on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MasteringLuminance {
+    /// Minimum luminance in cd/m², multiplied by 10 000
+    pub min: u32,
+    /// Maximum luminance in cd/m²
+    pub max: u32,
+}
+
+/// Image description made up of parameters
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ParametricImageDescription {
+    /// Transfer characteristic
+    pub transfer_characteristic: TransferCharacteristic,
+    /// Primaries of the primary color volume
+    pub primaries: ColorPrimaries,
+    /// Luminances of the primary color volume, the protocol defaults apply if `None`
+    pub luminances: Option<Luminances>,
+    /// Primaries of the mastering display
+    pub mastering_primaries: Option<Chromaticities>,
+    /// Luminance range of the mastering display
+    pub mastering_luminance: Option<MasteringLuminance>,
+    /// Maximum content light level in cd/m²
+    pub max_cll: Option<u32>,
+    /// Maximum frame-average light level in cd/m²
+    pub max_fall: Option<u32>,
+}
+
+impl ParametricImageDescription {
+    /// Create a description with the given transfer characteristic and primaries
+    pub fn new(
+        transfer_characteristic: TransferCharacteristic,
+        primaries: ColorPrimaries,
+    ) -> ParametricImageDescription {
+        ParametricImageDescription {
+            transfer_characteristic,
+            primaries,
+            luminances: None,
+            mastering_primaries: None,
+            mastering_luminance: None,
+            max_cll: None,
+            max_fall: None,
+        }
+    }
+
+    /// The sRGB color space
+    pub fn srgb() -> ParametricImageDescription {
+        ParametricImageDescription::new(
+            TransferCharacteristic::Named(TransferFunction::Srgb),
+            ColorPrimaries::Named(Primaries::Srgb),
+        )
+    }
+
+    /// Luminances of the primary color volume, with the protocol defaults applied
+    pub fn effective_luminances(&self) -> Luminances {
+        self.luminances
+            .unwrap_or_else(|| Luminances::default_for(self.transfer_characteristic))
+    }
+}
+
+/// Contents of an [`ImageDescription`]
+///
+/// More kinds of descriptions, like ICC profiles, may be added in the future.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ImageDescriptionContents {
+    /// Description made up of parameters
+    Parametric(ParametricImageDescription),
+}
+
+#[derive(Debug)]
+struct ImageDescriptionInner {
+    identity: u32,
+    contents: ImageDescriptionContents,
+}
+
+/// Immutable description of the color encoding of content
+///
+/// Descriptions are cheap to clone and compare equal if they share the same identity.
+#[derive(Debug, Clone)]
+pub struct ImageDescription(Arc<ImageDescriptionInner>);
+
+impl ImageDescription {
+    /// Create a new description with a new identity
+    pub fn new(contents: ImageDescriptionContents) -> ImageDescription {
+        ImageDescription(Arc::new(ImageDescriptionInner {
+            identity: next_identity(),
+            contents,
+        }))
+    }
+
+    /// Create a new parametric description
+    pub fn parametric(description: ParametricImageDescription) -> ImageDescription {
+        ImageDescription::new(ImageDescriptionContents::Parametric(description))
+    }
+
+    /// Identity of the description, as sent to clients
+    pub fn identity(&self) -> u32 {
+        self.0.identity
+    }
+
+    /// Contents of the description
+    pub fn contents(&self) -> &ImageDescriptionContents {
+        &self.0.contents
+    }
+}
+
+impl PartialEq for ImageDescription {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.identity == other.0.identity
+    }
+}
+
+impl Eq for ImageDescription {}
+
+#[cfg(test)]
+mod tests {
+    use super::{ImageDescription, Luminances, ParametricImageDescription, TransferCharacteristic};
+    use crate::wayland::color_management::protocol::server::wp_color_manager_v1::TransferFunction;
+
+    #[test]
+    fn descriptions_compare_by_identity() {
+        let first = ImageDescription::parametric(ParametricImageDescription::srgb());
+        let second = ImageDescription::parametric(ParametricImageDescription::srgb());
+        assert_ne!(first.identity(), 0);
+        assert_ne!(first, second);
+        assert_eq!(first, first.clone());
+    }
+
+    #[test]
+    fn default_luminances_follow_transfer_function() {
+        let pq = Luminances::default_for(TransferCharacteristic::Named(TransferFunction::St2084Pq));
+        assert_eq!((pq.min, pq.max, pq.reference), (50, 10_000, 203));
+        assert_eq!(
+            ParametricImageDescription::srgb().effective_luminances(),
+            Luminances {
+                min: 2_000,
+                max: 80,
+                reference: 80
+            }
+        );
+    }
+}
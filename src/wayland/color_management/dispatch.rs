@@ -0,0 +1,792 @@
+use std::sync::Mutex;
+
+use wayland_server::{
+    backend::{ClientId, ObjectId},
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+
+use crate::{
+    output::{Output, WeakOutput},
+    utils::IsAlive,
+    wayland::compositor::with_states,
+};
+
+use super::{
+    output_image_description, preferred_image_description,
+    protocol::server::{
+        wp_color_management_output_v1::{self, WpColorManagementOutputV1},
+        wp_color_management_surface_feedback_v1::{self, WpColorManagementSurfaceFeedbackV1},
+        wp_color_management_surface_v1::{self, WpColorManagementSurfaceV1},
+        wp_color_manager_v1::{self, Feature, TransferFunction, WpColorManagerV1},
+        wp_image_description_creator_params_v1::{self, WpImageDescriptionCreatorParamsV1},
+        wp_image_description_info_v1::{self, WpImageDescriptionInfoV1},
+        wp_image_description_v1::{self, Cause, WpImageDescriptionV1},
+    },
+    Chromaticities, ColorManagementHandler, ColorManagementState, ColorManagementSurfaceCachedState,
+    ColorPrimaries, ImageDescription, ImageDescriptionContents, Luminances, MasteringLuminance,
+    OutputColorMarker, ParametricImageDescription, SurfaceColorMarker, TransferCharacteristic,
+};
+
+use wayland_server::protocol::wl_surface::WlSurface;
+
+/// Data associated with a [`WpColorManagementOutputV1`]
+#[derive(Debug)]
+pub struct ColorOutputData {
+    output: Option<WeakOutput>,
+}
+
+/// Data associated with a [`WpColorManagementSurfaceV1`]
+#[derive(Debug)]
+pub struct ColorSurfaceData {
+    surface: WlSurface,
+}
+
+/// Data associated with a [`WpColorManagementSurfaceFeedbackV1`]
+#[derive(Debug)]
+pub struct ColorFeedbackData {
+    surface: WlSurface,
+}
+
+/// Data associated with a [`WpImageDescriptionCreatorParamsV1`]
+#[derive(Debug, Default)]
+pub struct ParametricCreatorData {
+    params: Mutex<Params>,
+}
+
+#[derive(Debug, Default)]
+struct Params {
+    transfer_characteristic: Option<TransferCharacteristic>,
+    primaries: Option<ColorPrimaries>,
+    luminances: Option<Luminances>,
+    mastering_primaries: Option<Chromaticities>,
+    mastering_luminance: Option<MasteringLuminance>,
+    max_cll: Option<u32>,
+    max_fall: Option<u32>,
+}
+
+/// Data associated with a [`WpImageDescriptionV1`]
+#[derive(Debug)]
+pub struct ImageDescriptionData {
+    description: Option<ImageDescription>,
+    /// Whether the client may query the contents, only allowed for descriptions of the compositor
+    allow_information: bool,
+}
+
+impl ImageDescriptionData {
+    /// The description, `None` if creating it failed
+    pub fn description(&self) -> Option<&ImageDescription> {
+        self.description.as_ref()
+    }
+}
+
+fn init_description<D>(
+    data_init: &mut DataInit<'_, D>,
+    id: New<WpImageDescriptionV1>,
+    description: ImageDescription,
+    allow_information: bool,
+) where
+    D: Dispatch<WpImageDescriptionV1, ImageDescriptionData> + 'static,
+{
+    let identity = description.identity();
+    let resource = data_init.init(
+        id,
+        ImageDescriptionData {
+            description: Some(description),
+            allow_information,
+        },
+    );
+    resource.ready(identity);
+}
+
+fn init_failed_description<D>(
+    data_init: &mut DataInit<'_, D>,
+    id: New<WpImageDescriptionV1>,
+    cause: Cause,
+    msg: &str,
+) where
+    D: Dispatch<WpImageDescriptionV1, ImageDescriptionData> + 'static,
+{
+    let resource = data_init.init(
+        id,
+        ImageDescriptionData {
+            description: None,
+            allow_information: false,
+        },
+    );
+    resource.failed(cause, msg.to_string());
+}
+
+impl<D> GlobalDispatch<WpColorManagerV1, (), D> for ColorManagementState
+where
+    D: GlobalDispatch<WpColorManagerV1, ()>
+        + Dispatch<WpColorManagerV1, ()>
+        + Dispatch<WpColorManagementOutputV1, ColorOutputData>
+        + Dispatch<WpColorManagementSurfaceV1, ColorSurfaceData>
+        + Dispatch<WpColorManagementSurfaceFeedbackV1, ColorFeedbackData>
+        + Dispatch<WpImageDescriptionCreatorParamsV1, ParametricCreatorData>
+        + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + Dispatch<WpImageDescriptionInfoV1, ()>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn bind(
+        state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<WpColorManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let manager = data_init.init(resource, ());
+        let capabilities = state.color_management_state().capabilities();
+        for intent in &capabilities.render_intents {
+            manager.supported_intent(*intent);
+        }
+        for feature in &capabilities.features {
+            manager.supported_feature(*feature);
+        }
+        for tf in &capabilities.transfer_functions {
+            manager.supported_tf_named(*tf);
+        }
+        for primaries in &capabilities.primaries {
+            manager.supported_primaries_named(*primaries);
+        }
+        manager.done();
+    }
+}
+
+impl<D> Dispatch<WpColorManagerV1, (), D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagerV1, ()>
+        + Dispatch<WpColorManagementOutputV1, ColorOutputData>
+        + Dispatch<WpColorManagementSurfaceV1, ColorSurfaceData>
+        + Dispatch<WpColorManagementSurfaceFeedbackV1, ColorFeedbackData>
+        + Dispatch<WpImageDescriptionCreatorParamsV1, ParametricCreatorData>
+        + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        resource: &WpColorManagerV1,
+        request: wp_color_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_color_manager_v1::Request::GetOutput { id, output } => {
+                let output = Output::from_resource(&output);
+                let instance = data_init.init(
+                    id,
+                    ColorOutputData {
+                        output: output.as_ref().map(Output::downgrade),
+                    },
+                );
+                if let Some(output) = output {
+                    output
+                        .user_data()
+                        .insert_if_missing_threadsafe(OutputColorMarker::default);
+                    let color_state = output.user_data().get::<OutputColorMarker>().unwrap();
+                    color_state.lock().unwrap().instances.push(instance);
+                }
+            }
+            wp_color_manager_v1::Request::GetSurface { id, surface } => {
+                let already_exists = with_states(&surface, |states| {
+                    states
+                        .data_map
+                        .insert_if_missing_threadsafe(SurfaceColorMarker::default);
+                    states
+                        .data_map
+                        .get::<SurfaceColorMarker>()
+                        .unwrap()
+                        .lock()
+                        .unwrap()
+                        .color_surface
+                        .is_some()
+                });
+                if already_exists {
+                    resource.post_error(
+                        wp_color_manager_v1::Error::SurfaceExists as u32,
+                        "the surface already has a color management surface associated".to_string(),
+                    );
+                    return;
+                }
+
+                let color_surface = data_init.init(
+                    id,
+                    ColorSurfaceData {
+                        surface: surface.clone(),
+                    },
+                );
+                with_states(&surface, |states| {
+                    let color_state = states.data_map.get::<SurfaceColorMarker>().unwrap();
+                    color_state.lock().unwrap().color_surface = Some(color_surface);
+                });
+            }
+            wp_color_manager_v1::Request::GetSurfaceFeedback { id, surface } => {
+                let feedback = data_init.init(
+                    id,
+                    ColorFeedbackData {
+                        surface: surface.clone(),
+                    },
+                );
+                with_states(&surface, |states| {
+                    states
+                        .data_map
+                        .insert_if_missing_threadsafe(SurfaceColorMarker::default);
+                    let color_state = states.data_map.get::<SurfaceColorMarker>().unwrap();
+                    color_state.lock().unwrap().feedbacks.push(feedback);
+                });
+            }
+            wp_color_manager_v1::Request::CreateIccCreator { .. } => {
+                resource.post_error(
+                    wp_color_manager_v1::Error::UnsupportedFeature as u32,
+                    "ICC image descriptions are not supported".to_string(),
+                );
+            }
+            wp_color_manager_v1::Request::CreateParametricCreator { obj } => {
+                if !state
+                    .color_management_state()
+                    .capabilities()
+                    .supports(Feature::Parametric)
+                {
+                    resource.post_error(
+                        wp_color_manager_v1::Error::UnsupportedFeature as u32,
+                        "parametric image descriptions are not supported".to_string(),
+                    );
+                    return;
+                }
+                data_init.init(obj, ParametricCreatorData::default());
+            }
+            wp_color_manager_v1::Request::CreateWindowsScrgb { image_description } => {
+                if !state
+                    .color_management_state()
+                    .capabilities()
+                    .supports(Feature::WindowsScrgb)
+                {
+                    resource.post_error(
+                        wp_color_manager_v1::Error::UnsupportedFeature as u32,
+                        "windows scRGB image descriptions are not supported".to_string(),
+                    );
+                    return;
+                }
+                // scRGB maps 1.0 to 80 cd/m² and extends beyond the sRGB primaries
+                let mut description = ParametricImageDescription::new(
+                    TransferCharacteristic::Named(TransferFunction::ExtLinear),
+                    ColorPrimaries::Named(wp_color_manager_v1::Primaries::Srgb),
+                );
+                description.luminances = Some(Luminances {
+                    min: 0,
+                    max: 10_000,
+                    reference: 80,
+                });
+                init_description(
+                    data_init,
+                    image_description,
+                    ImageDescription::parametric(description),
+                    false,
+                );
+            }
+            wp_color_manager_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WpColorManagementOutputV1, ColorOutputData, D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagementOutputV1, ColorOutputData>
+        + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _resource: &WpColorManagementOutputV1,
+        request: wp_color_management_output_v1::Request,
+        data: &ColorOutputData,
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_color_management_output_v1::Request::GetImageDescription { image_description } => {
+                match data.output.as_ref().and_then(WeakOutput::upgrade) {
+                    Some(output) => {
+                        let description = output_image_description(&output).unwrap_or_else(|| {
+                            state.color_management_state().default_image_description().clone()
+                        });
+                        init_description(data_init, image_description, description, true);
+                    }
+                    None => init_failed_description(
+                        data_init,
+                        image_description,
+                        Cause::NoOutput,
+                        "the output no longer exists",
+                    ),
+                }
+            }
+            wp_color_management_output_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(_state: &mut D, _client: ClientId, object_id: ObjectId, data: &ColorOutputData) {
+        if let Some(output) = data.output.as_ref().and_then(WeakOutput::upgrade) {
+            if let Some(color_state) = output.user_data().get::<OutputColorMarker>() {
+                color_state
+                    .lock()
+                    .unwrap()
+                    .instances
+                    .retain(|instance| instance.id() != object_id);
+            }
+        }
+    }
+}
+
+impl<D> Dispatch<WpColorManagementSurfaceV1, ColorSurfaceData, D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagementSurfaceV1, ColorSurfaceData> + ColorManagementHandler + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        resource: &WpColorManagementSurfaceV1,
+        request: wp_color_management_surface_v1::Request,
+        data: &ColorSurfaceData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        if matches!(request, wp_color_management_surface_v1::Request::Destroy) {
+            // handled in `destroyed`
+            return;
+        }
+        if !data.surface.alive() {
+            resource.post_error(
+                wp_color_management_surface_v1::Error::Inert as u32,
+                "the wl_surface was destroyed".to_string(),
+            );
+            return;
+        }
+
+        let pending = match request {
+            wp_color_management_surface_v1::Request::SetImageDescription {
+                image_description,
+                render_intent,
+            } => {
+                let render_intent = match render_intent.into_result() {
+                    Ok(intent)
+                        if state
+                            .color_management_state()
+                            .capabilities()
+                            .render_intents
+                            .contains(&intent) =>
+                    {
+                        intent
+                    }
+                    _ => {
+                        resource.post_error(
+                            wp_color_management_surface_v1::Error::RenderIntent as u32,
+                            "unsupported rendering intent".to_string(),
+                        );
+                        return;
+                    }
+                };
+                let Some(description) = image_description
+                    .data::<ImageDescriptionData>()
+                    .and_then(|data| data.description.clone())
+                else {
+                    resource.post_error(
+                        wp_color_management_surface_v1::Error::ImageDescription as u32,
+                        "the image description is not ready".to_string(),
+                    );
+                    return;
+                };
+                ColorManagementSurfaceCachedState {
+                    image_description: Some(description),
+                    render_intent,
+                }
+            }
+            wp_color_management_surface_v1::Request::UnsetImageDescription => {
+                ColorManagementSurfaceCachedState::default()
+            }
+            _ => unreachable!(),
+        };
+
+        with_states(&data.surface, |states| {
+            *states.cached_state.pending::<ColorManagementSurfaceCachedState>() = pending;
+        });
+    }
+
+    fn destroyed(_state: &mut D, _client: ClientId, _object_id: ObjectId, data: &ColorSurfaceData) {
+        if !data.surface.alive() {
+            return;
+        }
+        with_states(&data.surface, |states| {
+            // destroying the object unsets the description with the next commit
+            *states.cached_state.pending::<ColorManagementSurfaceCachedState>() = Default::default();
+            if let Some(color_state) = states.data_map.get::<SurfaceColorMarker>() {
+                color_state.lock().unwrap().color_surface = None;
+            }
+        });
+    }
+}
+
+impl<D> Dispatch<WpColorManagementSurfaceFeedbackV1, ColorFeedbackData, D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagementSurfaceFeedbackV1, ColorFeedbackData>
+        + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        resource: &WpColorManagementSurfaceFeedbackV1,
+        request: wp_color_management_surface_feedback_v1::Request,
+        data: &ColorFeedbackData,
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let image_description = match request {
+            // all descriptions are parametric
+            wp_color_management_surface_feedback_v1::Request::GetPreferred { image_description }
+            | wp_color_management_surface_feedback_v1::Request::GetPreferredParametric {
+                image_description,
+            } => image_description,
+            wp_color_management_surface_feedback_v1::Request::Destroy => return,
+            _ => unreachable!(),
+        };
+
+        if !data.surface.alive() {
+            resource.post_error(
+                wp_color_management_surface_feedback_v1::Error::Inert as u32,
+                "the wl_surface was destroyed".to_string(),
+            );
+            return;
+        }
+
+        let description = preferred_image_description(&data.surface)
+            .unwrap_or_else(|| state.color_management_state().default_image_description().clone());
+        init_description(data_init, image_description, description, true);
+    }
+
+    fn destroyed(_state: &mut D, _client: ClientId, object_id: ObjectId, data: &ColorFeedbackData) {
+        if !data.surface.alive() {
+            return;
+        }
+        with_states(&data.surface, |states| {
+            if let Some(color_state) = states.data_map.get::<SurfaceColorMarker>() {
+                color_state
+                    .lock()
+                    .unwrap()
+                    .feedbacks
+                    .retain(|feedback| feedback.id() != object_id);
+            }
+        });
+    }
+}
+
+impl<D> Dispatch<WpImageDescriptionCreatorParamsV1, ParametricCreatorData, D> for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionCreatorParamsV1, ParametricCreatorData>
+        + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        resource: &WpImageDescriptionCreatorParamsV1,
+        request: wp_image_description_creator_params_v1::Request,
+        data: &ParametricCreatorData,
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        use wp_image_description_creator_params_v1::{Error, Request};
+
+        let capabilities = state.color_management_state().capabilities();
+        let mut params = data.params.lock().unwrap();
+
+        let result = match request {
+            Request::Create { image_description } => {
+                let (Some(transfer_characteristic), Some(primaries)) =
+                    (params.transfer_characteristic, params.primaries)
+                else {
+                    resource.post_error(
+                        Error::IncompleteSet as u32,
+                        "transfer characteristic and primaries are required".to_string(),
+                    );
+                    return;
+                };
+                let mut description = ParametricImageDescription::new(transfer_characteristic, primaries);
+                description.luminances = params.luminances;
+                description.mastering_primaries = params.mastering_primaries;
+                description.mastering_luminance = params.mastering_luminance;
+                description.max_cll = params.max_cll;
+                description.max_fall = params.max_fall;
+                init_description(
+                    data_init,
+                    image_description,
+                    ImageDescription::new(ImageDescriptionContents::Parametric(description)),
+                    false,
+                );
+                return;
+            }
+            Request::SetTfNamed { tf } => match tf.into_result() {
+                Ok(tf) if capabilities.transfer_functions.contains(&tf) => set_once(
+                    &mut params.transfer_characteristic,
+                    TransferCharacteristic::Named(tf),
+                ),
+                _ => Err((Error::InvalidTf, "unsupported transfer function")),
+            },
+            Request::SetTfPower { eexp } => {
+                if !capabilities.supports(Feature::SetTfPower) {
+                    Err((Error::UnsupportedFeature, "set_tf_power is not supported"))
+                } else if !(10_000..=100_000).contains(&eexp) {
+                    Err((Error::InvalidTf, "exponent out of range"))
+                } else {
+                    set_once(
+                        &mut params.transfer_characteristic,
+                        TransferCharacteristic::Power(eexp),
+                    )
+                }
+            }
+            Request::SetPrimariesNamed { primaries } => match primaries.into_result() {
+                Ok(primaries) if capabilities.primaries.contains(&primaries) => {
+                    set_once(&mut params.primaries, ColorPrimaries::Named(primaries))
+                }
+                _ => Err((Error::InvalidPrimariesNamed, "unsupported primaries")),
+            },
+            Request::SetPrimaries {
+                r_x,
+                r_y,
+                g_x,
+                g_y,
+                b_x,
+                b_y,
+                w_x,
+                w_y,
+            } => {
+                if !capabilities.supports(Feature::SetPrimaries) {
+                    Err((Error::UnsupportedFeature, "set_primaries is not supported"))
+                } else {
+                    let chromaticities = Chromaticities {
+                        red: (r_x, r_y),
+                        green: (g_x, g_y),
+                        blue: (b_x, b_y),
+                        white: (w_x, w_y),
+                    };
+                    set_once(&mut params.primaries, ColorPrimaries::Custom(chromaticities))
+                }
+            }
+            Request::SetLuminances {
+                min_lum,
+                max_lum,
+                reference_lum,
+            } => {
+                if !capabilities.supports(Feature::SetLuminances) {
+                    Err((Error::UnsupportedFeature, "set_luminances is not supported"))
+                } else if !luminance_above(max_lum, min_lum) || !luminance_above(reference_lum, min_lum) {
+                    Err((
+                        Error::InvalidLuminance,
+                        "luminances must exceed the minimum luminance",
+                    ))
+                } else {
+                    set_once(
+                        &mut params.luminances,
+                        Luminances {
+                            min: min_lum,
+                            max: max_lum,
+                            reference: reference_lum,
+                        },
+                    )
+                }
+            }
+            Request::SetMasteringDisplayPrimaries {
+                r_x,
+                r_y,
+                g_x,
+                g_y,
+                b_x,
+                b_y,
+                w_x,
+                w_y,
+            } => {
+                if !capabilities.supports(Feature::SetMasteringDisplayPrimaries) {
+                    Err((
+                        Error::UnsupportedFeature,
+                        "set_mastering_display_primaries is not supported",
+                    ))
+                } else {
+                    let chromaticities = Chromaticities {
+                        red: (r_x, r_y),
+                        green: (g_x, g_y),
+                        blue: (b_x, b_y),
+                        white: (w_x, w_y),
+                    };
+                    set_once(&mut params.mastering_primaries, chromaticities)
+                }
+            }
+            Request::SetMasteringLuminance { min_lum, max_lum } => {
+                if !capabilities.supports(Feature::SetMasteringDisplayPrimaries) {
+                    Err((
+                        Error::UnsupportedFeature,
+                        "set_mastering_luminance is not supported",
+                    ))
+                } else if !luminance_above(max_lum, min_lum) {
+                    Err((
+                        Error::InvalidLuminance,
+                        "max luminance must exceed the min luminance",
+                    ))
+                } else {
+                    set_once(
+                        &mut params.mastering_luminance,
+                        MasteringLuminance {
+                            min: min_lum,
+                            max: max_lum,
+                        },
+                    )
+                }
+            }
+            Request::SetMaxCll { max_cll } => set_once(&mut params.max_cll, max_cll),
+            Request::SetMaxFall { max_fall } => set_once(&mut params.max_fall, max_fall),
+            _ => unreachable!(),
+        };
+
+        if let Err((error, msg)) = result {
+            resource.post_error(error as u32, msg.to_string());
+        }
+    }
+}
+
+/// Set a parameter, which may only be set once per creator
+fn set_once<T>(
+    param: &mut Option<T>,
+    value: T,
+) -> Result<(), (wp_image_description_creator_params_v1::Error, &'static str)> {
+    if param.is_some() {
+        return Err((
+            wp_image_description_creator_params_v1::Error::AlreadySet,
+            "parameter already set",
+        ));
+    }
+    *param = Some(value);
+    Ok(())
+}
+
+/// Whether `lum` in cd/m² exceeds `min_lum` in 0.0001 cd/m²
+fn luminance_above(lum: u32, min_lum: u32) -> bool {
+    lum as u64 * 10_000 > min_lum as u64
+}
+
+impl<D> Dispatch<WpImageDescriptionV1, ImageDescriptionData, D> for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + Dispatch<WpImageDescriptionInfoV1, ()>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &WpImageDescriptionV1,
+        request: wp_image_description_v1::Request,
+        data: &ImageDescriptionData,
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_image_description_v1::Request::GetInformation { information } => {
+                let Some(description) = data.description.as_ref() else {
+                    resource.post_error(
+                        wp_image_description_v1::Error::NotReady as u32,
+                        "the image description is not ready".to_string(),
+                    );
+                    return;
+                };
+                if !data.allow_information {
+                    resource.post_error(
+                        wp_image_description_v1::Error::NoInformation as u32,
+                        "information is only available for compositor descriptions".to_string(),
+                    );
+                    return;
+                }
+                let info = data_init.init(information, ());
+                send_information(&info, description);
+            }
+            wp_image_description_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WpImageDescriptionInfoV1, (), D> for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionInfoV1, ()>,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &WpImageDescriptionInfoV1,
+        _request: wp_image_description_info_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+    }
+}
+
+fn send_information(info: &WpImageDescriptionInfoV1, description: &ImageDescription) {
+    match description.contents() {
+        ImageDescriptionContents::Parametric(params) => {
+            let primaries = params.primaries.chromaticities();
+            send_chromaticities(primaries, |r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y| {
+                info.primaries(r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y)
+            });
+            if let ColorPrimaries::Named(named) = params.primaries {
+                info.primaries_named(named);
+            }
+
+            match params.transfer_characteristic {
+                TransferCharacteristic::Named(tf) => info.tf_named(tf),
+                TransferCharacteristic::Power(eexp) => info.tf_power(eexp),
+            }
+
+            let luminances = params.effective_luminances();
+            info.luminances(luminances.min, luminances.max, luminances.reference);
+
+            // the target color volume defaults to the primary color volume
+            let target_primaries = params.mastering_primaries.unwrap_or(primaries);
+            send_chromaticities(target_primaries, |r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y| {
+                info.target_primaries(r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y)
+            });
+            let target_luminance = params.mastering_luminance.unwrap_or(MasteringLuminance {
+                min: luminances.min,
+                max: luminances.max,
+            });
+            info.target_luminance(target_luminance.min, target_luminance.max);
+            if let Some(max_cll) = params.max_cll {
+                info.target_max_cll(max_cll);
+            }
+            if let Some(max_fall) = params.max_fall {
+                info.target_max_fall(max_fall);
+            }
+        }
+    }
+    info.done();
+}
+
+fn send_chromaticities(
+    chromaticities: Chromaticities,
+    send: impl FnOnce(i32, i32, i32, i32, i32, i32, i32, i32),
+) {
+    let Chromaticities {
+        red,
+        green,
+        blue,
+        white,
+    } = chromaticities;
+    send(red.0, red.1, green.0, green.1, blue.0, blue.1, white.0, white.1);
+}
@@ -0,0 +1,768 @@
+//! Utilities for handling the `wp_color_manager_v1` color management protocol
+//!
+//! This protocol lets clients describe the color encoding of their surfaces, e.g. HDR content
+//! using the PQ transfer function and BT.2020 primaries, and query the color properties of
+//! outputs and the encoding the compositor would prefer for a surface.
+//!
+//! This module only implements the protocol side. Which features, transfer functions and
+//! primaries are advertised is up to the compositor, see [`ColorManagementCapabilities`].
+//! Descriptions of ICC profiles are not supported yet.
+//!
+//! ## How to use it
+//!
+//! ### Initialization
+//!
+//! To initialize this implementation, create the [`ColorManagementState`] with the capabilities
+//! of your compositor, store it in your `State` struct and implement the required traits:
+//!
+//! ```no_run
+//! use smithay::delegate_color_management;
+//! use smithay::wayland::color_management::{
+//!     ColorManagementCapabilities, ColorManagementHandler, ColorManagementState,
+//! };
+//!
+//! pub struct State {
+//!     color_management_state: ColorManagementState,
+//! }
+//!
+//! impl ColorManagementHandler for State {
+//!     fn color_management_state(&mut self) -> &mut ColorManagementState {
+//!         &mut self.color_management_state
+//!     }
+//! }
+//!
+//! delegate_color_management!(State);
+//!
+//! # let display = wayland_server::Display::<State>::new().unwrap();
+//! let color_management_state = ColorManagementState::new::<State>(
+//!     &display.handle(),
+//!     ColorManagementCapabilities::default(),
+//! );
+//! ```
+//!
+//! ### Output and surface descriptions
+//!
+//! The color properties of an output are set using [`set_output_image_description`]. Until then
+//! outputs are described by the [default description](ColorManagementState::default_image_description),
+//! which is sRGB unless changed.
+//!
+//! The description a surface should preferably use, usually the one of the output it is mainly
+//! shown on, is set using [`set_preferred_image_description`].
+//!
+//! ### Rendering
+//!
+//! The description set by the client is stored in the double-buffered
+//! [`ColorManagementSurfaceCachedState`] of the surface, for the renderer to convert the contents
+//! of the surface accordingly. Surfaces without a description are to be treated as sRGB.
+
+use std::sync::Mutex;
+
+use tracing::warn;
+use wayland_server::{
+    backend::GlobalId, protocol::wl_surface::WlSurface, Dispatch, DisplayHandle, GlobalDispatch, Resource,
+};
+
+use crate::{
+    output::Output,
+    wayland::compositor::{with_states, Cacheable},
+};
+
+mod description;
+mod dispatch;
+
+pub use description::{
+    Chromaticities, ColorPrimaries, ImageDescription, ImageDescriptionContents, Luminances,
+    MasteringLuminance, ParametricImageDescription, TransferCharacteristic,
+};
+pub use dispatch::{
+    ColorFeedbackData, ColorOutputData, ColorSurfaceData, ImageDescriptionData, ParametricCreatorData,
+};
+
+/// Generated bindings of the `color-management-v1` protocol
+#[allow(missing_docs, non_upper_case_globals, non_camel_case_types, clippy::all)]
+pub mod protocol {
+    /// Server side of the protocol
+    pub mod server {
+        use wayland_server;
+        use wayland_server::protocol::*;
+
+        pub mod __interfaces {
+            use wayland_server::protocol::__interfaces::*;
+            wayland_scanner::generate_interfaces!("src/wayland/color_management/color-management-v1.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_server_code!("src/wayland/color_management/color-management-v1.xml");
+    }
+
+    /// Client side of the protocol, used by the tests
+    #[cfg(all(test, feature = "test_utils"))]
+    pub mod client {
+        use wayland_client;
+        use wayland_client::protocol::*;
+
+        pub mod __interfaces {
+            use wayland_client::protocol::__interfaces::*;
+            wayland_scanner::generate_interfaces!("src/wayland/color_management/color-management-v1.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_client_code!("src/wayland/color_management/color-management-v1.xml");
+    }
+}
+
+use protocol::server::{
+    wp_color_management_output_v1::WpColorManagementOutputV1,
+    wp_color_management_surface_feedback_v1::WpColorManagementSurfaceFeedbackV1,
+    wp_color_management_surface_v1::WpColorManagementSurfaceV1,
+    wp_color_manager_v1::{Feature, Primaries, RenderIntent, TransferFunction, WpColorManagerV1},
+    wp_image_description_creator_params_v1::WpImageDescriptionCreatorParamsV1,
+    wp_image_description_info_v1::WpImageDescriptionInfoV1,
+    wp_image_description_v1::WpImageDescriptionV1,
+};
+
+/// Capabilities advertised to clients
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorManagementCapabilities {
+    /// Supported rendering intents, perceptual is always supported
+    pub render_intents: Vec<RenderIntent>,
+    /// Supported features
+    ///
+    /// [`Feature::IccV2V4`] is not implemented and ignored.
+    pub features: Vec<Feature>,
+    /// Named transfer functions clients may use in parametric descriptions
+    pub transfer_functions: Vec<TransferFunction>,
+    /// Named primaries clients may use in parametric descriptions
+    pub primaries: Vec<Primaries>,
+}
+
+impl Default for ColorManagementCapabilities {
+    /// Parametric sRGB descriptions only
+    fn default() -> Self {
+        ColorManagementCapabilities {
+            render_intents: vec![RenderIntent::Perceptual],
+            features: vec![Feature::Parametric],
+            transfer_functions: vec![TransferFunction::Srgb, TransferFunction::Gamma22],
+            primaries: vec![Primaries::Srgb],
+        }
+    }
+}
+
+impl ColorManagementCapabilities {
+    /// Returns `true` if the feature is advertised
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.features.contains(&feature)
+    }
+}
+
+/// Handler trait for the color management protocol
+pub trait ColorManagementHandler {
+    /// [`ColorManagementState`] getter
+    fn color_management_state(&mut self) -> &mut ColorManagementState;
+}
+
+/// State of the wp_color_manager_v1 global
+#[derive(Debug)]
+pub struct ColorManagementState {
+    global: GlobalId,
+    capabilities: ColorManagementCapabilities,
+    default_description: ImageDescription,
+}
+
+impl ColorManagementState {
+    /// Create a new [`WpColorManagerV1`] global advertising the given capabilities
+    pub fn new<D>(
+        display: &DisplayHandle,
+        mut capabilities: ColorManagementCapabilities,
+    ) -> ColorManagementState
+    where
+        D: GlobalDispatch<WpColorManagerV1, ()>
+            + Dispatch<WpColorManagerV1, ()>
+            + Dispatch<WpColorManagementOutputV1, ColorOutputData>
+            + Dispatch<WpColorManagementSurfaceV1, ColorSurfaceData>
+            + Dispatch<WpColorManagementSurfaceFeedbackV1, ColorFeedbackData>
+            + Dispatch<WpImageDescriptionCreatorParamsV1, ParametricCreatorData>
+            + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+            + Dispatch<WpImageDescriptionInfoV1, ()>
+            + ColorManagementHandler
+            + 'static,
+    {
+        if capabilities.supports(Feature::IccV2V4) {
+            warn!("ICC image descriptions are not supported, not advertising them");
+            capabilities
+                .features
+                .retain(|feature| *feature != Feature::IccV2V4);
+        }
+        if !capabilities.render_intents.contains(&RenderIntent::Perceptual) {
+            capabilities.render_intents.insert(0, RenderIntent::Perceptual);
+        }
+
+        ColorManagementState {
+            global: display.create_global::<D, WpColorManagerV1, ()>(1, ()),
+            capabilities,
+            default_description: ImageDescription::parametric(ParametricImageDescription::srgb()),
+        }
+    }
+
+    /// Returns the color manager global.
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+
+    /// Capabilities advertised to clients
+    pub fn capabilities(&self) -> &ColorManagementCapabilities {
+        &self.capabilities
+    }
+
+    /// Description of outputs and surfaces the compositor did not set a description for
+    pub fn default_image_description(&self) -> &ImageDescription {
+        &self.default_description
+    }
+
+    /// Set the description of outputs and surfaces the compositor did not set a description for
+    ///
+    /// Clients are not notified about this change.
+    pub fn set_default_image_description(&mut self, description: ImageDescription) {
+        self.default_description = description;
+    }
+}
+
+/// Double-buffered color management state of a surface
+#[derive(Debug, Clone)]
+pub struct ColorManagementSurfaceCachedState {
+    /// Description of the contents of the surface, `None` if the client did not set one
+    pub image_description: Option<ImageDescription>,
+    /// Rendering intent for converting the contents
+    pub render_intent: RenderIntent,
+}
+
+impl Default for ColorManagementSurfaceCachedState {
+    fn default() -> Self {
+        ColorManagementSurfaceCachedState {
+            image_description: None,
+            render_intent: RenderIntent::Perceptual,
+        }
+    }
+}
+
+impl Cacheable for ColorManagementSurfaceCachedState {
+    fn commit(&mut self, _dh: &DisplayHandle) -> Self {
+        self.clone()
+    }
+
+    fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        *into = self;
+    }
+}
+
+/// Color management state of a surface, independent of its commits
+#[derive(Debug, Default)]
+struct SurfaceColorState {
+    color_surface: Option<WpColorManagementSurfaceV1>,
+    feedbacks: Vec<WpColorManagementSurfaceFeedbackV1>,
+    preferred: Option<ImageDescription>,
+}
+
+type SurfaceColorMarker = Mutex<SurfaceColorState>;
+
+/// Color management state of an output
+#[derive(Debug, Default)]
+struct OutputColorState {
+    description: Option<ImageDescription>,
+    instances: Vec<WpColorManagementOutputV1>,
+}
+
+type OutputColorMarker = Mutex<OutputColorState>;
+
+/// Set the description a surface should preferably use
+///
+/// Clients observing the surface are notified if the description changed.
+pub fn set_preferred_image_description(surface: &WlSurface, description: ImageDescription) {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .insert_if_missing_threadsafe(SurfaceColorMarker::default);
+        let mut color_state = states
+            .data_map
+            .get::<SurfaceColorMarker>()
+            .unwrap()
+            .lock()
+            .unwrap();
+        if color_state.preferred.as_ref() == Some(&description) {
+            return;
+        }
+        for feedback in &color_state.feedbacks {
+            feedback.preferred_changed(description.identity());
+        }
+        color_state.preferred = Some(description);
+    });
+}
+
+/// Description the surface should preferably use, if set by the compositor
+pub fn preferred_image_description(surface: &WlSurface) -> Option<ImageDescription> {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .get::<SurfaceColorMarker>()
+            .and_then(|color_state| color_state.lock().unwrap().preferred.clone())
+    })
+}
+
+/// Set the description of the color properties of an output
+///
+/// Clients observing the output are notified if the description changed.
+pub fn set_output_image_description(output: &Output, description: ImageDescription) {
+    output
+        .user_data()
+        .insert_if_missing_threadsafe(OutputColorMarker::default);
+    let mut color_state = output
+        .user_data()
+        .get::<OutputColorMarker>()
+        .unwrap()
+        .lock()
+        .unwrap();
+    if color_state.description.as_ref() == Some(&description) {
+        return;
+    }
+    color_state.description = Some(description);
+
+    for instance in &color_state.instances {
+        instance.image_description_changed();
+        // output events are completed by wl_output.done
+        if let Some(client) = instance.client() {
+            for wl_output in output.client_outputs(&client) {
+                if wl_output.version() >= 2 {
+                    wl_output.done();
+                }
+            }
+        }
+    }
+}
+
+/// Description of the color properties of an output, if set by the compositor
+pub fn output_image_description(output: &Output) -> Option<ImageDescription> {
+    output
+        .user_data()
+        .get::<OutputColorMarker>()
+        .and_then(|color_state| color_state.lock().unwrap().description.clone())
+}
+
+/// Macro to delegate implementation of the color management protocol to [`ColorManagementState`].
+///
+/// You must also implement [`ColorManagementHandler`] to use this.
+#[macro_export]
+macro_rules! delegate_color_management {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::color_management::protocol::server::wp_color_manager_v1::WpColorManagerV1: ()
+        ] => $crate::wayland::color_management::ColorManagementState);
+
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::color_management::protocol::server::wp_color_manager_v1::WpColorManagerV1: ()
+        ] => $crate::wayland::color_management::ColorManagementState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::color_management::protocol::server::wp_color_management_output_v1::WpColorManagementOutputV1: $crate::wayland::color_management::ColorOutputData
+        ] => $crate::wayland::color_management::ColorManagementState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::color_management::protocol::server::wp_color_management_surface_v1::WpColorManagementSurfaceV1: $crate::wayland::color_management::ColorSurfaceData
+        ] => $crate::wayland::color_management::ColorManagementState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::color_management::protocol::server::wp_color_management_surface_feedback_v1::WpColorManagementSurfaceFeedbackV1: $crate::wayland::color_management::ColorFeedbackData
+        ] => $crate::wayland::color_management::ColorManagementState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::color_management::protocol::server::wp_image_description_creator_params_v1::WpImageDescriptionCreatorParamsV1: $crate::wayland::color_management::ParametricCreatorData
+        ] => $crate::wayland::color_management::ColorManagementState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::color_management::protocol::server::wp_image_description_v1::WpImageDescriptionV1: $crate::wayland::color_management::ImageDescriptionData
+        ] => $crate::wayland::color_management::ColorManagementState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::color_management::protocol::server::wp_image_description_info_v1::WpImageDescriptionInfoV1: ()
+        ] => $crate::wayland::color_management::ColorManagementState);
+    };
+}
+
+#[cfg(all(test, feature = "test_utils"))]
+mod client_tests {
+    use std::time::Duration;
+
+    use wayland_client::{protocol::wl_compositor::WlCompositor, Proxy, WEnum};
+    use wayland_server::protocol::wl_surface::WlSurface;
+
+    use super::{
+        protocol::{
+            client::{
+                wp_color_management_surface_feedback_v1,
+                wp_color_management_surface_v1::{self, WpColorManagementSurfaceV1},
+                wp_color_manager_v1::{self, Primaries, RenderIntent, TransferFunction, WpColorManagerV1},
+                wp_image_description_creator_params_v1::{self, WpImageDescriptionCreatorParamsV1},
+                wp_image_description_info_v1,
+                wp_image_description_v1::{self, WpImageDescriptionV1},
+            },
+            server,
+        },
+        set_preferred_image_description, ColorManagementCapabilities, ColorManagementHandler,
+        ColorManagementState, ColorManagementSurfaceCachedState, ColorPrimaries, ImageDescription,
+        ImageDescriptionContents, ParametricImageDescription, TransferCharacteristic,
+    };
+    use crate::{
+        testing::{Recorded, TestClient, TestCompositor},
+        wayland::compositor::{with_states, CompositorHandler, CompositorState},
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    struct State {
+        compositor_state: CompositorState,
+        color_management_state: ColorManagementState,
+        surface: Option<WlSurface>,
+    }
+
+    impl CompositorHandler for State {
+        fn compositor_state(&mut self) -> &mut CompositorState {
+            &mut self.compositor_state
+        }
+
+        fn commit(&mut self, surface: &WlSurface) {
+            self.surface = Some(surface.clone());
+        }
+    }
+
+    impl ColorManagementHandler for State {
+        fn color_management_state(&mut self) -> &mut ColorManagementState {
+            &mut self.color_management_state
+        }
+    }
+
+    crate::delegate_compositor!(State);
+    crate::delegate_color_management!(State);
+
+    struct Setup {
+        compositor: TestCompositor<State>,
+        client: TestClient,
+        manager: WpColorManagerV1,
+        surface: wayland_client::protocol::wl_surface::WlSurface,
+    }
+
+    /// Connect a client with a committed surface
+    fn setup() -> Setup {
+        let mut compositor = TestCompositor::new(|dh, _| State {
+            compositor_state: CompositorState::new::<State>(dh),
+            color_management_state: ColorManagementState::new::<State>(
+                dh,
+                ColorManagementCapabilities::default(),
+            ),
+            surface: None,
+        });
+        let mut client = compositor.connect();
+        let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+        let manager = client.bind::<WpColorManagerV1, _>(&mut compositor);
+        let surface = wl_compositor.create_surface(&client.queue_handle(), Recorded);
+        surface.commit();
+        client.roundtrip(&mut compositor);
+        Setup {
+            compositor,
+            client,
+            manager,
+            surface,
+        }
+    }
+
+    impl Setup {
+        fn server_surface(&self) -> WlSurface {
+            self.compositor.state.surface.clone().unwrap()
+        }
+
+        fn color_surface(&self) -> WpColorManagementSurfaceV1 {
+            self.manager
+                .get_surface(&self.surface, &self.client.queue_handle(), Recorded)
+        }
+
+        fn creator(&self) -> WpImageDescriptionCreatorParamsV1 {
+            self.manager
+                .create_parametric_creator(&self.client.queue_handle(), Recorded)
+        }
+
+        /// Create a description from a complete creator
+        fn create(&self, creator: WpImageDescriptionCreatorParamsV1) -> WpImageDescriptionV1 {
+            creator.create(&self.client.queue_handle(), Recorded)
+        }
+
+        /// Identity of the next description to become ready
+        fn ready(&mut self) -> u32 {
+            match self
+                .client
+                .wait_for_event::<wp_image_description_v1::Event, _>(&mut self.compositor, TIMEOUT)
+            {
+                Some(wp_image_description_v1::Event::Ready { identity }) => identity,
+                event => panic!("Unexpected image description event: {:?}", event),
+            }
+        }
+
+        /// Code of the protocol error sent to the client and the interface it was sent for
+        fn protocol_error(&mut self) -> (u32, String) {
+            let error = self
+                .client
+                .wait_for_protocol_error(&mut self.compositor, TIMEOUT)
+                .expect("No protocol error");
+            (error.code, error.object_interface)
+        }
+    }
+
+    fn srgb(setup: &Setup) -> WpImageDescriptionCreatorParamsV1 {
+        let creator = setup.creator();
+        creator.set_tf_named(TransferFunction::Srgb);
+        creator.set_primaries_named(Primaries::Srgb);
+        creator
+    }
+
+    #[test]
+    fn capabilities_are_advertised() {
+        let mut setup = setup();
+        let (mut intents, mut features, mut tfs, mut primaries) = (vec![], vec![], vec![], vec![]);
+        loop {
+            let event = setup
+                .client
+                .wait_for_event::<wp_color_manager_v1::Event, _>(&mut setup.compositor, TIMEOUT)
+                .expect("Capabilities are not complete");
+            match event {
+                wp_color_manager_v1::Event::SupportedIntent { render_intent } => intents.push(render_intent),
+                wp_color_manager_v1::Event::SupportedFeature { feature } => features.push(feature),
+                wp_color_manager_v1::Event::SupportedTfNamed { tf } => tfs.push(tf),
+                wp_color_manager_v1::Event::SupportedPrimariesNamed { primaries: named } => {
+                    primaries.push(named)
+                }
+                wp_color_manager_v1::Event::Done => break,
+                _ => unreachable!(),
+            }
+        }
+        assert_eq!(intents, vec![WEnum::Value(RenderIntent::Perceptual)]);
+        assert_eq!(
+            features,
+            vec![WEnum::Value(wp_color_manager_v1::Feature::Parametric)]
+        );
+        assert_eq!(
+            tfs,
+            vec![
+                WEnum::Value(TransferFunction::Srgb),
+                WEnum::Value(TransferFunction::Gamma22)
+            ]
+        );
+        assert_eq!(primaries, vec![WEnum::Value(Primaries::Srgb)]);
+    }
+
+    #[test]
+    fn image_description_is_applied_on_commit() {
+        let mut setup = setup();
+        let color_surface = setup.color_surface();
+        let description = setup.create(srgb(&setup));
+        let identity = setup.ready();
+
+        color_surface.set_image_description(&description, RenderIntent::Perceptual);
+        setup.client.roundtrip(&mut setup.compositor);
+        let current = |setup: &Setup| {
+            with_states(&setup.server_surface(), |states| {
+                states
+                    .cached_state
+                    .current::<ColorManagementSurfaceCachedState>()
+                    .image_description
+                    .clone()
+            })
+        };
+        // double-buffered until the next commit
+        assert_eq!(current(&setup), None);
+
+        setup.surface.commit();
+        setup.client.roundtrip(&mut setup.compositor);
+        let applied = current(&setup).expect("No image description applied");
+        assert_eq!(applied.identity(), identity);
+        let ImageDescriptionContents::Parametric(params) = applied.contents();
+        assert_eq!(
+            params.transfer_characteristic,
+            TransferCharacteristic::Named(server::wp_color_manager_v1::TransferFunction::Srgb)
+        );
+        assert_eq!(
+            params.primaries,
+            ColorPrimaries::Named(server::wp_color_manager_v1::Primaries::Srgb)
+        );
+
+        // destroying the color surface unsets the description with the next commit
+        color_surface.destroy();
+        setup.surface.commit();
+        setup.client.roundtrip(&mut setup.compositor);
+        assert_eq!(current(&setup), None);
+    }
+
+    #[test]
+    fn preferred_description_is_reported() {
+        let mut setup = setup();
+        let qh = setup.client.queue_handle();
+        let feedback = setup.manager.get_surface_feedback(&setup.surface, &qh, Recorded);
+
+        // without a preferred description the default one is reported
+        let _default = feedback.get_preferred(&qh, Recorded);
+        let identity = setup.ready();
+        assert_eq!(
+            identity,
+            setup
+                .compositor
+                .state
+                .color_management_state
+                .default_image_description()
+                .identity()
+        );
+
+        let hdr = ImageDescription::parametric(ParametricImageDescription::new(
+            TransferCharacteristic::Named(server::wp_color_manager_v1::TransferFunction::St2084Pq),
+            ColorPrimaries::Named(server::wp_color_manager_v1::Primaries::Bt2020),
+        ));
+        set_preferred_image_description(&setup.server_surface(), hdr.clone());
+        let event = setup
+            .client
+            .wait_for_event::<wp_color_management_surface_feedback_v1::Event, _>(
+                &mut setup.compositor,
+                TIMEOUT,
+            );
+        assert!(matches!(
+            event,
+            Some(wp_color_management_surface_feedback_v1::Event::PreferredChanged { identity })
+                if identity == hdr.identity()
+        ));
+        // setting the same description again does not notify the client
+        set_preferred_image_description(&setup.server_surface(), hdr.clone());
+        setup.client.roundtrip(&mut setup.compositor);
+        assert!(setup
+            .client
+            .wait_for_event::<wp_color_management_surface_feedback_v1::Event, _>(
+                &mut setup.compositor,
+                Duration::ZERO,
+            )
+            .is_none());
+
+        let preferred = feedback.get_preferred_parametric(&qh, Recorded);
+        assert_eq!(setup.ready(), hdr.identity());
+        let _info = preferred.get_information(&qh, Recorded);
+        let (mut tf, mut named_primaries, mut luminances) = (None, None, None);
+        loop {
+            let event = setup
+                .client
+                .wait_for_event::<wp_image_description_info_v1::Event, _>(&mut setup.compositor, TIMEOUT)
+                .expect("Incomplete image description information");
+            match event {
+                wp_image_description_info_v1::Event::TfNamed { tf: named } => tf = Some(named),
+                wp_image_description_info_v1::Event::PrimariesNamed { primaries } => {
+                    named_primaries = Some(primaries)
+                }
+                wp_image_description_info_v1::Event::Luminances {
+                    min_lum,
+                    max_lum,
+                    reference_lum,
+                } => luminances = Some((min_lum, max_lum, reference_lum)),
+                wp_image_description_info_v1::Event::Done => break,
+                _ => {}
+            }
+        }
+        assert_eq!(tf, Some(WEnum::Value(TransferFunction::St2084Pq)));
+        assert_eq!(named_primaries, Some(WEnum::Value(Primaries::Bt2020)));
+        // the protocol defaults of PQ apply
+        assert_eq!(luminances, Some((50, 10_000, 203)));
+    }
+
+    #[test]
+    fn second_color_surface_is_an_error() {
+        let mut setup = setup();
+        let _first = setup.color_surface();
+        let _second = setup.color_surface();
+        assert_eq!(
+            setup.protocol_error(),
+            (
+                wp_color_manager_v1::Error::SurfaceExists as u32,
+                WpColorManagerV1::interface().name.to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn parameters_can_only_be_set_once() {
+        let mut setup = setup();
+        let creator = srgb(&setup);
+        creator.set_tf_named(TransferFunction::Gamma22);
+        assert_eq!(
+            setup.protocol_error(),
+            (
+                wp_image_description_creator_params_v1::Error::AlreadySet as u32,
+                WpImageDescriptionCreatorParamsV1::interface().name.to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn incomplete_parameters_are_an_error() {
+        let mut setup = setup();
+        let creator = setup.creator();
+        creator.set_tf_named(TransferFunction::Srgb);
+        let _description = setup.create(creator);
+        assert_eq!(
+            setup.protocol_error(),
+            (
+                wp_image_description_creator_params_v1::Error::IncompleteSet as u32,
+                WpImageDescriptionCreatorParamsV1::interface().name.to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn unsupported_parameters_are_an_error() {
+        let mut setup = setup();
+        let creator = setup.creator();
+        creator.set_tf_named(TransferFunction::St2084Pq);
+        assert_eq!(
+            setup.protocol_error(),
+            (
+                wp_image_description_creator_params_v1::Error::InvalidTf as u32,
+                WpImageDescriptionCreatorParamsV1::interface().name.to_string()
+            )
+        );
+
+        let mut setup = self::setup();
+        let creator = setup.creator();
+        creator.set_luminances(0, 1000, 203);
+        assert_eq!(
+            setup.protocol_error(),
+            (
+                wp_image_description_creator_params_v1::Error::UnsupportedFeature as u32,
+                WpImageDescriptionCreatorParamsV1::interface().name.to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn unsupported_render_intent_is_an_error() {
+        let mut setup = setup();
+        let color_surface = setup.color_surface();
+        let description = setup.create(srgb(&setup));
+        setup.ready();
+        color_surface.set_image_description(&description, RenderIntent::Saturation);
+        assert_eq!(
+            setup.protocol_error(),
+            (
+                wp_color_management_surface_v1::Error::RenderIntent as u32,
+                WpColorManagementSurfaceV1::interface().name.to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn client_descriptions_have_no_information() {
+        let mut setup = setup();
+        let description = setup.create(srgb(&setup));
+        setup.ready();
+        let _info = description.get_information(&setup.client.queue_handle(), Recorded);
+        assert_eq!(
+            setup.protocol_error(),
+            (
+                wp_image_description_v1::Error::NoInformation as u32,
+                WpImageDescriptionV1::interface().name.to_string()
+            )
+        );
+    }
+}
@@ -242,7 +242,20 @@ where
             wl_surface::Request::Commit => {
                 PrivateSurfaceData::invoke_pre_commit_hooks(handle, surface);
 
-                PrivateSurfaceData::commit(surface, handle);
+                let blocker = state.commit_blocked_by(surface);
+                if let Some(tx) = PrivateSurfaceData::commit(surface, handle, blocker) {
+                    let queue = &mut state.compositor_state().queue;
+                    queue.prune_dead();
+                    if queue.is_empty() && tx.is_ready() {
+                        // fast path, nothing to wait for
+                        tx.apply(handle);
+                    } else {
+                        trace!("Queueing blocked wl_surface.commit");
+                        queue.append(surface.clone(), tx);
+                        CompositorState::blocker_cleared(state, handle);
+                        return;
+                    }
+                }
 
                 PrivateSurfaceData::invoke_post_commit_hooks(handle, surface);
 
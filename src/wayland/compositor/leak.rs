@@ -0,0 +1,95 @@
+//! Detection of surface data outliving its destroyed surface
+//!
+//! The data of a surface is only freed once every handle to the `wl_surface` got dropped.
+//! Handles stored in the data of other objects, that are themselves kept alive by the surface,
+//! form reference cycles and leak the surface including its buffers and textures.
+//!
+//! With debug assertions enabled every destroyed surface is tracked until its data is dropped,
+//! so long-living offenders can be reported by [`report_surface_leaks`]. Without debug assertions
+//! nothing is tracked.
+
+use std::time::Duration;
+#[cfg(debug_assertions)]
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+use wayland_server::backend::ObjectId;
+
+#[cfg(debug_assertions)]
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+#[cfg(debug_assertions)]
+static DESTROYED: Mutex<Option<HashMap<u64, (String, Instant)>>> = Mutex::new(None);
+
+/// Tracker stored in the data of a surface
+#[derive(Debug)]
+pub(super) struct LeakTracker {
+    #[cfg(debug_assertions)]
+    id: u64,
+}
+
+impl LeakTracker {
+    pub(super) fn new() -> LeakTracker {
+        LeakTracker {
+            #[cfg(debug_assertions)]
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// Mark the surface as destroyed, its data is expected to be dropped soon
+    #[cfg_attr(not(debug_assertions), allow(unused_variables))]
+    pub(super) fn destroyed(&self, surface: &ObjectId) {
+        #[cfg(debug_assertions)]
+        DESTROYED
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .insert(self.id, (surface.to_string(), Instant::now()));
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Drop for LeakTracker {
+    fn drop(&mut self) {
+        if let Some(destroyed) = DESTROYED.lock().unwrap().as_mut() {
+            destroyed.remove(&self.id);
+        }
+    }
+}
+
+/// Log surfaces that were destroyed more than `older_than` ago, but whose data is still allocated
+///
+/// Returns the number of such surfaces. Destroyed surfaces are only tracked with debug assertions
+/// enabled, otherwise this always returns `0`.
+///
+/// A surface reported here is usually kept alive by a `WlSurface` handle stored in the compositor
+/// state or in a reference cycle between the data of different objects. Data associated to a surface
+/// should be cleaned up using a destruction hook, see [`add_destruction_hook`](super::add_destruction_hook).
+pub fn report_surface_leaks(older_than: Duration) -> usize {
+    report_destroyed(older_than)
+}
+
+#[cfg(debug_assertions)]
+fn report_destroyed(older_than: Duration) -> usize {
+    let now = Instant::now();
+    let guard = DESTROYED.lock().unwrap();
+    let mut count = 0;
+    for (surface, destroyed_at) in guard.iter().flat_map(|map| map.values()) {
+        let age = now.duration_since(*destroyed_at);
+        if age >= older_than {
+            tracing::warn!(surface = %surface, ?age, "Data of destroyed surface is still alive");
+            count += 1;
+        }
+    }
+    count
+}
+
+#[cfg(not(debug_assertions))]
+fn report_destroyed(_older_than: Duration) -> usize {
+    0
+}
@@ -89,7 +89,9 @@
 //!    the result of that commit. You can check if it is using [`is_sync_subsurface`].
 //! 5. If the surface is destroyed, destruction hooks are invoked. Such hooks can be registered
 //!    using the [`add_destruction_hook`] function. They are typically used to cleanup associated
-//!    state.
+//!    state, in particular to drop handles to other objects, which would otherwise keep the data
+//!    of the surface alive. Once all hooks ran, the cached state of the surface is dropped.
+//!    Leaked surfaces can be found in debug builds using [`report_surface_leaks`].
 //!
 //! ### Surface roles
 //!
@@ -101,14 +103,18 @@
 
 mod cache;
 mod handlers;
+mod leak;
 mod transaction;
 mod tree;
 
 pub use self::cache::{Cacheable, MultiCache};
 pub use self::handlers::{RegionUserData, SubsurfaceCachedState, SubsurfaceUserData, SurfaceUserData};
+pub use self::leak::report_surface_leaks;
+pub use self::transaction::Blocker;
+use self::transaction::TransactionQueue;
 use self::tree::PrivateSurfaceData;
 pub use self::tree::{AlreadyHasRole, TraversalAction};
-use crate::utils::{user_data::UserDataMap, Buffer, Logical, Point, Rectangle};
+use crate::utils::{user_data::UserDataMap, Buffer, IsAlive, Logical, Point, Rectangle};
 use wayland_server::backend::GlobalId;
 use wayland_server::protocol::wl_compositor::WlCompositor;
 use wayland_server::protocol::wl_subcompositor::WlSubcompositor;
@@ -162,6 +168,16 @@ pub struct SurfaceData {
     pub cached_state: MultiCache,
 }
 
+impl SurfaceData {
+    /// Take the frame callbacks of the current state without sending them
+    ///
+    /// This allows deferring the `done` event of the callbacks, e.g. until the frame
+    /// containing the current state of the surface was actually presented.
+    pub fn take_frame_callbacks(&self) -> Vec<wl_callback::WlCallback> {
+        std::mem::take(&mut self.cached_state.current::<SurfaceAttributes>().frame_callbacks)
+    }
+}
+
 /// New buffer assignation for a surface
 #[derive(Debug)]
 pub enum BufferAssignment {
@@ -374,6 +390,60 @@ pub fn get_role(surface: &WlSurface) -> Option<&'static str> {
     PrivateSurfaceData::get_role(surface)
 }
 
+/// Well-known roles of a surface, see [`surface_role`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SurfaceRole {
+    /// An xdg_toplevel surface
+    XdgToplevel,
+    /// An xdg_popup surface
+    XdgPopup,
+    /// A wlr layer surface
+    LayerSurface,
+    /// A pointer cursor image
+    Cursor,
+    /// A subsurface
+    Subsurface,
+    /// Any other role, identified by its name
+    Custom(&'static str),
+}
+
+impl SurfaceRole {
+    /// The role name as used by [`get_role`] and [`give_role`]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SurfaceRole::XdgToplevel => crate::wayland::shell::xdg::XDG_TOPLEVEL_ROLE,
+            SurfaceRole::XdgPopup => crate::wayland::shell::xdg::XDG_POPUP_ROLE,
+            SurfaceRole::LayerSurface => crate::wayland::shell::wlr_layer::LAYER_SURFACE_ROLE,
+            SurfaceRole::Cursor => crate::wayland::seat::CURSOR_IMAGE_ROLE,
+            SurfaceRole::Subsurface => SUBSURFACE_ROLE,
+            SurfaceRole::Custom(role) => role,
+        }
+    }
+}
+
+impl From<&'static str> for SurfaceRole {
+    fn from(role: &'static str) -> SurfaceRole {
+        [
+            SurfaceRole::XdgToplevel,
+            SurfaceRole::XdgPopup,
+            SurfaceRole::LayerSurface,
+            SurfaceRole::Cursor,
+            SurfaceRole::Subsurface,
+        ]
+        .into_iter()
+        .find(|known| known.as_str() == role)
+        .unwrap_or(SurfaceRole::Custom(role))
+    }
+}
+
+/// Get the current role of this surface as a [`SurfaceRole`]
+///
+/// Roles not known to smithay, like the ones of your own protocol implementations,
+/// are returned as [`SurfaceRole::Custom`].
+pub fn surface_role(surface: &WlSurface) -> Option<SurfaceRole> {
+    get_role(surface).map(SurfaceRole::from)
+}
+
 /// Register that this surface has given role
 ///
 /// Fails if the surface already has a role.
@@ -417,7 +487,8 @@ pub fn add_post_commit_hook(surface: &WlSurface, hook: fn(&DisplayHandle, &WlSur
 /// Register a destruction hook to be invoked on surface destruction
 ///
 /// It'll be invoked when the surface is destroyed (either explicitly by the client or on
-/// client disconnect).
+/// client disconnect). Hooks are invoked in the order they were registered, before the
+/// cached state of the surface is dropped, so the whole state is still readable.
 pub fn add_destruction_hook(surface: &WlSurface, hook: fn(&SurfaceData)) {
     PrivateSurfaceData::add_destruction_hook(surface, hook)
 }
@@ -429,6 +500,22 @@ pub trait CompositorHandler {
 
     /// Surface commit handler
     fn commit(&mut self, surface: &WlSurface);
+
+    /// Blocker for the state committed to a surface
+    ///
+    /// Called on every `wl_surface.commit`, before the pending state is applied. If a [`Blocker`]
+    /// is returned, the committed state (including the state of synchronized subsurfaces) is held
+    /// back until the blocker is ready, e.g. until the attached buffer finished rendering.
+    /// Later commits to the same surfaces are queued behind the blocked one.
+    ///
+    /// Once the blocker is ready, [`CompositorState::blocker_cleared`] has to be called to apply
+    /// the state, which invokes [`CompositorHandler::commit`] for the surface as usual.
+    ///
+    /// The default implementation never blocks.
+    fn commit_blocked_by(&mut self, surface: &WlSurface) -> Option<Box<dyn Blocker + Unpin>> {
+        let _ = surface;
+        None
+    }
 }
 
 /// State of a compositor
@@ -436,6 +523,7 @@ pub trait CompositorHandler {
 pub struct CompositorState {
     compositor: GlobalId,
     subcompositor: GlobalId,
+    queue: TransactionQueue,
 }
 
 #[doc(hidden)]
@@ -455,9 +543,30 @@ impl CompositorState {
         CompositorState {
             compositor,
             subcompositor,
+            queue: TransactionQueue::default(),
         }
     }
 
+    /// Apply all blocked states whose blockers are ready
+    ///
+    /// Has to be called once a [`Blocker`] returned from [`CompositorHandler::commit_blocked_by`]
+    /// became ready. [`CompositorHandler::commit`] is invoked for every surface, whose state
+    /// got applied.
+    pub fn blocker_cleared<D: CompositorHandler + 'static>(state: &mut D, dh: &DisplayHandle) {
+        let applied = state.compositor_state().queue.apply_ready(dh);
+        for surface in applied.into_iter().filter(|s| s.alive()) {
+            PrivateSurfaceData::invoke_post_commit_hooks(dh, &surface);
+            state.commit(&surface);
+        }
+    }
+
+    /// Whether states held back by a [`Blocker`] are waiting to be applied
+    #[cfg(test)]
+    pub(crate) fn has_blocked_states(&mut self) -> bool {
+        self.queue.prune_dead();
+        !self.queue.is_empty()
+    }
+
     /// Get id of compositor global
     pub fn compositor_global(&self) -> GlobalId {
         self.compositor.clone()
@@ -506,6 +615,15 @@ macro_rules! delegate_compositor {
 mod tests {
     use super::*;
 
+    #[test]
+    fn surface_role_names() {
+        assert_eq!(SurfaceRole::from(SUBSURFACE_ROLE), SurfaceRole::Subsurface);
+        assert_eq!(SurfaceRole::from("xdg_toplevel"), SurfaceRole::XdgToplevel);
+        assert_eq!(SurfaceRole::from("cursor_image"), SurfaceRole::Cursor);
+        assert_eq!(SurfaceRole::from("dnd_icon"), SurfaceRole::Custom("dnd_icon"));
+        assert_eq!(SurfaceRole::LayerSurface.as_str(), "zwlr_layer_surface_v1");
+    }
+
     #[test]
     fn region_attributes_empty() {
         let region = RegionAttributes { rects: vec![] };
@@ -554,4 +672,152 @@ mod tests {
         assert!(region.contains((5, 5)));
         assert!(region.contains((2, 2)));
     }
+
+    #[cfg(feature = "test_utils")]
+    mod blockers {
+        use std::{
+            pin::Pin,
+            sync::{
+                atomic::{AtomicBool, Ordering},
+                Arc,
+            },
+            task::{Context, Poll},
+        };
+
+        use wayland_client::{
+            protocol::{wl_compositor::WlCompositor, wl_surface::WlSurface as ClientSurface},
+            Proxy,
+        };
+        use wayland_server::protocol::wl_surface::WlSurface;
+
+        use super::super::{with_states, Blocker, CompositorHandler, CompositorState, SurfaceAttributes};
+        use crate::testing::{Recorded, TestClient, TestCompositor};
+
+        struct FlagBlocker(Arc<AtomicBool>);
+
+        impl Blocker for FlagBlocker {
+            fn is_ready(&self) -> bool {
+                self.0.load(Ordering::SeqCst)
+            }
+
+            fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+                if self.is_ready() {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            }
+        }
+
+        struct State {
+            compositor_state: CompositorState,
+            // blocker returned for the next commit
+            block: Option<Arc<AtomicBool>>,
+            commits: usize,
+        }
+
+        impl CompositorHandler for State {
+            fn compositor_state(&mut self) -> &mut CompositorState {
+                &mut self.compositor_state
+            }
+
+            fn commit(&mut self, _surface: &WlSurface) {
+                self.commits += 1;
+            }
+
+            fn commit_blocked_by(&mut self, _surface: &WlSurface) -> Option<Box<dyn Blocker + Unpin>> {
+                self.block
+                    .take()
+                    .map(|flag| Box::new(FlagBlocker(flag)) as Box<dyn Blocker + Unpin>)
+            }
+        }
+
+        crate::delegate_compositor!(State);
+
+        fn new_compositor() -> TestCompositor<State> {
+            TestCompositor::new(|dh, _| State {
+                compositor_state: CompositorState::new::<State>(dh),
+                block: None,
+                commits: 0,
+            })
+        }
+
+        fn buffer_scale(
+            compositor: &TestCompositor<State>,
+            client: &TestClient,
+            surface: &ClientSurface,
+        ) -> i32 {
+            let surface = client
+                .client()
+                .object_from_protocol_id::<WlSurface>(&compositor.handle(), surface.id().protocol_id())
+                .unwrap();
+            with_states(&surface, |states| {
+                states.cached_state.current::<SurfaceAttributes>().buffer_scale
+            })
+        }
+
+        #[test]
+        fn blocked_commit_delays_later_commits() {
+            let mut compositor = new_compositor();
+            let mut client = compositor.connect();
+            let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+            let surface = wl_compositor.create_surface(&client.queue_handle(), Recorded);
+
+            let flag = Arc::new(AtomicBool::new(false));
+            compositor.state.block = Some(flag.clone());
+            surface.set_buffer_scale(2);
+            surface.commit();
+            client.roundtrip(&mut compositor);
+            assert_eq!(compositor.state.commits, 0);
+            assert_eq!(buffer_scale(&compositor, &client, &surface), 1);
+
+            // the next commit is not blocked itself, but has to wait for the previous one
+            surface.set_buffer_scale(3);
+            surface.commit();
+            client.roundtrip(&mut compositor);
+            assert_eq!(compositor.state.commits, 0);
+            assert_eq!(buffer_scale(&compositor, &client, &surface), 1);
+            assert!(compositor.state.compositor_state.has_blocked_states());
+
+            // clearing without the blocker being ready changes nothing
+            let dh = compositor.handle();
+            CompositorState::blocker_cleared(&mut compositor.state, &dh);
+            assert_eq!(compositor.state.commits, 0);
+
+            flag.store(true, Ordering::SeqCst);
+            CompositorState::blocker_cleared(&mut compositor.state, &dh);
+            assert_eq!(compositor.state.commits, 2);
+            assert_eq!(buffer_scale(&compositor, &client, &surface), 3);
+            assert!(!compositor.state.compositor_state.has_blocked_states());
+
+            // without anything queued, commits are applied right away
+            surface.set_buffer_scale(4);
+            surface.commit();
+            client.roundtrip(&mut compositor);
+            assert_eq!(compositor.state.commits, 3);
+            assert_eq!(buffer_scale(&compositor, &client, &surface), 4);
+        }
+
+        #[test]
+        fn blocked_commits_of_dead_surfaces_are_pruned() {
+            let mut compositor = new_compositor();
+            let mut client = compositor.connect();
+            let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+            let qh = client.queue_handle();
+            let blocked = wl_compositor.create_surface(&qh, Recorded);
+            let other = wl_compositor.create_surface(&qh, Recorded);
+
+            // this blocker never becomes ready
+            compositor.state.block = Some(Arc::new(AtomicBool::new(false)));
+            blocked.commit();
+            client.roundtrip(&mut compositor);
+            assert!(compositor.state.compositor_state.has_blocked_states());
+
+            blocked.destroy();
+            other.commit();
+            client.roundtrip(&mut compositor);
+            assert_eq!(compositor.state.commits, 1);
+            assert!(!compositor.state.compositor_state.has_blocked_states());
+        }
+    }
 }
@@ -23,26 +23,22 @@
 // - Then, still on commit, if the surface is not a synchronized subsurface, its pending transaction is
 //   directly applied
 //
-// This last step will change once we have support for explicit synchronization (and further in the future,
-// of the wp_transaction protocol). Explicit synchronization introduces a notion of blockers: the transaction
-// cannot be applied before all blockers are released, and thus must wait for it to be the case.
+// Explicit synchronization (and further in the future, the wp_transaction protocol) introduces a
+// notion of blockers: the transaction cannot be applied before all blockers are ready, and thus must
+// wait for it to be the case. Blockers are registered by the compositor through
+// `CompositorHandler::commit_blocked_by`.
 //
-// For thoses situations, the (currently unused) `TransactionQueue` will come into play. It is a per-client
-// queue of transactions, that stores and applies them by both respecting their topological order
-// (ensuring that for each surface, states are applied in the correct order) and that all transactions
-// wait befor all their blockers are resolved to be merged. If a blocker is cancelled, the whole transaction
-// it blocks is cancelled as well, and simply dropped. Thanks to the logic of `Cache::apply_state`, the
-// associated state will be applied automatically when the next valid transaction is applied, ensuring
-// global coherence.
-
-// A significant part of the logic of this module is not yet used,
-// but will be once proper transaction & blockers support is
-// added to smithay
-#![allow(dead_code)]
+// For thoses situations, the `TransactionQueue` comes into play. It is a queue of transactions,
+// that stores and applies them by both respecting their topological order (ensuring that for each
+// surface, states are applied in the correct order) and that all transactions wait for all their
+// blockers to be ready to be merged.
 
 use std::{
     collections::HashSet,
+    fmt,
+    pin::Pin,
     sync::{Arc, Mutex},
+    task::{Context, Poll},
 };
 
 use wayland_server::{protocol::wl_surface::WlSurface, DisplayHandle, Resource};
@@ -51,20 +47,26 @@ use crate::{utils::IsAlive, utils::Serial};
 
 use super::tree::PrivateSurfaceData;
 
-pub trait Blocker {
-    fn state(&self) -> BlockerState;
-}
+/// Condition blocking a pending surface state from being applied
+///
+/// See [`CompositorHandler::commit_blocked_by`](super::CompositorHandler::commit_blocked_by).
+pub trait Blocker: Send {
+    /// Returns `true` once the blocked state may be applied
+    fn is_ready(&self) -> bool;
 
-pub enum BlockerState {
-    Pending,
-    Released,
-    Cancelled,
+    /// Poll the blocker for readiness
+    ///
+    /// Allows driving blockers from an async executor. Once this returns [`Poll::Ready`],
+    /// [`Blocker::is_ready`] has to return `true` as well.
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()>;
 }
 
+type BoxedBlocker = Box<dyn Blocker + Unpin>;
+
 #[derive(Default)]
 struct TransactionState {
     surfaces: Vec<(WlSurface, Serial)>,
-    blockers: Vec<Box<dyn Blocker + Send>>,
+    blockers: Vec<BoxedBlocker>,
 }
 
 impl TransactionState {
@@ -114,8 +116,8 @@ impl PendingTransaction {
         self.with_inner_state(|state| state.insert(surface, id))
     }
 
-    pub(crate) fn add_blocker<B: Blocker + Send + 'static>(&self, blocker: B) {
-        self.with_inner_state(|state| state.blockers.push(Box::new(blocker) as Box<_>))
+    pub(crate) fn add_blocker(&self, blocker: BoxedBlocker) {
+        self.with_inner_state(|state| state.blockers.push(blocker))
     }
 
     pub(crate) fn is_same_as(&self, other: &PendingTransaction) -> bool {
@@ -173,26 +175,13 @@ impl PendingTransaction {
 }
 pub(crate) struct Transaction {
     surfaces: Vec<(WlSurface, Serial)>,
-    blockers: Vec<Box<dyn Blocker + Send>>,
+    blockers: Vec<BoxedBlocker>,
 }
 
 impl Transaction {
-    /// Computes the global state of the transaction with regard to its blockers
-    ///
-    /// The logic is:
-    ///
-    /// - if at least one blocker is cancelled, the transaction is cancelled
-    /// - otherwise, if at least one blocker is pending, the transaction is pending
-    /// - otherwise, all blockers are released, and the transaction is also released
-    pub(crate) fn state(&self) -> BlockerState {
-        use BlockerState::*;
-        self.blockers
-            .iter()
-            .fold(Released, |acc, blocker| match (acc, blocker.state()) {
-                (Cancelled, _) | (_, Cancelled) => Cancelled,
-                (Pending, _) | (_, Pending) => Pending,
-                (Released, Released) => Released,
-            })
+    /// Returns whether all blockers of the transaction are ready
+    pub(crate) fn is_ready(&self) -> bool {
+        self.blockers.iter().all(|blocker| blocker.is_ready())
     }
 
     pub(crate) fn apply(self, dh: &DisplayHandle) {
@@ -204,20 +193,47 @@ impl Transaction {
     }
 }
 
-// This queue should be per-client
+/// Queue of transactions waiting for their blockers
+///
+/// Every transaction is stored with the surface whose commit finalized it,
+/// so the commit handler can be invoked for it once it was applied.
 #[derive(Default)]
 pub(crate) struct TransactionQueue {
-    transactions: Vec<Transaction>,
+    transactions: Vec<(WlSurface, Transaction)>,
     // we keep the hashset around to reuse allocations
     seen_surfaces: HashSet<u32>,
 }
 
+impl fmt::Debug for TransactionQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransactionQueue")
+            .field("transactions.len", &self.transactions.len())
+            .finish()
+    }
+}
+
 impl TransactionQueue {
-    pub(crate) fn append(&mut self, t: Transaction) {
-        self.transactions.push(t);
+    pub(crate) fn append(&mut self, root: WlSurface, t: Transaction) {
+        self.transactions.push((root, t));
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
     }
 
-    pub(crate) fn apply_ready<D: 'static>(&mut self, dh: &DisplayHandle) {
+    /// Drop all transactions whose surfaces were all destroyed
+    ///
+    /// Their blockers may never become ready, e.g. if the client disconnected,
+    /// which would otherwise keep them in the queue forever.
+    pub(crate) fn prune_dead(&mut self) {
+        self.transactions
+            .retain(|(_, transaction)| transaction.surfaces.iter().any(|(s, _)| s.alive()));
+    }
+
+    /// Apply all transactions that are ready, returning the surfaces that finalized them
+    pub(crate) fn apply_ready(&mut self, dh: &DisplayHandle) -> Vec<WlSurface> {
+        self.prune_dead();
+        let mut applied = Vec::new();
         // this is a very non-optimized implementation
         // we just iterate over the queue of transactions, keeping track of which
         // surface we have seen as they encode transaction dependencies
@@ -226,24 +242,12 @@ impl TransactionQueue {
         let mut i = 0;
         // the loop will terminate, as at every iteration either i is incremented by 1
         // or the lenght of self.transactions is reduced by 1.
-        while i <= self.transactions.len() {
-            let mut skip = false;
+        while i < self.transactions.len() {
             // does the transaction have any active blocker?
-            match self.transactions[i].state() {
-                BlockerState::Cancelled => {
-                    // this transaction is cancelled, remove it without further processing
-                    self.transactions.remove(i);
-                    continue;
-                }
-                BlockerState::Pending => {
-                    skip = true;
-                }
-                BlockerState::Released => {}
-            }
+            let mut skip = !self.transactions[i].1.is_ready();
             // if not, does this transaction depend on any previous transaction?
             if !skip {
-                for (s, _) in &self.transactions[i].surfaces {
-                    // TODO: is this alive check still needed?
+                for (s, _) in &self.transactions[i].1.surfaces {
                     if !s.alive() {
                         continue;
                     }
@@ -257,8 +261,7 @@ impl TransactionQueue {
             if skip {
                 // this transaction is not yet ready and should be skipped, add its surfaces to our
                 // seen list
-                for (s, _) in &self.transactions[i].surfaces {
-                    // TODO: is this alive check still needed?
+                for (s, _) in &self.transactions[i].1.surfaces {
                     if !s.alive() {
                         continue;
                     }
@@ -267,8 +270,11 @@ impl TransactionQueue {
                 i += 1;
             } else {
                 // this transaction is to be applied, yay!
-                self.transactions.remove(i).apply(dh);
+                let (root, transaction) = self.transactions.remove(i);
+                transaction.apply(dh);
+                applied.push(root);
             }
         }
+        applied
     }
 }
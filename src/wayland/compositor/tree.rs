@@ -3,7 +3,8 @@ use crate::{utils::Serial, wayland::compositor::SUBSURFACE_ROLE};
 use super::{
     cache::MultiCache,
     handlers::{is_effectively_sync, SurfaceUserData},
-    transaction::PendingTransaction,
+    leak::LeakTracker,
+    transaction::{Blocker, PendingTransaction, Transaction},
     BufferAssignment, SurfaceAttributes, SurfaceData,
 };
 use std::{
@@ -34,6 +35,7 @@ pub struct PrivateSurfaceData {
     pre_commit_hooks: Vec<fn(&DisplayHandle, &WlSurface)>,
     post_commit_hooks: Vec<fn(&DisplayHandle, &WlSurface)>,
     destruction_hooks: Vec<fn(&SurfaceData)>,
+    leak_tracker: LeakTracker,
 }
 
 impl fmt::Debug for PrivateSurfaceData {
@@ -99,6 +101,7 @@ impl PrivateSurfaceData {
             pre_commit_hooks: Vec::new(),
             post_commit_hooks: Vec::new(),
             destruction_hooks: Vec::new(),
+            leak_tracker: LeakTracker::new(),
         })
     }
 
@@ -111,9 +114,18 @@ impl PrivateSurfaceData {
     }
 
     /// Cleans the `as_ref().user_data` of that surface, must be called when it is destroyed
+    ///
+    /// Destruction hooks are invoked first, in the order they were registered, while the
+    /// state of the surface is still intact. Afterwards all state referencing other objects
+    /// is dropped, so the surface data does not keep them alive.
     pub fn cleanup(surface_data: &SurfaceUserData, surface_id: ObjectId) {
         let my_data_mutex = &surface_data.inner;
         let mut my_data = my_data_mutex.lock().unwrap();
+
+        for hook in std::mem::take(&mut my_data.destruction_hooks) {
+            hook(&my_data.public_data)
+        }
+
         if let Some(old_parent) = my_data.parent.take() {
             // We had a parent, lets unregister ourselves from it
             let old_parent_mutex = &old_parent.data::<SurfaceUserData>().unwrap().inner;
@@ -150,9 +162,13 @@ impl PrivateSurfaceData {
             buffer.release();
         };
 
-        for hook in &my_data.destruction_hooks {
-            hook(&my_data.public_data)
-        }
+        // drop all remaining double-buffered state, e.g. frame callbacks, and anything
+        // that could reference other surfaces
+        my_data.public_data.cached_state = MultiCache::new();
+        my_data.pending_transaction = Default::default();
+        my_data.pre_commit_hooks.clear();
+        my_data.post_commit_hooks.clear();
+        my_data.leak_tracker.destroyed(&surface_id);
     }
 
     pub fn set_role(surface: &WlSurface, role: &'static str) -> Result<(), AlreadyHasRole> {
@@ -219,7 +235,15 @@ impl PrivateSurfaceData {
         }
     }
 
-    pub fn commit(surface: &WlSurface, dh: &DisplayHandle) {
+    /// Commit the pending state of the surface
+    ///
+    /// If the surface is not effectively synchronized, the transaction it finalizes is returned
+    /// and has to be applied by the caller, once all its blockers are ready.
+    pub fn commit(
+        surface: &WlSurface,
+        dh: &DisplayHandle,
+        blocker: Option<Box<dyn Blocker + Unpin>>,
+    ) -> Option<Transaction> {
         let is_sync = is_effectively_sync(surface);
         let children = PrivateSurfaceData::get_children(surface);
         let my_data_mutex = &surface.data::<SurfaceUserData>().unwrap().inner;
@@ -250,13 +274,19 @@ impl PrivateSurfaceData {
         my_data
             .pending_transaction
             .insert_state(surface.clone(), my_data.current_txid);
+        if let Some(blocker) = blocker {
+            my_data.pending_transaction.add_blocker(blocker);
+        }
         if !is_sync {
-            // if we are not sync, apply the transaction immediately
+            // if we are not sync, the transaction is complete. Further commits need a new id,
+            // as the transaction may be held back by its blockers.
             let tx = std::mem::take(&mut my_data.pending_transaction);
+            my_data.current_txid.0 = my_data.current_txid.0.wrapping_add(1);
             // release the mutex, as applying the transaction will try to lock it
             std::mem::drop(my_data);
-            // apply the transaction
-            tx.finalize().apply(dh);
+            Some(tx.finalize())
+        } else {
+            None
         }
     }
 
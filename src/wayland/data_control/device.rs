@@ -1,21 +1,192 @@
+pub use super::protocol::server::ext_data_control_device_v1::ExtDataControlDeviceV1 as ExtDevice;
 pub use super::server::zwlr_data_control_device_v1::{Request, ZwlrDataControlDeviceV1 as Device};
 
-use std::cell::RefCell;
+use std::{
+    cell::RefCell,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
-use tracing::debug;
-use wayland_server::{protocol::wl_seat::WlSeat, Client, DataInit, Dispatch, DisplayHandle, Resource};
+use tracing::{debug, debug_span};
+use wayland_server::{
+    backend::{ClientId, ObjectData, ObjectId},
+    protocol::wl_seat::WlSeat,
+    Client, DataInit, Dispatch, DisplayHandle, Resource,
+};
 
 use crate::{
     input::{Seat, SeatHandler},
-    wayland::seat::WaylandFocus,
+    utils::{alive_tracker::AliveTracker, IsAlive},
+    wayland::{ext::ClientExt, seat::WaylandFocus},
 };
 
-use super::{Handler, SeatData, Selection, State};
+use super::{
+    offer::{ExtOffer, Offer},
+    protocol::server::ext_data_control_device_v1,
+    DataSource, Handler, Protocol, SeatData, Selection, SelectionTarget, State,
+};
 
 #[doc(hidden)]
 #[derive(Debug)]
 pub struct Data {
     pub(crate) wl_seat: WlSeat,
+    pub(crate) alive_tracker: AliveTracker,
+    /// The seat went away, the device was sent `finished`
+    pub(crate) detached: AtomicBool,
+}
+
+impl Data {
+    /// Mark the device as detached from its seat, returns whether it was attached before
+    pub(crate) fn detach(&self) -> bool {
+        !self.detached.swap(true, Ordering::AcqRel)
+    }
+
+    /// The seat of the device, unless it was removed through [`detach_seat`](super::detach_seat)
+    ///
+    /// Releasing the `wl_seat` the device was created with does not detach it, clipboard managers
+    /// commonly keep using their devices afterwards.
+    fn seat<D: SeatHandler + 'static>(&self) -> Option<Seat<D>> {
+        if self.detached.load(Ordering::Acquire) {
+            return None;
+        }
+        Seat::<D>::from_resource(&self.wl_seat)
+    }
+}
+
+/// A data device created through either of the data control protocols
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DataDevice {
+    /// Device of the `wlr-data-control-unstable-v1` protocol
+    Wlr(Device),
+    /// Device of the `ext-data-control-v1` protocol
+    Ext(ExtDevice),
+}
+
+impl DataDevice {
+    /// The protocol this device was created with
+    pub fn protocol(&self) -> Protocol {
+        match self {
+            DataDevice::Wlr(_) => Protocol::Wlr,
+            DataDevice::Ext(_) => Protocol::Ext,
+        }
+    }
+
+    /// Id of the underlying protocol object
+    pub fn id(&self) -> ObjectId {
+        match self {
+            DataDevice::Wlr(device) => device.id(),
+            DataDevice::Ext(device) => device.id(),
+        }
+    }
+
+    fn data(&self) -> Option<&Data> {
+        match self {
+            DataDevice::Wlr(device) => device.data::<Data>(),
+            DataDevice::Ext(device) => device.data::<Data>(),
+        }
+    }
+
+    pub(super) fn finished(&self) {
+        match self {
+            DataDevice::Wlr(device) => device.finished(),
+            DataDevice::Ext(device) => device.finished(),
+        }
+    }
+
+    /// Mark the device as detached from its seat, returns whether it was attached before
+    pub(super) fn detach(&self) -> bool {
+        self.data().map_or(false, Data::detach)
+    }
+
+    /// Whether the device can be sent the primary selection
+    pub(super) fn supports_primary_selection(&self) -> bool {
+        match self {
+            DataDevice::Wlr(device) => device.version() >= 2,
+            DataDevice::Ext(_) => true,
+        }
+    }
+
+    pub(super) fn clear_selection(&self, target: SelectionTarget) {
+        match (self, target) {
+            (DataDevice::Wlr(device), SelectionTarget::Clipboard) => device.selection(None),
+            (DataDevice::Wlr(device), SelectionTarget::Primary) => device.primary_selection(None),
+            (DataDevice::Ext(device), SelectionTarget::Clipboard) => device.selection(None),
+            (DataDevice::Ext(device), SelectionTarget::Primary) => device.primary_selection(None),
+        }
+    }
+
+    /// Create an offer of the matching protocol backed by `data` and advertize it as the given selection
+    pub(super) fn offer_selection<D: 'static>(
+        &self,
+        dh: &DisplayHandle,
+        client: &Client,
+        data: Arc<dyn ObjectData<D>>,
+        mime_types: &[String],
+        target: SelectionTarget,
+    ) {
+        let handle = dh.backend_handle();
+        match self {
+            DataDevice::Wlr(device) => {
+                let offer = handle
+                    .create_object::<D>(client.id(), Offer::interface(), device.version(), data)
+                    .unwrap();
+                let offer = Offer::from_id(dh, offer).unwrap();
+
+                device.data_offer(&offer);
+                for mime_type in mime_types.iter().cloned() {
+                    offer.offer(mime_type);
+                }
+                match target {
+                    SelectionTarget::Clipboard => device.selection(Some(&offer)),
+                    SelectionTarget::Primary => device.primary_selection(Some(&offer)),
+                }
+            }
+            DataDevice::Ext(device) => {
+                let offer = handle
+                    .create_object::<D>(client.id(), ExtOffer::interface(), device.version(), data)
+                    .unwrap();
+                let offer = ExtOffer::from_id(dh, offer).unwrap();
+
+                device.data_offer(&offer);
+                for mime_type in mime_types.iter().cloned() {
+                    offer.offer(mime_type);
+                }
+                match target {
+                    SelectionTarget::Clipboard => device.selection(Some(&offer)),
+                    SelectionTarget::Primary => device.primary_selection(Some(&offer)),
+                }
+            }
+        }
+    }
+}
+
+impl IsAlive for DataDevice {
+    fn alive(&self) -> bool {
+        match self {
+            DataDevice::Wlr(device) => device.alive(),
+            DataDevice::Ext(device) => device.alive(),
+        }
+    }
+}
+
+impl From<Device> for DataDevice {
+    fn from(device: Device) -> Self {
+        DataDevice::Wlr(device)
+    }
+}
+
+impl From<ExtDevice> for DataDevice {
+    fn from(device: ExtDevice) -> Self {
+        DataDevice::Ext(device)
+    }
+}
+
+/// Requests shared by the devices of both protocols
+enum DeviceRequest {
+    SetSelection(SelectionTarget, Option<DataSource>),
+    Destroy,
 }
 
 impl<D> Dispatch<Device, Data, D> for State<D>
@@ -35,37 +206,156 @@ where
         dh: &DisplayHandle,
         _data_init: &mut DataInit<'_, D>,
     ) {
-        if let Some(seat) = Seat::<D>::from_resource(&data.wl_seat) {
-            match request {
-                Request::SetSelection { source, .. } => {
-                    if let Some(keyboard) = seat.get_keyboard() {
-                        if keyboard.client_of_object_has_focus(&resource.id()) {
-                            let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
-
-                            Handler::new_selection(handler, source.clone());
-                            // The client has kbd focus, it can set the selection
-                            seat_data.borrow_mut().set_selection::<D>(
-                                dh,
-                                source.map(Selection::Client).unwrap_or(Selection::Empty),
-                            );
-                            return;
-                        }
-                    }
-                    debug!(
-                        client = ?client,
-                        "denying setting selection by a non-focused client"
-                    );
+        let request = match request {
+            Request::SetSelection { source } => {
+                DeviceRequest::SetSelection(SelectionTarget::Clipboard, source.map(DataSource::Wlr))
+            }
+            Request::SetPrimarySelection { source } => {
+                DeviceRequest::SetSelection(SelectionTarget::Primary, source.map(DataSource::Wlr))
+            }
+            Request::Destroy => DeviceRequest::Destroy,
+            _ => unreachable!(),
+        };
+        handle_request(
+            handler,
+            client,
+            &DataDevice::Wlr(resource.clone()),
+            request,
+            data,
+            dh,
+        );
+    }
+
+    fn destroyed(_state: &mut D, _client: ClientId, resource: ObjectId, data: &Data) {
+        destroyed::<D>(resource, data);
+    }
+}
+
+impl<D> Dispatch<ExtDevice, Data, D> for State<D>
+where
+    D: Dispatch<ExtDevice, Data>,
+    D: Handler,
+    D: SeatHandler,
+    <D as SeatHandler>::KeyboardFocus: WaylandFocus,
+    D: 'static,
+{
+    fn request(
+        handler: &mut D,
+        client: &Client,
+        resource: &ExtDevice,
+        request: ext_data_control_device_v1::Request,
+        data: &Data,
+        dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let request = match request {
+            ext_data_control_device_v1::Request::SetSelection { source } => {
+                DeviceRequest::SetSelection(SelectionTarget::Clipboard, source.map(DataSource::Ext))
+            }
+            ext_data_control_device_v1::Request::SetPrimarySelection { source } => {
+                DeviceRequest::SetSelection(SelectionTarget::Primary, source.map(DataSource::Ext))
+            }
+            ext_data_control_device_v1::Request::Destroy => DeviceRequest::Destroy,
+            _ => unreachable!(),
+        };
+        handle_request(
+            handler,
+            client,
+            &DataDevice::Ext(resource.clone()),
+            request,
+            data,
+            dh,
+        );
+    }
+
+    fn destroyed(_state: &mut D, _client: ClientId, resource: ObjectId, data: &Data) {
+        destroyed::<D>(resource, data);
+    }
+}
+
+fn handle_request<D>(
+    handler: &mut D,
+    client: &Client,
+    resource: &DataDevice,
+    request: DeviceRequest,
+    data: &Data,
+    dh: &DisplayHandle,
+) where
+    D: Handler,
+    <D as SeatHandler>::KeyboardFocus: WaylandFocus,
+    D: 'static,
+{
+    let Some(seat) = data.seat::<D>() else {
+        let _span = debug_span!(parent: &client.span(dh), "data_control_device").entered();
+        match request {
+            DeviceRequest::SetSelection(_, source) => {
+                debug!("ignoring selection set on a device without seat");
+                // the source never became the selection
+                if let Some(source) = source {
+                    source.cancelled();
                 }
-                Request::Destroy => {
-                    // Clean up the known devices
-                    seat.user_data()
-                        .get::<RefCell<SeatData>>()
-                        .unwrap()
-                        .borrow_mut()
-                        .retain_devices(|ndd| ndd != resource)
+                if data.detach() {
+                    resource.finished();
                 }
-                _ => unreachable!(),
             }
+            DeviceRequest::Destroy => {}
         }
+        return;
+    };
+
+    let _span = debug_span!(parent: &client.span(dh), "data_control_device", seat = seat.name()).entered();
+    match request {
+        DeviceRequest::SetSelection(target, source) => {
+            if let Some(keyboard) = seat.get_keyboard() {
+                if keyboard.client_of_object_has_focus(&resource.id()) {
+                    let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
+
+                    handler.new_data_control_selection(target, source.clone(), resource.protocol());
+                    // The client has kbd focus, it can set the selection
+                    seat_data.borrow_mut().set_selection::<D>(
+                        dh,
+                        target,
+                        source.map(Selection::Client).unwrap_or(Selection::Empty),
+                    );
+                    return;
+                }
+            }
+            debug!(?target, "denying setting selection by a non-focused client");
+        }
+        DeviceRequest::Destroy => {
+            // Clean up the known devices
+            seat.user_data()
+                .get::<RefCell<SeatData>>()
+                .unwrap()
+                .borrow_mut()
+                .retain_devices(|ndd| ndd != resource)
+        }
+    }
+}
+
+fn destroyed<D: SeatHandler + 'static>(resource: ObjectId, data: &Data) {
+    data.alive_tracker.destroy_notify();
+
+    // Clean up the known devices, also if the client disconnected without destroying it
+    if let Some(seat) = Seat::<D>::from_resource(&data.wl_seat) {
+        if let Some(seat_data) = seat.user_data().get::<RefCell<SeatData>>() {
+            seat_data
+                .borrow_mut()
+                .retain_devices(|device| device.id() != resource);
+        }
+    }
+}
+
+impl IsAlive for Device {
+    fn alive(&self) -> bool {
+        let data: &Data = self.data().unwrap();
+        data.alive_tracker.alive()
+    }
+}
+
+impl IsAlive for ExtDevice {
+    fn alive(&self) -> bool {
+        let data: &Data = self.data().unwrap();
+        data.alive_tracker.alive()
     }
 }
@@ -1,15 +1,24 @@
+pub use super::protocol::server::ext_data_control_manager_v1::ExtDataControlManagerV1 as ExtManager;
 pub use super::server::zwlr_data_control_manager_v1::{Request, ZwlrDataControlManagerV1 as Manager};
 
-use std::cell::RefCell;
+use std::{cell::RefCell, sync::atomic::AtomicBool};
 
-use tracing::error;
-use wayland_server::{Dispatch, DisplayHandle, GlobalDispatch};
+use tracing::{debug, error};
+use wayland_server::{protocol::wl_seat::WlSeat, Client, Dispatch, DisplayHandle, GlobalDispatch};
 
-use crate::input::{Seat, SeatHandler};
+use crate::{
+    input::{Seat, SeatHandler},
+    wayland::ext::ClientExt,
+};
 
-use super::{Handler, SeatData, State};
+use super::{
+    device::{self, DataDevice, ExtDevice},
+    protocol::server::ext_data_control_manager_v1,
+    source::{self, ExtSource},
+    Handler, SeatData, State,
+};
 
-use super::{device, source, Device, Source};
+use super::{Device, Source};
 
 impl<D> GlobalDispatch<Manager, (), D> for State<D>
 where
@@ -42,38 +51,123 @@ where
     D: 'static,
 {
     fn request(
-        _state: &mut D,
+        state: &mut D,
         client: &wayland_server::Client,
         _resource: &Manager,
         request: Request,
         _data: &(),
-        _dhandle: &DisplayHandle,
+        dhandle: &DisplayHandle,
         data_init: &mut wayland_server::DataInit<'_, D>,
     ) {
+        let _span = client.span(dhandle).entered();
         match request {
             Request::CreateDataSource { id } => {
                 data_init.init(id, source::Data::new());
             }
-            Request::GetDataDevice { id, seat: wl_seat } => match Seat::<D>::from_resource(&wl_seat) {
-                Some(seat) => {
-                    seat.user_data()
-                        .insert_if_missing(|| RefCell::new(SeatData::new()));
+            Request::GetDataDevice { id, seat: wl_seat } => {
+                get_data_device(state, dhandle, client, wl_seat, |data| {
+                    data_init.init(id, data).into()
+                });
+            }
+            Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
 
-                    let device = data_init.init(id, device::Data { wl_seat });
+impl<D> GlobalDispatch<ExtManager, (), D> for State<D>
+where
+    D: SeatHandler + GlobalDispatch<ExtManager, ()>,
+    D: Dispatch<ExtManager, ()>,
+    D: Dispatch<ExtSource, source::Data>,
+    D: Dispatch<ExtDevice, device::Data>,
+    D: Handler,
+    D: 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &wayland_server::Client,
+        resource: wayland_server::New<ExtManager>,
+        _global_data: &(),
+        data_init: &mut wayland_server::DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
 
-                    let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
-                    seat_data.borrow_mut().add_device(device);
-                }
-                None => {
-                    error!(
-                        primary_selection_device = ?id,
-                        client = ?client,
-                        "Unmanaged seat given to a primary selection device."
-                    );
-                }
-            },
-            Request::Destroy => {}
+impl<D> Dispatch<ExtManager, (), D> for State<D>
+where
+    D: Dispatch<ExtManager, ()>,
+    D: Dispatch<ExtSource, source::Data>,
+    D: Dispatch<ExtDevice, device::Data>,
+    D: Handler,
+    D: SeatHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        client: &wayland_server::Client,
+        _resource: &ExtManager,
+        request: ext_data_control_manager_v1::Request,
+        _data: &(),
+        dhandle: &DisplayHandle,
+        data_init: &mut wayland_server::DataInit<'_, D>,
+    ) {
+        let _span = client.span(dhandle).entered();
+        match request {
+            ext_data_control_manager_v1::Request::CreateDataSource { id } => {
+                data_init.init(id, source::Data::new());
+            }
+            ext_data_control_manager_v1::Request::GetDataDevice { id, seat: wl_seat } => {
+                get_data_device(state, dhandle, client, wl_seat, |data| {
+                    data_init.init(id, data).into()
+                });
+            }
+            ext_data_control_manager_v1::Request::Destroy => {}
             _ => unreachable!(),
         }
     }
 }
+
+/// Create a data device of either protocol and add it to the data of its seat
+///
+/// Devices exceeding the device limit of the client are finished right away,
+/// neither protocol has an error for this case.
+fn get_data_device<D, F>(state: &D, dhandle: &DisplayHandle, client: &Client, wl_seat: WlSeat, init: F)
+where
+    D: Handler + 'static,
+    F: FnOnce(device::Data) -> DataDevice,
+{
+    let Some(seat) = Seat::<D>::from_resource(&wl_seat) else {
+        error!(?wl_seat, "Unmanaged seat given to a data control device.");
+        return;
+    };
+
+    seat.user_data()
+        .insert_if_missing(|| RefCell::new(SeatData::new()));
+    let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
+    let mut seat_data = seat_data.borrow_mut();
+
+    let device = init(device::Data {
+        wl_seat,
+        alive_tracker: Default::default(),
+        detached: AtomicBool::new(seat_data.detached),
+    });
+
+    if seat_data.detached {
+        // the seat was removed, the device is never going to be used
+        debug!("data device created for a removed seat");
+        device.finished();
+        return;
+    }
+
+    let limit = state.data_control_state().max_devices_per_client();
+    if seat_data.device_count(dhandle, client) >= limit {
+        debug!(limit, "too many data devices, finishing the new one");
+        device.detach();
+        device.finished();
+        return;
+    }
+    seat_data.add_device(device);
+}
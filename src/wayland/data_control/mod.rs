@@ -6,6 +6,12 @@
 //! where text just needs to be selected in order to allow copying it elsewhere
 //! The de facto way to perform this action is the middle mouse button, although it is not limited to this one.
 //!
+//! Data control devices can be created through the `wlr-data-control-unstable-v1` protocol,
+//! advertized by [`State::new`], and the `ext-data-control-v1` protocol, additionally advertized by
+//! [`State::new_with_ext`]. Devices of both protocols share the per-seat state, so clients see the
+//! same selection and primary selection regardless of the protocol they use.
+//! [`Handler::new_data_control_selection`] is told which [`Protocol`] a selection came from.
+//!
 //! This module provides the freestanding [`set_primary_focus`] function:
 //!   This function sets the data device focus for a given seat; you'd typically call it
 //!   whenever the keyboard focus changes, to follow it (for example in the focus hook of your keyboards).
@@ -59,38 +65,126 @@
 //! ```
 
 use std::collections::HashMap;
-use std::{cell::RefCell, sync::Arc};
+use std::{cell::RefCell, sync::Arc, time::Duration};
 
 use std::os::unix::io::{AsRawFd, OwnedFd};
 
-use tracing::{debug, instrument};
+use tracing::{debug, instrument, warn};
 use wayland_protocols_wlr::data_control::v1::server;
 use wayland_server::{
     backend::GlobalId,
     backend::{protocol::Message, ClientId, Handle, ObjectData, ObjectId},
-    Client, DisplayHandle, GlobalDispatch, Resource,
+    Client, DisplayHandle, GlobalDispatch,
 };
 
 use crate::{
     input::{Seat, SeatHandler},
     utils::IsAlive,
+    wayland::selection::{
+        start_transfer, SelectionOwner, SelectionProtocol, SelectionTransfer, TransferError, TransferOptions,
+    },
 };
 
-pub use source::{with_source_metadata, Data, Metadata};
+pub use source::{clone_source_metadata, with_source_metadata, Data, DataSource, Metadata};
 
-pub use self::{device::Device, manager::Manager, offer::Offer, source::Source};
+pub use self::{
+    device::{DataDevice, Device, ExtDevice},
+    manager::{ExtManager, Manager},
+    offer::{ExtOffer, Offer},
+    source::{ExtSource, Source},
+    transform::{
+        ContentTransformer, DEFAULT_MAX_CONCURRENT_TRANSFORMS, DEFAULT_MAX_TRANSFORM_SIZE,
+        DEFAULT_TRANSFORM_TIMEOUT,
+    },
+};
 
 mod device;
 mod manager;
 mod offer;
 mod source;
+mod transform;
+
+/// Generated bindings of the `ext-data-control-v1` protocol
+#[allow(missing_docs, non_upper_case_globals, non_camel_case_types, clippy::all)]
+pub mod protocol {
+    /// Server side of the protocol
+    pub mod server {
+        use wayland_server;
+        use wayland_server::protocol::*;
+
+        pub mod __interfaces {
+            use wayland_server::protocol::__interfaces::*;
+            wayland_scanner::generate_interfaces!("src/wayland/data_control/ext-data-control-v1.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_server_code!("src/wayland/data_control/ext-data-control-v1.xml");
+    }
+
+    /// Client side of the protocol, used by the tests
+    #[cfg(all(test, feature = "test_utils"))]
+    pub mod client {
+        use wayland_client;
+        use wayland_client::protocol::*;
+
+        pub mod __interfaces {
+            use wayland_client::protocol::__interfaces::*;
+            wayland_scanner::generate_interfaces!("src/wayland/data_control/ext-data-control-v1.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_client_code!("src/wayland/data_control/ext-data-control-v1.xml");
+    }
+}
+
+/// The protocol a data control object was created with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    /// `wlr-data-control-unstable-v1`
+    Wlr,
+    /// `ext-data-control-v1`
+    Ext,
+}
+
+/// The selection a data control device operates on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SelectionTarget {
+    /// The regular clipboard selection
+    Clipboard,
+    /// The primary selection
+    Primary,
+}
 
 /// Events that are generated by interactions of the clients with the data device
-pub trait Handler: Sized {
-    /// A client has set the selection
+pub trait Handler: SeatHandler + Sized {
+    /// [State] getter
+    fn data_control_state(&self) -> &State<Self>;
+
+    /// A client has set the selection through a `wlr-data-control-unstable-v1` device
     #[allow(unused_variables)]
     fn new_selection(&mut self, source: Option<Source>) {}
 
+    /// A client has set the selection or the primary selection through a device of either protocol
+    ///
+    /// `protocol` is the protocol of the device the selection was set through, which is also
+    /// the protocol of `source`, if any.
+    ///
+    /// The default implementation forwards clipboard selections of the `wlr-data-control-unstable-v1`
+    /// protocol to [`Handler::new_selection`].
+    fn new_data_control_selection(
+        &mut self,
+        target: SelectionTarget,
+        source: Option<DataSource>,
+        protocol: Protocol,
+    ) {
+        if target == SelectionTarget::Clipboard && protocol == Protocol::Wlr {
+            self.new_selection(source.and_then(|source| match source {
+                DataSource::Wlr(source) => Some(source),
+                DataSource::Ext(_) => None,
+            }));
+        }
+    }
+
     /// A client requested to read the server-set selection
     ///
     /// * `mime_type` - the requested mime type
@@ -101,9 +195,19 @@ pub trait Handler: Sized {
 
 pub struct State<D: SeatHandler> {
     manager_global: GlobalId,
+    ext_manager_global: Option<GlobalId>,
     seats: HashMap<Seat<D>, SeatData>,
+    content_transformer: Option<Arc<ContentTransformer>>,
+    transform_limits: transform::Limits,
+    max_devices_per_client: usize,
 }
 
+/// Default for [`State::max_devices_per_client`]
+pub const DEFAULT_MAX_DEVICES_PER_CLIENT: usize = 16;
+
+/// Version 2 of `wlr-data-control-unstable-v1` added the primary selection
+const MANAGER_VERSION: u32 = 2;
+
 impl<D: SeatHandler> State<D> {
     /// Regiseter new [ZwpPrimarySelectionDeviceManagerV1] global
     pub fn new(display: &DisplayHandle) -> Self
@@ -111,11 +215,33 @@ impl<D: SeatHandler> State<D> {
         D: GlobalDispatch<Manager, ()> + 'static,
         D: Handler,
     {
-        let manager_global = display.create_global::<D, Manager, _>(1, ());
+        let manager_global = display.create_global::<D, Manager, _>(MANAGER_VERSION, ());
+        Self::with_globals(manager_global, None)
+    }
+
+    /// Register new [`Manager`] and [`ExtManager`] globals
+    ///
+    /// Clients can create data control devices through both the `wlr-data-control-unstable-v1`
+    /// and the `ext-data-control-v1` protocol. Devices of either protocol share the selections
+    /// of their seat.
+    pub fn new_with_ext(display: &DisplayHandle) -> Self
+    where
+        D: GlobalDispatch<Manager, ()> + GlobalDispatch<ExtManager, ()> + 'static,
+        D: Handler,
+    {
+        let manager_global = display.create_global::<D, Manager, _>(MANAGER_VERSION, ());
+        let ext_manager_global = display.create_global::<D, ExtManager, _>(1, ());
+        Self::with_globals(manager_global, Some(ext_manager_global))
+    }
 
+    fn with_globals(manager_global: GlobalId, ext_manager_global: Option<GlobalId>) -> Self {
         Self {
             manager_global,
+            ext_manager_global,
             seats: HashMap::new(),
+            content_transformer: None,
+            transform_limits: Default::default(),
+            max_devices_per_client: DEFAULT_MAX_DEVICES_PER_CLIENT,
         }
     }
 
@@ -123,6 +249,94 @@ impl<D: SeatHandler> State<D> {
     pub fn global(&self) -> GlobalId {
         self.manager_global.clone()
     }
+
+    /// [ExtManager] GlobalId getter, if the state was created with [`State::new_with_ext`]
+    pub fn ext_global(&self) -> Option<GlobalId> {
+        self.ext_manager_global.clone()
+    }
+
+    /// Set a transformer applied to the content of compositor-provided selections
+    ///
+    /// The content written by [`Handler::send_selection`] is passed through the transformer
+    /// before it reaches the client, e.g. to convert line endings when bridging the X11 clipboard.
+    /// Selections set by clients are passed through untransformed.
+    pub fn set_content_transformer(&mut self, transformer: Box<ContentTransformer>) {
+        self.content_transformer = Some(Arc::from(transformer));
+    }
+
+    /// Remove a previously set content transformer
+    pub fn clear_content_transformer(&mut self) {
+        self.content_transformer = None;
+    }
+
+    /// Maximum number of content transforms running at the same time
+    pub fn max_concurrent_transforms(&self) -> usize {
+        self.transform_limits.max_running
+    }
+
+    /// Set the maximum number of content transforms running at the same time
+    ///
+    /// Every transform runs on its own thread until the compositor finished writing the content.
+    /// Reads requested while the limit is reached are denied.
+    /// Defaults to [`DEFAULT_MAX_CONCURRENT_TRANSFORMS`].
+    pub fn set_max_concurrent_transforms(&mut self, limit: usize) {
+        self.transform_limits.max_running = limit;
+    }
+
+    /// Maximum size in bytes of the content passed through the content transformer
+    pub fn max_transform_size(&self) -> usize {
+        self.transform_limits.max_size
+    }
+
+    /// Set the maximum size in bytes of the content passed through the content transformer
+    ///
+    /// Larger content is dropped and the client reads nothing.
+    /// Defaults to [`DEFAULT_MAX_TRANSFORM_SIZE`].
+    pub fn set_max_transform_size(&mut self, limit: usize) {
+        self.transform_limits.max_size = limit;
+    }
+
+    /// Time a single content transform may take
+    pub fn transform_timeout(&self) -> Duration {
+        self.transform_limits.timeout
+    }
+
+    /// Set the time a single content transform may take
+    ///
+    /// The timeout covers both reading the content from the compositor and writing it to the
+    /// client, so a client that never reads its pipe does not hold a transform slot forever.
+    /// Defaults to [`DEFAULT_TRANSFORM_TIMEOUT`].
+    pub fn set_transform_timeout(&mut self, timeout: Duration) {
+        self.transform_limits.timeout = timeout;
+    }
+
+    /// Number of live data devices bound to the given seat
+    pub fn device_count(&self, seat: &Seat<D>) -> usize {
+        seat.user_data()
+            .get::<RefCell<SeatData>>()
+            .map_or(0, |seat_data| {
+                seat_data
+                    .borrow()
+                    .known_devices
+                    .iter()
+                    .filter(|device| device.alive())
+                    .count()
+            })
+    }
+
+    /// Maximum number of data devices a client may create per seat
+    pub fn max_devices_per_client(&self) -> usize {
+        self.max_devices_per_client
+    }
+
+    /// Set the maximum number of data devices a client may create per seat
+    ///
+    /// Devices created beyond the limit are sent the `finished` event right away and ignore
+    /// further requests, like devices of a removed seat.
+    /// Defaults to [`DEFAULT_MAX_DEVICES_PER_CLIENT`].
+    pub fn set_max_devices_per_client(&mut self, limit: usize) {
+        self.max_devices_per_client = limit;
+    }
 }
 
 /// Set the primary selection focus to a certain client for a given seat
@@ -137,6 +351,22 @@ where
     seat_data.borrow_mut().set_focus::<D>(dh, client);
 }
 
+/// Detach all data control devices from a seat that is being removed
+///
+/// Every device bound to the seat is sent the `finished` event and ignores further requests,
+/// a selection held by a client is cancelled. Devices created for the seat afterwards are
+/// finished right away. Call this before removing the seat global.
+#[instrument(name = "wayland_data_control", level = "debug", skip(seat), fields(seat = seat.name()))]
+pub fn detach_seat<D>(seat: &Seat<D>)
+where
+    D: SeatHandler + 'static,
+{
+    seat.user_data()
+        .insert_if_missing(|| RefCell::new(SeatData::new()));
+    let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
+    seat_data.borrow_mut().detach();
+}
+
 /// Set a compositor-provided primary selection for this seat
 ///
 /// You need to provide the available mime types for this selection.
@@ -151,21 +381,85 @@ where
     seat.user_data()
         .insert_if_missing(|| RefCell::new(SeatData::new()));
     let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
-    seat_data
-        .borrow_mut()
-        .set_selection::<D>(dh, Selection::Compositor(Metadata { mime_types }));
+    seat_data.borrow_mut().set_selection::<D>(
+        dh,
+        SelectionTarget::Clipboard,
+        Selection::Compositor(Metadata {
+            mime_types,
+            ..Default::default()
+        }),
+    );
+}
+
+/// Request the contents of the current selection of this seat
+///
+/// The owner of the selection is asked to write its contents for the given mime type into a
+/// pipe, which is the client holding it or, for selections set through [`set_primary_selection`],
+/// your [`Handler::send_selection`] implementation. The content transformer is not applied, you
+/// receive compositor-provided selections as written by your handler.
+///
+/// The returned [`SelectionTransfer`] has to be inserted into your event loop and delivers the data
+/// or the [`TransferError`] that ended the transfer, enforcing the limits given in `options`.
+#[instrument(name = "wayland_data_control", level = "debug", skip(state, seat), fields(seat = seat.name()))]
+pub fn request_data_control_selection<D>(
+    state: &mut D,
+    seat: &Seat<D>,
+    mime_type: String,
+    options: TransferOptions,
+) -> Result<SelectionTransfer, TransferError>
+where
+    D: SeatHandler + Handler + 'static,
+{
+    let owner = seat.user_data().get::<RefCell<SeatData>>().and_then(|seat_data| {
+        match &seat_data.borrow().selection {
+            Selection::Client(source) if source.alive() => Some(SelectionOwner::Client {
+                source: source.clone(),
+                mime_types: source
+                    .with_metadata(|meta| meta.mime_types.clone())
+                    .unwrap_or_default(),
+            }),
+            Selection::Compositor(meta) => Some(SelectionOwner::Compositor {
+                provider: (),
+                mime_types: meta.mime_types.clone(),
+            }),
+            _ => None,
+        }
+    });
+    start_transfer(
+        owner,
+        mime_type,
+        options,
+        |source, mime_type, fd| source::send(&source, mime_type, fd),
+        |(), mime_type, fd| state.send_selection(mime_type, fd),
+    )
+}
+
+impl<D> SelectionProtocol<D> for State<D>
+where
+    D: SeatHandler + Handler + 'static,
+{
+    fn request_selection(
+        state: &mut D,
+        seat: &Seat<D>,
+        mime_type: String,
+        options: TransferOptions,
+    ) -> Result<SelectionTransfer, TransferError> {
+        request_data_control_selection(state, seat, mime_type, options)
+    }
 }
 
 pub enum Selection {
     Empty,
-    Client(Source),
+    Client(DataSource),
     Compositor(source::Metadata),
 }
 
 pub struct SeatData {
-    known_devices: Vec<Device>,
+    known_devices: Vec<DataDevice>,
     selection: Selection,
+    primary_selection: Selection,
     current_focus: Option<wayland_server::Client>,
+    detached: bool,
 }
 
 impl Default for SeatData {
@@ -173,7 +467,9 @@ impl Default for SeatData {
         Self {
             known_devices: Vec::new(),
             selection: Selection::Empty,
+            primary_selection: Selection::Empty,
             current_focus: None,
+            detached: false,
         }
     }
 }
@@ -183,32 +479,69 @@ impl SeatData {
         Self::default()
     }
 
-    pub fn add_device(&mut self, device: Device) {
-        self.known_devices.push(device);
+    pub fn add_device(&mut self, device: impl Into<DataDevice>) {
+        self.known_devices.push(device.into());
+    }
+
+    /// Finish all devices, the seat is going away
+    pub fn detach(&mut self) {
+        self.detached = true;
+        for device in self.known_devices.drain(..) {
+            if !device.alive() {
+                continue;
+            }
+            if device.detach() {
+                device.finished();
+            }
+        }
+        for selection in [&mut self.selection, &mut self.primary_selection] {
+            if let Selection::Client(source) = std::mem::replace(selection, Selection::Empty) {
+                source.cancelled();
+            }
+        }
+        self.current_focus = None;
     }
 
     pub fn retain_devices<F>(&mut self, f: F)
     where
-        F: FnMut(&Device) -> bool,
+        F: FnMut(&DataDevice) -> bool,
     {
         self.known_devices.retain(f)
     }
 
+    /// Number of live devices of the given client
+    pub fn device_count(&mut self, dh: &DisplayHandle, client: &Client) -> usize {
+        self.known_devices.retain(|device| device.alive());
+        self.known_devices
+            .iter()
+            .filter(|device| dh.get_client(device.id()).map_or(false, |c| &c == client))
+            .count()
+    }
+
     pub fn set_focus<D>(&mut self, dh: &DisplayHandle, new_focus: Option<wayland_server::Client>)
     where
         D: Handler,
         D: 'static,
     {
         self.current_focus = new_focus;
-        self.send_selection::<D>(dh);
+        self.send_selection::<D>(dh, SelectionTarget::Clipboard);
+        self.send_selection::<D>(dh, SelectionTarget::Primary);
+    }
+
+    fn selection_mut(&mut self, target: SelectionTarget) -> &mut Selection {
+        match target {
+            SelectionTarget::Clipboard => &mut self.selection,
+            SelectionTarget::Primary => &mut self.primary_selection,
+        }
     }
 
-    pub fn set_selection<D>(&mut self, dh: &DisplayHandle, new_selection: Selection)
+    pub fn set_selection<D>(&mut self, dh: &DisplayHandle, target: SelectionTarget, new_selection: Selection)
     where
         D: Handler,
         D: 'static,
     {
-        if let Selection::Client(source) = &self.selection {
+        let selection = self.selection_mut(target);
+        if let Selection::Client(source) = &*selection {
             match &new_selection {
                 Selection::Client(new_source) if new_source == source => {}
                 _ => {
@@ -216,100 +549,73 @@ impl SeatData {
                 }
             }
         }
-        self.selection = new_selection;
-        self.send_selection::<D>(dh);
+        *selection = new_selection;
+        self.send_selection::<D>(dh, target);
     }
 
-    pub fn send_selection<D>(&mut self, dh: &DisplayHandle)
+    pub fn send_selection<D>(&mut self, dh: &DisplayHandle, target: SelectionTarget)
     where
         D: Handler,
         D: 'static,
     {
+        // drop devices of clients that went away without destroying them
+        self.known_devices.retain(|device| device.alive());
+
         let client = match self.current_focus.as_ref() {
             Some(c) => c,
             None => return,
         };
+        let selection = match target {
+            SelectionTarget::Clipboard => &mut self.selection,
+            SelectionTarget::Primary => &mut self.primary_selection,
+        };
         // first sanitize the selection, reseting it to null if the client holding
         // it dropped it
-        let cleanup = if let Selection::Client(ref source) = self.selection {
+        let cleanup = if let Selection::Client(source) = &*selection {
             !source.alive()
         } else {
             false
         };
         if cleanup {
-            self.selection = Selection::Empty;
+            *selection = Selection::Empty;
         }
 
         // then send it if appropriate
-        match self.selection {
-            Selection::Empty => {
-                // send an empty selection
-                for pd in &self.known_devices {
-                    // skip data devices not belonging to our client
-                    if dh.get_client(pd.id()).map(|c| &c != client).unwrap_or(true) {
-                        continue;
-                    }
-                    pd.selection(None);
-                }
+        for pd in &self.known_devices {
+            // skip data devices not belonging to our client
+            if dh.get_client(pd.id()).map(|c| &c != client).unwrap_or(true) {
+                continue;
             }
-            Selection::Client(ref source) => {
-                for pd in &self.known_devices {
-                    // skip data devices not belonging to our client
-                    if dh.get_client(pd.id()).map(|c| &c != client).unwrap_or(true) {
-                        continue;
-                    }
-                    let source_clone = source.clone();
-
-                    let handle = dh.backend_handle();
-                    // create a data offer
-                    let offer = handle
-                        .create_object::<D>(
-                            client.id(),
-                            Offer::interface(),
-                            pd.version(),
-                            Arc::new(ClientSelection { source: source_clone }),
-                        )
-                        .unwrap();
-                    let offer = Offer::from_id(dh, offer).unwrap();
-
-                    // advertize the offer to the client
-                    pd.data_offer(&offer);
-                    with_source_metadata(source, |meta| {
-                        for mime_type in meta.mime_types.iter().cloned() {
-                            offer.offer(mime_type);
-                        }
-                    })
-                    .unwrap();
-                    pd.selection(Some(&offer));
-                }
+            // devices of older protocol versions don't know about the primary selection
+            if target == SelectionTarget::Primary && !pd.supports_primary_selection() {
+                continue;
             }
-            Selection::Compositor(ref meta) => {
-                for pd in &self.known_devices {
-                    // skip data devices not belonging to our client
-                    if dh.get_client(pd.id()).map(|c| &c != client).unwrap_or(true) {
-                        continue;
-                    }
-
+            let protocol = pd.protocol();
+            match &*selection {
+                Selection::Empty => {
+                    // send an empty selection
+                    pd.clear_selection(target);
+                }
+                Selection::Client(source) => {
+                    let mime_types = source.with_metadata(|meta| meta.mime_types.clone()).unwrap();
+                    let source = source.clone();
+                    pd.offer_selection::<D>(
+                        dh,
+                        client,
+                        Arc::new(ClientSelection { source, protocol }),
+                        &mime_types,
+                        target,
+                    );
+                }
+                Selection::Compositor(meta) => {
                     let offer_meta = meta.clone();
-
-                    let handle = dh.backend_handle();
-                    // create a data offer
-                    let offer = handle
-                        .create_object::<D>(
-                            client.id(),
-                            Offer::interface(),
-                            pd.version(),
-                            Arc::new(ServerSelection { offer_meta }),
-                        )
-                        .unwrap();
-                    let offer = Offer::from_id(dh, offer).unwrap();
-
-                    // advertize the offer to the client
-                    pd.data_offer(&offer);
-                    for mime_type in meta.mime_types.iter().cloned() {
-                        offer.offer(mime_type);
-                    }
-                    pd.selection(Some(&offer));
+                    pd.offer_selection::<D>(
+                        dh,
+                        client,
+                        Arc::new(ServerSelection { offer_meta, protocol }),
+                        &meta.mime_types,
+                        target,
+                    );
                 }
             }
         }
@@ -317,7 +623,8 @@ impl SeatData {
 }
 
 struct ClientSelection {
-    source: Source,
+    source: DataSource,
+    protocol: Protocol,
 }
 
 impl<D> ObjectData<D> for ClientSelection
@@ -332,8 +639,8 @@ where
         msg: Message<ObjectId, OwnedFd>,
     ) -> Option<Arc<dyn ObjectData<D>>> {
         let dh = DisplayHandle::from(dh.clone());
-        if let Ok((_resource, request)) = Offer::parse_request(&dh, msg) {
-            handle_client_selection(request, &self.source);
+        if let Some((mime_type, fd)) = offer::parse_receive(&dh, self.protocol, msg) {
+            handle_client_selection(mime_type, fd, &self.source);
         }
 
         None
@@ -342,25 +649,24 @@ where
     fn destroyed(&self, _data: &mut D, _client_id: ClientId, _object_id: ObjectId) {}
 }
 
-fn handle_client_selection(request: offer::Request, source: &Source) {
-    // selection data offers only care about the `receive` event
-    if let offer::Request::Receive { fd, mime_type } = request {
-        // check if the source and associated mime type is still valid
-        let valid =
-            with_source_metadata(source, |meta| meta.mime_types.contains(&mime_type)).unwrap_or(false);
-        // TODO:?
-        // && source.as_ref().is_alive();
-        if !valid {
-            // deny the receive
-            debug!("Denying a zwp_primary_selection_offer_v1.receive with invalid source.");
-        } else {
-            source.send(mime_type, fd.as_raw_fd());
-        }
+fn handle_client_selection(mime_type: String, fd: OwnedFd, source: &DataSource) {
+    // check if the source and associated mime type is still valid
+    let valid = source
+        .with_metadata(|meta| meta.mime_types.contains(&mime_type))
+        .unwrap_or(false);
+    // TODO:?
+    // && source.as_ref().is_alive();
+    if !valid {
+        // deny the receive
+        debug!("Denying a zwp_primary_selection_offer_v1.receive with invalid source.");
+    } else {
+        source::send(source, mime_type, fd.as_raw_fd());
     }
 }
 
 struct ServerSelection {
     offer_meta: Metadata,
+    protocol: Protocol,
 }
 
 impl<D> ObjectData<D> for ServerSelection
@@ -375,8 +681,8 @@ where
         msg: Message<ObjectId, OwnedFd>,
     ) -> Option<Arc<dyn ObjectData<D>>> {
         let dh = DisplayHandle::from(dh.clone());
-        if let Ok((_resource, request)) = Offer::parse_request(&dh, msg) {
-            handle_server_selection(handler, request, &self.offer_meta);
+        if let Some((mime_type, fd)) = offer::parse_receive(&dh, self.protocol, msg) {
+            handle_server_selection(handler, mime_type, fd, &self.offer_meta);
         }
 
         None
@@ -385,19 +691,25 @@ where
     fn destroyed(&self, _data: &mut D, _client_id: ClientId, _object_id: ObjectId) {}
 }
 
-pub fn handle_server_selection<D>(handler: &mut D, request: offer::Request, offer_meta: &Metadata)
+pub fn handle_server_selection<D>(handler: &mut D, mime_type: String, fd: OwnedFd, offer_meta: &Metadata)
 where
     D: Handler,
 {
-    // selection data offers only care about the `receive` event
-    if let offer::Request::Receive { fd, mime_type } = request {
-        // check if the associated mime type is valid
-        if !offer_meta.mime_types.contains(&mime_type) {
-            // deny the receive
-            debug!("Denying a zwp_primary_selection_offer_v1.receive with invalid source.");
-        } else {
-            handler.send_selection(mime_type, fd);
+    // check if the associated mime type is valid
+    if !offer_meta.mime_types.contains(&mime_type) {
+        // deny the receive
+        debug!("Denying a zwp_primary_selection_offer_v1.receive with invalid source.");
+    } else if let Some(transformer) = handler.data_control_state().content_transformer.clone() {
+        let limits = &handler.data_control_state().transform_limits;
+        match transform::spawn_transform(transformer, limits, mime_type.clone(), fd) {
+            Ok(fd) => handler.send_selection(mime_type, fd),
+            Err(err) => warn!(
+                ?err,
+                "Denying a selection read, failed to start content transform"
+            ),
         }
+    } else {
+        handler.send_selection(mime_type, fd);
     }
 }
 
@@ -420,3 +732,676 @@ macro_rules! delegate {
         ] => $crate::wayland::primary_selection::PrimarySelectionState);
     };
 }
+
+#[cfg(all(test, feature = "test_utils"))]
+mod tests {
+    use std::{cell::RefCell, io::Write, os::unix::io::OwnedFd, time::Duration};
+
+    use wayland_server::protocol::{wl_seat::WlSeat, wl_surface::WlSurface};
+
+    use super::{
+        device, request_data_control_selection, set_primary_selection, source, Device, ExtDevice, ExtManager,
+        ExtSource, Handler, Manager, SeatData, Source, State,
+    };
+    use crate::{
+        input::{SeatHandler, SeatState},
+        testing::{TestClient, TestCompositor},
+        wayland::selection::{request_selection, TransferError, TransferOptions},
+    };
+
+    struct TestState {
+        seat_state: SeatState<TestState>,
+        data_control_state: State<TestState>,
+    }
+
+    impl SeatHandler for TestState {
+        type KeyboardFocus = WlSurface;
+        type PointerFocus = WlSurface;
+
+        fn seat_state(&mut self) -> &mut SeatState<Self> {
+            &mut self.seat_state
+        }
+    }
+
+    impl Handler for TestState {
+        fn data_control_state(&self) -> &State<Self> {
+            &self.data_control_state
+        }
+
+        fn send_selection(&mut self, _mime_type: String, fd: OwnedFd) {
+            std::fs::File::from(fd).write_all(b"compositor").unwrap();
+        }
+    }
+
+    impl wayland_server::Dispatch<WlSeat, ()> for TestState {
+        fn request(
+            _state: &mut Self,
+            _client: &wayland_server::Client,
+            _resource: &WlSeat,
+            _request: wayland_server::protocol::wl_seat::Request,
+            _data: &(),
+            _dhandle: &wayland_server::DisplayHandle,
+            _data_init: &mut wayland_server::DataInit<'_, Self>,
+        ) {
+        }
+    }
+
+    wayland_server::delegate_global_dispatch!(TestState: [Manager: ()] => State<TestState>);
+    wayland_server::delegate_dispatch!(TestState: [Manager: ()] => State<TestState>);
+    wayland_server::delegate_dispatch!(TestState: [Device: device::Data] => State<TestState>);
+    wayland_server::delegate_dispatch!(TestState: [Source: source::Data] => State<TestState>);
+    wayland_server::delegate_global_dispatch!(TestState: [ExtManager: ()] => State<TestState>);
+    wayland_server::delegate_dispatch!(TestState: [ExtManager: ()] => State<TestState>);
+    wayland_server::delegate_dispatch!(TestState: [ExtDevice: device::Data] => State<TestState>);
+    wayland_server::delegate_dispatch!(TestState: [ExtSource: source::Data] => State<TestState>);
+
+    fn new_compositor() -> TestCompositor<TestState> {
+        TestCompositor::new(|dh, _| TestState {
+            seat_state: SeatState::new(),
+            data_control_state: State::new::<TestState>(dh),
+        })
+    }
+
+    /// Connect a client and create `count` data devices for it
+    fn connect(compositor: &mut TestCompositor<TestState>, count: usize) -> (TestClient, Vec<Device>) {
+        let dh = compositor.handle();
+        let client = compositor.connect();
+        let wl_seat = client
+            .client()
+            .create_resource::<WlSeat, (), TestState>(&dh, 1, ())
+            .unwrap();
+        let devices = (0..count)
+            .map(|_| {
+                client
+                    .client()
+                    .create_resource::<Device, _, TestState>(
+                        &dh,
+                        1,
+                        device::Data {
+                            wl_seat: wl_seat.clone(),
+                            alive_tracker: Default::default(),
+                            detached: Default::default(),
+                        },
+                    )
+                    .unwrap()
+            })
+            .collect();
+        (client, devices)
+    }
+
+    /// Disconnect a client without destroying its objects
+    fn disconnect(compositor: &mut TestCompositor<TestState>, client: TestClient) {
+        drop(client);
+        compositor.advance();
+    }
+
+    #[test]
+    fn devices_of_disconnected_client_are_pruned() {
+        let mut compositor = new_compositor();
+        let dh = compositor.handle();
+
+        let (test_client, devices) = connect(&mut compositor, 10);
+        let client = test_client.client().clone();
+        let mut seat_data = SeatData::new();
+        for device in devices {
+            seat_data.add_device(device);
+        }
+        assert_eq!(seat_data.device_count(&dh, &client), 10);
+
+        disconnect(&mut compositor, test_client);
+
+        seat_data.set_focus::<TestState>(&dh, Some(client));
+        assert!(seat_data.known_devices.is_empty());
+    }
+
+    #[test]
+    fn device_count_follows_devices() {
+        let mut compositor = new_compositor();
+        let seat = compositor.state.seat_state.new_seat("seat");
+        seat.user_data()
+            .insert_if_missing(|| RefCell::new(SeatData::new()));
+        assert_eq!(compositor.state.data_control_state.device_count(&seat), 0);
+
+        let (_first, first_devices) = connect(&mut compositor, 2);
+        let (second, second_devices) = connect(&mut compositor, 3);
+        let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
+        for device in first_devices.into_iter().chain(second_devices) {
+            seat_data.borrow_mut().add_device(device);
+        }
+        assert_eq!(compositor.state.data_control_state.device_count(&seat), 5);
+
+        disconnect(&mut compositor, second);
+        assert_eq!(compositor.state.data_control_state.device_count(&seat), 2);
+    }
+
+    #[test]
+    fn compositor_selection_can_be_requested() {
+        let mut compositor = new_compositor();
+        let seat = compositor.state.seat_state.new_seat("seat");
+
+        let err = request_data_control_selection(
+            &mut compositor.state,
+            &seat,
+            "text/plain".into(),
+            TransferOptions::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, TransferError::NoSelection));
+
+        set_primary_selection(&compositor.handle(), &seat, vec!["text/plain".into()]);
+        let err = request_data_control_selection(
+            &mut compositor.state,
+            &seat,
+            "image/png".into(),
+            TransferOptions::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, TransferError::InvalidMimetype));
+
+        // the shared entry point reads the same selection
+        let transfer = request_selection::<State<TestState>, _>(
+            &mut compositor.state,
+            &seat,
+            "text/plain".into(),
+            TransferOptions::default(),
+        )
+        .unwrap();
+        let mut event_loop = calloop::EventLoop::<Vec<Vec<u8>>>::try_new().unwrap();
+        event_loop
+            .handle()
+            .insert_source(transfer, |result, _, received| received.push(result.unwrap()))
+            .unwrap();
+        let mut received = Vec::new();
+        event_loop.dispatch(Some(Duration::ZERO), &mut received).unwrap();
+        assert_eq!(received, vec![b"compositor".to_vec()]);
+    }
+}
+
+#[cfg(all(test, feature = "test_utils"))]
+mod client_tests {
+    use std::{
+        cell::RefCell,
+        fs::File,
+        io::{Read, Write},
+        os::unix::{io::AsRawFd, net::UnixStream},
+        time::Duration,
+    };
+
+    use wayland_client::protocol::wl_seat::WlSeat as ClientSeat;
+    use wayland_protocols_wlr::data_control::v1::client::{
+        zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1},
+        zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+        zwlr_data_control_offer_v1::ZwlrDataControlOfferV1,
+        zwlr_data_control_source_v1::{self, ZwlrDataControlSourceV1},
+    };
+    use wayland_server::{
+        protocol::wl_surface::{self, WlSurface},
+        Client, DataInit, Dispatch, DisplayHandle,
+    };
+
+    use super::{
+        detach_seat,
+        protocol::client::{
+            ext_data_control_device_v1, ext_data_control_manager_v1::ExtDataControlManagerV1,
+        },
+        set_primary_focus, DataSource, Handler, Protocol, SeatData, SelectionTarget,
+        State as DataControlState,
+    };
+    use crate::{
+        input::{keyboard::XkbConfig, Seat, SeatHandler, SeatState},
+        testing::{Recorded, TestClient, TestCompositor},
+        utils::SERIAL_COUNTER,
+        wayland::seat::SurfaceOrInternal,
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    struct State {
+        seat_state: SeatState<State>,
+        seat: Seat<State>,
+        data_control_state: DataControlState<State>,
+        selections: Vec<(Option<DataSource>, Protocol)>,
+        primary_selections: Vec<(Option<DataSource>, Protocol)>,
+    }
+
+    impl SeatHandler for State {
+        // any keyboard target can be used as internal element, even a client surface
+        type KeyboardFocus = SurfaceOrInternal<WlSurface>;
+        type PointerFocus = WlSurface;
+
+        fn seat_state(&mut self) -> &mut SeatState<Self> {
+            &mut self.seat_state
+        }
+    }
+
+    impl Handler for State {
+        fn data_control_state(&self) -> &DataControlState<Self> {
+            &self.data_control_state
+        }
+
+        fn new_data_control_selection(
+            &mut self,
+            target: SelectionTarget,
+            source: Option<DataSource>,
+            protocol: Protocol,
+        ) {
+            match target {
+                SelectionTarget::Clipboard => self.selections.push((source, protocol)),
+                SelectionTarget::Primary => self.primary_selections.push((source, protocol)),
+            }
+        }
+    }
+
+    // surfaces are only used as keyboard focus, without a compositor global
+    impl Dispatch<WlSurface, ()> for State {
+        fn request(
+            _state: &mut Self,
+            _client: &Client,
+            _resource: &WlSurface,
+            _request: wl_surface::Request,
+            _data: &(),
+            _dhandle: &DisplayHandle,
+            _data_init: &mut DataInit<'_, Self>,
+        ) {
+        }
+    }
+
+    crate::delegate_seat!(State);
+    wayland_server::delegate_global_dispatch!(State: [super::Manager: ()] => DataControlState<State>);
+    wayland_server::delegate_dispatch!(State: [super::Manager: ()] => DataControlState<State>);
+    wayland_server::delegate_dispatch!(State: [super::Device: super::device::Data] => DataControlState<State>);
+    wayland_server::delegate_dispatch!(State: [super::Source: super::source::Data] => DataControlState<State>);
+    wayland_server::delegate_global_dispatch!(State: [super::ExtManager: ()] => DataControlState<State>);
+    wayland_server::delegate_dispatch!(State: [super::ExtManager: ()] => DataControlState<State>);
+    wayland_server::delegate_dispatch!(State: [super::ExtDevice: super::device::Data] => DataControlState<State>);
+    wayland_server::delegate_dispatch!(State: [super::ExtSource: super::source::Data] => DataControlState<State>);
+
+    fn new_compositor() -> TestCompositor<State> {
+        compositor_with(DataControlState::new_with_ext)
+    }
+
+    fn compositor_with(new_state: fn(&DisplayHandle) -> DataControlState<State>) -> TestCompositor<State> {
+        TestCompositor::new(|dh, _| {
+            let mut seat_state = SeatState::new();
+            let mut seat = seat_state.new_wl_seat(dh, "seat");
+            seat.add_keyboard(XkbConfig::default(), 200, 25).unwrap();
+            State {
+                seat_state,
+                seat,
+                data_control_state: new_state(dh),
+                selections: Vec::new(),
+                primary_selections: Vec::new(),
+            }
+        })
+    }
+
+    /// Connect a client and create a data device for it
+    fn connect(
+        compositor: &mut TestCompositor<State>,
+    ) -> (TestClient, ZwlrDataControlManagerV1, ZwlrDataControlDeviceV1) {
+        let mut client = compositor.connect();
+        let seat = client.bind::<ClientSeat, _>(compositor);
+        let manager = client.bind::<ZwlrDataControlManagerV1, _>(compositor);
+        let device = manager.get_data_device(&seat, &client.queue_handle(), Recorded);
+        client.roundtrip(compositor);
+        (client, manager, device)
+    }
+
+    /// Give the keyboard focus to a surface of the client
+    fn focus_keyboard(compositor: &mut TestCompositor<State>, client: &TestClient) {
+        let surface = client
+            .client()
+            .create_resource::<WlSurface, (), State>(&compositor.handle(), 1, ())
+            .unwrap();
+        let keyboard = compositor.state.seat.get_keyboard().unwrap();
+        keyboard.set_focus(
+            &mut compositor.state,
+            Some(SurfaceOrInternal::Surface(surface)),
+            SERIAL_COUNTER.next_serial(),
+        );
+    }
+
+    /// Wait for a selection offer, skipping empty selections
+    fn next_selection(
+        client: &mut TestClient,
+        compositor: &mut TestCompositor<State>,
+    ) -> ZwlrDataControlOfferV1 {
+        loop {
+            match client.wait_for_event::<zwlr_data_control_device_v1::Event, _>(compositor, TIMEOUT) {
+                Some(zwlr_data_control_device_v1::Event::Selection { id: Some(offer) }) => return offer,
+                Some(_) => continue,
+                None => panic!("no selection received"),
+            }
+        }
+    }
+
+    #[test]
+    fn selection_round_trips_to_other_device() {
+        let mut compositor = new_compositor();
+        let (mut owner, owner_manager, owner_device) = connect(&mut compositor);
+        let (mut receiver, _receiver_manager, _receiver_device) = connect(&mut compositor);
+
+        focus_keyboard(&mut compositor, &owner);
+        let dh = compositor.handle();
+        set_primary_focus(&dh, &compositor.state.seat, Some(receiver.client().clone()));
+
+        // the owner sets a selection from its own device
+        let source = owner_manager.create_data_source(&owner.queue_handle(), Recorded);
+        source.offer("text/plain".into());
+        owner_device.set_selection(Some(&source));
+        owner.roundtrip(&mut compositor);
+        assert_eq!(compositor.state.selections.len(), 1);
+        assert!(compositor.state.selections[0].0.is_some());
+        assert_eq!(compositor.state.selections[0].1, Protocol::Wlr);
+
+        // the receiver is offered the selection and requests its content
+        let offer = next_selection(&mut receiver, &mut compositor);
+        let (mut read, write) = UnixStream::pair().unwrap();
+        offer.receive("text/plain".into(), write.as_raw_fd());
+        receiver.roundtrip(&mut compositor);
+        drop(write);
+
+        // the owner is asked to write it
+        match owner.wait_for_event::<zwlr_data_control_source_v1::Event, _>(&mut compositor, TIMEOUT) {
+            Some(zwlr_data_control_source_v1::Event::Send { mime_type, fd }) => {
+                assert_eq!(mime_type, "text/plain");
+                File::from(fd).write_all(b"hello").unwrap();
+            }
+            event => panic!("unexpected source event: {:?}", event),
+        }
+
+        let mut content = Vec::new();
+        read.read_to_end(&mut content).unwrap();
+        assert_eq!(content, b"hello");
+    }
+
+    #[test]
+    fn selection_of_unfocused_client_is_denied() {
+        let mut compositor = new_compositor();
+        let (mut owner, owner_manager, device) = connect(&mut compositor);
+
+        let source = owner_manager.create_data_source(&owner.queue_handle(), Recorded);
+        source.offer("text/plain".into());
+        device.set_selection(Some(&source));
+        owner.roundtrip(&mut compositor);
+
+        assert!(compositor.state.selections.is_empty());
+    }
+
+    #[test]
+    fn selection_is_denied_while_internal_element_is_focused() {
+        let mut compositor = new_compositor();
+        let (mut owner, owner_manager, device) = connect(&mut compositor);
+
+        // the element wraps a surface of the client, which must not count as client focus
+        let surface = owner
+            .client()
+            .create_resource::<WlSurface, (), State>(&compositor.handle(), 1, ())
+            .unwrap();
+        let keyboard = compositor.state.seat.get_keyboard().unwrap();
+        keyboard.set_focus(
+            &mut compositor.state,
+            Some(SurfaceOrInternal::Internal(surface)),
+            SERIAL_COUNTER.next_serial(),
+        );
+
+        let source = owner_manager.create_data_source(&owner.queue_handle(), Recorded);
+        source.offer("text/plain".into());
+        device.set_selection(Some(&source));
+        let primary_source = owner_manager.create_data_source(&owner.queue_handle(), Recorded);
+        primary_source.offer("text/plain".into());
+        device.set_primary_selection(Some(&primary_source));
+        owner.roundtrip(&mut compositor);
+
+        assert!(compositor.state.selections.is_empty());
+        assert!(compositor.state.primary_selections.is_empty());
+    }
+
+    #[test]
+    fn detached_device_is_finished() {
+        let mut compositor = new_compositor();
+        let (mut owner, owner_manager, device) = connect(&mut compositor);
+        focus_keyboard(&mut compositor, &owner);
+
+        let source = owner_manager.create_data_source(&owner.queue_handle(), Recorded);
+        source.offer("text/plain".into());
+        device.set_selection(Some(&source));
+        owner.roundtrip(&mut compositor);
+        assert_eq!(compositor.state.selections.len(), 1);
+
+        // removing the seat finishes the device and cancels its selection
+        detach_seat(&compositor.state.seat);
+        assert!(matches!(
+            owner.wait_for_event::<zwlr_data_control_device_v1::Event, _>(&mut compositor, TIMEOUT),
+            Some(zwlr_data_control_device_v1::Event::Finished)
+        ));
+        assert!(matches!(
+            owner.wait_for_event::<zwlr_data_control_source_v1::Event, _>(&mut compositor, TIMEOUT),
+            Some(zwlr_data_control_source_v1::Event::Cancelled)
+        ));
+
+        // requests on the device are ignored from now on
+        let source = owner_manager.create_data_source(&owner.queue_handle(), Recorded);
+        device.set_selection(Some(&source));
+        assert!(matches!(
+            owner.wait_for_event::<zwlr_data_control_source_v1::Event, _>(&mut compositor, TIMEOUT),
+            Some(zwlr_data_control_source_v1::Event::Cancelled)
+        ));
+        assert_eq!(compositor.state.selections.len(), 1);
+        assert_eq!(
+            compositor
+                .state
+                .data_control_state
+                .device_count(&compositor.state.seat),
+            0
+        );
+    }
+
+    #[test]
+    fn device_outlives_released_seat() {
+        let mut compositor = new_compositor();
+        let mut client = compositor.connect();
+        let seat = client.bind::<ClientSeat, _>(&mut compositor);
+        let manager = client.bind::<ZwlrDataControlManagerV1, _>(&mut compositor);
+        let device = manager.get_data_device(&seat, &client.queue_handle(), Recorded);
+        focus_keyboard(&mut compositor, &client);
+
+        // releasing the wl_seat proxy does not retire the device
+        seat.release();
+        let source = manager.create_data_source(&client.queue_handle(), Recorded);
+        source.offer("text/plain".into());
+        device.set_selection(Some(&source));
+        client.roundtrip(&mut compositor);
+        assert_eq!(compositor.state.selections.len(), 1);
+        assert!(!matches!(
+            client.wait_for_event::<zwlr_data_control_device_v1::Event, _>(&mut compositor, Duration::ZERO),
+            Some(zwlr_data_control_device_v1::Event::Finished)
+        ));
+    }
+
+    #[test]
+    fn selection_is_shared_between_protocols() {
+        let mut compositor = new_compositor();
+        let (mut owner, owner_manager, owner_device) = connect(&mut compositor);
+
+        let mut receiver = compositor.connect();
+        let seat = receiver.bind::<ClientSeat, _>(&mut compositor);
+        let manager = receiver.bind::<ExtDataControlManagerV1, _>(&mut compositor);
+        let receiver_device = manager.get_data_device(&seat, &receiver.queue_handle(), Recorded);
+        receiver.roundtrip(&mut compositor);
+
+        focus_keyboard(&mut compositor, &owner);
+        let dh = compositor.handle();
+        set_primary_focus(&dh, &compositor.state.seat, Some(receiver.client().clone()));
+
+        // a selection set through the wlr protocol
+        let source = owner_manager.create_data_source(&owner.queue_handle(), Recorded);
+        source.offer("text/plain".into());
+        owner_device.set_selection(Some(&source));
+        owner.roundtrip(&mut compositor);
+
+        // is offered to the device of the ext protocol
+        let offer = loop {
+            match receiver.wait_for_event::<ext_data_control_device_v1::Event, _>(&mut compositor, TIMEOUT) {
+                Some(ext_data_control_device_v1::Event::Selection { id: Some(offer) }) => break offer,
+                Some(_) => continue,
+                None => panic!("no selection received"),
+            }
+        };
+        let (mut read, write) = UnixStream::pair().unwrap();
+        offer.receive("text/plain".into(), write.as_raw_fd());
+        receiver.roundtrip(&mut compositor);
+        drop(write);
+
+        match owner.wait_for_event::<zwlr_data_control_source_v1::Event, _>(&mut compositor, TIMEOUT) {
+            Some(zwlr_data_control_source_v1::Event::Send { mime_type, fd }) => {
+                assert_eq!(mime_type, "text/plain");
+                File::from(fd).write_all(b"hello").unwrap();
+            }
+            event => panic!("unexpected source event: {:?}", event),
+        }
+        let mut content = Vec::new();
+        read.read_to_end(&mut content).unwrap();
+        assert_eq!(content, b"hello");
+
+        // the ext client takes over the selection, the wlr source is cancelled
+        focus_keyboard(&mut compositor, &receiver);
+        let ext_source = manager.create_data_source(&receiver.queue_handle(), Recorded);
+        ext_source.offer("text/html".into());
+        receiver_device.set_selection(Some(&ext_source));
+        receiver.roundtrip(&mut compositor);
+        assert_eq!(compositor.state.selections.len(), 2);
+        assert_eq!(compositor.state.selections[1].1, Protocol::Ext);
+        assert!(matches!(
+            compositor.state.selections[1].0,
+            Some(DataSource::Ext(_))
+        ));
+        assert!(matches!(
+            owner.wait_for_event::<zwlr_data_control_source_v1::Event, _>(&mut compositor, TIMEOUT),
+            Some(zwlr_data_control_source_v1::Event::Cancelled)
+        ));
+    }
+
+    #[test]
+    fn ext_global_is_opt_in() {
+        let mut compositor = compositor_with(DataControlState::new);
+        let mut client = compositor.connect();
+        client.roundtrip(&mut compositor);
+        assert!(client.has_global("zwlr_data_control_manager_v1"));
+        assert!(!client.has_global("ext_data_control_manager_v1"));
+        assert!(compositor.state.data_control_state.ext_global().is_none());
+    }
+
+    #[test]
+    fn primary_selection_is_shared_between_protocols() {
+        let mut compositor = new_compositor();
+        let (mut owner, owner_manager, owner_device) = connect(&mut compositor);
+
+        let mut receiver = compositor.connect();
+        let seat = receiver.bind::<ClientSeat, _>(&mut compositor);
+        let manager = receiver.bind::<ExtDataControlManagerV1, _>(&mut compositor);
+        let _receiver_device = manager.get_data_device(&seat, &receiver.queue_handle(), Recorded);
+        receiver.roundtrip(&mut compositor);
+
+        focus_keyboard(&mut compositor, &owner);
+        let dh = compositor.handle();
+        set_primary_focus(&dh, &compositor.state.seat, Some(receiver.client().clone()));
+
+        // a primary selection set through the wlr protocol
+        let source = owner_manager.create_data_source(&owner.queue_handle(), Recorded);
+        source.offer("text/plain".into());
+        owner_device.set_primary_selection(Some(&source));
+        owner.roundtrip(&mut compositor);
+        assert!(compositor.state.selections.is_empty());
+        assert_eq!(compositor.state.primary_selections.len(), 1);
+        assert_eq!(compositor.state.primary_selections[0].1, Protocol::Wlr);
+
+        // is offered as the primary selection to the device of the ext protocol
+        let offer = loop {
+            match receiver.wait_for_event::<ext_data_control_device_v1::Event, _>(&mut compositor, TIMEOUT) {
+                Some(ext_data_control_device_v1::Event::PrimarySelection { id: Some(offer) }) => break offer,
+                Some(ext_data_control_device_v1::Event::Selection { id: Some(_) }) => {
+                    panic!("primary selection offered as the clipboard selection")
+                }
+                Some(_) => continue,
+                None => panic!("no primary selection received"),
+            }
+        };
+        let (mut read, write) = UnixStream::pair().unwrap();
+        offer.receive("text/plain".into(), write.as_raw_fd());
+        receiver.roundtrip(&mut compositor);
+        drop(write);
+
+        match owner.wait_for_event::<zwlr_data_control_source_v1::Event, _>(&mut compositor, TIMEOUT) {
+            Some(zwlr_data_control_source_v1::Event::Send { mime_type, fd }) => {
+                assert_eq!(mime_type, "text/plain");
+                File::from(fd).write_all(b"primary").unwrap();
+            }
+            event => panic!("unexpected source event: {:?}", event),
+        }
+        let mut content = Vec::new();
+        read.read_to_end(&mut content).unwrap();
+        assert_eq!(content, b"primary");
+
+        // clearing the primary selection cancels the source
+        owner_device.set_primary_selection(None);
+        owner.roundtrip(&mut compositor);
+        assert!(matches!(
+            owner.wait_for_event::<zwlr_data_control_source_v1::Event, _>(&mut compositor, TIMEOUT),
+            Some(zwlr_data_control_source_v1::Event::Cancelled)
+        ));
+    }
+
+    /// Number of devices tracked by the seat, including dead ones not pruned yet
+    fn known_devices(compositor: &TestCompositor<State>) -> usize {
+        compositor
+            .state
+            .seat
+            .user_data()
+            .get::<RefCell<SeatData>>()
+            .unwrap()
+            .borrow()
+            .known_devices
+            .len()
+    }
+
+    #[test]
+    fn devices_beyond_limit_are_finished() {
+        let mut compositor = new_compositor();
+        compositor.state.data_control_state.set_max_devices_per_client(1);
+        let (mut client, manager, _device) = connect(&mut compositor);
+        assert!(client
+            .wait_for_event::<zwlr_data_control_device_v1::Event, _>(&mut compositor, Duration::ZERO)
+            .is_none());
+
+        let seat = client.bind::<ClientSeat, _>(&mut compositor);
+        let _second = manager.get_data_device(&seat, &client.queue_handle(), Recorded);
+        assert!(matches!(
+            client.wait_for_event::<zwlr_data_control_device_v1::Event, _>(&mut compositor, TIMEOUT),
+            Some(zwlr_data_control_device_v1::Event::Finished)
+        ));
+        assert!(client
+            .wait_for_protocol_error(&mut compositor, Duration::ZERO)
+            .is_none());
+        assert_eq!(
+            compositor
+                .state
+                .data_control_state
+                .device_count(&compositor.state.seat),
+            1
+        );
+    }
+
+    #[test]
+    fn devices_of_disconnected_client_are_pruned_on_destruction() {
+        let mut compositor = new_compositor();
+        let (_first, _first_manager, _first_device) = connect(&mut compositor);
+        let (second, _second_manager, _second_device) = connect(&mut compositor);
+        assert_eq!(known_devices(&compositor), 2);
+
+        // the client goes away without destroying its device
+        drop(second);
+        compositor.advance();
+        assert_eq!(known_devices(&compositor), 1);
+    }
+}
@@ -1 +1,31 @@
+pub use super::protocol::server::ext_data_control_offer_v1::ExtDataControlOfferV1 as ExtOffer;
 pub use super::server::zwlr_data_control_offer_v1::{Request, ZwlrDataControlOfferV1 as Offer};
+
+use std::os::unix::io::OwnedFd;
+
+use wayland_server::{
+    backend::{protocol::Message, ObjectId},
+    DisplayHandle, Resource,
+};
+
+use super::{protocol::server::ext_data_control_offer_v1, Protocol};
+
+/// Parse a request to an offer of the given protocol, returning the mime type and fd of a `receive`
+///
+/// Selection offers only care about `receive`, everything else yields `None`.
+pub(super) fn parse_receive(
+    dh: &DisplayHandle,
+    protocol: Protocol,
+    msg: Message<ObjectId, OwnedFd>,
+) -> Option<(String, OwnedFd)> {
+    match protocol {
+        Protocol::Wlr => match Offer::parse_request(dh, msg) {
+            Ok((_, Request::Receive { mime_type, fd })) => Some((mime_type, fd)),
+            _ => None,
+        },
+        Protocol::Ext => match ExtOffer::parse_request(dh, msg) {
+            Ok((_, ext_data_control_offer_v1::Request::Receive { mime_type, fd })) => Some((mime_type, fd)),
+            _ => None,
+        },
+    }
+}
@@ -1,6 +1,7 @@
+pub use super::protocol::server::ext_data_control_source_v1::ExtDataControlSourceV1 as ExtSource;
 pub use super::server::zwlr_data_control_source_v1::{Request, ZwlrDataControlSourceV1 as Source};
 
-use std::sync::Mutex;
+use std::{collections::HashMap, os::unix::io::RawFd, sync::Mutex};
 
 use wayland_server::{
     backend::{ClientId, ObjectId},
@@ -12,13 +13,23 @@ use crate::{
     utils::{alive_tracker::AliveTracker, IsAlive},
 };
 
-use super::{Handler, State};
+use super::{protocol::server::ext_data_control_source_v1, Handler, Protocol, State};
 
 /// The metadata describing a data source
 #[derive(Debug, Default, Clone)]
 pub struct Metadata {
     /// The MIME types supported by this source
     pub mime_types: Vec<String>,
+    /// How many times each MIME type was requested from this source
+    ///
+    /// Only MIME types that were requested at least once are present.
+    pub send_counts: HashMap<String, u64>,
+}
+
+impl Metadata {
+    fn record_send(&mut self, mime_type: &str) {
+        *self.send_counts.entry(mime_type.to_owned()).or_default() += 1;
+    }
 }
 
 #[doc(hidden)]
@@ -35,6 +46,10 @@ impl Data {
             alive_tracker: Default::default(),
         }
     }
+
+    fn clone_metadata(&self) -> Metadata {
+        self.inner.lock().unwrap().clone()
+    }
 }
 
 impl<D> Dispatch<Source, Data, D> for State<D>
@@ -69,6 +84,37 @@ where
     }
 }
 
+impl<D> Dispatch<ExtSource, Data, D> for State<D>
+where
+    D: SeatHandler + Dispatch<ExtSource, Data>,
+    D: Handler,
+    D: 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &wayland_server::Client,
+        _resource: &ExtSource,
+        request: ext_data_control_source_v1::Request,
+        data: &Data,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut wayland_server::DataInit<'_, D>,
+    ) {
+        let mut data = data.inner.lock().unwrap();
+
+        match request {
+            ext_data_control_source_v1::Request::Offer { mime_type } => {
+                data.mime_types.push(mime_type);
+            }
+            ext_data_control_source_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(_state: &mut D, _client: ClientId, _resource: ObjectId, data: &Data) {
+        data.alive_tracker.destroy_notify();
+    }
+}
+
 impl IsAlive for Source {
     fn alive(&self) -> bool {
         let data: &Data = self.data().unwrap();
@@ -76,9 +122,93 @@ impl IsAlive for Source {
     }
 }
 
+impl IsAlive for ExtSource {
+    fn alive(&self) -> bool {
+        let data: &Data = self.data().unwrap();
+        data.alive_tracker.alive()
+    }
+}
+
+/// A data source created through either of the data control protocols
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DataSource {
+    /// Source of the `wlr-data-control-unstable-v1` protocol
+    Wlr(Source),
+    /// Source of the `ext-data-control-v1` protocol
+    Ext(ExtSource),
+}
+
+impl DataSource {
+    /// The protocol this source was created with
+    pub fn protocol(&self) -> Protocol {
+        match self {
+            DataSource::Wlr(_) => Protocol::Wlr,
+            DataSource::Ext(_) => Protocol::Ext,
+        }
+    }
+
+    /// Access the metadata of this source, see [`with_source_metadata`]
+    pub fn with_metadata<T, F: FnOnce(&Metadata) -> T>(
+        &self,
+        f: F,
+    ) -> Result<T, crate::utils::UnmanagedResource> {
+        match self {
+            DataSource::Wlr(source) => with_source_metadata(source, f),
+            DataSource::Ext(source) => with_source_metadata(source, f),
+        }
+    }
+
+    /// Get a copy of the metadata of this source, see [`clone_source_metadata`]
+    pub fn clone_metadata(&self) -> Result<Metadata, crate::utils::UnmanagedResource> {
+        match self {
+            DataSource::Wlr(source) => clone_source_metadata(source),
+            DataSource::Ext(source) => clone_source_metadata(source),
+        }
+    }
+
+    pub(super) fn cancelled(&self) {
+        match self {
+            DataSource::Wlr(source) => source.cancelled(),
+            DataSource::Ext(source) => source.cancelled(),
+        }
+    }
+
+    fn data(&self) -> Option<&Data> {
+        match self {
+            DataSource::Wlr(source) => source.data::<Data>(),
+            DataSource::Ext(source) => source.data::<Data>(),
+        }
+    }
+}
+
+impl IsAlive for DataSource {
+    fn alive(&self) -> bool {
+        match self {
+            DataSource::Wlr(source) => source.alive(),
+            DataSource::Ext(source) => source.alive(),
+        }
+    }
+}
+
+impl From<Source> for DataSource {
+    fn from(source: Source) -> Self {
+        DataSource::Wlr(source)
+    }
+}
+
+impl From<ExtSource> for DataSource {
+    fn from(source: ExtSource) -> Self {
+        DataSource::Ext(source)
+    }
+}
+
 /// Access the metadata of a data source
-pub fn with_source_metadata<T, F: FnOnce(&Metadata) -> T>(
-    source: &Source,
+///
+/// Works for sources of both protocols, i.e. [`Source`] and [`ExtSource`]. The closure is invoked while the metadata of the source is locked. It must not call back
+/// into other data_control functions accessing the same source, as this will deadlock.
+/// Use [`clone_source_metadata`] if the closure needs to do so.
+pub fn with_source_metadata<S: Resource, T, F: FnOnce(&Metadata) -> T>(
+    source: &S,
     f: F,
 ) -> Result<T, crate::utils::UnmanagedResource> {
     match source.data::<Data>() {
@@ -86,3 +216,62 @@ pub fn with_source_metadata<T, F: FnOnce(&Metadata) -> T>(
         None => Err(crate::utils::UnmanagedResource),
     }
 }
+
+/// Get a copy of the metadata of a data source
+///
+/// Unlike [`with_source_metadata`] the lock on the metadata is released before returning,
+/// so no user code runs while it is held.
+pub fn clone_source_metadata<S: Resource>(source: &S) -> Result<Metadata, crate::utils::UnmanagedResource> {
+    match source.data::<Data>() {
+        Some(data) => Ok(data.clone_metadata()),
+        None => Err(crate::utils::UnmanagedResource),
+    }
+}
+
+/// Ask the source to send its contents for `mime_type` and account for it in its [`Metadata`]
+pub(super) fn send(source: &DataSource, mime_type: String, fd: RawFd) {
+    if let Some(data) = source.data() {
+        data.inner.lock().unwrap().record_send(&mime_type);
+    }
+    match source {
+        DataSource::Wlr(source) => source.send(mime_type, fd),
+        DataSource::Ext(source) => source.send(mime_type, fd),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Data, Metadata};
+
+    #[test]
+    fn send_counts_increment_per_send() {
+        let mut meta = Metadata {
+            mime_types: vec!["text/plain".into(), "text/html".into()],
+            ..Default::default()
+        };
+        assert!(meta.send_counts.is_empty());
+
+        meta.record_send("text/plain");
+        meta.record_send("text/plain");
+        meta.record_send("text/html");
+
+        assert_eq!(meta.send_counts.get("text/plain"), Some(&2));
+        assert_eq!(meta.send_counts.get("text/html"), Some(&1));
+        assert_eq!(meta.send_counts.get("image/png"), None);
+    }
+
+    #[test]
+    fn cloned_metadata_allows_reentrant_access() {
+        let data = Data::new();
+        data.inner.lock().unwrap().mime_types.push("text/plain".into());
+
+        let meta = data.clone_metadata();
+        for mime_type in &meta.mime_types {
+            // user code re-entering while holding the copy must not deadlock
+            data.inner.lock().unwrap().record_send(mime_type);
+            assert_eq!(data.clone_metadata().send_counts.get(mime_type), Some(&1));
+        }
+        // the copy is a snapshot
+        assert!(meta.send_counts.is_empty());
+    }
+}
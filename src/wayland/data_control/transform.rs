@@ -0,0 +1,324 @@
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, Write},
+    os::unix::io::{AsRawFd, OwnedFd},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use nix::{
+    errno::Errno,
+    fcntl::{fcntl, FcntlArg, OFlag},
+    poll::{poll, PollFd, PollFlags},
+};
+use tracing::warn;
+
+use crate::wayland::selection::{self, ReadStatus};
+
+/// Transformation applied to the content of compositor-provided selections
+///
+/// It is called with the requested mime type and the content provided by the compositor
+/// and returns the content to send to the client.
+pub type ContentTransformer = dyn for<'a> Fn(&str, &'a [u8]) -> Cow<'a, [u8]> + Send + Sync;
+
+/// Default for [`State::max_concurrent_transforms`](super::State::max_concurrent_transforms)
+pub const DEFAULT_MAX_CONCURRENT_TRANSFORMS: usize = 4;
+
+/// Default for [`State::max_transform_size`](super::State::max_transform_size)
+pub const DEFAULT_MAX_TRANSFORM_SIZE: usize = 16 * 1024 * 1024;
+
+/// Default for [`State::transform_timeout`](super::State::transform_timeout)
+pub const DEFAULT_TRANSFORM_TIMEOUT: Duration = selection::DEFAULT_TRANSFER_TIMEOUT;
+
+/// Bounds on the transforms running at the same time
+#[derive(Debug, Clone)]
+pub(super) struct Limits {
+    running: Arc<AtomicUsize>,
+    pub(super) max_running: usize,
+    pub(super) max_size: usize,
+    pub(super) timeout: Duration,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            running: Arc::new(AtomicUsize::new(0)),
+            max_running: DEFAULT_MAX_CONCURRENT_TRANSFORMS,
+            max_size: DEFAULT_MAX_TRANSFORM_SIZE,
+            timeout: DEFAULT_TRANSFORM_TIMEOUT,
+        }
+    }
+}
+
+/// Releases a slot of [`Limits::running`] when the transform finished
+struct RunningGuard(Arc<AtomicUsize>);
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Error of [`spawn_transform`]
+#[derive(Debug, thiserror::Error)]
+pub(super) enum TransformError {
+    /// The maximum number of concurrent transforms is reached
+    #[error("Too many selection transforms are running")]
+    TooManyTransforms,
+    /// Creating the pipe or thread failed
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Read all of `input`, transform it and write the result into `output`
+///
+/// Fails without writing anything if `input` holds more than `max_size` bytes. Both ends are
+/// used without blocking, so neither a compositor that never closes `input` nor a client that
+/// never reads `output` keeps the transform running past `deadline`.
+fn transform(
+    transformer: &ContentTransformer,
+    mime_type: &str,
+    max_size: usize,
+    deadline: Instant,
+    mut input: File,
+    mut output: File,
+) -> io::Result<()> {
+    set_nonblocking(&input)?;
+    set_nonblocking(&output)?;
+
+    let mut content = Vec::new();
+    loop {
+        match selection::read_available(&mut input, &mut content, Some(max_size))? {
+            ReadStatus::Pending => wait(&input, PollFlags::POLLIN, deadline)?,
+            ReadStatus::Finished => break,
+            ReadStatus::TooLarge => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("selection content exceeds {} bytes", max_size),
+                ))
+            }
+        }
+    }
+
+    let content = transformer(mime_type, &content);
+    let mut written = 0;
+    while written < content.len() {
+        match output.write(&content[written..]) {
+            Ok(n) => written += n,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                wait(&output, PollFlags::POLLOUT, deadline)?
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+fn set_nonblocking(file: &File) -> io::Result<()> {
+    let flags = OFlag::from_bits_truncate(fcntl(file.as_raw_fd(), FcntlArg::F_GETFL)?);
+    fcntl(file.as_raw_fd(), FcntlArg::F_SETFL(flags | OFlag::O_NONBLOCK))?;
+    Ok(())
+}
+
+/// Wait for `events` on `file`, failing with [`io::ErrorKind::TimedOut`] once `deadline` passed
+fn wait(file: &File, events: PollFlags, deadline: Instant) -> io::Result<()> {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining.is_zero() {
+        return Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "selection transform timed out",
+        ));
+    }
+    // round up, so we don't spin for the last fraction of a millisecond
+    let timeout = ((remaining.as_micros() + 999) / 1000).min(i32::MAX as u128) as i32;
+    let mut fds = [PollFd::new(file.as_raw_fd(), events)];
+    match poll(&mut fds, timeout) {
+        Ok(_) | Err(Errno::EINTR) => Ok(()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Interpose a transformer between the compositor and the client reading the selection
+///
+/// Returns the fd the compositor should write the content into. The content is transformed
+/// and written into `fd` on a separate thread once the compositor closed the returned fd.
+/// At most [`Limits::max_running`] transforms run at the same time, each for at most
+/// [`Limits::timeout`].
+pub(super) fn spawn_transform(
+    transformer: Arc<ContentTransformer>,
+    limits: &Limits,
+    mime_type: String,
+    fd: OwnedFd,
+) -> Result<OwnedFd, TransformError> {
+    let reserved = limits
+        .running
+        .fetch_update(Ordering::AcqRel, Ordering::Acquire, |running| {
+            (running < limits.max_running).then_some(running + 1)
+        });
+    if reserved.is_err() {
+        return Err(TransformError::TooManyTransforms);
+    }
+    let guard = RunningGuard(limits.running.clone());
+
+    let (read, write) = selection::pipe(false)?;
+    let max_size = limits.max_size;
+    let deadline = Instant::now() + limits.timeout;
+    std::thread::Builder::new()
+        .name("smithay-selection-transform".into())
+        .spawn(move || {
+            let _guard = guard;
+            let output = File::from(fd);
+            if let Err(err) = transform(&*transformer, &mime_type, max_size, deadline, read, output) {
+                warn!(?err, mime_type, "Failed to transform selection content");
+            }
+        })?;
+    Ok(write)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        borrow::Cow,
+        fs::File,
+        io::{self, Read, Write},
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use super::{spawn_transform, transform, ContentTransformer, Limits, TransformError};
+    use crate::wayland::selection::pipe;
+
+    fn crlf_to_lf<'a>(mime_type: &str, content: &'a [u8]) -> Cow<'a, [u8]> {
+        if mime_type != "text/plain" || !content.contains(&b'\r') {
+            return Cow::Borrowed(content);
+        }
+        Cow::Owned(content.iter().copied().filter(|b| *b != b'\r').collect())
+    }
+
+    /// Transform `input` written by the compositor into a pipe read by the client
+    fn run(mime_type: &str, max_size: usize, input: &[u8]) -> io::Result<Vec<u8>> {
+        let (compositor_read, compositor_write) = pipe(false)?;
+        File::from(compositor_write).write_all(input)?;
+        let (mut client_read, client_write) = pipe(false)?;
+        let deadline = Instant::now() + Duration::from_secs(5);
+        transform(
+            &crlf_to_lf,
+            mime_type,
+            max_size,
+            deadline,
+            compositor_read,
+            File::from(client_write),
+        )?;
+        let mut output = Vec::new();
+        client_read.read_to_end(&mut output)?;
+        Ok(output)
+    }
+
+    fn wait_for_free_slot(transformer: &Arc<ContentTransformer>, limits: &Limits) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let (_, fd) = pipe(false).unwrap();
+            match spawn_transform(transformer.clone(), limits, "text/plain".into(), fd) {
+                Ok(_) => break,
+                Err(TransformError::TooManyTransforms) if Instant::now() < deadline => {
+                    std::thread::sleep(Duration::from_millis(1))
+                }
+                Err(err) => panic!("transform slot was not released: {}", err),
+            }
+        }
+    }
+
+    #[test]
+    fn text_plain_is_transformed() {
+        assert_eq!(run("text/plain", 64, b"a\r\nb\r\n").unwrap(), b"a\nb\n");
+        assert_eq!(run("text/html", 64, b"a\r\nb\r\n").unwrap(), b"a\r\nb\r\n");
+    }
+
+    #[test]
+    fn transform_through_pipe() {
+        let (mut client, client_fd) = pipe(false).unwrap();
+
+        let transformer: Arc<ContentTransformer> = Arc::new(crlf_to_lf);
+        let compositor_fd =
+            spawn_transform(transformer, &Limits::default(), "text/plain".into(), client_fd).unwrap();
+        File::from(compositor_fd).write_all(b"hello\r\nworld").unwrap();
+
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).unwrap();
+        assert_eq!(received, b"hello\nworld");
+    }
+
+    #[test]
+    fn oversized_content_is_dropped() {
+        assert!(run("text/plain", 4, b"a\r\nb\r\n").is_err());
+        assert_eq!(run("text/plain", 4, b"a\r\nb").unwrap(), b"a\nb");
+    }
+
+    #[test]
+    fn concurrent_transforms_are_capped() {
+        let limits = Limits {
+            max_running: 1,
+            ..Limits::default()
+        };
+        let transformer: Arc<ContentTransformer> = Arc::new(crlf_to_lf);
+
+        let (mut first_client, first_fd) = pipe(false).unwrap();
+        let first = spawn_transform(transformer.clone(), &limits, "text/plain".into(), first_fd).unwrap();
+
+        // the first transform waits for its content, so there is no slot for a second one
+        let (_second_client, second_fd) = pipe(false).unwrap();
+        assert!(matches!(
+            spawn_transform(transformer.clone(), &limits, "text/plain".into(), second_fd),
+            Err(TransformError::TooManyTransforms)
+        ));
+
+        File::from(first).write_all(b"done").unwrap();
+        let mut received = Vec::new();
+        first_client.read_to_end(&mut received).unwrap();
+        assert_eq!(received, b"done");
+
+        // the slot is released once the transform thread exits
+        wait_for_free_slot(&transformer, &limits);
+    }
+
+    #[test]
+    fn client_that_never_reads_times_out() {
+        let limits = Limits {
+            max_running: 1,
+            timeout: Duration::from_millis(50),
+            ..Limits::default()
+        };
+        let transformer: Arc<ContentTransformer> = Arc::new(crlf_to_lf);
+
+        // the client keeps its end open, but never reads from it
+        let (_client, client_fd) = pipe(false).unwrap();
+        let compositor_fd =
+            spawn_transform(transformer.clone(), &limits, "text/plain".into(), client_fd).unwrap();
+        // more than fits into the pipe buffer of the client
+        File::from(compositor_fd).write_all(&[b'a'; 1024 * 1024]).unwrap();
+
+        wait_for_free_slot(&transformer, &limits);
+    }
+
+    #[test]
+    fn compositor_that_never_writes_times_out() {
+        let limits = Limits {
+            max_running: 1,
+            timeout: Duration::from_millis(50),
+            ..Limits::default()
+        };
+        let transformer: Arc<ContentTransformer> = Arc::new(crlf_to_lf);
+
+        let (_client, client_fd) = pipe(false).unwrap();
+        // the compositor keeps its end open without writing anything
+        let _compositor_fd =
+            spawn_transform(transformer.clone(), &limits, "text/plain".into(), client_fd).unwrap();
+
+        wait_for_free_slot(&transformer, &limits);
+    }
+}
@@ -15,9 +15,15 @@
 //! the contents of the data device:
 //!
 //! - the freestanding function [`set_data_device_selection`]
-//!   allows you to set the contents of the selection for your clients
+//!   allows you to set the contents of the selection for your clients, [`set_data_device_lazy_selection`]
+//!   does the same with a callback producing the contents only once they are requested
 //! - the freestanding function [`start_dnd`] allows you to initiate a drag'n'drop event from the compositor
 //!   itself and receive interactions of clients with it via an other dedicated callback.
+//! - the freestanding function [`request_data_device_selection`] reads any selection, including your own,
+//!   with a timeout and size limit, see the [`selection`](crate::wayland::selection) module.
+//!   [`request_data_device_client_selection`] does the same, but only for client-provided selections.
+//! - the freestanding function [`read_selection_blocking`] reads the current selection synchronously,
+//!   for compositors that do not want to deal with asynchronous transfers.
 //!
 //! The module defines the role `"dnd_icon"` that is assigned to surfaces used as drag'n'drop icons.
 //!
@@ -63,7 +69,13 @@
 //! // You're now ready to go!
 //! ```
 
-use std::{cell::RefCell, os::unix::io::OwnedFd};
+use std::{
+    cell::RefCell,
+    io,
+    os::unix::io::OwnedFd,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use tracing::instrument;
 use wayland_server::{
@@ -73,7 +85,7 @@ use wayland_server::{
         wl_data_source::WlDataSource,
         wl_surface::WlSurface,
     },
-    Client, DisplayHandle, GlobalDispatch,
+    Client, Display, DisplayHandle, GlobalDispatch,
 };
 
 use crate::{
@@ -81,12 +93,19 @@ use crate::{
         pointer::{Focus, GrabStartData as PointerGrabStartData},
         Seat, SeatHandler,
     },
-    utils::Serial,
-    wayland::seat::WaylandFocus,
+    utils::{IsAlive, Logical, Point, Serial},
+    wayland::{
+        seat::WaylandFocus,
+        selection::{
+            self, start_transfer, SelectionOwner, SelectionProtocol, SelectionTransfer, TransferError,
+            TransferOptions,
+        },
+    },
 };
 
 mod device;
 mod dnd_grab;
+mod read;
 mod seat_data;
 mod server_dnd_grab;
 mod source;
@@ -94,7 +113,7 @@ mod source;
 pub use device::{DataDeviceUserData, DND_ICON_ROLE};
 pub use source::{with_source_metadata, DataSourceUserData, SourceMetadata};
 
-use seat_data::{SeatData, Selection};
+use seat_data::{SeatData, Selection, SelectionProvider};
 
 /// Events that are generated by interactions of the clients with the data device
 #[allow(unused_variables)]
@@ -143,6 +162,14 @@ pub trait ClientDndGrabHandler: SeatHandler + Sized {
 /// Event generated by the interactions of clients with a server initiated drag'n'drop
 #[allow(unused_variables)]
 pub trait ServerDndGrabHandler {
+    /// The pointer moved during the drag'n'drop
+    ///
+    /// As the source of a server initiated drag'n'drop has no client surface acting as
+    /// its icon, this can be used to position an icon rendered by the compositor itself.
+    ///
+    /// * `location` - The new location of the pointer in compositor space
+    fn motion(&mut self, location: Point<f64, Logical>) {}
+
     /// The client chose an action
     fn action(&mut self, action: DndAction) {}
 
@@ -249,11 +276,206 @@ where
     );
 }
 
+/// Set a compositor-provided selection with lazily produced contents for this seat
+///
+/// Like [`set_data_device_selection`], but instead of [`DataDeviceHandler::send_selection`]
+/// the given `provider` is called with the requested mime type and the file descriptor to
+/// write the contents to. It is only invoked once a client actually pastes the selection,
+/// so large or expensive contents, like rendered images, are never produced needlessly.
+#[instrument(name = "wayland_data_device", level = "debug", skip(dh, seat, provider), fields(seat = seat.name()))]
+pub fn set_data_device_lazy_selection<D, F>(
+    dh: &DisplayHandle,
+    seat: &Seat<D>,
+    mime_types: Vec<String>,
+    provider: F,
+) where
+    D: SeatHandler + DataDeviceHandler + 'static,
+    F: Fn(String, OwnedFd) + Send + Sync + 'static,
+{
+    seat.user_data()
+        .insert_if_missing(|| RefCell::new(SeatData::new()));
+    let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
+    seat_data.borrow_mut().set_selection::<D>(
+        dh,
+        Selection::Lazy(
+            SourceMetadata {
+                mime_types,
+                dnd_action: DndAction::empty(),
+            },
+            Arc::new(provider),
+        ),
+    );
+}
+
+/// Write the contents of a compositor-provided selection through its provider or the handler
+fn send_compositor_selection<D: DataDeviceHandler>(
+    state: &mut D,
+    provider: Option<SelectionProvider>,
+    mime_type: String,
+    fd: OwnedFd,
+) {
+    match provider {
+        Some(provider) => provider(mime_type, fd),
+        None => state.send_selection(mime_type, fd),
+    }
+}
+
+/// Look up the owner of the current selection of this seat
+fn selection_owner<D: SeatHandler + 'static>(
+    seat: &Seat<D>,
+) -> Option<SelectionOwner<WlDataSource, Option<SelectionProvider>>> {
+    let seat_data = seat.user_data().get::<RefCell<SeatData>>()?.borrow();
+    match seat_data.selection() {
+        Selection::Client(source) if source.alive() => Some(SelectionOwner::Client {
+            source: source.clone(),
+            mime_types: with_source_metadata(source, |meta| meta.mime_types.clone()).unwrap_or_default(),
+        }),
+        Selection::Compositor(meta) => Some(SelectionOwner::Compositor {
+            provider: None,
+            mime_types: meta.mime_types.clone(),
+        }),
+        Selection::Lazy(meta, provider) => Some(SelectionOwner::Compositor {
+            provider: Some(provider.clone()),
+            mime_types: meta.mime_types.clone(),
+        }),
+        _ => None,
+    }
+}
+
+/// Request the contents of the current client-provided selection of this seat
+///
+/// Works like [`request_data_device_selection`], but fails with [`TransferError::NoSelection`]
+/// for selections set by the compositor through [`set_data_device_selection`], as their contents
+/// are already known to you. This does not need access to your state and can thus be called
+/// from anywhere.
+#[instrument(name = "wayland_data_device", level = "debug", skip(seat), fields(seat = seat.name()))]
+pub fn request_data_device_client_selection<D>(
+    seat: &Seat<D>,
+    mime_type: String,
+    options: TransferOptions,
+) -> Result<SelectionTransfer, TransferError>
+where
+    D: SeatHandler + DataDeviceHandler + 'static,
+{
+    let owner = selection_owner(seat).filter(|owner| matches!(owner, SelectionOwner::Client { .. }));
+    start_transfer(
+        owner,
+        mime_type,
+        options,
+        |source, mime_type, fd| source.send(mime_type, fd),
+        |_, _, _| unreachable!("compositor selections were filtered out"),
+    )
+}
+
+/// Request the contents of the current selection of this seat
+///
+/// Unlike [`request_data_device_client_selection`] this works for any selection. The owner of the
+/// selection is asked to write its contents for the given mime type into a pipe, which is the client
+/// holding it or, for selections set through [`set_data_device_selection`], your
+/// [`DataDeviceHandler::send_selection`] implementation or the provider of a lazy selection.
+///
+/// The returned [`SelectionTransfer`] has to be inserted into your event loop and delivers the data
+/// or the [`TransferError`] that ended the transfer, enforcing the limits given in `options`.
+#[instrument(name = "wayland_data_device", level = "debug", skip(state, seat), fields(seat = seat.name()))]
+pub fn request_data_device_selection<D>(
+    state: &mut D,
+    seat: &Seat<D>,
+    mime_type: String,
+    options: TransferOptions,
+) -> Result<SelectionTransfer, TransferError>
+where
+    D: SeatHandler + DataDeviceHandler + 'static,
+{
+    start_transfer(
+        selection_owner(seat),
+        mime_type,
+        options,
+        |source, mime_type, fd| source.send(mime_type, fd),
+        |provider, mime_type, fd| send_compositor_selection(state, provider, mime_type, fd),
+    )
+}
+
+impl<D> SelectionProtocol<D> for DataDeviceState
+where
+    D: SeatHandler + DataDeviceHandler + 'static,
+{
+    fn request_selection(
+        state: &mut D,
+        seat: &Seat<D>,
+        mime_type: String,
+        options: TransferOptions,
+    ) -> Result<SelectionTransfer, TransferError> {
+        request_data_device_selection(state, seat, mime_type, options)
+    }
+}
+
+/// Read the current selection of this seat, blocking until it was transferred
+///
+/// The owner of the selection is asked to write its contents for the given mime type into a
+/// pipe, which is then read until the owner closes it. For client-provided selections this is
+/// the client holding the selection, for selections set through [`set_data_device_selection`]
+/// it is your [`DataDeviceHandler::send_selection`] implementation and for lazy selections
+/// their provider.
+///
+/// # Deadlocks
+///
+/// **This blocks the calling thread for up to `timeout`.** To not deadlock on the client
+/// holding the selection, requests of all clients are dispatched from `display` and the
+/// resulting events are flushed while waiting. Your event loop is *not* dispatched, so a
+/// transfer that depends on any other event source of it, including your own
+/// [`send_selection`](DataDeviceHandler::send_selection) writing from one, can only end
+/// with a timeout. The pipe is non-blocking, so `send_selection` cannot synchronously write
+/// more than the pipe capacity (usually 64 KiB) and should hand larger transfers to another
+/// thread.
+///
+/// This can not be called while `display` is being dispatched, e.g. from a request handler.
+/// Prefer [`request_data_device_selection`] wherever an asynchronous read is possible.
+///
+/// # Errors
+///
+/// Fails with [`io::ErrorKind::NotFound`] if the seat has no selection, with
+/// [`io::ErrorKind::InvalidInput`] if the selection does not offer `mime_type` and with
+/// [`io::ErrorKind::TimedOut`] if the transfer did not finish within `timeout`.
+#[instrument(name = "wayland_data_device", level = "debug", skip(display, state, seat), fields(seat = seat.name()))]
+pub fn read_selection_blocking<D>(
+    display: &mut Display<D>,
+    state: &mut D,
+    seat: &Seat<D>,
+    mime_type: String,
+    timeout: Duration,
+) -> io::Result<Vec<u8>>
+where
+    D: SeatHandler + DataDeviceHandler + 'static,
+{
+    let deadline = Instant::now() + timeout;
+    let no_selection = || io::Error::new(io::ErrorKind::NotFound, TransferError::NoSelection);
+    let invalid_mime_type = || io::Error::new(io::ErrorKind::InvalidInput, TransferError::InvalidMimetype);
+
+    let owner = selection_owner(seat).ok_or_else(no_selection)?;
+    if !owner.offers(&mime_type) {
+        return Err(invalid_mime_type());
+    }
+    let (read, write) = selection::pipe(true)?;
+    owner.send(
+        mime_type,
+        write,
+        |source, mime_type, fd| source.send(mime_type, fd),
+        |provider, mime_type, fd| send_compositor_selection(state, provider, mime_type, fd),
+    );
+
+    read::read_selection_dispatching(read, display, state, deadline)
+}
+
 /// Start a drag'n'drop from a resource controlled by the compositor
 ///
 /// You'll receive events generated by the interaction of clients with your
 /// drag'n'drop in the provided callback. See [`ServerDndGrabHandler`] for details about
 /// which events can be generated and what response is expected from you to them.
+///
+/// This can be used for drags originating from compositor UI or drags bridged from
+/// other sources (e.g. XWayland). As there is no client surface representing the dragged
+/// content, you are responsible for drawing a drag icon yourself, following the pointer
+/// location reported through [`ServerDndGrabHandler::motion`].
 #[instrument(name = "wayland_data_device", level = "debug", skip(dh, seat, data), fields(seat = seat.name()))]
 pub fn start_dnd<D, C>(
     dh: &DisplayHandle,
@@ -331,7 +553,7 @@ mod handlers {
             _resource: &WlDataDeviceManager,
             request: wl_data_device_manager::Request,
             _data: &(),
-            _dhandle: &DisplayHandle,
+            dhandle: &DisplayHandle,
             data_init: &mut wayland_server::DataInit<'_, D>,
         ) {
             match request {
@@ -347,7 +569,7 @@ mod handlers {
                             let data_device = data_init.init(id, DataDeviceUserData { wl_seat });
 
                             let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
-                            seat_data.borrow_mut().add_device(data_device);
+                            seat_data.borrow_mut().add_device::<D>(dhandle, data_device);
                         }
                         None => {
                             error!(client = ?client, data_device = ?id, "Unmanaged seat given to a data device.");
@@ -379,3 +601,489 @@ macro_rules! delegate_data_device {
         ] => $crate::wayland::data_device::DataDeviceState);
     };
 }
+
+#[cfg(all(test, feature = "test_utils"))]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        os::unix::{io::OwnedFd, net::UnixStream},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use wayland_server::protocol::{
+        wl_data_device::WlDataDevice, wl_data_offer, wl_data_source::WlDataSource, wl_seat::WlSeat,
+        wl_surface::WlSurface,
+    };
+
+    use super::{
+        read_selection_blocking, seat_data::handle_server_selection, set_data_device_lazy_selection,
+        set_data_device_selection, ClientDndGrabHandler, DataDeviceHandler, DataDeviceState,
+        DataDeviceUserData, DataSourceUserData, SeatData, Selection, ServerDndGrabHandler,
+    };
+    use crate::{
+        input::{SeatHandler, SeatState},
+        testing::TestCompositor,
+    };
+
+    struct TestState {
+        seat_state: SeatState<Self>,
+        data_device_state: DataDeviceState,
+        // write ends of transfers, that are kept open instead of written to
+        stalled: Vec<OwnedFd>,
+        stall: bool,
+    }
+
+    impl SeatHandler for TestState {
+        type KeyboardFocus = WlSurface;
+        type PointerFocus = WlSurface;
+
+        fn seat_state(&mut self) -> &mut SeatState<Self> {
+            &mut self.seat_state
+        }
+    }
+
+    impl ClientDndGrabHandler for TestState {}
+    impl ServerDndGrabHandler for TestState {}
+    impl DataDeviceHandler for TestState {
+        fn data_device_state(&self) -> &DataDeviceState {
+            &self.data_device_state
+        }
+
+        fn send_selection(&mut self, mime_type: String, fd: OwnedFd) {
+            if self.stall {
+                self.stalled.push(fd);
+            } else {
+                let mut file = std::fs::File::from(fd);
+                file.write_all(mime_type.as_bytes()).unwrap();
+            }
+        }
+    }
+
+    crate::delegate_data_device!(TestState);
+
+    impl wayland_server::Dispatch<WlSeat, ()> for TestState {
+        fn request(
+            _state: &mut Self,
+            _client: &wayland_server::Client,
+            _resource: &WlSeat,
+            _request: wayland_server::protocol::wl_seat::Request,
+            _data: &(),
+            _dhandle: &wayland_server::DisplayHandle,
+            _data_init: &mut wayland_server::DataInit<'_, Self>,
+        ) {
+        }
+    }
+
+    fn new_compositor() -> TestCompositor<TestState> {
+        TestCompositor::new(|dh, _| TestState {
+            seat_state: SeatState::new(),
+            data_device_state: DataDeviceState::new::<TestState>(dh),
+            stalled: Vec::new(),
+            stall: false,
+        })
+    }
+
+    #[test]
+    fn blocking_read_of_compositor_selection() {
+        let mut compositor = new_compositor();
+        let seat = compositor.state.seat_state.new_seat("seat");
+
+        let err = read_selection_blocking(
+            &mut compositor.display,
+            &mut compositor.state,
+            &seat,
+            "text/plain".into(),
+            Duration::from_secs(1),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+        set_data_device_selection(&compositor.handle(), &seat, vec!["text/plain".into()]);
+        let data = read_selection_blocking(
+            &mut compositor.display,
+            &mut compositor.state,
+            &seat,
+            "text/plain".into(),
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        assert_eq!(data, b"text/plain");
+
+        let err = read_selection_blocking(
+            &mut compositor.display,
+            &mut compositor.state,
+            &seat,
+            "image/png".into(),
+            Duration::from_secs(1),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn blocking_read_times_out() {
+        let mut compositor = new_compositor();
+        let seat = compositor.state.seat_state.new_seat("seat");
+        compositor.state.stall = true;
+
+        set_data_device_selection(&compositor.handle(), &seat, vec!["text/plain".into()]);
+        let err = read_selection_blocking(
+            &mut compositor.display,
+            &mut compositor.state,
+            &seat,
+            "text/plain".into(),
+            Duration::from_millis(10),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        assert_eq!(compositor.state.stalled.len(), 1);
+    }
+
+    #[test]
+    fn device_bound_after_source_died_gets_empty_selection() {
+        let mut compositor = new_compositor();
+        let dh = compositor.handle();
+        let mut seat_data = SeatData::new();
+
+        // the owner of the selection ...
+        let owner = compositor.connect();
+        let source = owner
+            .client()
+            .create_resource::<WlDataSource, _, TestState>(&dh, 3, DataSourceUserData::new())
+            .unwrap();
+        seat_data.set_selection::<TestState>(&dh, Selection::Client(source));
+
+        // ... dies before the focused client binds its device
+        let client = compositor.connect();
+        seat_data.set_focus::<TestState>(&dh, Some(client.client().clone()));
+        drop(owner);
+        compositor.advance();
+
+        let wl_seat = client
+            .client()
+            .create_resource::<WlSeat, (), TestState>(&dh, 1, ())
+            .unwrap();
+        let device = client
+            .client()
+            .create_resource::<WlDataDevice, _, TestState>(&dh, 3, DataDeviceUserData { wl_seat })
+            .unwrap();
+        seat_data.add_device::<TestState>(&dh, device);
+
+        assert!(matches!(seat_data.selection(), Selection::Empty));
+        assert_eq!(seat_data.known_devices().len(), 1);
+    }
+
+    #[test]
+    fn lazy_selection_is_produced_on_paste() {
+        let mut compositor = new_compositor();
+        let dh = compositor.handle();
+        let seat = compositor.state.seat_state.new_seat("seat");
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        set_data_device_lazy_selection(&dh, &seat, vec!["text/plain".into()], move |mime_type, fd| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let mut file = std::fs::File::from(fd);
+            write!(file, "lazy {}", mime_type).unwrap();
+        });
+
+        // offering the selection to a focused client does not produce the contents
+        let client = compositor.connect();
+        let wl_seat = client
+            .client()
+            .create_resource::<WlSeat, (), TestState>(&dh, 1, ())
+            .unwrap();
+        let device = client
+            .client()
+            .create_resource::<WlDataDevice, _, TestState>(&dh, 3, DataDeviceUserData { wl_seat })
+            .unwrap();
+        {
+            let seat_data = seat.user_data().get::<std::cell::RefCell<SeatData>>().unwrap();
+            let mut seat_data = seat_data.borrow_mut();
+            seat_data.set_focus::<TestState>(&dh, Some(client.client().clone()));
+            seat_data.add_device::<TestState>(&dh, device);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        let (meta, provider) = {
+            let seat_data = seat.user_data().get::<std::cell::RefCell<SeatData>>().unwrap();
+            match seat_data.borrow().selection() {
+                Selection::Lazy(meta, provider) => (meta.clone(), provider.clone()),
+                _ => panic!("expected a lazy selection"),
+            }
+        };
+
+        // pasting a mime type not offered is denied
+        let (_read, write) = UnixStream::pair().unwrap();
+        let receive = wl_data_offer::Request::Receive {
+            mime_type: "image/png".into(),
+            fd: OwnedFd::from(write),
+        };
+        handle_server_selection(&mut compositor.state, receive, &meta, Some(&provider));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        // a client pasting it invokes the provider
+        let (mut read, write) = UnixStream::pair().unwrap();
+        let receive = wl_data_offer::Request::Receive {
+            mime_type: "text/plain".into(),
+            fd: OwnedFd::from(write),
+        };
+        handle_server_selection(&mut compositor.state, receive, &meta, Some(&provider));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        let mut data = String::new();
+        read.read_to_string(&mut data).unwrap();
+        assert_eq!(data, "lazy text/plain");
+
+        // and so does the compositor reading it
+        let data = read_selection_blocking(
+            &mut compositor.display,
+            &mut compositor.state,
+            &seat,
+            "text/plain".into(),
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        assert_eq!(data, b"lazy text/plain");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    mod server_dnd {
+        use std::{
+            io::{Read, Write},
+            os::unix::{
+                io::{AsRawFd, OwnedFd},
+                net::UnixStream,
+            },
+            time::Duration,
+        };
+
+        use wayland_client::{
+            protocol::{
+                wl_compositor::WlCompositor,
+                wl_data_device,
+                wl_data_device_manager::{DndAction as ClientDndAction, WlDataDeviceManager},
+                wl_seat::WlSeat as ClientSeat,
+            },
+            Proxy,
+        };
+        use wayland_server::protocol::{wl_data_device_manager::DndAction, wl_surface::WlSurface};
+
+        use super::super::{
+            start_dnd, ClientDndGrabHandler, DataDeviceHandler, DataDeviceState, ServerDndGrabHandler,
+            SourceMetadata,
+        };
+        use crate::{
+            backend::input::ButtonState,
+            input::{
+                pointer::{ButtonEvent, GrabStartData, MotionEvent},
+                Seat, SeatHandler, SeatState,
+            },
+            testing::{Recorded, TestCompositor},
+            utils::{Logical, Point, SERIAL_COUNTER},
+            wayland::compositor::{CompositorHandler, CompositorState},
+        };
+
+        #[derive(Debug, PartialEq)]
+        enum Hook {
+            Motion(Point<f64, Logical>),
+            Action(DndAction),
+            Send(String),
+            Dropped,
+            Cancelled,
+            Finished,
+        }
+
+        struct State {
+            compositor_state: CompositorState,
+            seat_state: SeatState<State>,
+            seat: Seat<State>,
+            data_device_state: DataDeviceState,
+            hooks: Vec<Hook>,
+        }
+
+        impl CompositorHandler for State {
+            fn compositor_state(&mut self) -> &mut CompositorState {
+                &mut self.compositor_state
+            }
+
+            fn commit(&mut self, _surface: &WlSurface) {}
+        }
+
+        impl SeatHandler for State {
+            type KeyboardFocus = WlSurface;
+            type PointerFocus = WlSurface;
+
+            fn seat_state(&mut self) -> &mut SeatState<Self> {
+                &mut self.seat_state
+            }
+        }
+
+        impl ClientDndGrabHandler for State {}
+        impl ServerDndGrabHandler for State {
+            fn motion(&mut self, location: Point<f64, Logical>) {
+                self.hooks.push(Hook::Motion(location));
+            }
+
+            fn action(&mut self, action: DndAction) {
+                self.hooks.push(Hook::Action(action));
+            }
+
+            fn dropped(&mut self) {
+                self.hooks.push(Hook::Dropped);
+            }
+
+            fn cancelled(&mut self) {
+                self.hooks.push(Hook::Cancelled);
+            }
+
+            fn send(&mut self, mime_type: String, fd: OwnedFd) {
+                let mut file = std::fs::File::from(fd);
+                file.write_all(b"dragged").unwrap();
+                self.hooks.push(Hook::Send(mime_type));
+            }
+
+            fn finished(&mut self) {
+                self.hooks.push(Hook::Finished);
+            }
+        }
+        impl DataDeviceHandler for State {
+            fn data_device_state(&self) -> &DataDeviceState {
+                &self.data_device_state
+            }
+        }
+
+        crate::delegate_compositor!(State);
+        crate::delegate_seat!(State);
+        crate::delegate_data_device!(State);
+
+        fn new_compositor() -> TestCompositor<State> {
+            TestCompositor::new(|dh, _| {
+                let mut seat_state = SeatState::new();
+                let mut seat = seat_state.new_wl_seat(dh, "seat");
+                seat.add_pointer();
+                State {
+                    compositor_state: CompositorState::new::<State>(dh),
+                    seat_state,
+                    seat,
+                    data_device_state: DataDeviceState::new::<State>(dh),
+                    hooks: Vec::new(),
+                }
+            })
+        }
+
+        fn start_drag(compositor: &mut TestCompositor<State>) {
+            let dh = compositor.handle();
+            let seat = compositor.state.seat.clone();
+            start_dnd(
+                &dh,
+                &seat,
+                &mut compositor.state,
+                SERIAL_COUNTER.next_serial(),
+                GrabStartData {
+                    focus: None,
+                    button: 0x110,
+                    location: (0.0, 0.0).into(),
+                },
+                SourceMetadata {
+                    mime_types: vec!["text/plain".into()],
+                    dnd_action: DndAction::Copy | DndAction::Move,
+                },
+            );
+        }
+
+        fn release(compositor: &mut TestCompositor<State>) {
+            let pointer = compositor.state.seat.get_pointer().unwrap();
+            pointer.button(
+                &mut compositor.state,
+                &ButtonEvent {
+                    serial: SERIAL_COUNTER.next_serial(),
+                    time: 0,
+                    button: 0x110,
+                    state: ButtonState::Released,
+                },
+            );
+        }
+
+        #[test]
+        fn compositor_drag_is_dropped_on_client() {
+            let mut compositor = new_compositor();
+            let mut client = compositor.connect();
+            let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+            let seat = client.bind::<ClientSeat, _>(&mut compositor);
+            let device_manager = client.bind::<WlDataDeviceManager, _>(&mut compositor);
+            let qh = client.queue_handle();
+            let _data_device = device_manager.get_data_device(&seat, &qh, Recorded);
+            let surface = wl_compositor.create_surface(&qh, Recorded);
+            client.roundtrip(&mut compositor);
+            let server_surface = client
+                .client()
+                .object_from_protocol_id::<WlSurface>(&compositor.handle(), surface.id().protocol_id())
+                .unwrap();
+
+            // moving the drag over the surface of the client offers it the compositor source
+            start_drag(&mut compositor);
+            let pointer = compositor.state.seat.get_pointer().unwrap();
+            pointer.motion(
+                &mut compositor.state,
+                Some((server_surface, (10, 10).into())),
+                &MotionEvent {
+                    location: (15.0, 20.0).into(),
+                    serial: SERIAL_COUNTER.next_serial(),
+                    time: 0,
+                },
+            );
+            let offer = loop {
+                // the offer is introduced by a data_offer event before entering the surface
+                match client
+                    .wait_for_event::<wl_data_device::Event, _>(&mut compositor, Duration::from_secs(1))
+                {
+                    Some(wl_data_device::Event::DataOffer { .. }) => continue,
+                    Some(wl_data_device::Event::Enter { x, y, id, .. }) => {
+                        assert_eq!((x, y), (5.0, 10.0));
+                        break id.expect("No offer for the drag");
+                    }
+                    event => panic!("Unexpected event: {:?}", event),
+                }
+            };
+
+            // the client negotiates the action and reads the data
+            offer.accept(0, Some("text/plain".into()));
+            offer.set_actions(ClientDndAction::Copy, ClientDndAction::Copy);
+            let (mut read, write) = UnixStream::pair().unwrap();
+            offer.receive("text/plain".into(), write.as_raw_fd());
+            client.roundtrip(&mut compositor);
+            drop(write);
+            let mut data = String::new();
+            read.read_to_string(&mut data).unwrap();
+            assert_eq!(data, "dragged");
+
+            release(&mut compositor);
+            offer.finish();
+            client.roundtrip(&mut compositor);
+
+            assert_eq!(
+                compositor.state.hooks,
+                vec![
+                    Hook::Motion((15.0, 20.0).into()),
+                    Hook::Action(DndAction::Copy),
+                    Hook::Send("text/plain".into()),
+                    Hook::Dropped,
+                    Hook::Finished,
+                ]
+            );
+        }
+
+        #[test]
+        fn compositor_drag_without_action_is_cancelled() {
+            let mut compositor = new_compositor();
+            start_drag(&mut compositor);
+            release(&mut compositor);
+
+            assert_eq!(compositor.state.hooks, vec![Hook::Dropped, Hook::Cancelled]);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::{fs::File, io, os::unix::io::AsRawFd, time::Instant};
+
+use nix::{
+    errno::Errno,
+    poll::{poll, PollFd, PollFlags},
+};
+use tracing::trace;
+use wayland_server::Display;
+
+use crate::wayland::selection::{read_available, ReadStatus};
+
+/// Read the pipe of a selection transfer to its end, dispatching clients while waiting
+///
+/// Fails with [`io::ErrorKind::TimedOut`] if the writer did not close the pipe before `deadline`.
+pub(crate) fn read_selection_dispatching<D>(
+    mut file: File,
+    display: &mut Display<D>,
+    state: &mut D,
+    deadline: Instant,
+) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    loop {
+        // clients may have disconnected in the meantime, which is reported on the next dispatch
+        let _ = display.flush_clients();
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "selection transfer timed out",
+            ));
+        }
+        // round up, so we don't spin for the last fraction of a millisecond
+        let timeout = ((remaining.as_micros() + 999) / 1000).min(i32::MAX as u128) as i32;
+
+        let mut fds = [
+            PollFd::new(file.as_raw_fd(), PollFlags::POLLIN),
+            PollFd::new(display.backend().poll_fd().as_raw_fd(), PollFlags::POLLIN),
+        ];
+        match poll(&mut fds, timeout) {
+            Ok(_) | Err(Errno::EINTR) => {}
+            Err(err) => return Err(err.into()),
+        }
+        let pipe_ready = fds[0].revents().map(|flags| !flags.is_empty()).unwrap_or(false);
+        let display_ready = fds[1].revents().map(|flags| !flags.is_empty()).unwrap_or(false);
+
+        if display_ready {
+            display.dispatch_clients(state)?;
+        }
+        if pipe_ready && read_available(&mut file, &mut buffer, None)? == ReadStatus::Finished {
+            trace!(len = buffer.len(), "Selection transfer finished");
+            return Ok(buffer);
+        }
+    }
+}
@@ -18,10 +18,15 @@ use crate::utils::IsAlive;
 
 use super::{with_source_metadata, DataDeviceHandler, SourceMetadata};
 
+/// Callback writing the contents of a lazy selection for a mime type into a file descriptor
+pub type SelectionProvider = Arc<dyn Fn(String, OwnedFd) + Send + Sync>;
+
 pub enum Selection {
     Empty,
     Client(WlDataSource),
     Compositor(SourceMetadata),
+    /// Compositor selection whose contents are only produced once requested
+    Lazy(SourceMetadata, SelectionProvider),
 }
 
 pub struct SeatData {
@@ -45,11 +50,29 @@ impl SeatData {
         Self::default()
     }
 
+    pub fn selection(&self) -> &Selection {
+        &self.selection
+    }
+
     pub fn known_devices(&self) -> &[WlDataDevice] {
         &self.known_devices
     }
 
-    pub fn add_device(&mut self, device: WlDataDevice) {
+    /// Add a new device, offering the current selection to it if its client is focused
+    pub fn add_device<D>(&mut self, dh: &DisplayHandle, device: WlDataDevice)
+    where
+        D: DataDeviceHandler,
+        D: 'static,
+    {
+        let focused = match (self.current_focus.as_ref(), dh.get_client(device.id())) {
+            (Some(focus), Ok(client)) => focus == &client,
+            _ => false,
+        };
+        if focused {
+            // the source may have died since it was set, don't offer a dead source
+            self.sanitize_selection();
+            self.offer_selection::<D>(dh, &device);
+        }
         self.known_devices.push(device);
     }
 
@@ -91,12 +114,25 @@ impl SeatData {
         D: DataDeviceHandler,
         D: 'static,
     {
+        // first sanitize the selection, reseting it to null if the client holding
+        // it dropped it
+        self.sanitize_selection();
         let client = match self.current_focus.as_ref() {
             Some(c) => c,
             None => return,
         };
-        // first sanitize the selection, reseting it to null if the client holding
-        // it dropped it
+
+        // then send it to the devices of the focused client
+        for dd in &self.known_devices {
+            // skip data devices not belonging to our client
+            if dh.get_client(dd.id()).map(|c| &c != client).unwrap_or(true) {
+                continue;
+            }
+            self.offer_selection::<D>(dh, dd);
+        }
+    }
+
+    fn sanitize_selection(&mut self) {
         let cleanup = if let Selection::Client(ref data_source) = self.selection {
             !data_source.alive()
         } else {
@@ -105,83 +141,89 @@ impl SeatData {
         if cleanup {
             self.selection = Selection::Empty;
         }
+    }
 
-        // then send it if appropriate
+    fn offer_selection<D>(&self, dh: &DisplayHandle, dd: &WlDataDevice)
+    where
+        D: DataDeviceHandler,
+        D: 'static,
+    {
+        let client = match dh.get_client(dd.id()) {
+            Ok(client) => client,
+            Err(_) => return,
+        };
         match self.selection {
             Selection::Empty => {
                 // send an empty selection
-                for dd in &self.known_devices {
-                    // skip data devices not belonging to our client
-                    if dh.get_client(dd.id()).map(|c| &c != client).unwrap_or(true) {
-                        continue;
-                    }
-                    dd.selection(None);
-                }
+                dd.selection(None);
             }
             Selection::Client(ref data_source) => {
-                for dd in &self.known_devices {
-                    // skip data devices not belonging to our client
-                    if dh.get_client(dd.id()).map(|c| &c != client).unwrap_or(true) {
-                        continue;
-                    }
-                    let source = data_source.clone();
-
-                    let handle = dh.backend_handle();
-                    // create a data offer
-                    let offer = handle
-                        .create_object::<D>(
-                            client.id(),
-                            WlDataOffer::interface(),
-                            dd.version(),
-                            Arc::new(ClientSelection { source }),
-                        )
-                        .unwrap();
-                    let offer = WlDataOffer::from_id(dh, offer).unwrap();
-
-                    // advertize the offer to the client
-                    dd.data_offer(&offer);
-                    with_source_metadata(data_source, |meta| {
-                        for mime_type in meta.mime_types.iter().cloned() {
-                            offer.offer(mime_type);
-                        }
-                    })
+                let source = data_source.clone();
+
+                let handle = dh.backend_handle();
+                // create a data offer
+                let offer = handle
+                    .create_object::<D>(
+                        client.id(),
+                        WlDataOffer::interface(),
+                        dd.version(),
+                        Arc::new(ClientSelection { source }),
+                    )
                     .unwrap();
-                    dd.selection(Some(&offer));
-                }
-            }
-            Selection::Compositor(ref meta) => {
-                for dd in &self.known_devices {
-                    // skip data devices not belonging to our client
-                    if dh.get_client(dd.id()).map(|c| &c != client).unwrap_or(true) {
-                        continue;
-                    }
+                let offer = WlDataOffer::from_id(dh, offer).unwrap();
 
-                    let offer_meta = meta.clone();
-
-                    let handle = dh.backend_handle();
-                    // create a data offer
-                    let offer = handle
-                        .create_object::<D>(
-                            client.id(),
-                            WlDataOffer::interface(),
-                            dd.version(),
-                            Arc::new(ServerSelection { offer_meta }),
-                        )
-                        .unwrap();
-                    let offer = WlDataOffer::from_id(dh, offer).unwrap();
-
-                    // advertize the offer to the client
-                    dd.data_offer(&offer);
+                // advertize the offer to the client
+                dd.data_offer(&offer);
+                with_source_metadata(data_source, |meta| {
                     for mime_type in meta.mime_types.iter().cloned() {
                         offer.offer(mime_type);
                     }
-                    dd.selection(Some(&offer));
-                }
+                })
+                .unwrap();
+                dd.selection(Some(&offer));
+            }
+            Selection::Compositor(ref meta) => {
+                offer_server_selection::<D>(dh, dd, &client, meta, None);
+            }
+            Selection::Lazy(ref meta, ref provider) => {
+                offer_server_selection::<D>(dh, dd, &client, meta, Some(provider.clone()));
             }
         }
     }
 }
 
+fn offer_server_selection<D>(
+    dh: &DisplayHandle,
+    dd: &WlDataDevice,
+    client: &Client,
+    meta: &SourceMetadata,
+    provider: Option<SelectionProvider>,
+) where
+    D: DataDeviceHandler,
+    D: 'static,
+{
+    let offer_meta = meta.clone();
+
+    let handle = dh.backend_handle();
+    // create a data offer
+    let offer = handle
+        .create_object::<D>(
+            client.id(),
+            WlDataOffer::interface(),
+            dd.version(),
+            Arc::new(ServerSelection { offer_meta, provider }),
+        )
+        .unwrap();
+    let offer = WlDataOffer::from_id(dh, offer).unwrap();
+
+    // advertize the offer to the client
+    dd.data_offer(&offer);
+    for mime_type in meta.mime_types.iter().cloned() {
+        offer.offer(mime_type);
+    }
+    dd.selection(Some(&offer));
+}
+
 struct ClientSelection {
     source: WlDataSource,
 }
@@ -227,6 +269,7 @@ fn handle_client_selection(request: wl_data_offer::Request, source: &WlDataSourc
 
 struct ServerSelection {
     offer_meta: SourceMetadata,
+    provider: Option<SelectionProvider>,
 }
 
 impl<D> ObjectData<D> for ServerSelection
@@ -242,7 +285,7 @@ where
     ) -> Option<Arc<dyn ObjectData<D>>> {
         let dh = DisplayHandle::from(dh.clone());
         if let Ok((_resource, request)) = WlDataOffer::parse_request(&dh, msg) {
-            handle_server_selection(handler, request, &self.offer_meta);
+            handle_server_selection(handler, request, &self.offer_meta, self.provider.as_ref());
         }
 
         None
@@ -255,6 +298,7 @@ pub fn handle_server_selection<D>(
     handler: &mut D,
     request: wl_data_offer::Request,
     offer_meta: &SourceMetadata,
+    provider: Option<&SelectionProvider>,
 ) where
     D: DataDeviceHandler,
 {
@@ -264,6 +308,8 @@ pub fn handle_server_selection<D>(
         if !offer_meta.mime_types.contains(&mime_type) {
             // deny the receive
             debug!("Denying a wl_data_offer.receive with invalid source.");
+        } else if let Some(provider) = provider {
+            provider(mime_type, fd);
         } else {
             handler.send_selection(mime_type, fd);
         }
@@ -75,6 +75,7 @@ where
 
         // While the grab is active, no client has pointer focus
         handle.motion(data, None, event);
+        ServerDndGrabHandler::motion(data, location);
 
         let seat_data = self
             .seat
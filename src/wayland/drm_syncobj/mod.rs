@@ -0,0 +1,605 @@
+//! Utilities for handling the `wp_linux_drm_syncobj_v1` explicit synchronization protocol
+//!
+//! This protocol lets clients attach acquire and release points on DRM syncobj timelines to
+//! their buffer submissions. The compositor has to wait for the acquire point to be signaled
+//! before using the buffer, and signals the release point once it is done with the buffer.
+//!
+//! ## How to use it
+//!
+//! ### Initialization
+//!
+//! To initialize this implementation, create the [`DrmSyncobjState`] with the DRM device used to
+//! import timelines, store it in your `State` struct and implement the required traits:
+//!
+//! ```no_run
+//! use smithay::backend::drm::DrmDeviceFd;
+//! use smithay::delegate_drm_syncobj;
+//! use smithay::wayland::drm_syncobj::{DrmSyncobjHandler, DrmSyncobjState};
+//!
+//! pub struct State {
+//!     drm_syncobj_state: DrmSyncobjState,
+//! }
+//!
+//! impl DrmSyncobjHandler for State {
+//!     fn drm_syncobj_state(&mut self) -> &mut DrmSyncobjState {
+//!         &mut self.drm_syncobj_state
+//!     }
+//! }
+//!
+//! delegate_drm_syncobj!(State);
+//!
+//! # let display = wayland_server::Display::<State>::new().unwrap();
+//! # let device: DrmDeviceFd = unimplemented!();
+//! let drm_syncobj_state = DrmSyncobjState::new::<State>(&display.handle(), device);
+//! ```
+//!
+//! ### Waiting for acquire points
+//!
+//! The acquire point is exposed as a [`Blocker`](crate::wayland::compositor::Blocker) through
+//! [`acquire_point_blocker`], which should be returned from
+//! [`CompositorHandler::commit_blocked_by`](crate::wayland::compositor::CompositorHandler::commit_blocked_by).
+//! The state of the surface is then held back until the point is signaled. The accompanying
+//! [`DrmSyncPointSource`] has to be inserted into your event loop, it fires once the point got
+//! signaled:
+//!
+//! ```no_run
+//! # use smithay::reexports::{calloop::LoopHandle, wayland_server::{DisplayHandle, protocol::wl_surface::WlSurface}};
+//! # use smithay::wayland::compositor::{Blocker, CompositorHandler, CompositorState};
+//! use smithay::wayland::drm_syncobj::acquire_point_blocker;
+//!
+//! # struct State { compositor_state: CompositorState, handle: LoopHandle<'static, State>, dh: DisplayHandle }
+//! impl CompositorHandler for State {
+//! #   fn compositor_state(&mut self) -> &mut CompositorState { &mut self.compositor_state }
+//! #   fn commit(&mut self, surface: &WlSurface) {}
+//!     fn commit_blocked_by(&mut self, surface: &WlSurface) -> Option<Box<dyn Blocker + Unpin>> {
+//!         let (blocker, source) = acquire_point_blocker(surface)?;
+//!         self.handle
+//!             .insert_source(source, |_, _, state| {
+//!                 let dh = state.dh.clone();
+//!                 CompositorState::blocker_cleared(state, &dh);
+//!             })
+//!             .ok()?;
+//!         Some(Box::new(blocker))
+//!     }
+//! }
+//! ```
+//!
+//! ### Signaling release points
+//!
+//! The points of the current state are stored in the double-buffered [`DrmSyncobjCachedState`].
+//! The release point of a commit is a [`DrmSyncReleasePoint`], which is signaled once the last
+//! handle to it is dropped, e.g. when the state is replaced by a later commit. Clone it to keep
+//! the point unsignaled for as long as the buffer is in use, or signal it earlier through
+//! [`DrmSyncReleasePoint::signal`].
+
+use std::{os::unix::io::AsFd, sync::Mutex};
+
+use tracing::trace;
+use wayland_server::{
+    backend::GlobalId,
+    protocol::wl_surface::{self, WlSurface},
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+
+use crate::{
+    backend::drm::DrmDeviceFd,
+    utils::IsAlive,
+    wayland::{
+        compositor::{self, with_states, BufferAssignment, Cacheable, SurfaceAttributes},
+        dmabuf::get_dmabuf,
+    },
+};
+
+mod sync_point;
+pub use sync_point::{
+    DrmSyncPoint, DrmSyncPointBlocker, DrmSyncPointSource, DrmSyncReleasePoint, DrmTimeline,
+};
+
+/// Generated bindings of the `linux-drm-syncobj-v1` protocol
+#[allow(missing_docs, non_upper_case_globals, non_camel_case_types, clippy::all)]
+pub mod protocol {
+    /// Server side of the protocol
+    pub mod server {
+        use wayland_server;
+        use wayland_server::protocol::*;
+
+        pub mod __interfaces {
+            use wayland_server::protocol::__interfaces::*;
+            wayland_scanner::generate_interfaces!("src/wayland/drm_syncobj/linux-drm-syncobj-v1.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_server_code!("src/wayland/drm_syncobj/linux-drm-syncobj-v1.xml");
+    }
+
+    /// Client side of the protocol, used by the tests
+    #[cfg(all(test, feature = "test_utils"))]
+    pub mod client {
+        use wayland_client;
+        use wayland_client::protocol::*;
+
+        pub mod __interfaces {
+            use wayland_client::protocol::__interfaces::*;
+            wayland_scanner::generate_interfaces!("src/wayland/drm_syncobj/linux-drm-syncobj-v1.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_client_code!("src/wayland/drm_syncobj/linux-drm-syncobj-v1.xml");
+    }
+}
+
+use protocol::server::{
+    wp_linux_drm_syncobj_manager_v1::{self, WpLinuxDrmSyncobjManagerV1},
+    wp_linux_drm_syncobj_surface_v1::{self, WpLinuxDrmSyncobjSurfaceV1},
+    wp_linux_drm_syncobj_timeline_v1::{self, WpLinuxDrmSyncobjTimelineV1},
+};
+
+/// Double-buffered explicit synchronization state of a surface
+#[derive(Debug, Default, Clone)]
+pub struct DrmSyncobjCachedState {
+    /// Point the compositor has to wait for before accessing the buffer
+    pub acquire_point: Option<DrmSyncPoint>,
+    /// Point the compositor has to signal once it is done with the buffer
+    ///
+    /// Signaled once the last handle is dropped, so states replaced without being used
+    /// release their buffer as well.
+    pub release_point: Option<DrmSyncReleasePoint>,
+}
+
+impl Cacheable for DrmSyncobjCachedState {
+    fn commit(&mut self, _dh: &DisplayHandle) -> Self {
+        // the points are only valid for a single commit
+        DrmSyncobjCachedState {
+            acquire_point: self.acquire_point.take(),
+            release_point: self.release_point.take(),
+        }
+    }
+
+    fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        *into = self;
+    }
+}
+
+/// Handler trait for the drm syncobj protocol
+pub trait DrmSyncobjHandler {
+    /// [`DrmSyncobjState`] getter
+    fn drm_syncobj_state(&mut self) -> &mut DrmSyncobjState;
+}
+
+/// State of the wp_linux_drm_syncobj_manager_v1 global
+#[derive(Debug)]
+pub struct DrmSyncobjState {
+    global: GlobalId,
+    import_device: DrmDeviceFd,
+}
+
+impl DrmSyncobjState {
+    /// Create a new [`WpLinuxDrmSyncobjManagerV1`] global
+    ///
+    /// Timelines of clients are imported using `import_device`.
+    pub fn new<D>(display: &DisplayHandle, import_device: DrmDeviceFd) -> DrmSyncobjState
+    where
+        D: GlobalDispatch<WpLinuxDrmSyncobjManagerV1, ()>
+            + Dispatch<WpLinuxDrmSyncobjManagerV1, ()>
+            + Dispatch<WpLinuxDrmSyncobjSurfaceV1, DrmSyncobjSurfaceData>
+            + Dispatch<WpLinuxDrmSyncobjTimelineV1, DrmTimeline>
+            + DrmSyncobjHandler
+            + 'static,
+    {
+        DrmSyncobjState {
+            global: display.create_global::<D, WpLinuxDrmSyncobjManagerV1, ()>(1, ()),
+            import_device,
+        }
+    }
+
+    /// Returns the drm syncobj global.
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+
+    /// Device used to import timelines
+    pub fn import_device(&self) -> &DrmDeviceFd {
+        &self.import_device
+    }
+}
+
+/// Returns a [`Blocker`](crate::wayland::compositor::Blocker) for the pending acquire point of the surface, if any
+///
+/// Intended to be returned from
+/// [`CompositorHandler::commit_blocked_by`](crate::wayland::compositor::CompositorHandler::commit_blocked_by),
+/// after inserting the [`DrmSyncPointSource`] into the event loop.
+pub fn acquire_point_blocker(surface: &WlSurface) -> Option<(DrmSyncPointBlocker, DrmSyncPointSource)> {
+    let acquire_point = with_states(surface, |states| {
+        states
+            .cached_state
+            .pending::<DrmSyncobjCachedState>()
+            .acquire_point
+            .clone()
+    })?;
+    Some(acquire_point.generate_blocker())
+}
+
+struct DrmSyncobjSurfaceMarker(Mutex<Option<WpLinuxDrmSyncobjSurfaceV1>>);
+
+/// Data associated with a [`WpLinuxDrmSyncobjSurfaceV1`]
+#[derive(Debug)]
+pub struct DrmSyncobjSurfaceData {
+    surface: wl_surface::WlSurface,
+}
+
+impl<D> GlobalDispatch<WpLinuxDrmSyncobjManagerV1, (), D> for DrmSyncobjState
+where
+    D: GlobalDispatch<WpLinuxDrmSyncobjManagerV1, ()>
+        + Dispatch<WpLinuxDrmSyncobjManagerV1, ()>
+        + Dispatch<WpLinuxDrmSyncobjSurfaceV1, DrmSyncobjSurfaceData>
+        + Dispatch<WpLinuxDrmSyncobjTimelineV1, DrmTimeline>
+        + DrmSyncobjHandler
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<WpLinuxDrmSyncobjManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D> Dispatch<WpLinuxDrmSyncobjManagerV1, (), D> for DrmSyncobjState
+where
+    D: GlobalDispatch<WpLinuxDrmSyncobjManagerV1, ()>
+        + Dispatch<WpLinuxDrmSyncobjManagerV1, ()>
+        + Dispatch<WpLinuxDrmSyncobjSurfaceV1, DrmSyncobjSurfaceData>
+        + Dispatch<WpLinuxDrmSyncobjTimelineV1, DrmTimeline>
+        + DrmSyncobjHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        resource: &WpLinuxDrmSyncobjManagerV1,
+        request: wp_linux_drm_syncobj_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_linux_drm_syncobj_manager_v1::Request::GetSurface { id, surface } => {
+                let already_exists = with_states(&surface, |states| {
+                    states
+                        .data_map
+                        .get::<DrmSyncobjSurfaceMarker>()
+                        .map(|marker| marker.0.lock().unwrap().is_some())
+                        .unwrap_or(false)
+                });
+                if already_exists {
+                    resource.post_error(
+                        wp_linux_drm_syncobj_manager_v1::Error::SurfaceExists as u32,
+                        "the surface already has a syncobj surface associated".to_string(),
+                    );
+                    return;
+                }
+
+                let syncobj_surface = data_init.init(
+                    id,
+                    DrmSyncobjSurfaceData {
+                        surface: surface.clone(),
+                    },
+                );
+                let first_syncobj_surface = with_states(&surface, |states| {
+                    let inserted = states
+                        .data_map
+                        .insert_if_missing_threadsafe(|| DrmSyncobjSurfaceMarker(Mutex::new(None)));
+                    *states
+                        .data_map
+                        .get::<DrmSyncobjSurfaceMarker>()
+                        .unwrap()
+                        .0
+                        .lock()
+                        .unwrap() = Some(syncobj_surface);
+                    inserted
+                });
+                // the hook stays registered after the syncobj surface is destroyed,
+                // it only acts while the marker holds one
+                if first_syncobj_surface {
+                    compositor::add_pre_commit_hook(&surface, commit_hook);
+                }
+            }
+            wp_linux_drm_syncobj_manager_v1::Request::ImportTimeline { id, fd } => {
+                let import_device = state.drm_syncobj_state().import_device.clone();
+                match DrmTimeline::new(&import_device, fd.as_fd()) {
+                    Ok(timeline) => {
+                        data_init.init(id, timeline);
+                    }
+                    Err(err) => {
+                        trace!(?err, "Failed to import drm syncobj timeline");
+                        resource.post_error(
+                            wp_linux_drm_syncobj_manager_v1::Error::InvalidTimeline as u32,
+                            format!("failed to import timeline: {}", err),
+                        );
+                    }
+                }
+            }
+            wp_linux_drm_syncobj_manager_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WpLinuxDrmSyncobjTimelineV1, DrmTimeline, D> for DrmSyncobjState
+where
+    D: Dispatch<WpLinuxDrmSyncobjTimelineV1, DrmTimeline>,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &WpLinuxDrmSyncobjTimelineV1,
+        request: wp_linux_drm_syncobj_timeline_v1::Request,
+        _data: &DrmTimeline,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            // points referencing the timeline keep it alive
+            wp_linux_drm_syncobj_timeline_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WpLinuxDrmSyncobjSurfaceV1, DrmSyncobjSurfaceData, D> for DrmSyncobjState
+where
+    D: Dispatch<WpLinuxDrmSyncobjSurfaceV1, DrmSyncobjSurfaceData>,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &WpLinuxDrmSyncobjSurfaceV1,
+        request: wp_linux_drm_syncobj_surface_v1::Request,
+        data: &DrmSyncobjSurfaceData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let (timeline, point_hi, point_lo, is_acquire) = match request {
+            wp_linux_drm_syncobj_surface_v1::Request::Destroy => {
+                if data.surface.alive() {
+                    with_states(&data.surface, |states| {
+                        if let Some(marker) = states.data_map.get::<DrmSyncobjSurfaceMarker>() {
+                            marker.0.lock().unwrap().take();
+                        }
+                        *states.cached_state.pending::<DrmSyncobjCachedState>() = Default::default();
+                    });
+                }
+                return;
+            }
+            wp_linux_drm_syncobj_surface_v1::Request::SetAcquirePoint {
+                timeline,
+                point_hi,
+                point_lo,
+            } => (timeline, point_hi, point_lo, true),
+            wp_linux_drm_syncobj_surface_v1::Request::SetReleasePoint {
+                timeline,
+                point_hi,
+                point_lo,
+            } => (timeline, point_hi, point_lo, false),
+            _ => unreachable!(),
+        };
+
+        if !data.surface.alive() {
+            resource.post_error(
+                wp_linux_drm_syncobj_surface_v1::Error::NoSurface as u32,
+                "the wl_surface was destroyed".to_string(),
+            );
+            return;
+        }
+
+        let sync_point = DrmSyncPoint {
+            timeline: timeline.data::<DrmTimeline>().unwrap().clone(),
+            point: ((point_hi as u64) << 32) | point_lo as u64,
+        };
+        with_states(&data.surface, |states| {
+            let mut pending = states.cached_state.pending::<DrmSyncobjCachedState>();
+            if is_acquire {
+                pending.acquire_point = Some(sync_point);
+            } else {
+                // a replaced release point is signaled, as its buffer is never used
+                pending.release_point = Some(DrmSyncReleasePoint::new(sync_point));
+            }
+        });
+    }
+}
+
+fn commit_hook(_dh: &DisplayHandle, surface: &WlSurface) {
+    with_states(surface, |states| {
+        let Some(syncobj_surface) = states
+            .data_map
+            .get::<DrmSyncobjSurfaceMarker>()
+            .and_then(|marker| marker.0.lock().unwrap().clone())
+        else {
+            return;
+        };
+
+        let pending = states.cached_state.pending::<DrmSyncobjCachedState>();
+        let buffer = match &states.cached_state.pending::<SurfaceAttributes>().buffer {
+            Some(BufferAssignment::NewBuffer(buffer)) => Some(buffer.clone()),
+            _ => None,
+        };
+
+        let error = match (&buffer, &pending.acquire_point, &pending.release_point) {
+            (None, None, None) => None,
+            (None, _, _) => Some((
+                wp_linux_drm_syncobj_surface_v1::Error::NoBuffer,
+                "timeline points set without a buffer attached",
+            )),
+            (Some(_), None, _) => Some((
+                wp_linux_drm_syncobj_surface_v1::Error::NoAcquirePoint,
+                "buffer attached without an acquire point",
+            )),
+            (Some(_), _, None) => Some((
+                wp_linux_drm_syncobj_surface_v1::Error::NoReleasePoint,
+                "buffer attached without a release point",
+            )),
+            (Some(buffer), Some(acquire), Some(release)) => {
+                if get_dmabuf(buffer).is_err() {
+                    Some((
+                        wp_linux_drm_syncobj_surface_v1::Error::UnsupportedBuffer,
+                        "explicit synchronization is only supported for dmabuf buffers",
+                    ))
+                } else if acquire.timeline == release.sync_point().timeline
+                    && acquire.point >= release.sync_point().point
+                {
+                    Some((
+                        wp_linux_drm_syncobj_surface_v1::Error::ConflictingPoints,
+                        "release point has to be greater than the acquire point",
+                    ))
+                } else {
+                    None
+                }
+            }
+        };
+
+        if let Some((error, msg)) = error {
+            syncobj_surface.post_error(error as u32, msg.to_string());
+        }
+    });
+}
+
+/// Macro to delegate implementation of the drm syncobj protocol to [`DrmSyncobjState`].
+///
+/// You must also implement [`DrmSyncobjHandler`] to use this.
+#[macro_export]
+macro_rules! delegate_drm_syncobj {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::drm_syncobj::protocol::server::wp_linux_drm_syncobj_manager_v1::WpLinuxDrmSyncobjManagerV1: ()
+        ] => $crate::wayland::drm_syncobj::DrmSyncobjState);
+
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::drm_syncobj::protocol::server::wp_linux_drm_syncobj_manager_v1::WpLinuxDrmSyncobjManagerV1: ()
+        ] => $crate::wayland::drm_syncobj::DrmSyncobjState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::drm_syncobj::protocol::server::wp_linux_drm_syncobj_surface_v1::WpLinuxDrmSyncobjSurfaceV1: $crate::wayland::drm_syncobj::DrmSyncobjSurfaceData
+        ] => $crate::wayland::drm_syncobj::DrmSyncobjState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::drm_syncobj::protocol::server::wp_linux_drm_syncobj_timeline_v1::WpLinuxDrmSyncobjTimelineV1: $crate::wayland::drm_syncobj::DrmTimeline
+        ] => $crate::wayland::drm_syncobj::DrmSyncobjState);
+    };
+}
+
+#[cfg(all(test, feature = "test_utils"))]
+mod tests {
+    use std::{fs::File, os::unix::io::AsRawFd, time::Duration};
+
+    use wayland_client::protocol::wl_compositor::WlCompositor;
+    use wayland_server::protocol::wl_surface::WlSurface;
+
+    use super::{
+        protocol::client::wp_linux_drm_syncobj_manager_v1::{self, WpLinuxDrmSyncobjManagerV1},
+        DrmSyncobjHandler, DrmSyncobjState,
+    };
+    use crate::{
+        backend::drm::DrmDeviceFd,
+        testing::{Recorded, TestClient, TestCompositor},
+        utils::DeviceFd,
+        wayland::compositor::{CompositorHandler, CompositorState},
+    };
+
+    struct State {
+        compositor_state: CompositorState,
+        drm_syncobj_state: DrmSyncobjState,
+    }
+
+    impl CompositorHandler for State {
+        fn compositor_state(&mut self) -> &mut CompositorState {
+            &mut self.compositor_state
+        }
+
+        fn commit(&mut self, _surface: &WlSurface) {}
+    }
+
+    impl DrmSyncobjHandler for State {
+        fn drm_syncobj_state(&mut self) -> &mut DrmSyncobjState {
+            &mut self.drm_syncobj_state
+        }
+    }
+
+    crate::delegate_compositor!(State);
+    crate::delegate_drm_syncobj!(State);
+
+    fn null_fd() -> File {
+        File::open("/dev/null").unwrap()
+    }
+
+    fn setup() -> (
+        TestCompositor<State>,
+        TestClient,
+        WlCompositor,
+        WpLinuxDrmSyncobjManagerV1,
+    ) {
+        let mut compositor = TestCompositor::new(|dh, _| {
+            // timelines are never imported successfully, which the tests do not need
+            let device = DrmDeviceFd::new(DeviceFd::from(std::os::unix::io::OwnedFd::from(null_fd())));
+            State {
+                compositor_state: CompositorState::new::<State>(dh),
+                drm_syncobj_state: DrmSyncobjState::new::<State>(dh, device),
+            }
+        });
+        let mut client = compositor.connect();
+        let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+        let manager = client.bind::<WpLinuxDrmSyncobjManagerV1, _>(&mut compositor);
+        (compositor, client, wl_compositor, manager)
+    }
+
+    #[test]
+    fn second_syncobj_surface_is_an_error() {
+        let (mut compositor, mut client, wl_compositor, manager) = setup();
+        let qh = client.queue_handle();
+        let surface = wl_compositor.create_surface(&qh, Recorded);
+        manager.get_surface(&surface, &qh, Recorded);
+        manager.get_surface(&surface, &qh, Recorded);
+
+        let err = client
+            .wait_for_protocol_error(&mut compositor, Duration::from_secs(1))
+            .expect("no protocol error");
+        assert_eq!(
+            err.code,
+            wp_linux_drm_syncobj_manager_v1::Error::SurfaceExists as u32
+        );
+    }
+
+    #[test]
+    fn syncobj_surface_can_be_recreated() {
+        let (mut compositor, mut client, wl_compositor, manager) = setup();
+        let qh = client.queue_handle();
+        let surface = wl_compositor.create_surface(&qh, Recorded);
+        for _ in 0..3 {
+            let syncobj_surface = manager.get_surface(&surface, &qh, Recorded);
+            surface.commit();
+            syncobj_surface.destroy();
+        }
+        surface.commit();
+
+        assert!(client
+            .wait_for_protocol_error(&mut compositor, Duration::ZERO)
+            .is_none());
+    }
+
+    #[test]
+    fn invalid_timeline_is_an_error() {
+        let (mut compositor, mut client, _wl_compositor, manager) = setup();
+        let qh = client.queue_handle();
+        let fd = null_fd();
+        manager.import_timeline(fd.as_raw_fd(), &qh, Recorded);
+
+        let err = client
+            .wait_for_protocol_error(&mut compositor, Duration::from_secs(1))
+            .expect("no protocol error");
+        assert_eq!(
+            err.code,
+            wp_linux_drm_syncobj_manager_v1::Error::InvalidTimeline as u32
+        );
+    }
+}
@@ -0,0 +1,421 @@
+use std::{
+    io,
+    os::unix::io::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use calloop::{
+    generic::Generic,
+    ping::{make_ping, Ping, PingSource},
+    timer::{TimeoutAction, Timer},
+    EventSource, Interest, Mode, Poll as LoopPoll, PostAction, Readiness, Token, TokenFactory,
+};
+use drm::control::{syncobj, Device as ControlDevice};
+use tracing::{debug, warn};
+
+use crate::{backend::drm::DrmDeviceFd, wayland::compositor::Blocker};
+
+/// Argument of `DRM_IOCTL_SYNCOBJ_EVENTFD`, not wrapped by the drm crate yet
+#[repr(C)]
+struct DrmSyncobjEventfd {
+    handle: u32,
+    flags: u32,
+    point: u64,
+    fd: i32,
+    pad: u32,
+}
+
+nix::ioctl_readwrite!(drm_ioctl_syncobj_eventfd, b'd', 0xCF, DrmSyncobjEventfd);
+
+/// Interval of polling a point, if the kernel can not notify about it through an eventfd
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+#[derive(Debug)]
+struct DrmTimelineInner {
+    device: DrmDeviceFd,
+    syncobj: syncobj::Handle,
+}
+
+impl Drop for DrmTimelineInner {
+    fn drop(&mut self) {
+        let _ = self.device.destroy_syncobj(self.syncobj);
+    }
+}
+
+/// Timeline of a DRM synchronization object
+#[derive(Debug, Clone)]
+pub struct DrmTimeline(Arc<DrmTimelineInner>);
+
+impl PartialEq for DrmTimeline {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl DrmTimeline {
+    /// Import a timeline from a DRM syncobj file descriptor
+    pub fn new(device: &DrmDeviceFd, fd: BorrowedFd<'_>) -> Result<DrmTimeline, drm::SystemError> {
+        let syncobj = device.fd_to_syncobj(fd, false)?;
+        Ok(DrmTimeline(Arc::new(DrmTimelineInner {
+            device: device.clone(),
+            syncobj,
+        })))
+    }
+
+    /// Returns the latest signaled point of the timeline
+    pub fn query_signaled_point(&self) -> Result<u64, drm::SystemError> {
+        let mut points = [0];
+        self.0
+            .device
+            .syncobj_timeline_query(&[self.0.syncobj], &mut points, false)?;
+        Ok(points[0])
+    }
+}
+
+/// Point on a [`DrmTimeline`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrmSyncPoint {
+    pub(super) timeline: DrmTimeline,
+    pub(super) point: u64,
+}
+
+impl DrmSyncPoint {
+    /// Timeline of this point
+    pub fn timeline(&self) -> &DrmTimeline {
+        &self.timeline
+    }
+
+    /// Value of this point on its timeline
+    pub fn point(&self) -> u64 {
+        self.point
+    }
+
+    /// Returns `true` if the point was signaled
+    pub fn is_signaled(&self) -> Result<bool, drm::SystemError> {
+        Ok(self.timeline.query_signaled_point()? >= self.point)
+    }
+
+    /// Signal the point from the CPU
+    ///
+    /// Used to signal a release point, once the compositor is done with the buffer.
+    pub fn signal(&self) -> Result<(), drm::SystemError> {
+        let inner = &self.timeline.0;
+        inner
+            .device
+            .syncobj_timeline_signal(&[inner.syncobj], &[self.point])
+    }
+
+    /// Wait for the point to be signaled
+    ///
+    /// `deadline` is an absolute timestamp of the `CLOCK_MONOTONIC` clock in nanoseconds,
+    /// `i64::MAX` waits without timeout.
+    pub fn wait(&self, deadline: i64) -> Result<(), drm::SystemError> {
+        let inner = &self.timeline.0;
+        inner
+            .device
+            .syncobj_timeline_wait(&[inner.syncobj], &[self.point], deadline, true, true, false)
+            .map(|_| ())
+    }
+
+    /// Export the point as a sync file
+    ///
+    /// The point has to be submitted already. The returned fence can be used as the
+    /// `IN_FENCE_FD` of a plane, see [`PlaneConfig::fence`](crate::backend::drm::PlaneConfig::fence).
+    pub fn export_sync_file(&self) -> Result<OwnedFd, drm::SystemError> {
+        let inner = &self.timeline.0;
+        let tmp = inner.device.create_syncobj(false)?;
+        let res = inner
+            .device
+            .syncobj_timeline_transfer(inner.syncobj, tmp, self.point, 0)
+            .and_then(|_| inner.device.syncobj_to_fd(tmp, true));
+        let _ = inner.device.destroy_syncobj(tmp);
+        res
+    }
+
+    /// Create an eventfd, which becomes readable once this point is signaled
+    pub fn eventfd(&self) -> io::Result<OwnedFd> {
+        let inner = &self.timeline.0;
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Safety: the fd was just created and is not owned by anything else
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut args = DrmSyncobjEventfd {
+            handle: inner.syncobj.into(),
+            flags: 0,
+            point: self.point,
+            fd: fd.as_raw_fd(),
+            pad: 0,
+        };
+        // Safety: `args` is a valid drm_syncobj_eventfd for the duration of the call
+        unsafe { drm_ioctl_syncobj_eventfd(inner.device.as_raw_fd(), &mut args) }?;
+        Ok(fd)
+    }
+
+    /// Create a [`Blocker`] ready once this point is signaled, together with the event source
+    /// driving it
+    ///
+    /// The [`DrmSyncPointSource`] has to be inserted into the event loop. Its callback is invoked
+    /// once the point got signaled, which is the time to call
+    /// [`CompositorState::blocker_cleared`](crate::wayland::compositor::CompositorState::blocker_cleared).
+    /// Dropping the blocker, e.g. because the surface was destroyed before the point got signaled,
+    /// removes the source from the event loop without invoking the callback.
+    ///
+    /// Kernels without `DRM_IOCTL_SYNCOBJ_EVENTFD` (before 6.6) can not notify about the point,
+    /// in which case the source polls it instead.
+    pub fn generate_blocker(&self) -> (DrmSyncPointBlocker, DrmSyncPointSource) {
+        let wait = match self.eventfd() {
+            Ok(fd) => Wait::Eventfd(Generic::new(fd, Interest::READ, Mode::Level)),
+            Err(err) => {
+                debug!(
+                    ?err,
+                    "Failed to create eventfd for drm syncobj point, polling it instead"
+                );
+                Wait::Poll {
+                    timer: Timer::immediate(),
+                    sync_point: self.clone(),
+                }
+            }
+        };
+        let (ping, cancelled) = match make_ping() {
+            Ok((ping, source)) => (Some(ping), Some(source)),
+            Err(err) => {
+                warn!(
+                    ?err,
+                    "Failed to create ping, the drm syncobj source outlives its blocker"
+                );
+                (None, None)
+            }
+        };
+        let signal = Arc::new(Signal::default());
+        let blocker = DrmSyncPointBlocker {
+            sync_point: self.clone(),
+            signal: signal.clone(),
+            dropped: ping,
+        };
+        let source = DrmSyncPointSource {
+            wait,
+            cancelled,
+            signal,
+        };
+        (blocker, source)
+    }
+}
+
+#[derive(Debug)]
+struct ReleasePointInner {
+    sync_point: DrmSyncPoint,
+    signaled: AtomicBool,
+}
+
+impl Drop for ReleasePointInner {
+    fn drop(&mut self) {
+        if !self.signaled.load(Ordering::Acquire) {
+            if let Err(err) = self.sync_point.signal() {
+                warn!(?err, "Failed to signal drm syncobj release point");
+            }
+        }
+    }
+}
+
+/// Release point of a commit
+///
+/// The point is signaled once the last clone of the handle is dropped, unless it was signaled
+/// through [`DrmSyncReleasePoint::signal`] before. Keep a clone around for as long as the buffer
+/// of the commit is in use, e.g. until a frame using it was presented.
+#[derive(Debug, Clone)]
+pub struct DrmSyncReleasePoint(Arc<ReleasePointInner>);
+
+impl DrmSyncReleasePoint {
+    pub(super) fn new(sync_point: DrmSyncPoint) -> DrmSyncReleasePoint {
+        DrmSyncReleasePoint(Arc::new(ReleasePointInner {
+            sync_point,
+            signaled: AtomicBool::new(false),
+        }))
+    }
+
+    /// The point on its timeline
+    pub fn sync_point(&self) -> &DrmSyncPoint {
+        &self.0.sync_point
+    }
+
+    /// Signal the point right away, instead of once the last handle is dropped
+    pub fn signal(&self) -> Result<(), drm::SystemError> {
+        if self.0.signaled.swap(true, Ordering::AcqRel) {
+            return Ok(());
+        }
+        self.0.sync_point.signal()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Signal {
+    signaled: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Signal {
+    fn signal(&self) {
+        self.signaled.store(true, Ordering::Release);
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+/// [`Blocker`] waiting for a [`DrmSyncPoint`] to be signaled
+///
+/// The blocker is woken by its [`DrmSyncPointSource`], see [`DrmSyncPoint::generate_blocker`].
+#[derive(Debug)]
+pub struct DrmSyncPointBlocker {
+    sync_point: DrmSyncPoint,
+    signal: Arc<Signal>,
+    dropped: Option<Ping>,
+}
+
+impl Drop for DrmSyncPointBlocker {
+    fn drop(&mut self) {
+        // nothing waits for the point anymore, so the source can go away
+        if let Some(ping) = &self.dropped {
+            ping.ping();
+        }
+    }
+}
+
+impl Blocker for DrmSyncPointBlocker {
+    fn is_ready(&self) -> bool {
+        if self.signal.signaled.load(Ordering::Acquire) {
+            return true;
+        }
+        let signaled = self.sync_point.is_signaled().unwrap_or_else(|err| {
+            // do not block the surface forever on a broken timeline
+            warn!(?err, "Failed to query drm syncobj timeline");
+            true
+        });
+        if signaled {
+            self.signal.signaled.store(true, Ordering::Release);
+        }
+        signaled
+    }
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        *self.signal.waker.lock().unwrap() = Some(cx.waker().clone());
+        if self.is_ready() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Wait {
+    Eventfd(Generic<OwnedFd>),
+    Poll { timer: Timer, sync_point: DrmSyncPoint },
+}
+
+/// Event source signaling a [`DrmSyncPointBlocker`]
+///
+/// The callback is invoked a single time once the point got signaled, afterwards the source
+/// removes itself. It also removes itself once its blocker was dropped.
+/// See [`DrmSyncPoint::generate_blocker`].
+#[derive(Debug)]
+pub struct DrmSyncPointSource {
+    wait: Wait,
+    cancelled: Option<PingSource>,
+    signal: Arc<Signal>,
+}
+
+impl EventSource for DrmSyncPointSource {
+    type Event = ();
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> io::Result<PostAction>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let mut signaled = false;
+        let action = match &mut self.wait {
+            Wait::Eventfd(source) => source.process_events(readiness, token, |_, _| {
+                signaled = true;
+                Ok(PostAction::Remove)
+            })?,
+            Wait::Poll { timer, sync_point } => timer
+                .process_events(readiness, token, |_, _| {
+                    // do not block the surface forever on a broken timeline
+                    signaled = sync_point.is_signaled().unwrap_or(true);
+                    if signaled {
+                        TimeoutAction::Drop
+                    } else {
+                        TimeoutAction::ToDuration(POLL_INTERVAL)
+                    }
+                })
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?,
+        };
+
+        let mut cancelled = false;
+        if let Some(source) = self.cancelled.as_mut() {
+            source
+                .process_events(readiness, token, |_, _| cancelled = true)
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        }
+
+        if signaled {
+            self.signal.signal();
+            callback((), &mut ());
+            Ok(PostAction::Remove)
+        } else if cancelled {
+            Ok(PostAction::Remove)
+        } else {
+            Ok(action)
+        }
+    }
+
+    fn register(&mut self, poll: &mut LoopPoll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        match &mut self.wait {
+            Wait::Eventfd(source) => source.register(poll, token_factory)?,
+            Wait::Poll { timer, .. } => timer.register(poll, token_factory)?,
+        }
+        if let Some(source) = self.cancelled.as_mut() {
+            source.register(poll, token_factory)?;
+        }
+        Ok(())
+    }
+
+    fn reregister(&mut self, poll: &mut LoopPoll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        match &mut self.wait {
+            Wait::Eventfd(source) => source.reregister(poll, token_factory)?,
+            Wait::Poll { timer, .. } => timer.reregister(poll, token_factory)?,
+        }
+        if let Some(source) = self.cancelled.as_mut() {
+            source.reregister(poll, token_factory)?;
+        }
+        Ok(())
+    }
+
+    fn unregister(&mut self, poll: &mut LoopPoll) -> calloop::Result<()> {
+        match &mut self.wait {
+            Wait::Eventfd(source) => source.unregister(poll)?,
+            Wait::Poll { timer, .. } => timer.unregister(poll)?,
+        }
+        if let Some(source) = self.cancelled.as_mut() {
+            source.unregister(poll)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,340 @@
+//! Extension traits for types of `wayland_server`
+//!
+//! ## Client credentials
+//!
+//! The [`ClientExt`] trait exposes the credentials of the process on the other end of a client
+//! connection, as reported by the kernel when the client connected. They can be used to implement
+//! access control, like restricting privileged protocols to certain applications.
+//!
+//! ```no_run
+//! use smithay::wayland::ext::ClientExt;
+//! # fn example(client: &wayland_server::Client, dh: &wayland_server::DisplayHandle) {
+//!
+//! if client.uid(dh) != Some(0) && client.app_id_from_pid(dh).as_deref() != Some("org.example.Screenshot") {
+//!     // deny access to the screencopy global
+//! }
+//! # }
+//! ```
+//!
+//! Note that the pid of a client may be reused once the client exited and that a client can
+//! pass its connection to a different process, so these should not be the only line of defense.
+//!
+//! ### Credentials in global filters
+//!
+//! Global filter closures, like the one given to
+//! [`DmabufState::create_global_with_filter`](crate::wayland::dmabuf::DmabufState::create_global_with_filter),
+//! only receive the [`Client`] without a [`DisplayHandle`]. To make decisions there, capture the
+//! [`ClientCredentials`] when accepting the connection and store them in your client data:
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use smithay::wayland::ext::ClientCredentials;
+//! use wayland_server::{backend::ClientData, Client};
+//!
+//! struct ClientState {
+//!     credentials: Option<ClientCredentials>,
+//! }
+//! impl ClientData for ClientState {}
+//!
+//! # fn accept(dh: &mut wayland_server::DisplayHandle, stream: std::os::unix::net::UnixStream) {
+//! let credentials = ClientCredentials::from_stream(&stream);
+//! dh.insert_client(stream, Arc::new(ClientState { credentials })).unwrap();
+//! # }
+//!
+//! fn filter(client: &Client) -> bool {
+//!     client
+//!         .get_data::<ClientState>()
+//!         .and_then(|data| data.credentials.as_ref())
+//!         .and_then(|credentials| credentials.exe_path())
+//!         .map_or(false, |exe| exe == std::path::Path::new("/usr/bin/grim"))
+//! }
+//! ```
+//!
+//! ## Tracing
+//!
+//! [`ClientExt::span`] returns a span carrying the id and credentials of a client. Entering it
+//! while handling requests of the client attributes all messages logged meanwhile to it:
+//!
+//! ```no_run
+//! use smithay::wayland::ext::ClientExt;
+//! # fn example(client: &wayland_server::Client, dh: &wayland_server::DisplayHandle) {
+//!
+//! let _span = client.span(dh).entered();
+//! tracing::debug!("handled by the client span");
+//! # }
+//! ```
+
+use std::{
+    fs,
+    os::unix::{
+        io::{AsRawFd, FromRawFd, OwnedFd},
+        net::UnixStream,
+    },
+    path::PathBuf,
+};
+
+use nix::{
+    poll::{poll, PollFd, PollFlags},
+    sys::socket::{getsockopt, sockopt::PeerCredentials},
+};
+use tracing::debug_span;
+use wayland_server::{backend::Credentials, Client, DisplayHandle};
+
+/// Extension trait to query the credentials of a [`Client`]
+///
+/// All methods return `None` if the client is already disconnected.
+pub trait ClientExt {
+    /// Credentials of the client process at the time it connected
+    fn credentials(&self, dh: &DisplayHandle) -> Option<Credentials>;
+
+    /// Tracing span of the client
+    ///
+    /// The span carries the `client_id` of the client and the `pid` and `uid` of its process.
+    /// The credentials are only queried if the span is enabled.
+    fn span(&self, dh: &DisplayHandle) -> tracing::Span;
+
+    /// Process id of the client
+    fn pid(&self, dh: &DisplayHandle) -> Option<u32> {
+        self.credentials(dh)
+            .and_then(|credentials| u32::try_from(credentials.pid).ok())
+            .filter(|pid| *pid != 0)
+    }
+
+    /// User id of the client process
+    fn uid(&self, dh: &DisplayHandle) -> Option<u32> {
+        self.credentials(dh).map(|credentials| credentials.uid)
+    }
+
+    /// Group id of the client process
+    fn gid(&self, dh: &DisplayHandle) -> Option<u32> {
+        self.credentials(dh).map(|credentials| credentials.gid)
+    }
+
+    /// Best-effort guess of the application id of the client
+    ///
+    /// The application id is taken from the name of the systemd unit the process runs in,
+    /// if it was launched following the `app-<launcher>-<app id>` naming scheme used by
+    /// desktop environments and flatpak. Otherwise the file name of the executable, as found
+    /// in `/proc/<pid>/cmdline`, is returned.
+    ///
+    /// This reads from procfs, which is racy and may be spoofed by the client. It should not be
+    /// used for security decisions on its own.
+    fn app_id_from_pid(&self, dh: &DisplayHandle) -> Option<String> {
+        let pid = self.pid(dh)?;
+        fs::read_to_string(format!("/proc/{}/cgroup", pid))
+            .ok()
+            .and_then(|cgroup| app_id_from_cgroup(&cgroup))
+            .or_else(|| {
+                fs::read(format!("/proc/{}/cmdline", pid))
+                    .ok()
+                    .and_then(|cmdline| app_id_from_cmdline(&cmdline))
+            })
+    }
+
+    /// Path of the executable of the client process, read from `/proc/<pid>/exe`
+    ///
+    /// Racy like [`ClientExt::app_id_from_pid`], prefer [`ClientCredentials::exe_path`].
+    fn exe_path(&self, dh: &DisplayHandle) -> Option<PathBuf> {
+        fs::read_link(format!("/proc/{}/exe", self.pid(dh)?)).ok()
+    }
+
+    /// Contents of `/proc/<pid>/cgroup` of the client process
+    ///
+    /// Racy like [`ClientExt::app_id_from_pid`], prefer [`ClientCredentials::cgroup`].
+    fn cgroup(&self, dh: &DisplayHandle) -> Option<String> {
+        fs::read_to_string(format!("/proc/{}/cgroup", self.pid(dh)?)).ok()
+    }
+}
+
+impl ClientExt for Client {
+    fn credentials(&self, dh: &DisplayHandle) -> Option<Credentials> {
+        self.get_credentials(dh).ok()
+    }
+
+    fn span(&self, dh: &DisplayHandle) -> tracing::Span {
+        debug_span!(
+            "client",
+            client_id = ?self.id(),
+            pid = self.pid(dh),
+            uid = self.uid(dh),
+        )
+    }
+}
+
+/// Credentials of a client process, captured when accepting its connection
+///
+/// Unlike [`ClientExt`], which queries the kernel each time, this holds a pidfd of the client
+/// process where the kernel supports it (Linux 5.3 and later). Information read from procfs
+/// through [`ClientCredentials::exe_path`] and [`ClientCredentials::cgroup`] is discarded if
+/// the process exited meanwhile, so a process reusing its pid cannot be mistaken for the client.
+#[derive(Debug)]
+pub struct ClientCredentials {
+    pid: Option<u32>,
+    uid: u32,
+    gid: u32,
+    pidfd: Option<OwnedFd>,
+}
+
+impl ClientCredentials {
+    /// Query the credentials of the peer of a client stream
+    ///
+    /// Returns `None` if the stream has no peer credentials. Streams created with
+    /// [`UnixStream::pair`] report the credentials of the process which created the pair, which
+    /// is typically the compositor itself when it spawns a client with a pre-connected socket.
+    pub fn from_stream(stream: &UnixStream) -> Option<ClientCredentials> {
+        let credentials = getsockopt(stream.as_raw_fd(), PeerCredentials).ok()?;
+        let pid = u32::try_from(credentials.pid()).ok().filter(|pid| *pid != 0);
+        Some(ClientCredentials {
+            pid,
+            uid: credentials.uid(),
+            gid: credentials.gid(),
+            pidfd: pid.and_then(pidfd_open),
+        })
+    }
+
+    /// Process id of the client
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// User id of the client process
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// Group id of the client process
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// Returns `false` once the client process exited
+    ///
+    /// Always returns `true` if no pidfd could be opened for the process.
+    pub fn is_alive(&self) -> bool {
+        let Some(pidfd) = &self.pidfd else {
+            return true;
+        };
+        // a pidfd becomes readable once the process exited
+        let mut fds = [PollFd::new(pidfd.as_raw_fd(), PollFlags::POLLIN)];
+        !matches!(poll(&mut fds, 0), Ok(n) if n > 0)
+    }
+
+    /// Path of the executable of the client process, read from `/proc/<pid>/exe`
+    pub fn exe_path(&self) -> Option<PathBuf> {
+        self.read_proc(|pid| fs::read_link(format!("/proc/{}/exe", pid)).ok())
+    }
+
+    /// Contents of `/proc/<pid>/cgroup` of the client process
+    pub fn cgroup(&self) -> Option<String> {
+        self.read_proc(|pid| fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok())
+    }
+
+    /// Best-effort guess of the application id of the client
+    ///
+    /// See [`ClientExt::app_id_from_pid`].
+    pub fn app_id(&self) -> Option<String> {
+        self.cgroup()
+            .and_then(|cgroup| app_id_from_cgroup(&cgroup))
+            .or_else(|| {
+                self.read_proc(|pid| fs::read(format!("/proc/{}/cmdline", pid)).ok())
+                    .and_then(|cmdline| app_id_from_cmdline(&cmdline))
+            })
+    }
+
+    fn read_proc<T>(&self, read: impl FnOnce(u32) -> Option<T>) -> Option<T> {
+        let pid = self.pid?;
+        if !self.is_alive() {
+            return None;
+        }
+        let value = read(pid)?;
+        // the pid may have been reused while reading
+        self.is_alive().then_some(value)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn pidfd_open(pid: u32) -> Option<OwnedFd> {
+    // SAFETY: pidfd_open takes no pointers and returns a new fd on success
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+    // SAFETY: the fd was just created and is owned by nobody else
+    (fd >= 0).then(|| unsafe { OwnedFd::from_raw_fd(fd as i32) })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pidfd_open(_pid: u32) -> Option<OwnedFd> {
+    None
+}
+
+/// Extract the application id from the systemd unit in the contents of `/proc/<pid>/cgroup`
+///
+/// Units are named `app[-<launcher>]-<app id>[@<random>].service` or
+/// `app[-<launcher>]-<app id>-<random>.scope`, with dashes in the app id escaped as `\x2d`.
+fn app_id_from_cgroup(cgroup: &str) -> Option<String> {
+    let unit = cgroup
+        .lines()
+        .filter_map(|line| line.rsplit('/').next())
+        .find(|unit| unit.starts_with("app-"))?;
+    let name = unit.strip_prefix("app-")?;
+    let name = if let Some(scope) = name.strip_suffix(".scope") {
+        scope.rsplit_once('-').map_or(scope, |(name, _random)| name)
+    } else {
+        let service = name.strip_suffix(".service")?;
+        service.split_once('@').map_or(service, |(name, _random)| name)
+    };
+    let app_id = name.split_once('-').map_or(name, |(_launcher, app_id)| app_id);
+
+    (!app_id.is_empty()).then(|| app_id.replace("\\x2d", "-"))
+}
+
+/// Extract the executable name from the contents of `/proc/<pid>/cmdline`
+fn app_id_from_cmdline(cmdline: &[u8]) -> Option<String> {
+    let executable = cmdline.split(|byte| *byte == 0).next()?;
+    let name = executable.rsplit(|byte| *byte == b'/').next()?;
+    (!name.is_empty()).then(|| String::from_utf8_lossy(name).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net::UnixStream;
+
+    use super::{app_id_from_cgroup, app_id_from_cmdline, ClientCredentials};
+
+    #[test]
+    fn credentials_of_socket_pair() {
+        let (client, _server) = UnixStream::pair().unwrap();
+        let credentials = ClientCredentials::from_stream(&client).unwrap();
+        assert_eq!(credentials.pid(), Some(std::process::id()));
+        assert_eq!(credentials.uid(), nix::unistd::getuid().as_raw());
+        assert!(credentials.is_alive());
+        assert_eq!(credentials.exe_path(), std::env::current_exe().ok());
+    }
+
+    #[test]
+    fn app_id_from_systemd_unit() {
+        let cgroup = "0::/user.slice/user-1000.slice/user@1000.service/app.slice/app-gnome-org.gnome.Terminal-4242.scope\n";
+        assert_eq!(app_id_from_cgroup(cgroup).as_deref(), Some("org.gnome.Terminal"));
+
+        let cgroup = "0::/user.slice/user-1000.slice/user@1000.service/app.slice/app-flatpak-org.mozilla.firefox@12.service\n";
+        assert_eq!(app_id_from_cgroup(cgroup).as_deref(), Some("org.mozilla.firefox"));
+
+        let cgroup =
+            "0::/user.slice/user-1000.slice/user@1000.service/app.slice/app-kde-my\\x2dapp-1.scope\n";
+        assert_eq!(app_id_from_cgroup(cgroup).as_deref(), Some("my-app"));
+
+        let cgroup = "0::/user.slice/user-1000.slice/session-2.scope\n";
+        assert_eq!(app_id_from_cgroup(cgroup), None);
+    }
+
+    #[test]
+    fn app_id_from_executable() {
+        assert_eq!(
+            app_id_from_cmdline(b"/usr/bin/foot\0--server\0").as_deref(),
+            Some("foot")
+        );
+        assert_eq!(
+            app_id_from_cmdline(b"weston-terminal\0").as_deref(),
+            Some("weston-terminal")
+        );
+        assert_eq!(app_id_from_cmdline(b""), None);
+    }
+}
@@ -0,0 +1,617 @@
+//! Utilities for handling the `wp_fifo_v1` protocol
+//!
+//! This protocol lets clients pace their frames on the refresh cycle of the display, without
+//! relying on the implicit backpressure of `wl_surface.frame` callbacks. A client sets a barrier
+//! with a commit and makes a later commit wait for the barrier, which is cleared once the content
+//! of the first commit was presented.
+//!
+//! ## How to use it
+//!
+//! ### Initialization
+//!
+//! To initialize this implementation, create the [`FifoManagerState`], store it in your `State`
+//! struct and implement the required traits:
+//!
+//! ```no_run
+//! use smithay::delegate_fifo;
+//! use smithay::wayland::fifo::{FifoHandler, FifoManagerState};
+//!
+//! pub struct State {
+//!     fifo_manager_state: FifoManagerState,
+//! }
+//!
+//! impl FifoHandler for State {
+//!     fn fifo_manager_state(&mut self) -> &mut FifoManagerState {
+//!         &mut self.fifo_manager_state
+//!     }
+//! }
+//!
+//! delegate_fifo!(State);
+//!
+//! # let display = wayland_server::Display::<State>::new().unwrap();
+//! let fifo_manager_state = FifoManagerState::new::<State>(&display.handle());
+//! ```
+//!
+//! ### Waiting for barriers
+//!
+//! A commit waiting for a barrier is exposed as a [`Blocker`] through [`fifo_barrier_blocker`],
+//! which should be returned from
+//! [`CompositorHandler::commit_blocked_by`](crate::wayland::compositor::CompositorHandler::commit_blocked_by).
+//! The state of the surface is then held back until the barrier is cleared.
+//!
+//! ### Clearing barriers
+//!
+//! Once a frame containing the surface was presented, e.g. from the page-flip handler of a drm
+//! device, the compositor has to clear the barrier of the surface using [`signal_fifo_barrier`]
+//! and apply the states waiting for it using
+//! [`CompositorState::blocker_cleared`](crate::wayland::compositor::CompositorState::blocker_cleared).
+//! Surfaces that are not presented at all, e.g. because they are occluded, should have their
+//! barriers cleared as well, so their clients keep making progress.
+//!
+//! [`FifoHandler::surface_fifo_mode_changed`] notifies the compositor about surfaces using
+//! the protocol, so it can adjust its render scheduling for them.
+
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use wayland_server::{
+    backend::{ClientId, GlobalId, ObjectId},
+    protocol::wl_surface::WlSurface,
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+
+use crate::{
+    utils::IsAlive,
+    wayland::compositor::{self, with_states, Blocker, Cacheable},
+};
+
+/// Generated bindings of the `fifo-v1` protocol
+#[allow(missing_docs, non_upper_case_globals, non_camel_case_types, clippy::all)]
+pub mod protocol {
+    /// Server side of the protocol
+    pub mod server {
+        use wayland_server;
+        use wayland_server::protocol::*;
+
+        pub mod __interfaces {
+            use wayland_server::protocol::__interfaces::*;
+            wayland_scanner::generate_interfaces!("src/wayland/fifo/fifo-v1.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_server_code!("src/wayland/fifo/fifo-v1.xml");
+    }
+
+    /// Client side of the protocol, used by the tests
+    #[cfg(all(test, feature = "test_utils"))]
+    pub mod client {
+        use wayland_client;
+        use wayland_client::protocol::*;
+
+        pub mod __interfaces {
+            use wayland_client::protocol::__interfaces::*;
+            wayland_scanner::generate_interfaces!("src/wayland/fifo/fifo-v1.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_client_code!("src/wayland/fifo/fifo-v1.xml");
+    }
+}
+
+use protocol::server::{
+    wp_fifo_manager_v1::{self, WpFifoManagerV1},
+    wp_fifo_v1::{self, WpFifoV1},
+};
+
+/// Handler trait for the fifo protocol
+pub trait FifoHandler {
+    /// [`FifoManagerState`] getter
+    fn fifo_manager_state(&mut self) -> &mut FifoManagerState;
+
+    /// A surface started or stopped using the fifo protocol
+    ///
+    /// Surfaces using the protocol rely on the compositor clearing their barriers once per
+    /// refresh cycle, see [`signal_fifo_barrier`].
+    fn surface_fifo_mode_changed(&mut self, surface: &WlSurface, enabled: bool) {
+        let _ = (surface, enabled);
+    }
+}
+
+/// State of the wp_fifo_manager_v1 global
+#[derive(Debug)]
+pub struct FifoManagerState {
+    global: GlobalId,
+}
+
+impl FifoManagerState {
+    /// Create a new [`WpFifoManagerV1`] global
+    pub fn new<D>(display: &DisplayHandle) -> FifoManagerState
+    where
+        D: GlobalDispatch<WpFifoManagerV1, ()>
+            + Dispatch<WpFifoManagerV1, ()>
+            + Dispatch<WpFifoV1, FifoSurfaceData>
+            + FifoHandler
+            + 'static,
+    {
+        FifoManagerState {
+            global: display.create_global::<D, WpFifoManagerV1, ()>(1, ()),
+        }
+    }
+
+    /// Returns the fifo manager global.
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Barrier {
+    /// The commit setting the barrier was applied
+    applied: AtomicBool,
+    cleared: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Barrier {
+    fn clear(&self) {
+        self.cleared.store(true, Ordering::Release);
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Double-buffered fifo state of a surface
+#[derive(Debug, Default, Clone)]
+pub struct FifoCachedState {
+    /// The commit sets a barrier
+    pub set_barrier: bool,
+    /// The commit waits for the barrier set by a previous commit
+    pub wait_barrier: bool,
+    barrier: Option<Arc<Barrier>>,
+    waits_for: Option<Arc<Barrier>>,
+}
+
+impl Cacheable for FifoCachedState {
+    fn commit(&mut self, _dh: &DisplayHandle) -> Self {
+        // barriers only apply to a single commit
+        std::mem::take(self)
+    }
+
+    fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        *into = self;
+    }
+}
+
+/// Barriers that were set but not cleared yet, oldest first
+type Barriers = Arc<Mutex<VecDeque<Arc<Barrier>>>>;
+
+/// Fifo state of a surface, independent of its commits
+#[derive(Debug, Default)]
+struct FifoSurfaceState {
+    fifo: Option<WpFifoV1>,
+    barriers: Barriers,
+}
+
+type FifoSurfaceMarker = Mutex<FifoSurfaceState>;
+
+/// Returns a [`Blocker`] for the pending state of the surface, if it waits for a barrier
+///
+/// Intended to be returned from
+/// [`CompositorHandler::commit_blocked_by`](crate::wayland::compositor::CompositorHandler::commit_blocked_by).
+pub fn fifo_barrier_blocker(surface: &WlSurface) -> Option<Box<dyn Blocker + Unpin>> {
+    let barrier = with_states(surface, |states| {
+        states.cached_state.pending::<FifoCachedState>().waits_for.clone()
+    })?;
+    if barrier.cleared.load(Ordering::Acquire) {
+        return None;
+    }
+    Some(Box::new(FifoBarrierBlocker(barrier)))
+}
+
+/// Clear the barriers of a surface, after the content setting them was presented
+///
+/// Returns `true` if a barrier was cleared, in which case
+/// [`CompositorState::blocker_cleared`](crate::wayland::compositor::CompositorState::blocker_cleared)
+/// has to be called to apply the states waiting for it. All barriers of applied states are
+/// cleared, barriers of states that were not applied yet are left untouched.
+pub fn signal_fifo_barrier(surface: &WlSurface) -> bool {
+    with_states(surface, |states| {
+        let Some(marker) = states.data_map.get::<FifoSurfaceMarker>() else {
+            return false;
+        };
+        let fifo_state = marker.lock().unwrap();
+        let mut barriers = fifo_state.barriers.lock().unwrap();
+        let mut cleared = false;
+        // states are applied in order, so applied barriers are always the oldest ones
+        while let Some(barrier) = barriers.front() {
+            if !barrier.applied.load(Ordering::Acquire) {
+                break;
+            }
+            barriers.pop_front().unwrap().clear();
+            cleared = true;
+        }
+        cleared
+    })
+}
+
+/// [`Blocker`] waiting for the fifo barrier of a surface to be cleared
+#[derive(Debug)]
+pub struct FifoBarrierBlocker(Arc<Barrier>);
+
+impl Blocker for FifoBarrierBlocker {
+    fn is_ready(&self) -> bool {
+        self.0.cleared.load(Ordering::Acquire)
+    }
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        *self.0.waker.lock().unwrap() = Some(cx.waker().clone());
+        if self.is_ready() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Data associated with a [`WpFifoV1`]
+#[derive(Debug)]
+pub struct FifoSurfaceData {
+    surface: WlSurface,
+    barriers: Barriers,
+}
+
+impl<D> GlobalDispatch<WpFifoManagerV1, (), D> for FifoManagerState
+where
+    D: GlobalDispatch<WpFifoManagerV1, ()>
+        + Dispatch<WpFifoManagerV1, ()>
+        + Dispatch<WpFifoV1, FifoSurfaceData>
+        + FifoHandler
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<WpFifoManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D> Dispatch<WpFifoManagerV1, (), D> for FifoManagerState
+where
+    D: GlobalDispatch<WpFifoManagerV1, ()>
+        + Dispatch<WpFifoManagerV1, ()>
+        + Dispatch<WpFifoV1, FifoSurfaceData>
+        + FifoHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        resource: &WpFifoManagerV1,
+        request: wp_fifo_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_fifo_manager_v1::Request::GetFifo { id, surface } => {
+                let (new, already_exists, barriers) = with_states(&surface, |states| {
+                    let new = states
+                        .data_map
+                        .insert_if_missing_threadsafe(FifoSurfaceMarker::default);
+                    let fifo_state = states
+                        .data_map
+                        .get::<FifoSurfaceMarker>()
+                        .unwrap()
+                        .lock()
+                        .unwrap();
+                    (new, fifo_state.fifo.is_some(), fifo_state.barriers.clone())
+                });
+                if already_exists {
+                    resource.post_error(
+                        wp_fifo_manager_v1::Error::AlreadyExists as u32,
+                        "the surface already has a fifo object associated".to_string(),
+                    );
+                    return;
+                }
+
+                if new {
+                    compositor::add_pre_commit_hook(&surface, pre_commit_hook);
+                    compositor::add_post_commit_hook(&surface, post_commit_hook);
+                }
+
+                let fifo = data_init.init(
+                    id,
+                    FifoSurfaceData {
+                        surface: surface.clone(),
+                        barriers,
+                    },
+                );
+                with_states(&surface, |states| {
+                    states
+                        .data_map
+                        .get::<FifoSurfaceMarker>()
+                        .unwrap()
+                        .lock()
+                        .unwrap()
+                        .fifo = Some(fifo);
+                });
+                state.surface_fifo_mode_changed(&surface, true);
+            }
+            wp_fifo_manager_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WpFifoV1, FifoSurfaceData, D> for FifoManagerState
+where
+    D: Dispatch<WpFifoV1, FifoSurfaceData> + FifoHandler,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &WpFifoV1,
+        request: wp_fifo_v1::Request,
+        data: &FifoSurfaceData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let is_set = match request {
+            wp_fifo_v1::Request::SetBarrier => true,
+            wp_fifo_v1::Request::WaitBarrier => false,
+            // handled in `destroyed`
+            wp_fifo_v1::Request::Destroy => return,
+            _ => unreachable!(),
+        };
+
+        if !data.surface.alive() {
+            resource.post_error(
+                wp_fifo_v1::Error::SurfaceDestroyed as u32,
+                "the wl_surface was destroyed".to_string(),
+            );
+            return;
+        }
+
+        with_states(&data.surface, |states| {
+            let mut pending = states.cached_state.pending::<FifoCachedState>();
+            if is_set {
+                pending.set_barrier = true;
+            } else {
+                pending.wait_barrier = true;
+            }
+        });
+    }
+
+    fn destroyed(state: &mut D, _client: ClientId, _object_id: ObjectId, data: &FifoSurfaceData) {
+        // nobody is going to wait for the barriers anymore. On client disconnect the surface is
+        // usually destroyed first, so this must not depend on the state of the surface.
+        for barrier in data.barriers.lock().unwrap().drain(..) {
+            barrier.clear();
+        }
+
+        if !data.surface.alive() {
+            return;
+        }
+        with_states(&data.surface, |states| {
+            if let Some(marker) = states.data_map.get::<FifoSurfaceMarker>() {
+                marker.lock().unwrap().fifo = None;
+            }
+        });
+        state.surface_fifo_mode_changed(&data.surface, false);
+    }
+}
+
+fn pre_commit_hook(_dh: &DisplayHandle, surface: &WlSurface) {
+    with_states(surface, |states| {
+        let Some(marker) = states.data_map.get::<FifoSurfaceMarker>() else {
+            return;
+        };
+        let fifo_state = marker.lock().unwrap();
+        let mut barriers = fifo_state.barriers.lock().unwrap();
+        let mut pending = states.cached_state.pending::<FifoCachedState>();
+
+        // wait for the latest barrier of previous commits, before this commit sets a new one
+        if pending.wait_barrier {
+            pending.waits_for = barriers.back().cloned();
+        }
+        if pending.set_barrier {
+            let barrier = Arc::new(Barrier::default());
+            pending.barrier = Some(barrier.clone());
+            barriers.push_back(barrier);
+        }
+    });
+}
+
+fn post_commit_hook(_dh: &DisplayHandle, surface: &WlSurface) {
+    with_states(surface, |states| {
+        if let Some(barrier) = &states.cached_state.current::<FifoCachedState>().barrier {
+            barrier.applied.store(true, Ordering::Release);
+        }
+    });
+}
+
+/// Macro to delegate implementation of the fifo protocol to [`FifoManagerState`].
+///
+/// You must also implement [`FifoHandler`] to use this.
+#[macro_export]
+macro_rules! delegate_fifo {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::fifo::protocol::server::wp_fifo_manager_v1::WpFifoManagerV1: ()
+        ] => $crate::wayland::fifo::FifoManagerState);
+
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::fifo::protocol::server::wp_fifo_manager_v1::WpFifoManagerV1: ()
+        ] => $crate::wayland::fifo::FifoManagerState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::fifo::protocol::server::wp_fifo_v1::WpFifoV1: $crate::wayland::fifo::FifoSurfaceData
+        ] => $crate::wayland::fifo::FifoManagerState);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::Ordering, Arc};
+
+    use super::{Barrier, FifoBarrierBlocker};
+    use crate::wayland::compositor::Blocker;
+
+    #[test]
+    fn blocker_is_ready_once_barrier_is_cleared() {
+        let barrier = Arc::new(Barrier::default());
+        let blocker = FifoBarrierBlocker(barrier.clone());
+        assert!(!blocker.is_ready());
+
+        barrier.applied.store(true, Ordering::Release);
+        barrier.clear();
+        assert!(blocker.is_ready());
+    }
+}
+
+#[cfg(all(test, feature = "test_utils"))]
+mod client_tests {
+    use std::sync::atomic::Ordering;
+
+    use wayland_client::protocol::wl_compositor::WlCompositor;
+    use wayland_server::protocol::wl_surface::WlSurface;
+
+    use super::{
+        fifo_barrier_blocker,
+        protocol::client::{wp_fifo_manager_v1::WpFifoManagerV1, wp_fifo_v1::WpFifoV1},
+        signal_fifo_barrier, FifoHandler, FifoManagerState, FifoSurfaceMarker,
+    };
+    use crate::{
+        testing::{Recorded, TestClient, TestCompositor},
+        utils::IsAlive,
+        wayland::compositor::{with_states, Blocker, CompositorHandler, CompositorState},
+    };
+
+    struct State {
+        compositor_state: CompositorState,
+        fifo_manager_state: FifoManagerState,
+        surface: Option<WlSurface>,
+        commits: usize,
+    }
+
+    impl CompositorHandler for State {
+        fn compositor_state(&mut self) -> &mut CompositorState {
+            &mut self.compositor_state
+        }
+
+        fn commit(&mut self, surface: &WlSurface) {
+            self.surface = Some(surface.clone());
+            self.commits += 1;
+        }
+
+        fn commit_blocked_by(&mut self, surface: &WlSurface) -> Option<Box<dyn Blocker + Unpin>> {
+            fifo_barrier_blocker(surface)
+        }
+    }
+
+    impl FifoHandler for State {
+        fn fifo_manager_state(&mut self) -> &mut FifoManagerState {
+            &mut self.fifo_manager_state
+        }
+    }
+
+    crate::delegate_compositor!(State);
+    crate::delegate_fifo!(State);
+
+    /// Clear the barriers of the surface like a presented frame would
+    fn present(compositor: &mut TestCompositor<State>) -> bool {
+        let surface = compositor.state.surface.clone().unwrap();
+        let cleared = signal_fifo_barrier(&surface);
+        let dh = compositor.handle();
+        CompositorState::blocker_cleared(&mut compositor.state, &dh);
+        cleared
+    }
+
+    /// Connect a client running `commits` iterations of the usual fifo loop, where every commit
+    /// sets a barrier and waits for the one of the previous commit
+    fn fifo_loop(commits: usize) -> (TestCompositor<State>, TestClient, WpFifoV1) {
+        let mut compositor = TestCompositor::new(|dh, _| State {
+            compositor_state: CompositorState::new::<State>(dh),
+            fifo_manager_state: FifoManagerState::new::<State>(dh),
+            surface: None,
+            commits: 0,
+        });
+        let mut client = compositor.connect();
+        let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+        let manager = client.bind::<WpFifoManagerV1, _>(&mut compositor);
+        let qh = client.queue_handle();
+        let surface = wl_compositor.create_surface(&qh, Recorded);
+        let fifo = manager.get_fifo(&surface, &qh, Recorded);
+
+        for _ in 0..commits {
+            fifo.set_barrier();
+            fifo.wait_barrier();
+            surface.commit();
+        }
+        client.roundtrip(&mut compositor);
+        (compositor, client, fifo)
+    }
+
+    #[test]
+    fn back_to_back_barriers_are_released_in_order() {
+        let (mut compositor, _client, _fifo) = fifo_loop(3);
+        // the first commit has no previous barrier, the second one waits for it
+        assert_eq!(compositor.state.commits, 1);
+
+        assert!(present(&mut compositor));
+        assert_eq!(compositor.state.commits, 2);
+        assert!(present(&mut compositor));
+        assert_eq!(compositor.state.commits, 3);
+
+        // the barrier of the last commit is cleared, nothing is left afterwards
+        assert!(present(&mut compositor));
+        assert!(!present(&mut compositor));
+    }
+
+    #[test]
+    fn destroying_fifo_releases_waiting_commits() {
+        let (mut compositor, mut client, fifo) = fifo_loop(3);
+        assert_eq!(compositor.state.commits, 1);
+
+        fifo.destroy();
+        client.roundtrip(&mut compositor);
+        let dh = compositor.handle();
+        CompositorState::blocker_cleared(&mut compositor.state, &dh);
+        assert_eq!(compositor.state.commits, 3);
+    }
+
+    #[test]
+    fn disconnect_clears_barriers() {
+        let (mut compositor, client, _fifo) = fifo_loop(3);
+        assert!(compositor.state.compositor_state.has_blocked_states());
+        let surface = compositor.state.surface.clone().unwrap();
+        let barriers = with_states(&surface, |states| {
+            let marker = states.data_map.get::<FifoSurfaceMarker>().unwrap();
+            marker.lock().unwrap().barriers.clone()
+        });
+        let barriers = barriers.lock().unwrap().iter().cloned().collect::<Vec<_>>();
+        assert_eq!(barriers.len(), 3);
+
+        drop(client);
+        compositor.advance();
+        assert!(!surface.alive());
+        assert!(barriers
+            .iter()
+            .all(|barrier| barrier.cleared.load(Ordering::Acquire)));
+        let dh = compositor.handle();
+        CompositorState::blocker_cleared(&mut compositor.state, &dh);
+        assert!(!compositor.state.compositor_state.has_blocked_states());
+    }
+}
@@ -44,22 +44,48 @@
 //! clients about whether they are currently visible or not (allowing them to stop drawing if they
 //! are not, for example).
 //!
+//! ## Restricting globals
+//!
+//! Which globals a client can see and bind is decided by
+//! [`GlobalDispatch::can_view`](wayland_server::GlobalDispatch::can_view) of every global.
+//! `wayland-server` offers no hook on the [`DisplayHandle`](wayland_server::DisplayHandle) to
+//! filter all globals at once. Globals which typically need to be restricted, like
+//! [`dmabuf`] or [`virtual_keyboard`], instead accept a filter closure on creation, e.g.
+//! [`DmabufState::create_global_with_filter`](dmabuf::DmabufState::create_global_with_filter).
+//! Those closures can base their decision on [`ext::ClientCredentials`] stored in the client data.
+//!
+//! ## Protocol debugging
+//!
+//! Messages exchanged with clients can be logged by setting the `WAYLAND_DEBUG` environment
+//! variable to `server` (or `1`) before creating the [`Display`](wayland_server::Display).
+//! Every request and event is then printed to stderr together with the object id, interface
+//! and message name and its arguments. `wayland-server` does not expose a hook to intercept
+//! messages, so this output cannot be redirected to a custom logger.
+//!
 
 pub mod buffer;
+pub mod color_management;
 pub mod compositor;
 pub mod data_control;
 pub mod data_device;
 pub mod dmabuf;
+#[cfg(feature = "backend_drm")]
+pub mod drm_syncobj;
+pub mod ext;
+pub mod fifo;
 pub mod fractional_scale;
 pub mod input_method;
 pub mod keyboard_shortcuts_inhibit;
 pub mod output;
+pub mod pointer_constraints;
 pub mod presentation;
 pub mod primary_selection;
 pub mod relative_pointer;
 pub mod seat;
+pub mod selection;
 pub mod shell;
 pub mod shm;
+pub mod shutdown;
 pub mod socket;
 pub mod tablet_manager;
 pub mod text_input;
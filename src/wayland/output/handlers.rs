@@ -37,7 +37,7 @@ where
 
         let mut inner = global_data.inner.0.lock().unwrap();
 
-        let span = warn_span!("output_bind", name = inner.name);
+        let span = warn_span!(parent: &inner.span, "output_bind");
         let _enter = span.enter();
 
         trace!("New WlOutput global instantiated");
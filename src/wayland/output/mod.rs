@@ -8,9 +8,13 @@
 //!
 //! After you have instantiated an [`Output`] you need to use [`Output::create_global`]
 //! to advertise a new output global to clients.
-//! The resulting `GlobalId` can later be destroyed again to stop advertising it
+//! The global can later be removed again using [`Output::destroy_global`] to stop advertising it
 //! without destroying it's state. E.g. in case the matching physical output got disabled at runtime.
 //!
+//! An [`Output`] without a global is not visible to clients, but can otherwise be used as usual,
+//! e.g. for virtual outputs used for screencasting. Events referring to an output, like
+//! `wl_surface.enter`, are only sent to clients that have bound its global.
+//!
 //! If you change the properties of your output (if the current resolution mode changes for example),
 //! it'll automatically forward any changes to the clients.
 //!
@@ -155,24 +159,48 @@ impl Output {
     /// Create a new output global.
     ///
     /// The global is directly registered into the event loop, and this function
-    /// returns the global handle in case you wish to remove this global in the future.
+    /// returns the global handle.
     ///
-    /// Calling this function multiple times without destroying the global in between,
-    /// will result in multiple globals, meaning the output will be advertised to clients
-    /// multiple times.
+    /// Calling this function multiple times without destroying the global in between
+    /// returns the existing global. Use [`Output::destroy_global`] to remove it again.
     pub fn create_global<D>(&self, display: &DisplayHandle) -> GlobalId
     where
         D: GlobalDispatch<WlOutput, WlOutputData>,
         D: 'static,
     {
-        info!(output = self.name(), "Creating new wl_output");
-        self.inner.0.lock().unwrap().handle = Some(display.backend_handle().downgrade());
-        display.create_global::<D, WlOutput, _>(
+        let mut inner = self.inner.0.lock().unwrap();
+        if let Some(global) = inner.global.as_ref() {
+            return global.clone();
+        }
+
+        info!(output = inner.name, "Creating new wl_output");
+        inner.handle = Some(display.backend_handle().downgrade());
+        let global = display.create_global::<D, WlOutput, _>(
             4,
             WlOutputData {
                 inner: self.inner.clone(),
             },
-        )
+        );
+        inner.global = Some(global.clone());
+        global
+    }
+
+    /// Remove the output global created by [`Output::create_global`], if any.
+    ///
+    /// Clients can no longer bind the output, but `wl_output` instances already bound
+    /// stay valid until the clients release them and continue to receive updates.
+    /// The [`Output`] itself stays usable and a new global can be created later on.
+    pub fn destroy_global<D: 'static>(&self, display: &DisplayHandle) {
+        let global = self.inner.0.lock().unwrap().global.take();
+        if let Some(global) = global {
+            info!(output = self.name(), "Removing wl_output");
+            display.remove_global::<D>(global);
+        }
+    }
+
+    /// Returns the output global, if it currently exists
+    pub fn global(&self) -> Option<GlobalId> {
+        self.inner.0.lock().unwrap().global.clone()
     }
 
     /// Attempt to retrieve a [`Output`] from an existing resource
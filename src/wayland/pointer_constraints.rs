@@ -0,0 +1,881 @@
+//! Utilities for pointer constraints support
+//!
+//! The `zwp_pointer_constraints_v1` protocol lets clients lock the pointer in place or confine
+//! it to a region of one of their surfaces. Constraints are requested by the client, but only
+//! take effect once the compositor activates them, usually when the pointer enters the surface
+//! within the constraint region.
+//!
+//! ```no_run
+//! # extern crate wayland_server;
+//! use smithay::delegate_pointer_constraints;
+//! use smithay::input::{pointer::PointerHandle, SeatHandler};
+//! use smithay::wayland::pointer_constraints::{
+//!     activate_pointer_constraint, with_pointer_constraint, PointerConstraintsHandler,
+//!     PointerConstraintsState,
+//! };
+//! use wayland_server::protocol::wl_surface::WlSurface;
+//! # use smithay::input::SeatState;
+//! # use smithay::utils::{Logical, Point};
+//!
+//! # struct State { seat_state: SeatState<State> }
+//! # impl SeatHandler for State {
+//! #     type KeyboardFocus = WlSurface;
+//! #     type PointerFocus = WlSurface;
+//! #     fn seat_state(&mut self) -> &mut SeatState<Self> { &mut self.seat_state }
+//! # }
+//! impl PointerConstraintsHandler for State {
+//!     fn new_constraint(&mut self, surface: &WlSurface, pointer: &PointerHandle<Self>) {
+//!         // activate the constraint right away, if the pointer is above the surface
+//!         if pointer.current_focus().as_ref() == Some(surface) {
+//!             activate_pointer_constraint(surface, pointer);
+//!         }
+//!     }
+//! }
+//! delegate_pointer_constraints!(State);
+//!
+//! # let display = wayland_server::Display::<State>::new().unwrap();
+//! let state = PointerConstraintsState::new::<State>(&display.handle());
+//!
+//! // while handling pointer motion, check the constraint before moving the pointer
+//! # fn motion(surface: &WlSurface, pointer: &PointerHandle<State>, surface_local: Point<f64, Logical>) {
+//! let locked = with_pointer_constraint(surface, pointer, |constraint| {
+//!     constraint.map_or(false, |constraint| constraint.is_active() && constraint.is_locked())
+//! });
+//! # }
+//! ```
+//!
+//! ## Cursor position hints
+//!
+//! Clients holding a lock can tell where they drew the cursor using a cursor position hint.
+//! When the lock is deactivated using [`deactivate_pointer_constraint`] or destroyed by the client
+//! while active, the pointer is warped to the last committed hint, if the pointer is still above
+//! the locked surface. The hint is clamped to the constraint region and, if known, to the size of
+//! the surface. Constraints deactivated because the pointer left the surface do not apply their hint.
+
+use std::{cell::RefCell, fmt};
+
+use tracing::trace;
+use wayland_protocols::wp::pointer_constraints::zv1::server::{
+    zwp_confined_pointer_v1::{self, ZwpConfinedPointerV1},
+    zwp_locked_pointer_v1::{self, ZwpLockedPointerV1},
+    zwp_pointer_constraints_v1::{self, Lifetime, ZwpPointerConstraintsV1},
+};
+use wayland_server::{
+    backend::{ClientId, GlobalId, ObjectId},
+    protocol::{wl_region::WlRegion, wl_surface::WlSurface},
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, WEnum,
+};
+
+use crate::{
+    backend::renderer::utils::RendererSurfaceStateUserData,
+    input::{pointer::PointerHandle, SeatHandler},
+    utils::{
+        clock::{Clock, Monotonic},
+        Logical, Point, Rectangle, SERIAL_COUNTER,
+    },
+    wayland::{
+        compositor::{self, with_states, RectangleKind, RegionAttributes},
+        seat::{PointerUserData, WaylandFocus},
+    },
+};
+
+const VERSION: u32 = 1;
+
+/// Handler trait for pointer constraints
+pub trait PointerConstraintsHandler: SeatHandler {
+    /// A client requested a new constraint of `pointer` on `surface`
+    ///
+    /// The constraint is inactive, use [`activate_pointer_constraint`] once it should take effect.
+    fn new_constraint(&mut self, surface: &WlSurface, pointer: &PointerHandle<Self>);
+}
+
+/// State of the pointer constraints global
+#[derive(Debug)]
+pub struct PointerConstraintsState {
+    global: GlobalId,
+}
+
+impl PointerConstraintsState {
+    /// Register a new [ZwpPointerConstraintsV1] global
+    pub fn new<D>(display: &DisplayHandle) -> Self
+    where
+        D: GlobalDispatch<ZwpPointerConstraintsV1, ()>,
+        D: Dispatch<ZwpPointerConstraintsV1, ()>,
+        D: Dispatch<ZwpConfinedPointerV1, PointerConstraintUserData<D>>,
+        D: Dispatch<ZwpLockedPointerV1, PointerConstraintUserData<D>>,
+        D: SeatHandler,
+        D: 'static,
+    {
+        let global = display.create_global::<D, ZwpPointerConstraintsV1, _>(VERSION, ());
+
+        Self { global }
+    }
+
+    /// [ZwpPointerConstraintsV1] GlobalId getter
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+}
+
+/// A constraint imposed on a pointer by a client
+#[derive(Debug)]
+pub enum PointerConstraint {
+    /// The pointer is confined to a region of the surface
+    Confined(ConfinedPointer),
+    /// The pointer is locked in place
+    Locked(LockedPointer),
+}
+
+/// A pointer confinement
+#[derive(Debug)]
+pub struct ConfinedPointer {
+    handle: ZwpConfinedPointerV1,
+    region: Option<RegionAttributes>,
+    pending_region: Option<Option<RegionAttributes>>,
+    lifetime: Lifetime,
+    active: bool,
+}
+
+/// A pointer lock
+#[derive(Debug)]
+pub struct LockedPointer {
+    handle: ZwpLockedPointerV1,
+    region: Option<RegionAttributes>,
+    pending_region: Option<Option<RegionAttributes>>,
+    cursor_position_hint: Option<Point<f64, Logical>>,
+    pending_cursor_position_hint: Option<Point<f64, Logical>>,
+    lifetime: Lifetime,
+    active: bool,
+}
+
+impl LockedPointer {
+    /// Last committed cursor position hint, in surface-local coordinates
+    pub fn cursor_position_hint(&self) -> Option<Point<f64, Logical>> {
+        self.cursor_position_hint
+    }
+}
+
+impl PointerConstraint {
+    /// Whether the constraint is currently active
+    pub fn is_active(&self) -> bool {
+        match self {
+            PointerConstraint::Confined(confined) => confined.active,
+            PointerConstraint::Locked(locked) => locked.active,
+        }
+    }
+
+    /// Whether the constraint is a lock
+    pub fn is_locked(&self) -> bool {
+        matches!(self, PointerConstraint::Locked(_))
+    }
+
+    /// Committed region of the constraint, in surface-local coordinates
+    ///
+    /// `None` means the constraint applies to the whole input region of the surface.
+    pub fn region(&self) -> Option<&RegionAttributes> {
+        match self {
+            PointerConstraint::Confined(confined) => confined.region.as_ref(),
+            PointerConstraint::Locked(locked) => locked.region.as_ref(),
+        }
+    }
+
+    fn lifetime(&self) -> Lifetime {
+        match self {
+            PointerConstraint::Confined(confined) => confined.lifetime,
+            PointerConstraint::Locked(locked) => locked.lifetime,
+        }
+    }
+
+    fn set_active(&mut self, active: bool) {
+        match self {
+            PointerConstraint::Confined(confined) if confined.active != active => {
+                confined.active = active;
+                if active {
+                    confined.handle.confined();
+                } else {
+                    confined.handle.unconfined();
+                }
+            }
+            PointerConstraint::Locked(locked) if locked.active != active => {
+                locked.active = active;
+                if active {
+                    locked.handle.locked();
+                } else {
+                    locked.handle.unlocked();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn commit(&mut self) {
+        match self {
+            PointerConstraint::Confined(confined) => {
+                if let Some(region) = confined.pending_region.take() {
+                    confined.region = region;
+                }
+            }
+            PointerConstraint::Locked(locked) => {
+                if let Some(region) = locked.pending_region.take() {
+                    locked.region = region;
+                }
+                if let Some(hint) = locked.pending_cursor_position_hint.take() {
+                    locked.cursor_position_hint = Some(hint);
+                }
+            }
+        }
+    }
+
+    fn object_id(&self) -> ObjectId {
+        match self {
+            PointerConstraint::Confined(confined) => confined.handle.id(),
+            PointerConstraint::Locked(locked) => locked.handle.id(),
+        }
+    }
+}
+
+/// Constraints of a surface, with the pointer they apply to
+struct PointerConstraintData<D: SeatHandler> {
+    constraints: Vec<(PointerHandle<D>, PointerConstraint)>,
+}
+
+type PointerConstraintUserDataMap<D> = RefCell<PointerConstraintData<D>>;
+
+/// Marks surfaces the commit hook of the constraints was added to
+struct PointerConstraintsCommitHookMarker;
+
+fn with_constraints<D, T, F>(surface: &WlSurface, f: F) -> T
+where
+    D: SeatHandler + 'static,
+    F: FnOnce(&mut Vec<(PointerHandle<D>, PointerConstraint)>) -> T,
+{
+    with_states(surface, |states| {
+        states.data_map.insert_if_missing(|| {
+            RefCell::new(PointerConstraintData::<D> {
+                constraints: Vec::new(),
+            })
+        });
+        let data = states.data_map.get::<PointerConstraintUserDataMap<D>>().unwrap();
+        f(&mut data.borrow_mut().constraints)
+    })
+}
+
+/// Access the constraint of `pointer` on `surface`, if any
+pub fn with_pointer_constraint<D, T, F>(surface: &WlSurface, pointer: &PointerHandle<D>, f: F) -> T
+where
+    D: SeatHandler + 'static,
+    F: FnOnce(Option<&PointerConstraint>) -> T,
+{
+    with_constraints::<D, _, _>(surface, |constraints| {
+        f(constraints
+            .iter()
+            .find(|(handle, _)| handle == pointer)
+            .map(|(_, constraint)| constraint))
+    })
+}
+
+/// Activate the constraint of `pointer` on `surface`, if any
+///
+/// This notifies the client that the pointer is now locked or confined.
+pub fn activate_pointer_constraint<D>(surface: &WlSurface, pointer: &PointerHandle<D>)
+where
+    D: SeatHandler + 'static,
+{
+    with_constraints::<D, _, _>(surface, |constraints| {
+        if let Some((_, constraint)) = constraints.iter_mut().find(|(handle, _)| handle == pointer) {
+            constraint.set_active(true);
+        }
+    })
+}
+
+/// Deactivate the constraint of `pointer` on `surface`, if any
+///
+/// This notifies the client that the pointer is no longer locked or confined. One-shot
+/// constraints are dead afterwards, persistent ones may be activated again.
+///
+/// If the constraint is an active lock with a committed cursor position hint and the pointer
+/// is still above `surface`, the pointer is warped to the hint.
+pub fn deactivate_pointer_constraint<D>(
+    data: &mut D,
+    surface: &WlSurface,
+    pointer: &PointerHandle<D>,
+    time: u32,
+) where
+    D: SeatHandler + 'static,
+    <D as SeatHandler>::PointerFocus: WaylandFocus,
+{
+    let hint = with_constraints::<D, _, _>(surface, |constraints| {
+        let idx = constraints.iter().position(|(handle, _)| handle == pointer)?;
+        deactivate(constraints, idx)
+    });
+    if let Some(hint) = hint {
+        apply_cursor_position_hint(data, surface, pointer, hint, time);
+    }
+}
+
+/// Deactivate the constraint at `idx`, returning the cursor position hint to apply
+fn deactivate<D: SeatHandler>(
+    constraints: &mut Vec<(PointerHandle<D>, PointerConstraint)>,
+    idx: usize,
+) -> Option<Point<f64, Logical>> {
+    let (_, constraint) = &mut constraints[idx];
+    let hint = active_lock_hint(constraint);
+    constraint.set_active(false);
+    if constraint.lifetime() == Lifetime::Oneshot {
+        constraints.remove(idx);
+    }
+    hint
+}
+
+/// Committed cursor position hint of an active lock, clamped to its region
+fn active_lock_hint(constraint: &PointerConstraint) -> Option<Point<f64, Logical>> {
+    match constraint {
+        PointerConstraint::Locked(locked) if locked.active => locked
+            .cursor_position_hint
+            .and_then(|hint| clamp_to_region(hint, locked.region.as_ref())),
+        _ => None,
+    }
+}
+
+/// Deactivate the constraint of `pointer` on `surface` without applying any cursor position hint
+///
+/// Used once the pointer left the surface.
+pub(crate) fn pointer_left_surface<D: SeatHandler + 'static>(
+    surface: &WlSurface,
+    pointer: &PointerHandle<D>,
+) {
+    with_constraints::<D, _, _>(surface, |constraints| {
+        if let Some(idx) = constraints.iter().position(|(handle, _)| handle == pointer) {
+            deactivate(constraints, idx);
+        }
+    });
+}
+
+fn apply_cursor_position_hint<D>(
+    data: &mut D,
+    surface: &WlSurface,
+    pointer: &PointerHandle<D>,
+    hint: Point<f64, Logical>,
+    time: u32,
+) where
+    D: SeatHandler + 'static,
+    <D as SeatHandler>::PointerFocus: WaylandFocus,
+{
+    let Some((focus, origin)) = pointer.current_focus_with_location() else {
+        return;
+    };
+    if focus.wl_surface().as_ref() != Some(surface) {
+        trace!("Pointer left the locked surface, ignoring the cursor position hint");
+        return;
+    }
+
+    let surface_size = with_states(surface, |states| {
+        states
+            .data_map
+            .get::<RendererSurfaceStateUserData>()
+            .and_then(|data| data.borrow().surface_size())
+    });
+    let hint = match surface_size {
+        Some(size) => clamp_to_rect(hint, Rectangle::from_loc_and_size((0, 0), size)),
+        None => hint,
+    };
+
+    let location = origin.to_f64() + hint;
+    pointer.warp(
+        data,
+        Some((focus, origin)),
+        location,
+        SERIAL_COUNTER.next_serial(),
+        time,
+    );
+}
+
+/// Clamp a surface-local point into the region, returning `None` if the region is empty
+fn clamp_to_region(
+    point: Point<f64, Logical>,
+    region: Option<&RegionAttributes>,
+) -> Option<Point<f64, Logical>> {
+    let Some(region) = region else {
+        return Some(point);
+    };
+    if region.contains(point.to_i32_floor::<i32>()) {
+        return Some(point);
+    }
+
+    region
+        .rects
+        .iter()
+        .filter(|(kind, _)| matches!(kind, RectangleKind::Add))
+        .map(|(_, rect)| clamp_to_rect(point, *rect))
+        .filter(|clamped| region.contains(clamped.to_i32_floor::<i32>()))
+        .min_by(|a, b| {
+            let distance = |p: &Point<f64, Logical>| (p.x - point.x).powi(2) + (p.y - point.y).powi(2);
+            distance(a).total_cmp(&distance(b))
+        })
+}
+
+fn clamp_to_rect(point: Point<f64, Logical>, rect: Rectangle<i32, Logical>) -> Point<f64, Logical> {
+    // the right and bottom edges are not part of the rectangle
+    let max_x = (rect.loc.x + rect.size.w - 1).max(rect.loc.x) as f64;
+    let max_y = (rect.loc.y + rect.size.h - 1).max(rect.loc.y) as f64;
+    (
+        point.x.clamp(rect.loc.x as f64, max_x),
+        point.y.clamp(rect.loc.y as f64, max_y),
+    )
+        .into()
+}
+
+fn commit_hook<D: SeatHandler + 'static>(_dh: &DisplayHandle, surface: &WlSurface) {
+    with_constraints::<D, _, _>(surface, |constraints| {
+        for (_, constraint) in constraints {
+            constraint.commit();
+        }
+    });
+}
+
+/// User data of the pointer constraint objects
+pub struct PointerConstraintUserData<D: SeatHandler> {
+    surface: WlSurface,
+    pointer: Option<PointerHandle<D>>,
+}
+
+impl<D: SeatHandler> fmt::Debug for PointerConstraintUserData<D>
+where
+    <D as SeatHandler>::PointerFocus: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PointerConstraintUserData")
+            .field("surface", &self.surface)
+            .field("pointer", &self.pointer)
+            .finish()
+    }
+}
+
+impl<D> GlobalDispatch<ZwpPointerConstraintsV1, (), D> for PointerConstraintsState
+where
+    D: GlobalDispatch<ZwpPointerConstraintsV1, ()>,
+    D: Dispatch<ZwpPointerConstraintsV1, ()>,
+    D: SeatHandler,
+    D: 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwpPointerConstraintsV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D> Dispatch<ZwpPointerConstraintsV1, (), D> for PointerConstraintsState
+where
+    D: Dispatch<ZwpPointerConstraintsV1, ()>,
+    D: Dispatch<ZwpConfinedPointerV1, PointerConstraintUserData<D>>,
+    D: Dispatch<ZwpLockedPointerV1, PointerConstraintUserData<D>>,
+    D: PointerConstraintsHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        constraints: &ZwpPointerConstraintsV1,
+        request: zwp_pointer_constraints_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let (surface, pointer, region, lifetime) = match &request {
+            zwp_pointer_constraints_v1::Request::LockPointer {
+                surface,
+                pointer,
+                region,
+                lifetime,
+                ..
+            }
+            | zwp_pointer_constraints_v1::Request::ConfinePointer {
+                surface,
+                pointer,
+                region,
+                lifetime,
+                ..
+            } => (surface.clone(), pointer, region, lifetime),
+            zwp_pointer_constraints_v1::Request::Destroy => return,
+            _ => unreachable!(),
+        };
+        let handle = pointer.data::<PointerUserData<D>>().unwrap().handle.clone();
+        let region = region.as_ref().map(compositor::get_region_attributes);
+        let lifetime = match lifetime {
+            WEnum::Value(lifetime) => *lifetime,
+            WEnum::Unknown(_) => Lifetime::Oneshot,
+        };
+
+        let already_constrained = handle.as_ref().map_or(false, |handle| {
+            with_pointer_constraint(&surface, handle, |constraint| constraint.is_some())
+        });
+        if already_constrained {
+            constraints.post_error(
+                zwp_pointer_constraints_v1::Error::AlreadyConstrained as u32,
+                "the pointer is already constrained on this surface".to_string(),
+            );
+            return;
+        }
+
+        let user_data = PointerConstraintUserData {
+            surface: surface.clone(),
+            pointer: handle.clone(),
+        };
+        let constraint = match request {
+            zwp_pointer_constraints_v1::Request::LockPointer { id, .. } => {
+                PointerConstraint::Locked(LockedPointer {
+                    handle: data_init.init(id, user_data),
+                    region,
+                    pending_region: None,
+                    cursor_position_hint: None,
+                    pending_cursor_position_hint: None,
+                    lifetime,
+                    active: false,
+                })
+            }
+            zwp_pointer_constraints_v1::Request::ConfinePointer { id, .. } => {
+                PointerConstraint::Confined(ConfinedPointer {
+                    handle: data_init.init(id, user_data),
+                    region,
+                    pending_region: None,
+                    lifetime,
+                    active: false,
+                })
+            }
+            _ => unreachable!(),
+        };
+
+        // constraints of pointers of other compositors are inert
+        let Some(handle) = handle else {
+            return;
+        };
+        let new = with_states(&surface, |states| {
+            states
+                .data_map
+                .insert_if_missing(|| PointerConstraintsCommitHookMarker)
+        });
+        if new {
+            compositor::add_pre_commit_hook(&surface, commit_hook::<D>);
+        }
+        with_constraints::<D, _, _>(&surface, |constraints| {
+            constraints.push((handle.clone(), constraint));
+        });
+        state.new_constraint(&surface, &handle);
+    }
+}
+
+fn set_region<D: SeatHandler + 'static>(
+    data: &PointerConstraintUserData<D>,
+    object_id: ObjectId,
+    region: Option<WlRegion>,
+) {
+    let Some(pointer) = &data.pointer else {
+        return;
+    };
+    let region = region.as_ref().map(compositor::get_region_attributes);
+    with_constraints::<D, _, _>(&data.surface, |constraints| {
+        let constraint = constraints
+            .iter_mut()
+            .find(|(handle, constraint)| handle == pointer && constraint.object_id() == object_id);
+        match constraint {
+            Some((_, PointerConstraint::Confined(confined))) => confined.pending_region = Some(region),
+            Some((_, PointerConstraint::Locked(locked))) => locked.pending_region = Some(region),
+            None => {}
+        }
+    });
+}
+
+fn remove_constraint<D: SeatHandler + 'static>(data: &PointerConstraintUserData<D>, object_id: ObjectId) {
+    if let Some(pointer) = &data.pointer {
+        with_constraints::<D, _, _>(&data.surface, |constraints| {
+            constraints
+                .retain(|(handle, constraint)| handle != pointer || constraint.object_id() != object_id);
+        });
+    }
+}
+
+impl<D> Dispatch<ZwpConfinedPointerV1, PointerConstraintUserData<D>, D> for PointerConstraintsState
+where
+    D: Dispatch<ZwpConfinedPointerV1, PointerConstraintUserData<D>>,
+    D: SeatHandler,
+    D: 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        confined: &ZwpConfinedPointerV1,
+        request: zwp_confined_pointer_v1::Request,
+        data: &PointerConstraintUserData<D>,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwp_confined_pointer_v1::Request::SetRegion { region } => set_region(data, confined.id(), region),
+            zwp_confined_pointer_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        _state: &mut D,
+        _client: ClientId,
+        object_id: ObjectId,
+        data: &PointerConstraintUserData<D>,
+    ) {
+        remove_constraint(data, object_id);
+    }
+}
+
+impl<D> Dispatch<ZwpLockedPointerV1, PointerConstraintUserData<D>, D> for PointerConstraintsState
+where
+    D: Dispatch<ZwpLockedPointerV1, PointerConstraintUserData<D>>,
+    D: SeatHandler,
+    <D as SeatHandler>::PointerFocus: WaylandFocus,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        locked: &ZwpLockedPointerV1,
+        request: zwp_locked_pointer_v1::Request,
+        data: &PointerConstraintUserData<D>,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let Some(pointer) = &data.pointer else {
+            return;
+        };
+        match request {
+            zwp_locked_pointer_v1::Request::SetCursorPositionHint { surface_x, surface_y } => {
+                with_constraints::<D, _, _>(&data.surface, |constraints| {
+                    let constraint = constraints.iter_mut().find(|(handle, constraint)| {
+                        handle == pointer && constraint.object_id() == locked.id()
+                    });
+                    if let Some((_, PointerConstraint::Locked(locked))) = constraint {
+                        locked.pending_cursor_position_hint = Some((surface_x, surface_y).into());
+                    }
+                });
+            }
+            zwp_locked_pointer_v1::Request::SetRegion { region } => set_region(data, locked.id(), region),
+            zwp_locked_pointer_v1::Request::Destroy => {
+                // destroying an active lock deactivates it, so its hint has to be applied
+                let hint = with_constraints::<D, _, _>(&data.surface, |constraints| {
+                    let idx = constraints.iter().position(|(handle, constraint)| {
+                        handle == pointer && constraint.object_id() == locked.id()
+                    })?;
+                    let (_, constraint) = constraints.remove(idx);
+                    active_lock_hint(&constraint)
+                });
+                if let Some(hint) = hint {
+                    apply_cursor_position_hint(state, &data.surface, pointer, hint, now_ms());
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        _state: &mut D,
+        _client: ClientId,
+        object_id: ObjectId,
+        data: &PointerConstraintUserData<D>,
+    ) {
+        remove_constraint(data, object_id);
+    }
+}
+
+/// Current time in milliseconds of the monotonic clock, the clock input events are timestamped with
+fn now_ms() -> u32 {
+    Clock::<Monotonic>::new()
+        .map(|clock| clock.now().as_millis())
+        .unwrap_or(0)
+}
+
+/// Macro to delegate implementation of the pointer constraints protocol
+#[macro_export]
+macro_rules! delegate_pointer_constraints {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::wp::pointer_constraints::zv1::server::zwp_pointer_constraints_v1::ZwpPointerConstraintsV1: ()
+        ] => $crate::wayland::pointer_constraints::PointerConstraintsState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::wp::pointer_constraints::zv1::server::zwp_pointer_constraints_v1::ZwpPointerConstraintsV1: ()
+        ] => $crate::wayland::pointer_constraints::PointerConstraintsState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::wp::pointer_constraints::zv1::server::zwp_confined_pointer_v1::ZwpConfinedPointerV1: $crate::wayland::pointer_constraints::PointerConstraintUserData<Self>
+        ] => $crate::wayland::pointer_constraints::PointerConstraintsState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::wp::pointer_constraints::zv1::server::zwp_locked_pointer_v1::ZwpLockedPointerV1: $crate::wayland::pointer_constraints::PointerConstraintUserData<Self>
+        ] => $crate::wayland::pointer_constraints::PointerConstraintsState);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::clamp_to_region;
+    use crate::{
+        utils::Rectangle,
+        wayland::compositor::{RectangleKind, RegionAttributes},
+    };
+
+    #[test]
+    fn hint_is_clamped_into_region() {
+        let region = RegionAttributes {
+            rects: vec![
+                (
+                    RectangleKind::Add,
+                    Rectangle::from_loc_and_size((0, 0), (100, 100)),
+                ),
+                (
+                    RectangleKind::Subtract,
+                    Rectangle::from_loc_and_size((0, 0), (50, 100)),
+                ),
+            ],
+        };
+
+        assert_eq!(
+            clamp_to_region((75.5, 20.0).into(), Some(&region)),
+            Some((75.5, 20.0).into())
+        );
+        assert_eq!(
+            clamp_to_region((150.0, 120.0).into(), Some(&region)),
+            Some((99.0, 99.0).into())
+        );
+        assert_eq!(
+            clamp_to_region((-5.0, 5.0).into(), None),
+            Some((-5.0, 5.0).into())
+        );
+
+        // the closest point of the added rectangle was subtracted again
+        assert_eq!(clamp_to_region((10.0, 10.0).into(), Some(&region)), None);
+    }
+
+    #[cfg(feature = "test_utils")]
+    mod lock {
+        use wayland_client::protocol::{wl_compositor::WlCompositor, wl_seat::WlSeat as ClientSeat};
+        use wayland_protocols::wp::pointer_constraints::zv1::client::zwp_pointer_constraints_v1::{
+            Lifetime, ZwpPointerConstraintsV1,
+        };
+        use wayland_server::protocol::wl_surface::WlSurface;
+
+        use super::super::{
+            activate_pointer_constraint, deactivate_pointer_constraint, with_pointer_constraint,
+            PointerConstraint, PointerConstraintsHandler, PointerConstraintsState,
+        };
+        use crate::{
+            input::{
+                pointer::{MotionEvent, PointerHandle},
+                Seat, SeatHandler, SeatState,
+            },
+            testing::{Recorded, TestCompositor},
+            utils::SERIAL_COUNTER,
+            wayland::compositor::{CompositorHandler, CompositorState},
+        };
+
+        struct State {
+            compositor_state: CompositorState,
+            seat_state: SeatState<State>,
+            seat: Seat<State>,
+            _constraints_state: PointerConstraintsState,
+            constrained: Vec<WlSurface>,
+        }
+
+        impl CompositorHandler for State {
+            fn compositor_state(&mut self) -> &mut CompositorState {
+                &mut self.compositor_state
+            }
+
+            fn commit(&mut self, _surface: &WlSurface) {}
+        }
+
+        impl SeatHandler for State {
+            type KeyboardFocus = WlSurface;
+            type PointerFocus = WlSurface;
+
+            fn seat_state(&mut self) -> &mut SeatState<Self> {
+                &mut self.seat_state
+            }
+        }
+
+        impl PointerConstraintsHandler for State {
+            fn new_constraint(&mut self, surface: &WlSurface, pointer: &PointerHandle<Self>) {
+                activate_pointer_constraint(surface, pointer);
+                self.constrained.push(surface.clone());
+            }
+        }
+
+        crate::delegate_compositor!(State);
+        crate::delegate_seat!(State);
+        crate::delegate_pointer_constraints!(State);
+
+        #[test]
+        fn committed_hint_is_applied_on_deactivate() {
+            let mut compositor = TestCompositor::new(|dh, _| {
+                let mut seat_state = SeatState::new();
+                let mut seat = seat_state.new_wl_seat(dh, "seat");
+                seat.add_pointer();
+                State {
+                    compositor_state: CompositorState::new::<State>(dh),
+                    seat_state,
+                    seat,
+                    _constraints_state: PointerConstraintsState::new::<State>(dh),
+                    constrained: Vec::new(),
+                }
+            });
+            let mut client = compositor.connect();
+            let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+            let seat = client.bind::<ClientSeat, _>(&mut compositor);
+            let constraints = client.bind::<ZwpPointerConstraintsV1, _>(&mut compositor);
+            let qh = client.queue_handle();
+            let surface = wl_compositor.create_surface(&qh, Recorded);
+            let wl_pointer = seat.get_pointer(&qh, Recorded);
+
+            // the first lock is replaced by a second one, which must not add another commit hook
+            let lock =
+                constraints.lock_pointer(&surface, &wl_pointer, None, Lifetime::Persistent, &qh, Recorded);
+            lock.destroy();
+            let lock =
+                constraints.lock_pointer(&surface, &wl_pointer, None, Lifetime::Persistent, &qh, Recorded);
+            client.roundtrip(&mut compositor);
+            let server_surface = compositor.state.constrained[1].clone();
+
+            // the pointer enters the surface, placed at (100, 100)
+            let pointer = compositor.state.seat.get_pointer().unwrap();
+            pointer.motion(
+                &mut compositor.state,
+                Some((server_surface.clone(), (100, 100).into())),
+                &MotionEvent {
+                    location: (110.0, 110.0).into(),
+                    serial: SERIAL_COUNTER.next_serial(),
+                    time: 0,
+                },
+            );
+
+            // hints only take effect once committed
+            lock.set_cursor_position_hint(20.0, 30.0);
+            client.roundtrip(&mut compositor);
+            let hint = with_pointer_constraint(&server_surface, &pointer, |constraint| {
+                constraint.and_then(|constraint| match constraint {
+                    PointerConstraint::Locked(locked) => locked.cursor_position_hint(),
+                    PointerConstraint::Confined(_) => None,
+                })
+            });
+            assert_eq!(hint, None);
+            surface.commit();
+            client.roundtrip(&mut compositor);
+
+            deactivate_pointer_constraint(&mut compositor.state, &server_surface, &pointer, 0);
+            assert_eq!(pointer.current_location(), (120.0, 130.0).into());
+            let active = with_pointer_constraint(&server_surface, &pointer, |constraint| {
+                constraint.map(|constraint| constraint.is_active())
+            });
+            assert_eq!(active, Some(false));
+        }
+    }
+}
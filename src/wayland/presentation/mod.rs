@@ -77,7 +77,7 @@ use wayland_server::{
     backend::GlobalId, protocol::wl_surface, Dispatch, DisplayHandle, GlobalDispatch, Resource, Weak,
 };
 
-use crate::output::Output;
+use crate::{backend::PresentFlags, output::Output, utils::duration_as_timespec_triple};
 
 use super::compositor::{with_states, Cacheable};
 
@@ -232,10 +232,7 @@ impl PresentationFeedbackCallback {
             self.callback.sync_output(&output);
         }
 
-        let time = time.into();
-        let tv_sec_hi = (time.as_secs() >> 32) as u32;
-        let tv_sec_lo = (time.as_secs() & 0xFFFFFFFF) as u32;
-        let tv_nsec = time.subsec_nanos();
+        let (tv_sec_hi, tv_sec_lo, tv_nsec) = duration_as_timespec_triple(time.into());
         let seq_hi = (seq >> 32) as u32;
         let seq_lo = (seq & 0xFFFFFFFF) as u32;
 
@@ -249,6 +246,12 @@ impl PresentationFeedbackCallback {
     }
 }
 
+impl From<PresentFlags> for wp_presentation_feedback::Kind {
+    fn from(flags: PresentFlags) -> Self {
+        wp_presentation_feedback::Kind::from_bits_truncate(flags.bits())
+    }
+}
+
 /// State of a single presentation feedback requested
 /// for a surface
 #[derive(Debug, Default)]
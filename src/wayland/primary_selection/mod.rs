@@ -15,6 +15,8 @@
 //!
 //! - the freestanding function [`set_primary_selection`]
 //!   allows you to set the contents of the selection for your clients
+//! - the freestanding function [`request_primary_selection`] allows you to read the contents
+//!   of the selection, see the [`selection`](crate::wayland::selection) module
 //! - the `PrimarySelectionHandle` gives you the option to inspect new selections
 //!   by overriding [`PrimarySelectionHandler::new_selection].
 //!
@@ -67,7 +69,13 @@ use wayland_protocols::wp::primary_selection::zv1::server::{
 };
 use wayland_server::{backend::GlobalId, Client, DisplayHandle, GlobalDispatch};
 
-use crate::input::{Seat, SeatHandler};
+use crate::{
+    input::{Seat, SeatHandler},
+    utils::IsAlive,
+    wayland::selection::{
+        start_transfer, SelectionOwner, SelectionProtocol, SelectionTransfer, TransferError, TransferOptions,
+    },
+};
 
 mod device;
 mod seat_data;
@@ -150,6 +158,60 @@ where
         .set_selection::<D>(dh, Selection::Compositor(SourceMetadata { mime_types }));
 }
 
+/// Request the contents of the current primary selection of this seat
+///
+/// The owner of the selection is asked to write its contents for the given mime type into a
+/// pipe, which is the client holding it or, for selections set through [`set_primary_selection`],
+/// your [`PrimarySelectionHandler::send_selection`] implementation.
+///
+/// The returned [`SelectionTransfer`] has to be inserted into your event loop and delivers the data
+/// or the [`TransferError`] that ended the transfer, enforcing the limits given in `options`.
+#[instrument(name = "wayland_primary_selection", level = "debug", skip(state, seat), fields(seat = seat.name()))]
+pub fn request_primary_selection<D>(
+    state: &mut D,
+    seat: &Seat<D>,
+    mime_type: String,
+    options: TransferOptions,
+) -> Result<SelectionTransfer, TransferError>
+where
+    D: SeatHandler + PrimarySelectionHandler + 'static,
+{
+    let owner = seat.user_data().get::<RefCell<SeatData>>().and_then(|seat_data| {
+        match seat_data.borrow().selection() {
+            Selection::Client(source) if source.alive() => Some(SelectionOwner::Client {
+                source: source.clone(),
+                mime_types: with_source_metadata(source, |meta| meta.mime_types.clone()).unwrap_or_default(),
+            }),
+            Selection::Compositor(meta) => Some(SelectionOwner::Compositor {
+                provider: (),
+                mime_types: meta.mime_types.clone(),
+            }),
+            _ => None,
+        }
+    });
+    start_transfer(
+        owner,
+        mime_type,
+        options,
+        |source, mime_type, fd| source.send(mime_type, fd),
+        |(), mime_type, fd| state.send_selection(mime_type, fd),
+    )
+}
+
+impl<D> SelectionProtocol<D> for PrimarySelectionState
+where
+    D: SeatHandler + PrimarySelectionHandler + 'static,
+{
+    fn request_selection(
+        state: &mut D,
+        seat: &Seat<D>,
+        mime_type: String,
+        options: TransferOptions,
+    ) -> Result<SelectionTransfer, TransferError> {
+        request_primary_selection(state, seat, mime_type, options)
+    }
+}
+
 mod handlers {
     use std::cell::RefCell;
 
@@ -163,7 +225,11 @@ mod handlers {
     };
     use wayland_server::{Dispatch, DisplayHandle, GlobalDispatch};
 
-    use crate::input::{Seat, SeatHandler};
+    use crate::{
+        input::{Seat, SeatHandler},
+        utils::IsAlive,
+        wayland::selection::{SelectionTransfer, TransferError, TransferOptions},
+    };
 
     use super::{device::PrimaryDeviceUserData, seat_data::SeatData, source::PrimarySourceUserData};
     use super::{PrimarySelectionHandler, PrimarySelectionState};
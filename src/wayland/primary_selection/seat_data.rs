@@ -45,6 +45,10 @@ impl SeatData {
         Self::default()
     }
 
+    pub fn selection(&self) -> &Selection {
+        &self.selection
+    }
+
     pub fn add_device(&mut self, device: PrimaryDevice) {
         self.known_devices.push(device);
     }
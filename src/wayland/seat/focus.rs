@@ -0,0 +1,387 @@
+use wayland_server::{protocol::wl_surface::WlSurface, Resource};
+
+use super::WaylandFocus;
+use crate::{
+    backend::input::KeyState,
+    input::{
+        keyboard::{KeyboardTarget, KeysymHandle, ModifiersState},
+        pointer::{
+            AxisFrame, ButtonEvent, GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent,
+            GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent,
+            GestureSwipeUpdateEvent, MotionEvent, PointerTarget, RelativeMotionEvent,
+        },
+        Seat, SeatHandler,
+    },
+    utils::{IsAlive, Serial},
+};
+
+/// Focus target, that is either a client surface or a compositor-internal element
+///
+/// Can be used as [`SeatHandler::KeyboardFocus`] and [`SeatHandler::PointerFocus`] by compositors
+/// drawing their own ui, like launchers or region selectors, that needs to receive input without
+/// faking a client surface.
+///
+/// Events for [`SurfaceOrInternal::Surface`] are delivered to the client as usual, while
+/// [`SurfaceOrInternal::Internal`] receives them through its [`KeyboardTarget`] and
+/// [`PointerTarget`] implementations. Internal elements never have an underlying [`WlSurface`],
+/// so no client is considered focused while they are, e.g. for selection requests.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SurfaceOrInternal<E> {
+    /// A surface of a client
+    Surface(WlSurface),
+    /// An element of the compositor
+    Internal(E),
+}
+
+impl<E> From<WlSurface> for SurfaceOrInternal<E> {
+    fn from(surface: WlSurface) -> Self {
+        SurfaceOrInternal::Surface(surface)
+    }
+}
+
+impl<E: IsAlive> IsAlive for SurfaceOrInternal<E> {
+    fn alive(&self) -> bool {
+        match self {
+            SurfaceOrInternal::Surface(surface) => surface.alive(),
+            SurfaceOrInternal::Internal(element) => element.alive(),
+        }
+    }
+}
+
+impl<E> WaylandFocus for SurfaceOrInternal<E> {
+    fn wl_surface(&self) -> Option<WlSurface> {
+        match self {
+            SurfaceOrInternal::Surface(surface) => Some(surface.clone()),
+            SurfaceOrInternal::Internal(_) => None,
+        }
+    }
+
+    fn same_client_as(&self, object_id: &wayland_server::backend::ObjectId) -> bool {
+        match self {
+            SurfaceOrInternal::Surface(surface) => surface.id().same_client_as(object_id),
+            SurfaceOrInternal::Internal(_) => false,
+        }
+    }
+}
+
+impl<D, E> KeyboardTarget<D> for SurfaceOrInternal<E>
+where
+    D: SeatHandler + 'static,
+    E: KeyboardTarget<D>,
+{
+    fn enter(&self, seat: &Seat<D>, data: &mut D, keys: Vec<KeysymHandle<'_>>, serial: Serial) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => KeyboardTarget::enter(surface, seat, data, keys, serial),
+            SurfaceOrInternal::Internal(element) => KeyboardTarget::enter(element, seat, data, keys, serial),
+        }
+    }
+
+    fn leave(&self, seat: &Seat<D>, data: &mut D, serial: Serial) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => KeyboardTarget::leave(surface, seat, data, serial),
+            SurfaceOrInternal::Internal(element) => KeyboardTarget::leave(element, seat, data, serial),
+        }
+    }
+
+    fn key(
+        &self,
+        seat: &Seat<D>,
+        data: &mut D,
+        key: KeysymHandle<'_>,
+        state: KeyState,
+        serial: Serial,
+        time: u32,
+    ) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => surface.key(seat, data, key, state, serial, time),
+            SurfaceOrInternal::Internal(element) => element.key(seat, data, key, state, serial, time),
+        }
+    }
+
+    fn modifiers(&self, seat: &Seat<D>, data: &mut D, modifiers: ModifiersState, serial: Serial) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => surface.modifiers(seat, data, modifiers, serial),
+            SurfaceOrInternal::Internal(element) => element.modifiers(seat, data, modifiers, serial),
+        }
+    }
+}
+
+impl<D, E> PointerTarget<D> for SurfaceOrInternal<E>
+where
+    D: SeatHandler + 'static,
+    E: PointerTarget<D>,
+{
+    fn enter(&self, seat: &Seat<D>, data: &mut D, event: &MotionEvent) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => PointerTarget::enter(surface, seat, data, event),
+            SurfaceOrInternal::Internal(element) => PointerTarget::enter(element, seat, data, event),
+        }
+    }
+
+    fn motion(&self, seat: &Seat<D>, data: &mut D, event: &MotionEvent) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => surface.motion(seat, data, event),
+            SurfaceOrInternal::Internal(element) => element.motion(seat, data, event),
+        }
+    }
+
+    fn relative_motion(&self, seat: &Seat<D>, data: &mut D, event: &RelativeMotionEvent) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => surface.relative_motion(seat, data, event),
+            SurfaceOrInternal::Internal(element) => element.relative_motion(seat, data, event),
+        }
+    }
+
+    fn button(&self, seat: &Seat<D>, data: &mut D, event: &ButtonEvent) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => surface.button(seat, data, event),
+            SurfaceOrInternal::Internal(element) => element.button(seat, data, event),
+        }
+    }
+
+    fn axis(&self, seat: &Seat<D>, data: &mut D, frame: AxisFrame) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => surface.axis(seat, data, frame),
+            SurfaceOrInternal::Internal(element) => element.axis(seat, data, frame),
+        }
+    }
+
+    fn leave(&self, seat: &Seat<D>, data: &mut D, serial: Serial, time: u32) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => PointerTarget::leave(surface, seat, data, serial, time),
+            SurfaceOrInternal::Internal(element) => PointerTarget::leave(element, seat, data, serial, time),
+        }
+    }
+
+    fn gesture_swipe_begin(&self, seat: &Seat<D>, data: &mut D, event: &GestureSwipeBeginEvent) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => surface.gesture_swipe_begin(seat, data, event),
+            SurfaceOrInternal::Internal(element) => element.gesture_swipe_begin(seat, data, event),
+        }
+    }
+
+    fn gesture_swipe_update(&self, seat: &Seat<D>, data: &mut D, event: &GestureSwipeUpdateEvent) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => surface.gesture_swipe_update(seat, data, event),
+            SurfaceOrInternal::Internal(element) => element.gesture_swipe_update(seat, data, event),
+        }
+    }
+
+    fn gesture_swipe_end(&self, seat: &Seat<D>, data: &mut D, event: &GestureSwipeEndEvent) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => surface.gesture_swipe_end(seat, data, event),
+            SurfaceOrInternal::Internal(element) => element.gesture_swipe_end(seat, data, event),
+        }
+    }
+
+    fn gesture_pinch_begin(&self, seat: &Seat<D>, data: &mut D, event: &GesturePinchBeginEvent) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => surface.gesture_pinch_begin(seat, data, event),
+            SurfaceOrInternal::Internal(element) => element.gesture_pinch_begin(seat, data, event),
+        }
+    }
+
+    fn gesture_pinch_update(&self, seat: &Seat<D>, data: &mut D, event: &GesturePinchUpdateEvent) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => surface.gesture_pinch_update(seat, data, event),
+            SurfaceOrInternal::Internal(element) => element.gesture_pinch_update(seat, data, event),
+        }
+    }
+
+    fn gesture_pinch_end(&self, seat: &Seat<D>, data: &mut D, event: &GesturePinchEndEvent) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => surface.gesture_pinch_end(seat, data, event),
+            SurfaceOrInternal::Internal(element) => element.gesture_pinch_end(seat, data, event),
+        }
+    }
+
+    fn gesture_hold_begin(&self, seat: &Seat<D>, data: &mut D, event: &GestureHoldBeginEvent) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => surface.gesture_hold_begin(seat, data, event),
+            SurfaceOrInternal::Internal(element) => element.gesture_hold_begin(seat, data, event),
+        }
+    }
+
+    fn gesture_hold_end(&self, seat: &Seat<D>, data: &mut D, event: &GestureHoldEndEvent) {
+        match self {
+            SurfaceOrInternal::Surface(surface) => surface.gesture_hold_end(seat, data, event),
+            SurfaceOrInternal::Internal(element) => element.gesture_hold_end(seat, data, event),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Launcher {
+        open: bool,
+    }
+
+    impl IsAlive for Launcher {
+        fn alive(&self) -> bool {
+            self.open
+        }
+    }
+
+    #[test]
+    fn internal_target_has_no_client() {
+        let target = SurfaceOrInternal::Internal(Launcher { open: true });
+        assert!(target.alive());
+        assert_eq!(target.wl_surface(), None);
+
+        let closed = SurfaceOrInternal::Internal(Launcher { open: false });
+        assert!(!closed.alive());
+    }
+
+    #[cfg(feature = "test_utils")]
+    mod selection {
+        use wayland_client::protocol::{
+            wl_data_device_manager::WlDataDeviceManager, wl_seat::WlSeat as ClientSeat,
+        };
+        use wayland_server::{
+            protocol::{
+                wl_data_source::WlDataSource,
+                wl_surface::{self, WlSurface},
+            },
+            Client, DataInit, Dispatch, DisplayHandle,
+        };
+
+        use super::{super::SurfaceOrInternal, Launcher};
+        use crate::{
+            backend::input::KeyState,
+            input::{
+                keyboard::{KeyboardTarget, KeysymHandle, ModifiersState, XkbConfig},
+                Seat, SeatHandler, SeatState,
+            },
+            testing::{Recorded, TestCompositor},
+            utils::{Serial, SERIAL_COUNTER},
+            wayland::data_device::{
+                ClientDndGrabHandler, DataDeviceHandler, DataDeviceState, ServerDndGrabHandler,
+            },
+        };
+
+        struct State {
+            seat_state: SeatState<State>,
+            seat: Seat<State>,
+            data_device_state: DataDeviceState,
+            selections: usize,
+        }
+
+        impl KeyboardTarget<State> for Launcher {
+            fn enter(
+                &self,
+                _seat: &Seat<State>,
+                _data: &mut State,
+                _keys: Vec<KeysymHandle<'_>>,
+                _serial: Serial,
+            ) {
+            }
+            fn leave(&self, _seat: &Seat<State>, _data: &mut State, _serial: Serial) {}
+            fn key(
+                &self,
+                _seat: &Seat<State>,
+                _data: &mut State,
+                _key: KeysymHandle<'_>,
+                _state: KeyState,
+                _serial: Serial,
+                _time: u32,
+            ) {
+            }
+            fn modifiers(
+                &self,
+                _seat: &Seat<State>,
+                _data: &mut State,
+                _modifiers: ModifiersState,
+                _serial: Serial,
+            ) {
+            }
+        }
+
+        impl SeatHandler for State {
+            type KeyboardFocus = SurfaceOrInternal<Launcher>;
+            type PointerFocus = WlSurface;
+
+            fn seat_state(&mut self) -> &mut SeatState<Self> {
+                &mut self.seat_state
+            }
+        }
+
+        impl ClientDndGrabHandler for State {}
+        impl ServerDndGrabHandler for State {}
+        impl DataDeviceHandler for State {
+            fn data_device_state(&self) -> &DataDeviceState {
+                &self.data_device_state
+            }
+
+            fn new_selection(&mut self, _source: Option<WlDataSource>) {
+                self.selections += 1;
+            }
+        }
+
+        // surfaces are only used as keyboard focus, without a compositor global
+        impl Dispatch<WlSurface, ()> for State {
+            fn request(
+                _state: &mut Self,
+                _client: &Client,
+                _resource: &WlSurface,
+                _request: wl_surface::Request,
+                _data: &(),
+                _dhandle: &DisplayHandle,
+                _data_init: &mut DataInit<'_, Self>,
+            ) {
+            }
+        }
+
+        crate::delegate_seat!(State);
+        crate::delegate_data_device!(State);
+
+        #[test]
+        fn internal_focus_denies_client_selections() {
+            let mut compositor = TestCompositor::new(|dh, _| {
+                let mut seat_state = SeatState::new();
+                let mut seat = seat_state.new_wl_seat(dh, "seat");
+                seat.add_keyboard(XkbConfig::default(), 200, 25).unwrap();
+                State {
+                    seat_state,
+                    seat,
+                    data_device_state: DataDeviceState::new::<State>(dh),
+                    selections: 0,
+                }
+            });
+            let mut client = compositor.connect();
+            let seat = client.bind::<ClientSeat, _>(&mut compositor);
+            let device_manager = client.bind::<WlDataDeviceManager, _>(&mut compositor);
+            let qh = client.queue_handle();
+            let data_device = device_manager.get_data_device(&seat, &qh, Recorded);
+            let source = device_manager.create_data_source(&qh, Recorded);
+            client.roundtrip(&mut compositor);
+
+            // the launcher is focused, so no client may set the selection
+            let keyboard = compositor.state.seat.get_keyboard().unwrap();
+            keyboard.set_focus(
+                &mut compositor.state,
+                Some(SurfaceOrInternal::Internal(Launcher { open: true })),
+                SERIAL_COUNTER.next_serial(),
+            );
+            data_device.set_selection(Some(&source), 0);
+            client.roundtrip(&mut compositor);
+            assert_eq!(compositor.state.selections, 0);
+
+            // once a surface of the client is focused, the same requests are accepted
+            let surface = client
+                .client()
+                .create_resource::<WlSurface, (), State>(&compositor.handle(), 1, ())
+                .unwrap();
+            keyboard.set_focus(
+                &mut compositor.state,
+                Some(SurfaceOrInternal::Surface(surface)),
+                SERIAL_COUNTER.next_serial(),
+            );
+            data_device.set_selection(Some(&source), 0);
+            client.roundtrip(&mut compositor);
+            assert_eq!(compositor.state.selections, 1);
+        }
+    }
+}
@@ -62,10 +62,12 @@ where
             kbd.repeat_info(guard.repeat_rate, guard.repeat_delay);
         }
         if let Some((focused, serial)) = guard.focus.as_ref() {
-            if focused.same_client_as(&kbd.id()) {
+            // focus targets without a surface never belong to a client
+            let surface = focused.wl_surface().filter(|_| focused.same_client_as(&kbd.id()));
+            if let Some(surface) = surface {
                 let serialized = guard.mods_state.serialized;
                 let keys = serialize_pressed_keys(guard.pressed_keys.iter().cloned().collect());
-                kbd.enter((*serial).into(), &focused.wl_surface().unwrap(), keys);
+                kbd.enter((*serial).into(), &surface, keys);
                 // Modifiers must be send after enter event.
                 kbd.modifiers(
                     (*serial).into(),
@@ -57,6 +57,7 @@
 //! This module further defines the `"cursor_image"` role, that is assigned to surfaces used by clients
 //! to change the cursor icon.
 
+mod focus;
 pub(crate) mod keyboard;
 mod pointer;
 mod touch;
@@ -66,6 +67,7 @@ use std::{fmt, sync::Arc};
 use crate::input::{Inner, Seat, SeatHandler, SeatRc, SeatState};
 
 pub use self::{
+    focus::SurfaceOrInternal,
     keyboard::KeyboardUserData,
     pointer::{PointerUserData, CURSOR_IMAGE_ROLE},
     touch::{TouchHandle, TouchUserData},
@@ -107,7 +109,7 @@ impl WaylandFocus for wl_surface::WlSurface {
     }
 }
 
-impl<D: SeatHandler> Inner<D> {
+impl<D: SeatHandler + 'static> Inner<D> {
     fn compute_caps(&self) -> wl_seat::Capability {
         let mut caps = wl_seat::Capability::empty();
         if self.pointer.is_some() {
@@ -122,11 +124,24 @@ impl<D: SeatHandler> Inner<D> {
         caps
     }
 
-    pub(crate) fn send_all_caps(&self) {
+    pub(crate) fn send_all_caps(&mut self) {
         let capabilities = self.compute_caps();
+        if let Some(update) = self.capability_update.as_mut() {
+            update.removed |= update.before & !capabilities;
+            update.dirty = true;
+            return;
+        }
+        self.send_caps(capabilities);
+    }
+
+    fn send_caps(&self, capabilities: wl_seat::Capability) {
         for seat in &self.known_seats {
             if let Ok(seat) = seat.upgrade() {
-                seat.capabilities(capabilities);
+                let filter = seat
+                    .data::<SeatUserData<D>>()
+                    .map(|data| data.capabilities)
+                    .unwrap_or_else(wl_seat::Capability::all);
+                seat.capabilities(capabilities & filter);
             }
         }
     }
@@ -243,6 +258,49 @@ impl<D: SeatHandler + 'static> Seat<D> {
         touch
     }
 
+    /// Hold back `wl_seat.capabilities` events until the returned guard is dropped
+    ///
+    /// Every capability added or removed otherwise results in its own event, so clients see
+    /// intermediate states, e.g. a seat with only a keyboard during initialization. While the
+    /// guard is alive, changes are collected and announced with a single event once it is
+    /// dropped. Guards can be nested, the event is sent when the last one is dropped.
+    ///
+    /// Devices that were replaced meanwhile are still announced as removed first.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use smithay::input::{Seat, SeatState, SeatHandler, keyboard::XkbConfig};
+    /// # use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+    /// #
+    /// # struct State;
+    /// # impl SeatHandler for State {
+    /// #     type KeyboardFocus = WlSurface;
+    /// #     type PointerFocus = WlSurface;
+    /// #     fn seat_state(&mut self) -> &mut SeatState<Self> { unimplemented!() }
+    /// # }
+    /// # let mut seat: Seat<State> = unimplemented!();
+    /// let guard = seat.begin_capability_update();
+    /// seat.add_keyboard(XkbConfig::default(), 200, 25).unwrap();
+    /// seat.add_pointer();
+    /// // clients are notified about both capabilities at once
+    /// drop(guard);
+    /// ```
+    pub fn begin_capability_update(&self) -> CapabilityUpdateGuard<D> {
+        let mut inner = self.arc.inner.lock().unwrap();
+        let before = inner.compute_caps();
+        inner
+            .capability_update
+            .get_or_insert(CapabilityUpdate {
+                depth: 0,
+                before,
+                removed: wl_seat::Capability::empty(),
+                dirty: false,
+            })
+            .depth += 1;
+        CapabilityUpdateGuard { seat: self.clone() }
+    }
+
     /// Access the touch device of this seat, if any.
     pub fn get_touch(&self) -> Option<TouchHandle> {
         self.arc.inner.lock().unwrap().touch.clone()
@@ -260,9 +318,62 @@ impl<D: SeatHandler + 'static> Seat<D> {
     }
 }
 
+/// Capability changes held back by a [`CapabilityUpdateGuard`]
+#[derive(Debug)]
+pub(crate) struct CapabilityUpdate {
+    /// Number of guards alive
+    depth: usize,
+    /// Capabilities when the first guard was created
+    before: wl_seat::Capability,
+    /// Capabilities of `before` that were removed meanwhile
+    removed: wl_seat::Capability,
+    dirty: bool,
+}
+
+/// Guard holding back `wl_seat.capabilities` events, see [`Seat::begin_capability_update`]
+pub struct CapabilityUpdateGuard<D: SeatHandler + 'static> {
+    seat: Seat<D>,
+}
+
+impl<D: SeatHandler + 'static> fmt::Debug for CapabilityUpdateGuard<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CapabilityUpdateGuard")
+            .field("seat", &self.seat.name())
+            .finish()
+    }
+}
+
+impl<D: SeatHandler + 'static> Drop for CapabilityUpdateGuard<D> {
+    fn drop(&mut self) {
+        let mut inner = self.seat.arc.inner.lock().unwrap();
+        let Some(update) = inner.capability_update.as_mut() else {
+            return;
+        };
+        update.depth -= 1;
+        if update.depth > 0 {
+            return;
+        }
+        let update = inner.capability_update.take().unwrap();
+        if !update.dirty {
+            return;
+        }
+
+        let capabilities = inner.compute_caps();
+        // devices that were replaced still have to be seen as unplugged by the clients
+        let replaced = update.removed & capabilities;
+        if !replaced.is_empty() {
+            inner.send_caps(capabilities & !replaced);
+        } else if capabilities == update.before {
+            return;
+        }
+        inner.send_caps(capabilities);
+    }
+}
+
 /// User data for seat
 pub struct SeatUserData<D: SeatHandler> {
     arc: Arc<SeatRc<D>>,
+    capabilities: wl_seat::Capability,
 }
 
 impl<D: SeatHandler> fmt::Debug for SeatUserData<D>
@@ -271,7 +382,10 @@ where
     <D as SeatHandler>::PointerFocus: fmt::Debug,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("SeatUserData").field("arc", &self.arc).finish()
+        f.debug_struct("SeatUserData")
+            .field("arc", &self.arc)
+            .field("capabilities", &self.capabilities)
+            .finish()
     }
 }
 
@@ -320,15 +434,20 @@ where
         match request {
             wl_seat::Request::GetPointer { id } => {
                 let inner = data.arc.inner.lock().unwrap();
+                // objects of filtered capabilities stay inert
+                let handle = inner
+                    .pointer
+                    .clone()
+                    .filter(|_| data.capabilities.contains(wl_seat::Capability::Pointer));
 
                 let pointer = data_init.init(
                     id,
                     PointerUserData {
-                        handle: inner.pointer.clone(),
+                        handle: handle.clone(),
                     },
                 );
 
-                if let Some(ref ptr_handle) = inner.pointer {
+                if let Some(ref ptr_handle) = handle {
                     ptr_handle.new_pointer(pointer);
                 } else {
                     // we should send a protocol error... but the protocol does not allow
@@ -337,15 +456,19 @@ where
             }
             wl_seat::Request::GetKeyboard { id } => {
                 let inner = data.arc.inner.lock().unwrap();
+                let handle = inner
+                    .keyboard
+                    .clone()
+                    .filter(|_| data.capabilities.contains(wl_seat::Capability::Keyboard));
 
                 let keyboard = data_init.init(
                     id,
                     KeyboardUserData {
-                        handle: inner.keyboard.clone(),
+                        handle: handle.clone(),
                     },
                 );
 
-                if let Some(ref h) = inner.keyboard {
+                if let Some(ref h) = handle {
                     h.new_kbd(keyboard);
                 } else {
                     // same as pointer, should error but cannot
@@ -353,15 +476,19 @@ where
             }
             wl_seat::Request::GetTouch { id } => {
                 let inner = data.arc.inner.lock().unwrap();
+                let handle = inner
+                    .touch
+                    .clone()
+                    .filter(|_| data.capabilities.contains(wl_seat::Capability::Touch));
 
                 let touch = data_init.init(
                     id,
                     TouchUserData {
-                        handle: inner.touch.clone(),
+                        handle: handle.clone(),
                     },
                 );
 
-                if let Some(ref h) = inner.touch {
+                if let Some(ref h) = handle {
                     h.new_touch(touch);
                 } else {
                     // same as pointer, should error but cannot
@@ -395,15 +522,20 @@ where
     D: 'static,
 {
     fn bind(
-        _state: &mut D,
+        state: &mut D,
         _dh: &DisplayHandle,
-        _client: &wayland_server::Client,
+        client: &wayland_server::Client,
         resource: New<WlSeat>,
         global_data: &SeatGlobalData<D>,
         data_init: &mut DataInit<'_, D>,
     ) {
+        let seat = Seat {
+            arc: global_data.arc.clone(),
+        };
+        let capabilities = state.client_seat_filter(&seat, client);
         let data = SeatUserData {
             arc: global_data.arc.clone(),
+            capabilities,
         };
 
         let resource = data_init.init(resource, data);
@@ -413,7 +545,125 @@ where
         }
 
         let mut inner = global_data.arc.inner.lock().unwrap();
-        resource.capabilities(inner.compute_caps());
+        resource.capabilities(inner.compute_caps() & capabilities);
         inner.known_seats.push(resource.downgrade());
     }
 }
+
+#[cfg(all(test, feature = "test_utils"))]
+mod tests {
+    use std::time::Duration;
+
+    use wayland_client::{protocol::wl_seat as client_seat, WEnum};
+    use wayland_server::{
+        protocol::{wl_seat::Capability, wl_surface::WlSurface},
+        Client,
+    };
+
+    use crate::{
+        input::{Seat, SeatHandler, SeatState},
+        testing::{TestClient, TestCompositor},
+    };
+
+    struct State {
+        seat_state: SeatState<State>,
+        seat: Seat<State>,
+    }
+
+    impl SeatHandler for State {
+        type KeyboardFocus = WlSurface;
+        type PointerFocus = WlSurface;
+
+        fn seat_state(&mut self) -> &mut SeatState<Self> {
+            &mut self.seat_state
+        }
+
+        fn client_seat_filter(&mut self, _seat: &Seat<Self>, _client: &Client) -> Capability {
+            Capability::Pointer
+        }
+    }
+
+    crate::delegate_seat!(State);
+
+    /// Wait for the next capabilities event, skipping the seat name
+    fn next_capabilities(
+        client: &mut TestClient,
+        compositor: &mut TestCompositor<State>,
+    ) -> Option<client_seat::Capability> {
+        loop {
+            match client.wait_for_event::<client_seat::Event, _>(compositor, Duration::from_secs(1))? {
+                client_seat::Event::Capabilities {
+                    capabilities: WEnum::Value(capabilities),
+                } => return Some(capabilities),
+                client_seat::Event::Name { .. } => continue,
+                _ => return None,
+            }
+        }
+    }
+
+    #[test]
+    fn client_seat_filter_masks_capabilities() {
+        let mut compositor = TestCompositor::new(|dh, _| {
+            let mut seat_state = SeatState::new();
+            let mut seat = seat_state.new_wl_seat(dh, "seat");
+            seat.add_pointer();
+            seat.add_touch();
+            State { seat_state, seat }
+        });
+
+        let mut client = compositor.connect();
+        let _seat = client.bind::<client_seat::WlSeat, _>(&mut compositor);
+        assert_eq!(
+            next_capabilities(&mut client, &mut compositor),
+            Some(client_seat::Capability::Pointer)
+        );
+
+        // capability changes are filtered as well
+        compositor.state.seat.remove_pointer();
+        assert_eq!(
+            next_capabilities(&mut client, &mut compositor),
+            Some(client_seat::Capability::empty())
+        );
+    }
+
+    #[test]
+    fn capability_update_sends_single_event() {
+        let mut compositor = TestCompositor::new(|dh, _| {
+            let mut seat_state = SeatState::new();
+            let seat = seat_state.new_wl_seat(dh, "seat");
+            State { seat_state, seat }
+        });
+
+        let mut client = compositor.connect();
+        let _seat = client.bind::<client_seat::WlSeat, _>(&mut compositor);
+        assert_eq!(
+            next_capabilities(&mut client, &mut compositor),
+            Some(client_seat::Capability::empty())
+        );
+
+        let guard = compositor.state.seat.begin_capability_update();
+        let nested = compositor.state.seat.begin_capability_update();
+        compositor.state.seat.add_touch();
+        drop(nested);
+        compositor.state.seat.add_pointer();
+        drop(guard);
+        // without the guard, adding touch would have sent an empty set first
+        assert_eq!(
+            next_capabilities(&mut client, &mut compositor),
+            Some(client_seat::Capability::Pointer)
+        );
+
+        // replacing a device is still announced as an unplug
+        let guard = compositor.state.seat.begin_capability_update();
+        compositor.state.seat.add_pointer();
+        drop(guard);
+        assert_eq!(
+            next_capabilities(&mut client, &mut compositor),
+            Some(client_seat::Capability::empty())
+        );
+        assert_eq!(
+            next_capabilities(&mut client, &mut compositor),
+            Some(client_seat::Capability::Pointer)
+        );
+    }
+}
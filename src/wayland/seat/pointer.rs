@@ -22,8 +22,8 @@ use crate::{
         },
         Seat,
     },
-    utils::Serial,
-    wayland::compositor,
+    utils::{IsAlive, Serial},
+    wayland::{compositor, pointer_constraints},
 };
 
 use super::{SeatHandler, SeatState, WaylandFocus};
@@ -87,6 +87,9 @@ where
         })
     }
     fn leave(&self, seat: &Seat<D>, _data: &mut D, serial: Serial, _time: u32) {
+        if let Some(pointer) = seat.get_pointer().filter(|_| self.alive()) {
+            pointer_constraints::pointer_left_surface(self, &pointer);
+        }
         for_each_focused_pointers(seat, self, |ptr| {
             ptr.leave(serial.into(), self);
             if ptr.version() >= 5 {
@@ -10,7 +10,7 @@ use wayland_server::{
 };
 
 use super::{SeatHandler, SeatState};
-use crate::backend::input::TouchSlot;
+use crate::backend::input::{TouchShape, TouchSlot};
 use crate::utils::Serial;
 use crate::utils::{Logical, Point};
 use crate::wayland::seat::wl_surface::WlSurface;
@@ -50,10 +50,36 @@ impl TouchHandle {
         slot: TouchSlot,
         location: Point<f64, Logical>,
     ) {
-        self.inner
-            .lock()
-            .unwrap()
-            .down(serial, time, surface, surface_offset, slot, location);
+        self.down_with_contact(serial, time, surface, surface_offset, slot, location, None, None);
+    }
+
+    /// Notify clients about new touch points including the geometry of the contact.
+    ///
+    /// `shape` and `orientation` are sent in the same frame as the `down` event
+    /// to clients binding `wl_touch` version 6 or newer, see
+    /// [`TouchDownEvent`](crate::backend::input::TouchDownEvent).
+    #[allow(clippy::too_many_arguments)]
+    pub fn down_with_contact(
+        &mut self,
+        serial: Serial,
+        time: u32,
+        surface: &WlSurface,
+        surface_offset: Point<i32, Logical>,
+        slot: TouchSlot,
+        location: Point<f64, Logical>,
+        shape: Option<TouchShape>,
+        orientation: Option<f64>,
+    ) {
+        self.inner.lock().unwrap().down(
+            serial,
+            time,
+            surface,
+            surface_offset,
+            slot,
+            location,
+            shape,
+            orientation,
+        );
     }
 
     /// Notify clients about touch point removal.
@@ -63,7 +89,26 @@ impl TouchHandle {
 
     /// Notify clients about touch motion.
     pub fn motion(&self, time: u32, slot: TouchSlot, location: Point<f64, Logical>) {
-        self.inner.lock().unwrap().motion(time, slot, location);
+        self.motion_with_contact(time, slot, location, None, None);
+    }
+
+    /// Notify clients about touch motion including the geometry of the contact.
+    ///
+    /// `shape` and `orientation` are sent in the same frame as the `motion` event
+    /// to clients binding `wl_touch` version 6 or newer, see
+    /// [`TouchMotionEvent`](crate::backend::input::TouchMotionEvent).
+    pub fn motion_with_contact(
+        &self,
+        time: u32,
+        slot: TouchSlot,
+        location: Point<f64, Logical>,
+        shape: Option<TouchShape>,
+        orientation: Option<f64>,
+    ) {
+        self.inner
+            .lock()
+            .unwrap()
+            .motion(time, slot, location, shape, orientation);
     }
 
     /// Notify clients about touch shape changes.
@@ -99,6 +144,7 @@ struct TouchInternal {
 }
 
 impl TouchInternal {
+    #[allow(clippy::too_many_arguments)]
     fn down(
         &mut self,
         serial: Serial,
@@ -107,6 +153,8 @@ impl TouchInternal {
         surface_offset: Point<i32, Logical>,
         slot: TouchSlot,
         location: Point<f64, Logical>,
+        shape: Option<TouchShape>,
+        orientation: Option<f64>,
     ) {
         // Update focused client state.
         let focus = self.focus.entry(slot).or_default();
@@ -122,7 +170,8 @@ impl TouchInternal {
 
         let (x, y) = (location - focus.surface_offset).into();
         self.with_focused_handles(slot, |handle| {
-            handle.down(serial.into(), time, surface, slot.into(), x, y)
+            handle.down(serial.into(), time, surface, slot.into(), x, y);
+            send_contact(handle, slot, shape, orientation);
         });
     }
 
@@ -135,30 +184,34 @@ impl TouchInternal {
         }
     }
 
-    fn motion(&self, time: u32, slot: TouchSlot, location: Point<f64, Logical>) {
+    fn motion(
+        &self,
+        time: u32,
+        slot: TouchSlot,
+        location: Point<f64, Logical>,
+        shape: Option<TouchShape>,
+        orientation: Option<f64>,
+    ) {
         let focus = match self.focus.get(&slot) {
             Some(slot) => slot,
             None => return,
         };
 
         let (x, y) = (location - focus.surface_offset).into();
-        self.with_focused_handles(slot, |handle| handle.motion(time, slot.into(), x, y));
+        self.with_focused_handles(slot, |handle| {
+            handle.motion(time, slot.into(), x, y);
+            send_contact(handle, slot, shape, orientation);
+        });
     }
 
     fn shape(&self, slot: TouchSlot, major: f64, minor: f64) {
         self.with_focused_handles(slot, |handle| {
-            if handle.version() >= 6 {
-                handle.shape(slot.into(), major, minor);
-            }
+            send_contact(handle, slot, Some(TouchShape { major, minor }), None)
         });
     }
 
     fn orientation(&self, slot: TouchSlot, orientation: f64) {
-        self.with_focused_handles(slot, |handle| {
-            if handle.version() >= 6 {
-                handle.orientation(slot.into(), orientation);
-            }
-        });
+        self.with_focused_handles(slot, |handle| send_contact(handle, slot, None, Some(orientation)));
     }
 
     fn cancel(&mut self) {
@@ -183,6 +236,19 @@ impl TouchInternal {
     }
 }
 
+/// Send the contact geometry of a touch point, if supported by the client
+fn send_contact(handle: &WlTouch, slot: TouchSlot, shape: Option<TouchShape>, orientation: Option<f64>) {
+    if handle.version() < 6 {
+        return;
+    }
+    if let Some(shape) = shape {
+        handle.shape(slot.into(), shape.major, shape.minor);
+    }
+    if let Some(orientation) = orientation {
+        handle.orientation(slot.into(), orientation);
+    }
+}
+
 /// User data for keyboard
 #[derive(Debug)]
 pub struct TouchUserData {
@@ -0,0 +1,515 @@
+//! Compositor-initiated reads of the selection
+//!
+//! Compositors sometimes need to read the selection themselves, e.g. to show a clipboard history
+//! or to bridge it to X11. [`request_selection`] starts such a read for the current selection of a
+//! seat, regardless of whether it is provided by a client or by the compositor itself. The
+//! selection protocol to read from is chosen through its state, which implements
+//! [`SelectionProtocol`]:
+//!
+//! - [`DataDeviceState`](super::data_device::DataDeviceState) for the clipboard of the
+//!   [`data_device`](super::data_device), also available as
+//!   [`request_data_device_selection`](super::data_device::request_data_device_selection)
+//! - [`PrimarySelectionState`](super::primary_selection::PrimarySelectionState) for the
+//!   [`primary_selection`](super::primary_selection), also available as
+//!   [`request_primary_selection`](super::primary_selection::request_primary_selection)
+//! - [`data_control::State`](super::data_control::State) for the selection of the
+//!   [`data_control`](super::data_control) module, also available as
+//!   [`request_data_control_selection`](super::data_control::request_data_control_selection)
+//!
+//! The read is a [`SelectionTransfer`], which has to be inserted into your event loop. It reads
+//! the selection without blocking and enforces the [`TransferOptions`], so a stalled or malicious
+//! client can neither hang the compositor nor exhaust its memory.
+//!
+//! ```no_run
+//! # use smithay::input::{Seat, SeatHandler};
+//! # use smithay::wayland::data_device::{DataDeviceHandler, DataDeviceState};
+//! use smithay::wayland::selection::{request_selection, TransferOptions};
+//!
+//! # fn example<State: SeatHandler + DataDeviceHandler + 'static>(
+//! #     state: &mut State,
+//! #     seat: &Seat<State>,
+//! #     handle: calloop::LoopHandle<'static, State>,
+//! # ) {
+//! let transfer = request_selection::<DataDeviceState, _>(
+//!     state,
+//!     seat,
+//!     "text/plain;charset=utf-8".into(),
+//!     TransferOptions::default(),
+//! )
+//! .unwrap();
+//! handle
+//!     .insert_source(transfer, |result, _, _| match result {
+//!         Ok(data) => println!("Selection contains {} bytes", data.len()),
+//!         Err(err) => println!("Failed to read the selection: {}", err),
+//!     })
+//!     .unwrap();
+//! # }
+//! ```
+
+use std::{
+    fs::File,
+    io::{self, Read},
+    os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    time::Duration,
+};
+
+use calloop::{
+    generic::Generic,
+    timer::{TimeoutAction, Timer},
+    EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+use nix::{fcntl::OFlag, unistd::pipe2};
+use tracing::trace;
+
+use crate::input::{Seat, SeatHandler};
+
+/// Size of the chunks read from a selection pipe
+const READ_CHUNK_SIZE: usize = 4096;
+
+/// Default for [`TransferOptions::timeout`]
+pub const DEFAULT_TRANSFER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default for [`TransferOptions::max_size`]
+pub const DEFAULT_TRANSFER_MAX_SIZE: usize = 16 * 1024 * 1024;
+
+/// Limits of a [`SelectionTransfer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferOptions {
+    /// Time the selection owner has to write the whole selection, `None` waits forever
+    pub timeout: Option<Duration>,
+    /// Maximum size of the selection in bytes, `None` reads selections of any size
+    pub max_size: Option<usize>,
+}
+
+impl Default for TransferOptions {
+    fn default() -> Self {
+        TransferOptions {
+            timeout: Some(DEFAULT_TRANSFER_TIMEOUT),
+            max_size: Some(DEFAULT_TRANSFER_MAX_SIZE),
+        }
+    }
+}
+
+/// Errors of a compositor-initiated selection read
+#[derive(Debug, thiserror::Error)]
+pub enum TransferError {
+    /// The seat has no selection
+    #[error("no selection is set on this seat")]
+    NoSelection,
+    /// The current selection does not offer the requested mime type
+    #[error("the selection does not offer the requested mime type")]
+    InvalidMimetype,
+    /// The selection owner did not finish writing in time
+    #[error("the selection transfer timed out")]
+    TimedOut,
+    /// The selection is larger than the configured maximum size
+    #[error("the selection exceeds the maximum size of {0} bytes")]
+    TooLarge(usize),
+    /// Creating or reading the pipe failed
+    #[error("failed to transfer the selection")]
+    Io(#[source] io::Error),
+}
+
+/// Read end of a selection transfer initiated by the compositor
+///
+/// This implements [`EventSource`] and is supposed to be inserted into your event loop.
+/// The callback is invoked a single time, either with the complete contents of the selection
+/// once the owner closed its end of the pipe, or with the [`TransferError`] that ended the
+/// transfer. The source removes itself afterwards.
+///
+/// The transfer can be cancelled at any time by removing the source from the event loop
+/// or by dropping it before it was inserted. In both cases the read end of the pipe is
+/// closed and the writer receives `EPIPE` on its next write.
+#[derive(Debug)]
+pub struct SelectionTransfer {
+    pipe: Generic<File>,
+    timer: Option<Timer>,
+    max_size: Option<usize>,
+    buffer: Vec<u8>,
+}
+
+impl SelectionTransfer {
+    /// Create a new pipe for a selection transfer
+    ///
+    /// Returns the reading side of the transfer as well as the write end of the pipe,
+    /// which has to be handed to the owner of the selection.
+    pub(crate) fn new(options: TransferOptions) -> io::Result<(SelectionTransfer, OwnedFd)> {
+        let (read, write) = pipe(true)?;
+        let transfer = SelectionTransfer {
+            pipe: Generic::new(read, Interest::READ, Mode::Level),
+            timer: options.timeout.map(Timer::from_duration),
+            max_size: options.max_size,
+            buffer: Vec::new(),
+        };
+        Ok((transfer, write))
+    }
+
+    /// Amount of bytes already read from the pipe
+    pub fn bytes_read(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+impl EventSource for SelectionTransfer {
+    /// The full contents of the selection or the error that ended the transfer
+    type Event = Result<Vec<u8>, TransferError>;
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> io::Result<PostAction>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let mut result = None;
+
+        let buffer = &mut self.buffer;
+        let max_size = self.max_size;
+        self.pipe.process_events(readiness, token, |_, file| {
+            result = match read_available(file, buffer, max_size) {
+                Ok(ReadStatus::Pending) => None,
+                Ok(ReadStatus::Finished) => Some(Ok(std::mem::take(buffer))),
+                Ok(ReadStatus::TooLarge) => Some(Err(TransferError::TooLarge(max_size.unwrap_or_default()))),
+                Err(err) => Some(Err(TransferError::Io(err))),
+            };
+            Ok(PostAction::Continue)
+        })?;
+
+        if result.is_none() {
+            if let Some(timer) = self.timer.as_mut() {
+                timer.process_events(readiness, token, |_, _| {
+                    result = Some(Err(TransferError::TimedOut));
+                    TimeoutAction::Drop
+                })?;
+            }
+        }
+
+        match result {
+            Some(result) => {
+                trace!(
+                    len = self.buffer.len(),
+                    ok = result.is_ok(),
+                    "Selection transfer ended"
+                );
+                callback(result, &mut ());
+                Ok(PostAction::Remove)
+            }
+            None => Ok(PostAction::Continue),
+        }
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.pipe.register(poll, token_factory)?;
+        if let Some(timer) = self.timer.as_mut() {
+            timer.register(poll, token_factory)?;
+        }
+        Ok(())
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.pipe.reregister(poll, token_factory)?;
+        if let Some(timer) = self.timer.as_mut() {
+            timer.reregister(poll, token_factory)?;
+        }
+        Ok(())
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.pipe.unregister(poll)?;
+        if let Some(timer) = self.timer.as_mut() {
+            timer.unregister(poll)?;
+        }
+        Ok(())
+    }
+}
+
+/// A selection protocol whose selection the compositor can read
+///
+/// Implemented by the state of every selection protocol, see the [module docs](self).
+pub trait SelectionProtocol<D: SeatHandler> {
+    /// Start reading the current selection of `seat` as `mime_type`
+    fn request_selection(
+        state: &mut D,
+        seat: &Seat<D>,
+        mime_type: String,
+        options: TransferOptions,
+    ) -> Result<SelectionTransfer, TransferError>;
+}
+
+/// Start reading the current selection of `seat` offered through the selection protocol `P`
+///
+/// The owner of the selection is asked to write its contents for the given mime type into a pipe,
+/// which is the client holding it or, for selections set by the compositor, the `send_selection`
+/// implementation of the handler of `P`.
+///
+/// The returned [`SelectionTransfer`] has to be inserted into your event loop and delivers the data
+/// or the [`TransferError`] that ended the transfer, enforcing the limits given in `options`.
+pub fn request_selection<P, D>(
+    state: &mut D,
+    seat: &Seat<D>,
+    mime_type: String,
+    options: TransferOptions,
+) -> Result<SelectionTransfer, TransferError>
+where
+    P: SelectionProtocol<D>,
+    D: SeatHandler,
+{
+    P::request_selection(state, seat, mime_type, options)
+}
+
+/// Owner of the current selection of a seat, as looked up to read it from the compositor
+///
+/// `C` is the client source of the selection protocol and `P` whatever else the compositor
+/// needs to write a selection it provides itself.
+pub(crate) enum SelectionOwner<C, P> {
+    /// A client holds the selection
+    Client {
+        /// The source the client set the selection with
+        source: C,
+        /// Mime types offered by the source
+        mime_types: Vec<String>,
+    },
+    /// The compositor set the selection
+    Compositor {
+        /// Needed to write the selection, e.g. the provider of a lazy selection
+        provider: P,
+        /// Mime types offered by the compositor
+        mime_types: Vec<String>,
+    },
+}
+
+impl<C, P> SelectionOwner<C, P> {
+    /// Whether the selection is offered as `mime_type`
+    pub(crate) fn offers(&self, mime_type: &str) -> bool {
+        let (SelectionOwner::Client { mime_types, .. } | SelectionOwner::Compositor { mime_types, .. }) =
+            self;
+        mime_types.iter().any(|offered| offered == mime_type)
+    }
+
+    /// Ask the owner to write the selection as `mime_type` into `fd`
+    ///
+    /// Clients are asked through `send_client`, the compositor through `send_compositor`.
+    pub(crate) fn send(
+        self,
+        mime_type: String,
+        fd: OwnedFd,
+        send_client: impl FnOnce(C, String, RawFd),
+        send_compositor: impl FnOnce(P, String, OwnedFd),
+    ) {
+        match self {
+            SelectionOwner::Client { source, .. } => {
+                send_client(source, mime_type, fd.as_raw_fd());
+                // the client receives its own copy of the fd, we don't need the write end anymore
+                drop(fd);
+            }
+            SelectionOwner::Compositor { provider, .. } => send_compositor(provider, mime_type, fd),
+        }
+    }
+}
+
+/// Start a [`SelectionTransfer`] of the selection held by `owner`, see [`SelectionOwner::send`]
+///
+/// The owner has to be looked up beforehand, so `send_compositor` may access the seat again.
+pub(crate) fn start_transfer<C, P>(
+    owner: Option<SelectionOwner<C, P>>,
+    mime_type: String,
+    options: TransferOptions,
+    send_client: impl FnOnce(C, String, RawFd),
+    send_compositor: impl FnOnce(P, String, OwnedFd),
+) -> Result<SelectionTransfer, TransferError> {
+    let owner = owner.ok_or(TransferError::NoSelection)?;
+    if !owner.offers(&mime_type) {
+        return Err(TransferError::InvalidMimetype);
+    }
+    let (transfer, write) = SelectionTransfer::new(options).map_err(TransferError::Io)?;
+    owner.send(mime_type, write, send_client, send_compositor);
+    Ok(transfer)
+}
+
+/// Create a pipe for a selection transfer, returning its read and write end
+pub(crate) fn pipe(nonblocking: bool) -> io::Result<(File, OwnedFd)> {
+    let flags = if nonblocking {
+        OFlag::O_CLOEXEC | OFlag::O_NONBLOCK
+    } else {
+        OFlag::O_CLOEXEC
+    };
+    let (read_fd, write_fd) = pipe2(flags)?;
+    // SAFETY: both fds were just returned by `pipe2` and are exclusively owned by us
+    Ok(unsafe { (File::from_raw_fd(read_fd), OwnedFd::from_raw_fd(write_fd)) })
+}
+
+/// State of a pipe after reading everything currently available
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReadStatus {
+    /// The writer may still write more data
+    Pending,
+    /// The writer closed its end of the pipe
+    Finished,
+    /// More data than allowed was written
+    TooLarge,
+}
+
+/// Read everything currently available from a non-blocking pipe into `buffer`
+pub(crate) fn read_available(
+    file: &mut File,
+    buffer: &mut Vec<u8>,
+    max_size: Option<usize>,
+) -> io::Result<ReadStatus> {
+    let mut chunk = [0u8; READ_CHUNK_SIZE];
+    loop {
+        match file.read(&mut chunk) {
+            Ok(0) => return Ok(ReadStatus::Finished),
+            Ok(n) => {
+                buffer.extend_from_slice(&chunk[..n]);
+                if max_size.map_or(false, |max_size| buffer.len() > max_size) {
+                    return Ok(ReadStatus::TooLarge);
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(ReadStatus::Pending),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, io::Write, time::Duration};
+
+    use super::{start_transfer, SelectionOwner, SelectionTransfer, TransferError, TransferOptions};
+
+    type Results = Vec<Result<Vec<u8>, TransferError>>;
+
+    fn start(options: TransferOptions) -> (calloop::EventLoop<'static, Results>, std::fs::File) {
+        let event_loop = calloop::EventLoop::<Results>::try_new().unwrap();
+        let (transfer, write) = SelectionTransfer::new(options).unwrap();
+        event_loop
+            .handle()
+            .insert_source(transfer, |result, _, results| results.push(result))
+            .unwrap();
+        (event_loop, std::fs::File::from(write))
+    }
+
+    #[test]
+    fn transfer_delivers_data() {
+        let (mut event_loop, mut write) = start(TransferOptions::default());
+        write.write_all(b"hello").unwrap();
+        drop(write);
+
+        let mut results = Vec::new();
+        event_loop.dispatch(Some(Duration::ZERO), &mut results).unwrap();
+        assert!(matches!(&results[..], [Ok(data)] if data == b"hello"));
+    }
+
+    #[test]
+    fn transfer_enforces_max_size() {
+        let (mut event_loop, mut write) = start(TransferOptions {
+            timeout: None,
+            max_size: Some(4),
+        });
+        write.write_all(b"hello").unwrap();
+
+        let mut results = Vec::new();
+        event_loop.dispatch(Some(Duration::ZERO), &mut results).unwrap();
+        assert!(matches!(&results[..], [Err(TransferError::TooLarge(4))]));
+        // the source removed itself and closed the pipe
+        assert!(write.write_all(b"more").is_err());
+    }
+
+    #[test]
+    fn stalled_transfer_times_out() {
+        let (mut event_loop, mut write) = start(TransferOptions {
+            timeout: Some(Duration::from_millis(10)),
+            max_size: None,
+        });
+        write.write_all(b"partial").unwrap();
+
+        let mut results = Vec::new();
+        event_loop.dispatch(Some(Duration::ZERO), &mut results).unwrap();
+        assert!(results.is_empty());
+
+        event_loop
+            .dispatch(Some(Duration::from_millis(100)), &mut results)
+            .unwrap();
+        assert!(matches!(&results[..], [Err(TransferError::TimedOut)]));
+    }
+
+    /// Assert that the read end of the pipe behind `write` was closed
+    fn assert_reader_closed(write: &mut std::fs::File) {
+        let err = write.write_all(b"more").unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(nix::errno::Errno::EPIPE as i32));
+    }
+
+    #[test]
+    fn dropping_unregistered_transfer_closes_pipe() {
+        let (transfer, write) = SelectionTransfer::new(TransferOptions::default()).unwrap();
+        let mut write = std::fs::File::from(write);
+        drop(transfer);
+        assert_reader_closed(&mut write);
+    }
+
+    #[test]
+    fn removing_transfer_mid_read_closes_pipe() {
+        let mut event_loop = calloop::EventLoop::<Results>::try_new().unwrap();
+        let (transfer, write) = SelectionTransfer::new(TransferOptions::default()).unwrap();
+        let mut write = std::fs::File::from(write);
+        let token = event_loop
+            .handle()
+            .insert_source(transfer, |result, _, results| results.push(result))
+            .unwrap();
+
+        write.write_all(b"partial").unwrap();
+        let mut results = Vec::new();
+        event_loop.dispatch(Some(Duration::ZERO), &mut results).unwrap();
+        assert!(results.is_empty());
+
+        // cancel the transfer while the writer is still alive
+        event_loop.handle().remove(token);
+        assert_reader_closed(&mut write);
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn transfer_is_started_with_the_owner() {
+        let start = |owner: Option<SelectionOwner<(), ()>>, mime_type: &str| {
+            let sent = Cell::new(None);
+            let result = start_transfer(
+                owner,
+                mime_type.into(),
+                TransferOptions::default(),
+                |(), _, _| sent.set(Some("client")),
+                |(), _, _| sent.set(Some("compositor")),
+            );
+            (result, sent.get())
+        };
+        let compositor = || SelectionOwner::Compositor {
+            provider: (),
+            mime_types: vec!["text/plain".into()],
+        };
+
+        let (result, sent) = start(None, "text/plain");
+        assert!(matches!(result, Err(TransferError::NoSelection)));
+        assert_eq!(sent, None);
+
+        let (result, sent) = start(Some(compositor()), "image/png");
+        assert!(matches!(result, Err(TransferError::InvalidMimetype)));
+        assert_eq!(sent, None);
+
+        let (result, sent) = start(Some(compositor()), "text/plain");
+        assert!(result.is_ok());
+        assert_eq!(sent, Some("compositor"));
+
+        let client = SelectionOwner::Client {
+            source: (),
+            mime_types: vec!["text/plain".into()],
+        };
+        let (result, sent) = start(Some(client), "text/plain");
+        assert!(result.is_ok());
+        assert_eq!(sent, Some("client"));
+    }
+}
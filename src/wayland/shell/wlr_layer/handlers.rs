@@ -105,7 +105,7 @@ where
                             .unwrap()
                             .lock()
                             .unwrap();
-                        attributes.surface = id.clone();
+                        attributes.surface = Some(id.clone());
                     }
 
                     states.cached_state.pending::<LayerSurfaceCachedState>().layer = layer;
@@ -126,18 +126,22 @@ where
                             let pending = states.cached_state.pending::<LayerSurfaceCachedState>();
 
                             if pending.size.w == 0 && !pending.anchor.anchored_horizontally() {
-                                guard.surface.post_error(
-                                    zwlr_layer_surface_v1::Error::InvalidSize,
-                                    "width 0 requested without setting left and right anchors",
-                                );
+                                if let Some(surface) = guard.surface.as_ref() {
+                                    surface.post_error(
+                                        zwlr_layer_surface_v1::Error::InvalidSize,
+                                        "width 0 requested without setting left and right anchors",
+                                    );
+                                }
                                 return;
                             }
 
                             if pending.size.h == 0 && !pending.anchor.anchored_vertically() {
-                                guard.surface.post_error(
-                                    zwlr_layer_surface_v1::Error::InvalidSize,
-                                    "height 0 requested without setting top and bottom anchors",
-                                );
+                                if let Some(surface) = guard.surface.as_ref() {
+                                    surface.post_error(
+                                        zwlr_layer_surface_v1::Error::InvalidSize,
+                                        "height 0 requested without setting top and bottom anchors",
+                                    );
+                                }
                                 return;
                             }
 
@@ -146,6 +150,14 @@ where
                             }
                         });
                     });
+                    compositor::add_destruction_hook(&wl_surface, |states| {
+                        // the layer surface holds a handle to the wl_surface, drop it to not keep
+                        // the surface data alive. The remaining state is reset once the layer
+                        // surface is destroyed, after the handler saw it.
+                        if let Some(attributes) = states.data_map.get::<Mutex<LayerSurfaceAttributes>>() {
+                            attributes.lock().unwrap().surface = None;
+                        }
+                    });
                 }
 
                 let handle = super::LayerSurface {
@@ -246,9 +258,18 @@ where
             } => {
                 match KeyboardInteractivity::try_from(keyboard_interactivity) {
                     Ok(keyboard_interactivity) => {
-                        with_surface_pending_state(layer_surface, |data| {
+                        let changed = with_surface_pending_state(layer_surface, |data| {
+                            let changed = data.keyboard_interactivity != keyboard_interactivity;
                             data.keyboard_interactivity = keyboard_interactivity;
+                            changed
                         });
+                        if changed {
+                            WlrLayerShellHandler::keyboard_interactivity_changed(
+                                state,
+                                make_surface_handle(layer_surface),
+                                keyboard_interactivity,
+                            );
+                        }
                     }
                     Err((err, msg)) => {
                         layer_surface.post_error(err, msg);
@@ -90,7 +90,8 @@ pub type LayerSurfaceData = Mutex<LayerSurfaceAttributes>;
 /// Attributes for layer surface
 #[derive(Debug)]
 pub struct LayerSurfaceAttributes {
-    surface: zwlr_layer_surface_v1::ZwlrLayerSurfaceV1,
+    /// Unset once the wl_surface got destroyed
+    surface: Option<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1>,
     /// Defines if the surface has received at least one
     /// layer_surface.ack_configure from the client
     pub configured: bool,
@@ -107,6 +108,9 @@ pub struct LayerSurfaceAttributes {
     /// than the acknowledged one will be discarded during
     /// processing layer_surface.ack_configure.
     pending_configures: Vec<LayerSurfaceConfigure>,
+    /// A configure has been scheduled and will be sent
+    /// on the next flush of the pending configures.
+    configure_scheduled: bool,
     /// Holds the pending state as set by the server.
     pub server_pending: Option<LayerSurfaceState>,
     /// Holds the last server_pending state that has been acknowledged
@@ -121,11 +125,12 @@ pub struct LayerSurfaceAttributes {
 impl LayerSurfaceAttributes {
     fn new(surface: zwlr_layer_surface_v1::ZwlrLayerSurfaceV1) -> Self {
         Self {
-            surface,
+            surface: Some(surface),
             configured: false,
             configure_serial: None,
             initial_configure_sent: false,
             pending_configures: Vec::new(),
+            configure_scheduled: false,
             server_pending: None,
             last_acked: None,
             current: Default::default(),
@@ -152,6 +157,7 @@ impl LayerSurfaceAttributes {
         self.configure_serial = None;
         self.initial_configure_sent = false;
         self.pending_configures = Vec::new();
+        self.configure_scheduled = false;
         self.server_pending = None;
         self.last_acked = None;
         self.current = Default::default();
@@ -225,6 +231,22 @@ impl WlrLayerShellState {
     pub fn layer_surfaces(&self) -> impl DoubleEndedIterator<Item = LayerSurface> {
         self.known_layers.lock().unwrap().clone().into_iter()
     }
+
+    /// Send all configures scheduled since the last flush
+    ///
+    /// Every layer surface marked with [`LayerSurface::schedule_configure`] receives a
+    /// single configure containing all of its pending changes.
+    ///
+    /// See [`XdgShellState::flush_pending_configures`](crate::wayland::shell::xdg::XdgShellState::flush_pending_configures)
+    /// for the xdg-shell counterpart.
+    pub fn flush_pending_configures(&self) {
+        for layer in self
+            .layer_surfaces()
+            .filter(|l| l.alive() && l.is_configure_scheduled())
+        {
+            layer.send_configure();
+        }
+    }
 }
 
 /// Handler for wlr layer shell
@@ -253,6 +275,16 @@ pub trait WlrLayerShellHandler {
 
     /// A layer surface was destroyed.
     fn layer_destroyed(&mut self, surface: LayerSurface) {}
+
+    /// A layer surface requested a different keyboard interactivity
+    ///
+    /// The change is double-buffered and applies with the next commit of the surface, until then
+    /// [`LayerSurface::keyboard_interactivity`] still returns the previous mode.
+    ///
+    /// Compositors should update the keyboard focus accordingly: a surface on the top or overlay
+    /// layer requesting [`KeyboardInteractivity::Exclusive`] is expected to receive the keyboard
+    /// focus and keep it, while a surface switching to [`KeyboardInteractivity::None`] must lose it.
+    fn keyboard_interactivity_changed(&mut self, surface: LayerSurface, mode: KeyboardInteractivity) {}
 }
 
 /// A handle to a layer surface
@@ -324,6 +356,8 @@ impl LayerSurface {
                 .unwrap()
                 .lock()
                 .unwrap();
+            // everything pending is sent now, nothing left to flush
+            attributes.configure_scheduled = false;
             if let Some(pending) = self.get_pending_state(&mut attributes) {
                 let configure = LayerSurfaceConfigure {
                     serial: SERIAL_COUNTER.next_serial(),
@@ -348,6 +382,39 @@ impl LayerSurface {
         }
     }
 
+    /// Schedule a configure event for this layer surface
+    ///
+    /// The surface is marked as dirty and the configure is sent on the next call to
+    /// [`WlrLayerShellState::flush_pending_configures`], merging all changes made until
+    /// then into a single configure with a single serial.
+    ///
+    /// Use [`send_configure`](#method.send_configure) instead if you need the configure
+    /// to be sent immediately.
+    pub fn schedule_configure(&self) {
+        compositor::with_states(&self.wl_surface, |states| {
+            states
+                .data_map
+                .get::<Mutex<LayerSurfaceAttributes>>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .configure_scheduled = true;
+        })
+    }
+
+    /// Returns `true` if a configure has been scheduled but not sent yet
+    pub fn is_configure_scheduled(&self) -> bool {
+        compositor::with_states(&self.wl_surface, |states| {
+            states
+                .data_map
+                .get::<Mutex<LayerSurfaceAttributes>>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .configure_scheduled
+        })
+    }
+
     /// Make sure this surface was configured
     ///
     /// Returns `true` if it was, if not, returns `false` and raise
@@ -390,7 +457,8 @@ impl LayerSurface {
     /// This should be used to inform the client about size and state changes,
     /// for example after a resize request from the client.
     ///
-    /// The state will be sent to the client when calling [`send_configure`](#method.send_configure).
+    /// The state will be sent to the client when calling [`send_configure`](#method.send_configure),
+    /// or on the next flush after calling [`schedule_configure`](#method.schedule_configure).
     pub fn with_pending_state<F, T>(&self, f: F) -> T
     where
         F: FnOnce(&mut LayerSurfaceState) -> T,
@@ -411,6 +479,16 @@ impl LayerSurface {
         })
     }
 
+    /// Keyboard interactivity of this layer surface, as of its last commit
+    pub fn keyboard_interactivity(&self) -> KeyboardInteractivity {
+        compositor::with_states(&self.wl_surface, |states| {
+            states
+                .cached_state
+                .current::<LayerSurfaceCachedState>()
+                .keyboard_interactivity
+        })
+    }
+
     /// Gets a copy of the current state of this layer
     ///
     /// Returns `None` if the underlying surface has been
@@ -466,3 +544,198 @@ macro_rules! delegate_layer_shell {
         ] => $crate::wayland::shell::wlr_layer::WlrLayerShellState);
     };
 }
+
+#[cfg(all(test, feature = "test_utils"))]
+mod tests {
+    use std::time::Duration;
+
+    use wayland_client::protocol::{wl_compositor::WlCompositor, wl_surface::WlSurface as ClientSurface};
+    use wayland_protocols_wlr::layer_shell::v1::client::{
+        zwlr_layer_shell_v1::{self, ZwlrLayerShellV1},
+        zwlr_layer_surface_v1::{self, ZwlrLayerSurfaceV1},
+    };
+    use wayland_server::protocol::{wl_output::WlOutput, wl_surface::WlSurface};
+
+    use super::{KeyboardInteractivity, Layer, LayerSurface, WlrLayerShellHandler, WlrLayerShellState};
+    use crate::{
+        testing::{Recorded, TestClient, TestCompositor},
+        wayland::compositor::{CompositorHandler, CompositorState},
+    };
+
+    struct State {
+        compositor_state: CompositorState,
+        layer_shell_state: WlrLayerShellState,
+        surfaces: Vec<LayerSurface>,
+        changes: Vec<KeyboardInteractivity>,
+    }
+
+    impl CompositorHandler for State {
+        fn compositor_state(&mut self) -> &mut CompositorState {
+            &mut self.compositor_state
+        }
+
+        fn commit(&mut self, _surface: &WlSurface) {}
+    }
+
+    impl WlrLayerShellHandler for State {
+        fn shell_state(&mut self) -> &mut WlrLayerShellState {
+            &mut self.layer_shell_state
+        }
+
+        fn new_layer_surface(
+            &mut self,
+            surface: LayerSurface,
+            _output: Option<WlOutput>,
+            _layer: Layer,
+            _namespace: String,
+        ) {
+            self.surfaces.push(surface);
+        }
+
+        fn keyboard_interactivity_changed(&mut self, _surface: LayerSurface, mode: KeyboardInteractivity) {
+            self.changes.push(mode);
+        }
+    }
+
+    crate::delegate_compositor!(State);
+    crate::delegate_layer_shell!(State);
+
+    fn compositor() -> TestCompositor<State> {
+        TestCompositor::new(|dh, _| State {
+            compositor_state: CompositorState::new::<State>(dh),
+            layer_shell_state: WlrLayerShellState::new::<State>(dh),
+            surfaces: Vec::new(),
+            changes: Vec::new(),
+        })
+    }
+
+    /// Sizes of the configure events received since the last call
+    fn configures(client: &mut TestClient, compositor: &mut TestCompositor<State>) -> Vec<(u32, u32)> {
+        client.roundtrip(compositor);
+        let mut configures = Vec::new();
+        while let Some(event) =
+            client.wait_for_event::<zwlr_layer_surface_v1::Event, _>(compositor, Duration::ZERO)
+        {
+            if let zwlr_layer_surface_v1::Event::Configure { width, height, .. } = event {
+                configures.push((width, height));
+            }
+        }
+        configures
+    }
+
+    /// Create a layer surface, which already received its initial configure
+    fn configured_layer_surface(
+        client: &mut TestClient,
+        compositor: &mut TestCompositor<State>,
+    ) -> (ClientSurface, ZwlrLayerSurfaceV1, LayerSurface) {
+        let wl_compositor = client.bind::<WlCompositor, _>(compositor);
+        let layer_shell = client.bind::<ZwlrLayerShellV1, _>(compositor);
+        let qh = client.queue_handle();
+
+        let surface = wl_compositor.create_surface(&qh, Recorded);
+        let layer_surface = layer_shell.get_layer_surface(
+            &surface,
+            None,
+            zwlr_layer_shell_v1::Layer::Top,
+            "panel".into(),
+            &qh,
+            Recorded,
+        );
+        layer_surface.set_size(200, 30);
+        surface.commit();
+        client.roundtrip(compositor);
+
+        let server_surface = compositor.state.surfaces.last().unwrap().clone();
+        server_surface.send_configure();
+        assert_eq!(configures(client, compositor), vec![(0, 0)]);
+        (surface, layer_surface, server_surface)
+    }
+
+    #[test]
+    fn scheduled_changes_are_sent_in_one_configure() {
+        let mut compositor = compositor();
+        let mut client = compositor.connect();
+        let (_surface, _layer_surface, layer) = configured_layer_surface(&mut client, &mut compositor);
+
+        layer.with_pending_state(|state| state.size = Some((100, 50).into()));
+        layer.schedule_configure();
+        layer.with_pending_state(|state| state.size = Some((200, 100).into()));
+        layer.schedule_configure();
+
+        // nothing is sent before the flush
+        assert!(layer.is_configure_scheduled());
+        assert!(configures(&mut client, &mut compositor).is_empty());
+
+        compositor.state.layer_shell_state.flush_pending_configures();
+        assert!(!layer.is_configure_scheduled());
+        assert_eq!(configures(&mut client, &mut compositor), vec![(200, 100)]);
+    }
+
+    #[test]
+    fn sent_or_unchanged_state_is_not_flushed() {
+        let mut compositor = compositor();
+        let mut client = compositor.connect();
+        let (_surface, _layer_surface, layer) = configured_layer_surface(&mut client, &mut compositor);
+
+        // send_configure sends everything scheduled so far
+        layer.with_pending_state(|state| state.size = Some((100, 50).into()));
+        layer.schedule_configure();
+        layer.send_configure();
+        assert!(!layer.is_configure_scheduled());
+        assert_eq!(configures(&mut client, &mut compositor), vec![(100, 50)]);
+        compositor.state.layer_shell_state.flush_pending_configures();
+        assert!(configures(&mut client, &mut compositor).is_empty());
+
+        // setting the size that was already sent is no change
+        layer.with_pending_state(|state| state.size = Some((100, 50).into()));
+        layer.schedule_configure();
+        compositor.state.layer_shell_state.flush_pending_configures();
+        assert!(!layer.is_configure_scheduled());
+        assert!(configures(&mut client, &mut compositor).is_empty());
+    }
+
+    #[test]
+    fn keyboard_interactivity_applies_on_commit() {
+        let mut compositor = compositor();
+        let mut client = compositor.connect();
+        let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+        let layer_shell = client.bind::<ZwlrLayerShellV1, _>(&mut compositor);
+        let qh = client.queue_handle();
+
+        let surface = wl_compositor.create_surface(&qh, Recorded);
+        let layer_surface = layer_shell.get_layer_surface(
+            &surface,
+            None,
+            zwlr_layer_shell_v1::Layer::Overlay,
+            "lock".into(),
+            &qh,
+            Recorded,
+        );
+        layer_surface.set_size(200, 100);
+        surface.commit();
+        client.roundtrip(&mut compositor);
+        let server_surface = compositor.state.surfaces[0].clone();
+        assert_eq!(
+            server_surface.keyboard_interactivity(),
+            KeyboardInteractivity::None
+        );
+
+        layer_surface.set_keyboard_interactivity(zwlr_layer_surface_v1::KeyboardInteractivity::Exclusive);
+        // setting the same mode again is not reported twice
+        layer_surface.set_keyboard_interactivity(zwlr_layer_surface_v1::KeyboardInteractivity::Exclusive);
+        client.roundtrip(&mut compositor);
+        assert_eq!(compositor.state.changes, vec![KeyboardInteractivity::Exclusive]);
+        // the change is double-buffered
+        assert_eq!(
+            server_surface.keyboard_interactivity(),
+            KeyboardInteractivity::None
+        );
+
+        surface.commit();
+        client.roundtrip(&mut compositor);
+        assert_eq!(
+            server_surface.keyboard_interactivity(),
+            KeyboardInteractivity::Exclusive
+        );
+    }
+}
@@ -81,6 +81,8 @@ where
             drop(shell_data);
             let surface = popup.wl_surface().clone();
             XdgShellHandler::popup_destroyed(state, popup);
+            // reset the role state only after the handler saw it, the wl_surface may
+            // already be destroyed, e.g. on client disconnect
             compositor::with_states(&surface, |states| {
                 *states
                     .data_map
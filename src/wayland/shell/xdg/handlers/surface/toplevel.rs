@@ -136,6 +136,8 @@ where
             drop(shell_data);
             let surface = toplevel.wl_surface().clone();
             XdgShellHandler::toplevel_destroyed(state, toplevel);
+            // reset the role state only after the handler saw it, the wl_surface may
+            // already be destroyed, e.g. on client disconnect
             compositor::with_states(&surface, |states| {
                 *states
                     .data_map
@@ -98,6 +98,7 @@ use crate::wayland::compositor::Cacheable;
 use crate::wayland::shell::is_toplevel_equivalent;
 use std::fmt::Debug;
 use std::sync::{Arc, Mutex};
+use tracing::debug;
 
 use wayland_protocols::xdg::decoration::zv1::server::zxdg_toplevel_decoration_v1;
 use wayland_protocols::xdg::shell::server::xdg_surface;
@@ -169,6 +170,9 @@ macro_rules! xdg_role {
                 /// than the acknowledged one will be discarded during
                 /// processing xdg_surface.ack_configure.
                 pending_configures: Vec<$configure_name>,
+                /// A configure has been scheduled and will be sent
+                /// on the next flush of the pending configures.
+                configure_scheduled: bool,
                 /// Holds the pending state as set by the server.
                 pub server_pending: Option<$state>,
                 /// Holds the last server_pending state that has been acknowledged
@@ -261,6 +265,7 @@ macro_rules! xdg_role {
                     configured: false,
                     configure_serial: None,
                     pending_configures: Vec::new(),
+                    configure_scheduled: false,
                     initial_configure_sent: false,
                     initial_decoration_configure_sent: false,
                     server_pending: None,
@@ -957,6 +962,38 @@ impl XdgShellState {
     pub fn global(&self) -> GlobalId {
         self.global.clone()
     }
+
+    /// Send all configures scheduled since the last flush
+    ///
+    /// Every toplevel and popup surface marked with `schedule_configure` receives a
+    /// single configure containing all of its pending changes. Surfaces whose pending
+    /// state did not actually change are skipped.
+    ///
+    /// This is typically called once per event loop iteration, for example from an
+    /// idle callback inserted with [`LoopHandle::insert_idle`](calloop::LoopHandle::insert_idle)
+    /// whenever a configure has been scheduled.
+    pub fn flush_pending_configures(&self) {
+        // clone the handles so that no lock is held while sending events
+        let (toplevels, popups) = {
+            let inner = self.inner.lock().unwrap();
+            (inner.known_toplevels.clone(), inner.known_popups.clone())
+        };
+
+        for toplevel in toplevels
+            .iter()
+            .filter(|t| t.alive() && t.is_configure_scheduled())
+        {
+            toplevel.send_configure();
+        }
+
+        for popup in popups.iter().filter(|p| p.alive() && p.is_configure_scheduled()) {
+            if let Err(err) = popup.send_configure() {
+                // the configure is not allowed anymore, so drop it instead of retrying forever
+                popup.set_configure_scheduled(false);
+                debug!(surface = ?popup.wl_surface(), "Dropping scheduled popup configure: {}", err);
+            }
+        }
+    }
 }
 
 #[derive(Default, Debug)]
@@ -1115,6 +1152,8 @@ impl ToplevelSurface {
                 .unwrap()
                 .lock()
                 .unwrap();
+            // everything pending is sent now, nothing left to flush
+            attributes.configure_scheduled = false;
             if let Some(pending) = self.get_pending_state(&mut attributes) {
                 // retrieve the current state before adding it to the
                 // pending state so that we can compare what has changed
@@ -1162,6 +1201,40 @@ impl ToplevelSurface {
         }
     }
 
+    /// Schedule a configure event for this toplevel surface
+    ///
+    /// Instead of sending a configure right away, the surface is marked as dirty and the
+    /// configure is sent on the next call to [`XdgShellState::flush_pending_configures`].
+    /// All changes made with [`with_pending_state`](#method.with_pending_state) until then
+    /// are merged into a single configure with a single serial.
+    ///
+    /// Use [`send_configure`](#method.send_configure) instead if you need the configure
+    /// to be sent immediately, e.g. during an interactive resize.
+    pub fn schedule_configure(&self) {
+        compositor::with_states(&self.wl_surface, |states| {
+            states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .configure_scheduled = true;
+        })
+    }
+
+    /// Returns `true` if a configure has been scheduled but not sent yet
+    pub fn is_configure_scheduled(&self) -> bool {
+        compositor::with_states(&self.wl_surface, |states| {
+            states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .configure_scheduled
+        })
+    }
+
     /// Handles the role specific commit logic
     ///
     /// This should be called when the underlying WlSurface
@@ -1232,7 +1305,8 @@ impl ToplevelSurface {
     /// This should be used to inform the client about size and state changes,
     /// for example after a resize request from the client.
     ///
-    /// The state will be sent to the client when calling [`send_configure`](#method.send_configure).
+    /// The state will be sent to the client when calling [`send_configure`](#method.send_configure),
+    /// or on the next flush after calling [`schedule_configure`](#method.schedule_configure).
     pub fn with_pending_state<F, T>(&self, f: F) -> T
     where
         F: FnOnce(&mut ToplevelState) -> T,
@@ -1372,6 +1446,8 @@ impl PopupSurface {
                 .unwrap()
                 .lock()
                 .unwrap();
+            // everything pending is sent now, nothing left to flush
+            attributes.configure_scheduled = false;
 
             if !attributes.initial_configure_sent
                 || attributes.has_pending_changes()
@@ -1450,6 +1526,43 @@ impl PopupSurface {
         self.send_configure_internal(Some(token))
     }
 
+    /// Schedule a configure event for this popup surface
+    ///
+    /// The popup is marked as dirty and the configure is sent on the next call to
+    /// [`XdgShellState::flush_pending_configures`], merging all changes made until then
+    /// into a single configure.
+    ///
+    /// Use [`send_configure`](#method.send_configure) instead if you need the configure
+    /// to be sent immediately.
+    pub fn schedule_configure(&self) {
+        self.set_configure_scheduled(true)
+    }
+
+    fn set_configure_scheduled(&self, scheduled: bool) {
+        compositor::with_states(&self.wl_surface, |states| {
+            states
+                .data_map
+                .get::<XdgPopupSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .configure_scheduled = scheduled;
+        })
+    }
+
+    /// Returns `true` if a configure has been scheduled but not sent yet
+    pub fn is_configure_scheduled(&self) -> bool {
+        compositor::with_states(&self.wl_surface, |states| {
+            states
+                .data_map
+                .get::<XdgPopupSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .configure_scheduled
+        })
+    }
+
     /// Handles the role specific commit logic
     ///
     /// This should be called when the underlying WlSurface
@@ -1551,7 +1664,8 @@ impl PopupSurface {
     /// This should be used to inform the client about size and position changes,
     /// for example after a move of the parent toplevel.
     ///
-    /// The state will be sent to the client when calling [`send_configure`](#method.send_configure).
+    /// The state will be sent to the client when calling [`send_configure`](#method.send_configure),
+    /// or on the next flush after calling [`schedule_configure`](#method.schedule_configure).
     pub fn with_pending_state<F, T>(&self, f: F) -> T
     where
         F: FnOnce(&mut PopupState) -> T,
@@ -1623,3 +1737,348 @@ macro_rules! delegate_xdg_shell {
         ] => $crate::wayland::shell::xdg::XdgShellState);
     };
 }
+
+#[cfg(all(test, feature = "test_utils"))]
+mod tests {
+    use std::{
+        sync::{Arc, Weak},
+        time::Duration,
+    };
+
+    use wayland_client::protocol::{wl_compositor::WlCompositor, wl_surface::WlSurface as ClientSurface};
+    use wayland_protocols::xdg::shell::{
+        client::{
+            xdg_surface::{self, XdgSurface},
+            xdg_toplevel::{self, XdgToplevel},
+            xdg_wm_base::XdgWmBase,
+        },
+        server::xdg_toplevel::State as ToplevelState,
+    };
+    use wayland_server::protocol::{wl_seat, wl_surface::WlSurface};
+
+    use super::{
+        PopupSurface, PositionerState, ToplevelSurface, XdgShellHandler, XdgShellState,
+        XdgToplevelSurfaceData,
+    };
+    use crate::{
+        testing::{Recorded, TestClient, TestCompositor},
+        utils::Serial,
+        wayland::compositor::{self, CompositorHandler, CompositorState},
+    };
+
+    /// Marker inserted into the data of every surface, to notice when the data is freed
+    struct SurfaceDataAlive(#[allow(dead_code)] Arc<()>);
+
+    #[derive(Default)]
+    struct Observed {
+        surface_data: Vec<Weak<()>>,
+        destroyed_titles: Vec<Option<String>>,
+        toplevels: Vec<ToplevelSurface>,
+        popups: Vec<PopupSurface>,
+    }
+
+    struct State {
+        compositor_state: CompositorState,
+        xdg_shell_state: XdgShellState,
+        observed: Observed,
+    }
+
+    impl CompositorHandler for State {
+        fn compositor_state(&mut self) -> &mut CompositorState {
+            &mut self.compositor_state
+        }
+
+        fn commit(&mut self, surface: &WlSurface) {
+            let observed = &mut self.observed;
+            compositor::with_states(surface, |states| {
+                let alive = Arc::new(());
+                if states
+                    .data_map
+                    .insert_if_missing_threadsafe(|| SurfaceDataAlive(alive.clone()))
+                {
+                    observed.surface_data.push(Arc::downgrade(&alive));
+                }
+            });
+        }
+    }
+
+    impl XdgShellHandler for State {
+        fn xdg_shell_state(&mut self) -> &mut XdgShellState {
+            &mut self.xdg_shell_state
+        }
+
+        fn new_toplevel(&mut self, surface: ToplevelSurface) {
+            self.observed.toplevels.push(surface);
+        }
+
+        fn new_popup(&mut self, surface: PopupSurface, _positioner: PositionerState) {
+            self.observed.popups.push(surface);
+        }
+
+        fn grab(&mut self, _surface: PopupSurface, _seat: wl_seat::WlSeat, _serial: Serial) {}
+
+        fn toplevel_destroyed(&mut self, surface: ToplevelSurface) {
+            let title = compositor::with_states(surface.wl_surface(), |states| {
+                states
+                    .data_map
+                    .get::<XdgToplevelSurfaceData>()
+                    .unwrap()
+                    .lock()
+                    .unwrap()
+                    .title
+                    .clone()
+            });
+            self.observed.destroyed_titles.push(title);
+        }
+    }
+
+    crate::delegate_compositor!(State);
+    crate::delegate_xdg_shell!(State);
+
+    fn compositor() -> TestCompositor<State> {
+        TestCompositor::new(|dh, _| State {
+            compositor_state: CompositorState::new::<State>(dh),
+            xdg_shell_state: XdgShellState::new::<State>(dh),
+            observed: Observed::default(),
+        })
+    }
+
+    #[test]
+    fn toplevel_state_is_readable_after_surface_destruction() {
+        let mut compositor = compositor();
+        let mut client = compositor.connect();
+        let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+        let wm_base = client.bind::<XdgWmBase, _>(&mut compositor);
+        let qh = client.queue_handle();
+
+        let surface = wl_compositor.create_surface(&qh, Recorded);
+        let xdg_surface = wm_base.get_xdg_surface(&surface, &qh, Recorded);
+        let toplevel = xdg_surface.get_toplevel(&qh, Recorded);
+        toplevel.set_title("title".into());
+        client.roundtrip(&mut compositor);
+
+        // like on client disconnect, the wl_surface goes away before its role object
+        surface.destroy();
+        toplevel.destroy();
+        xdg_surface.destroy();
+        client.roundtrip(&mut compositor);
+
+        assert_eq!(
+            compositor.state.observed.destroyed_titles,
+            vec![Some("title".into())]
+        );
+    }
+
+    #[test]
+    fn destroyed_surfaces_free_their_data() {
+        const SURFACES: usize = 10_000;
+        const BATCH: usize = 500;
+
+        let mut compositor = compositor();
+        let mut client = compositor.connect();
+        let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+        let wm_base = client.bind::<XdgWmBase, _>(&mut compositor);
+        let qh = client.queue_handle();
+
+        for _ in 0..SURFACES / BATCH {
+            let mut parent = None;
+            let mut objects = Vec::with_capacity(BATCH);
+            for _ in 0..BATCH {
+                let surface = wl_compositor.create_surface(&qh, Recorded);
+                let xdg_surface = wm_base.get_xdg_surface(&surface, &qh, Recorded);
+                let toplevel = xdg_surface.get_toplevel(&qh, Recorded);
+                // parents reference the surfaces of other toplevels
+                toplevel.set_parent(parent.as_ref());
+                surface.commit();
+                parent = Some(toplevel.clone());
+                objects.push((surface, xdg_surface, toplevel));
+            }
+            client.roundtrip(&mut compositor);
+
+            for (surface, xdg_surface, toplevel) in objects.into_iter().rev() {
+                toplevel.destroy();
+                xdg_surface.destroy();
+                surface.destroy();
+            }
+            client.roundtrip(&mut compositor);
+        }
+
+        let observed = &compositor.state.observed;
+        assert_eq!(observed.surface_data.len(), SURFACES);
+        let alive = observed
+            .surface_data
+            .iter()
+            .filter(|data| data.upgrade().is_some())
+            .count();
+        assert_eq!(
+            alive, 0,
+            "data of {} destroyed surfaces is still allocated",
+            alive
+        );
+    }
+
+    /// Serials of the `xdg_surface.configure` events received since the last call
+    fn surface_configures(client: &mut TestClient, compositor: &mut TestCompositor<State>) -> Vec<u32> {
+        client.roundtrip(compositor);
+        let mut serials = Vec::new();
+        while let Some(event) = client.wait_for_event::<xdg_surface::Event, _>(compositor, Duration::ZERO) {
+            if let xdg_surface::Event::Configure { serial } = event {
+                serials.push(serial);
+            }
+        }
+        serials
+    }
+
+    /// Sizes and states of the `xdg_toplevel.configure` events received since the last call
+    fn toplevel_configures(
+        client: &mut TestClient,
+        compositor: &mut TestCompositor<State>,
+    ) -> Vec<((i32, i32), Vec<u32>)> {
+        client.roundtrip(compositor);
+        let mut configures = Vec::new();
+        while let Some(event) = client.wait_for_event::<xdg_toplevel::Event, _>(compositor, Duration::ZERO) {
+            if let xdg_toplevel::Event::Configure {
+                width,
+                height,
+                states,
+            } = event
+            {
+                let states = states
+                    .chunks_exact(4)
+                    .map(|state| u32::from_ne_bytes(state.try_into().unwrap()))
+                    .collect();
+                configures.push(((width, height), states));
+            }
+        }
+        configures
+    }
+
+    /// Create a toplevel, which already received its initial configure
+    fn configured_toplevel(
+        client: &mut TestClient,
+        compositor: &mut TestCompositor<State>,
+    ) -> (ClientSurface, XdgSurface, XdgToplevel, ToplevelSurface) {
+        let wl_compositor = client.bind::<WlCompositor, _>(compositor);
+        let wm_base = client.bind::<XdgWmBase, _>(compositor);
+        let qh = client.queue_handle();
+
+        let surface = wl_compositor.create_surface(&qh, Recorded);
+        let xdg_surface = wm_base.get_xdg_surface(&surface, &qh, Recorded);
+        let toplevel = xdg_surface.get_toplevel(&qh, Recorded);
+        surface.commit();
+        client.roundtrip(compositor);
+
+        let server_toplevel = compositor.state.observed.toplevels.last().unwrap().clone();
+        server_toplevel.send_configure();
+        assert_eq!(surface_configures(client, compositor).len(), 1);
+        assert_eq!(toplevel_configures(client, compositor).len(), 1);
+        (surface, xdg_surface, toplevel, server_toplevel)
+    }
+
+    #[test]
+    fn scheduled_changes_are_sent_in_one_configure() {
+        let mut compositor = compositor();
+        let mut client = compositor.connect();
+        let (_surface, _xdg_surface, _toplevel, toplevel) = configured_toplevel(&mut client, &mut compositor);
+
+        toplevel.with_pending_state(|state| state.size = Some((100, 100).into()));
+        toplevel.schedule_configure();
+        toplevel.with_pending_state(|state| state.states.set(ToplevelState::Activated));
+        toplevel.schedule_configure();
+        toplevel.with_pending_state(|state| state.size = Some((200, 150).into()));
+        toplevel.schedule_configure();
+
+        // nothing is sent before the flush
+        assert!(toplevel.is_configure_scheduled());
+        assert!(surface_configures(&mut client, &mut compositor).is_empty());
+
+        compositor.state.xdg_shell_state.flush_pending_configures();
+        assert!(!toplevel.is_configure_scheduled());
+        let serials = surface_configures(&mut client, &mut compositor);
+        assert_eq!(serials.len(), 1);
+        assert_eq!(
+            toplevel_configures(&mut client, &mut compositor),
+            vec![((200, 150), vec![ToplevelState::Activated as u32])]
+        );
+
+        // the configure is tracked with the serial sent to the client
+        let pending_serial = compositor::with_states(toplevel.wl_surface(), |states| {
+            let attributes = states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap();
+            attributes.pending_configures.last().unwrap().serial
+        });
+        assert_eq!(Serial::from(serials[0]), pending_serial);
+    }
+
+    #[test]
+    fn send_configure_clears_the_scheduled_configure() {
+        let mut compositor = compositor();
+        let mut client = compositor.connect();
+        let (_surface, _xdg_surface, _toplevel, toplevel) = configured_toplevel(&mut client, &mut compositor);
+
+        toplevel.with_pending_state(|state| state.size = Some((100, 100).into()));
+        toplevel.schedule_configure();
+        toplevel.send_configure();
+        assert!(!toplevel.is_configure_scheduled());
+        assert_eq!(surface_configures(&mut client, &mut compositor).len(), 1);
+
+        // the change was already sent, so the flush has nothing left to do
+        compositor.state.xdg_shell_state.flush_pending_configures();
+        assert!(surface_configures(&mut client, &mut compositor).is_empty());
+    }
+
+    #[test]
+    fn unchanged_state_sends_nothing() {
+        let mut compositor = compositor();
+        let mut client = compositor.connect();
+        let (_surface, _xdg_surface, _toplevel, toplevel) = configured_toplevel(&mut client, &mut compositor);
+
+        // a change reverted before the flush is no change at all
+        toplevel.with_pending_state(|state| state.states.set(ToplevelState::Activated));
+        toplevel.with_pending_state(|state| state.states.unset(ToplevelState::Activated));
+        toplevel.schedule_configure();
+        compositor.state.xdg_shell_state.flush_pending_configures();
+
+        assert!(!toplevel.is_configure_scheduled());
+        assert!(surface_configures(&mut client, &mut compositor).is_empty());
+        assert!(toplevel_configures(&mut client, &mut compositor).is_empty());
+    }
+
+    #[test]
+    fn failed_popup_configure_is_dropped() {
+        let mut compositor = compositor();
+        let mut client = compositor.connect();
+        let (_surface, parent, _toplevel, _) = configured_toplevel(&mut client, &mut compositor);
+        let wl_compositor = client.bind::<WlCompositor, _>(&mut compositor);
+        let wm_base = client.bind::<XdgWmBase, _>(&mut compositor);
+        let qh = client.queue_handle();
+
+        // the positioner is not reactive, so the popup can not be configured again
+        let positioner = wm_base.create_positioner(&qh, Recorded);
+        positioner.set_size(10, 10);
+        positioner.set_anchor_rect(0, 0, 1, 1);
+        let surface = wl_compositor.create_surface(&qh, Recorded);
+        let xdg_surface = wm_base.get_xdg_surface(&surface, &qh, Recorded);
+        let _popup = xdg_surface.get_popup(Some(&parent), &positioner, &qh, Recorded);
+        surface.commit();
+        client.roundtrip(&mut compositor);
+
+        let popup = compositor.state.observed.popups[0].clone();
+        popup.send_configure().unwrap();
+        assert_eq!(surface_configures(&mut client, &mut compositor).len(), 1);
+
+        popup.with_pending_state(|state| state.geometry.loc = (5, 5).into());
+        popup.schedule_configure();
+        compositor.state.xdg_shell_state.flush_pending_configures();
+        assert!(!popup.is_configure_scheduled());
+        assert!(surface_configures(&mut client, &mut compositor).is_empty());
+
+        // the configure is not retried on later flushes
+        compositor.state.xdg_shell_state.flush_pending_configures();
+        assert!(surface_configures(&mut client, &mut compositor).is_empty());
+    }
+}
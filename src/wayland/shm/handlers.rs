@@ -182,10 +182,16 @@ where
             Request::Resize { size } => {
                 if size <= 0 {
                     pool.post_error(wl_shm::Error::InvalidFd, "invalid wl_shm_pool size");
+                    return;
                 }
 
-                if let Err(err) = arc_pool.resize(NonZeroUsize::try_from(size as usize).unwrap()) {
-                    match err {
+                let old_size = arc_pool.size();
+                match arc_pool.resize(NonZeroUsize::try_from(size as usize).unwrap()) {
+                    Ok(()) if old_size < size as usize => {
+                        state.pool_resized(pool, old_size as i32, size);
+                    }
+                    Ok(()) => {}
+                    Err(err) => match err {
                         ResizeError::InvalidSize => {
                             pool.post_error(wl_shm::Error::InvalidFd, "cannot shrink wl_shm_pool");
                         }
@@ -193,7 +199,7 @@ where
                         ResizeError::MremapFailed => {
                             pool.post_error(wl_shm::Error::InvalidFd, "mremap failed");
                         }
-                    }
+                    },
                 }
             }
 
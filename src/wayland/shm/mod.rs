@@ -169,6 +169,14 @@ impl ShmState {
 pub trait ShmHandler {
     /// Return the Shm global state
     fn shm_state(&self) -> &ShmState;
+
+    /// A client grew one of its pools
+    ///
+    /// The pool is already remapped with its new size at this point, so buffers may now
+    /// reference the new memory. Sizes are in bytes.
+    fn pool_resized(&mut self, pool: &WlShmPool, old_size: i32, new_size: i32) {
+        let _ = (pool, old_size, new_size);
+    }
 }
 
 /// Error that can occur when accessing an SHM buffer
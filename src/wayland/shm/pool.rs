@@ -164,6 +164,19 @@ impl MemMap {
         })
     }
 
+    /// Grow the mapping in place or move it, keeping the old mapping valid on failure
+    #[cfg(target_os = "linux")]
+    fn remap(&mut self, newsize: NonZeroUsize) -> Result<(), ()> {
+        if self.ptr.is_null() {
+            return Err(());
+        }
+        let ptr = unsafe { remap(self.ptr, self.size, newsize) }?;
+        self.ptr = ptr;
+        self.size = usize::from(newsize);
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
     fn remap(&mut self, newsize: NonZeroUsize) -> Result<(), ()> {
         if self.ptr.is_null() {
             return Err(());
@@ -224,6 +237,21 @@ unsafe fn map(fd: RawFd, size: NonZeroUsize) -> Result<*mut u8, ()> {
     ret.map(|p| p as *mut u8).map_err(|_| ())
 }
 
+/// A simple wrapper with some default arguments for `nix::mman::mremap`.
+#[cfg(target_os = "linux")]
+unsafe fn remap(ptr: *mut u8, size: usize, newsize: NonZeroUsize) -> Result<*mut u8, ()> {
+    let ret = unsafe {
+        mman::mremap(
+            ptr as *mut _,
+            size,
+            newsize.into(),
+            mman::MRemapFlags::MREMAP_MAYMOVE,
+            None,
+        )
+    };
+    ret.map(|p| p as *mut u8).map_err(|_| ())
+}
+
 /// A simple wrapper for `nix::mman::munmap`.
 unsafe fn unmap(ptr: *mut u8, size: usize) -> Result<(), ()> {
     let ret = unsafe { mman::munmap(ptr as *mut _, size) };
@@ -314,3 +342,38 @@ unsafe fn siginfo_si_addr(info: *mut libc::siginfo_t) -> *mut libc::c_void {
 unsafe fn siginfo_si_addr(info: *mut libc::siginfo_t) -> *mut libc::c_void {
     (*info).si_addr
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::CString, num::NonZeroUsize, os::unix::io::FromRawFd};
+
+    use nix::{
+        sys::memfd::{memfd_create, MemFdCreateFlag},
+        unistd::ftruncate,
+    };
+
+    use super::{OwnedFd, Pool};
+
+    #[test]
+    fn grown_pool_maps_new_memory() {
+        let name = CString::new("smithay-shm-test").unwrap();
+        let fd = memfd_create(&name, MemFdCreateFlag::MFD_CLOEXEC).unwrap();
+        ftruncate(fd, 4096).unwrap();
+        // SAFETY: the fd was just created and is exclusively owned by us
+        let pool = Pool::new(
+            unsafe { OwnedFd::from_raw_fd(fd) },
+            NonZeroUsize::new(4096).unwrap(),
+        )
+        .unwrap();
+        pool.with_data_mut(|ptr, _| unsafe { *ptr = 42 }).unwrap();
+
+        ftruncate(fd, 8192).unwrap();
+        assert!(pool.resize(NonZeroUsize::new(8192).unwrap()).is_ok());
+        assert_eq!(pool.size(), 8192);
+        pool.with_data_mut(|ptr, len| unsafe { *ptr.add(len - 1) = 1 })
+            .unwrap();
+        assert_eq!(pool.with_data(|ptr, _| unsafe { *ptr }).unwrap(), 42);
+
+        assert!(pool.resize(NonZeroUsize::new(4096).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,383 @@
+//! Graceful shutdown of the compositor
+//!
+//! Exiting by just dropping the state leaves clients with dangling globals and grabs, and
+//! closing DRM devices with page flips still in flight can leave the VT in a broken state.
+//! [`Shutdown`] orders the required steps and is driven from the event loop of the compositor:
+//!
+//! 1. [`Shutdown::begin`] disables all globals, releases the pointer and keyboard of every seat,
+//!    cancelling their grabs and drag'n'drop operations. It neither blocks nor dispatches
+//!    anything, so it can be called from any event source callback, e.g. one handling `SIGTERM`.
+//! 2. [`Shutdown::dispatch`] is then called on every iteration of the event loop, after the
+//!    clients were dispatched. It flushes the clients, waits for pending page flips reported by
+//!    [`ShutdownHandler::pending_page_flips`], removes the globals once clients had time to
+//!    notice them being disabled (see [`Shutdown::with_global_removal_delay`]) and finally calls
+//!    [`ShutdownHandler::release_devices`], in which the compositor should drop DRM master
+//!    (see [`DrmDevice::pause`](crate::backend::drm::DrmDevice::pause)) and close its session
+//!    to restore text mode.
+//! 3. Once [`ShutdownProgress::Done`] is returned, the event loop can be exited.
+//!
+//! Waiting for page flips is bounded by the timeout given to [`Shutdown::begin`], a device
+//! that never completes its flip does not prevent the compositor from exiting.
+
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info, warn};
+use wayland_server::{backend::GlobalId, Display, DisplayHandle};
+
+use crate::{
+    input::{pointer::MotionEvent, Seat, SeatHandler},
+    output::Output,
+    utils::SERIAL_COUNTER,
+};
+
+/// Time after which disabled globals are removed by default
+const DEFAULT_GLOBAL_REMOVAL_DELAY: Duration = Duration::from_secs(1);
+
+/// Handler trait for [`Shutdown`]
+pub trait ShutdownHandler: SeatHandler + Sized + 'static {
+    /// Globals to remove on shutdown
+    ///
+    /// Seat globals and the globals of the outputs returned by [`ShutdownHandler::shutdown_outputs`]
+    /// are removed without being listed here. Everything else, like the globals of the `*State`
+    /// types of protocols, has to be returned by the compositor.
+    fn shutdown_globals(&mut self) -> Vec<GlobalId>;
+
+    /// Outputs whose globals should be removed on shutdown
+    ///
+    /// Their globals are removed using [`Output::destroy_global`], so [`Output::global`] does not
+    /// return a stale id afterwards. The default implementation returns no outputs.
+    fn shutdown_outputs(&mut self) -> Vec<Output> {
+        Vec::new()
+    }
+
+    /// Number of page flips that were queued but have not completed yet
+    ///
+    /// The shutdown waits until this drops to zero or its timeout passes before releasing
+    /// the devices. The default implementation reports no pending page flips.
+    fn pending_page_flips(&mut self) -> usize {
+        0
+    }
+
+    /// Release the devices of the compositor
+    ///
+    /// Called once at the end of the shutdown. Compositors should drop DRM master and close
+    /// their session here, which restores text mode on the VT.
+    fn release_devices(&mut self) {}
+}
+
+/// Progress of a [`Shutdown`], returned by [`Shutdown::dispatch`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownProgress {
+    /// Globals were disabled and clients got notified, they are removed later on
+    ///
+    /// Also returned while waiting for the global removal delay to pass.
+    GlobalsDisabled,
+    /// Waiting for page flips to complete
+    WaitingForPageFlips {
+        /// Number of page flips still pending
+        pending: usize,
+    },
+    /// Globals were removed and the devices released, the compositor can exit
+    Done {
+        /// Whether pending page flips were abandoned because the timeout passed
+        timed_out: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Disabled,
+    Flushed,
+    Done { timed_out: bool },
+}
+
+/// Orchestration of a graceful shutdown
+///
+/// See the [module documentation](self) for details.
+#[derive(Debug)]
+pub struct Shutdown {
+    globals: Vec<GlobalId>,
+    outputs: Vec<Output>,
+    disabled_at: Instant,
+    removal_delay: Duration,
+    deadline: Instant,
+    stage: Stage,
+}
+
+impl Shutdown {
+    /// Begin shutting down the compositor
+    ///
+    /// Disables all globals, releases the keyboard and pointer focus of all seats and cancels
+    /// their grabs. `timeout` limits how long [`Shutdown::dispatch`] waits for pending page flips.
+    ///
+    /// This should only be called once, store the returned [`Shutdown`] to drive it.
+    pub fn begin<D: ShutdownHandler>(state: &mut D, display: &DisplayHandle, timeout: Duration) -> Shutdown {
+        info!("Shutting down");
+
+        let seats = state.seat_state().seats.clone();
+        let outputs = state.shutdown_outputs();
+        let mut globals = Vec::new();
+        let requested = state.shutdown_globals();
+        for global in requested
+            .into_iter()
+            .chain(seats.iter().filter_map(|seat| seat.global()))
+            .chain(outputs.iter().filter_map(|output| output.global()))
+        {
+            if !globals.contains(&global) {
+                display.disable_global::<D>(global.clone());
+                globals.push(global);
+            }
+        }
+
+        for seat in &seats {
+            release_seat(state, seat);
+        }
+
+        let now = Instant::now();
+        Shutdown {
+            globals,
+            outputs,
+            disabled_at: now,
+            removal_delay: DEFAULT_GLOBAL_REMOVAL_DELAY,
+            deadline: now + timeout,
+            stage: Stage::Disabled,
+        }
+    }
+
+    /// Set the time after which the disabled globals are removed, one second by default
+    ///
+    /// Clients that did not process the removal of a global before it is destroyed may still
+    /// try to bind it. Globals are removed after at least one call to [`Shutdown::dispatch`],
+    /// even with a delay of zero.
+    pub fn with_global_removal_delay(mut self, delay: Duration) -> Shutdown {
+        self.removal_delay = delay;
+        self
+    }
+
+    /// Advance the shutdown
+    ///
+    /// Has to be called on every iteration of the event loop after dispatching the clients,
+    /// until [`ShutdownProgress::Done`] is returned.
+    pub fn dispatch<D: ShutdownHandler>(
+        &mut self,
+        state: &mut D,
+        display: &mut Display<D>,
+    ) -> ShutdownProgress {
+        // clients may already have disconnected, which does not matter anymore
+        let _ = display.flush_clients();
+
+        match self.stage {
+            Stage::Disabled => {
+                // clients get an iteration to see the globals going away before they are removed
+                self.stage = Stage::Flushed;
+                return ShutdownProgress::GlobalsDisabled;
+            }
+            Stage::Flushed => {}
+            Stage::Done { timed_out } => return ShutdownProgress::Done { timed_out },
+        }
+        if self.disabled_at.elapsed() < self.removal_delay {
+            return ShutdownProgress::GlobalsDisabled;
+        }
+
+        let pending = state.pending_page_flips();
+        let timed_out = pending > 0 && Instant::now() >= self.deadline;
+        if pending > 0 && !timed_out {
+            return ShutdownProgress::WaitingForPageFlips { pending };
+        }
+        if timed_out {
+            warn!(pending, "Abandoning pending page flips");
+        }
+
+        let dh = display.handle();
+        for seat in &state.seat_state().seats {
+            let mut inner = seat.arc.inner.lock().unwrap();
+            if inner
+                .global
+                .as_ref()
+                .map_or(false, |global| self.globals.contains(global))
+            {
+                inner.global = None;
+            }
+        }
+        for output in self.outputs.drain(..) {
+            if let Some(global) = output.global() {
+                self.globals.retain(|other| *other != global);
+                output.destroy_global::<D>(&dh);
+            }
+        }
+        for global in self.globals.drain(..) {
+            dh.remove_global::<D>(global);
+        }
+        let _ = display.flush_clients();
+
+        debug!("Releasing devices");
+        state.release_devices();
+
+        self.stage = Stage::Done { timed_out };
+        ShutdownProgress::Done { timed_out }
+    }
+
+    /// Whether the shutdown completed
+    pub fn is_done(&self) -> bool {
+        matches!(self.stage, Stage::Done { .. })
+    }
+}
+
+fn release_seat<D: ShutdownHandler>(state: &mut D, seat: &Seat<D>) {
+    let serial = SERIAL_COUNTER.next_serial();
+    if let Some(pointer) = seat.get_pointer() {
+        // dropping the grab also abandons drag'n'drop operations
+        pointer.unset_grab(state, serial, 0);
+        let location = pointer.current_location();
+        pointer.motion(
+            state,
+            None,
+            &MotionEvent {
+                location,
+                serial,
+                time: 0,
+            },
+        );
+    }
+    if let Some(keyboard) = seat.get_keyboard() {
+        keyboard.unset_grab();
+        keyboard.set_focus(state, None, serial);
+    }
+}
+
+#[cfg(all(test, feature = "test_utils"))]
+mod tests {
+    use std::time::Duration;
+
+    use wayland_server::protocol::wl_surface::WlSurface;
+
+    use super::{Shutdown, ShutdownHandler, ShutdownProgress};
+    use crate::{
+        input::{Seat, SeatHandler, SeatState},
+        output::Output,
+        testing::{test_output, TestCompositor},
+    };
+
+    struct State {
+        seat_state: SeatState<Self>,
+        output: Output,
+        pending_page_flips: usize,
+        released: bool,
+    }
+
+    impl SeatHandler for State {
+        type KeyboardFocus = WlSurface;
+        type PointerFocus = WlSurface;
+
+        fn seat_state(&mut self) -> &mut SeatState<Self> {
+            &mut self.seat_state
+        }
+    }
+
+    impl ShutdownHandler for State {
+        fn shutdown_globals(&mut self) -> Vec<wayland_server::backend::GlobalId> {
+            Vec::new()
+        }
+
+        fn shutdown_outputs(&mut self) -> Vec<Output> {
+            vec![self.output.clone()]
+        }
+
+        fn pending_page_flips(&mut self) -> usize {
+            self.pending_page_flips
+        }
+
+        fn release_devices(&mut self) {
+            assert!(!self.released, "devices released twice");
+            self.released = true;
+        }
+    }
+
+    crate::delegate_seat!(State);
+    crate::delegate_output!(State);
+
+    fn compositor() -> (TestCompositor<State>, Seat<State>) {
+        let mut compositor = TestCompositor::new(|dh, _| {
+            let output = test_output("test", (1920, 1080));
+            output.create_global::<State>(dh);
+            State {
+                seat_state: SeatState::new(),
+                output,
+                pending_page_flips: 0,
+                released: false,
+            }
+        });
+        let dh = compositor.handle();
+        let seat = compositor.state.seat_state.new_wl_seat(&dh, "seat");
+        (compositor, seat)
+    }
+
+    #[test]
+    fn shutdown_waits_for_page_flips() {
+        let (mut compositor, seat) = compositor();
+        let mut client = compositor.connect();
+        client.roundtrip(&mut compositor);
+        assert!(client.has_global("wl_seat"));
+        assert!(client.has_global("wl_output"));
+
+        let dh = compositor.handle();
+        let mut shutdown = Shutdown::begin(&mut compositor.state, &dh, Duration::from_secs(60))
+            .with_global_removal_delay(Duration::ZERO);
+        compositor.state.pending_page_flips = 1;
+
+        client.roundtrip(&mut compositor);
+        assert!(!client.has_global("wl_seat"));
+        assert!(!client.has_global("wl_output"));
+
+        let progress = shutdown.dispatch(&mut compositor.state, &mut compositor.display);
+        assert_eq!(progress, ShutdownProgress::GlobalsDisabled);
+        let progress = shutdown.dispatch(&mut compositor.state, &mut compositor.display);
+        assert_eq!(progress, ShutdownProgress::WaitingForPageFlips { pending: 1 });
+        assert!(!compositor.state.released);
+        assert!(seat.global().is_some());
+        assert!(compositor.state.output.global().is_some());
+
+        compositor.state.pending_page_flips = 0;
+        let progress = shutdown.dispatch(&mut compositor.state, &mut compositor.display);
+        assert_eq!(progress, ShutdownProgress::Done { timed_out: false });
+        assert!(shutdown.is_done());
+        assert!(compositor.state.released);
+        assert!(seat.global().is_none());
+        assert!(compositor.state.output.global().is_none());
+
+        // further dispatches do not repeat the shutdown
+        let progress = shutdown.dispatch(&mut compositor.state, &mut compositor.display);
+        assert_eq!(progress, ShutdownProgress::Done { timed_out: false });
+    }
+
+    #[test]
+    fn shutdown_abandons_page_flips_after_timeout() {
+        let (mut compositor, _seat) = compositor();
+        compositor.state.pending_page_flips = 2;
+
+        let dh = compositor.handle();
+        let mut shutdown = Shutdown::begin(&mut compositor.state, &dh, Duration::ZERO)
+            .with_global_removal_delay(Duration::ZERO);
+        let progress = shutdown.dispatch(&mut compositor.state, &mut compositor.display);
+        assert_eq!(progress, ShutdownProgress::GlobalsDisabled);
+        let progress = shutdown.dispatch(&mut compositor.state, &mut compositor.display);
+        assert_eq!(progress, ShutdownProgress::Done { timed_out: true });
+        assert!(compositor.state.released);
+    }
+
+    #[test]
+    fn globals_are_removed_after_delay() {
+        let (mut compositor, seat) = compositor();
+        let dh = compositor.handle();
+        let mut shutdown = Shutdown::begin(&mut compositor.state, &dh, Duration::ZERO)
+            .with_global_removal_delay(Duration::from_secs(60));
+
+        for _ in 0..3 {
+            let progress = shutdown.dispatch(&mut compositor.state, &mut compositor.display);
+            assert_eq!(progress, ShutdownProgress::GlobalsDisabled);
+        }
+        assert!(seat.global().is_some());
+        assert!(compositor.state.output.global().is_some());
+        assert!(!compositor.state.released);
+    }
+}
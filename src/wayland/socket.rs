@@ -43,14 +43,37 @@
 //! #     ) {}
 //! # }
 //! ```
+//!
+//! # Multiple sockets
+//!
+//! Compositors listening on more than one socket, e.g. a public socket and one for privileged
+//! helpers, or using sockets passed via systemd socket activation, may use the [`SocketManager`].
+//! It inserts all its sockets into the event loop and creates the client data for new clients
+//! using a per-socket factory, so clients of different sockets can be told apart, e.g. in
+//! global filters.
 
-use std::{ffi::OsStr, io, os::unix::net::UnixStream};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    fmt, io,
+    os::unix::{
+        io::{FromRawFd, RawFd},
+        net::{UnixListener, UnixStream},
+    },
+    rc::Rc,
+    sync::Arc,
+};
 
 use calloop::{
-    generic::Generic, EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory,
+    generic::Generic, EventSource, Interest, LoopHandle, Mode, Poll, PostAction, Readiness,
+    RegistrationToken, Token, TokenFactory,
+};
+use tracing::{debug, info, warn};
+use wayland_server::{
+    backend::{ClientData, ClientId, DisconnectReason},
+    BindError, Client, DisplayHandle, ListeningSocket,
 };
-use tracing::{debug, info};
-use wayland_server::{BindError, ListeningSocket};
 
 /// A Wayland listening socket event source.
 ///
@@ -133,3 +156,320 @@ impl EventSource for ListeningSocketSource {
         self.socket.unregister(poll)
     }
 }
+
+/// Identifier of a socket of a [`SocketManager`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SocketId(usize);
+
+/// Errors of the [`SocketManager`]
+#[derive(Debug, thiserror::Error)]
+pub enum SocketError {
+    /// Binding a named socket failed
+    #[error("Failed to bind socket: {0}")]
+    Bind(#[from] BindError),
+    /// Accessing a socket failed
+    #[error("Failed to access socket: {0}")]
+    Io(#[from] io::Error),
+    /// Inserting the socket into the event loop failed
+    #[error("Failed to insert socket into the event loop: {0}")]
+    EventLoop(#[source] calloop::Error),
+}
+
+/// Factory for the [`ClientData`] of clients connecting to a socket of a [`SocketManager`]
+pub type ClientDataFactory = Box<dyn FnMut(&UnixStream) -> Arc<dyn ClientData>>;
+
+type NewClientCallback<D> = Rc<RefCell<dyn FnMut(&mut D, SocketId, &Client)>>;
+
+#[derive(Debug)]
+struct SocketEntry {
+    name: Option<OsString>,
+    token: RegistrationToken,
+    clients: Vec<ClientId>,
+}
+
+/// Manager of multiple listening sockets
+///
+/// Every socket is inserted into the event loop as its own source. New clients of any socket are
+/// inserted into the display with the client data created by the factory of their socket, before
+/// the callback given to [`SocketManager::new`] is invoked.
+pub struct SocketManager<D: 'static> {
+    handle: LoopHandle<'static, D>,
+    display: DisplayHandle,
+    callback: NewClientCallback<D>,
+    sockets: Rc<RefCell<HashMap<SocketId, SocketEntry>>>,
+    next_id: usize,
+}
+
+impl<D: 'static> fmt::Debug for SocketManager<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SocketManager")
+            .field("display", &self.display)
+            .field("sockets", &self.sockets)
+            .field("next_id", &self.next_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<D: 'static> SocketManager<D> {
+    /// Create a new socket manager without any sockets
+    ///
+    /// The `callback` is invoked for every new client after it was inserted into the display.
+    pub fn new<F>(handle: LoopHandle<'static, D>, display: DisplayHandle, callback: F) -> SocketManager<D>
+    where
+        F: FnMut(&mut D, SocketId, &Client) + 'static,
+    {
+        SocketManager {
+            handle,
+            display,
+            callback: Rc::new(RefCell::new(callback)),
+            sockets: Rc::new(RefCell::new(HashMap::new())),
+            next_id: 0,
+        }
+    }
+
+    /// Add a socket, automatically choosing the next available `wayland` socket name
+    pub fn add_auto<F>(&mut self, client_data: F) -> Result<SocketId, SocketError>
+    where
+        F: FnMut(&UnixStream) -> Arc<dyn ClientData> + 'static,
+    {
+        let source = ListeningSocketSource::new_auto()?;
+        let name = source.socket_name().to_os_string();
+        self.insert(source, Some(name), Box::new(client_data))
+    }
+
+    /// Add a socket with the given name
+    pub fn add_named<F>(&mut self, name: &str, client_data: F) -> Result<SocketId, SocketError>
+    where
+        F: FnMut(&UnixStream) -> Arc<dyn ClientData> + 'static,
+    {
+        let source = ListeningSocketSource::with_name(name)?;
+        let name = source.socket_name().to_os_string();
+        self.insert(source, Some(name), Box::new(client_data))
+    }
+
+    /// Add an already listening socket
+    pub fn add_listener<F>(&mut self, listener: UnixListener, client_data: F) -> Result<SocketId, SocketError>
+    where
+        F: FnMut(&UnixStream) -> Arc<dyn ClientData> + 'static,
+    {
+        listener.set_nonblocking(true)?;
+        let source = ListenerSource {
+            listener: Generic::new(listener, Interest::READ, Mode::Level),
+        };
+        self.insert(source, None, Box::new(client_data))
+    }
+
+    /// Add the sockets passed by systemd socket activation
+    ///
+    /// Reads the `LISTEN_PID` and `LISTEN_FDS` environment variables as described in `sd_listen_fds(3)`
+    /// and removes them afterwards, so the sockets are not picked up a second time or by child
+    /// processes. All sockets share the same client data factory.
+    ///
+    /// Returns an empty list, if no sockets were passed to this process.
+    pub fn add_activated_sockets<F>(&mut self, client_data: F) -> Result<Vec<SocketId>, SocketError>
+    where
+        F: FnMut(&UnixStream) -> Arc<dyn ClientData> + 'static,
+    {
+        let fds = listen_fds(
+            std::env::var("LISTEN_PID").ok().as_deref(),
+            std::env::var("LISTEN_FDS").ok().as_deref(),
+            std::process::id(),
+        );
+        std::env::remove_var("LISTEN_PID");
+        std::env::remove_var("LISTEN_FDS");
+        std::env::remove_var("LISTEN_FDNAMES");
+
+        let client_data = Rc::new(RefCell::new(client_data));
+        let mut ids = Vec::new();
+        for fd in fds {
+            nix::fcntl::fcntl(fd, nix::fcntl::FcntlArg::F_SETFD(nix::fcntl::FdFlag::FD_CLOEXEC))
+                .map_err(io::Error::from)?;
+            // SAFETY: the fd was passed to this process by the service manager and is owned by us
+            let listener = unsafe { UnixListener::from_raw_fd(fd) };
+            let client_data = client_data.clone();
+            ids.push(self.add_listener(listener, move |stream: &UnixStream| {
+                (&mut *client_data.borrow_mut())(stream)
+            })?);
+        }
+        info!(count = ids.len(), "Added activated sockets");
+        Ok(ids)
+    }
+
+    /// Remove a socket
+    ///
+    /// If `disconnect_clients` is set, all clients which connected through this socket
+    /// are disconnected. Returns `false` if the socket was already removed.
+    pub fn remove_socket(&mut self, id: SocketId, disconnect_clients: bool) -> bool {
+        let entry = match self.sockets.borrow_mut().remove(&id) {
+            Some(entry) => entry,
+            None => return false,
+        };
+        self.handle.remove(entry.token);
+        info!(socket = ?entry.name, "Removed socket");
+
+        if disconnect_clients {
+            for client in entry.clients {
+                self.display
+                    .backend_handle()
+                    .kill_client(client, DisconnectReason::ConnectionClosed);
+            }
+        }
+        true
+    }
+
+    /// Returns the name of a socket, if it is a named socket
+    pub fn socket_name(&self, id: SocketId) -> Option<OsString> {
+        self.sockets
+            .borrow()
+            .get(&id)
+            .and_then(|entry| entry.name.clone())
+    }
+
+    /// Returns the ids of all sockets of this manager
+    pub fn sockets(&self) -> Vec<SocketId> {
+        let mut ids = self.sockets.borrow().keys().copied().collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    /// Returns the socket a client connected through, if it is managed by this manager
+    pub fn socket_of_client(&self, client: &ClientId) -> Option<SocketId> {
+        self.sockets
+            .borrow()
+            .iter()
+            .find(|(_, entry)| entry.clients.contains(client))
+            .map(|(id, _)| *id)
+    }
+
+    fn insert<S>(
+        &mut self,
+        source: S,
+        name: Option<OsString>,
+        mut client_data: ClientDataFactory,
+    ) -> Result<SocketId, SocketError>
+    where
+        S: EventSource<Event = UnixStream, Metadata = (), Ret = ()> + 'static,
+    {
+        let id = SocketId(self.next_id);
+        self.next_id += 1;
+
+        let mut display = self.display.clone();
+        let callback = self.callback.clone();
+        let sockets = self.sockets.clone();
+        let token = self
+            .handle
+            .insert_source(source, move |stream, _, state| {
+                let data = client_data(&stream);
+                let client = match display.insert_client(stream, data) {
+                    Ok(client) => client,
+                    Err(err) => {
+                        warn!(?err, "Failed to insert client");
+                        return;
+                    }
+                };
+
+                if let Some(entry) = sockets.borrow_mut().get_mut(&id) {
+                    entry
+                        .clients
+                        .retain(|client| display.backend_handle().get_client_data(client.clone()).is_ok());
+                    entry.clients.push(client.id());
+                }
+                (&mut *callback.borrow_mut())(state, id, &client);
+            })
+            .map_err(|err| SocketError::EventLoop(err.error))?;
+
+        info!(socket = ?name, "Added socket");
+        self.sockets.borrow_mut().insert(
+            id,
+            SocketEntry {
+                name,
+                token,
+                clients: Vec::new(),
+            },
+        );
+        Ok(id)
+    }
+}
+
+/// Event source accepting clients on an already listening socket
+#[derive(Debug)]
+struct ListenerSource {
+    listener: Generic<UnixListener>,
+}
+
+impl EventSource for ListenerSource {
+    type Event = UnixStream;
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> io::Result<PostAction>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        self.listener.process_events(readiness, token, |_, listener| {
+            loop {
+                match listener.accept() {
+                    Ok((client, _)) => {
+                        debug!(client = ?client, "New client connected");
+                        callback(client, &mut ());
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(err) => return Err(err),
+                }
+            }
+
+            Ok(PostAction::Continue)
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.listener.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.listener.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.listener.unregister(poll)
+    }
+}
+
+/// Parses the socket activation environment as described in `sd_listen_fds(3)`
+fn listen_fds(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> Vec<RawFd> {
+    const SD_LISTEN_FDS_START: RawFd = 3;
+
+    if listen_pid.and_then(|listen_pid| listen_pid.parse::<u32>().ok()) != Some(pid) {
+        return Vec::new();
+    }
+    match listen_fds.and_then(|listen_fds| listen_fds.parse::<RawFd>().ok()) {
+        Some(count) if count > 0 => (SD_LISTEN_FDS_START..SD_LISTEN_FDS_START + count).collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::listen_fds;
+
+    #[test]
+    fn listen_fds_requires_matching_pid() {
+        assert_eq!(listen_fds(Some("42"), Some("2"), 42), vec![3, 4]);
+        assert!(listen_fds(Some("41"), Some("2"), 42).is_empty());
+        assert!(listen_fds(None, Some("2"), 42).is_empty());
+    }
+
+    #[test]
+    fn listen_fds_ignores_invalid_counts() {
+        assert!(listen_fds(Some("42"), None, 42).is_empty());
+        assert!(listen_fds(Some("42"), Some("0"), 42).is_empty());
+        assert!(listen_fds(Some("42"), Some("-1"), 42).is_empty());
+        assert!(listen_fds(Some("42"), Some("many"), 42).is_empty());
+    }
+}